@@ -0,0 +1,73 @@
+//! Compares [`Digest::leading_zero_bits()`] with the bit-by-bit count it replaced,
+//! which runs once per nonce in the mining loop.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use jellyfish_core::{Difficulty, Digest};
+
+/// Counts leading zero bits bit by bit, as Proof-of-Work originally did.
+fn bitwise_leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut count = 0;
+    for &byte in bytes {
+        for i in (0..8).rev() {
+            if byte & (1 << i) != 0 {
+                return count;
+            }
+            count += 1;
+        }
+    }
+    count
+}
+
+fn digest_with_zero_bits(zero_bits: u32) -> Digest {
+    let mut bytes = [0xff; 32];
+    for i in 0..zero_bits as usize {
+        bytes[i / 8] &= !(0x80 >> (i % 8));
+    }
+    Digest::from_bytes(bytes)
+}
+
+fn leading_zero_bits(c: &mut Criterion) {
+    let mut group = c.benchmark_group("leading_zero_bits");
+    for zero_bits in [0, 20, 64, 200, 256] {
+        let digest = digest_with_zero_bits(zero_bits);
+        assert_eq!(
+            digest.leading_zero_bits(),
+            bitwise_leading_zero_bits(digest.as_bytes())
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("words", zero_bits),
+            &digest,
+            |b, digest| b.iter(|| black_box(digest).leading_zero_bits()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("bitwise", zero_bits),
+            &digest,
+            |b, digest| b.iter(|| bitwise_leading_zero_bits(black_box(digest).as_bytes())),
+        );
+    }
+    group.finish();
+}
+
+fn verify_digest(c: &mut Criterion) {
+    let difficulty = Difficulty::new(20);
+    let digests = (0..=256).map(digest_with_zero_bits).collect::<Vec<_>>();
+    for digest in digests.iter() {
+        assert_eq!(
+            difficulty.verify_digest(digest),
+            bitwise_leading_zero_bits(digest.as_bytes()) >= difficulty.bits()
+        );
+    }
+
+    c.bench_function("verify_digest", |b| {
+        b.iter(|| {
+            digests
+                .iter()
+                .filter(|digest| difficulty.verify_digest(black_box(digest)))
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, leading_zero_bits, verify_digest);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+//! Compares mining throughput of hash algorithms, which is digests of a header per second with various nonces.
+//!
+//! Run with `--features blake3` to include BLAKE3.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jellyfish_core::block::{Block, Header, MiningContext};
+use jellyfish_core::hashing::{HashAlgorithm, Sha256};
+use jellyfish_core::jellyfish_transaction::JellyfishTransactionContent;
+use jellyfish_core::{Difficulty, Digest, SecretAccount, Timestamp, Transaction};
+
+/// Number of nonces tried in an iteration.
+const NONCES: u64 = 1000;
+
+fn header<H: HashAlgorithm>() -> Header<H> {
+    let account = SecretAccount::create(&mut rand_core::OsRng {});
+    let transactions = (0..100)
+        .map(|i| {
+            let content = JellyfishTransactionContent::insert(i.to_string());
+            Transaction::create(&account, Timestamp::now(), content)
+        })
+        .collect();
+    let block = Block::<_, _, _, H>::create_with_algorithm(
+        1,
+        Timestamp::now(),
+        Digest::ZERO,
+        Difficulty::new(1),
+        transactions,
+    )
+    .unwrap();
+    block.header().clone()
+}
+
+fn bench_algorithm<H: HashAlgorithm>(c: &mut Criterion, name: &str) {
+    let header = header::<H>();
+    let mut group = c.benchmark_group("mining");
+    group.throughput(Throughput::Elements(NONCES));
+
+    group.bench_function(BenchmarkId::new("midstate", name), |b| {
+        let mut context = MiningContext::new(&header);
+        b.iter(|| {
+            (0..NONCES)
+                .map(|nonce| context.digest(black_box(nonce)))
+                .last()
+        })
+    });
+    group.bench_function(BenchmarkId::new("modify_nonce", name), |b| {
+        let mut header = header.clone();
+        b.iter(|| {
+            (0..NONCES)
+                .map(|nonce| {
+                    header.modify_nonce(black_box(nonce));
+                    *header.digest()
+                })
+                .last()
+        })
+    });
+    group.finish();
+}
+
+fn mining(c: &mut Criterion) {
+    bench_algorithm::<Sha256>(c, "sha256");
+    #[cfg(feature = "blake3")]
+    bench_algorithm::<jellyfish_core::hashing::Blake3>(c, "blake3");
+}
+
+criterion_group!(benches, mining);
+criterion_main!(benches);
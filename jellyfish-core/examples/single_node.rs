@@ -0,0 +1,128 @@
+//! Run a single regtest node, which creates transactions, mines blocks and applies them to its ledger state.
+//!
+//! Run by `cargo run --example single_node`. `cargo test` runs it too, checking its output.
+
+use std::error::Error;
+use std::io::{self, Write};
+
+use jellyfish_core::prelude::*;
+
+/// Mine a block of the transactions on the tip of the chain at regtest difficulty.
+fn mine(
+    params: &ChainParams,
+    height: u64,
+    previous_digest: Digest,
+    transactions: Vec<Transaction<JellyfishTransactionContent, Verified>>,
+) -> Block<JellyfishTransactionContent, Yet, Yet> {
+    let transactions = transactions
+        .into_iter()
+        .map(Transaction::into_unverified)
+        .collect();
+    let mut block = Block::create(
+        height,
+        Timestamp::now(),
+        previous_digest,
+        params.min_difficulty,
+        transactions,
+    )
+    .expect("A block has at least one transaction.");
+    block.header_mut().modify_network(params.network());
+    block.header_mut().modify_hash_mode(params.hash_mode);
+    block
+        .mine_regtest()
+        .expect("Regtest difficulty is minimal.")
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    run(&mut io::stdout().lock())
+}
+
+/// Run the example, writing its progress to `out`.
+fn run<W: Write>(out: &mut W) -> Result<(), Box<dyn Error>> {
+    let params = ChainParams::regtest();
+    let alice = SecretAccount::create(&mut rand_core::OsRng {});
+    let bob = SecretAccount::create(&mut rand_core::OsRng {});
+    let create = |account: &SecretAccount, content| {
+        Transaction::create_for(&params, account, Timestamp::now(), content)
+    };
+
+    // Genesis block begins the chain, so it is applied to the state directly.
+    let genesis = mine(
+        &params,
+        0,
+        Digest::ZERO,
+        vec![create(
+            &alice,
+            JellyfishTransactionContent::insert("genesis"),
+        )],
+    );
+    let genesis = genesis.verify_block(|_| true)?.verify_transactions()?;
+    let mut state = LedgerState::new().with_max_record_len(params.max_record_bytes);
+    state.apply_block(&genesis)?;
+    let mut chain = Chain::new(genesis).with_params(params.clone());
+    writeln!(out, "genesis: {}", chain.tip().header().summary())?;
+
+    // Block 1: Alice and Bob insert records.
+    let note = create(&alice, JellyfishTransactionContent::insert("alice's note"));
+    let draft = create(&bob, JellyfishTransactionContent::insert("bob's draft"));
+    let note_id = TransactionIdentifier::new(1, *note.sign());
+    let draft_id = TransactionIdentifier::new(1, *draft.sign());
+    let block = mine(
+        &params,
+        1,
+        *chain.tip().header().digest(),
+        vec![note, draft],
+    );
+    chain.append_with_state(block, &mut state)?;
+
+    // Block 2: Alice modifies her record.
+    let modify = JellyfishTransactionContent::modify("alice's revised note", note_id.clone());
+    let block = mine(
+        &params,
+        2,
+        *chain.tip().header().digest(),
+        vec![create(&alice, modify)],
+    );
+    chain.append_with_state(block, &mut state)?;
+
+    // Block 3: Bob removes his record.
+    let remove = JellyfishTransactionContent::remove(draft_id.clone());
+    let block = mine(
+        &params,
+        3,
+        *chain.tip().header().digest(),
+        vec![create(&bob, remove)],
+    );
+    chain.append_with_state(block, &mut state)?;
+
+    writeln!(out, "height: {}", chain.tip().header().height())?;
+    writeln!(out, "state root: {}", hex::encode(state.state_root()))?;
+    let mut records = state
+        .records()
+        .map(|(_, record)| record)
+        .collect::<Vec<_>>();
+    records.sort_unstable();
+    for record in records {
+        writeln!(out, "record: {}", record)?;
+    }
+    writeln!(out, "removed: {}", state.record(&draft_id).is_none())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn run() {
+        let mut out = vec![];
+        super::run(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(&"height: 3"));
+        assert!(lines.contains(&"record: alice's revised note"));
+        assert!(lines.contains(&"record: genesis"));
+        assert!(!out.contains("bob's draft"));
+        assert!(lines.contains(&"removed: true"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Sync a fresh regtest node from another node, exchanging messages in process instead of over sockets.
+//!
+//! Run by `cargo run --example sync_two_nodes`. `cargo test` runs it too, checking its output.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, Write};
+
+use jellyfish_core::prelude::*;
+use jellyfish_core::protocol::Message;
+use jellyfish_core::sync::{Action, PeerId, SyncMachine};
+
+type Content = JellyfishTransactionContent;
+
+/// Chain and ledger state of a node.
+struct Node {
+    chain: Chain<Content>,
+    state: LedgerState,
+}
+
+impl Node {
+    /// Create a node whose chain begins with the genesis block.
+    fn new(params: &ChainParams, genesis: Block<Content, Yet, Yet>) -> Self {
+        let genesis = genesis
+            .verify_block(|_| true)
+            .and_then(Block::verify_transactions)
+            .expect("Genesis block is valid.");
+        let mut state = LedgerState::new().with_max_record_len(params.max_record_bytes);
+        state
+            .apply_block(&genesis)
+            .expect("Genesis block is applicable.");
+        let chain = Chain::new(genesis).with_params(params.clone());
+        Self { chain, state }
+    }
+
+    /// Respond to a request from a peer.
+    fn respond(&self, request: Message<Content>) -> Option<Message<Content>> {
+        match request {
+            Message::GetHeaders {
+                from_height,
+                max_count,
+            } => {
+                let headers = (from_height..)
+                    .map_while(|height| self.chain.block(height))
+                    .take(max_count)
+                    .map(|block| block.header().clone())
+                    .collect();
+                Some(Message::Headers(headers))
+            }
+            Message::GetBlocks { heights } => {
+                let blocks = heights
+                    .iter()
+                    .filter_map(|&height| self.chain.block(height))
+                    .map(|block| block.clone().into_unverified())
+                    .collect();
+                Some(Message::Blocks(blocks))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Mine a block inserting the record at regtest difficulty.
+fn mine(
+    params: &ChainParams,
+    account: &SecretAccount,
+    height: u64,
+    previous_digest: Digest,
+    record: &str,
+) -> Block<Content, Yet, Yet> {
+    let content = JellyfishTransactionContent::insert(record);
+    let tx = Transaction::create_for(params, account, Timestamp::now(), content);
+    let mut block = Block::create(
+        height,
+        Timestamp::now(),
+        previous_digest,
+        params.min_difficulty,
+        vec![tx.into_unverified()],
+    )
+    .expect("A block has at least one transaction.");
+    block.header_mut().modify_network(params.network());
+    block.header_mut().modify_hash_mode(params.hash_mode);
+    block
+        .mine_regtest()
+        .expect("Regtest difficulty is minimal.")
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    run(&mut io::stdout().lock())
+}
+
+/// Run the example, writing its progress to `out`.
+fn run<W: Write>(out: &mut W) -> Result<(), Box<dyn Error>> {
+    let params = ChainParams::regtest();
+    let account = SecretAccount::create(&mut rand_core::OsRng {});
+    let genesis = mine(&params, &account, 0, Digest::ZERO, "genesis");
+
+    // The source node has mined 5 blocks on the genesis block.
+    let mut source = Node::new(&params, genesis.clone());
+    for height in 1..=5 {
+        let previous_digest = *source.chain.tip().header().digest();
+        let record = format!("block {}", height);
+        let block = mine(&params, &account, height, previous_digest, &record);
+        source.chain.append_with_state(block, &mut source.state)?;
+    }
+
+    // The fresh node knows only the genesis block.
+    let mut fresh = Node::new(&params, genesis);
+    let mut machine =
+        SyncMachine::new(fresh.chain.tip().header().clone(), Timestamp::now()).with_params(params);
+    let peer = PeerId(1);
+
+    let mut actions = machine.add_peer(peer).into_iter().collect::<VecDeque<_>>();
+    while let Some(action) = actions.pop_front() {
+        match action {
+            Action::Send(peer, request) => {
+                if let Some(response) = source.respond(request) {
+                    actions.extend(machine.on_message(peer, response));
+                }
+            }
+            Action::AppendBlock(block) => {
+                let height = block.header().height();
+                let outcome = fresh.chain.append_with_state(block, &mut fresh.state)?;
+                writeln!(out, "block {}: {:?}", height, outcome)?;
+            }
+            Action::Disconnect(peer, reason) => {
+                return Err(format!("Disconnected from {:?}: {:?}", peer, reason).into());
+            }
+            // Every request is answered immediately, so no tick is needed.
+            Action::ScheduleTick(_) => {}
+        }
+    }
+
+    let synced = fresh.chain.tip().header() == source.chain.tip().header()
+        && fresh.state.state_root() == source.state.state_root();
+    writeln!(out, "height: {}", fresh.chain.tip().header().height())?;
+    writeln!(out, "synced: {}", synced)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn run() {
+        let mut out = vec![];
+        super::run(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(&"block 5: Appended"));
+        assert!(lines.contains(&"height: 5"));
+        assert!(lines.contains(&"synced: true"));
+    }
+}
@@ -1,17 +1,96 @@
-use std::fmt::{self, Display, Formatter};
+use std::cmp::Ordering;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::str::FromStr;
+#[cfg(feature = "vanity")]
+use std::sync::atomic::{self, AtomicBool, AtomicU64};
+#[cfg(feature = "vanity")]
+use std::thread;
 
-use ed25519_dalek::{Keypair, PublicKey, Signer, Verifier};
+#[cfg(feature = "keystore")]
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+#[cfg(feature = "keystore")]
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ed25519_dalek::Digest as PrehashDigest;
+use ed25519_dalek::{
+    Keypair, PublicKey, SecretKey, Signer, Verifier, KEYPAIR_LENGTH, PUBLIC_KEY_LENGTH,
+    SECRET_KEY_LENGTH,
+};
 use hex::ToHex;
 use rand_core::{CryptoRng, RngCore};
-use serde::de::Error as _;
+use serde::de::{Error as _, MapAccess, Visitor};
 use serde::Deserializer;
 use serde::Serializer;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha512};
+use zeroize::{Zeroize, Zeroizing};
 
-use crate::signature::SignatureError;
+use crate::digest::Digest;
+use crate::hex0x;
+use crate::ordering::canonical_sort;
+use crate::signature::{self, SignatureError, VerifyPolicy};
 use crate::ByteOrder;
 use crate::Signature;
 
+/// Offset of hardened indexes of child account derivation.
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// Number of attempts by a worker of [`SecretAccount::generate_vanity_with_progress()`] between progress reports.
+#[cfg(feature = "vanity")]
+pub const VANITY_PROGRESS_INTERVAL: u64 = 64;
+
+/// Permission bits of files written by [`SecretAccount::save_to_file()`], which only the owner can read and write.
+#[cfg(unix)]
+pub const KEY_FILE_MODE: u32 = 0o600;
+
+/// Version of keystores written by [`SecretAccount::save_to_keystore()`], which consist of the following bytes.
+/// | Bytes | Content |
+/// | --- | --- |
+/// | 1 | Version, which is [`KEYSTORE_VERSION`] |
+/// | 4 | Memory cost of Argon2id in KiB as big endian u32 |
+/// | 4 | Number of iterations of Argon2id as big endian u32 |
+/// | 4 | Parallelism of Argon2id as big endian u32 |
+/// | 16 | Salt of Argon2id |
+/// | 12 | Nonce of ChaCha20-Poly1305 |
+/// | 80 | Encrypted bytes of [`SecretAccount::to_bytes()`] followed by 16-byte tag |
+///
+/// The first 41 bytes are authenticated as associated data.
+#[cfg(feature = "keystore")]
+pub const KEYSTORE_VERSION: u8 = 1;
+
+#[cfg(feature = "keystore")]
+const KEYSTORE_SALT_LENGTH: usize = 16;
+#[cfg(feature = "keystore")]
+const KEYSTORE_NONCE_LENGTH: usize = 12;
+#[cfg(feature = "keystore")]
+const KEYSTORE_HEADER_LENGTH: usize = 1 + 4 + 4 + 4 + KEYSTORE_SALT_LENGTH + KEYSTORE_NONCE_LENGTH;
+#[cfg(feature = "keystore")]
+const KEYSTORE_TAG_LENGTH: usize = 16;
+
+/// Largest memory cost of Argon2id in KiB which [`SecretAccount::load_from_keystore()`] accepts, 16 times the default of 19 MiB.
+///
+/// Costs are read from the file, so they are bounded before the key is derived.
+#[cfg(feature = "keystore")]
+pub const KEYSTORE_MAX_M_COST: u32 = 16 * argon2::Params::DEFAULT_M_COST;
+
+/// Largest number of iterations of Argon2id which [`SecretAccount::load_from_keystore()`] accepts, 16 times the default.
+#[cfg(feature = "keystore")]
+pub const KEYSTORE_MAX_T_COST: u32 = 16 * argon2::Params::DEFAULT_T_COST;
+
+/// Largest parallelism of Argon2id which [`SecretAccount::load_from_keystore()`] accepts.
+#[cfg(feature = "keystore")]
+pub const KEYSTORE_MAX_P_COST: u32 = 16;
+
+/// HMAC key of the master key derivation, which is defined by SLIP-0010 for ed25519.
+const SLIP10_SEED_KEY: &[u8] = b"ed25519 seed";
+
 /// jellyfish-chain protocol's account.
 /// Ledger, miner, and client have own account.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -22,37 +101,401 @@ pub struct Account {
 }
 
 impl Account {
+    /// Restores an account from its public key bytes.
+    ///
+    /// Bytes can be obtained from `Account::as_bytes()`.
+    /// Weak keys of small order are rejected by [`VerifyPolicy::default()`] as [`Account::from_bytes_with_policy()`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AccountError> {
+        Self::from_bytes_with_policy(bytes, VerifyPolicy::default())
+    }
+
+    /// Same as [`Account::from_bytes()`], but by the given policy.
+    ///
+    /// # Returns
+    /// `Err(AccountError::WeakKey)` if the policy is [`VerifyPolicy::Strict`] and the key has small order,
+    /// for which a signature may be valid for many messages.
+    pub fn from_bytes_with_policy(
+        bytes: &[u8],
+        policy: VerifyPolicy,
+    ) -> Result<Self, AccountError> {
+        let name = decode_key(bytes, policy)?;
+        Ok(Self { name })
+    }
+
+    /// Restores an account from lowercase or uppercase hex of its public key, which is given by [`Display`].
+    ///
+    /// # Returns
+    /// `Err(AccountError::Hex)` if the text is not hex, or `Err(AccountError::Key)` if the bytes are not a public key.
+    /// Weak keys are rejected as [`Account::from_bytes()`] does.
+    pub fn from_hex(hex: &str) -> Result<Self, AccountError> {
+        let name = decode_name(hex)?;
+        Ok(Self { name })
+    }
+
+    /// Returns bytes of the public key, which are the same as the byte order of the account.
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LENGTH] {
+        self.name.as_bytes()
+    }
+
+    /// Returns a copy of [`Account::as_bytes()`].
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_LENGTH] {
+        self.name.to_bytes()
+    }
+
     /// Returns whether given message and sign was created by the account.
+    ///
+    /// Signatures are checked by [`VerifyPolicy::default()`].
     pub fn verify<T>(&self, msg: &T, sign: &Signature) -> Result<(), SignatureError>
     where
         T: AsRef<[u8]> + ?Sized,
     {
-        self.name.verify(msg.as_ref(), sign.as_raw_sign())?;
-        Ok(())
+        self.verify_with_policy(msg, sign, VerifyPolicy::default())
+    }
+
+    /// Same as [`Account::verify()`], but always by [`VerifyPolicy::Strict`].
+    pub fn verify_strict<T>(&self, msg: &T, sign: &Signature) -> Result<(), SignatureError>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        self.verify_with_policy(msg, sign, VerifyPolicy::Strict)
+    }
+
+    /// Same as [`Account::verify()`], but by the given policy.
+    pub fn verify_with_policy<T>(
+        &self,
+        msg: &T,
+        sign: &Signature,
+        policy: VerifyPolicy,
+    ) -> Result<(), SignatureError>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        let result = match policy {
+            VerifyPolicy::Loose => self.name.verify(msg.as_ref(), sign.as_raw_sign()),
+            VerifyPolicy::Strict => {
+                // verify_strict does not tell why it fails, so weak keys are found beforehand.
+                if has_small_order(self, sign) {
+                    return Err(SignatureError::WeakKey);
+                }
+                self.name.verify_strict(msg.as_ref(), sign.as_raw_sign())
+            }
+        };
+        result.map_err(SignatureError::VerificationFailed)
+    }
+
+    /// Returns whether given message and sign was created by [`SecretAccount::sign_with_context()`] under the same context.
+    pub fn verify_with_context<T>(
+        &self,
+        context: &[u8],
+        msg: &T,
+        sign: &Signature,
+    ) -> Result<(), SignatureError>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        self.verify(&build_context_message(context, msg.as_ref()), sign)
+    }
+
+    /// Returns whether the signature was created by [`SecretAccount::sign_prehashed()`] for the prehash and context.
+    ///
+    /// ed25519ph is not interchangeable with ed25519, so a signature by [`SecretAccount::sign()`] is never valid here, and vice versa.
+    /// Weak keys and `R` of small order are rejected by [`VerifyPolicy::Strict`], as [`Account::verify()`] does.
+    pub fn verify_prehashed(
+        &self,
+        prehash: &Prehash,
+        context: Option<&[u8]>,
+        sign: &Signature,
+    ) -> Result<(), SignatureError> {
+        // ed25519-dalek has no strict ed25519ph, so small-order components are rejected beforehand.
+        if VerifyPolicy::default() == VerifyPolicy::Strict && has_small_order(self, sign) {
+            return Err(SignatureError::WeakKey);
+        }
+        self.name
+            .verify_prehashed(prehash.clone(), context, sign.as_raw_sign())
+            .map_err(SignatureError::VerificationFailed)
+    }
+}
+
+/// Formats the account as lowercase hex of its public key, which is the same as its serialized form.
+impl Display for Account {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_name(&self.name))
+    }
+}
+
+impl FromStr for Account {
+    type Err = AccountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl Hash for Account {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+/// Accounts are ordered lexicographically by bytes of their public keys,
+/// which agrees with the order of their byte orders, i.e., what gets signed.
+impl PartialOrd for Account {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Account {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+/// Bytes of the public key, so that the account can be given to digest helpers directly.
+impl AsRef<[u8]> for Account {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Same as [`Account::from_bytes()`], which validates the key as deserialization does.
+impl TryFrom<&[u8]> for Account {
+    type Error = AccountError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+/// Same as [`Account::from_bytes()`]. Not every 32 bytes is a valid key, so this is not `From`.
+impl TryFrom<[u8; PUBLIC_KEY_LENGTH]> for Account {
+    type Error = AccountError;
+
+    fn try_from(bytes: [u8; PUBLIC_KEY_LENGTH]) -> Result<Self, Self::Error> {
+        Self::from_bytes(&bytes)
     }
 }
 
 impl ByteOrder for Account {
     fn append_bytes(&self, buf: &mut Vec<u8>) {
-        buf.extend(self.name.as_bytes());
+        buf.extend(self.as_bytes());
+    }
+
+    fn append_to_hasher(&self, hasher: &mut crate::digest::Hasher) {
+        hasher.update(self.as_bytes());
+    }
+
+    fn byte_len(&self) -> usize {
+        PUBLIC_KEY_LENGTH
+    }
+}
+
+/// Length of [`Fingerprint`] in bytes.
+pub const FINGERPRINT_LENGTH: usize = 8;
+
+/// Short identifier of an account for UI and logs, which is the first 8 bytes of SHA256 of the public key.
+///
+/// Fingerprints are NOT unique, since different accounts may share one.
+/// Use them only for display and correlation, never for consensus or lookups of accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint([u8; FINGERPRINT_LENGTH]);
+
+impl Fingerprint {
+    pub fn as_bytes(&self) -> &[u8; FINGERPRINT_LENGTH] {
+        &self.0
+    }
+}
+
+impl Account {
+    /// Returns the fingerprint of the account, which never changes across releases.
+    pub fn fingerprint(&self) -> Fingerprint {
+        let digest = Digest::create(self.as_bytes());
+        let mut bytes = [0; FINGERPRINT_LENGTH];
+        bytes.copy_from_slice(&digest.as_bytes()[..FINGERPRINT_LENGTH]);
+        Fingerprint(bytes)
+    }
+}
+
+/// Formats the fingerprint as 16 lowercase hex chars.
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.encode_hex::<String>())
+    }
+}
+
+/// Parses lowercase or uppercase hex given by [`Display`].
+impl FromStr for Fingerprint {
+    type Err = AccountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex0x::decode(s).map_err(AccountError::Hex)?;
+        let bytes = <[u8; FINGERPRINT_LENGTH]>::try_from(bytes.as_slice()).map_err(|_| {
+            AccountError::Length {
+                expected: FINGERPRINT_LENGTH,
+                actual: bytes.len(),
+            }
+        })?;
+        Ok(Self(bytes))
+    }
+}
+
+/// Hex in human-readable formats, and raw 8 bytes in binary formats, as [`Account`] is.
+impl Serialize for Fingerprint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.to_string().serialize(serializer)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let hex = String::deserialize(deserializer)?;
+            hex.parse().map_err(D::Error::custom)
+        } else {
+            <[u8; FINGERPRINT_LENGTH]>::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+/// SHA-512 hasher of messages signed by ed25519ph, i.e., [`SecretAccount::sign_prehashed()`].
+///
+/// This is SHA-512 of ed25519-dalek, whose `Digest` trait may be another version from one of `sha2` used elsewhere.
+pub type Prehash = ed25519_dalek::Sha512;
+
+/// Returns the prehash of the message.
+pub fn prehash<T>(msg: &T) -> Prehash
+where
+    T: AsRef<[u8]> + ?Sized,
+{
+    let mut prehash = Prehash::new();
+    PrehashDigest::update(&mut prehash, msg.as_ref());
+    prehash
+}
+
+/// Returns the prehash of all bytes of the reader, which are read by chunks without being kept in memory.
+pub fn prehash_reader<R: Read>(mut reader: R) -> io::Result<Prehash> {
+    let mut prehash = Prehash::new();
+    let mut buf = [0; 8 * 1024];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(prehash),
+            Ok(len) => PrehashDigest::update(&mut prehash, &buf[..len]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Verify signatures of many messages at once, which is faster than [`Account::verify()`] for each of them.
+///
+/// Signatures are checked by [`VerifyPolicy::default()`] as [`Account::verify()`] does.
+/// If the batch is rejected, each signature is verified individually to find which ones are invalid.
+///
+/// # Returns
+/// `Err` reporting indexes of all invalid signatures in `items`.
+pub fn verify_batch(items: &[(&Account, &[u8], &Signature)]) -> Result<(), BatchVerifyError> {
+    let policy = VerifyPolicy::default();
+    // Batch equation does not reject small-order components, so strict policy checks them beforehand.
+    let is_batchable = policy == VerifyPolicy::Loose
+        || !items
+            .iter()
+            .any(|(account, _, sign)| has_small_order(account, sign));
+
+    if is_batchable {
+        let messages = items.iter().map(|(_, msg, _)| *msg).collect::<Vec<_>>();
+        let signatures = items
+            .iter()
+            .map(|(_, _, sign)| *sign.as_raw_sign())
+            .collect::<Vec<_>>();
+        let public_keys = items
+            .iter()
+            .map(|(account, _, _)| account.name)
+            .collect::<Vec<_>>();
+        if ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_ok() {
+            return Ok(());
+        }
+    }
+
+    let indices = items
+        .iter()
+        .enumerate()
+        .filter(|(_, (account, msg, sign))| account.verify_with_policy(msg, sign, policy).is_err())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if indices.is_empty() {
+        Ok(())
+    } else {
+        Err(BatchVerifyError { indices })
+    }
+}
+
+/// Bytes signed under a context, which are `context length || context || message`.
+///
+/// The length is tagged so that a context is never read as a prefix of another context followed by a message.
+pub(crate) fn build_context_message(context: &[u8], msg: &[u8]) -> Vec<u8> {
+    let len = u8::try_from(context.len()).expect("Context must not be longer than 255 bytes.");
+    let mut source = Vec::with_capacity(1 + context.len() + msg.len());
+    source.push(len);
+    source.extend(context);
+    source.extend(msg);
+    source
+}
+
+/// Text form of a public key, which is shared by serde and [`Display`] so that they never differ.
+fn encode_name(name: &PublicKey) -> String {
+    name.as_bytes().encode_hex()
+}
+
+fn decode_name(hex: &str) -> Result<PublicKey, AccountError> {
+    let bytes = hex0x::decode(hex).map_err(AccountError::Hex)?;
+    decode_key(&bytes, VerifyPolicy::default())
+}
+
+/// Strict policy also rejects small-order keys, which [`PublicKey::from_bytes()`] accepts.
+fn decode_key(bytes: &[u8], policy: VerifyPolicy) -> Result<PublicKey, AccountError> {
+    let key = PublicKey::from_bytes(bytes).map_err(AccountError::Key)?;
+    if policy == VerifyPolicy::Strict && signature::is_small_order(bytes) {
+        return Err(AccountError::WeakKey);
     }
+    Ok(key)
 }
 
+/// Returns whether the public key or `R` of the signature has small order, which [`VerifyPolicy::Strict`] rejects.
+fn has_small_order(account: &Account, sign: &Signature) -> bool {
+    signature::is_small_order(account.as_bytes()) || signature::is_small_order(&sign.as_ref()[..32])
+}
+
+/// Public key is hex in human-readable formats such as JSON, and raw 32 bytes in binary formats such as bincode.
 fn serialize_name<S: Serializer>(name: &PublicKey, serializer: S) -> Result<S::Ok, S::Error> {
-    let hex: String = name.as_bytes().encode_hex();
-    hex.serialize(serializer)
+    if serializer.is_human_readable() {
+        encode_name(name).serialize(serializer)
+    } else {
+        name.as_bytes().serialize(serializer)
+    }
 }
 
 fn deserialize_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PublicKey, D::Error> {
-    let hex = String::deserialize(deserializer)?;
-    let bytes = hex::decode(&hex).map_err(D::Error::custom)?;
-    PublicKey::from_bytes(&bytes).map_err(D::Error::custom)
+    if deserializer.is_human_readable() {
+        let hex = String::deserialize(deserializer)?;
+        decode_name(&hex).map_err(D::Error::custom)
+    } else {
+        let bytes = <[u8; PUBLIC_KEY_LENGTH]>::deserialize(deserializer)?;
+        decode_key(&bytes, VerifyPolicy::default()).map_err(D::Error::custom)
+    }
 }
 
 /// jellyfish-chain protocol's account with secret key.
 ///
 /// DO NOT reveal its secret key.
-#[derive(Debug)]
+///
+/// The secret key is wiped from memory when the account is dropped,
+/// and `Debug` prints the public key only.
+/// Raw bytes of the secret key are obtained only through [`ExposedSecret`], e.g., by [`SecretAccount::expose_secret()`],
+/// so that call sites revealing the key can be audited.
 pub struct SecretAccount {
     keypair: Keypair,
 }
@@ -67,167 +510,2430 @@ impl SecretAccount {
         Self { keypair }
     }
 
+    /// Generates an account whose name, i.e., lowercase hex of its public key, begins with the prefix.
+    ///
+    /// This is [`SecretAccount::generate_vanity_with_progress()`] without progress reports.
+    #[cfg(feature = "vanity")]
+    pub fn generate_vanity(prefix: &str, threads: usize, cancel: &AtomicBool) -> Option<Self> {
+        Self::generate_vanity_with_progress(prefix, threads, cancel, |_| {})
+    }
+
+    /// Generates an account whose name, i.e., lowercase hex of its public key, begins with the prefix.
+    ///
+    /// Each of `threads` workers creates accounts from the OS's random source until one of them finds a match.
+    /// Expected number of attempts is `16^prefix.len()`, so a long prefix may take practically forever.
+    /// Every [`VANITY_PROGRESS_INTERVAL`] attempts of a worker, `progress` is called with the total attempts so far.
+    ///
+    /// # Returns
+    /// `None` if the prefix is not lowercase hex no longer than a name, or `cancel` is set before a match is found.
+    #[cfg(feature = "vanity")]
+    pub fn generate_vanity_with_progress<F>(
+        prefix: &str,
+        threads: usize,
+        cancel: &AtomicBool,
+        progress: F,
+    ) -> Option<Self>
+    where
+        F: Fn(u64) + Sync,
+    {
+        let is_hex = prefix
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        if !is_hex || prefix.len() > PUBLIC_KEY_LENGTH * 2 {
+            return None;
+        }
+
+        let found = AtomicBool::new(false);
+        let attempts = AtomicU64::new(0);
+        let worker = || {
+            let mut rng = rand_core::OsRng;
+            let mut local_attempts = 0;
+            while !found.load(atomic::Ordering::Relaxed) && !cancel.load(atomic::Ordering::Relaxed)
+            {
+                let account = Self::create(&mut rng);
+                if encode_name(&account.keypair.public).starts_with(prefix) {
+                    // Only the first match is returned.
+                    return (!found.swap(true, atomic::Ordering::Relaxed)).then_some(account);
+                }
+
+                local_attempts += 1;
+                if local_attempts % VANITY_PROGRESS_INTERVAL == 0 {
+                    let total =
+                        attempts.fetch_add(VANITY_PROGRESS_INTERVAL, atomic::Ordering::Relaxed);
+                    progress(total + VANITY_PROGRESS_INTERVAL);
+                }
+            }
+            None
+        };
+
+        thread::scope(|scope| {
+            let workers = (0..threads.max(1))
+                .map(|_| scope.spawn(worker))
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .filter_map(|worker| worker.join().unwrap())
+                .next()
+        })
+    }
+
     /// Restores an account from the bytes.
     ///
     /// Bytes can be obtained from `SecretAccount::to_bytes()`.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, AccountError> {
-        let keypair = Keypair::from_bytes(bytes).map_err(AccountError)?;
+        let keypair = Keypair::from_bytes(bytes).map_err(AccountError::Key)?;
         Ok(Self { keypair })
     }
 
-    /// Obtain bytes representation of the account.
-    pub fn to_bytes(&self) -> [u8; ed25519_dalek::KEYPAIR_LENGTH] {
-        self.keypair.to_bytes()
+    /// Derives an account from its secret key bytes, which deterministically decide the public key.
+    ///
+    /// This is useful to reproduce accounts, e.g., from a seed in tests.
+    /// Same as [`SecretAccount::from_secret_bytes()`].
+    pub fn from_secret_key(bytes: &[u8]) -> Result<Self, AccountError> {
+        Self::from_secret_bytes(bytes)
     }
 
-    pub fn public_key(&self) -> &[u8] {
-        self.keypair.public.as_ref()
+    /// Restores an account from only its 32-byte secret key, which most external tools export,
+    /// deriving the public key from it.
+    ///
+    /// Bytes can be obtained from [`SecretAccount::to_secret_bytes()`].
+    ///
+    /// # Returns
+    /// `Err(AccountError::Length)` if the bytes are not 32 bytes, e.g., a 64-byte keypair of [`SecretAccount::to_bytes()`],
+    /// which is never truncated.
+    pub fn from_secret_bytes(bytes: &[u8]) -> Result<Self, AccountError> {
+        if bytes.len() != SECRET_KEY_LENGTH {
+            return Err(AccountError::Length {
+                expected: SECRET_KEY_LENGTH,
+                actual: bytes.len(),
+            });
+        }
+        let secret = SecretKey::from_bytes(bytes).map_err(AccountError::Key)?;
+        let public = PublicKey::from(&secret);
+        let keypair = Keypair { secret, public };
+        Ok(Self { keypair })
     }
 
-    pub fn secret_key(&self) -> &[u8] {
-        self.keypair.secret.as_ref()
+    /// Creates an account whose secret key is the seed, as [`SecretAccount::create()`] does with random bytes.
+    ///
+    /// Identical seeds always give identical accounts, so this is useful for reproducible fixtures
+    /// and for keys from external entropy. The seed is given back by [`SecretAccount::seed()`].
+    pub fn from_seed(seed: [u8; SECRET_KEY_LENGTH]) -> Self {
+        let seed = Zeroizing::new(seed);
+        // Any 32 bytes are a valid secret key.
+        Self::from_secret_key(&*seed).unwrap()
     }
 
-    /// Sign to the given message.
-    pub fn sign<T>(&self, msg: &T) -> Signature
-    where
-        T: AsRef<[u8]> + ?Sized,
-    {
-        let raw = self.keypair.sign(msg.as_ref());
-        Signature::from_raw_sign(raw)
+    /// Restores an account from a 24-word phrase given by [`SecretAccount::to_mnemonic()`].
+    ///
+    /// Words are separated by whitespace, and case is ignored.
+    ///
+    /// # Returns
+    /// `Err(AccountError::Mnemonic)` if a word is unknown, the checksum is wrong, or the phrase is not of 24 words.
+    #[cfg(feature = "mnemonic")]
+    pub fn from_mnemonic(phrase: &str) -> Result<Self, AccountError> {
+        let mnemonic = bip39::Mnemonic::parse_normalized(&phrase.to_lowercase())
+            .map_err(AccountError::Mnemonic)?;
+        let (entropy, len) = mnemonic.to_entropy_array();
+        if len != SECRET_KEY_LENGTH {
+            let error = bip39::Error::BadWordCount(mnemonic.word_count());
+            return Err(AccountError::Mnemonic(error));
+        }
+        Self::from_secret_key(&entropy[..len])
     }
 
-    /// Returns public part of the account.
-    pub fn to_public(&self) -> Account {
-        Account {
-            name: self.keypair.public.clone(),
-        }
+    /// Returns a BIP39 phrase of 24 English words, which encodes the secret key as entropy.
+    ///
+    /// The phrase restores the same account by [`SecretAccount::from_mnemonic()`].
+    /// DO NOT reveal the phrase, since it is equivalent to the secret key.
+    #[cfg(feature = "mnemonic")]
+    pub fn to_mnemonic(&self) -> String {
+        // Secret key has 256 bits, which is a valid entropy length.
+        bip39::Mnemonic::from_entropy(&*self.expose_secret())
+            .unwrap()
+            .to_string()
     }
-}
 
-#[derive(Debug)]
-pub struct AccountError(ed25519_dalek::ed25519::Error);
+    /// Derives a child account of the index by SLIP-0010 hardened ed25519 derivation,
+    /// where the secret key of this account is used as the seed.
+    ///
+    /// This is equivalent to `self.derive_path(&[index])`.
+    pub fn derive_child(&self, index: u32) -> SecretAccount {
+        self.derive_path(&[index])
+    }
 
-impl Display for AccountError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+    /// Derives a descendant account along the path of indexes from this account as the master seed.
+    ///
+    /// Ed25519 supports hardened derivation only, so every index is hardened,
+    /// i.e., `i` and `i + HARDENED_OFFSET` derive the same child.
+    /// Chain codes are carried between levels, so `derive_path(&[a, b])` differs from `derive_child(a).derive_child(b)`.
+    pub fn derive_path(&self, path: &[u32]) -> SecretAccount {
+        let secret = Zeroizing::new(slip10_derive(&*self.expose_secret(), path));
+        // Derived key is always 32 bytes.
+        Self::from_secret_key(&*secret).unwrap()
     }
-}
 
-impl std::error::Error for AccountError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.0)
+    /// Obtain bytes representation of the account.
+    ///
+    /// Bytes contain the secret key, so they are exposed as [`SecretAccount::expose_secret()`] does.
+    pub fn to_bytes(&self) -> ExposedSecret<[u8; KEYPAIR_LENGTH]> {
+        ExposedSecret(Zeroizing::new(self.keypair.to_bytes()))
     }
-}
 
-#[cfg(test)]
-mod tests_account {
-    use super::*;
+    /// Write bytes of [`SecretAccount::to_bytes()`] to the file.
+    ///
+    /// On Unix, the file is restricted to [`KEY_FILE_MODE`] before the key is written, even if it existed before.
+    ///
+    /// # Returns
+    /// `Err(AccountError::Io)` if the file exists and `overwrite` is `false`, or the file cannot be written.
+    pub fn save_to_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        overwrite: bool,
+    ) -> Result<(), AccountError> {
+        write_key_file(path.as_ref(), &*self.to_bytes(), overwrite)
+    }
 
-    const NAME_BYTES: [u8; 32] = [
-        140, 35, 172, 38, 161, 125, 175, 252, 1, 188, 200, 221, 219, 141, 254, 225, 118, 102, 219,
-        28, 94, 112, 152, 162, 97, 84, 7, 31, 81, 218, 151, 240,
-    ];
+    /// Read an account from the file written by [`SecretAccount::save_to_file()`].
+    ///
+    /// # Returns
+    /// `Err(AccountError::Permissions)` on Unix if group or others can access the file,
+    /// which callers may ignore by [`SecretAccount::load_from_file_ignoring_permissions()`].
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, AccountError> {
+        check_key_file_permissions(path.as_ref())?;
+        Self::load_from_file_ignoring_permissions(path)
+    }
 
-    const NAME_HEX: &'static str =
-        "8c23ac26a17daffc01bcc8dddb8dfee17666db1c5e7098a26154071f51da97f0";
+    /// Same as [`SecretAccount::load_from_file()`], but the file is read regardless of its permissions.
+    pub fn load_from_file_ignoring_permissions<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, AccountError> {
+        let bytes = Zeroizing::new(fs::read(path).map_err(AccountError::Io)?);
+        Self::from_bytes(&bytes)
+    }
 
-    #[test]
-    fn serialize() {
-        let publickey = PublicKey::from_bytes(&NAME_BYTES).unwrap();
-        let account = Account { name: publickey };
+    /// Same as [`SecretAccount::save_to_file()`], but the file is a keystore encrypted by the passphrase.
+    ///
+    /// The encryption key is derived from the passphrase and a random salt by Argon2id,
+    /// and the keypair is encrypted by ChaCha20-Poly1305. See [`KEYSTORE_VERSION`] for the layout.
+    #[cfg(feature = "keystore")]
+    pub fn save_to_keystore<P: AsRef<Path>>(
+        &self,
+        path: P,
+        passphrase: &str,
+        overwrite: bool,
+    ) -> Result<(), AccountError> {
+        let keystore = self.encrypt_keystore(passphrase, &mut rand_core::OsRng)?;
+        write_key_file(path.as_ref(), &keystore, overwrite)
+    }
 
-        let serialized = serde_json::to_string(&account).unwrap();
-        let json = format!(r#"{{"name":"{}"}}"#, NAME_HEX);
+    /// Read an account from the keystore written by [`SecretAccount::save_to_keystore()`].
+    ///
+    /// # Returns
+    /// `Err(AccountError::Decryption)` if the passphrase is wrong or the keystore is corrupt.
+    /// `Err(AccountError::Permissions)` as [`SecretAccount::load_from_file()`].
+    #[cfg(feature = "keystore")]
+    pub fn load_from_keystore<P: AsRef<Path>>(
+        path: P,
+        passphrase: &str,
+    ) -> Result<Self, AccountError> {
+        check_key_file_permissions(path.as_ref())?;
+        Self::load_from_keystore_ignoring_permissions(path, passphrase)
+    }
 
-        assert_eq!(serialized, json);
+    /// Same as [`SecretAccount::load_from_keystore()`], but the file is read regardless of its permissions.
+    #[cfg(feature = "keystore")]
+    pub fn load_from_keystore_ignoring_permissions<P: AsRef<Path>>(
+        path: P,
+        passphrase: &str,
+    ) -> Result<Self, AccountError> {
+        let keystore = fs::read(path).map_err(AccountError::Io)?;
+        Self::decrypt_keystore(&keystore, passphrase)
     }
 
-    #[test]
-    fn deserialize() {
-        let json = format!(r#"{{ "name": "{}" }}"#, NAME_HEX);
+    #[cfg(feature = "keystore")]
+    fn encrypt_keystore<R>(&self, passphrase: &str, rng: &mut R) -> Result<Vec<u8>, AccountError>
+    where
+        R: CryptoRng + RngCore,
+    {
+        let params = argon2::Params::default();
+        let mut salt = [0; KEYSTORE_SALT_LENGTH];
+        let mut nonce = [0; KEYSTORE_NONCE_LENGTH];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
 
-        let deserialized: Account = serde_json::from_str(&json).unwrap();
-        let hex = hex::encode(deserialized.name);
+        let mut keystore = vec![KEYSTORE_VERSION];
+        keystore.extend(params.m_cost().to_be_bytes());
+        keystore.extend(params.t_cost().to_be_bytes());
+        keystore.extend(params.p_cost().to_be_bytes());
+        keystore.extend(salt);
+        keystore.extend(nonce);
 
-        assert_eq!(hex, NAME_HEX);
+        let key = derive_keystore_key(passphrase, &salt, params)?;
+        let bytes = self.to_bytes();
+        let payload = Payload {
+            msg: bytes.as_ref(),
+            aad: &keystore,
+        };
+        let ciphertext = ChaCha20Poly1305::new(&Key::from(*key))
+            .encrypt(&Nonce::from(nonce), payload)
+            .expect("Encryption of in-memory data must succeed.");
+        keystore.extend(ciphertext);
+        Ok(keystore)
     }
 
-    #[test]
-    fn deserialize_fail_too_short_key() {
-        let too_short_name = "1234567890123456789012345678901"; // Too short (31 bytes)
-        let json = format!(r#"{{ "name": "{}" }}"#, too_short_name);
+    #[cfg(feature = "keystore")]
+    fn decrypt_keystore(keystore: &[u8], passphrase: &str) -> Result<Self, AccountError> {
+        if keystore.len() != KEYSTORE_HEADER_LENGTH + KEYPAIR_LENGTH + KEYSTORE_TAG_LENGTH {
+            return Err(AccountError::Length {
+                expected: KEYSTORE_HEADER_LENGTH + KEYPAIR_LENGTH + KEYSTORE_TAG_LENGTH,
+                actual: keystore.len(),
+            });
+        }
+        if keystore[0] != KEYSTORE_VERSION {
+            return Err(AccountError::KeystoreVersion(keystore[0]));
+        }
 
-        let res = serde_json::from_str::<Account>(&json);
+        let (header, ciphertext) = keystore.split_at(KEYSTORE_HEADER_LENGTH);
+        let cost = |i: usize| u32::from_be_bytes(header[i..i + 4].try_into().unwrap());
+        let (m_cost, t_cost, p_cost) = (cost(1), cost(5), cost(9));
+        // Costs are authenticated only by the derived key, so a crafted file could demand huge memory or time without the bounds.
+        if m_cost > KEYSTORE_MAX_M_COST
+            || t_cost > KEYSTORE_MAX_T_COST
+            || p_cost > KEYSTORE_MAX_P_COST
+        {
+            return Err(AccountError::KeystoreCost {
+                m_cost,
+                t_cost,
+                p_cost,
+            });
+        }
+        let params = argon2::Params::new(m_cost, t_cost, p_cost, None)
+            .map_err(|_| AccountError::Decryption)?;
+        let salt = &header[13..13 + KEYSTORE_SALT_LENGTH];
+        let nonce: [u8; KEYSTORE_NONCE_LENGTH] =
+            header[13 + KEYSTORE_SALT_LENGTH..].try_into().unwrap();
 
-        assert!(res.is_err());
+        let key = derive_keystore_key(passphrase, salt, params)?;
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+        let bytes = ChaCha20Poly1305::new(&Key::from(*key))
+            .decrypt(&Nonce::from(nonce), payload)
+            .map_err(|_| AccountError::Decryption)?;
+        Self::from_bytes(&Zeroizing::new(bytes))
     }
 
-    #[test]
-    fn deserialize_fail_too_long_key() {
-        let too_long_name = "123456789012345678901234567890123"; // Too long (33 bytes)
-        let json = format!(r#"{{ "name": "{}" }}"#, too_long_name);
-
-        let res = serde_json::from_str::<Account>(&json);
+    pub fn public_key(&self) -> &[u8] {
+        self.keypair.public.as_ref()
+    }
 
-        assert!(res.is_err());
+    /// Returns a copy of the secret key, which is wiped from memory when dropped.
+    ///
+    /// This is the explicit way to access raw bytes of the secret key. DO NOT reveal them.
+    pub fn expose_secret(&self) -> ExposedSecret<[u8; SECRET_KEY_LENGTH]> {
+        ExposedSecret(Zeroizing::new(*self.keypair.secret.as_bytes()))
     }
-}
 
-#[cfg(test)]
-mod tests_secret_account {
-    use super::*;
+    /// Same as [`SecretAccount::expose_secret()`].
+    pub fn secret_key(&self) -> ExposedSecret<[u8; SECRET_KEY_LENGTH]> {
+        self.expose_secret()
+    }
 
-    fn create_secret_account() -> SecretAccount {
-        SecretAccount::create(&mut rand_core::OsRng {})
+    /// Returns the secret key which restores the account by [`SecretAccount::from_secret_bytes()`].
+    ///
+    /// This is the same as [`SecretAccount::expose_secret()`], so DO NOT reveal it.
+    pub fn to_secret_bytes(&self) -> ExposedSecret<[u8; SECRET_KEY_LENGTH]> {
+        self.expose_secret()
     }
 
-    #[test]
-    fn from_to_bytes() {
-        let secret_account = create_secret_account();
+    /// Returns the seed which restores the account by [`SecretAccount::from_seed()`].
+    ///
+    /// This is the same as the secret key, so DO NOT reveal it.
+    pub fn seed(&self) -> ExposedSecret<[u8; SECRET_KEY_LENGTH]> {
+        self.expose_secret()
+    }
+
+    /// Sign to the given message.
+    pub fn sign<T>(&self, msg: &T) -> Signature
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        let raw = self.keypair.sign(msg.as_ref());
+        Signature::from_raw_sign(raw)
+    }
+
+    /// Sign the message under the domain of the context,
+    /// so that the signature is never valid for the same message under other contexts or without a context.
+    ///
+    /// # Panics
+    /// If the context is longer than 255 bytes.
+    pub fn sign_with_context<T>(&self, context: &'static [u8], msg: &T) -> Signature
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        self.sign(&build_context_message(context, msg.as_ref()))
+    }
+
+    /// Sign SHA-512 of a message by ed25519ph, which does not need the whole message at once, e.g., for a large record.
+    ///
+    /// The context is the domain of ed25519ph, which must be the same on verification by [`Account::verify_prehashed()`].
+    ///
+    /// # Panics
+    /// If the context is longer than 255 bytes.
+    pub fn sign_prehashed(&self, prehash: &Prehash, context: Option<&[u8]>) -> Signature {
+        let raw = self
+            .keypair
+            .sign_prehashed(prehash.clone(), context)
+            .expect("Context must not be longer than 255 bytes.");
+        Signature::from_raw_sign(raw)
+    }
+
+    /// Same as [`SecretAccount::sign_prehashed()`], but the message is read and hashed incrementally by [`prehash_reader()`].
+    ///
+    /// # Panics
+    /// If the context is longer than 255 bytes.
+    pub fn sign_reader<R: Read>(&self, reader: R, context: Option<&[u8]>) -> io::Result<Signature> {
+        let prehash = prehash_reader(reader)?;
+        Ok(self.sign_prehashed(&prehash, context))
+    }
+
+    /// Returns public part of the account.
+    pub fn to_public(&self) -> Account {
+        Account {
+            name: self.keypair.public,
+        }
+    }
+
+    /// Same as [`Account::fingerprint()`] of [`SecretAccount::to_public()`].
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.to_public().fingerprint()
+    }
+}
+
+/// Prints `SecretAccount { public: "<hex>", secret: <redacted> }`, so that a stray `{:?}` never leaks the secret key.
+impl Debug for SecretAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretAccount")
+            .field("public", &encode_name(&self.keypair.public))
+            .field("secret", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+/// Replaces the account with the one of the all-zero secret key, wiping the original secret key.
+impl Zeroize for SecretAccount {
+    fn zeroize(&mut self) {
+        let secret = SecretKey::from_bytes(&[0; SECRET_KEY_LENGTH]).unwrap();
+        let public = PublicKey::from(&secret);
+        // Secret key of ed25519-dalek overwrites itself with zeros on drop.
+        self.keypair = Keypair { secret, public };
+    }
+}
+
+/// Returns secret key of the path from the seed, according to SLIP-0010 for ed25519.
+fn slip10_derive(seed: &[u8], path: &[u32]) -> [u8; 32] {
+    let split = |i: [u8; 64]| {
+        let (key, chain_code) = i.split_at(32);
+        (key.try_into().unwrap(), chain_code.try_into().unwrap())
+    };
+    let (key, chain_code): ([u8; 32], [u8; 32]) = split(hmac_sha512(SLIP10_SEED_KEY, seed));
+
+    path.iter()
+        .fold((key, chain_code), |(key, chain_code), index| {
+            let mut data = vec![0];
+            data.extend(key);
+            data.extend((index | HARDENED_OFFSET).to_be_bytes());
+            split(hmac_sha512(&chain_code, &data))
+        })
+        .0
+}
+
+/// HMAC-SHA512 defined in RFC 2104.
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    const BLOCK_SIZE: usize = 128;
+
+    let mut block = [0; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..64].copy_from_slice(&Sha512::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha512::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha512::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Secret bytes obtained from [`SecretAccount::expose_secret()`] or [`SecretAccount::to_bytes()`].
+///
+/// Bytes are accessed by `Deref`, wiped from memory when dropped, and redacted from `Debug`.
+#[derive(PartialEq, Eq)]
+pub struct ExposedSecret<B: Zeroize>(Zeroizing<B>);
+
+impl<B: Zeroize> Deref for ExposedSecret<B> {
+    type Target = B;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<B: Zeroize + AsRef<[u8]>> AsRef<[u8]> for ExposedSecret<B> {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
+
+impl<B: Zeroize> Debug for ExposedSecret<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("ExposedSecret(<redacted>)")
+    }
+}
+
+impl<B: Zeroize> Zeroize for ExposedSecret<B> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Write the key file as [`SecretAccount::save_to_file()`] describes.
+fn write_key_file(path: &Path, bytes: &[u8], overwrite: bool) -> Result<(), AccountError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    options.mode(KEY_FILE_MODE);
+
+    let mut file = options.open(path).map_err(AccountError::Io)?;
+    // Mode of `open` applies only to a new file, so an overwritten file is restricted here.
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(KEY_FILE_MODE))
+        .map_err(AccountError::Io)?;
+    file.write_all(bytes).map_err(AccountError::Io)
+}
+
+/// # Returns
+/// `Err(AccountError::Permissions)` on Unix if group or others can access the file.
+fn check_key_file_permissions(path: &Path) -> Result<(), AccountError> {
+    #[cfg(unix)]
+    {
+        let mode = fs::metadata(path)
+            .map_err(AccountError::Io)?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            return Err(AccountError::Permissions(mode & 0o777));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Derives the encryption key of a keystore by Argon2id.
+#[cfg(feature = "keystore")]
+fn derive_keystore_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: argon2::Params,
+) -> Result<Zeroizing<[u8; 32]>, AccountError> {
+    let mut key = Zeroizing::new([0; 32]);
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|_| AccountError::Decryption)?;
+    Ok(key)
+}
+
+/// Account cannot be restored.
+#[derive(Debug)]
+pub enum AccountError {
+    /// Text is not hex.
+    Hex(hex::FromHexError),
+    /// Bytes are not a valid key, e.g., of wrong length.
+    Key(ed25519_dalek::ed25519::Error),
+    /// Key has small order, which strict policy rejects.
+    WeakKey,
+    /// Bytes have wrong length.
+    Length { expected: usize, actual: usize },
+    /// Key file cannot be read or written.
+    Io(io::Error),
+    /// Group or others can access the key file, whose permission bits are given.
+    /// This is a warning, which callers may ignore by [`SecretAccount::load_from_file_ignoring_permissions()`].
+    Permissions(u32),
+    /// Mnemonic phrase is invalid.
+    #[cfg(feature = "mnemonic")]
+    Mnemonic(bip39::Error),
+    /// Keystore has an unknown version.
+    #[cfg(feature = "keystore")]
+    KeystoreVersion(u8),
+    /// Argon2id costs of the keystore exceed [`KEYSTORE_MAX_M_COST`], [`KEYSTORE_MAX_T_COST`] or [`KEYSTORE_MAX_P_COST`].
+    #[cfg(feature = "keystore")]
+    KeystoreCost {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    },
+    /// Passphrase is wrong, or the keystore is corrupt.
+    #[cfg(feature = "keystore")]
+    Decryption,
+}
+
+impl Display for AccountError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountError::Hex(e) => write!(f, "Account is not hex: {}", e),
+            AccountError::Key(e) => Display::fmt(e, f),
+            AccountError::WeakKey => write!(f, "Account is a weak key of small order."),
+            AccountError::Length { expected, actual } => {
+                write!(f, "Key must be {} bytes, but {} bytes.", expected, actual)
+            }
+            AccountError::Io(e) => write!(f, "Key file cannot be accessed: {}", e),
+            AccountError::Permissions(mode) => write!(
+                f,
+                "Key file is accessible by group or others by mode {:o}.",
+                mode
+            ),
+            #[cfg(feature = "mnemonic")]
+            AccountError::Mnemonic(e) => write!(f, "Mnemonic phrase is invalid: {}", e),
+            #[cfg(feature = "keystore")]
+            AccountError::KeystoreVersion(version) => {
+                write!(f, "Keystore version {} is unknown.", version)
+            }
+            #[cfg(feature = "keystore")]
+            AccountError::KeystoreCost {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => write!(
+                f,
+                "Keystore costs m={} KiB, t={}, p={} exceed the limits.",
+                m_cost, t_cost, p_cost
+            ),
+            #[cfg(feature = "keystore")]
+            AccountError::Decryption => write!(f, "Passphrase is wrong, or keystore is corrupt."),
+        }
+    }
+}
+
+impl std::error::Error for AccountError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AccountError::Hex(e) => Some(e),
+            AccountError::Key(e) => Some(e),
+            AccountError::Io(e) => Some(e),
+            AccountError::WeakKey | AccountError::Length { .. } | AccountError::Permissions(_) => {
+                None
+            }
+            #[cfg(feature = "mnemonic")]
+            AccountError::Mnemonic(e) => Some(e),
+            #[cfg(feature = "keystore")]
+            AccountError::KeystoreVersion(_)
+            | AccountError::KeystoreCost { .. }
+            | AccountError::Decryption => None,
+        }
+    }
+}
+
+/// Some signatures of [`verify_batch()`] are invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchVerifyError {
+    indices: Vec<usize>,
+}
+
+impl BatchVerifyError {
+    /// Returns indexes of invalid signatures in ascending order.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+}
+
+impl Display for BatchVerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Signatures at {:?} are invalid.", self.indices)
+    }
+}
+
+impl std::error::Error for BatchVerifyError {}
+
+/// Local names of accounts, e.g., "this key is the logging service", which are never written to the chain.
+///
+/// Aliases are looked up case-insensitively, so aliases differing only in case are duplicates.
+/// The book is serialized as a map from aliases to accounts, which [`AliasBook::save()`] writes as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AliasBook {
+    /// Lowercase alias to the alias as inserted and its account.
+    entries: BTreeMap<String, (String, Account)>,
+}
+
+impl AliasBook {
+    /// Create an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a book from the JSON file written by [`AliasBook::save()`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AliasBookError> {
+        let json = fs::read_to_string(path).map_err(AliasBookError::Io)?;
+        serde_json::from_str(&json).map_err(AliasBookError::Json)
+    }
+
+    /// Write the book to the file as JSON, replacing its contents.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AliasBookError> {
+        let json = serde_json::to_string_pretty(self).map_err(AliasBookError::Json)?;
+        fs::write(path, json).map_err(AliasBookError::Io)
+    }
+
+    /// Name the account by the alias.
+    ///
+    /// # Returns
+    /// `Err` if the alias is already in the book, ignoring case.
+    pub fn insert(&mut self, alias: String, account: Account) -> Result<(), AliasBookError> {
+        match self.entries.entry(alias.to_lowercase()) {
+            Entry::Occupied(_) => Err(AliasBookError::Duplicate(alias)),
+            Entry::Vacant(entry) => {
+                entry.insert((alias, account));
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the account named by the alias, ignoring case.
+    pub fn resolve(&self, alias: &str) -> Option<&Account> {
+        self.entries
+            .get(&alias.to_lowercase())
+            .map(|(_, account)| account)
+    }
+
+    /// Returns the alias of the account.
+    /// If the account has multiple aliases, the first one in [`AliasBook::iter()`] order is returned.
+    pub fn name_of(&self, account: &Account) -> Option<&str> {
+        self.iter()
+            .find(|(_, named)| *named == account)
+            .map(|(alias, _)| alias)
+    }
+
+    /// Returns pairs of aliases and accounts in order of aliases ignoring case, which is suitable for display.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Account)> {
+        self.entries
+            .values()
+            .map(|(alias, account)| (alias.as_str(), account))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Serialize for AliasBook {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for AliasBook {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AliasBookVisitor;
+
+        impl<'de> Visitor<'de> for AliasBookVisitor {
+            type Value = AliasBook;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a map from aliases to accounts")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut book = AliasBook::new();
+                while let Some((alias, account)) = map.next_entry()? {
+                    book.insert(alias, account).map_err(A::Error::custom)?;
+                }
+                Ok(book)
+            }
+        }
+
+        deserializer.deserialize_map(AliasBookVisitor)
+    }
+}
+
+/// Alias book cannot be updated, read or written.
+#[derive(Debug)]
+pub enum AliasBookError {
+    /// Alias is already in the book, ignoring case.
+    Duplicate(String),
+    /// File cannot be read or written.
+    Io(io::Error),
+    /// File is not a book in JSON.
+    Json(serde_json::Error),
+}
+
+impl Display for AliasBookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AliasBookError::Duplicate(alias) => write!(f, "Alias '{}' is already in use.", alias),
+            AliasBookError::Io(e) => write!(f, "Alias book cannot be accessed: {}", e),
+            AliasBookError::Json(e) => write!(f, "Alias book is malformed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AliasBookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AliasBookError::Duplicate(_) => None,
+            AliasBookError::Io(e) => Some(e),
+            AliasBookError::Json(e) => Some(e),
+        }
+    }
+}
+
+/// Account controlled by its members, which signs a message when at least `threshold` members sign it.
+///
+/// Members are kept sorted and deduplicated, so that the same members and threshold always give the same [`MultisigAccount::identifier()`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "MultisigAccountFields")]
+pub struct MultisigAccount {
+    threshold: u8,
+    members: Vec<Account>,
+}
+
+impl MultisigAccount {
+    /// Create an account which requires signatures of `threshold` members.
+    /// Duplicated members are counted once.
+    ///
+    /// # Returns
+    /// `Err` if the threshold is 0 or more than the members, or there are more than 255 members.
+    pub fn new(threshold: u8, mut members: Vec<Account>) -> Result<Self, MultisigError> {
+        canonical_sort(&mut members);
+        members.dedup();
+
+        if members.len() > u8::MAX as usize {
+            return Err(MultisigError::TooManyMembers(members.len()));
+        }
+        if threshold == 0 || threshold as usize > members.len() {
+            return Err(MultisigError::Threshold {
+                threshold,
+                members: members.len(),
+            });
+        }
+        Ok(Self { threshold, members })
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns members in ascending order.
+    pub fn members(&self) -> &[Account] {
+        &self.members
+    }
+
+    /// Returns whether the account is a member.
+    pub fn is_member(&self, account: &Account) -> bool {
+        self.members.binary_search(account).is_ok()
+    }
+
+    /// Returns digest of the byte order, i.e., the threshold and the sorted members,
+    /// which identifies the account regardless of the order members were given.
+    pub fn identifier(&self) -> Digest {
+        Digest::of_byte_order(self)
+    }
+
+    /// Returns whether enough members signed the message.
+    ///
+    /// Each signature is checked as [`Account::verify()`], and a member signing more than once is counted once.
+    ///
+    /// # Returns
+    /// `Err` if a signer is not a member, a signature is invalid, or distinct signers are fewer than the threshold.
+    pub fn verify<T>(&self, msg: &T, sign: &MultisigSignature) -> Result<(), MultisigError>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        let mut signers = Vec::with_capacity(sign.0.len());
+        for (account, sign) in sign.0.iter() {
+            if !self.is_member(account) {
+                return Err(MultisigError::NonMember(Box::new(account.clone())));
+            }
+            account
+                .verify(msg, sign)
+                .map_err(MultisigError::Signature)?;
+            signers.push(account);
+        }
+        canonical_sort(&mut signers);
+        signers.dedup();
+
+        if signers.len() < self.threshold as usize {
+            Err(MultisigError::BelowThreshold {
+                threshold: self.threshold,
+                signers: signers.len(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Byte order is `threshold || number of members || members`.
+impl ByteOrder for MultisigAccount {
+    fn append_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(self.threshold);
+        buf.push(self.members.len() as u8);
+        for member in self.members.iter() {
+            member.append_bytes(buf);
+        }
+    }
+
+    fn byte_len(&self) -> usize {
+        2 + self.members.len() * PUBLIC_KEY_LENGTH
+    }
+}
+
+/// Serialized form of [`MultisigAccount`], which is validated by [`MultisigAccount::new()`] on deserialization.
+#[derive(Deserialize)]
+struct MultisigAccountFields {
+    threshold: u8,
+    members: Vec<Account>,
+}
+
+impl TryFrom<MultisigAccountFields> for MultisigAccount {
+    type Error = MultisigError;
+
+    fn try_from(fields: MultisigAccountFields) -> Result<Self, Self::Error> {
+        Self::new(fields.threshold, fields.members)
+    }
+}
+
+/// Signatures by members of a [`MultisigAccount`], each of which is paired with its signer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigSignature(pub Vec<(Account, Signature)>);
+
+/// Multisig account is invalid, or its signature is not enough.
+#[derive(Debug)]
+pub enum MultisigError {
+    /// Threshold is 0 or more than the members.
+    Threshold { threshold: u8, members: usize },
+    /// More than 255 members.
+    TooManyMembers(usize),
+    /// Signer is not a member.
+    NonMember(Box<Account>),
+    /// Signature of a member is invalid.
+    Signature(SignatureError),
+    /// Distinct signers are fewer than the threshold.
+    BelowThreshold { threshold: u8, signers: usize },
+}
+
+impl Display for MultisigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use MultisigError::*;
+
+        match self {
+            Threshold { threshold, members } => write!(
+                f,
+                "Threshold must be 1 to the number of members {}, but {}.",
+                members, threshold
+            ),
+            TooManyMembers(len) => write!(f, "Members must be at most 255, but {}.", len),
+            NonMember(account) => write!(f, "Signer {} is not a member.", account),
+            Signature(e) => write!(f, "Signature of a member is invalid: {}", e),
+            BelowThreshold { threshold, signers } => write!(
+                f,
+                "{} members signed, but {} are required.",
+                signers, threshold
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MultisigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MultisigError::Signature(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_account {
+    use std::collections::{BTreeSet, HashMap};
+
+    use super::*;
+    use crate::chain::tests_stab::*;
+    use crate::{Timestamp, Transaction};
+
+    const NAME_BYTES: [u8; 32] = [
+        140, 35, 172, 38, 161, 125, 175, 252, 1, 188, 200, 221, 219, 141, 254, 225, 118, 102, 219,
+        28, 94, 112, 152, 162, 97, 84, 7, 31, 81, 218, 151, 240,
+    ];
+
+    const NAME_HEX: &'static str =
+        "8c23ac26a17daffc01bcc8dddb8dfee17666db1c5e7098a26154071f51da97f0";
+
+    #[test]
+    fn serialize() {
+        let publickey = PublicKey::from_bytes(&NAME_BYTES).unwrap();
+        let account = Account { name: publickey };
+
+        let serialized = serde_json::to_string(&account).unwrap();
+        let json = format!(r#"{{"name":"{}"}}"#, NAME_HEX);
+
+        assert_eq!(serialized, json);
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let account = Account::from_bytes(&NAME_BYTES).unwrap();
+
+        let serialized = bincode::serialize(&account).unwrap();
+        assert_eq!(serialized, NAME_BYTES);
+        assert_eq!(
+            bincode::deserialize::<Account>(&serialized).unwrap(),
+            account
+        );
+
+        assert!(bincode::deserialize::<Account>(&NAME_BYTES[..31]).is_err());
+    }
+
+    #[test]
+    fn deserialize() {
+        let json = format!(r#"{{ "name": "{}" }}"#, NAME_HEX);
+
+        let deserialized: Account = serde_json::from_str(&json).unwrap();
+        let hex = hex::encode(deserialized.name);
+
+        assert_eq!(hex, NAME_HEX);
+    }
+
+    #[test]
+    fn display_from_str() {
+        let account = Account::from_bytes(&NAME_BYTES).unwrap();
+
+        assert_eq!(account.to_string(), NAME_HEX);
+        assert_eq!(NAME_HEX.parse::<Account>().unwrap(), account);
+        assert_eq!(
+            Account::from_hex(&NAME_HEX.to_uppercase()).unwrap(),
+            account
+        );
+    }
+
+    #[test]
+    fn display_agrees_with_serde() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {}).to_public();
+
+        let json = serde_json::to_value(&account).unwrap();
+        assert_eq!(json["name"], account.to_string());
+
+        let json = format!(r#"{{"name":"{}"}}"#, account);
+        let deserialized: Account = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, account);
+        assert_eq!(
+            deserialized.to_string().parse::<Account>().unwrap(),
+            account
+        );
+    }
+
+    #[test]
+    fn from_hex_fail() {
+        assert!(matches!(
+            Account::from_hex("not hex"),
+            Err(AccountError::Hex(_))
+        ));
+        assert!(matches!(
+            Account::from_hex(&NAME_HEX[1..]),
+            Err(AccountError::Hex(_))
+        ));
+        // Valid hex of 31 bytes.
+        assert!(matches!(
+            Account::from_hex(&NAME_HEX[2..]),
+            Err(AccountError::Key(_))
+        ));
+    }
+
+    #[test]
+    fn key_maps_by_bytes() {
+        let secret_accounts = (0..10)
+            .map(|_| SecretAccount::create(&mut rand_core::OsRng {}))
+            .collect::<Vec<_>>();
+        // Some accounts send several transactions.
+        let transactions = (0..30)
+            .map(|i| {
+                let account = &secret_accounts[i * 7 % secret_accounts.len()];
+                Transaction::create(account, Timestamp::now(), Stab(i as u64))
+            })
+            .collect();
+        let block = mine_transactions(1, Digest::ZERO, transactions);
+
+        let mut counts = HashMap::<Account, usize>::new();
+        for tx in block.transactions() {
+            *counts.entry(tx.account().clone()).or_default() += 1;
+        }
+        assert_eq!(counts.len(), secret_accounts.len());
+        assert_eq!(counts.values().sum::<usize>(), 30);
+        assert_eq!(counts[&secret_accounts[0].to_public()], 3);
+
+        // Iteration order of a set is deterministic, and agrees with byte orders.
+        let accounts = block
+            .transactions()
+            .iter()
+            .map(|tx| tx.account().clone())
+            .collect::<BTreeSet<_>>();
+        let byte_orders = accounts
+            .iter()
+            .map(ByteOrder::build_byte_order)
+            .collect::<Vec<_>>();
+        assert!(byte_orders.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(accounts
+            .iter()
+            .zip(&byte_orders)
+            .all(|(account, bytes)| account.as_bytes() == bytes.as_slice()));
+    }
+
+    #[test]
+    fn verify_strict() {
+        // Identity point is a weak key of small order,
+        // and the signature of identity `R` and zero `s` is valid for any message by cofactorless verification.
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let account = Account::from_bytes_with_policy(&identity, VerifyPolicy::Loose).unwrap();
+        let mut sign_bytes = [0; 64];
+        sign_bytes[0] = 1;
+        let sign = Signature::from_bytes(&sign_bytes).unwrap();
+
+        for message in ["hello", "world"] {
+            assert!(account
+                .verify_with_policy(message, &sign, VerifyPolicy::Loose)
+                .is_ok());
+            assert!(account.verify_strict(message, &sign).is_err());
+            assert_eq!(
+                account.verify(message, &sign).is_ok(),
+                !cfg!(feature = "strict-verification")
+            );
+        }
+
+        // Honest signatures pass both.
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let account = secret_account.to_public();
+        let sign = secret_account.sign("hello");
+        assert!(account.verify_strict("hello", &sign).is_ok());
+        assert!(account
+            .verify_with_policy("hello", &sign, VerifyPolicy::Loose)
+            .is_ok());
+    }
+
+    #[test]
+    fn verify_batch() {
+        let secret_accounts = (0..10)
+            .map(|_| SecretAccount::create(&mut rand_core::OsRng {}))
+            .collect::<Vec<_>>();
+        let messages = (0..300u32)
+            .map(|i| i.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let accounts = (0..messages.len())
+            .map(|i| secret_accounts[i % secret_accounts.len()].to_public())
+            .collect::<Vec<_>>();
+        let signs = messages
+            .iter()
+            .enumerate()
+            .map(|(i, msg)| secret_accounts[i % secret_accounts.len()].sign(msg))
+            .collect::<Vec<_>>();
+        fn zip_items<'a>(
+            accounts: &'a [Account],
+            messages: &'a [Vec<u8>],
+            signs: &'a [Signature],
+        ) -> Vec<(&'a Account, &'a [u8], &'a Signature)> {
+            accounts
+                .iter()
+                .zip(messages)
+                .zip(signs)
+                .map(|((account, msg), sign)| (account, msg.as_slice(), sign))
+                .collect()
+        }
+
+        assert_eq!(
+            super::verify_batch(&zip_items(&accounts, &messages, &signs)),
+            Ok(())
+        );
+        assert_eq!(super::verify_batch(&[]), Ok(()));
+
+        let mut corrupted = messages.clone();
+        corrupted[123][0] ^= 1;
+        let error = super::verify_batch(&zip_items(&accounts, &corrupted, &signs)).unwrap_err();
+        assert_eq!(error.indices(), &[123]);
+
+        corrupted[7][0] ^= 1;
+        let error = super::verify_batch(&zip_items(&accounts, &corrupted, &signs)).unwrap_err();
+        assert_eq!(error.indices(), &[7, 123]);
+    }
+
+    #[test]
+    fn verify_batch_weak_key() {
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let weak = Account::from_bytes_with_policy(&identity, VerifyPolicy::Loose).unwrap();
+        let mut sign_bytes = [0; 64];
+        sign_bytes[0] = 1;
+        let forged = Signature::from_bytes(&sign_bytes).unwrap();
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let account = secret_account.to_public();
+        let sign = secret_account.sign("hello");
+
+        let items = [
+            (&account, b"hello".as_slice(), &sign),
+            (&weak, b"hello".as_slice(), &forged),
+        ];
+        let result = super::verify_batch(&items);
+
+        if cfg!(feature = "strict-verification") {
+            assert_eq!(result.unwrap_err().indices(), &[1]);
+        } else {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn verify_error_variants() {
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let weak = Account::from_bytes_with_policy(&identity, VerifyPolicy::Loose).unwrap();
+        let mut sign_bytes = [0; 64];
+        sign_bytes[0] = 1;
+        let forged = Signature::from_bytes(&sign_bytes).unwrap();
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let account = secret_account.to_public();
+        let sign = secret_account.sign("hello");
+
+        assert!(matches!(
+            account.verify_with_policy("other", &sign, VerifyPolicy::Strict),
+            Err(SignatureError::VerificationFailed(_))
+        ));
+        assert!(matches!(
+            account.verify_with_policy("other", &sign, VerifyPolicy::Loose),
+            Err(SignatureError::VerificationFailed(_))
+        ));
+        assert!(matches!(
+            weak.verify_with_policy("hello", &forged, VerifyPolicy::Strict),
+            Err(SignatureError::WeakKey)
+        ));
+        assert!(weak
+            .verify_with_policy("hello", &forged, VerifyPolicy::Loose)
+            .is_ok());
+    }
+
+    #[test]
+    fn bytes_round_trip_agrees_with_serde() {
+        let account = Account::from_bytes(&NAME_BYTES).unwrap();
+        let bytes = account.to_bytes();
+
+        let from_slice = Account::try_from(&bytes[..]).unwrap();
+        let from_array = Account::try_from(bytes).unwrap();
+        let json = serde_json::to_string(&account).unwrap();
+        let from_json = serde_json::from_str::<Account>(&json).unwrap();
+
+        assert_eq!(&bytes, account.as_bytes());
+        assert_eq!(account.as_ref(), &bytes[..]);
+        assert_eq!(from_slice, account);
+        assert_eq!(from_array, account);
+        assert_eq!(from_json, account);
+        assert_eq!(bytes, NAME_BYTES);
+        assert_eq!(json, format!(r#"{{"name":"{}"}}"#, NAME_HEX));
+    }
+
+    #[test]
+    fn invalid_bytes_rejected_consistently() {
+        let mut weak = [0; PUBLIC_KEY_LENGTH];
+        weak[0] = 1;
+        let invalids: [&[u8]; 3] = [&[0; 31], &[0; 33], &weak];
+
+        for bytes in invalids {
+            let json = format!(r#"{{ "name": "{}" }}"#, hex::encode(bytes));
+            let by_bytes = Account::try_from(bytes);
+            let by_serde = serde_json::from_str::<Account>(&json);
+
+            assert_eq!(by_bytes.is_err(), by_serde.is_err(), "{:?}", bytes);
+            assert_eq!(
+                Account::from_hex(&hex::encode(bytes)).is_err(),
+                by_bytes.is_err()
+            );
+        }
+        assert!(Account::try_from(&[0; 31][..]).is_err());
+        assert!(Account::try_from(&[0; 33][..]).is_err());
+        assert_eq!(
+            Account::try_from(weak).is_err(),
+            cfg!(feature = "strict-verification")
+        );
+    }
+
+    /// Canonical encodings of the 8 points of small order.
+    const SMALL_ORDER_KEYS: [&str; 8] = [
+        "0100000000000000000000000000000000000000000000000000000000000000",
+        "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000080",
+        "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc05",
+        "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc85",
+        "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+        "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+    ];
+
+    #[test]
+    fn from_bytes_fail_weak_key() {
+        for hex in SMALL_ORDER_KEYS {
+            let bytes = hex::decode(hex).unwrap();
+
+            let result = Account::from_bytes_with_policy(&bytes, VerifyPolicy::Strict);
+
+            assert!(result.is_err(), "{} is accepted", hex);
+        }
+
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        assert!(matches!(
+            Account::from_bytes_with_policy(&identity, VerifyPolicy::Strict),
+            Err(AccountError::WeakKey)
+        ));
+        assert!(Account::from_bytes_with_policy(&identity, VerifyPolicy::Loose).is_ok());
+    }
+
+    #[test]
+    fn decode_weak_key_by_default_policy() {
+        let is_strict = cfg!(feature = "strict-verification");
+        let hex = SMALL_ORDER_KEYS[0];
+        let bytes = hex::decode(hex).unwrap();
+        let json = format!(r#"{{ "name": "{}" }}"#, hex);
+        let mut array = [0; PUBLIC_KEY_LENGTH];
+        array.copy_from_slice(&bytes);
+        let binary = bincode::serialize(&array).unwrap();
+
+        assert_eq!(Account::from_bytes(&bytes).is_err(), is_strict);
+        assert_eq!(Account::from_hex(hex).is_err(), is_strict);
+        assert_eq!(serde_json::from_str::<Account>(&json).is_err(), is_strict);
+        assert_eq!(bincode::deserialize::<Account>(&binary).is_err(), is_strict);
+    }
+
+    #[test]
+    fn deserialize_fail_too_short_key() {
+        let too_short_name = "1234567890123456789012345678901"; // Too short (31 bytes)
+        let json = format!(r#"{{ "name": "{}" }}"#, too_short_name);
+
+        let res = serde_json::from_str::<Account>(&json);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn deserialize_fail_too_long_key() {
+        let too_long_name = "123456789012345678901234567890123"; // Too long (33 bytes)
+        let json = format!(r#"{{ "name": "{}" }}"#, too_long_name);
+
+        let res = serde_json::from_str::<Account>(&json);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn deserialize_prefixed_hex() {
+        let account = Account::from_bytes(&NAME_BYTES).unwrap();
+        let mixed_case = NAME_HEX.replacen("c", "C", 3);
+
+        for hex in [
+            format!("0x{}", NAME_HEX),
+            format!("0X{}", NAME_HEX.to_uppercase()),
+            format!("0x{}", mixed_case),
+            mixed_case,
+        ] {
+            let json = format!(r#"{{ "name": "{}" }}"#, hex);
+
+            assert_eq!(serde_json::from_str::<Account>(&json).unwrap(), account);
+            assert_eq!(Account::from_hex(&hex).unwrap(), account);
+        }
+
+        // Serialization stays unprefixed.
+        assert_eq!(account.to_string(), NAME_HEX);
+        assert!(matches!(
+            Account::from_hex(&format!("0x{}", &NAME_HEX[2..])),
+            Err(AccountError::Key(_))
+        ));
+    }
+
+    /// Fingerprints must never change across releases.
+    #[test]
+    fn fingerprint_vector() {
+        let vectors = [
+            (NAME_HEX, "20d6915e24669718"),
+            // Public key of RFC 8032 7.1 TEST 1.
+            (
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "21fe31dfa154a261",
+            ),
+        ];
+
+        for (name, expected) in vectors {
+            let fingerprint = Account::from_hex(name).unwrap().fingerprint();
+
+            assert_eq!(fingerprint.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn fingerprint_parse_and_serde() {
+        let fingerprint = Account::from_bytes(&NAME_BYTES).unwrap().fingerprint();
+
+        assert_eq!(
+            "20D6915E24669718".parse::<Fingerprint>().unwrap(),
+            fingerprint
+        );
+
+        let json = serde_json::to_string(&fingerprint).unwrap();
+        assert_eq!(json, r#""20d6915e24669718""#);
+        assert_eq!(
+            serde_json::from_str::<Fingerprint>(&json).unwrap(),
+            fingerprint
+        );
+
+        let binary = bincode::serialize(&fingerprint).unwrap();
+        assert_eq!(binary, fingerprint.as_bytes());
+        assert_eq!(
+            bincode::deserialize::<Fingerprint>(&binary).unwrap(),
+            fingerprint
+        );
+    }
+
+    #[test]
+    fn fingerprint_parse_fail() {
+        assert!(matches!(
+            "20d6915e246697".parse::<Fingerprint>(),
+            Err(AccountError::Length {
+                expected: FINGERPRINT_LENGTH,
+                actual: 7
+            })
+        ));
+        assert!(matches!(
+            "zzd6915e24669718".parse::<Fingerprint>(),
+            Err(AccountError::Hex(_))
+        ));
+    }
+
+    #[test]
+    fn fingerprint_ord() {
+        let low = Fingerprint([0; FINGERPRINT_LENGTH]);
+        let high = Fingerprint([0, 0, 0, 0, 0, 0, 0, 1]);
+
+        assert!(low < high);
+    }
+}
+
+#[cfg(test)]
+mod tests_secret_account {
+    use super::*;
+
+    fn create_secret_account() -> SecretAccount {
+        SecretAccount::create(&mut rand_core::OsRng {})
+    }
+
+    #[test]
+    fn from_to_bytes() {
+        let secret_account = create_secret_account();
 
         let bytes = secret_account.to_bytes();
-        let restored_secret_account = SecretAccount::from_bytes(&bytes).unwrap();
+        let restored_secret_account = SecretAccount::from_bytes(&*bytes).unwrap();
+
+        // Sign to the same message
+        let message = "hello";
+
+        let sign1 = secret_account.sign(message);
+        let sign2 = restored_secret_account.sign(message);
+
+        assert_eq!(sign1, sign2);
+    }
+
+    #[test]
+    fn sign() {
+        let secret_account = create_secret_account();
+        let message = "The altimate answer=42";
+
+        let sign = secret_account.sign(message);
+
+        let account = secret_account.to_public();
+        assert!(account.verify(message, &sign).is_ok());
+    }
+
+    #[test]
+    fn sign_corrupt_message() {
+        let secret_account = create_secret_account();
+        let message = "The altimate answer=42";
+
+        let sign = secret_account.sign(message);
+
+        let account = secret_account.to_public();
+        assert!(account.verify("The altimate answer=43", &sign).is_err());
+    }
+
+    #[test]
+    fn sign_with_context() {
+        let secret_account = create_secret_account();
+        let account = secret_account.to_public();
+        let message = "The altimate answer=42";
+
+        let sign = secret_account.sign_with_context(b"jellyfish-test", message);
+
+        assert!(account
+            .verify_with_context(b"jellyfish-test", message, &sign)
+            .is_ok());
+        assert!(account
+            .verify_with_context(b"jellyfish-other", message, &sign)
+            .is_err());
+        assert!(account.verify(message, &sign).is_err());
+        // Length tag keeps a context from absorbing the head of the message.
+        assert!(account
+            .verify_with_context(b"jellyfish-testThe", " altimate answer=42", &sign)
+            .is_err());
+    }
+
+    #[test]
+    fn sign_without_context_not_verified_with_context() {
+        let secret_account = create_secret_account();
+        let account = secret_account.to_public();
+        let message = "The altimate answer=42";
+
+        let sign = secret_account.sign(message);
+
+        assert!(account
+            .verify_with_context(b"jellyfish-test", message, &sign)
+            .is_err());
+        assert!(account.verify_with_context(b"", message, &sign).is_err());
+    }
+
+    #[test]
+    fn sign_prehashed() {
+        let secret_account = create_secret_account();
+        let account = secret_account.to_public();
+        let message = "The altimate answer=42";
+
+        let sign = secret_account.sign_prehashed(&prehash(message), Some(b"jellyfish-test"));
+
+        assert!(account
+            .verify_prehashed(&prehash(message), Some(b"jellyfish-test"), &sign)
+            .is_ok());
+        assert!(account
+            .verify_prehashed(
+                &prehash("The altimate answer=43"),
+                Some(b"jellyfish-test"),
+                &sign
+            )
+            .is_err());
+        assert!(account
+            .verify_prehashed(&prehash(message), Some(b"jellyfish-other"), &sign)
+            .is_err());
+        assert!(account
+            .verify_prehashed(&prehash(message), None, &sign)
+            .is_err());
+    }
+
+    #[test]
+    fn prehashed_and_plain_sign_not_interchangeable() {
+        let secret_account = create_secret_account();
+        let account = secret_account.to_public();
+        let message = "The altimate answer=42";
+
+        let plain = secret_account.sign(message);
+        let prehashed = secret_account.sign_prehashed(&prehash(message), None);
+
+        assert_ne!(plain, prehashed);
+        assert!(account.verify(message, &prehashed).is_err());
+        assert!(account
+            .verify_prehashed(&prehash(message), None, &plain)
+            .is_err());
+    }
+
+    #[test]
+    fn sign_reader() {
+        let secret_account = create_secret_account();
+        let account = secret_account.to_public();
+        // Longer than the buffer of the reader, so it is hashed by multiple chunks.
+        let message = (0..20_000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let sign = secret_account.sign_reader(&message[..], None).unwrap();
+        let prehash_read = prehash_reader(&message[..]).unwrap();
+
+        assert_eq!(
+            sign,
+            secret_account.sign_prehashed(&prehash(&message), None)
+        );
+        assert!(account.verify_prehashed(&prehash_read, None, &sign).is_ok());
+    }
+
+    #[test]
+    fn sign_corrupt_sign() {
+        let secret_account = create_secret_account();
+        let message = "The altimate answer=42";
+
+        let sign = secret_account.sign("The altimate answer=43");
+
+        let account = secret_account.to_public();
+        assert!(account.verify(message, &sign).is_err());
+    }
+
+    #[test]
+    fn from_secret_key() {
+        let secret_account = create_secret_account();
+
+        let restored = SecretAccount::from_secret_key(&*secret_account.secret_key()).unwrap();
+        assert_eq!(restored.to_bytes(), secret_account.to_bytes());
+
+        let message = "The altimate answer=42";
+        let sign = restored.sign(message);
+        assert!(secret_account.to_public().verify(message, &sign).is_ok());
+
+        assert!(SecretAccount::from_secret_key(&[0; 31]).is_err());
+    }
+
+    #[test]
+    fn from_secret_bytes() {
+        let secret_account = create_secret_account();
+        let message = "The altimate answer=42";
+
+        let restored =
+            SecretAccount::from_secret_bytes(&*secret_account.to_secret_bytes()).unwrap();
+
+        assert_eq!(restored.to_public(), secret_account.to_public());
+        assert_eq!(restored.sign(message), secret_account.sign(message));
+        assert_eq!(restored.to_secret_bytes(), secret_account.to_secret_bytes());
+    }
+
+    #[test]
+    fn from_secret_bytes_fail_length() {
+        let secret_account = create_secret_account();
 
-        // Sign to the same message
-        let message = "hello";
+        // Full keypair is rejected rather than truncated to its secret half.
+        let keypair = secret_account.to_bytes();
+        assert!(matches!(
+            SecretAccount::from_secret_bytes(&*keypair),
+            Err(AccountError::Length {
+                expected: SECRET_KEY_LENGTH,
+                actual: KEYPAIR_LENGTH
+            })
+        ));
+        assert!(matches!(
+            SecretAccount::from_secret_bytes(&keypair[..31]),
+            Err(AccountError::Length { actual: 31, .. })
+        ));
+        assert!(SecretAccount::from_secret_bytes(&[]).is_err());
+    }
 
-        let sign1 = secret_account.sign(message);
-        let sign2 = restored_secret_account.sign(message);
+    #[test]
+    fn from_seed() {
+        let seed = [42; SECRET_KEY_LENGTH];
+        let account = SecretAccount::from_seed(seed);
+        let same = SecretAccount::from_seed(seed);
 
-        assert_eq!(sign1, sign2);
+        assert_eq!(*account.seed(), seed);
+        assert_eq!(account.to_public(), same.to_public());
+        let message = "The altimate answer=42";
+        assert_eq!(account.sign(message), same.sign(message));
+        assert_ne!(
+            SecretAccount::from_seed([43; SECRET_KEY_LENGTH]).to_public(),
+            account.to_public()
+        );
+
+        let restored = SecretAccount::from_bytes(&*account.to_bytes()).unwrap();
+        assert_eq!(restored.to_bytes(), account.to_bytes());
+        let restored = SecretAccount::from_seed(*account.seed());
+        assert_eq!(restored.to_bytes(), account.to_bytes());
     }
 
     #[test]
-    fn sign() {
+    fn fingerprint_vector() {
+        // RFC 8032 7.1 TEST 1.
+        let secret =
+            hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60")
+                .unwrap();
+        let secret_account = SecretAccount::from_secret_bytes(&secret).unwrap();
+
+        assert_eq!(secret_account.fingerprint().to_string(), "21fe31dfa154a261");
+        assert_eq!(
+            secret_account.fingerprint(),
+            secret_account.to_public().fingerprint()
+        );
+    }
+
+    #[test]
+    fn debug_redacts_secret_key() {
         let secret_account = create_secret_account();
+
+        let debug = format!("{:?}", secret_account);
+        assert_eq!(
+            debug,
+            format!(
+                r#"SecretAccount {{ public: "{}", secret: <redacted> }}"#,
+                secret_account.to_public()
+            )
+        );
+        assert!(!debug.contains(&hex::encode(*secret_account.expose_secret())));
+        assert!(!debug.contains(&format!("{:?}", *secret_account.expose_secret())));
+
+        let exposed = format!(
+            "{:?} {:?}",
+            secret_account.expose_secret(),
+            secret_account.to_bytes()
+        );
+        assert_eq!(
+            exposed,
+            "ExposedSecret(<redacted>) ExposedSecret(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn zeroize() {
+        let mut secret_account = create_secret_account();
+
+        // Wrappers of secret bytes are zeroized in the same way on drop.
+        let mut bytes = secret_account.to_bytes();
+        bytes.zeroize();
+        assert_eq!(*bytes, [0; KEYPAIR_LENGTH]);
+        let mut secret_key = secret_account.secret_key();
+        secret_key.zeroize();
+        assert_eq!(*secret_key, [0; SECRET_KEY_LENGTH]);
+
+        secret_account.zeroize();
+        assert_eq!(*secret_account.secret_key(), [0; SECRET_KEY_LENGTH]);
+        let zero = SecretAccount::from_secret_key(&[0; SECRET_KEY_LENGTH]).unwrap();
+        assert_eq!(secret_account.to_public(), zero.to_public());
+    }
+
+    #[test]
+    fn slip10_vector() {
+        // Test vector 1 for ed25519 of SLIP-0010.
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+
+        assert_eq!(
+            hex::encode(slip10_derive(&seed, &[])),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(slip10_derive(&seed, &[0])),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert_eq!(
+            hex::encode(slip10_derive(&seed, &[0, 1, 2, 2, 1_000_000_000])),
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"
+        );
+    }
+
+    #[test]
+    fn derive_child() {
+        let master = SecretAccount::from_secret_key(&[0; 32]).unwrap();
+
+        let child = master.derive_child(0);
+        assert_eq!(
+            hex::encode(*child.secret_key()),
+            "a922eec8ba0abe10fa7b8ec1c4536ce3a8ad0e09bbdf35a5fb9783838ec733ed"
+        );
+        assert_eq!(
+            hex::encode(*master.derive_child(1).secret_key()),
+            "8fdef1bdee2940773604705dc982add5e5bb6bfb872ee96b8460e77c0458d62a"
+        );
+        assert_eq!(master.derive_child(0).to_bytes(), child.to_bytes());
+        assert_eq!(
+            master.derive_child(HARDENED_OFFSET).to_bytes(),
+            child.to_bytes()
+        );
+
         let message = "The altimate answer=42";
+        let sign = child.sign(message);
+        assert!(child.to_public().verify(message, &sign).is_ok());
+        assert!(master.to_public().verify(message, &sign).is_err());
+    }
 
-        let sign = secret_account.sign(message);
+    #[test]
+    fn derive_path() {
+        let master = SecretAccount::from_secret_key(&[0; 32]).unwrap();
 
-        let account = secret_account.to_public();
-        assert!(account.verify(message, &sign).is_ok());
+        let descendant = master.derive_path(&[44, 7]);
+        assert_eq!(
+            hex::encode(*descendant.secret_key()),
+            "4cc748ea87b8bc40eec35ab2418fede69e99ea36407379c7936c05a2d92596dd"
+        );
+        assert_eq!(
+            *master.derive_path(&[]).secret_key(),
+            slip10_derive(&[0; 32], &[])
+        );
+        assert_eq!(
+            master.derive_path(&[44]).to_bytes(),
+            master.derive_child(44).to_bytes()
+        );
+        assert_ne!(
+            descendant.to_bytes(),
+            master.derive_child(44).derive_child(7).to_bytes()
+        );
     }
+}
+
+#[cfg(all(test, feature = "mnemonic"))]
+mod tests_mnemonic {
+    use super::*;
+
+    /// 256-bit zero entropy of BIP39 test vectors.
+    const ZERO_PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+        abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+        abandon abandon abandon abandon art";
 
     #[test]
-    fn sign_corrupt_message() {
+    fn from_mnemonic_vector() {
+        let account = SecretAccount::from_mnemonic(ZERO_PHRASE).unwrap();
+
+        assert_eq!(*account.secret_key(), [0; 32]);
+        assert_eq!(
+            account.to_public().to_string(),
+            "3b6a27bcceb6a42d62a3a8d02a6f0d73653215771de243a63ac048a18b59da29"
+        );
+        assert_eq!(
+            account.to_mnemonic(),
+            ZERO_PHRASE.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+    }
+
+    #[test]
+    fn to_from_mnemonic() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+
+        let phrase = account.to_mnemonic();
+        assert_eq!(phrase.split(' ').count(), 24);
+
+        let restored = SecretAccount::from_mnemonic(&phrase).unwrap();
+        assert_eq!(restored.to_public(), account.to_public());
+        let restored = SecretAccount::from_mnemonic(&phrase.to_uppercase()).unwrap();
+        assert_eq!(restored.to_bytes(), account.to_bytes());
+    }
+
+    #[test]
+    fn from_mnemonic_fail() {
+        let unknown = ZERO_PHRASE.replacen("abandon", "jellyfish", 1);
+        assert!(matches!(
+            SecretAccount::from_mnemonic(&unknown),
+            Err(AccountError::Mnemonic(bip39::Error::UnknownWord(0)))
+        ));
+
+        let checksum = ZERO_PHRASE.replace("art", "abandon");
+        assert!(matches!(
+            SecretAccount::from_mnemonic(&checksum),
+            Err(AccountError::Mnemonic(bip39::Error::InvalidChecksum))
+        ));
+
+        // Valid BIP39 phrase, but of 128-bit entropy.
+        let short = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert!(matches!(
+            SecretAccount::from_mnemonic(short),
+            Err(AccountError::Mnemonic(bip39::Error::BadWordCount(12)))
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "vanity"))]
+mod tests_vanity {
+    use std::sync::atomic::Ordering;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn generate_vanity() {
+        let cancel = AtomicBool::new(false);
+
+        for prefix in ["", "a", "3f"] {
+            let account = SecretAccount::generate_vanity(prefix, 4, &cancel).unwrap();
+            assert!(account.to_public().to_string().starts_with(prefix));
+
+            let message = "The altimate answer=42";
+            let sign = account.sign(message);
+            assert!(account.to_public().verify(message, &sign).is_ok());
+        }
+    }
+
+    #[test]
+    fn generate_vanity_fail_invalid_prefix() {
+        let cancel = AtomicBool::new(false);
+
+        for prefix in ["A", "g", "0x", &"0".repeat(65)] {
+            assert!(SecretAccount::generate_vanity(prefix, 2, &cancel).is_none());
+        }
+    }
+
+    #[test]
+    fn generate_vanity_cancel() {
+        let cancel = AtomicBool::new(true);
+        assert!(SecretAccount::generate_vanity("", 2, &cancel).is_none());
+
+        // Practically never matches, so workers run until cancelled.
+        let cancel = AtomicBool::new(false);
+        let reports = Mutex::new(vec![]);
+        let account =
+            SecretAccount::generate_vanity_with_progress(&"0".repeat(64), 2, &cancel, |attempts| {
+                reports.lock().unwrap().push(attempts);
+                if attempts >= 4 * VANITY_PROGRESS_INTERVAL {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            });
+
+        assert!(account.is_none());
+        // Each report counts attempts of all workers.
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort_unstable();
+        assert!(reports.len() >= 4);
+        assert!(reports
+            .iter()
+            .zip(1..)
+            .all(|(&attempts, i)| attempts == i * VANITY_PROGRESS_INTERVAL));
+    }
+}
+
+#[cfg(test)]
+mod tests_alias_book {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn insert_resolve() {
+        let logger = fixtures::account(0).to_public();
+        let miner = fixtures::account(1).to_public();
+        let mut book = AliasBook::new();
+
+        book.insert("Logger".to_owned(), logger.clone()).unwrap();
+        book.insert("miner".to_owned(), miner.clone()).unwrap();
+
+        assert_eq!(book.resolve("logger"), Some(&logger));
+        assert_eq!(book.resolve("LOGGER"), Some(&logger));
+        assert_eq!(book.resolve("Miner"), Some(&miner));
+        assert_eq!(book.resolve("unknown"), None);
+        assert_eq!(book.name_of(&logger), Some("Logger"));
+        assert_eq!(book.name_of(&fixtures::account(2).to_public()), None);
+    }
+
+    #[test]
+    fn insert_fail_duplicate() {
+        let mut book = AliasBook::new();
+        book.insert("logger".to_owned(), fixtures::account(0).to_public())
+            .unwrap();
+
+        let result = book.insert("LOGGER".to_owned(), fixtures::account(1).to_public());
+
+        assert!(matches!(result, Err(AliasBookError::Duplicate(alias)) if alias == "LOGGER"));
+        assert_eq!(book.len(), 1);
+    }
+
+    #[test]
+    fn iter_sorted_by_alias() {
+        let mut book = AliasBook::new();
+        for (i, alias) in ["carol", "Bob", "alice"].into_iter().enumerate() {
+            book.insert(alias.to_owned(), fixtures::account(i).to_public())
+                .unwrap();
+        }
+
+        let aliases = book.iter().map(|(alias, _)| alias).collect::<Vec<_>>();
+
+        assert_eq!(aliases, vec!["alice", "Bob", "carol"]);
+    }
+
+    #[test]
+    fn name_of_first_alias() {
+        let account = fixtures::account(0).to_public();
+        let mut book = AliasBook::new();
+        book.insert("second".to_owned(), account.clone()).unwrap();
+        book.insert("first".to_owned(), account.clone()).unwrap();
+
+        assert_eq!(book.name_of(&account), Some("first"));
+    }
+
+    #[test]
+    fn serde() {
+        let account = fixtures::account(0).to_public();
+        let mut book = AliasBook::new();
+        book.insert("Logger".to_owned(), account.clone()).unwrap();
+
+        let json = serde_json::to_string(&book).unwrap();
+        assert_eq!(json, format!(r#"{{"Logger":{{"name":"{}"}}}}"#, account));
+
+        let restored = serde_json::from_str::<AliasBook>(&json).unwrap();
+        assert_eq!(restored, book);
+    }
+
+    #[test]
+    fn deserialize_fail_duplicate() {
+        let json = format!(
+            r#"{{ "logger": {{ "name": "{}" }}, "Logger": {{ "name": "{}" }} }}"#,
+            fixtures::account(0).to_public(),
+            fixtures::account(1).to_public()
+        );
+
+        assert!(serde_json::from_str::<AliasBook>(&json).is_err());
+    }
+
+    #[test]
+    fn save_load() {
+        let path =
+            std::env::temp_dir().join(format!("jellyfish-alias-book-{}.json", std::process::id()));
+        let mut book = AliasBook::new();
+        book.insert("logger".to_owned(), fixtures::account(0).to_public())
+            .unwrap();
+        book.insert("miner".to_owned(), fixtures::account(1).to_public())
+            .unwrap();
+
+        book.save(&path).unwrap();
+        let loaded = AliasBook::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), book);
+    }
+
+    #[test]
+    fn load_fail_missing_file() {
+        let path = std::env::temp_dir().join("jellyfish-alias-book-missing.json");
+
+        assert!(matches!(AliasBook::load(path), Err(AliasBookError::Io(_))));
+    }
+}
+
+#[cfg(test)]
+mod tests_multisig {
+    use super::*;
+
+    fn create_secret_accounts(n: usize) -> Vec<SecretAccount> {
+        (0..n)
+            .map(|_| SecretAccount::create(&mut rand_core::OsRng {}))
+            .collect()
+    }
+
+    /// 2 of 3 account and its members.
+    fn two_of_three() -> (MultisigAccount, Vec<SecretAccount>) {
+        let secrets = create_secret_accounts(3);
+        let members = secrets.iter().map(SecretAccount::to_public).collect();
+        (MultisigAccount::new(2, members).unwrap(), secrets)
+    }
+
+    fn sign_by(signers: &[&SecretAccount], msg: &str) -> MultisigSignature {
+        let signs = signers
+            .iter()
+            .map(|secret| (secret.to_public(), secret.sign(msg)))
+            .collect();
+        MultisigSignature(signs)
+    }
+
+    #[test]
+    fn verify_exactly_threshold() {
+        let (multisig, secrets) = two_of_three();
+        let message = "modify record";
+
+        let sign = sign_by(&[&secrets[0], &secrets[2]], message);
+
+        assert!(multisig.verify(message, &sign).is_ok());
+        assert!(multisig.verify("other record", &sign).is_err());
+    }
+
+    #[test]
+    fn verify_all_members() {
+        let (multisig, secrets) = two_of_three();
+        let message = "modify record";
+
+        let sign = sign_by(&secrets.iter().collect::<Vec<_>>(), message);
+
+        assert!(multisig.verify(message, &sign).is_ok());
+    }
+
+    #[test]
+    fn verify_fail_below_threshold() {
+        let (multisig, secrets) = two_of_three();
+        let message = "modify record";
+
+        let sign = sign_by(&[&secrets[1]], message);
+
+        assert!(matches!(
+            multisig.verify(message, &sign),
+            Err(MultisigError::BelowThreshold {
+                threshold: 2,
+                signers: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_fail_duplicate_signer() {
+        let (multisig, secrets) = two_of_three();
+        let message = "modify record";
+
+        let sign = sign_by(&[&secrets[0], &secrets[0]], message);
+
+        assert!(matches!(
+            multisig.verify(message, &sign),
+            Err(MultisigError::BelowThreshold {
+                threshold: 2,
+                signers: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_fail_non_member_signer() {
+        let (multisig, secrets) = two_of_three();
+        let outsider = &create_secret_accounts(1)[0];
+        let message = "modify record";
+
+        let sign = sign_by(&[&secrets[0], outsider], message);
+
+        assert!(matches!(
+            multisig.verify(message, &sign),
+            Err(MultisigError::NonMember(account)) if *account == outsider.to_public()
+        ));
+    }
+
+    #[test]
+    fn verify_fail_forged_member_signature() {
+        let (multisig, secrets) = two_of_three();
+        let message = "modify record";
+
+        let mut sign = sign_by(&[&secrets[0], &secrets[1]], message);
+        sign.0[1].1 = secrets[2].sign(message);
+
+        assert!(matches!(
+            multisig.verify(message, &sign),
+            Err(MultisigError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn new_fail_threshold() {
+        let members = create_secret_accounts(2)
+            .iter()
+            .map(SecretAccount::to_public)
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            MultisigAccount::new(0, members.clone()),
+            Err(MultisigError::Threshold { .. })
+        ));
+        assert!(matches!(
+            MultisigAccount::new(3, members.clone()),
+            Err(MultisigError::Threshold { .. })
+        ));
+        // Duplicated members do not raise the maximum threshold.
+        let duplicated = [members.clone(), members].concat();
+        assert!(matches!(
+            MultisigAccount::new(3, duplicated),
+            Err(MultisigError::Threshold {
+                threshold: 3,
+                members: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn identifier_independent_of_member_order() {
+        let (multisig, _) = two_of_three();
+        let mut reversed = multisig.members().to_vec();
+        reversed.reverse();
+
+        let same = MultisigAccount::new(2, reversed.clone()).unwrap();
+        let other_threshold = MultisigAccount::new(3, reversed).unwrap();
+
+        assert_eq!(same, multisig);
+        assert_eq!(same.identifier(), multisig.identifier());
+        assert_ne!(other_threshold.identifier(), multisig.identifier());
+    }
+
+    #[test]
+    fn byte_order() {
+        let (multisig, _) = two_of_three();
+
+        let bytes = multisig.build_byte_order();
+
+        assert_eq!(bytes.len(), multisig.byte_len());
+        assert_eq!(&bytes[..2], &[2, 3]);
+        assert_eq!(&bytes[2..34], multisig.members()[0].as_bytes());
+        assert_eq!(multisig.identifier(), Digest::create(&bytes));
+    }
+
+    #[test]
+    fn serde() {
+        let (multisig, secrets) = two_of_three();
+        let sign = sign_by(&[&secrets[0], &secrets[1]], "modify record");
+
+        let json = serde_json::to_string(&multisig).unwrap();
+        let restored = serde_json::from_str::<MultisigAccount>(&json).unwrap();
+        assert_eq!(restored, multisig);
+
+        let json = serde_json::to_string(&sign).unwrap();
+        let restored = serde_json::from_str::<MultisigSignature>(&json).unwrap();
+        assert_eq!(restored, sign);
+    }
+
+    #[test]
+    fn deserialize_fail_threshold() {
+        let (multisig, _) = two_of_three();
+        let mut value = serde_json::to_value(&multisig).unwrap();
+        value["threshold"] = serde_json::json!(4);
+
+        assert!(serde_json::from_value::<MultisigAccount>(value).is_err());
+    }
+}
+
+#[cfg(test)]
+mod tests_key_file {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Directory removed on drop, which is unique to the test.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "jellyfish-key-file-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn join(&self, file: &str) -> PathBuf {
+            self.0.join(file)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn create_secret_account() -> SecretAccount {
+        SecretAccount::create(&mut rand_core::OsRng {})
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn save_load() {
+        let dir = TempDir::new("save-load");
+        let path = dir.join("key");
         let secret_account = create_secret_account();
-        let message = "The altimate answer=42";
 
-        let sign = secret_account.sign(message);
+        secret_account.save_to_file(&path, false).unwrap();
+        let loaded = SecretAccount::load_from_file(&path).unwrap();
 
-        let account = secret_account.to_public();
-        assert!(account.verify("The altimate answer=43", &sign).is_err());
+        assert_eq!(loaded.to_bytes(), secret_account.to_bytes());
+        #[cfg(unix)]
+        assert_eq!(mode(&path), KEY_FILE_MODE);
     }
 
     #[test]
-    fn sign_corrupt_sign() {
+    fn save_fail_existing() {
+        let dir = TempDir::new("save-existing");
+        let path = dir.join("key");
+        let first = create_secret_account();
+        let second = create_secret_account();
+        first.save_to_file(&path, false).unwrap();
+
+        let result = second.save_to_file(&path, false);
+
+        assert!(matches!(
+            result,
+            Err(AccountError::Io(e)) if e.kind() == io::ErrorKind::AlreadyExists
+        ));
+        let loaded = SecretAccount::load_from_file(&path).unwrap();
+        assert_eq!(loaded.to_public(), first.to_public());
+    }
+
+    #[test]
+    fn save_overwrite() {
+        let dir = TempDir::new("save-overwrite");
+        let path = dir.join("key");
+        fs::write(&path, b"not a key").unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
         let secret_account = create_secret_account();
-        let message = "The altimate answer=42";
 
-        let sign = secret_account.sign("The altimate answer=43");
+        secret_account.save_to_file(&path, true).unwrap();
 
-        let account = secret_account.to_public();
-        assert!(account.verify(message, &sign).is_err());
+        let loaded = SecretAccount::load_from_file(&path).unwrap();
+        assert_eq!(loaded.to_public(), secret_account.to_public());
+        #[cfg(unix)]
+        assert_eq!(mode(&path), KEY_FILE_MODE);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn load_fail_permissions() {
+        let dir = TempDir::new("load-permissions");
+        let path = dir.join("key");
+        let secret_account = create_secret_account();
+        secret_account.save_to_file(&path, false).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let result = SecretAccount::load_from_file(&path);
+
+        assert!(matches!(result, Err(AccountError::Permissions(0o644))));
+        let loaded = SecretAccount::load_from_file_ignoring_permissions(&path).unwrap();
+        assert_eq!(loaded.to_public(), secret_account.to_public());
+    }
+
+    #[test]
+    fn load_fail_malformed() {
+        let dir = TempDir::new("load-malformed");
+        let path = dir.join("key");
+        let secret_account = create_secret_account();
+        secret_account.save_to_file(&path, false).unwrap();
+        fs::write(&path, &secret_account.secret_key()[..]).unwrap();
+
+        assert!(SecretAccount::load_from_file(&path).is_err());
+        assert!(matches!(
+            SecretAccount::load_from_file(dir.join("missing")),
+            Err(AccountError::Io(_))
+        ));
+    }
+    #[cfg(feature = "keystore")]
+    #[test]
+    fn keystore_save_load() {
+        let dir = TempDir::new("keystore-save-load");
+        let path = dir.join("keystore");
+        let secret_account = create_secret_account();
+
+        secret_account
+            .save_to_keystore(&path, "correct horse", false)
+            .unwrap();
+
+        let keystore = fs::read(&path).unwrap();
+        assert_eq!(keystore.len(), 41 + KEYPAIR_LENGTH + 16);
+        assert_eq!(keystore[0], KEYSTORE_VERSION);
+        // Secret key never appears in plain.
+        let secret = secret_account.expose_secret();
+        assert!(!keystore.windows(secret.len()).any(|w| w == &secret[..]));
+        #[cfg(unix)]
+        assert_eq!(mode(&path), KEY_FILE_MODE);
+
+        let loaded = SecretAccount::load_from_keystore(&path, "correct horse").unwrap();
+        assert_eq!(loaded.to_bytes(), secret_account.to_bytes());
+        assert!(matches!(
+            SecretAccount::load_from_keystore(&path, "wrong horse"),
+            Err(AccountError::Decryption)
+        ));
+        // Keystore is not a plain key file.
+        assert!(SecretAccount::load_from_file(&path).is_err());
+    }
+
+    #[cfg(feature = "keystore")]
+    #[test]
+    fn keystore_save_fail_existing() {
+        let dir = TempDir::new("keystore-existing");
+        let path = dir.join("keystore");
+        let secret_account = create_secret_account();
+        secret_account.save_to_file(&path, false).unwrap();
+
+        let result = secret_account.save_to_keystore(&path, "passphrase", false);
+
+        assert!(matches!(
+            result,
+            Err(AccountError::Io(e)) if e.kind() == io::ErrorKind::AlreadyExists
+        ));
+    }
+
+    #[cfg(all(unix, feature = "keystore"))]
+    #[test]
+    fn keystore_load_fail_permissions() {
+        let dir = TempDir::new("keystore-permissions");
+        let path = dir.join("keystore");
+        let secret_account = create_secret_account();
+        secret_account
+            .save_to_keystore(&path, "passphrase", false)
+            .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let result = SecretAccount::load_from_keystore(&path, "passphrase");
+
+        assert!(matches!(result, Err(AccountError::Permissions(0o640))));
+        let loaded =
+            SecretAccount::load_from_keystore_ignoring_permissions(&path, "passphrase").unwrap();
+        assert_eq!(loaded.to_public(), secret_account.to_public());
+    }
+
+    #[cfg(feature = "keystore")]
+    #[test]
+    fn keystore_load_fail_tampered() {
+        let dir = TempDir::new("keystore-tampered");
+        let path = dir.join("keystore");
+        let secret_account = create_secret_account();
+        secret_account
+            .save_to_keystore(&path, "passphrase", false)
+            .unwrap();
+        let keystore = fs::read(&path).unwrap();
+
+        let load = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            SecretAccount::load_from_keystore(&path, "passphrase")
+        };
+
+        // Salt is authenticated as a part of the header.
+        let mut salt_flipped = keystore.clone();
+        salt_flipped[13] ^= 1;
+        assert!(matches!(load(&salt_flipped), Err(AccountError::Decryption)));
+
+        let mut version = keystore.clone();
+        version[0] = KEYSTORE_VERSION + 1;
+        assert!(matches!(
+            load(&version),
+            Err(AccountError::KeystoreVersion(v)) if v == KEYSTORE_VERSION + 1
+        ));
+
+        assert!(matches!(
+            load(&keystore[..keystore.len() - 1]),
+            Err(AccountError::Length { .. })
+        ));
+    }
+
+    #[cfg(feature = "keystore")]
+    #[test]
+    fn keystore_load_fail_oversized_cost() {
+        let dir = TempDir::new("keystore-cost");
+        let path = dir.join("keystore");
+        let secret_account = create_secret_account();
+        secret_account
+            .save_to_keystore(&path, "passphrase", false)
+            .unwrap();
+        let keystore = fs::read(&path).unwrap();
+
+        // About 4 TiB of memory, which is rejected before the key is derived.
+        let mut oversized = keystore.clone();
+        oversized[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+        fs::write(&path, &oversized).unwrap();
+        assert!(matches!(
+            SecretAccount::load_from_keystore(&path, "passphrase"),
+            Err(AccountError::KeystoreCost {
+                m_cost: u32::MAX,
+                ..
+            })
+        ));
+
+        let mut oversized = keystore;
+        oversized[5..9].copy_from_slice(&(KEYSTORE_MAX_T_COST + 1).to_be_bytes());
+        fs::write(&path, &oversized).unwrap();
+        assert!(matches!(
+            SecretAccount::load_from_keystore(&path, "passphrase"),
+            Err(AccountError::KeystoreCost { .. })
+        ));
     }
 }
@@ -0,0 +1,255 @@
+//! Short text form of accounts, which is Base58Check of the public key with a network prefix.
+//!
+//! An address is `Base58(prefix || public key || checksum)`,
+//! where the checksum is the first 4 bytes of double SHA256 of `prefix || public key`.
+//! The prefix is [`ChainParams::address_prefix`](crate::params::ChainParams::address_prefix),
+//! so that an address of one network is never read as an account of another one.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use ed25519_dalek::PUBLIC_KEY_LENGTH;
+
+use crate::account::AccountError;
+use crate::digest::Digest;
+use crate::Account;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const CHECKSUM_LENGTH: usize = 4;
+
+/// Length of decoded bytes of an address.
+const PAYLOAD_LENGTH: usize = 1 + PUBLIC_KEY_LENGTH + CHECKSUM_LENGTH;
+
+impl Account {
+    /// Returns the address of the account on the network of the prefix.
+    pub fn to_address(&self, prefix: u8) -> String {
+        let mut payload = vec![prefix];
+        payload.extend(self.as_bytes());
+        let checksum = checksum(&payload);
+        payload.extend(checksum);
+        encode_base58(&payload)
+    }
+
+    /// Restores an account from its address on the network of the prefix.
+    ///
+    /// # Returns
+    /// `Err` if the address is not Base58, its checksum is wrong, or it belongs to another network.
+    pub fn from_address(address: &str, prefix: u8) -> Result<Self, AddressError> {
+        let payload = decode_base58(address)?;
+        if payload.len() != PAYLOAD_LENGTH {
+            return Err(AddressError::Length(payload.len()));
+        }
+
+        let (body, checksum_bytes) = payload.split_at(PAYLOAD_LENGTH - CHECKSUM_LENGTH);
+        if checksum(body) != checksum_bytes {
+            return Err(AddressError::Checksum);
+        }
+        if body[0] != prefix {
+            return Err(AddressError::Prefix {
+                expected: prefix,
+                actual: body[0],
+            });
+        }
+        Account::from_bytes(&body[1..]).map_err(AddressError::Key)
+    }
+}
+
+/// Address cannot be read as an account.
+#[derive(Debug)]
+pub enum AddressError {
+    /// Address contains a character out of the Base58 alphabet.
+    Base58 { index: usize, character: char },
+    /// Decoded address has wrong length.
+    Length(usize),
+    /// Checksum does not match, e.g., by a typo.
+    Checksum,
+    /// Address belongs to another network.
+    Prefix { expected: u8, actual: u8 },
+    /// Address does not contain a valid public key.
+    Key(AccountError),
+}
+
+impl Display for AddressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use AddressError::*;
+
+        match self {
+            Base58 { index, character } => write!(
+                f,
+                "Address contains invalid character '{}' at {}.",
+                character, index
+            ),
+            Length(len) => write!(
+                f,
+                "Address must be {} bytes, but {} bytes.",
+                PAYLOAD_LENGTH, len
+            ),
+            Checksum => write!(f, "Checksum of address does not match."),
+            Prefix { expected, actual } => write!(
+                f,
+                "Address is of network prefix {:#04x}, but {:#04x} is expected.",
+                actual, expected
+            ),
+            Key(e) => write!(f, "Address does not contain a valid account: {}", e),
+        }
+    }
+}
+
+impl Error for AddressError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AddressError::Key(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// First bytes of double SHA256.
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LENGTH] {
+    let digest = Digest::create(Digest::create(bytes).as_bytes());
+    let mut checksum = [0; CHECKSUM_LENGTH];
+    checksum.copy_from_slice(&digest.as_bytes()[..CHECKSUM_LENGTH]);
+    checksum
+}
+
+fn encode_base58(bytes: &[u8]) -> String {
+    // Digits in little endian.
+    let mut digits: Vec<u8> = vec![];
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    // Each leading zero byte is represented by the first character.
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    std::iter::repeat_n(ALPHABET[0], zeros)
+        .chain(digits.iter().rev().map(|&digit| ALPHABET[digit as usize]))
+        .map(char::from)
+        .collect()
+}
+
+fn decode_base58(text: &str) -> Result<Vec<u8>, AddressError> {
+    // Bytes in little endian.
+    let mut bytes: Vec<u8> = vec![];
+    for (index, character) in text.chars().enumerate() {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&c| char::from(c) == character)
+            .ok_or(AddressError::Base58 { index, character })? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros = text.chars().take_while(|&c| c == '1').count();
+    bytes.resize(bytes.len() + zeros, 0);
+    bytes.reverse();
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::params::{ChainParams, MAINNET_ADDRESS_PREFIX, TESTNET_ADDRESS_PREFIX};
+
+    #[test]
+    fn base58_vector() {
+        // Test vectors of Bitcoin's Base58 implementation.
+        let vectors: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (&[0x61], "2g"),
+            (&[0x00, 0x00, 0x28, 0x7f, 0xb4, 0xcd], "11233QC4"),
+            (b"hello world", "StV1DL6CwTryKyV"),
+            (
+                &[
+                    0x00, 0xeb, 0x15, 0x23, 0x1d, 0xfc, 0xeb, 0x60, 0x92, 0x58, 0x86, 0xb6, 0x7d,
+                    0x06, 0x52, 0x99, 0x92, 0x59, 0x15, 0xae, 0xb1, 0x72, 0xc0, 0x66, 0x47,
+                ],
+                "1NS17iag9jJgTHD1VXjvLCEnZuQ3rJDE9L",
+            ),
+        ];
+
+        for (bytes, text) in vectors {
+            assert_eq!(encode_base58(bytes), text);
+            assert_eq!(decode_base58(text).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn to_from_address() {
+        let account = fixtures::account(0).to_public();
+
+        for params in [
+            ChainParams::mainnet(),
+            ChainParams::testnet(),
+            ChainParams::regtest(),
+        ] {
+            let address = account.to_address(params.address_prefix);
+            let restored = Account::from_address(&address, params.address_prefix).unwrap();
+            assert_eq!(restored, account);
+        }
+        assert_ne!(
+            account.to_address(MAINNET_ADDRESS_PREFIX),
+            account.to_address(TESTNET_ADDRESS_PREFIX)
+        );
+    }
+
+    #[test]
+    fn from_address_fail_checksum() {
+        let account = fixtures::account(0).to_public();
+        let address = account.to_address(MAINNET_ADDRESS_PREFIX);
+
+        // Replace a character by another one of the alphabet.
+        let mut typo = address.clone().into_bytes();
+        let last = typo.len() - 1;
+        typo[last] = if typo[last] == b'2' { b'3' } else { b'2' };
+        let typo = String::from_utf8(typo).unwrap();
+
+        assert!(matches!(
+            Account::from_address(&typo, MAINNET_ADDRESS_PREFIX),
+            Err(AddressError::Checksum)
+        ));
+        assert!(matches!(
+            Account::from_address(
+                &address.replacen(&address[..1], "0", 1),
+                MAINNET_ADDRESS_PREFIX
+            ),
+            Err(AddressError::Base58 {
+                index: 0,
+                character: '0'
+            })
+        ));
+        assert!(matches!(
+            Account::from_address(&address[1..], MAINNET_ADDRESS_PREFIX),
+            Err(AddressError::Length(_) | AddressError::Checksum)
+        ));
+    }
+
+    #[test]
+    fn from_address_fail_prefix() {
+        let account = fixtures::account(0).to_public();
+        let address = account.to_address(TESTNET_ADDRESS_PREFIX);
+
+        assert!(matches!(
+            Account::from_address(&address, MAINNET_ADDRESS_PREFIX),
+            Err(AddressError::Prefix { expected, actual })
+                if expected == MAINNET_ADDRESS_PREFIX && actual == TESTNET_ADDRESS_PREFIX
+        ));
+    }
+}
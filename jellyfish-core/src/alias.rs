@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::account::AccountError;
+use crate::jellyfish_transaction::{ContentError, ContentRule, JellyfishTransactionContent};
+use crate::schema::RecordValidator;
+use crate::Account;
+
+/// Records beginning with this prefix are treated as alias records.
+pub const ALIAS_PREFIX: &str = "alias:";
+
+/// Minimum number of characters in an alias name.
+pub const MIN_ALIAS_LENGTH: usize = 3;
+
+/// Maximum number of characters in an alias name.
+pub const MAX_ALIAS_LENGTH: usize = 32;
+
+/// Human-readable name which is resolved to an account.
+///
+/// An alias is stored in a record as `alias:<name>=<hex of account>`.
+/// A name consists of lowercase ASCII letters, digits, `-` and `_`, and begins with a letter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasRecord {
+    name: String,
+    account: Account,
+}
+
+impl AliasRecord {
+    /// Create an alias record, checking that the name satisfies the policy.
+    pub fn new<'a, T>(name: T, account: Account) -> Result<Self, AliasError>
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        let name = name.into().into_owned();
+        validate_name(&name)?;
+        Ok(Self { name, account })
+    }
+
+    /// Parse a record.
+    ///
+    /// # Returns
+    /// `None` if the record is not an alias record, that is, the record does not begin with [`ALIAS_PREFIX`].
+    pub fn parse(record: &str) -> Option<Result<Self, AliasError>> {
+        let body = record.strip_prefix(ALIAS_PREFIX)?;
+
+        let result = body
+            .split_once('=')
+            .ok_or(AliasError::Format)
+            .and_then(|(name, account)| {
+                let bytes = hex::decode(account).map_err(|_| AliasError::Format)?;
+                let account = Account::from_bytes(&bytes).map_err(AliasError::Account)?;
+                Self::new(name, account)
+            });
+        Some(result)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    /// Returns record text, which is stored by `Insert` or `Modify` transaction.
+    pub fn to_record(&self) -> String {
+        format!(
+            "{}{}={}",
+            ALIAS_PREFIX,
+            self.name,
+            hex::encode(self.account.as_bytes())
+        )
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), AliasError> {
+    let is_valid_length = (MIN_ALIAS_LENGTH..=MAX_ALIAS_LENGTH).contains(&name.len());
+    let begins_with_letter = name.starts_with(|c: char| c.is_ascii_lowercase());
+    let is_valid_charset = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+
+    if is_valid_length && begins_with_letter && is_valid_charset {
+        Ok(())
+    } else {
+        Err(AliasError::Name)
+    }
+}
+
+/// Rejects malformed alias records and alias names violating the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AliasRule;
+
+impl ContentRule for AliasRule {
+    fn check(&self, content: &JellyfishTransactionContent) -> Result<(), ContentError> {
+        match content.record() {
+            Some(record) => self
+                .validate(record)
+                .map_err(|e| ContentError::new(e.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Validates records of [`ALIAS_PREFIX`] registered to [`RecordSchemaRegistry`](crate::schema::RecordSchemaRegistry).
+impl RecordValidator for AliasRule {
+    fn validate(&self, record: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match AliasRecord::parse(record) {
+            Some(Err(e)) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Alias record is malformed.
+#[derive(Debug)]
+pub enum AliasError {
+    /// Record is not `alias:<name>=<hex of account>` form.
+    Format,
+    /// Name violates the length or charset policy.
+    Name,
+    /// Account is invalid.
+    Account(AccountError),
+}
+
+impl Display for AliasError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use AliasError::*;
+
+        match self {
+            Format => write!(f, "Alias record is malformed."),
+            Name => write!(
+                f,
+                "Alias name must be {} to {} characters of lowercase letters, digits, '-' and '_', beginning with a letter.",
+                MIN_ALIAS_LENGTH, MAX_ALIAS_LENGTH
+            ),
+            Account(e) => write!(f, "Alias account is invalid: {}", e),
+        }
+    }
+}
+
+impl Error for AliasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AliasError::Account(e) => Some(e),
+            AliasError::Format | AliasError::Name => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn to_record_parse() {
+        let alias = AliasRecord::new("alice", fixtures::account(0).to_public()).unwrap();
+
+        let record = alias.to_record();
+        assert!(record.starts_with("alias:alice="));
+
+        let parsed = AliasRecord::parse(&record).unwrap().unwrap();
+        assert_eq!(parsed, alias);
+    }
+
+    #[test]
+    fn parse_not_alias() {
+        assert!(AliasRecord::parse("hello").is_none());
+        assert!(AliasRecord::parse("ALIAS:alice").is_none());
+    }
+
+    #[test]
+    fn parse_fail_format() {
+        assert!(matches!(
+            AliasRecord::parse("alias:alice"),
+            Some(Err(AliasError::Format))
+        ));
+        assert!(matches!(
+            AliasRecord::parse("alias:alice=xyz"),
+            Some(Err(AliasError::Format))
+        ));
+        assert!(matches!(
+            AliasRecord::parse("alias:alice=0011"),
+            Some(Err(AliasError::Account(_)))
+        ));
+    }
+
+    #[test]
+    fn name_policy() {
+        let account = fixtures::account(0).to_public();
+        let is_valid = |name: &str| AliasRecord::new(name, account.clone()).is_ok();
+
+        assert!(is_valid("bob"));
+        assert!(is_valid("carol_42-x"));
+        assert!(is_valid(&"a".repeat(MAX_ALIAS_LENGTH)));
+
+        assert!(!is_valid("ab"));
+        assert!(!is_valid(&"a".repeat(MAX_ALIAS_LENGTH + 1)));
+        assert!(!is_valid("Alice"));
+        assert!(!is_valid("42alice"));
+        assert!(!is_valid("_alice"));
+        assert!(!is_valid("ali ce"));
+        assert!(!is_valid("ålice"));
+    }
+
+    #[test]
+    fn alias_rule() {
+        let alias = AliasRecord::new("alice", fixtures::account(0).to_public()).unwrap();
+        let valid = JellyfishTransactionContent::insert(alias.to_record());
+        let plain = JellyfishTransactionContent::insert("hello");
+        let bad_name = JellyfishTransactionContent::insert(
+            alias.to_record().replace("alias:alice", "alias:Alice"),
+        );
+        let malformed = JellyfishTransactionContent::insert("alias:alice");
+
+        assert!(AliasRule.check(&valid).is_ok());
+        assert!(AliasRule.check(&plain).is_ok());
+        assert!(AliasRule.check(&bad_name).is_err());
+        assert!(AliasRule.check(&malformed).is_err());
+    }
+}
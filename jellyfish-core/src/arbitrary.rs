@@ -0,0 +1,315 @@
+//! [`proptest`] support for property tests of downstream crates.
+//!
+//! Core types implement [`Arbitrary`], so they can be drawn by `any::<T>()`.
+//! Signatures and accounts are created by throwaway keys, so that every drawn signature is a real one.
+//!
+//! [`verified_transaction()`] and [`mined_block()`] generate values which pass [`Transaction::verify()`],
+//! [`Block::verify_block()`] and [`Block::verify_transactions()`], so that property tests exercise the happy path.
+//! [`corrupt()`] breaks such a block in a specific way to exercise the failure paths.
+//!
+//! ```
+//! # use jellyfish_core::arbitrary::{corrupt, mined_block, Corruption};
+//! # use jellyfish_core::block::BlockError;
+//! # use proptest::prelude::*;
+//! proptest! {
+//!     fn merkle_root_is_checked(block in mined_block(4)) {
+//!         let block = corrupt(&block, Corruption::MerkleRoot);
+//!         prop_assert!(matches!(block.verify_block(|_| true), Err(BlockError::Merkle)));
+//!     }
+//! }
+//! # merkle_root_is_checked();
+//! ```
+
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::collection::vec;
+use proptest::prop_oneof;
+use proptest::strategy::{BoxedStrategy, Strategy};
+
+use crate::block::Block;
+use crate::difficulty::{MAX_DIFFICULTY, MIN_DIFFICULTY};
+use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+use crate::transaction::VerifiedTransaction;
+use crate::{Account, Difficulty, Digest, SecretAccount, Signature, Timestamp, Transaction, Yet};
+
+/// Maximum difficulty of blocks by [`mined_block()`], which keeps Proof-of-Work process to a few dozen attempts.
+pub const MAX_MINED_DIFFICULTY: u64 = 4;
+
+/// Records and fragments of drawn contents are printable ASCII of at most this length.
+const MAX_RECORD_CHARS: usize = 32;
+
+/// Maximum number of non-`Insert` operations of drawn `Batch` contents.
+const MAX_BATCH_MODIFICATIONS: usize = 4;
+
+/// How [`corrupt()`] breaks a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// Change the timestamp of the first transaction, which its sign no longer matches.
+    /// Merkle root is unchanged since its leaves are digests of signs,
+    /// so [`Block::verify_block()`] passes and [`Block::verify_transactions()`] rejects it as `BlockError::Transaction`.
+    Signature,
+    /// Flip a bit of the merkle root, which [`Block::verify_block()`] rejects as `BlockError::Merkle`.
+    MerkleRoot,
+    /// Change the nonce without re-calculating the digest, which [`Block::verify_block()`] rejects as `BlockError::Digest`.
+    Nonce,
+}
+
+/// Returns a copy of the block broken as `corruption`.
+///
+/// The block is edited in its serialized form, as if a peer sent a tampered block.
+pub fn corrupt(
+    block: &Block<JellyfishTransactionContent, Yet, Yet>,
+    corruption: Corruption,
+) -> Block<JellyfishTransactionContent, Yet, Yet> {
+    let mut value = serde_json::to_value(block).expect("Block must be serializable.");
+    match corruption {
+        Corruption::Signature => {
+            let timestamp = block.transactions()[0].timestamp().nanos() ^ 1;
+            value["transactions"][0]["timestamp"] = timestamp.into();
+        }
+        Corruption::MerkleRoot => {
+            let mut root = block.header().merkle_root().to_bytes();
+            root[0] ^= 1;
+            value["header"]["merkle_root"] = serde_json::to_value(Digest::from_bytes(root))
+                .expect("Digest must be serializable.");
+        }
+        Corruption::Nonce => {
+            let nonce = block.header().nonce().wrapping_add(1);
+            value["header"]["nonce"] = nonce.into();
+        }
+    }
+    serde_json::from_value(value).expect("Corrupted block must be deserializable.")
+}
+
+/// Strategy of secret accounts derived from random seeds.
+pub fn secret_account() -> impl Strategy<Value = SecretAccount> {
+    any::<[u8; 32]>().prop_map(SecretAccount::from_seed)
+}
+
+/// Strategy of transactions in the legacy format, which are signed by random accounts and pass [`Transaction::verify()`].
+pub fn verified_transaction(
+) -> impl Strategy<Value = VerifiedTransaction<JellyfishTransactionContent>> {
+    (
+        secret_account(),
+        any::<Timestamp>(),
+        any::<JellyfishTransactionContent>(),
+    )
+        .prop_map(|(account, timestamp, content)| Transaction::create(&account, timestamp, content))
+}
+
+/// Strategy of mined blocks of 1 to `max_transactions` transactions, as received from a peer.
+///
+/// The blocks pass [`Block::verify_block()`] and [`Block::verify_transactions()`].
+/// Their heights, timestamps and previous digests are random, so they do not form a chain,
+/// and their contents may refer to transactions which do not exist.
+///
+/// # Panics
+/// If `max_transactions` is 0.
+pub fn mined_block(
+    max_transactions: usize,
+) -> impl Strategy<Value = Block<JellyfishTransactionContent, Yet, Yet>> {
+    assert!(
+        max_transactions > 0,
+        "A block needs at least one transaction."
+    );
+    (
+        any::<u64>(),
+        any::<Timestamp>(),
+        any::<Digest>(),
+        0..=MAX_MINED_DIFFICULTY,
+        vec(verified_transaction(), 1..=max_transactions),
+    )
+        .prop_map(
+            |(height, timestamp, previous_digest, difficulty, transactions)| {
+                Block::create(
+                    height,
+                    timestamp,
+                    previous_digest,
+                    Difficulty::new(difficulty),
+                    transactions,
+                )
+                .expect("Generated block must have at least one transaction.")
+                .mine()
+                .into_unverified()
+            },
+        )
+}
+
+impl Arbitrary for Digest {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        any::<[u8; 32]>().prop_map(Digest::from_bytes).boxed()
+    }
+}
+
+/// Signatures of random messages by random accounts.
+impl Arbitrary for Signature {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (secret_account(), vec(any::<u8>(), 0..64))
+            .prop_map(|(account, msg)| account.sign(&msg))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Account {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        secret_account()
+            .prop_map(|account| account.to_public())
+            .boxed()
+    }
+}
+
+/// Valid timestamps, i.e., from [`Timestamp::EPOCH`] to [`Timestamp::MAX`].
+impl Arbitrary for Timestamp {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (Timestamp::EPOCH.nanos()..=Timestamp::MAX.nanos())
+            .prop_map(Timestamp::from_nanos)
+            .boxed()
+    }
+}
+
+/// Difficulties from [`MIN_DIFFICULTY`] to [`MAX_DIFFICULTY`].
+impl Arbitrary for Difficulty {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (MIN_DIFFICULTY.zero_bits()..=MAX_DIFFICULTY.zero_bits())
+            .prop_map(Difficulty::new)
+            .boxed()
+    }
+}
+
+impl Arbitrary for TransactionIdentifier {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (any::<u64>(), any::<Signature>())
+            .prop_map(|(height, sign)| TransactionIdentifier::new(height, sign))
+            .boxed()
+    }
+}
+
+/// Contents of every method, whose targets are random identifiers.
+/// `Batch` contents satisfy [`JellyfishTransactionContent::batch()`].
+impl Arbitrary for JellyfishTransactionContent {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        let batch = (
+            proptest::option::of(insert_content()),
+            vec(modification_content(), 1..=MAX_BATCH_MODIFICATIONS),
+        )
+            .prop_map(|(insert, modifications)| {
+                let operations = insert.into_iter().chain(modifications).collect();
+                JellyfishTransactionContent::batch(operations)
+                    .expect("Generated batch must be valid.")
+            });
+        prop_oneof![insert_content(), modification_content(), batch].boxed()
+    }
+}
+
+fn record() -> impl Strategy<Value = String> {
+    proptest::string::string_regex(&format!("[ -~]{{0,{}}}", MAX_RECORD_CHARS))
+        .expect("Record pattern must be valid.")
+}
+
+/// `Insert` with an optional lifetime.
+fn insert_content() -> impl Strategy<Value = JellyfishTransactionContent> {
+    (record(), proptest::option::of(1..=i64::MAX)).prop_map(|(record, ttl_nanos)| {
+        let content = JellyfishTransactionContent::insert(record);
+        match ttl_nanos {
+            Some(ttl_nanos) => content.with_ttl(ttl_nanos),
+            None => content,
+        }
+    })
+}
+
+/// `Modify`, `Remove` or `Append`.
+fn modification_content() -> impl Strategy<Value = JellyfishTransactionContent> {
+    prop_oneof![
+        (record(), any::<TransactionIdentifier>())
+            .prop_map(|(record, target)| JellyfishTransactionContent::modify(record, target)),
+        any::<TransactionIdentifier>().prop_map(JellyfishTransactionContent::remove),
+        (record(), any::<TransactionIdentifier>()).prop_map(|(fragment, target)| {
+            JellyfishTransactionContent::append(fragment, target)
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::block::BlockError;
+    use crate::transaction::TransactionError;
+
+    proptest! {
+        #[test]
+        fn serde_round_trip(
+            digest in any::<Digest>(),
+            sign in any::<Signature>(),
+            account in any::<Account>(),
+            timestamp in any::<Timestamp>(),
+            content in any::<JellyfishTransactionContent>(),
+        ) {
+            let json = serde_json::to_string(&(digest, sign, &account, timestamp, &content)).unwrap();
+            let from_json: (Digest, Signature, Account, Timestamp, JellyfishTransactionContent) =
+                serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(from_json, (digest, sign, account.clone(), timestamp, content));
+
+            // Contents omit absent fields, so only fixed-size types are round-tripped by binary formats.
+            let bin = bincode::serialize(&(digest, sign, &account, timestamp)).unwrap();
+            let from_bin: (Digest, Signature, Account, Timestamp) = bincode::deserialize(&bin).unwrap();
+            prop_assert_eq!(from_bin, (digest, sign, account, timestamp));
+        }
+
+        #[test]
+        fn transaction_verify_after_build(tx in verified_transaction()) {
+            prop_assert!(tx.into_unverified().verify().is_ok());
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn block_verify_after_build(block in mined_block(4)) {
+            let json = serde_json::to_string(&block).unwrap();
+            let block: Block<JellyfishTransactionContent, Yet, Yet> = serde_json::from_str(&json).unwrap();
+
+            let verified = block.verify_block(|_| true).and_then(Block::verify_transactions);
+            prop_assert!(verified.is_ok());
+        }
+
+        #[test]
+        fn corrupted_block_fails(block in mined_block(4)) {
+            let signature = corrupt(&block, Corruption::Signature)
+                .verify_block(|_| true)
+                .and_then(Block::verify_transactions);
+            prop_assert!(matches!(
+                signature,
+                Err(BlockError::Transaction(TransactionError::Signature(_)))
+            ));
+
+            let merkle = corrupt(&block, Corruption::MerkleRoot).verify_block(|_| true);
+            prop_assert!(matches!(merkle, Err(BlockError::Merkle)));
+
+            let nonce = corrupt(&block, Corruption::Nonce).verify_block(|_| true);
+            prop_assert!(matches!(nonce, Err(BlockError::Digest)));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Command line tool of jellyfish-chain protocol.
+//!
+//! # Usage
+//! - `jellyfish compat export [FILE]` writes test vectors of [`compat`] to the file, or to stdout.
+//! - `jellyfish compat check [FILE]` checks test vectors read from the file, or from stdin,
+//!   and fails at the first mismatching entry.
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use jellyfish_core::compat;
+
+const USAGE: &str = "Usage:
+    jellyfish compat export [FILE]
+    jellyfish compat check [FILE]";
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match run(&args, io::stdin().lock(), io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the subcommand of the arguments, which exclude the program name.
+fn run<R: Read, W: Write>(args: &[String], input: R, mut out: W) -> Result<(), Box<dyn Error>> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    match args.as_slice() {
+        ["compat", "export"] => compat::export_vectors(out)?,
+        ["compat", "export", path] => {
+            let mut file = BufWriter::new(File::create(path)?);
+            compat::export_vectors(&mut file)?;
+            file.flush()?;
+        }
+        ["compat", "check"] => {
+            compat::check_vectors(input)?;
+            writeln!(out, "All vectors match.")?;
+        }
+        ["compat", "check", path] => {
+            compat::check_vectors(BufReader::new(File::open(path)?))?;
+            writeln!(out, "All vectors match.")?;
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn compat_export_check() {
+        let mut exported = vec![];
+        run(&args(&["compat", "export"]), io::empty(), &mut exported).unwrap();
+
+        let mut out = vec![];
+        run(&args(&["compat", "check"]), &exported[..], &mut out).unwrap();
+        assert_eq!(out, b"All vectors match.\n");
+
+        let mut tampered = serde_json::from_slice::<serde_json::Value>(&exported).unwrap();
+        // Public key of another account, which mismatches the secret key.
+        tampered["accounts"][0]["public_key"] = tampered["accounts"][1]["public_key"].clone();
+        let tampered = serde_json::to_vec(&tampered).unwrap();
+        assert!(run(&args(&["compat", "check"]), &tampered[..], io::sink()).is_err());
+    }
+
+    #[test]
+    fn compat_export_check_file() {
+        let path = env::temp_dir().join(format!("jellyfish-compat-{}.json", std::process::id()));
+        let path_arg = path.to_str().unwrap();
+
+        run(
+            &args(&["compat", "export", path_arg]),
+            io::empty(),
+            io::sink(),
+        )
+        .unwrap();
+        let result = run(
+            &args(&["compat", "check", path_arg]),
+            io::empty(),
+            io::sink(),
+        );
+        let _ = std::fs::remove_file(&path);
+
+        result.unwrap();
+    }
+
+    #[test]
+    fn usage() {
+        for invalid in [&[][..], &["compat"], &["compat", "import"], &["mine"]] {
+            let error = run(&args(invalid), io::empty(), io::sink()).unwrap_err();
+            assert_eq!(error.to_string(), USAGE);
+        }
+    }
+}
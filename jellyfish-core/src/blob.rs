@@ -0,0 +1,279 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::digest::Digest;
+use crate::jellyfish_transaction::{
+    ContentError, ContentRule, JellyfishTransactionContent, Method,
+};
+use crate::schema::RecordValidator;
+
+/// Records beginning with this prefix are treated as references to off-chain blobs.
+pub const REF_PREFIX: &str = "ref:";
+
+/// Reference to a blob stored off chain, which the chain commits to by its digest.
+///
+/// A reference is stored in a record as `ref:<hex of digest>:<length>`, optionally followed by `:<uri hint>`.
+/// The hint tells where the blob may be fetched, but the blob is trusted only by [`BlobVerifier`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordRef {
+    digest: Digest,
+    length: u64,
+    uri_hint: Option<String>,
+}
+
+impl RecordRef {
+    pub fn new(digest: Digest, length: u64, uri_hint: Option<&str>) -> Self {
+        Self {
+            digest,
+            length,
+            uri_hint: uri_hint.map(str::to_owned),
+        }
+    }
+
+    /// Create a reference to the blob.
+    pub fn for_blob(blob: &[u8], uri_hint: Option<&str>) -> Self {
+        Self::new(Digest::create(blob), blob.len() as u64, uri_hint)
+    }
+
+    /// Parse a record.
+    ///
+    /// # Returns
+    /// `None` if the record is not a reference, that is, the record does not begin with [`REF_PREFIX`].
+    pub fn parse(record: &str) -> Option<Result<Self, BlobError>> {
+        let body = record.strip_prefix(REF_PREFIX)?;
+
+        let mut parts = body.splitn(3, ':');
+        let result = (|| {
+            let digest = parts.next().ok_or(BlobError::Format)?;
+            let length = parts.next().ok_or(BlobError::Format)?;
+            let mut bytes = [0; 32];
+            hex::decode_to_slice(digest, &mut bytes).map_err(|_| BlobError::Format)?;
+            let length = length.parse().map_err(|_| BlobError::Format)?;
+            Ok(Self::new(Digest::from_bytes(bytes), length, parts.next()))
+        })();
+        Some(result)
+    }
+
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// Returns length of the blob in bytes.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn uri_hint(&self) -> Option<&str> {
+        self.uri_hint.as_deref()
+    }
+
+    /// Returns record text, which is stored by `Insert` or `Modify` transaction.
+    pub fn to_record(&self) -> String {
+        let mut record = format!("{}{}:{}", REF_PREFIX, hex::encode(self.digest), self.length);
+        if let Some(uri_hint) = &self.uri_hint {
+            record.push(':');
+            record.push_str(uri_hint);
+        }
+        record
+    }
+}
+
+/// Checks that a blob fetched off chain is the one a [`RecordRef`] commits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlobVerifier;
+
+impl BlobVerifier {
+    /// Check the length first, so that a blob of wrong length is rejected without digesting it.
+    pub fn verify_blob(&self, reference: &RecordRef, blob: &[u8]) -> Result<(), BlobError> {
+        let actual = blob.len() as u64;
+        if actual != reference.length {
+            return Err(BlobError::Length {
+                expected: reference.length,
+                actual,
+            });
+        }
+        if Digest::create(blob) != reference.digest {
+            return Err(BlobError::Digest);
+        }
+        Ok(())
+    }
+}
+
+/// Rejects malformed references, and optionally requires references for large `Insert` records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecordRefRule {
+    max_inline_bytes: Option<usize>,
+}
+
+impl RecordRefRule {
+    /// Returns a rule which accepts inline records of any size.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `Insert` records longer than the given bytes must be references.
+    pub fn with_max_inline_bytes(self, max_inline_bytes: usize) -> Self {
+        Self {
+            max_inline_bytes: Some(max_inline_bytes),
+        }
+    }
+}
+
+impl ContentRule for RecordRefRule {
+    fn check(&self, content: &JellyfishTransactionContent) -> Result<(), ContentError> {
+        for operation in content.operations() {
+            let record = match operation.record() {
+                Some(record) => record,
+                None => continue,
+            };
+            self.validate(record)
+                .map_err(|e| ContentError::new(e.to_string()))?;
+
+            let is_inline = !record.starts_with(REF_PREFIX);
+            let is_large = self.max_inline_bytes.is_some_and(|max| record.len() > max);
+            if operation.method() == Method::Insert && is_inline && is_large {
+                return Err(ContentError::new(format!(
+                    "Insert record of {} bytes must be a reference to an off-chain blob.",
+                    record.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validates only the format of records of [`REF_PREFIX`] registered to [`RecordSchemaRegistry`](crate::schema::RecordSchemaRegistry),
+/// since the limit of inline records depends on the method of the operation.
+impl RecordValidator for RecordRefRule {
+    fn validate(&self, record: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match RecordRef::parse(record) {
+            Some(Err(e)) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reference is malformed, or a blob does not match its reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlobError {
+    /// Record is not `ref:<hex of digest>:<length>[:<uri hint>]` form.
+    Format,
+    /// Blob length differs from the reference.
+    Length { expected: u64, actual: u64 },
+    /// Blob digest differs from the reference.
+    Digest,
+}
+
+impl Display for BlobError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use BlobError::*;
+
+        match self {
+            Format => write!(f, "Blob reference record is malformed."),
+            Length { expected, actual } => write!(
+                f,
+                "Blob has {} bytes, but the reference expects {} bytes.",
+                actual, expected
+            ),
+            Digest => write!(f, "Blob digest differs from the reference."),
+        }
+    }
+}
+
+impl Error for BlobError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOB: &[u8] = b"large binary artifact";
+
+    #[test]
+    fn to_record_parse() {
+        let reference = RecordRef::for_blob(BLOB, None);
+        let record = reference.to_record();
+        assert_eq!(
+            record,
+            format!("ref:{}:21", hex::encode(Digest::create(BLOB)))
+        );
+        assert_eq!(RecordRef::parse(&record), Some(Ok(reference)));
+
+        // Hint may contain separators.
+        let reference = RecordRef::for_blob(BLOB, Some("https://example.com:8080/blob"));
+        let parsed = RecordRef::parse(&reference.to_record()).unwrap().unwrap();
+        assert_eq!(parsed.uri_hint(), Some("https://example.com:8080/blob"));
+        assert_eq!(parsed, reference);
+    }
+
+    #[test]
+    fn insert_ref() {
+        let digest = Digest::create(BLOB);
+        let content = JellyfishTransactionContent::insert_ref(digest, 21, Some("ipfs://blob"));
+
+        assert_eq!(content.method(), Method::Insert);
+        let reference = content.record_ref().unwrap().unwrap();
+        assert_eq!(reference, RecordRef::new(digest, 21, Some("ipfs://blob")));
+
+        assert!(JellyfishTransactionContent::insert("hello")
+            .record_ref()
+            .is_none());
+    }
+
+    #[test]
+    fn parse_fail_format() {
+        assert!(RecordRef::parse("hello").is_none());
+        for record in ["ref:", "ref:0011:21", "ref:xyz:21", "ref:00:abc"] {
+            assert_eq!(RecordRef::parse(record), Some(Err(BlobError::Format)));
+        }
+        let digest = hex::encode(Digest::create(BLOB));
+        assert_eq!(
+            RecordRef::parse(&format!("ref:{}", digest)),
+            Some(Err(BlobError::Format))
+        );
+        assert_eq!(
+            RecordRef::parse(&format!("ref:{}:-1", digest)),
+            Some(Err(BlobError::Format))
+        );
+    }
+
+    #[test]
+    fn verify_blob() {
+        let reference = RecordRef::for_blob(BLOB, None);
+
+        assert_eq!(BlobVerifier.verify_blob(&reference, BLOB), Ok(()));
+        assert_eq!(
+            BlobVerifier.verify_blob(&reference, b"short"),
+            Err(BlobError::Length {
+                expected: 21,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            BlobVerifier.verify_blob(&reference, b"LARGE binary artifact"),
+            Err(BlobError::Digest)
+        );
+    }
+
+    #[test]
+    fn record_ref_rule() {
+        let reference =
+            JellyfishTransactionContent::insert(RecordRef::for_blob(BLOB, None).to_record());
+        let small = JellyfishTransactionContent::insert("a".repeat(16));
+        let large = JellyfishTransactionContent::insert("a".repeat(17));
+        let malformed = JellyfishTransactionContent::insert("ref:0011:21");
+
+        let rule = RecordRefRule::new();
+        assert!(rule.check(&large).is_ok());
+        assert!(rule.check(&malformed).is_err());
+
+        let rule = RecordRefRule::new().with_max_inline_bytes(16);
+        assert!(rule.check(&reference).is_ok());
+        assert!(rule.check(&small).is_ok());
+        assert!(rule.check(&large).is_err());
+        assert!(rule.check(&malformed).is_err());
+
+        // Insert in a batch is checked too.
+        let batch = JellyfishTransactionContent::batch(vec![large]).unwrap();
+        assert!(rule.check(&batch).is_err());
+    }
+}
@@ -2,36 +2,81 @@ use std::fmt::{self, Display, Formatter};
 use std::marker::PhantomData;
 
 use itertools::Itertools;
-use rs_merkle::algorithms::Sha256;
 use rs_merkle::MerkleTree;
-use serde::{Deserialize, Serialize};
-
-use crate::digest::calculate_digest;
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::chain::{ChainError, TargetError};
+use crate::codec::{BoundedReader, DecodeError};
+use crate::difficulty::{PowMode, Target, MIN_DIFFICULTY};
+use crate::digest::{Digest, HashMode, Hasher, Sha256Digest};
+use crate::filter::AccountFilter;
+use crate::hashing::{DefaultMerkle, HashAlgorithm, Sha256};
+use crate::hex0x;
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::params::{ChainParams, NetworkId, ParamsError, MAX_HEADER_BYTES};
+use crate::sig_cache::SigCache;
+use crate::state::StateError;
 use crate::transaction::TransactionError;
-use crate::{ByteOrder, Difficulty, Sha256Digest, Verified, Yet};
+use crate::{ByteOrder, Difficulty, Verified, Yet};
 use crate::{Timestamp, Transaction};
 
+/// Tag preceding the state root in byte order of a header.
+pub const STATE_ROOT_TAG: u8 = 1;
+
+/// Tag preceding the network in byte order of a header.
+pub const NETWORK_TAG: u8 = 2;
+
+/// Tag preceding the compact target in byte order of a header.
+pub const TARGET_TAG: u8 = 3;
+
+/// Tag preceding [`HashAlgorithm::ID`] in byte order of a header, which is omitted for [`Sha256`].
+pub const ALGORITHM_TAG: u8 = 4;
+
 /// Block header. This contains all data of a block, except for transactions.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Header {
+/// # Generic type parameters
+/// - `H` Hash algorithm of the digest and the merkle root.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Header<H = Sha256> {
     /// Block height.
     height: u64,
     /// When the block created.
     timestamp: Timestamp,
     /// Digest of the previous block.
     /// This is used to verify block relationship.
-    #[serde(with = "hex")]
-    previous_digest: Sha256Digest,
+    previous_digest: Digest,
     /// How difficult to find the block based on Proof-of-Work.
     difficulty: Difficulty,
     /// Merkle root of the transactions of the block.
-    #[serde(with = "hex")]
-    merkle_root: Sha256Digest,
+    merkle_root: Digest,
     /// Nonce, which is required to meet with Proof-of-Work condition.
     nonce: u64,
+    /// Root of ledger state after applying the block.
+    ///
+    /// Headers without state root keep the original byte order, so that their digests are unchanged.
+    /// Otherwise the byte order contains it after [`STATE_ROOT_TAG`].
+    #[serde(default, deserialize_with = "deserialize_optional_digest")]
+    state_root: Option<Digest>,
+    /// Network of the block, which is given since protocol version 1.
+    ///
+    /// Headers without network keep the original byte order, as well as state root.
+    /// Otherwise the byte order contains it after [`NETWORK_TAG`].
+    #[serde(default, deserialize_with = "deserialize_optional_hex")]
+    network: Option<NetworkId>,
+    /// How the digest is calculated from the byte order, which is given by [`ChainParams::hash_mode`].
+    #[serde(default)]
+    hash_mode: HashMode,
+    /// Compact target which the digest must not exceed, instead of the difficulty, on [`PowMode::Target`].
+    ///
+    /// Headers without target keep the original byte order, as well as state root.
+    /// Otherwise the byte order contains it after [`TARGET_TAG`].
+    #[serde(default, deserialize_with = "deserialize_compact_target")]
+    target: Option<u32>,
     /// Digest of the header.
-    #[serde(with = "hex")]
-    digest: Sha256Digest,
+    digest: Digest,
+    #[serde(skip)]
+    _algorithm: PhantomData<fn() -> H>,
 }
 
 impl Header {
@@ -41,13 +86,15 @@ impl Header {
     /// # Returns
     /// `None` if empty transaction is given, otherwise, `Some(header)`.
     ///
+    /// Genesis block's header is given [`Digest::ZERO`] as `previous_digest`.
+    ///
     /// # Caution:
     /// Nonce after [`create()`] is not valid value for meeting with Proof-of-Work condition.
     /// Proof-of-Work process must be executed manually by using [`modify_nonce()`] and [`digest()`].
     pub fn create<T, VT>(
         height: u64,
         timestamp: Timestamp,
-        previous_digest: Sha256Digest,
+        previous_digest: Digest,
         difficulty: Difficulty,
         transactions: &[Transaction<T, VT>],
         nonce: u64,
@@ -55,7 +102,31 @@ impl Header {
     where
         T: ByteOrder,
     {
-        let merkle_root = build_merkle_tree(transactions).root()?;
+        Self::create_with_algorithm(
+            height,
+            timestamp,
+            previous_digest,
+            difficulty,
+            transactions,
+            nonce,
+        )
+    }
+}
+
+impl<H: HashAlgorithm> Header<H> {
+    /// Same as [`Header::create()`], but the digest and the merkle root are calculated by the algorithm.
+    pub fn create_with_algorithm<T, VT>(
+        height: u64,
+        timestamp: Timestamp,
+        previous_digest: Digest,
+        difficulty: Difficulty,
+        transactions: &[Transaction<T, VT>],
+        nonce: u64,
+    ) -> Option<Self>
+    where
+        T: ByteOrder,
+    {
+        let merkle_root = Digest::from_bytes(build_merkle_tree::<H, _, _>(transactions).root()?);
 
         let mut header = Self {
             height,
@@ -64,7 +135,12 @@ impl Header {
             difficulty,
             merkle_root,
             nonce,
-            digest: calculate_digest(""), // Temporal value to instantiate
+            state_root: None,
+            network: None,
+            hash_mode: HashMode::Single,
+            target: None,
+            digest: Digest::ZERO, // Temporal value to instantiate
+            _algorithm: PhantomData,
         };
 
         header.modify_nonce(nonce); // Set nonce and calculate digest
@@ -80,7 +156,7 @@ impl Header {
         self.timestamp
     }
 
-    pub fn previous_digest(&self) -> &Sha256Digest {
+    pub fn previous_digest(&self) -> &Digest {
         &self.previous_digest
     }
 
@@ -88,7 +164,7 @@ impl Header {
         self.difficulty
     }
 
-    pub fn merkle_root(&self) -> &Sha256Digest {
+    pub fn merkle_root(&self) -> &Digest {
         &self.merkle_root
     }
 
@@ -96,10 +172,101 @@ impl Header {
         self.nonce
     }
 
-    pub fn digest(&self) -> &Sha256Digest {
+    /// Returns root of ledger state after applying the block, if the header commits to it.
+    pub fn state_root(&self) -> Option<&Digest> {
+        self.state_root.as_ref()
+    }
+
+    /// Returns the network, which transactions in the block are signed for.
+    pub fn network(&self) -> Option<&NetworkId> {
+        self.network.as_ref()
+    }
+
+    pub fn hash_mode(&self) -> HashMode {
+        self.hash_mode
+    }
+
+    /// Returns the target which the digest must satisfy instead of the difficulty, if the header is of [`PowMode::Target`].
+    pub fn target(&self) -> Option<Target> {
+        self.target.map(|compact| {
+            Target::from_compact(compact).expect("Header must have a valid compact target.")
+        })
+    }
+
+    /// Returns the compact form of [`Header::target()`], which the byte order contains.
+    pub fn compact_target(&self) -> Option<u32> {
+        self.target
+    }
+
+    /// Returns the Proof-of-Work mode of the header, which is [`PowMode::Target`] if it has a target.
+    pub fn pow_mode(&self) -> PowMode {
+        match self.target {
+            Some(_) => PowMode::Target,
+            None => PowMode::ZeroBits,
+        }
+    }
+
+    pub fn digest(&self) -> &Digest {
         &self.digest
     }
 
+    /// Calculates digest of the header by its hash mode, which equals to [`Header::digest()`] if the header is intact.
+    pub fn calculate_digest(&self) -> Digest {
+        H::finish_mode(self.hash_mode, H::hash_byte_order(self))
+    }
+
+    /// Returns a line for logs, e.g., `height=42 digest=ba7816bf…0015ad previous=00000000…000000 nonce=7 difficulty=1`.
+    ///
+    /// The format is fixed so that log parsers can rely on it.
+    pub fn summary(&self) -> String {
+        format!(
+            "height={} digest={} previous={} nonce={} difficulty={}",
+            self.height,
+            self.digest.abbrev(),
+            self.previous_digest.abbrev(),
+            self.nonce,
+            self.difficulty.zero_bits()
+        )
+    }
+
+    /// Returns expected number of attempts to find the header, by [`Target::work()`] if it has a target,
+    /// otherwise by [`Difficulty::work()`].
+    pub fn work(&self) -> u128 {
+        match self.target() {
+            Some(target) => target.work(),
+            None => self.difficulty.work(),
+        }
+    }
+
+    /// Returns whether the header is of a genesis block, i.e., its height is 0 and its previous digest is [`Digest::ZERO`].
+    pub fn is_genesis(&self) -> bool {
+        self.height == 0 && self.previous_digest.is_zero()
+    }
+
+    /// Returns whether the header directly follows `previous` in a chain,
+    /// i.e., its height is next to `previous` and its previous digest points to `previous`.
+    pub fn follows(&self, previous: &Header<H>) -> bool {
+        previous.height.checked_add(1) == Some(self.height)
+            && self.previous_digest.ct_eq(&previous.digest)
+    }
+
+    /// Verify that the header's digest is correctly calculated and satisfies its difficulty.
+    ///
+    /// This requires only the header, so it can be used by clients which do not download block bodies.
+    ///
+    /// The digest must satisfy the target if the header has one, otherwise the difficulty.
+    pub fn verify_proof_of_work(&self) -> Result<(), BlockError> {
+        if !self.satisfies_work(&self.digest) {
+            return Err(BlockError::Difficulty);
+        }
+
+        if !self.digest.ct_eq(&self.calculate_digest()) {
+            return Err(BlockError::Digest);
+        }
+
+        Ok(())
+    }
+
     /// Sets the given nonce, then re-calculates header's digest.
     pub fn modify_nonce(&mut self, nonce: u64) {
         self.nonce = nonce;
@@ -107,21 +274,342 @@ impl Header {
         self.set_digest();
     }
 
+    /// Sets the given state root, then re-calculates header's digest.
+    ///
+    /// Since the digest changes, Proof-of-Work process must be executed after this.
+    pub fn modify_state_root(&mut self, state_root: Option<Digest>) {
+        self.state_root = state_root;
+        self.set_digest();
+    }
+
+    /// Sets the given network, then re-calculates header's digest.
+    ///
+    /// Since the digest changes, Proof-of-Work process must be executed after this.
+    pub fn modify_network(&mut self, network: Option<NetworkId>) {
+        self.network = network;
+        self.set_digest();
+    }
+
+    /// Sets the given hash mode, then re-calculates header's digest.
+    ///
+    /// Since the digest changes, Proof-of-Work process must be executed after this.
+    pub fn modify_hash_mode(&mut self, hash_mode: HashMode) {
+        self.hash_mode = hash_mode;
+        self.set_digest();
+    }
+
+    /// Sets the given target in the compact form, then re-calculates header's digest.
+    ///
+    /// Since the digest changes, Proof-of-Work process must be executed after this.
+    /// The target may be truncated by [`Target::to_compact()`], which [`Header::target()`] returns afterwards.
+    pub fn modify_target(&mut self, target: Option<Target>) {
+        self.target = target.map(|target| target.to_compact());
+        self.set_digest();
+    }
+
+    /// Checks whether the digest satisfies the target if the header has one, otherwise the difficulty.
+    fn satisfies_work(&self, digest: &Digest) -> bool {
+        match self.target() {
+            Some(target) => target.verify_digest(digest),
+            None => self.difficulty.verify_digest(digest),
+        }
+    }
+
     fn set_digest(&mut self) {
-        let byte_order = self.build_byte_order();
-        self.digest = calculate_digest(&byte_order);
+        self.digest = self.calculate_digest();
     }
-}
 
-impl ByteOrder for Header {
-    fn append_bytes(&self, buf: &mut Vec<u8>) {
+    /// Returns [`HashAlgorithm::ID`] which the byte order contains, that is, `None` for [`Sha256`].
+    fn algorithm_id() -> Option<u8> {
+        (H::ID != Sha256::ID).then_some(H::ID)
+    }
+
+    /// Append byte order of fields before the nonce.
+    fn append_prefix(&self, buf: &mut Vec<u8>) {
         buf.extend(self.height.to_be_bytes());
         self.timestamp.append_bytes(buf);
-        buf.extend(&self.previous_digest);
+        buf.extend(self.previous_digest.as_bytes());
         self.difficulty.append_bytes(buf);
-        buf.extend(&self.merkle_root);
-        buf.extend(self.nonce.to_le_bytes());
+        buf.extend(self.merkle_root.as_bytes());
+    }
+
+    /// Append byte order of the given nonce and fields after it.
+    /// Each present optional field follows its tag, so that headers with different optional fields never share byte order.
+    fn append_suffix(&self, nonce: u64, buf: &mut Vec<u8>) {
+        buf.extend(nonce.to_le_bytes());
+        if let Some(state_root) = &self.state_root {
+            buf.push(STATE_ROOT_TAG);
+            buf.extend(state_root.as_bytes());
+        }
+        if let Some(network) = &self.network {
+            buf.push(NETWORK_TAG);
+            buf.extend(network);
+        }
+        if let Some(target) = self.target {
+            buf.push(TARGET_TAG);
+            buf.extend(target.to_le_bytes());
+        }
+        if let Some(id) = Self::algorithm_id() {
+            buf.push(ALGORITHM_TAG);
+            buf.push(id);
+        }
+    }
+
+    /// Append wire bytes of the header, which are its hash mode followed by its byte order prefixed by the length as big endian `u32`.
+    fn append_wire_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(self.hash_mode.wire_byte());
+        buf.extend((self.byte_len() as u32).to_be_bytes());
+        self.append_bytes(buf);
     }
+
+    /// Read a header written by [`Header::append_wire_bytes()`], and calculate its digest.
+    fn read_wire(reader: &mut BoundedReader<'_>) -> Result<Self, DecodeError> {
+        let [byte] = reader.read_array("hash mode")?;
+        let hash_mode = [HashMode::Single, HashMode::Double]
+            .into_iter()
+            .find(|mode| mode.wire_byte() == byte)
+            .ok_or_else(|| {
+                malformed(
+                    "hash mode",
+                    format!("Unknown hash mode byte 0x{:02x}.", byte),
+                )
+            })?;
+        let bytes = reader.read_length_prefixed("header", MAX_HEADER_BYTES)?;
+        let mut reader = BoundedReader::new(bytes, MAX_HEADER_BYTES);
+
+        let height = u64::from_be_bytes(reader.read_array("height")?);
+        let timestamp = i64::from_le_bytes(reader.read_array("timestamp")?);
+        let timestamp =
+            Timestamp::try_from_nanos(timestamp).map_err(|e| malformed("timestamp", e))?;
+        let previous_digest = Digest::from_bytes(reader.read_array("previous digest")?);
+        let difficulty = u64::from_le_bytes(reader.read_array("difficulty")?);
+        let difficulty = Difficulty::try_new(difficulty).map_err(|e| malformed("difficulty", e))?;
+        let merkle_root = Digest::from_bytes(reader.read_array("merkle root")?);
+        let nonce = u64::from_le_bytes(reader.read_array("nonce")?);
+
+        let mut header = Self {
+            height,
+            timestamp,
+            previous_digest,
+            difficulty,
+            merkle_root,
+            nonce,
+            state_root: None,
+            network: None,
+            hash_mode,
+            target: None,
+            digest: Digest::ZERO, // Temporal value to instantiate
+            _algorithm: PhantomData,
+        };
+        let mut algorithm_id = None;
+        // Optional fields are in ascending order of their tags, each of which appears at most once.
+        let mut last_tag = 0;
+        while reader.remaining() > 0 {
+            let [tag] = reader.read_array("optional field")?;
+            if tag <= last_tag {
+                return Err(malformed(
+                    "optional field",
+                    format!("Tag 0x{:02x} is out of order.", tag),
+                ));
+            }
+            match tag {
+                STATE_ROOT_TAG => {
+                    header.state_root = Some(Digest::from_bytes(reader.read_array("state root")?))
+                }
+                NETWORK_TAG => header.network = Some(reader.read_array("network")?),
+                TARGET_TAG => {
+                    let compact = u32::from_le_bytes(reader.read_array("target")?);
+                    Target::from_compact(compact).map_err(|e| malformed("target", e))?;
+                    header.target = Some(compact);
+                }
+                ALGORITHM_TAG => {
+                    let [id] = reader.read_array("algorithm")?;
+                    algorithm_id = Some(id);
+                }
+                _ => {
+                    return Err(malformed(
+                        "optional field",
+                        format!("Unknown tag 0x{:02x}.", tag),
+                    ))
+                }
+            }
+            last_tag = tag;
+        }
+        if algorithm_id != Self::algorithm_id() {
+            return Err(malformed(
+                "algorithm",
+                format!("Header must be of algorithm {}.", H::ID),
+            ));
+        }
+        header.set_digest();
+
+        Ok(header)
+    }
+}
+
+fn malformed(field: &'static str, reason: impl Display) -> DecodeError {
+    DecodeError::Malformed {
+        field,
+        reason: reason.to_string(),
+    }
+}
+
+/// Optional fields are omitted if absent in human-readable formats such as JSON, so that legacy headers keep their JSON.
+/// So is the hash mode if it is [`HashMode::Single`]. The target is in the compact form.
+/// Binary formats such as bincode cannot omit fields of a struct, so they always contain optional fields and the hash mode.
+impl<H: HashAlgorithm> Serialize for Header<H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let omits_absent = serializer.is_human_readable();
+        let optionals = [
+            ("state_root", self.state_root.map(hex::encode)),
+            ("network", self.network.map(hex::encode)),
+        ];
+        let omits_hash_mode = omits_absent && self.hash_mode == HashMode::Single;
+        let omits_target = omits_absent && self.target.is_none();
+        let len = 7
+            + optionals
+                .iter()
+                .filter(|(_, value)| value.is_some() || !omits_absent)
+                .count()
+            + usize::from(!omits_hash_mode)
+            + usize::from(!omits_target);
+
+        let mut state = serializer.serialize_struct("Header", len)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("timestamp", &self.timestamp)?;
+        state.serialize_field("previous_digest", &self.previous_digest)?;
+        state.serialize_field("difficulty", &self.difficulty)?;
+        state.serialize_field("merkle_root", &self.merkle_root)?;
+        state.serialize_field("nonce", &self.nonce)?;
+        for (key, value) in &optionals {
+            if value.is_none() && omits_absent {
+                state.skip_field(key)?;
+            } else {
+                state.serialize_field(key, value)?;
+            }
+        }
+        if omits_hash_mode {
+            state.skip_field("hash_mode")?;
+        } else {
+            state.serialize_field("hash_mode", &self.hash_mode)?;
+        }
+        if omits_target {
+            state.skip_field("target")?;
+        } else {
+            state.serialize_field("target", &self.target)?;
+        }
+        state.serialize_field("digest", &self.digest)?;
+        state.end()
+    }
+}
+
+impl<H: HashAlgorithm> ByteOrder for Header<H> {
+    fn append_bytes(&self, buf: &mut Vec<u8>) {
+        self.append_prefix(buf);
+        self.append_suffix(self.nonce, buf);
+    }
+
+    /// Same layout as [`Header::append_prefix()`] and [`Header::append_suffix()`], without a buffer.
+    fn append_to_hasher(&self, hasher: &mut Hasher) {
+        hasher.update(&self.height.to_be_bytes());
+        self.timestamp.append_to_hasher(hasher);
+        hasher.update(self.previous_digest.as_bytes());
+        self.difficulty.append_to_hasher(hasher);
+        hasher.update(self.merkle_root.as_bytes());
+        hasher.update(&self.nonce.to_le_bytes());
+        if let Some(state_root) = &self.state_root {
+            hasher.update(&[STATE_ROOT_TAG]);
+            hasher.update(state_root.as_bytes());
+        }
+        if let Some(network) = &self.network {
+            hasher.update(&[NETWORK_TAG]);
+            hasher.update(network);
+        }
+        if let Some(target) = self.target {
+            hasher.update(&[TARGET_TAG]);
+            hasher.update(&target.to_le_bytes());
+        }
+        if let Some(id) = Self::algorithm_id() {
+            hasher.update(&[ALGORITHM_TAG, id]);
+        }
+    }
+
+    fn byte_len(&self) -> usize {
+        let fixed = 8 + self.timestamp.byte_len() + 32 + self.difficulty.byte_len() + 32 + 8;
+        fixed
+            + self.state_root.map_or(0, |root| 1 + root.as_bytes().len())
+            + self.network.map_or(0, |network| 1 + network.len())
+            + self.target.map_or(0, |_| 1 + std::mem::size_of::<u32>())
+            + Self::algorithm_id().map_or(0, |_| 2)
+    }
+}
+
+/// Calculates digests of a header with various nonces.
+///
+/// Byte order before the nonce is absorbed only once,
+/// so each attempt costs cloning [`Midstate`] and absorbing the nonce and the following fields.
+#[derive(Debug, Clone)]
+pub struct MiningContext<H: HashAlgorithm = Sha256> {
+    midstate: H::Midstate,
+    /// Byte order from the nonce, whose first 8 bytes are replaced on each attempt.
+    suffix: Vec<u8>,
+    hash_mode: HashMode,
+}
+
+impl<H: HashAlgorithm> MiningContext<H> {
+    pub fn new(header: &Header<H>) -> Self {
+        let mut prefix = vec![];
+        header.append_prefix(&mut prefix);
+        let mut suffix = vec![];
+        header.append_suffix(header.nonce, &mut suffix);
+
+        Self {
+            midstate: H::absorb_prefix(&prefix),
+            suffix,
+            hash_mode: header.hash_mode,
+        }
+    }
+
+    /// Returns digest of the header whose nonce is replaced with the given one.
+    pub fn digest(&mut self, nonce: u64) -> Digest {
+        self.suffix[..8].copy_from_slice(&nonce.to_le_bytes());
+        H::finish_mode(self.hash_mode, H::finish_with(&self.midstate, &self.suffix))
+    }
+}
+
+/// Rejects an invalid compact target, so that [`Header::target()`] never fails.
+fn deserialize_compact_target<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    let compact = Option::<u32>::deserialize(deserializer)?;
+    if let Some(compact) = compact {
+        Target::from_compact(compact).map_err(D::Error::custom)?;
+    }
+    Ok(compact)
+}
+
+pub(crate) fn serialize_optional_hex<S: Serializer, const N: usize>(
+    bytes: &Option<[u8; N]>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    bytes.map(hex::encode).serialize(serializer)
+}
+
+fn deserialize_optional_digest<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Digest>, D::Error> {
+    deserialize_optional_hex(deserializer).map(|bytes| bytes.map(Digest::from_bytes))
+}
+
+pub(crate) fn deserialize_optional_hex<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<Option<[u8; N]>, D::Error> {
+    let hex = match Option::<String>::deserialize(deserializer)? {
+        Some(hex) => hex,
+        None => return Ok(None),
+    };
+    let bytes = hex0x::decode_to_array(&hex).map_err(D::Error::custom)?;
+    Ok(Some(bytes))
 }
 
 /// Block.
@@ -129,21 +617,193 @@ impl ByteOrder for Header {
 /// - `T` Transaction content.
 /// - `VT` Verification process marker of transactions.
 /// - `VB` Verification process marker of block integrity.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Block<T, VT, VB> {
-    header: Header,
+/// - `H` Hash algorithm of the header and the merkle tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(bound(serialize = "T: Serialize, H: HashAlgorithm"))]
+pub struct Block<T, VT, VB, H = Sha256> {
+    header: Header<H>,
     transactions: Vec<Transaction<T, VT>>,
+    #[serde(skip_serializing)]
     _phantom: PhantomData<fn() -> VB>,
 }
 
-impl<T, VT, VB> Block<T, VT, VB> {
-    pub fn header(&self) -> &Header {
+impl<T, VT, VB, H: HashAlgorithm> Block<T, VT, VB, H> {
+    pub fn header(&self) -> &Header<H> {
         &self.header
     }
 
     pub fn transactions(&self) -> &[Transaction<T, VT>] {
         &self.transactions
     }
+
+    /// Drop the verification markers, e.g., to send the block to another node, which verifies it by itself.
+    pub fn into_unverified(self) -> Block<T, Yet, Yet, H> {
+        Block {
+            header: self.header,
+            transactions: self
+                .transactions
+                .into_iter()
+                .map(Transaction::into_unverified)
+                .collect(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Build a filter over accounts which offered transactions in the block.
+    pub fn account_filter(&self) -> AccountFilter {
+        AccountFilter::from_accounts(self.transactions.iter().map(Transaction::account))
+    }
+
+    /// Verify that the state root declared in the header matches with locally computed one.
+    /// A header without state root always passes.
+    pub fn verify_state_root(&self, computed: &Digest) -> Result<(), BlockError> {
+        match self.header.state_root() {
+            Some(declared) if !declared.ct_eq(computed) => Err(BlockError::StateRoot {
+                expected: *computed,
+                actual: *declared,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T, VT, VB> Block<T, VT, VB> {
+    /// Build a proof that the transaction at the given index is contained in the block.
+    ///
+    /// # Returns
+    /// `None` if the index is out of range, otherwise, `Some(proof)`.
+    pub fn merkle_proof(&self, tx_index: usize) -> Option<MerkleProof> {
+        if tx_index >= self.transactions.len() {
+            return None;
+        }
+
+        let proof = build_merkle_tree::<Sha256, _, _>(&self.transactions).proof(&[tx_index]);
+        let proof = MerkleProof {
+            tx_index,
+            tx_count: self.transactions.len(),
+            hashes: proof.to_bytes(),
+        };
+        Some(proof)
+    }
+}
+
+impl<T: ByteOrder, VT, VB, H: HashAlgorithm> Block<T, VT, VB, H> {
+    /// Returns wire bytes of the block, which [`Block::from_wire_bytes()`] decodes.
+    ///
+    /// Wire bytes are the header, the number of transactions as big endian `u32` and the transactions.
+    /// Unlike [`Block::encoded_size()`], they also contain the hash mode, sign modes and lengths of contents.
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        self.header.append_wire_bytes(&mut buf);
+        buf.extend((self.transactions.len() as u32).to_be_bytes());
+        for tx in self.transactions.iter() {
+            tx.append_wire_bytes(&mut buf);
+        }
+        buf
+    }
+
+    /// Returns size of the block in bytes, which is byte order of the header and [`Transaction::encoded_size()`] of each transaction.
+    ///
+    /// This is the size limited by [`ChainParams::max_block_bytes`](crate::params::ChainParams::max_block_bytes).
+    pub fn encoded_size(&self) -> usize {
+        self.header.byte_len()
+            + self
+                .transactions
+                .iter()
+                .map(Transaction::encoded_size)
+                .sum::<usize>()
+    }
+
+    /// Returns sizes of the header and each transaction, which sum up to [`Block::encoded_size()`].
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        let header = self.header.byte_len();
+        let transactions = self
+            .transactions
+            .iter()
+            .map(Transaction::encoded_size)
+            .collect::<Vec<_>>();
+        let total = header + transactions.iter().sum::<usize>();
+        SizeBreakdown {
+            header,
+            transactions,
+            total,
+        }
+    }
+}
+
+/// Sizes of parts of a block in bytes, which is returned by [`Block::size_breakdown()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeBreakdown {
+    pub header: usize,
+    /// Size of each transaction, in the same order as the block.
+    pub transactions: Vec<usize>,
+    pub total: usize,
+}
+
+/// Module-inner struct, which has same field with Block, except verification marker field.
+/// This is used to deserialize data into unverified block.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct BlockWithoutMarker<T, H> {
+    header: Header<H>,
+    transactions: Vec<Transaction<T, Yet>>,
+}
+
+impl<'de, T, H> Deserialize<'de> for Block<T, Yet, Yet, H>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Deserialize into block without marker
+        let inner = BlockWithoutMarker::deserialize(deserializer)?;
+
+        // Append 'unverified' marker to deserialized block.
+        Ok(Block {
+            header: inner.header,
+            transactions: inner.transactions,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl Block<JellyfishTransactionContent, Yet, Yet> {
+    /// Decode wire bytes given by [`Block::to_wire_bytes()`], e.g., ones received from a peer.
+    ///
+    /// Every count and length is checked against the limits of the parameters before it is used,
+    /// so that a malicious input fails fast without a large allocation.
+    /// The block is not verified, even whether it satisfies the limits as a whole.
+    pub fn from_wire_bytes(buf: &[u8], params: &ChainParams) -> Result<Self, DecodeError> {
+        // Each transaction has a sign mode and a length of its content in addition to its encoded size.
+        let budget = 1
+            + 4
+            + MAX_HEADER_BYTES
+            + 4
+            + params.max_block_bytes
+            + params.max_block_transactions * (1 + 4);
+        let mut reader = BoundedReader::new(buf, budget);
+
+        let header = Header::read_wire(&mut reader)?;
+        let count = reader.read_count("transactions", params.max_block_transactions)?;
+        let mut transactions = Vec::with_capacity(count);
+        for _ in 0..count {
+            let tx = Transaction::read_wire(
+                &mut reader,
+                params.max_block_bytes,
+                params.max_record_bytes,
+            )?;
+            transactions.push(tx);
+        }
+        reader.finish("block")?;
+
+        Ok(Block {
+            header,
+            transactions,
+            _phantom: PhantomData,
+        })
+    }
 }
 
 impl<T, VT> Block<T, VT, Yet> {
@@ -157,15 +817,31 @@ impl<T, VT> Block<T, VT, Yet> {
     pub fn create(
         height: u64,
         timestamp: Timestamp,
-        previous_digest: Sha256Digest,
+        previous_digest: Digest,
         difficulty: Difficulty,
         transactions: Vec<Transaction<T, VT>>,
     ) -> Result<Block<T, VT, Yet>, BlockError>
+    where
+        T: ByteOrder,
+    {
+        Self::create_with_algorithm(height, timestamp, previous_digest, difficulty, transactions)
+    }
+}
+
+impl<T, VT, H: HashAlgorithm> Block<T, VT, Yet, H> {
+    /// Same as [`Block::create()`], but the header's digest and the merkle root are calculated by the algorithm.
+    pub fn create_with_algorithm(
+        height: u64,
+        timestamp: Timestamp,
+        previous_digest: Digest,
+        difficulty: Difficulty,
+        transactions: Vec<Transaction<T, VT>>,
+    ) -> Result<Self, BlockError>
     where
         T: ByteOrder,
     {
         let nonce = 0;
-        let header = Header::create(
+        let header = Header::create_with_algorithm(
             height,
             timestamp,
             previous_digest,
@@ -185,21 +861,85 @@ impl<T, VT> Block<T, VT, Yet> {
     /// Returns mutable reference to the header.
     ///
     /// This method is designed to execute Proof-of-Work process via [`Header::modify_nonce()`].
-    pub fn header_mut(&mut self) -> &mut Header {
+    pub fn header_mut(&mut self) -> &mut Header<H> {
         &mut self.header
     }
+
+    /// Execute Proof-of-Work process, increasing nonce from the current one until the digest satisfies the difficulty,
+    /// or the target if the header has one.
+    pub fn mine(mut self) -> Self {
+        let mut context = MiningContext::new(&self.header);
+        let mut nonce = self.header.nonce;
+        while !self.header.satisfies_work(&context.digest(nonce)) {
+            nonce += 1;
+        }
+        self.header.modify_nonce(nonce);
+        self
+    }
+
+    /// Fast path of [`Block::mine()`] for regtest, which accepts the current nonce without calculating digest.
+    ///
+    /// # Returns
+    /// `Err(BlockError::Difficulty)` if the difficulty is not [`MIN_DIFFICULTY`],
+    /// so that blocks of real networks never skip Proof-of-Work process.
+    /// Headers with a target are also rejected, since even the easiest compact target does not accept every digest.
+    pub fn mine_regtest(self) -> Result<Self, BlockError> {
+        if self.header.difficulty == MIN_DIFFICULTY && self.header.target.is_none() {
+            Ok(self)
+        } else {
+            Err(BlockError::Difficulty)
+        }
+    }
 }
 
-impl<T, VB> Block<T, Yet, VB>
+impl<T, VB, H: HashAlgorithm> Block<T, Yet, VB, H>
 where
     T: ByteOrder,
 {
     /// Verify all sign of transactions in the block.
-    pub fn verify_transactions(self) -> Result<Block<T, Verified, VB>, BlockError> {
+    ///
+    /// If the header has a network, signatures must be created for the network by [`Transaction::create_for()`].
+    pub fn verify_transactions(self) -> Result<Block<T, Verified, VB, H>, BlockError> {
+        self.verify_transactions_with(None, None)
+    }
+
+    /// Same as [`Block::verify_transactions()`], but signatures verified before, e.g., in mempool, are accepted by the cache.
+    pub fn verify_transactions_cached(
+        self,
+        cache: &SigCache,
+    ) -> Result<Block<T, Verified, VB, H>, BlockError> {
+        self.verify_transactions_with(None, Some(cache))
+    }
+
+    /// Same as [`Block::verify_transactions()`], but signatures must be made under [`ChainParams::transaction_context()`].
+    ///
+    /// Headers do not tell the protocol version, so blocks of protocol version 2 or later must be verified by this.
+    pub fn verify_transactions_with_params(
+        self,
+        params: &ChainParams,
+    ) -> Result<Block<T, Verified, VB, H>, BlockError> {
+        self.verify_transactions_with(params.transaction_context(), None)
+    }
+
+    /// Same as [`Block::verify_transactions_with_params()`], but signatures verified before are accepted by the cache.
+    pub fn verify_transactions_cached_with_params(
+        self,
+        params: &ChainParams,
+        cache: &SigCache,
+    ) -> Result<Block<T, Verified, VB, H>, BlockError> {
+        self.verify_transactions_with(params.transaction_context(), Some(cache))
+    }
+
+    fn verify_transactions_with(
+        self,
+        context: Option<&[u8]>,
+        cache: Option<&SigCache>,
+    ) -> Result<Block<T, Verified, VB, H>, BlockError> {
+        let network = self.header.network;
         let result = self
             .transactions
             .into_iter()
-            .map(Transaction::verify)
+            .map(|tx| tx.verify_with(network.as_ref(), context, cache))
             .collect::<Result<Vec<_>, TransactionError>>();
         match result {
             Ok(transactions) => {
@@ -215,7 +955,7 @@ where
     }
 }
 
-impl<T, VT> Block<T, VT, Yet> {
+impl<T, VT, H: HashAlgorithm> Block<T, VT, Yet, H> {
     /// Verify integrity of the block.
     /// # Parameters
     /// - `previous_digest_judge` Given a header of verification-target block, returns `true` if the previous digest is meet with blockchain.
@@ -228,26 +968,20 @@ impl<T, VT> Block<T, VT, Yet> {
     pub fn verify_block<F>(
         self,
         previous_digest_judge: F,
-    ) -> Result<Block<T, VT, Verified>, BlockError>
+    ) -> Result<Block<T, VT, Verified, H>, BlockError>
     where
-        F: FnOnce(&Header) -> bool,
+        F: FnOnce(&Header<H>) -> bool,
     {
-        let merkle_root = match build_merkle_tree(&self.transactions).root() {
-            Some(root) => root,
+        let merkle_root = match build_merkle_tree::<H, _, _>(&self.transactions).root() {
+            Some(root) => Digest::from_bytes(root),
             None => return Err(BlockError::Empty),
         };
 
-        if &merkle_root != self.header.merkle_root() {
+        if !self.header.merkle_root().ct_eq(&merkle_root) {
             return Err(BlockError::Merkle);
         }
 
-        if !self.header.difficulty().verify_digest(self.header.digest()) {
-            return Err(BlockError::Difficulty);
-        }
-
-        if self.header.digest() != &calculate_digest(&self.header().build_byte_order()) {
-            return Err(BlockError::Digest);
-        }
+        self.header.verify_proof_of_work()?;
 
         if !previous_digest_judge(&self.header) {
             return Err(BlockError::PreviousDigest);
@@ -263,6 +997,44 @@ impl<T, VT> Block<T, VT, Yet> {
     }
 }
 
+/// Proof that a transaction is contained in a block, without revealing other transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Index of the transaction in the block.
+    tx_index: usize,
+    /// Number of transactions in the block.
+    tx_count: usize,
+    /// Sibling hashes required to calculate merkle root.
+    #[serde(with = "hex")]
+    hashes: Vec<u8>,
+}
+
+impl MerkleProof {
+    pub fn tx_index(&self) -> usize {
+        self.tx_index
+    }
+
+    pub fn tx_count(&self) -> usize {
+        self.tx_count
+    }
+
+    /// Returns whether the transaction is contained in a block which has the given merkle root.
+    pub fn verify<T, VT>(&self, merkle_root: &Digest, transaction: &Transaction<T, VT>) -> bool {
+        let proof = match rs_merkle::MerkleProof::<DefaultMerkle>::from_bytes(&self.hashes) {
+            Ok(proof) => proof,
+            Err(_) => return false,
+        };
+        let leaf = merkle_leaf::<Sha256, _, _>(transaction);
+
+        proof.verify(
+            merkle_root.to_bytes(),
+            &[self.tx_index],
+            &[leaf],
+            self.tx_count,
+        )
+    }
+}
+
 /// An error occurred during creating or verifying a block.
 #[derive(Debug)]
 pub enum BlockError {
@@ -274,10 +1046,23 @@ pub enum BlockError {
     Digest,
     /// Previous digest does not match.
     PreviousDigest,
+    /// Block height does not follow the previous block.
+    Height,
     /// Header's markle root does not match with that from block's transactions.
     Merkle,
     /// Block digest does not satisfy difficulty.
     Difficulty,
+    /// Transactions cannot be applied to ledger state.
+    State(StateError),
+    /// Header or block violates chain parameters.
+    Params(ParamsError),
+    /// Header's state root does not match with locally computed one.
+    /// `expected` is the computed root and `actual` is the declared one.
+    StateRoot { expected: Digest, actual: Digest },
+    /// Target of a transaction cannot be resolved against the chain.
+    Target(TargetError),
+    /// Block is inconsistent with the chain history, e.g., it declares a wrong difficulty.
+    Chain(ChainError),
 }
 
 impl Display for BlockError {
@@ -289,116 +1074,819 @@ impl Display for BlockError {
             Empty => write!(f, "No transaction in block."),
             Digest => write!(f, "Header's digest does not match."),
             PreviousDigest => write!(f, "Previous digest does not match."),
+            Height => write!(f, "Block height does not follow the previous block."),
             Merkle => write!(
                 f,
                 "Header's markle root does not match with that from block's transactions."
             ),
             Difficulty => write!(f, "Block digest does not satisfy difficulty."),
+            State(e) => write!(f, "Ledger state update failed: {}", e),
+            Params(e) => e.fmt(f),
+            StateRoot { expected, actual } => write!(
+                f,
+                "Header's state root {} does not match with computed one {}.",
+                actual.abbrev(),
+                expected.abbrev()
+            ),
+            Target(e) => write!(f, "Transaction target is invalid: {}", e),
+            Chain(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use BlockError::*;
+
+        match self {
+            Transaction(e) => Some(e),
+            State(e) => Some(e),
+            Params(e) => Some(e),
+            Target(e) => Some(e),
+            Chain(e) => Some(e),
+            Empty | Digest | PreviousDigest | Height | Merkle | Difficulty | StateRoot { .. } => {
+                None
+            }
+        }
+    }
+}
+
+/// Returns the sum of [`Header::work()`] of the headers, which saturates at `u128::MAX`.
+///
+/// Forks are compared by this rather than by their heights, since a shorter fork may contain more work.
+/// The headers are not checked to form a chain.
+pub fn cumulative_work(headers: &[Header]) -> u128 {
+    headers
+        .iter()
+        .fold(0, |sum: u128, header| sum.saturating_add(header.work()))
+}
+
+/// Build merkle tree from given transactions by the algorithm.
+fn build_merkle_tree<H: HashAlgorithm, T, VT>(
+    transactions: &[Transaction<T, VT>],
+) -> MerkleTree<H::Merkle> {
+    let digests = transactions
+        .iter()
+        .map(merkle_leaf::<H, _, _>)
+        .collect_vec();
+
+    MerkleTree::from_leaves(&digests)
+}
+
+/// Leaf of merkle tree, which is digest of the transaction's sign by the algorithm.
+fn merkle_leaf<H: HashAlgorithm, T, VT>(transaction: &Transaction<T, VT>) -> Sha256Digest {
+    H::hash(transaction.sign().as_ref()).to_bytes()
+}
+
+#[cfg(test)]
+mod tests_stab {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Stab(pub &'static str);
+
+    impl crate::byteorder::ByteOrder for Stab {
+        fn append_bytes(&self, buf: &mut Vec<u8>) {
+            buf.extend(self.0.as_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_header {
+    use crate::SecretAccount;
+
+    use super::tests_stab::*;
+    use super::*;
+
+    #[test]
+    fn create_empty_transaction() {
+        let height = 42;
+        let timestamp = Timestamp::now();
+        let previous_digest = Digest::ZERO;
+        let difficulty = Difficulty::new(1);
+        let transactions: Vec<Transaction<Stab, Verified>> = vec![];
+        let nonce = 0;
+
+        let option = Header::create(
+            height,
+            timestamp,
+            previous_digest,
+            difficulty,
+            &transactions,
+            nonce,
+        );
+
+        assert!(option.is_none());
+    }
+
+    #[test]
+    fn modify_nonce() {
+        let height = 42;
+        let timestamp = Timestamp::now();
+        let previous_digest = Digest::ZERO;
+        let difficulty = Difficulty::new(1);
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            let content = Stab("hello");
+            let tx = Transaction::create(&secret_account, timestamp, content);
+            vec![tx]
+        };
+        let nonce = 0;
+
+        let mut header = Header::create(
+            height,
+            timestamp,
+            previous_digest,
+            difficulty,
+            &transactions,
+            nonce,
+        )
+        .unwrap();
+
+        let digest1 = *header.digest();
+
+        // Modify nonce, then digest is re-calculated.
+        // Thus, digest should change.
+        header.modify_nonce(header.nonce() + 1);
+        let digest2 = header.digest();
+
+        assert_ne!(&digest1, digest2);
+    }
+
+    #[test]
+    fn summary() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::from_bytes([0xab; 32]),
+            Difficulty::new(3),
+            &transactions,
+            7,
+        )
+        .unwrap();
+
+        let digest = header.digest().to_string();
+        assert_eq!(
+            header.summary(),
+            format!(
+                "height=42 digest={}…{} previous=abababab…ababab nonce=7 difficulty=3",
+                &digest[..8],
+                &digest[58..]
+            )
+        );
+    }
+
+    #[test]
+    fn is_genesis() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let create = |height, previous_digest| {
+            Header::create(
+                height,
+                Timestamp::now(),
+                previous_digest,
+                Difficulty::new(1),
+                &transactions,
+                0,
+            )
+            .unwrap()
+        };
+
+        assert!(create(0, Digest::ZERO).is_genesis());
+        // Height 0 with a parent is not genesis.
+        assert!(!create(0, Digest::create("parent")).is_genesis());
+        assert!(!create(1, Digest::ZERO).is_genesis());
+        assert!(!create(1, Digest::create("parent")).is_genesis());
+    }
+
+    #[test]
+    fn modify_state_root() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            Difficulty::new(1),
+            &transactions,
+            0,
+        )
+        .unwrap();
+        let byte_order = header.build_byte_order();
+        let digest = *header.digest();
+
+        header.modify_state_root(Some(Digest::from_bytes([7; 32])));
+
+        // State root is appended to the original byte order after its tag.
+        assert_eq!(
+            header.build_byte_order(),
+            [&byte_order[..], &[STATE_ROOT_TAG], &[7; 32]].concat()
+        );
+        assert_ne!(header.digest(), &digest);
+
+        header.modify_state_root(None);
+        assert_eq!(header.digest(), &digest);
+    }
+
+    #[test]
+    fn optional_fields_tagged() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
+        let target = Target::from_compact(0x2000ffff).unwrap();
+
+        // Network and compact target have the same length, but do not share byte order.
+        let mut network = header.clone();
+        network.modify_network(Some(target.to_compact().to_le_bytes()));
+        let mut compact = header.clone();
+        compact.modify_target(Some(target));
+        assert_ne!(network.build_byte_order(), compact.build_byte_order());
+        assert_ne!(network.digest(), compact.digest());
+
+        for header in [network, compact] {
+            assert_eq!(header.build_byte_order().len(), header.byte_len());
+            assert_eq!(header.digest(), &Digest::create(&header.build_byte_order()));
         }
     }
-}
 
-impl std::error::Error for BlockError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        use BlockError::*;
+    #[test]
+    fn target() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut block = Block::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            transactions,
+        )
+        .unwrap();
+        let byte_order = block.header().build_byte_order();
+        let digest = *block.header().digest();
+        assert_eq!(block.header().pow_mode(), PowMode::ZeroBits);
+
+        // Target is appended to the original byte order in the compact form after its tag.
+        let target = Target::from_compact(0x2000ffff).unwrap();
+        block.header_mut().modify_target(Some(target));
+        assert_eq!(
+            block.header().build_byte_order(),
+            [&byte_order[..], &[TARGET_TAG], &0x2000ffffu32.to_le_bytes()].concat()
+        );
+        assert_eq!(block.header().target(), Some(target));
+        assert_eq!(block.header().pow_mode(), PowMode::Target);
+
+        let mut header = block.header().clone();
+        header.modify_target(None);
+        assert_eq!(header.digest(), &digest);
+
+        // Digest must satisfy the target, although the difficulty is minimal.
+        let block = block.mine();
+        assert!(target.verify_digest(block.header().digest()));
+        assert!(block.header().verify_proof_of_work().is_ok());
+        assert!(block.clone().mine_regtest().is_err());
+    }
+
+    #[test]
+    fn work() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let header = |difficulty: Difficulty, target: Option<Target>| {
+            let mut header = Header::create(
+                42,
+                Timestamp::now(),
+                Digest::ZERO,
+                difficulty,
+                &transactions,
+                0,
+            )
+            .unwrap();
+            header.modify_target(target);
+            header
+        };
+
+        assert_eq!(header(Difficulty::new(3), None).work(), 8);
+        // Target takes precedence over the difficulty.
+        let target = Target::from_compact(0x20400000).unwrap();
+        assert_eq!(header(Difficulty::new(3), Some(target)).work(), 3);
+
+        assert_eq!(super::cumulative_work(&[]), 0);
+
+        // Longer chain of easier blocks, and shorter chain of harder blocks.
+        let long = vec![header(Difficulty::new(4), None); 10];
+        let short = vec![header(Difficulty::new(8), None); 2];
+        assert_eq!(super::cumulative_work(&long), 160);
+        assert_eq!(super::cumulative_work(&short), 512);
+        assert!(super::cumulative_work(&short) > super::cumulative_work(&long));
+
+        // Saturated.
+        let heavy = vec![header(Difficulty::new(127), None); 3];
+        assert_eq!(super::cumulative_work(&heavy), u128::MAX);
+        let saturated = [header(Difficulty::new(200), None)];
+        assert_eq!(super::cumulative_work(&saturated), u128::MAX);
+    }
+
+    #[test]
+    fn verify_proof_of_work_fail_target() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
+        header.modify_target(Some(Target::ZERO));
+
+        assert!(matches!(
+            header.verify_proof_of_work(),
+            Err(BlockError::Difficulty)
+        ));
+    }
+
+    #[test]
+    fn serialize_deserialize_target() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
+
+        // Absent target is omitted, so that headers keep the format before the target was introduced.
+        let ser = serde_json::to_string(&header).unwrap();
+        assert!(!ser.contains("target"));
+
+        header.modify_target(Some(Target::from_compact(0x1d00ffff).unwrap()));
+        let ser = serde_json::to_string(&header).unwrap();
+        assert!(ser.contains(r#""target":486604799"#));
+        assert_eq!(serde_json::from_str::<Header>(&ser).unwrap(), header);
+
+        // Negative compact target.
+        let negative = ser.replace("486604799", "76690518");
+        assert!(serde_json::from_str::<Header>(&negative).is_err());
+    }
+
+    #[test]
+    fn mining_context() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            Difficulty::new(1),
+            &transactions,
+            0,
+        )
+        .unwrap();
 
-        match self {
-            Transaction(e) => Some(e),
-            Empty | Digest | PreviousDigest | Merkle | Difficulty => None,
+        for hash_mode in [HashMode::Single, HashMode::Double] {
+            header.modify_hash_mode(hash_mode);
+            for (state_root, network) in [
+                (None, None),
+                (Some(Digest::from_bytes([7; 32])), None),
+                (None, Some([1, 2, 3, 4])),
+                (Some(Digest::from_bytes([7; 32])), Some([1, 2, 3, 4])),
+            ] {
+                header.modify_state_root(state_root);
+                header.modify_network(network);
+                let mut context = MiningContext::new(&header);
+
+                for nonce in [0, 1, 255, 256, u64::MAX] {
+                    header.modify_nonce(nonce);
+                    assert_eq!(&context.digest(nonce), header.digest());
+                }
+            }
         }
     }
-}
 
-/// Build merkle tree from given transactions.
-fn build_merkle_tree<T, VT>(transactions: &[Transaction<T, VT>]) -> MerkleTree<Sha256> {
-    let digests = transactions
-        .iter()
-        .map(|tx| tx.sign().as_ref())
-        .map(calculate_digest)
-        // Digest is always 32bytes, so the below unwrap() always succeeds.
-        .map(|digest| digest.as_ref().try_into().unwrap())
-        .collect_vec();
+    #[test]
+    fn hash_mode() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
+        let single = *header.digest();
+
+        header.modify_hash_mode(HashMode::Double);
+        let byte_order = header.build_byte_order();
+        assert_eq!(header.digest(), &Digest::create_double(&byte_order));
+        assert_ne!(header.digest(), &single);
+        assert!(header.verify_proof_of_work().is_ok());
+
+        // Digest of one mode does not verify in the other.
+        let mut flipped = header.clone();
+        flipped.hash_mode = HashMode::Single;
+        assert!(matches!(
+            flipped.verify_proof_of_work(),
+            Err(BlockError::Digest)
+        ));
+
+        header.modify_hash_mode(HashMode::Single);
+        assert_eq!(header.digest(), &single);
+        assert!(header.verify_proof_of_work().is_ok());
+        let mut flipped = header.clone();
+        flipped.hash_mode = HashMode::Double;
+        assert!(matches!(
+            flipped.verify_proof_of_work(),
+            Err(BlockError::Digest)
+        ));
+    }
 
-    MerkleTree::from_leaves(&digests)
-}
+    #[test]
+    fn serialize_deserialize_hash_mode() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
 
-#[cfg(test)]
-mod tests_stab {
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct Stab(pub &'static str);
+        // Single is omitted, so that headers keep the format before the mode was introduced.
+        let ser = serde_json::to_string(&header).unwrap();
+        assert!(!ser.contains("hash_mode"));
+        assert_eq!(serde_json::from_str::<Header>(&ser).unwrap(), header);
 
-    impl crate::byteorder::ByteOrder for Stab {
-        fn append_bytes(&self, buf: &mut Vec<u8>) {
-            buf.extend(self.0.as_bytes())
-        }
+        header.modify_hash_mode(HashMode::Double);
+        let ser = serde_json::to_string(&header).unwrap();
+        assert!(ser.contains(r#""hash_mode":"double""#));
+        let de = serde_json::from_str::<Header>(&ser).unwrap();
+        assert_eq!(de, header);
+        assert!(de.verify_proof_of_work().is_ok());
+
+        let bin = bincode::serialize(&header).unwrap();
+        assert_eq!(bincode::deserialize::<Header>(&bin).unwrap(), header);
     }
-}
 
-#[cfg(test)]
-mod tests_header {
-    use crate::SecretAccount;
+    #[test]
+    fn deserialize_prefixed_hex() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::from_bytes([0xab; 32]),
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
+        header.modify_state_root(Some(Digest::from_bytes([0xcd; 32])));
+        let ser = serde_json::to_string(&header).unwrap();
+
+        let previous = hex::encode([0xab; 32]);
+        let root = hex::encode([0xcd; 32]);
+        for (prefix, mixed_case) in [("0x", false), ("0X", true), ("", true)] {
+            let convert = |hex: &str| {
+                let hex = if mixed_case {
+                    hex.replacen("ab", "AB", 8)
+                } else {
+                    hex.to_owned()
+                };
+                format!(r#""{}{}""#, prefix, hex)
+            };
+            let prefixed = ser
+                .replace(&format!(r#""{}""#, previous), &convert(&previous))
+                .replace(&format!(r#""{}""#, root), &convert(&root));
 
-    use super::tests_stab::*;
-    use super::*;
+            assert_eq!(serde_json::from_str::<Header>(&prefixed).unwrap(), header);
+        }
+
+        // Prefix is not counted as the length.
+        let short = ser.replace(&previous, &format!("0x{}", &previous[2..]));
+        assert!(serde_json::from_str::<Header>(&short).is_err());
+    }
 
     #[test]
-    fn create_empty_transaction() {
-        let height = 42;
-        let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
-        let difficulty = Difficulty::new(1);
-        let transactions: Vec<Transaction<Stab, Verified>> = vec![];
-        let nonce = 0;
+    fn serialize_deserialize_state_root() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
 
-        let option = Header::create(
-            height,
-            timestamp,
-            previous_digest,
-            difficulty,
+        // Header without state root is serialized in the original format.
+        let ser = serde_json::to_string(&header).unwrap();
+        assert!(!ser.contains("state_root"));
+        assert_eq!(serde_json::from_str::<Header>(&ser).unwrap(), header);
+
+        header.modify_state_root(Some(Digest::from_bytes([7; 32])));
+        let ser = serde_json::to_string(&header).unwrap();
+        assert!(ser.contains(&hex::encode([7; 32])));
+        assert_eq!(serde_json::from_str::<Header>(&ser).unwrap(), header);
+    }
+
+    #[test]
+    fn streaming_digest_agrees_with_buffered() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::from_bytes([3; 32]),
+            MIN_DIFFICULTY,
             &transactions,
-            nonce,
-        );
+            7,
+        )
+        .unwrap();
 
-        assert!(option.is_none());
+        let assert_agrees = |header: &Header| {
+            let buffered = Digest::create(&header.build_byte_order());
+            assert_eq!(Digest::of_byte_order(header), buffered);
+            assert_eq!(header.digest(), &buffered);
+        };
+
+        assert_agrees(&header);
+        header.modify_state_root(Some(Digest::from_bytes([7; 32])));
+        assert_agrees(&header);
+        header.modify_network(Some([1, 2, 3, 4]));
+        assert_agrees(&header);
+        header.modify_state_root(None);
+        assert_agrees(&header);
+        for nonce in 0..100 {
+            header.modify_nonce(nonce);
+            assert_agrees(&header);
+        }
     }
 
     #[test]
-    fn modify_nonce() {
-        let height = 42;
-        let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
-        let difficulty = Difficulty::new(1);
+    fn bincode_round_trip() {
         let transactions = {
             let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
-            let content = Stab("hello");
-            let tx = Transaction::create(&secret_account, timestamp, content);
-            vec![tx]
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
         };
-        let nonce = 0;
+        let mut header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::ZERO,
+            MIN_DIFFICULTY,
+            &transactions,
+            0,
+        )
+        .unwrap();
+
+        // Binary formats contain absent optional fields, unlike JSON.
+        let legacy = bincode::serialize(&header).unwrap();
+        assert_eq!(bincode::deserialize::<Header>(&legacy).unwrap(), header);
+
+        header.modify_state_root(Some(Digest::from_bytes([7; 32])));
+        header.modify_network(Some([1, 2, 3, 4]));
+        let ser = bincode::serialize(&header).unwrap();
+        assert_eq!(bincode::deserialize::<Header>(&ser).unwrap(), header);
+        assert!(ser.len() > legacy.len());
+        // Stable across serializations.
+        assert_eq!(bincode::serialize(&header).unwrap(), ser);
+    }
+
+    #[test]
+    fn serialization_layout() {
+        /// Header in JSON, whose digests are hex.
+        #[derive(Serialize)]
+        struct JsonHeader {
+            height: u64,
+            timestamp: Timestamp,
+            #[serde(with = "hex")]
+            previous_digest: Sha256Digest,
+            difficulty: Difficulty,
+            #[serde(with = "hex")]
+            merkle_root: Sha256Digest,
+            nonce: u64,
+            state_root: Option<String>,
+            network: Option<String>,
+            hash_mode: HashMode,
+            target: Option<u32>,
+            #[serde(with = "hex")]
+            digest: Sha256Digest,
+        }
+
+        /// Header in binary formats, whose digests are raw 32 bytes.
+        #[derive(Serialize)]
+        struct BinaryHeader {
+            height: u64,
+            timestamp: Timestamp,
+            previous_digest: Sha256Digest,
+            difficulty: Difficulty,
+            merkle_root: Sha256Digest,
+            nonce: u64,
+            state_root: Option<String>,
+            network: Option<String>,
+            hash_mode: HashMode,
+            target: Option<u32>,
+            digest: Sha256Digest,
+        }
 
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
         let mut header = Header::create(
-            height,
-            timestamp,
-            previous_digest,
-            difficulty,
+            42,
+            Timestamp::now(),
+            Digest::from_bytes([0xab; 32]),
+            Difficulty::new(1),
             &transactions,
-            nonce,
+            7,
         )
         .unwrap();
+        // Every field is present, so that JSON contains the same fields as the mirrors.
+        header.modify_state_root(Some(Digest::from_bytes([0xcd; 32])));
+        header.modify_network(Some([1, 2, 3, 4]));
+        header.modify_hash_mode(HashMode::Double);
+        header.modify_target(Some(Difficulty::new(1).to_target()));
+
+        let json_header = JsonHeader {
+            height: header.height(),
+            timestamp: header.timestamp(),
+            previous_digest: header.previous_digest().to_bytes(),
+            difficulty: header.difficulty(),
+            merkle_root: header.merkle_root().to_bytes(),
+            nonce: header.nonce(),
+            state_root: header.state_root().map(hex::encode),
+            network: header.network().map(hex::encode),
+            hash_mode: header.hash_mode(),
+            target: header.compact_target(),
+            digest: header.digest().to_bytes(),
+        };
+        let binary_header = BinaryHeader {
+            height: header.height(),
+            timestamp: header.timestamp(),
+            previous_digest: header.previous_digest().to_bytes(),
+            difficulty: header.difficulty(),
+            merkle_root: header.merkle_root().to_bytes(),
+            nonce: header.nonce(),
+            state_root: header.state_root().map(hex::encode),
+            network: header.network().map(hex::encode),
+            hash_mode: header.hash_mode(),
+            target: header.compact_target(),
+            digest: header.digest().to_bytes(),
+        };
 
-        let digest1 = header.digest().clone();
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(json, serde_json::to_string(&json_header).unwrap());
+        assert_eq!(serde_json::from_str::<Header>(&json).unwrap(), header);
 
-        // Modify nonce, then digest is re-calculated.
-        // Thus, digest should change.
-        header.modify_nonce(header.nonce() + 1);
-        let digest2 = header.digest();
+        let bin = bincode::serialize(&header).unwrap();
+        assert_eq!(bin, bincode::serialize(&binary_header).unwrap());
+        assert_eq!(bincode::deserialize::<Header>(&bin).unwrap(), header);
+    }
 
-        assert_ne!(&digest1, digest2);
+    #[test]
+    fn bincode_fail_truncated() {
+        let transactions = {
+            let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+            vec![Transaction::create(
+                &secret_account,
+                Timestamp::now(),
+                Stab("hello"),
+            )]
+        };
+        let header = Header::create(
+            42,
+            Timestamp::now(),
+            Digest::from_bytes([0xab; 32]),
+            Difficulty::new(1),
+            &transactions,
+            7,
+        )
+        .unwrap();
+        let bin = bincode::serialize(&header).unwrap();
+
+        for len in 0..bin.len() {
+            assert!(bincode::deserialize::<Header>(&bin[..len]).is_err());
+        }
     }
 }
 
 #[cfg(test)]
 mod tests_block {
+    use crate::difficulty::MAX_DIFFICULTY;
     use crate::SecretAccount;
 
     use super::tests_stab::*;
@@ -416,7 +1904,7 @@ mod tests_block {
     fn create_empty_transaction() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions: Vec<Transaction<Stab, Verified>> = vec![];
 
@@ -429,7 +1917,7 @@ mod tests_block {
     fn verify_block_fail_empty_transaction() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions = {
             let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
@@ -452,14 +1940,14 @@ mod tests_block {
     fn verify_block() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions = stab_transactions();
 
         let mut block = Block::create(
             height,
             timestamp,
-            previous_digest.clone(),
+            previous_digest,
             difficulty,
             transactions.clone(),
         )
@@ -495,7 +1983,7 @@ mod tests_block {
     fn verify_block_fail_merkle_root() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions = stab_transactions();
 
@@ -524,14 +2012,14 @@ mod tests_block {
     fn verify_block_fail_difficulty() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions = stab_transactions();
 
         let mut block = Block::create(
             height,
             timestamp,
-            previous_digest.clone(),
+            previous_digest,
             difficulty,
             transactions.clone(),
         )
@@ -550,7 +2038,7 @@ mod tests_block {
         }
 
         // Cheat difficulty
-        block.header.difficulty = Difficulty::new(u64::MAX);
+        block.header.difficulty = MAX_DIFFICULTY;
 
         let result = block.verify_block(|_header| true);
         assert!(result.is_err());
@@ -560,14 +2048,14 @@ mod tests_block {
     fn verify_block_fail_digest() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions = stab_transactions();
 
         let mut block = Block::create(
             height,
             timestamp,
-            previous_digest.clone(),
+            previous_digest,
             difficulty,
             transactions.clone(),
         )
@@ -579,7 +2067,7 @@ mod tests_block {
             let h = block.header();
             if h.difficulty().verify_digest(h.digest()) {
                 // Cheat digest after nonce was found
-                block.header.digest = [255; 32];
+                block.header.digest = Digest::from_bytes([255; 32]);
 
                 break;
             } else {
@@ -596,14 +2084,14 @@ mod tests_block {
     fn verify_block_fail_previous_digest() {
         let height = 42;
         let timestamp = Timestamp::now();
-        let previous_digest = [0; 32];
+        let previous_digest = Digest::ZERO;
         let difficulty = Difficulty::new(1);
         let transactions = stab_transactions();
 
         let mut block = Block::create(
             height,
             timestamp,
-            previous_digest.clone(),
+            previous_digest,
             difficulty,
             transactions.clone(),
         )
@@ -625,6 +2113,403 @@ mod tests_block {
         let result = block.verify_block(|_| false);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn encoded_size() {
+        use crate::byteorder::ByteOrderBuilder;
+        use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+        use crate::state::DEFAULT_MAX_RECORD_LEN;
+
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let target = TransactionIdentifier::new(1, secret_account.sign(b"target"));
+        let contents = vec![
+            JellyfishTransactionContent::insert(""),
+            JellyfishTransactionContent::insert("a".repeat(DEFAULT_MAX_RECORD_LEN)),
+            JellyfishTransactionContent::modify("b", target.clone()).with_ttl(1_000),
+            JellyfishTransactionContent::remove(target.clone()),
+            JellyfishTransactionContent::append("c", target.clone()),
+            JellyfishTransactionContent::batch(vec![
+                JellyfishTransactionContent::insert("d"),
+                JellyfishTransactionContent::remove(target),
+            ])
+            .unwrap(),
+        ];
+        let transactions = contents
+            .into_iter()
+            .map(|content| Transaction::create(&secret_account, Timestamp::now(), content))
+            .collect::<Vec<_>>();
+        for tx in &transactions {
+            let encoding = ByteOrderBuilder::new()
+                .append(tx.account())
+                .append(&tx.timestamp())
+                .append(tx.content())
+                .append(tx.sign())
+                .finalize();
+            assert_eq!(tx.encoded_size(), encoding.len());
+        }
+
+        let mut block = Block::create(
+            2,
+            Timestamp::now(),
+            Digest::ZERO,
+            Difficulty::new(1),
+            transactions,
+        )
+        .unwrap();
+        for (state_root, network) in [
+            (None, None),
+            (Some(Digest::from_bytes([1; 32])), Some([2; 4])),
+        ] {
+            block.header_mut().modify_state_root(state_root);
+            block.header_mut().modify_network(network);
+            let header_len = block.header().build_byte_order().len();
+            assert_eq!(block.header().byte_len(), header_len);
+
+            let breakdown = block.size_breakdown();
+            assert_eq!(breakdown.header, header_len);
+            assert_eq!(breakdown.transactions.len(), 6);
+            assert_eq!(breakdown.transactions[0], 32 + 8 + 1 + 64);
+            assert_eq!(breakdown.total, block.encoded_size());
+            assert_eq!(
+                block.encoded_size(),
+                header_len
+                    + block
+                        .transactions()
+                        .iter()
+                        .map(Transaction::encoded_size)
+                        .sum::<usize>()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_merkle_proof {
+    use crate::SecretAccount;
+
+    use super::tests_stab::*;
+    use super::*;
+
+    fn create_block(tx_count: usize) -> Block<Stab, Verified, Yet> {
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let timestamp = Timestamp::now();
+        let transactions = (0..tx_count)
+            .map(|_| Transaction::create(&secret_account, Timestamp::now(), Stab("hello")))
+            .collect();
+
+        Block::create(1, timestamp, Digest::ZERO, Difficulty::new(1), transactions).unwrap()
+    }
+
+    #[test]
+    fn verify() {
+        for tx_count in 1..=7 {
+            let block = create_block(tx_count);
+            let merkle_root = block.header().merkle_root();
+
+            for (i, tx) in block.transactions().iter().enumerate() {
+                let proof = block.merkle_proof(i).unwrap();
+                assert!(proof.verify(merkle_root, tx));
+            }
+        }
+    }
+
+    #[test]
+    fn verify_fail_other_transaction() {
+        let block = create_block(5);
+        let merkle_root = block.header().merkle_root();
+
+        let proof = block.merkle_proof(1).unwrap();
+
+        assert!(!proof.verify(merkle_root, &block.transactions()[2]));
+    }
+
+    #[test]
+    fn verify_fail_other_block() {
+        let block = create_block(5);
+        let other = create_block(5);
+
+        let proof = block.merkle_proof(1).unwrap();
+
+        assert!(!proof.verify(other.header().merkle_root(), &block.transactions()[1]));
+    }
+
+    #[test]
+    fn merkle_proof_out_of_range() {
+        let block = create_block(3);
+        assert!(block.merkle_proof(3).is_none());
+    }
+
+    #[test]
+    fn serialize_deserialize() {
+        let block = create_block(4);
+        let proof = block.merkle_proof(2).unwrap();
+
+        let ser = serde_json::to_string(&proof).unwrap();
+        let de = serde_json::from_str::<MerkleProof>(&ser).unwrap();
+
+        assert_eq!(proof, de);
+    }
+}
+
+#[cfg(test)]
+mod tests_wire {
+    use crate::codec::DecodeError;
+    use crate::fixtures;
+    use crate::jellyfish_transaction::TransactionIdentifier;
+    use crate::transaction::{SignMode, PREHASH_THRESHOLD};
+
+    use super::*;
+
+    fn create_block(params: &ChainParams) -> Block<JellyfishTransactionContent, Verified, Yet> {
+        let account = fixtures::account(0);
+        let timestamp = Timestamp::now();
+        let first = Transaction::create_for(
+            params,
+            &account,
+            timestamp,
+            JellyfishTransactionContent::insert("hello").with_ttl(1_000),
+        );
+        let target = TransactionIdentifier::new(1, *first.sign());
+        let contents = [
+            JellyfishTransactionContent::modify("world", target.clone()),
+            JellyfishTransactionContent::batch(vec![
+                JellyfishTransactionContent::insert("a"),
+                JellyfishTransactionContent::remove(target),
+            ])
+            .unwrap(),
+            JellyfishTransactionContent::insert("x".repeat(PREHASH_THRESHOLD + 1)),
+        ];
+        let transactions = std::iter::once(first)
+            .chain(
+                contents
+                    .into_iter()
+                    .map(|content| Transaction::create_for(params, &account, timestamp, content)),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(transactions[3].sign_mode(), SignMode::Prehashed);
+
+        let mut block =
+            Block::create(2, timestamp, Digest::ZERO, MIN_DIFFICULTY, transactions).unwrap();
+        block
+            .header_mut()
+            .modify_state_root(Some(Digest::from_bytes([1; 32])));
+        block.header_mut().modify_network(params.network());
+        block
+            .header_mut()
+            .modify_target(Some(Target::from_compact(0x2000ffff).unwrap()));
+        block.header_mut().modify_hash_mode(HashMode::Double);
+        block.mine()
+    }
+
+    #[test]
+    fn round_trip() {
+        let params = ChainParams::regtest();
+        let block = create_block(&params);
+        // SHA256 headers omit only the hash algorithm.
+        assert_eq!(block.header().byte_len() + 2, MAX_HEADER_BYTES);
+        let bytes = block.to_wire_bytes();
+
+        let de = Block::from_wire_bytes(&bytes, &params).unwrap();
+        assert_eq!(de.to_wire_bytes(), bytes);
+        assert_eq!(de.header(), block.header());
+
+        let de = de
+            .verify_transactions_with_params(&params)
+            .unwrap()
+            .verify_block(|_| true)
+            .unwrap();
+        assert_eq!(de, block.verify_block(|_| true).unwrap());
+    }
+
+    #[test]
+    fn from_wire_bytes_fail_too_many_transactions() {
+        let params = ChainParams::regtest();
+        let block = create_block(&params);
+        let mut bytes = vec![];
+        block.header().append_wire_bytes(&mut bytes);
+        bytes.extend(u32::MAX.to_be_bytes());
+
+        assert_eq!(
+            Block::from_wire_bytes(&bytes, &params),
+            Err(DecodeError::LimitExceeded {
+                field: "transactions",
+                limit: params.max_block_transactions,
+                requested: u32::MAX as usize,
+            })
+        );
+    }
+
+    #[test]
+    fn from_wire_bytes_fail_truncated() {
+        let params = ChainParams::regtest();
+        let bytes = create_block(&params).to_wire_bytes();
+
+        for len in [0, 1, 5, 100, bytes.len() / 2, bytes.len() - 1] {
+            assert!(matches!(
+                Block::from_wire_bytes(&bytes[..len], &params),
+                Err(DecodeError::UnexpectedEnd { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn from_wire_bytes_fail_trailing_bytes() {
+        let params = ChainParams::regtest();
+        let mut bytes = create_block(&params).to_wire_bytes();
+        bytes.push(0);
+
+        assert!(matches!(
+            Block::from_wire_bytes(&bytes, &params),
+            Err(DecodeError::Malformed { field: "block", .. })
+        ));
+    }
+
+    #[test]
+    fn from_wire_bytes_fail_negative_timestamp() {
+        let params = ChainParams::regtest();
+        let mut bytes = create_block(&params).to_wire_bytes();
+        // Timestamp follows the hash mode, the header length and the height.
+        bytes[13..21].copy_from_slice(&(-1i64).to_le_bytes());
+
+        assert!(matches!(
+            Block::from_wire_bytes(&bytes, &params),
+            Err(DecodeError::Malformed {
+                field: "timestamp",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn from_wire_bytes_fail_header_tag_out_of_order() {
+        let params = ChainParams::regtest();
+        let block = create_block(&params);
+        let mut header = block.header().clone();
+        header.modify_state_root(None);
+        header.modify_target(None);
+        let mut bytes = vec![];
+        header.append_wire_bytes(&mut bytes);
+        // Repeat the network after itself.
+        let len = header.byte_len() + 1 + 4;
+        bytes[1..5].copy_from_slice(&(len as u32).to_be_bytes());
+        bytes.push(NETWORK_TAG);
+        bytes.extend(params.network().unwrap());
+
+        assert!(matches!(
+            Block::from_wire_bytes(&bytes, &params),
+            Err(DecodeError::Malformed {
+                field: "optional field",
+                ..
+            })
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "blake3"))]
+mod tests_blake3 {
+    use crate::hashing::Blake3;
+    use crate::SecretAccount;
+
+    use super::tests_stab::*;
+    use super::*;
+
+    fn mine_block<H: HashAlgorithm>() -> Block<Stab, Verified, Yet, H> {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let tx = Transaction::create(&account, Timestamp::now(), Stab("hello"));
+        Block::create_with_algorithm(
+            1,
+            Timestamp::now(),
+            Digest::ZERO,
+            Difficulty::new(8),
+            vec![tx],
+        )
+        .unwrap()
+        .mine()
+    }
+
+    /// Returns a header having the same fields as the given one, but of another algorithm.
+    fn reinterpret<H1, H2>(header: &Header<H1>) -> Header<H2> {
+        Header {
+            height: header.height,
+            timestamp: header.timestamp,
+            previous_digest: header.previous_digest,
+            difficulty: header.difficulty,
+            merkle_root: header.merkle_root,
+            nonce: header.nonce,
+            state_root: header.state_root,
+            network: header.network,
+            hash_mode: header.hash_mode,
+            target: header.target,
+            digest: header.digest,
+            _algorithm: PhantomData,
+        }
+    }
+
+    #[test]
+    fn verify_block() {
+        let block = mine_block::<Blake3>();
+        let header = block.header().clone();
+        let leaf = Blake3::hash(block.transactions()[0].sign().as_ref());
+
+        // A single leaf is the merkle root.
+        assert_eq!(header.merkle_root(), &leaf);
+        assert_eq!(header.digest(), &Blake3::hash(&header.build_byte_order()));
+        assert!(block.verify_block(|_| true).is_ok());
+    }
+
+    #[test]
+    fn byte_order_contains_algorithm() {
+        let header = mine_block::<Blake3>().header().clone();
+        let bytes = header.build_byte_order();
+
+        assert!(bytes.ends_with(&[ALGORITHM_TAG, Blake3::ID]));
+        assert_eq!(header.byte_len(), bytes.len());
+        let sha256 = reinterpret::<_, Sha256>(&header);
+        assert_eq!(sha256.build_byte_order(), bytes[..bytes.len() - 2]);
+    }
+
+    #[test]
+    fn verify_proof_of_work_fail_other_algorithm() {
+        let blake3 = mine_block::<Blake3>().header().clone();
+        assert!(matches!(
+            reinterpret::<_, Sha256>(&blake3).verify_proof_of_work(),
+            Err(BlockError::Digest)
+        ));
+
+        let sha256 = mine_block::<Sha256>().header().clone();
+        assert!(matches!(
+            reinterpret::<_, Blake3>(&sha256).verify_proof_of_work(),
+            Err(BlockError::Digest)
+        ));
+    }
+
+    #[test]
+    fn read_wire() {
+        let blake3 = mine_block::<Blake3>().header().clone();
+        let mut buf = vec![];
+        blake3.append_wire_bytes(&mut buf);
+
+        let de = Header::<Blake3>::read_wire(&mut BoundedReader::new(&buf, usize::MAX)).unwrap();
+        assert_eq!(de, blake3);
+        assert!(matches!(
+            Header::<Sha256>::read_wire(&mut BoundedReader::new(&buf, usize::MAX)),
+            Err(DecodeError::Malformed {
+                field: "algorithm",
+                ..
+            })
+        ));
+
+        let sha256 = mine_block::<Sha256>().header().clone();
+        let mut buf = vec![];
+        sha256.append_wire_bytes(&mut buf);
+        assert!(matches!(
+            Header::<Blake3>::read_wire(&mut BoundedReader::new(&buf, usize::MAX)),
+            Err(DecodeError::Malformed {
+                field: "algorithm",
+                ..
+            })
+        ));
+    }
 }
 
 #[cfg(test)]
@@ -641,9 +2526,9 @@ mod tests_function {
         let content = Stab("hello");
         let tx = Transaction::create(&secret_account, timestamp, content);
 
-        let expected_merkle_root = calculate_digest(tx.sign());
+        let expected_merkle_root = Digest::create(tx.sign()).to_bytes();
 
-        let tree = super::build_merkle_tree(&vec![tx]);
+        let tree = super::build_merkle_tree::<Sha256, _, _>(&vec![tx]);
         let merkle_root = tree.root().unwrap();
 
         assert_eq!(merkle_root, expected_merkle_root);
@@ -651,7 +2536,7 @@ mod tests_function {
 
     #[test]
     fn build_merkle_tree_empty() {
-        let tree = super::build_merkle_tree::<Stab, Verified>(&[]);
+        let tree = super::build_merkle_tree::<Sha256, Stab, Verified>(&[]);
         assert!(tree.root().is_none());
     }
 }
@@ -0,0 +1,427 @@
+//! Self-contained proofs that a transaction was committed on chain.
+//!
+//! An [`InclusionBundle`] carries headers from a trusted anchor up to the block of a transaction,
+//! so that a third party can verify the transaction offline, without a chain or a light client.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Header, MerkleProof};
+use crate::chain::Chain;
+use crate::checkpoint::{CheckpointError, SignedCheckpoint};
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::params::ChainParams;
+use crate::transaction::{TransactionError, VerifiedTransaction};
+use crate::{Account, Digest, Transaction, Yet};
+
+/// What the verifier of an [`InclusionBundle`] trusts in advance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustAnchor {
+    /// Digest of the header at the height, obtained from a trusted source.
+    Digest { height: u64, digest: Digest },
+    /// Accounts, at least `threshold` of which must sign the checkpoint in the bundle.
+    Signers {
+        accounts: Vec<Account>,
+        threshold: usize,
+    },
+}
+
+/// Proof that a transaction is contained in a block, which descends from a trusted anchor.
+///
+/// A bundle is serialized as a single JSON document, so that it can be handed to anyone as it is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionBundle {
+    /// Signed checkpoint of the first header, required by [`TrustAnchor::Signers`].
+    checkpoint: Option<SignedCheckpoint>,
+    /// Consecutive headers from the anchor to the block containing the transaction.
+    headers: Vec<Header>,
+    proof: MerkleProof,
+    transaction: Transaction<JellyfishTransactionContent, Yet>,
+}
+
+impl InclusionBundle {
+    /// Attach a checkpoint of the anchor header, so that the bundle can be verified by [`TrustAnchor::Signers`].
+    pub fn with_checkpoint(self, checkpoint: SignedCheckpoint) -> Self {
+        Self {
+            checkpoint: Some(checkpoint),
+            ..self
+        }
+    }
+
+    pub fn checkpoint(&self) -> Option<&SignedCheckpoint> {
+        self.checkpoint.as_ref()
+    }
+
+    pub fn headers(&self) -> &[Header] {
+        &self.headers
+    }
+
+    pub fn proof(&self) -> &MerkleProof {
+        &self.proof
+    }
+
+    pub fn transaction(&self) -> &Transaction<JellyfishTransactionContent, Yet> {
+        &self.transaction
+    }
+
+    /// Returns the height of the block containing the transaction.
+    pub fn height(&self) -> Option<u64> {
+        self.headers.last().map(Header::height)
+    }
+
+    /// Verify that the transaction is contained in a block descending from the anchor.
+    ///
+    /// The first header must be the anchor. Each header must satisfy its Proof-of-Work and follow the previous one,
+    /// then the merkle proof and the transaction signature are checked against the last header.
+    ///
+    /// The signature is verified in the format without [`ChainParams::transaction_context()`].
+    /// Use [`InclusionBundle::verify_with_params()`] for chains of protocol version 2 or later.
+    pub fn verify(
+        &self,
+        trusted: &TrustAnchor,
+    ) -> Result<VerifiedTransaction<JellyfishTransactionContent>, BundleError> {
+        self.verify_with(trusted, None)
+    }
+
+    /// Same as [`InclusionBundle::verify()`], but the signature must be made under [`ChainParams::transaction_context()`].
+    pub fn verify_with_params(
+        &self,
+        trusted: &TrustAnchor,
+        params: &ChainParams,
+    ) -> Result<VerifiedTransaction<JellyfishTransactionContent>, BundleError> {
+        self.verify_with(trusted, params.transaction_context())
+    }
+
+    fn verify_with(
+        &self,
+        trusted: &TrustAnchor,
+        context: Option<&[u8]>,
+    ) -> Result<VerifiedTransaction<JellyfishTransactionContent>, BundleError> {
+        let anchor = self.headers.first().ok_or(BundleError::Empty)?;
+        self.verify_anchor(anchor, trusted)?;
+
+        for header in &self.headers {
+            header
+                .verify_proof_of_work()
+                .map_err(|_| BundleError::ProofOfWork {
+                    height: header.height(),
+                })?;
+        }
+        for (previous, header) in self.headers.iter().tuple_windows() {
+            if !header.follows(previous) {
+                return Err(BundleError::Linkage {
+                    height: header.height(),
+                });
+            }
+        }
+
+        // Headers are not empty, so the below unwrap() always succeeds.
+        let header = self.headers.last().unwrap();
+        if !self.proof.verify(header.merkle_root(), &self.transaction) {
+            return Err(BundleError::Proof);
+        }
+
+        self.transaction
+            .clone()
+            .verify_with(header.network(), context, None)
+            .map_err(BundleError::Transaction)
+    }
+
+    fn verify_anchor(&self, anchor: &Header, trusted: &TrustAnchor) -> Result<(), BundleError> {
+        let (height, digest) = match trusted {
+            TrustAnchor::Digest { height, digest } => (*height, digest),
+            TrustAnchor::Signers {
+                accounts,
+                threshold,
+            } => {
+                let checkpoint = self
+                    .checkpoint
+                    .as_ref()
+                    .ok_or(BundleError::MissingCheckpoint)?;
+                checkpoint
+                    .verify(accounts, *threshold)
+                    .map_err(BundleError::Checkpoint)?;
+                (checkpoint.height(), checkpoint.digest())
+            }
+        };
+
+        if anchor.height() == height && anchor.digest().ct_eq(digest) {
+            Ok(())
+        } else {
+            Err(BundleError::Anchor { height })
+        }
+    }
+}
+
+impl Chain<JellyfishTransactionContent> {
+    /// Build a bundle proving the transaction at `tx_index` in the block at `height`,
+    /// which is verified against the header at `anchor_height`.
+    ///
+    /// # Returns
+    /// `None` if `anchor_height` is above `height`, either height is out of the chain, or the index is out of range.
+    pub fn make_bundle(
+        &self,
+        height: u64,
+        tx_index: usize,
+        anchor_height: u64,
+    ) -> Option<InclusionBundle> {
+        if anchor_height > height {
+            return None;
+        }
+
+        let block = self.block(height)?;
+        let tx = block.transactions().get(tx_index)?;
+        let proof = block.merkle_proof(tx_index)?;
+        let headers = (anchor_height..=height)
+            .map(|height| self.block(height).map(|block| block.header().clone()))
+            .collect::<Option<Vec<_>>>()?;
+        let transaction = tx.clone().into_unverified();
+
+        Some(InclusionBundle {
+            checkpoint: None,
+            headers,
+            proof,
+            transaction,
+        })
+    }
+}
+
+/// An error occurred during verifying an [`InclusionBundle`].
+#[derive(Debug)]
+pub enum BundleError {
+    /// Bundle contains no header.
+    Empty,
+    /// Trust anchor requires a signed checkpoint, but the bundle has none.
+    MissingCheckpoint,
+    /// Checkpoint in the bundle is not signed by enough trusted accounts.
+    Checkpoint(CheckpointError),
+    /// First header is not the trusted one at the height.
+    Anchor { height: u64 },
+    /// Header at the height does not satisfy its Proof-of-Work.
+    ProofOfWork { height: u64 },
+    /// Header at the height does not follow the previous header.
+    Linkage { height: u64 },
+    /// Merkle proof does not match the transaction and the last header.
+    Proof,
+    /// Transaction signature verification failed.
+    Transaction(TransactionError),
+}
+
+impl Display for BundleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use BundleError::*;
+
+        match self {
+            Empty => write!(f, "Bundle contains no header."),
+            MissingCheckpoint => write!(f, "Bundle contains no checkpoint."),
+            Checkpoint(e) => write!(f, "{}", e),
+            Anchor { height } => write!(
+                f,
+                "Bundle does not start from the trusted header at {}.",
+                height
+            ),
+            ProofOfWork { height } => write!(f, "Header at {} has invalid Proof-of-Work.", height),
+            Linkage { height } => write!(
+                f,
+                "Header at {} does not follow the previous header.",
+                height
+            ),
+            Proof => write!(f, "Merkle proof is invalid."),
+            Transaction(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for BundleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BundleError::Checkpoint(e) => Some(e),
+            BundleError::Transaction(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::FixtureChain;
+    use crate::SecretAccount;
+
+    fn build() -> FixtureChain {
+        FixtureChain::new(ChainParams::regtest())
+            .with_block(|b| b.insert("a"))
+            .with_block(|b| b.insert("b").insert("c").insert("d"))
+            .with_block(|b| b.insert("e"))
+            .with_block(|b| b.insert("f"))
+    }
+
+    fn anchor(fixture: &FixtureChain, height: u64) -> TrustAnchor {
+        let header = fixture.chain().block(height).unwrap().header();
+        TrustAnchor::Digest {
+            height,
+            digest: *header.digest(),
+        }
+    }
+
+    /// Round trip the bundle through JSON, after editing the document by the closure.
+    fn edit_json<F>(bundle: &InclusionBundle, edit: F) -> InclusionBundle
+    where
+        F: FnOnce(&mut serde_json::Value),
+    {
+        let mut json = serde_json::to_value(bundle).unwrap();
+        edit(&mut json);
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn verify() {
+        let fixture = build();
+        let bundle = fixture.chain().make_bundle(1, 2, 0).unwrap();
+
+        assert_eq!(bundle.height(), Some(1));
+        assert_eq!(bundle.headers().len(), 2);
+        let tx = bundle.verify(&anchor(&fixture, 0)).unwrap();
+        assert_eq!(tx.content().record(), Some("d"));
+
+        // Bundle is a single JSON document.
+        let json = serde_json::to_string(&bundle).unwrap();
+        let de = serde_json::from_str::<InclusionBundle>(&json).unwrap();
+        assert_eq!(de, bundle);
+        assert!(de.verify(&anchor(&fixture, 0)).is_ok());
+
+        // Anchor may be the block itself.
+        let bundle = fixture.chain().make_bundle(3, 0, 3).unwrap();
+        assert!(bundle.verify(&anchor(&fixture, 3)).is_ok());
+    }
+
+    #[test]
+    fn verify_with_params() {
+        let params = ChainParams {
+            protocol_version: 2,
+            ..ChainParams::regtest()
+        };
+        let fixture = FixtureChain::new(params.clone())
+            .with_block(|b| b.insert("a"))
+            .with_block(|b| b.insert("b"));
+        let bundle = fixture.chain().make_bundle(1, 0, 0).unwrap();
+
+        let tx = bundle
+            .verify_with_params(&anchor(&fixture, 0), &params)
+            .unwrap();
+        assert_eq!(tx.content().record(), Some("b"));
+
+        // Signature under the transaction context is not valid in the legacy format.
+        assert!(matches!(
+            bundle.verify(&anchor(&fixture, 0)),
+            Err(BundleError::Transaction(_))
+        ));
+        assert!(matches!(
+            bundle.verify_with_params(&anchor(&fixture, 0), &ChainParams::regtest()),
+            Err(BundleError::Transaction(_))
+        ));
+    }
+
+    #[test]
+    fn make_bundle_fail() {
+        let fixture = build();
+        let chain = fixture.chain();
+
+        assert!(chain.make_bundle(2, 0, 3).is_none());
+        assert!(chain.make_bundle(2, 3, 0).is_none());
+        assert!(chain.make_bundle(5, 0, 0).is_none());
+    }
+
+    #[test]
+    fn verify_checkpoint() {
+        let fixture = build();
+        let signer = SecretAccount::create(&mut rand_core::OsRng {});
+        let header = fixture.chain().block(0).unwrap().header();
+        let checkpoint = SignedCheckpoint::sign(&signer, 0, *header.digest());
+        let trusted = TrustAnchor::Signers {
+            accounts: vec![signer.to_public()],
+            threshold: 1,
+        };
+
+        let bundle = fixture.chain().make_bundle(3, 0, 0).unwrap();
+        assert!(matches!(
+            bundle.verify(&trusted),
+            Err(BundleError::MissingCheckpoint)
+        ));
+
+        let bundle = bundle.with_checkpoint(checkpoint);
+        assert!(bundle.verify(&trusted).is_ok());
+
+        let untrusted = TrustAnchor::Signers {
+            accounts: vec![SecretAccount::create(&mut rand_core::OsRng {}).to_public()],
+            threshold: 1,
+        };
+        assert!(matches!(
+            bundle.verify(&untrusted),
+            Err(BundleError::Checkpoint(_))
+        ));
+    }
+
+    #[test]
+    fn verify_fail_anchor() {
+        let fixture = build();
+        let bundle = fixture.chain().make_bundle(2, 0, 1).unwrap();
+
+        assert!(matches!(
+            bundle.verify(&anchor(&fixture, 0)),
+            Err(BundleError::Anchor { height: 0 })
+        ));
+    }
+
+    #[test]
+    fn verify_fail_linkage() {
+        let fixture = build();
+        let bundle = fixture.chain().make_bundle(3, 0, 0).unwrap();
+
+        // Skip a header.
+        let bundle = edit_json(&bundle, |json| {
+            json["headers"].as_array_mut().unwrap().remove(2);
+        });
+        assert!(matches!(
+            bundle.verify(&anchor(&fixture, 0)),
+            Err(BundleError::Linkage { height: 3 })
+        ));
+    }
+
+    #[test]
+    fn verify_fail_proof() {
+        let fixture = build();
+        let bundle = fixture.chain().make_bundle(1, 0, 0).unwrap();
+        let other = fixture.chain().make_bundle(1, 1, 0).unwrap();
+
+        // Proof of another transaction in the same block.
+        let forged = edit_json(&bundle, |json| {
+            json["proof"] = serde_json::to_value(other.proof()).unwrap()
+        });
+        assert!(matches!(
+            forged.verify(&anchor(&fixture, 0)),
+            Err(BundleError::Proof)
+        ));
+    }
+
+    #[test]
+    fn verify_fail_transaction() {
+        let fixture = build();
+        let bundle = fixture.chain().make_bundle(1, 0, 0).unwrap();
+
+        // Merkle leaf is the digest of the sign, so only the signature detects altered content.
+        let other = fixture.chain().block(1).unwrap().transactions()[1]
+            .content()
+            .clone();
+        let tampered = edit_json(&bundle, |json| {
+            json["transaction"]["content"] = serde_json::to_value(other).unwrap()
+        });
+        assert!(matches!(
+            tampered.verify(&anchor(&fixture, 0)),
+            Err(BundleError::Transaction(_))
+        ));
+    }
+}
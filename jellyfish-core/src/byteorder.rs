@@ -1,3 +1,8 @@
+use ed25519_dalek::Digest as PrehashDigest;
+
+use crate::account::Prehash;
+use crate::digest::Hasher;
+
 /// Represents its implementator as a byte sequence based on jellyfish protocol.
 ///
 /// This is used to build source of signature and digest.
@@ -8,11 +13,32 @@ pub trait ByteOrder: Sized {
     fn build_byte_order(&self) -> Vec<u8> {
         ByteOrderBuilder::new().append(self).finalize()
     }
+
+    /// Writes the byte order into the hasher, which gives the same digest as the built byte order.
+    ///
+    /// The default implementation builds the byte order, so implementors on hot paths should override this without allocation.
+    fn append_to_hasher(&self, hasher: &mut Hasher) {
+        hasher.update(&self.build_byte_order());
+    }
+
+    /// Writes the byte order into the SHA-512 prehash of prehashed signatures, which gives the same prehash as the built byte order.
+    ///
+    /// The default implementation builds the byte order, so implementors which may be large should override this without building it.
+    fn append_to_prehash(&self, prehash: &mut Prehash) {
+        PrehashDigest::update(prehash, self.build_byte_order());
+    }
+
+    /// Returns length of the byte order.
+    ///
+    /// The default implementation builds the byte order, so implementors should override this without allocation.
+    fn byte_len(&self) -> usize {
+        self.build_byte_order().len()
+    }
 }
 
 /// Builds a byte sequence for signature and digest.
 /// # Examples
-/// ```ignore
+/// ```
 /// use jellyfish_core::byteorder::{ByteOrder, ByteOrderBuilder};
 ///
 /// struct Stab(u8);
@@ -34,8 +60,8 @@ pub trait ByteOrder: Sized {
 ///     .finalize();
 /// assert_eq!(bytes, vec![0x01, 0x02]);
 /// ```
-#[derive(Debug)]
-pub(crate) struct ByteOrderBuilder {
+#[derive(Debug, Default)]
+pub struct ByteOrderBuilder {
     buf: Vec<u8>,
 }
 
@@ -0,0 +1,1782 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use itertools::Itertools;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::block::{Block, BlockError, Header};
+use crate::difficulty::MIN_DIFFICULTY;
+use crate::digest::Digest;
+use crate::filter::{AccountFilter, RecordFilter, StandardTokenizer};
+use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+use crate::params::ChainParams;
+use crate::state::{
+    touched_records, LedgerState, LedgerStateView, Receipt, RecordEvent, StateDiff, StateError,
+    StateSnapshot,
+};
+use crate::subscription::{
+    BlockBroadcast, BlockReceiver, ChainEvent, DEFAULT_SUBSCRIPTION_CAPACITY,
+};
+use crate::window::HeaderWindow;
+use crate::{Account, ByteOrder, Difficulty, Signature, Transaction, Verified, Yet};
+
+/// Default number of blocks between state snapshots taken by [`Chain::append_with_state()`].
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 1000;
+
+/// Default number of blocks below the tip, within which fork blocks are kept, if the chain has no parameters.
+pub const DEFAULT_FORK_DEPTH: u64 = 100;
+
+/// Result of a successful [`Chain::append()`] or [`Chain::append_with_state()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppendOutcome {
+    /// The block was verified and became the new tip.
+    Appended,
+    /// A block with the same digest is already in the chain or among fork blocks, so nothing was changed.
+    /// Such a block should not be relayed again.
+    AlreadyKnown,
+    /// The block was verified and follows a block below the tip, so it was kept as a fork block
+    /// without changing the tip.
+    StoredAsFork,
+}
+
+/// Blockchain, which consists of verified and linked blocks.
+#[derive(Debug, Clone)]
+pub struct Chain<T> {
+    blocks: Vec<Block<T, Verified, Verified>>,
+    /// Account filter of each block, in the same order as `blocks`.
+    account_filters: Vec<AccountFilter>,
+    /// Index of transactions in each block by their signs, in the same order as `blocks`.
+    sign_indexes: Vec<HashMap<Signature, usize>>,
+    /// Receipts of blocks applied to ledger state, keyed by block digest.
+    receipts: HashMap<Digest, Vec<Receipt>>,
+    /// Snapshots of ledger state after applying the block at each height.
+    snapshots: BTreeMap<u64, StateSnapshot>,
+    snapshot_interval: u64,
+    /// Changes of each record in blocks appended with ledger state.
+    record_index: HashMap<TransactionIdentifier, Vec<RecordEvent>>,
+    /// Parameters which appended blocks must satisfy. `None` if only the block format is checked.
+    params: Option<ChainParams>,
+    /// Receivers of connected and disconnected blocks.
+    broadcast: BlockBroadcast<T>,
+    /// Verified blocks which do not extend the tip, keyed by their digests.
+    forks: HashMap<Digest, Block<T, Verified, Verified>>,
+}
+
+/// Where an incoming block would be placed, decided only by its header.
+enum Placement {
+    Known,
+    Fork,
+    Tip,
+}
+
+impl<T> Chain<T> {
+    /// Create a chain which begins with the given block.
+    pub fn new(genesis: Block<T, Verified, Verified>) -> Self {
+        let account_filters = vec![genesis.account_filter()];
+        let sign_indexes = vec![sign_index(&genesis)];
+        Self {
+            blocks: vec![genesis],
+            account_filters,
+            sign_indexes,
+            receipts: HashMap::new(),
+            snapshots: BTreeMap::new(),
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            record_index: HashMap::new(),
+            params: None,
+            broadcast: BlockBroadcast::new(),
+            forks: HashMap::new(),
+        }
+    }
+
+    /// Check blocks appended after this by [`ChainParams::check_block()`].
+    pub fn with_params(self, params: ChainParams) -> Self {
+        Self {
+            params: Some(params),
+            ..self
+        }
+    }
+
+    pub fn params(&self) -> Option<&ChainParams> {
+        self.params.as_ref()
+    }
+
+    /// Take a state snapshot every `snapshot_interval` blocks. `0` disables snapshots.
+    pub fn with_snapshot_interval(self, snapshot_interval: u64) -> Self {
+        Self {
+            snapshot_interval,
+            ..self
+        }
+    }
+
+    /// Returns all blocks in ascending order of height.
+    pub fn blocks(&self) -> &[Block<T, Verified, Verified>] {
+        &self.blocks
+    }
+
+    /// Returns the block at the given height.
+    pub fn block(&self, height: u64) -> Option<&Block<T, Verified, Verified>> {
+        let index = height.checked_sub(self.first_height())?;
+        self.blocks.get(usize::try_from(index).ok()?)
+    }
+
+    /// Returns the latest block.
+    pub fn tip(&self) -> &Block<T, Verified, Verified> {
+        // A chain has at least genesis block, so the below unwrap() always succeeds.
+        self.blocks.last().unwrap()
+    }
+
+    /// Returns heights of blocks which may contain transactions offered by the given account.
+    ///
+    /// This is decided only by account filters of blocks, so some of returned blocks may not contain the account's transaction.
+    /// On the other hand, all blocks containing the account's transaction are returned.
+    pub fn blocks_possibly_touching(&self, account: &Account) -> Vec<u64> {
+        self.blocks
+            .iter()
+            .zip(&self.account_filters)
+            .filter(|(_, filter)| filter.maybe_contains(account))
+            .map(|(block, _)| block.header().height())
+            .collect()
+    }
+
+    /// Returns receipts of the block at the given height.
+    /// `None` if the block was not applied to ledger state by [`Chain::append_with_state()`].
+    pub fn receipts(&self, height: u64) -> Option<&[Receipt]> {
+        let block = self.block(height)?;
+        self.receipts
+            .get(block.header().digest())
+            .map(Vec::as_slice)
+    }
+
+    /// Returns receipt of the transaction at the given index in the block at the given height.
+    pub fn receipt(&self, height: u64, tx_index: usize) -> Option<&Receipt> {
+        self.receipts(height)?.get(tx_index)
+    }
+
+    /// Returns at most `len` contiguous headers ending at `end_height`, in ascending order of height.
+    ///
+    /// A window extending below the first block of the chain is truncated there rather than rejected,
+    /// so that computations near genesis see every available header.
+    /// The window contains at least the header at `end_height`, even if `len` is zero.
+    ///
+    /// # Returns
+    /// `Err(ChainError::HeightOutOfRange)` if `end_height` is out of the chain.
+    pub fn header_window(
+        &self,
+        end_height: u64,
+        len: usize,
+    ) -> Result<HeaderWindow<'_>, ChainError> {
+        let end = end_height
+            .checked_sub(self.first_height())
+            .and_then(|index| usize::try_from(index).ok())
+            .filter(|&index| index < self.blocks.len())
+            .ok_or(ChainError::HeightOutOfRange { height: end_height })?;
+        let start = (end + 1).saturating_sub(len.max(1));
+
+        let headers = self.blocks[start..=end].iter().map(Block::header).collect();
+        Ok(HeaderWindow::new(headers))
+    }
+
+    /// Returns difficulty which the block at the height must declare, according to the chain history up to the previous block.
+    ///
+    /// Blocks below the first retarget boundary, i.e., [`ChainParams::retarget_window`], use the minimum difficulty of the parameters,
+    /// and every boundary is retargeted by [`ChainParams::retarget()`] over the window ending with the previous block.
+    /// Other blocks use the same difficulty as the previous block.
+    /// Without parameters, difficulty is not enforced, and this returns that of the previous block.
+    ///
+    /// For a height not following a block in the chain, e.g., the first block, this returns the initial difficulty.
+    pub fn expected_difficulty(&self, height: u64) -> Difficulty {
+        match height.checked_sub(1).and_then(|height| self.block(height)) {
+            Some(previous) => self.difficulty_after(previous.header()),
+            None => self
+                .params
+                .as_ref()
+                .map_or(MIN_DIFFICULTY, |params| params.min_difficulty),
+        }
+    }
+
+    /// Returns the fork block with the given digest, which was stored by [`Chain::append()`] as [`AppendOutcome::StoredAsFork`].
+    pub fn fork_block(&self, digest: &Digest) -> Option<&Block<T, Verified, Verified>> {
+        self.forks.get(digest)
+    }
+
+    /// Subscribe to blocks connected to and disconnected from the tip after this call.
+    ///
+    /// Each receiver buffers at most [`DEFAULT_SUBSCRIPTION_CAPACITY`] events.
+    /// A clone of the chain does not notify receivers subscribed to the original.
+    pub fn subscribe_blocks(&self) -> BlockReceiver<T> {
+        self.subscribe_blocks_with_capacity(DEFAULT_SUBSCRIPTION_CAPACITY)
+    }
+
+    /// Same as [`Chain::subscribe_blocks()`], but the receiver buffers at most `capacity` events.
+    pub fn subscribe_blocks_with_capacity(&self, capacity: usize) -> BlockReceiver<T> {
+        self.broadcast.subscribe(capacity)
+    }
+
+    /// Remove the tip block, e.g., to switch to another fork, then notify subscribers.
+    ///
+    /// Receipts, snapshots and indexed record changes of the block are removed too,
+    /// but ledger state is left to the caller, which can undo the block by [`LedgerState::disconnect_block()`].
+    ///
+    /// # Returns
+    /// `None` if the tip is the first block of the chain, otherwise, `Some(block)` removed.
+    pub fn disconnect_tip(&mut self) -> Option<Block<T, Verified, Verified>> {
+        if self.blocks.len() <= 1 {
+            return None;
+        }
+
+        let block = self.blocks.pop()?;
+        self.account_filters.pop();
+        self.sign_indexes.pop();
+        let height = block.header().height();
+        self.receipts.remove(block.header().digest());
+        self.snapshots.remove(&height);
+        self.record_index.retain(|_, history| {
+            history.retain(|event| event.height() < height);
+            !history.is_empty()
+        });
+
+        self.broadcast
+            .send(ChainEvent::Disconnected(block.header().clone()));
+        Some(block)
+    }
+
+    /// Returns the block containing the transaction identified by `id` and its index, looking up the sign index of the block.
+    fn locate(
+        &self,
+        id: &TransactionIdentifier,
+    ) -> Result<(&Block<T, Verified, Verified>, usize), TargetError> {
+        let height = id.height;
+        let block = self
+            .block(height)
+            .ok_or(TargetError::HeightOutOfRange { height })?;
+        // The block is in the chain, so the below index is in range.
+        let sign_index = &self.sign_indexes[(height - self.first_height()) as usize];
+        let &tx_index = sign_index
+            .get(&id.sign)
+            .ok_or(TargetError::SignNotInBlock { height })?;
+        Ok((block, tx_index))
+    }
+
+    fn first_height(&self) -> u64 {
+        self.blocks[0].header().height()
+    }
+
+    /// Decide where the block with the given header would be placed.
+    ///
+    /// Blocks are identified by their digests, so the digest is re-calculated rather than trusted.
+    /// A header which is neither known nor a fork is left to the linkage check against the tip.
+    fn placement(&self, header: &Header) -> Placement {
+        let digest = header.digest();
+        if digest == &header.calculate_digest() {
+            let in_chain = self
+                .block(header.height())
+                .is_some_and(|block| block.header().digest() == digest);
+            if in_chain || self.forks.contains_key(digest) {
+                return Placement::Known;
+            }
+        }
+
+        let tip = self.tip().header();
+        if header.follows(tip) {
+            return Placement::Tip;
+        }
+        let parent = header
+            .height()
+            .checked_sub(1)
+            .and_then(|height| self.block(height))
+            .map(Block::header)
+            .filter(|parent| header.follows(parent))
+            .or_else(|| {
+                self.forks
+                    .get(header.previous_digest())
+                    .map(Block::header)
+                    .filter(|parent| header.follows(parent))
+            });
+        match parent {
+            Some(_) => Placement::Fork,
+            None => Placement::Tip,
+        }
+    }
+
+    /// Returns the block header which the given header follows, either in the chain or among fork blocks.
+    fn parent(&self, header: &Header) -> Option<&Header> {
+        let height = header.height().checked_sub(1)?;
+        self.forks
+            .get(header.previous_digest())
+            .map(Block::header)
+            .or_else(|| self.block(height).map(Block::header))
+            .filter(|parent| header.follows(parent))
+    }
+
+    /// Returns difficulty of the block following `previous`, which may be a fork block.
+    fn difficulty_after(&self, previous: &Header) -> Difficulty {
+        match &self.params {
+            // The window is truncated at the first block of the chain.
+            Some(params) => params.difficulty_after(previous, |header| self.parent(header)),
+            None => previous.difficulty(),
+        }
+    }
+
+    /// Check that the header declares the difficulty demanded by the history it follows.
+    /// Difficulty is not enforced without parameters.
+    fn check_difficulty(&self, header: &Header) -> Result<(), BlockError> {
+        let previous = match (&self.params, self.parent(header)) {
+            (Some(_), Some(previous)) => previous,
+            // Linkage is checked elsewhere.
+            _ => return Ok(()),
+        };
+        let expected = self.difficulty_after(previous);
+        let declared = header.difficulty();
+        if declared == expected {
+            Ok(())
+        } else {
+            Err(BlockError::Chain(ChainError::WrongDifficulty {
+                expected,
+                declared,
+            }))
+        }
+    }
+
+    /// Drop fork blocks deeper than the maximum reorganization depth below the tip.
+    fn prune_forks(&mut self) {
+        let depth = self
+            .params
+            .as_ref()
+            .map_or(DEFAULT_FORK_DEPTH, |params| params.max_reorg_depth);
+        let tip_height = self.tip().header().height();
+        self.forks
+            .retain(|_, block| block.header().height().saturating_add(depth) > tip_height);
+    }
+}
+
+impl<T: ByteOrder + Clone> Chain<T> {
+    /// Verify the block, then append it to the tip of the chain.
+    ///
+    /// A block whose digest equals a block in the chain or a fork block is not verified again, and is reported as [`AppendOutcome::AlreadyKnown`].
+    /// A block following a block below the tip is verified and kept as [`AppendOutcome::StoredAsFork`];
+    /// it can be obtained by [`Chain::fork_block()`] until it gets deeper than the maximum reorganization depth.
+    pub fn append(&mut self, block: Block<T, Yet, Yet>) -> Result<AppendOutcome, BlockError> {
+        match self.placement(block.header()) {
+            Placement::Known => return Ok(AppendOutcome::AlreadyKnown),
+            Placement::Fork => return self.store_fork(block),
+            Placement::Tip => {}
+        }
+
+        check_linkage(self.tip().header(), block.header())?;
+        check_params(self.params.as_ref(), &block)?;
+        self.check_difficulty(block.header())?;
+        let block = verify_contents(block, self.params.as_ref())?;
+
+        self.connect(block);
+        Ok(AppendOutcome::Appended)
+    }
+
+    /// Verify the block which follows a block below the tip, then keep it as a fork block.
+    fn store_fork(&mut self, block: Block<T, Yet, Yet>) -> Result<AppendOutcome, BlockError> {
+        check_params(self.params.as_ref(), &block)?;
+        self.check_difficulty(block.header())?;
+        let block = verify_contents(block, self.params.as_ref())?;
+
+        self.forks.insert(*block.header().digest(), block);
+        Ok(AppendOutcome::StoredAsFork)
+    }
+
+    /// Push the verified block to the tip, then notify subscribers.
+    fn connect(&mut self, block: Block<T, Verified, Verified>) {
+        self.account_filters.push(block.account_filter());
+        self.sign_indexes.push(sign_index(&block));
+        if self.broadcast.has_subscribers() {
+            self.broadcast
+                .send(ChainEvent::Connected(Arc::new(block.clone())));
+        }
+        self.blocks.push(block);
+        self.prune_forks();
+    }
+}
+
+impl Chain<JellyfishTransactionContent> {
+    /// Build record filters of blocks at the given heights by using [`StandardTokenizer`].
+    /// Heights out of the chain are skipped.
+    ///
+    /// This is used to respond to [`Message::GetFilters`](crate::protocol::Message::GetFilters).
+    pub fn record_filters(&self, heights: &[u64]) -> Vec<(u64, RecordFilter)> {
+        heights
+            .iter()
+            .filter_map(|&height| self.block(height).map(|block| (height, block)))
+            .map(|(height, block)| (height, RecordFilter::build(block, &StandardTokenizer)))
+            .collect()
+    }
+
+    /// Verify the block and apply it to the state, then append it to the tip of the chain.
+    ///
+    /// If the header commits to state root, it must match with the root of the state after applying the block.
+    /// On failure, neither the chain nor the state is changed, and observers of the state are not notified.
+    ///
+    /// Receipts of the block are kept, and can be obtained by [`Chain::receipt()`].
+    /// Changes of records are indexed for [`Chain::record_at()`];
+    /// on the first call, all records in the given state are indexed as well.
+    /// The state is snapshotted if the block's height is a multiple of the snapshot interval.
+    ///
+    /// Known blocks and fork blocks are handled as [`Chain::append()`], and the state is not changed by them.
+    pub fn append_with_state(
+        &mut self,
+        block: Block<JellyfishTransactionContent, Yet, Yet>,
+        state: &mut LedgerState,
+    ) -> Result<AppendOutcome, BlockError> {
+        match self.placement(block.header()) {
+            Placement::Known => return Ok(AppendOutcome::AlreadyKnown),
+            Placement::Fork => return self.store_fork(block),
+            Placement::Tip => {}
+        }
+
+        check_linkage(self.tip().header(), block.header())?;
+        check_params(self.params.as_ref(), &block)?;
+        self.check_difficulty(block.header())?;
+        let block = verify_contents(block, self.params.as_ref())?;
+        self.check_targets(&block).map_err(BlockError::Target)?;
+
+        let mut next = state.clone();
+        let (receipts, events) = next
+            .apply_block_unnotified(&block)
+            .map_err(BlockError::State)?;
+        block.verify_state_root(&next.state_root())?;
+
+        if self.receipts.is_empty() {
+            for (id, history) in state.histories() {
+                self.record_index.insert(id.clone(), history.to_vec());
+            }
+        }
+        *state = next;
+        state.notify_observers(&events);
+
+        let height = block.header().height();
+        for id in touched_records(&block).unique() {
+            let changes = state
+                .history(&id)
+                .iter()
+                .filter(|event| event.height() == height)
+                .cloned();
+            self.record_index.entry(id).or_default().extend(changes);
+        }
+        if self.snapshot_interval > 0 && height % self.snapshot_interval == 0 {
+            self.snapshots.insert(height, state.snapshot());
+        }
+        self.receipts.insert(*block.header().digest(), receipts);
+        self.connect(block);
+        Ok(AppendOutcome::Appended)
+    }
+
+    /// Find the target transaction of `Modify`, `Remove` or `Append`, and decide whether its record is live at the tip.
+    ///
+    /// Liveness is decided by changes indexed by [`Chain::append_with_state()`],
+    /// so it is [`Liveness::Unindexed`] for records in blocks appended without state.
+    ///
+    /// # Returns
+    /// `Err(TargetError::AlreadyRemoved)` if the record has been removed.
+    pub fn resolve_target(
+        &self,
+        id: &TransactionIdentifier,
+    ) -> Result<ResolvedTarget<'_>, TargetError> {
+        let (block, tx_index) = self.locate(id)?;
+
+        let liveness = match self.record_index.get(id).and_then(|history| history.last()) {
+            None => Liveness::Unindexed,
+            Some(latest) if latest.record().is_none() => {
+                return Err(TargetError::AlreadyRemoved {
+                    removed_at_height: latest.height(),
+                })
+            }
+            Some(latest) if latest.is_expired_at(self.tip().header().timestamp()) => {
+                Liveness::Expired
+            }
+            Some(_) => Liveness::Live,
+        };
+
+        Ok(ResolvedTarget {
+            transaction: &block.transactions()[tx_index],
+            header: block.header(),
+            liveness,
+        })
+    }
+
+    /// Check that every target of transactions in the block is resolved by [`Chain::resolve_target()`],
+    /// or is an earlier transaction in the block.
+    ///
+    /// Targets before the first block of the chain cannot be resolved, so they are left to the ledger state.
+    fn check_targets(
+        &self,
+        block: &Block<JellyfishTransactionContent, Verified, Verified>,
+    ) -> Result<(), TargetError> {
+        let height = block.header().height();
+        let in_block = sign_index(block);
+
+        for (tx_index, tx) in block.transactions().iter().enumerate() {
+            let targets = tx
+                .content()
+                .operations()
+                .iter()
+                .filter_map(JellyfishTransactionContent::target_transaction);
+            for target in targets {
+                if target.height == height {
+                    match in_block.get(&target.sign) {
+                        Some(&index) if index <= tx_index => continue,
+                        _ => return Err(TargetError::SignNotInBlock { height }),
+                    }
+                }
+                if target.height >= self.first_height() {
+                    self.resolve_target(target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns changes of records from the state at `from_height` to the state at `to_height`.
+    /// State at a height includes transactions in the block at the height.
+    ///
+    /// The state at `from_height` is built by replaying blocks from the nearest snapshot or the first block,
+    /// then only records touched by the later blocks in the range, or records which may expire, are compared.
+    /// States enforce only the default content rules of [`LedgerState::new()`].
+    pub fn state_diff(&self, from_height: u64, to_height: u64) -> Result<StateDiff, StateError> {
+        let is_in_chain = self.block(from_height).is_some() && self.block(to_height).is_some();
+        if from_height > to_height || !is_in_chain {
+            return Err(StateError::InvalidRange {
+                from_height,
+                to_height,
+            });
+        }
+
+        // Both heights are in the chain, so the below indices are in range.
+        let from_index = (from_height - self.first_height()) as usize;
+        let to_index = (to_height - self.first_height()) as usize;
+
+        let from_state = self.replay(from_height, true)?;
+
+        let mut to_state = from_state.clone();
+        let mut touched = vec![];
+        for block in &self.blocks[from_index + 1..=to_index] {
+            to_state.apply_block(block)?;
+            touched.extend(touched_records(block));
+        }
+
+        let ids = touched.iter().chain(from_state.expirable_records());
+        Ok(from_state.diff_records(&to_state, ids))
+    }
+
+    /// Returns the ledger state as of the given height, including transactions in the block at the height.
+    ///
+    /// The state is built by replaying blocks after the nearest snapshot not above the height,
+    /// or from the first block if there is no such snapshot.
+    /// States enforce only the default content rules of [`LedgerState::new()`].
+    pub fn state_at(&self, height: u64) -> Result<LedgerStateView, StateError> {
+        let state = self.replay(height, true)?;
+        Ok(LedgerStateView::new(height, state))
+    }
+
+    /// Returns the record as of the given height, or `None` if it did not exist, had been removed or had expired.
+    ///
+    /// Unlike [`Chain::state_at()`], this looks up only the record's changes indexed by [`Chain::append_with_state()`],
+    /// so records in blocks appended without state are unknown.
+    pub fn record_at(
+        &self,
+        id: &TransactionIdentifier,
+        height: u64,
+    ) -> Result<Option<String>, StateError> {
+        let block = self
+            .block(height)
+            .ok_or(StateError::UnknownHeight(height))?;
+        let now = block.header().timestamp();
+
+        let record = self
+            .record_index
+            .get(id)
+            .and_then(|history| history.iter().rev().find(|event| event.height() <= height))
+            .filter(|event| !event.is_expired_at(now))
+            .and_then(|event| event.record().map(str::to_owned));
+        Ok(record)
+    }
+
+    /// Build the state as of the given height, starting from the nearest snapshot if `use_snapshot` is `true`.
+    fn replay(&self, height: u64, use_snapshot: bool) -> Result<LedgerState, StateError> {
+        if self.block(height).is_none() {
+            return Err(StateError::UnknownHeight(height));
+        }
+
+        let snapshot = self
+            .snapshots
+            .range(..=height)
+            .next_back()
+            .filter(|_| use_snapshot);
+        let (mut state, start) = match snapshot {
+            Some((&snapshot_height, snapshot)) => (
+                LedgerState::from_snapshot(snapshot.clone()),
+                snapshot_height + 1,
+            ),
+            None => (LedgerState::new(), self.first_height()),
+        };
+
+        // Heights in the range are in the chain, so the below indices are in range.
+        let start_index = (start - self.first_height()) as usize;
+        let end_index = (height - self.first_height()) as usize;
+        for block in self.blocks.get(start_index..=end_index).unwrap_or_default() {
+            state.apply_block(block)?;
+        }
+        Ok(state)
+    }
+}
+
+/// Target transaction found by [`Chain::resolve_target()`].
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedTarget<'a> {
+    pub transaction: &'a Transaction<JellyfishTransactionContent, Verified>,
+    /// Header of the block containing the transaction.
+    pub header: &'a Header,
+    /// Whether the target record is live at the tip.
+    pub liveness: Liveness,
+}
+
+/// Whether a record is live at the tip of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    /// Record can be changed.
+    Live,
+    /// TTL of the record has elapsed.
+    Expired,
+    /// Changes of the record are not indexed, since blocks were appended without state.
+    Unindexed,
+}
+
+/// Target of a transaction cannot be resolved against a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetError {
+    /// No block at the claimed height.
+    HeightOutOfRange { height: u64 },
+    /// Block at the claimed height has no transaction of the claimed sign.
+    SignNotInBlock { height: u64 },
+    /// Record has been removed.
+    AlreadyRemoved { removed_at_height: u64 },
+    /// TTL of the record has elapsed.
+    Expired,
+}
+
+impl Display for TargetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use TargetError::*;
+
+        match self {
+            HeightOutOfRange { height } => write!(f, "No block at target height {}.", height),
+            SignNotInBlock { height } => {
+                write!(f, "Block at height {} has no target transaction.", height)
+            }
+            AlreadyRemoved { removed_at_height } => write!(
+                f,
+                "Target record was removed at height {}.",
+                removed_at_height
+            ),
+            Expired => write!(f, "Target record has expired."),
+        }
+    }
+}
+
+impl Error for TargetError {}
+
+/// An error occurred during querying a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    /// No block at the height in the chain.
+    HeightOutOfRange { height: u64 },
+    /// Block declares another difficulty than the one demanded by [`Chain::expected_difficulty()`].
+    WrongDifficulty {
+        expected: Difficulty,
+        declared: Difficulty,
+    },
+}
+
+impl Display for ChainError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::HeightOutOfRange { height } => {
+                write!(f, "No block at height {} in the chain.", height)
+            }
+            ChainError::WrongDifficulty { expected, declared } => write!(
+                f,
+                "Block declares difficulty {:?}, but the chain demands {:?}.",
+                declared, expected
+            ),
+        }
+    }
+}
+
+impl Error for ChainError {}
+
+fn sign_index<T>(block: &Block<T, Verified, Verified>) -> HashMap<Signature, usize> {
+    block
+        .transactions()
+        .iter()
+        .enumerate()
+        .map(|(index, tx)| (*tx.sign(), index))
+        .collect()
+}
+
+/// Default number of blocks verified concurrently by [`validate_pipeline()`].
+pub const DEFAULT_PIPELINE_WINDOW: usize = 16;
+
+/// Verified blocks, or index of the first invalid block and its error.
+type PipelineResult<T> = Result<Vec<Block<T, Verified, Verified>>, (usize, BlockError)>;
+
+/// Verifies sequential blocks which follow `anchor`.
+///
+/// Expensive per-block checks (merkle root, transaction signatures, difficulty and digest) run concurrently,
+/// while linkage between consecutive headers is checked in order.
+/// At most [`DEFAULT_PIPELINE_WINDOW`] blocks are in flight at once.
+///
+/// # Returns
+/// `Ok(blocks)` if all blocks are valid, otherwise, `Err((index, err))` of the first invalid block.
+pub fn validate_pipeline<T>(blocks: Vec<Block<T, Yet, Yet>>, anchor: &Header) -> PipelineResult<T>
+where
+    T: ByteOrder + Send,
+{
+    validate_pipeline_with_window(blocks, anchor, DEFAULT_PIPELINE_WINDOW)
+}
+
+/// Same as [`validate_pipeline()`], but at most `window` blocks are in flight at once.
+///
+/// `window` of zero is treated as one.
+pub fn validate_pipeline_with_window<T>(
+    blocks: Vec<Block<T, Yet, Yet>>,
+    anchor: &Header,
+    window: usize,
+) -> PipelineResult<T>
+where
+    T: ByteOrder + Send,
+{
+    validate(blocks, anchor, window, None)
+}
+
+/// Same as [`validate_pipeline()`], but each block must also satisfy [`ChainParams::check_block()`].
+pub fn validate_pipeline_with_params<T>(
+    blocks: Vec<Block<T, Yet, Yet>>,
+    anchor: &Header,
+    params: &ChainParams,
+) -> PipelineResult<T>
+where
+    T: ByteOrder + Send,
+{
+    validate(blocks, anchor, DEFAULT_PIPELINE_WINDOW, Some(params))
+}
+
+fn validate<T>(
+    blocks: Vec<Block<T, Yet, Yet>>,
+    anchor: &Header,
+    window: usize,
+    params: Option<&ChainParams>,
+) -> PipelineResult<T>
+where
+    T: ByteOrder + Send,
+{
+    let window = window.max(1);
+    let mut verified = Vec::with_capacity(blocks.len());
+    let mut previous = anchor.clone();
+    let mut blocks = blocks.into_iter();
+
+    loop {
+        let chunk = blocks.by_ref().take(window).collect::<Vec<_>>();
+        if chunk.is_empty() {
+            break;
+        }
+
+        for result in verify_chunk(chunk, params) {
+            let index = verified.len();
+            let block = result.map_err(|e| (index, e))?;
+            check_linkage(&previous, block.header()).map_err(|e| (index, e))?;
+            check_params(params, &block).map_err(|e| (index, e))?;
+
+            previous = block.header().clone();
+            verified.push(block);
+        }
+    }
+
+    Ok(verified)
+}
+
+#[cfg(feature = "parallel")]
+fn verify_chunk<T>(
+    chunk: Vec<Block<T, Yet, Yet>>,
+    params: Option<&ChainParams>,
+) -> Vec<Result<Block<T, Verified, Verified>, BlockError>>
+where
+    T: ByteOrder + Send,
+{
+    chunk
+        .into_par_iter()
+        .map(|block| verify_contents(block, params))
+        .collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn verify_chunk<T>(
+    chunk: Vec<Block<T, Yet, Yet>>,
+    params: Option<&ChainParams>,
+) -> Vec<Result<Block<T, Verified, Verified>, BlockError>>
+where
+    T: ByteOrder + Send,
+{
+    chunk
+        .into_iter()
+        .map(|block| verify_contents(block, params))
+        .collect()
+}
+
+/// Verifies everything of the block except for its linkage to the previous block.
+///
+/// Transaction signatures follow [`ChainParams::transaction_context()`] if the parameters are given.
+fn verify_contents<T: ByteOrder>(
+    block: Block<T, Yet, Yet>,
+    params: Option<&ChainParams>,
+) -> Result<Block<T, Verified, Verified>, BlockError> {
+    // Linkage depends on the previous block's result, so it is checked later in order.
+    let block = block.verify_block(|_header| true)?;
+    match params {
+        Some(params) => block.verify_transactions_with_params(params),
+        None => block.verify_transactions(),
+    }
+}
+
+fn check_params<T, VT, VB>(
+    params: Option<&ChainParams>,
+    block: &Block<T, VT, VB>,
+) -> Result<(), BlockError>
+where
+    T: ByteOrder,
+{
+    match params {
+        Some(params) => params.check_block(block).map_err(BlockError::Params),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn check_linkage(previous: &Header, header: &Header) -> Result<(), BlockError> {
+    if previous.height().checked_add(1) != Some(header.height()) {
+        Err(BlockError::Height)
+    } else if !header.follows(previous) {
+        Err(BlockError::PreviousDigest)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests_stab {
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::difficulty::MIN_DIFFICULTY;
+    use crate::{SecretAccount, Timestamp, Transaction};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Stab(pub u64);
+
+    impl ByteOrder for Stab {
+        fn append_bytes(&self, buf: &mut Vec<u8>) {
+            buf.extend(self.0.to_le_bytes());
+        }
+    }
+
+    /// Create a block, then execute Proof-of-Work process.
+    pub fn mine(
+        height: u64,
+        previous_digest: Digest,
+        account: &SecretAccount,
+    ) -> Block<Stab, Verified, Yet> {
+        let transactions = vec![Transaction::create(account, Timestamp::now(), Stab(height))];
+        mine_transactions(height, previous_digest, transactions)
+    }
+
+    /// Create a block containing the given transactions, then execute Proof-of-Work process.
+    pub fn mine_transactions<T: ByteOrder>(
+        height: u64,
+        previous_digest: Digest,
+        transactions: Vec<Transaction<T, Verified>>,
+    ) -> Block<T, Verified, Yet> {
+        let timestamp = Timestamp::now();
+
+        let block = Block::create(
+            height,
+            timestamp,
+            previous_digest,
+            MIN_DIFFICULTY,
+            transactions,
+        )
+        .unwrap();
+        proof_of_work(block)
+    }
+
+    /// Create a block containing the given transactions for the network of the parameters, then execute Proof-of-Work process.
+    pub fn mine_transactions_for<T: ByteOrder>(
+        params: &ChainParams,
+        height: u64,
+        previous_digest: Digest,
+        transactions: Vec<Transaction<T, Verified>>,
+    ) -> Block<T, Verified, Yet> {
+        let timestamp = Timestamp::now();
+        let difficulty = params.min_difficulty;
+
+        let mut block =
+            Block::create(height, timestamp, previous_digest, difficulty, transactions).unwrap();
+        block.header_mut().modify_network(params.network());
+        proof_of_work(block)
+    }
+
+    /// Execute Proof-of-Work process on the block.
+    pub fn proof_of_work<T>(block: Block<T, Verified, Yet>) -> Block<T, Verified, Yet> {
+        block.mine()
+    }
+
+    /// Convert block to unverified one, but same content.
+    pub fn unverified<T>(block: &Block<T, Verified, Yet>) -> Block<T, Yet, Yet>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let ser = serde_json::to_string(block).unwrap();
+        serde_json::from_str(&ser).unwrap()
+    }
+
+    /// Build `len` blocks following the returned anchor.
+    /// `corrupt` may replace a block at the given index.
+    pub fn build_chain<F>(len: usize, corrupt: F) -> (Header, Vec<Block<Stab, Yet, Yet>>)
+    where
+        F: Fn(usize, Block<Stab, Verified, Yet>) -> Block<Stab, Verified, Yet>,
+    {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let anchor = mine(0, Digest::ZERO, &account).header().clone();
+
+        let mut previous = anchor.clone();
+        let mut blocks = vec![];
+        for i in 0..len {
+            let block = corrupt(i, mine(previous.height() + 1, *previous.digest(), &account));
+            previous = block.header().clone();
+            blocks.push(unverified(&block));
+        }
+
+        (anchor, blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests_pipeline {
+    use super::tests_stab::*;
+    use super::*;
+    use crate::difficulty::MAX_DIFFICULTY;
+    use crate::SecretAccount;
+
+    #[test]
+    fn validate_pipeline() {
+        let (anchor, blocks) = build_chain(50, |_, block| block);
+        let expected_digests = blocks
+            .iter()
+            .map(|block| *block.header().digest())
+            .collect::<Vec<_>>();
+
+        let verified = super::validate_pipeline(blocks, &anchor).unwrap();
+        let digests = verified
+            .iter()
+            .map(|block| *block.header().digest())
+            .collect::<Vec<_>>();
+
+        assert_eq!(digests, expected_digests);
+    }
+
+    #[test]
+    fn validate_pipeline_fail_corrupt_block() {
+        let (anchor, blocks) = build_chain(50, |i, block| {
+            if i == 30 {
+                // Cheat difficulty without executing Proof-of-Work
+                let header = block.header();
+                Block::create(
+                    header.height(),
+                    header.timestamp(),
+                    *header.previous_digest(),
+                    MAX_DIFFICULTY,
+                    block.transactions().to_vec(),
+                )
+                .unwrap()
+            } else {
+                block
+            }
+        });
+
+        let result = super::validate_pipeline(blocks, &anchor);
+
+        assert!(matches!(result, Err((30, BlockError::Difficulty))));
+    }
+
+    #[test]
+    fn validate_pipeline_fail_linkage() {
+        let (anchor, blocks) = build_chain(20, |i, block| {
+            if i == 5 {
+                // Cheat previous digest
+                let header = block.header();
+                mine(
+                    header.height(),
+                    Digest::from_bytes([255; 32]),
+                    &SecretAccount::create(&mut rand_core::OsRng {}),
+                )
+            } else {
+                block
+            }
+        });
+
+        // Any window size reports the same index.
+        for window in [1, 4, 16, 64] {
+            let result = super::validate_pipeline_with_window(blocks.clone(), &anchor, window);
+            assert!(matches!(result, Err((5, BlockError::PreviousDigest))));
+        }
+    }
+
+    #[test]
+    fn validate_pipeline_fail_height() {
+        let (anchor, mut blocks) = build_chain(3, |_, block| block);
+        blocks.remove(0);
+
+        let result = super::validate_pipeline(blocks, &anchor);
+
+        assert!(matches!(result, Err((0, BlockError::Height))));
+    }
+}
+
+#[cfg(test)]
+mod tests_chain {
+    use super::tests_stab::*;
+    use super::*;
+    use crate::fixtures;
+    use crate::{SecretAccount, Timestamp};
+
+    fn genesis(account: &SecretAccount) -> Chain<Stab> {
+        let block = unverified(&mine(0, Digest::ZERO, account));
+        Chain::new(
+            block
+                .verify_block(|_| true)
+                .unwrap()
+                .verify_transactions()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn append() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+
+        for height in 1..=3 {
+            let tip = chain.tip().header();
+            let block = mine(height, *tip.digest(), &account);
+            chain.append(unverified(&block)).unwrap();
+        }
+
+        assert_eq!(chain.blocks().len(), 4);
+        assert_eq!(chain.tip().header().height(), 3);
+        assert_eq!(chain.block(2).unwrap().header().height(), 2);
+        assert!(chain.block(4).is_none());
+    }
+
+    #[test]
+    fn append_fail_previous_digest() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+
+        let block = mine(1, Digest::from_bytes([255; 32]), &account);
+        let result = chain.append(unverified(&block));
+
+        assert!(matches!(result, Err(BlockError::PreviousDigest)));
+        assert_eq!(chain.blocks().len(), 1);
+    }
+
+    #[test]
+    fn append_fail_height() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+
+        let block = mine(2, *chain.tip().header().digest(), &account);
+        let result = chain.append(unverified(&block));
+
+        assert!(matches!(result, Err(BlockError::Height)));
+    }
+
+    #[test]
+    fn append_already_known() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+
+        let block = mine(1, *chain.tip().header().digest(), &account);
+        assert!(matches!(
+            chain.append(unverified(&block)),
+            Ok(AppendOutcome::Appended)
+        ));
+        assert!(matches!(
+            chain.append(unverified(&block)),
+            Ok(AppendOutcome::AlreadyKnown)
+        ));
+        assert_eq!(chain.blocks().len(), 2);
+
+        // A block below the tip is known as well.
+        let tip = mine(2, *block.header().digest(), &account);
+        chain.append(unverified(&tip)).unwrap();
+        assert!(matches!(
+            chain.append(unverified(&block)),
+            Ok(AppendOutcome::AlreadyKnown)
+        ));
+        assert_eq!(chain.tip().header(), tip.header());
+    }
+
+    #[test]
+    fn append_fork() {
+        let account = fixtures::account(0);
+        let rival = fixtures::account(1);
+        let mut chain = genesis(&account);
+        let genesis_digest = *chain.tip().header().digest();
+        for height in 1..=2 {
+            let block = mine(height, *chain.tip().header().digest(), &account);
+            chain.append(unverified(&block)).unwrap();
+        }
+        let tip = chain.tip().header().clone();
+
+        let fork = mine(1, genesis_digest, &rival);
+        assert!(matches!(
+            chain.append(unverified(&fork)),
+            Ok(AppendOutcome::StoredAsFork)
+        ));
+        assert!(matches!(
+            chain.append(unverified(&fork)),
+            Ok(AppendOutcome::AlreadyKnown)
+        ));
+        assert!(chain.fork_block(fork.header().digest()).is_some());
+
+        // A block following a fork block is also a fork block.
+        let next = mine(2, *fork.header().digest(), &rival);
+        assert!(matches!(
+            chain.append(unverified(&next)),
+            Ok(AppendOutcome::StoredAsFork)
+        ));
+
+        assert_eq!(chain.tip().header(), &tip);
+        assert_eq!(chain.blocks().len(), 3);
+    }
+
+    #[test]
+    fn append_fork_fail_verification() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+        let genesis_digest = *chain.tip().header().digest();
+        let block = mine(1, genesis_digest, &account);
+        chain.append(unverified(&block)).unwrap();
+
+        // Cheat the digest without executing Proof-of-Work.
+        let fork = mine(1, genesis_digest, &fixtures::account(1));
+        let header = fork.header();
+        let fork = Block::create(
+            header.height(),
+            header.timestamp(),
+            *header.previous_digest(),
+            crate::difficulty::MAX_DIFFICULTY,
+            fork.transactions().to_vec(),
+        )
+        .unwrap();
+
+        let result = chain.append(unverified(&fork));
+        assert!(matches!(result, Err(BlockError::Difficulty)));
+        assert!(chain.fork_block(fork.header().digest()).is_none());
+    }
+
+    #[test]
+    fn prune_forks() {
+        let params = ChainParams {
+            max_reorg_depth: 2,
+            ..ChainParams::regtest()
+        };
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account).with_params(params.clone());
+        let mine = |height, previous_digest, account: &SecretAccount| {
+            let tx = Transaction::create_for(&params, account, Timestamp::now(), Stab(height));
+            unverified(&mine_transactions_for(
+                &params,
+                height,
+                previous_digest,
+                vec![tx],
+            ))
+        };
+
+        let genesis_digest = *chain.tip().header().digest();
+        chain.append(mine(1, genesis_digest, &account)).unwrap();
+        let fork = mine(1, genesis_digest, &fixtures::account(1));
+        let fork_digest = *fork.header().digest();
+        assert!(matches!(
+            chain.append(fork),
+            Ok(AppendOutcome::StoredAsFork)
+        ));
+        assert!(chain.fork_block(&fork_digest).is_some());
+
+        for height in 2..=3 {
+            let block = mine(height, *chain.tip().header().digest(), &account);
+            chain.append(block).unwrap();
+        }
+        assert!(chain.fork_block(&fork_digest).is_none());
+    }
+
+    #[test]
+    fn append_verifies_transaction_context() {
+        let v2 = ChainParams {
+            protocol_version: 2,
+            ..ChainParams::regtest()
+        };
+        let v1 = ChainParams::regtest();
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account).with_params(v2.clone());
+        let genesis_digest = *chain.tip().header().digest();
+
+        // Signed without the context of protocol version 2, though the network is the same.
+        let tx = Transaction::create_for(&v1, &account, Timestamp::now(), Stab(1));
+        let block = mine_transactions_for(&v2, 1, genesis_digest, vec![tx]);
+        assert!(matches!(
+            chain.append(unverified(&block)),
+            Err(BlockError::Transaction(_))
+        ));
+
+        let tx = Transaction::create_for(&v2, &account, Timestamp::now(), Stab(1));
+        let block = mine_transactions_for(&v2, 1, genesis_digest, vec![tx]);
+        assert!(unverified(&block).verify_transactions().is_err());
+        assert!(matches!(
+            chain.append(unverified(&block)),
+            Ok(AppendOutcome::Appended)
+        ));
+    }
+
+    #[test]
+    fn blocks_possibly_touching() {
+        let alice = fixtures::account(0);
+        let bob = fixtures::account(1);
+        let carol = fixtures::account(2);
+        let mut chain = genesis(&carol);
+
+        for (height, account) in [(1, &alice), (2, &bob), (3, &alice)] {
+            let block = mine(height, *chain.tip().header().digest(), account);
+            chain.append(unverified(&block)).unwrap();
+        }
+
+        // False-positive rate of a filter containing a single account is negligible.
+        assert_eq!(
+            chain.blocks_possibly_touching(&alice.to_public()),
+            vec![1, 3]
+        );
+        assert_eq!(chain.blocks_possibly_touching(&bob.to_public()), vec![2]);
+        assert_eq!(chain.blocks_possibly_touching(&carol.to_public()), vec![0]);
+    }
+}
+
+#[cfg(test)]
+mod tests_difficulty {
+    use std::time::Duration;
+
+    use super::tests_stab::*;
+    use super::*;
+    use crate::{SecretAccount, Timestamp};
+
+    /// Retargets every 4 blocks aiming at a block per minute, by at most 2 steps.
+    fn params() -> ChainParams {
+        ChainParams {
+            min_difficulty: Difficulty::new(1),
+            max_difficulty: Difficulty::new(8),
+            target_block_interval: Duration::from_secs(60),
+            retarget_window: 4,
+            retarget_clamp: 2,
+            ..ChainParams::regtest()
+        }
+    }
+
+    fn mine_at(
+        params: &ChainParams,
+        account: &SecretAccount,
+        previous: &Header,
+        interval: Duration,
+        difficulty: Difficulty,
+    ) -> Block<Stab, Yet, Yet> {
+        let height = previous.height() + 1;
+        let timestamp = previous.timestamp() + interval;
+        let tx = Transaction::create_for(params, account, timestamp, Stab(height));
+        let mut block =
+            Block::create(height, timestamp, *previous.digest(), difficulty, vec![tx]).unwrap();
+        block.header_mut().modify_network(params.network());
+        unverified(&block.mine())
+    }
+
+    fn genesis(params: &ChainParams, account: &SecretAccount) -> Chain<Stab> {
+        let tx = Transaction::create_for(params, account, Timestamp::from_nanos(0), Stab(0));
+        let mut block = Block::create(
+            0,
+            Timestamp::from_nanos(0),
+            Digest::ZERO,
+            params.min_difficulty,
+            vec![tx],
+        )
+        .unwrap();
+        block.header_mut().modify_network(params.network());
+        let block = unverified(&block.mine())
+            .verify_block(|_| true)
+            .unwrap()
+            .verify_transactions()
+            .unwrap();
+        Chain::new(block).with_params(params.clone())
+    }
+
+    /// Append honest blocks at the given interval until the height.
+    fn mine_until(
+        chain: &mut Chain<Stab>,
+        account: &SecretAccount,
+        height: u64,
+        interval: Duration,
+    ) {
+        let params = chain.params().unwrap().clone();
+        while chain.tip().header().height() < height {
+            let tip = chain.tip().header().clone();
+            let expected = chain.expected_difficulty(tip.height() + 1);
+            let block = mine_at(&params, account, &tip, interval, expected);
+            chain.append(block).unwrap();
+        }
+    }
+
+    #[test]
+    fn retarget_boundaries() {
+        let params = params();
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let mut chain = genesis(&params, &account);
+
+        // Blocks before the first boundary use the initial difficulty.
+        assert_eq!(chain.expected_difficulty(0), Difficulty::new(1));
+        mine_until(&mut chain, &account, 3, Duration::from_secs(1));
+        assert!(chain
+            .blocks()
+            .iter()
+            .all(|block| block.header().difficulty() == Difficulty::new(1)));
+
+        // Blocks were too fast, so difficulty is raised by the clamp.
+        assert_eq!(chain.expected_difficulty(4), Difficulty::new(3));
+        mine_until(&mut chain, &account, 7, Duration::from_secs(240));
+        assert_eq!(
+            chain.block(5).unwrap().header().difficulty(),
+            Difficulty::new(3)
+        );
+
+        // Blocks were too slow, so difficulty is eased down to the minimum.
+        assert_eq!(chain.expected_difficulty(8), Difficulty::new(1));
+        mine_until(&mut chain, &account, 9, Duration::from_secs(60));
+        assert_eq!(chain.tip().header().height(), 9);
+    }
+
+    #[test]
+    fn append_fail_wrong_difficulty() {
+        let params = params();
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let mut chain = genesis(&params, &account);
+        mine_until(&mut chain, &account, 3, Duration::from_secs(1));
+
+        // Under-declared difficulty at the boundary.
+        let tip = chain.tip().header().clone();
+        let interval = Duration::from_secs(1);
+        let block = mine_at(&params, &account, &tip, interval, Difficulty::new(1));
+        let result = chain.append(block);
+        assert!(matches!(
+            result,
+            Err(BlockError::Chain(ChainError::WrongDifficulty { expected, declared }))
+                if expected == Difficulty::new(3) && declared == Difficulty::new(1)
+        ));
+        assert_eq!(chain.tip().header(), &tip);
+
+        // Difficulty must not change between boundaries either.
+        mine_until(&mut chain, &account, 5, interval);
+        let tip = chain.tip().header().clone();
+        let block = mine_at(&params, &account, &tip, interval, Difficulty::new(4));
+        assert!(matches!(
+            chain.append(block),
+            Err(BlockError::Chain(ChainError::WrongDifficulty { .. }))
+        ));
+
+        // Fork blocks are checked against their own history.
+        let fork_parent = chain.block(3).unwrap().header().clone();
+        let interval = Duration::from_secs(2);
+        let block = mine_at(
+            &params,
+            &account,
+            &fork_parent,
+            interval,
+            Difficulty::new(1),
+        );
+        assert!(matches!(
+            chain.append(block),
+            Err(BlockError::Chain(ChainError::WrongDifficulty { .. }))
+        ));
+        let block = mine_at(
+            &params,
+            &account,
+            &fork_parent,
+            interval,
+            Difficulty::new(3),
+        );
+        assert!(matches!(
+            chain.append(block),
+            Ok(AppendOutcome::StoredAsFork)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests_state_diff {
+    use super::tests_stab::*;
+    use super::*;
+    use crate::{SecretAccount, Timestamp, Transaction};
+
+    type Tx = Transaction<JellyfishTransactionContent, Verified>;
+
+    /// Append a block containing the given transactions.
+    fn append(chain: &mut Chain<JellyfishTransactionContent>, transactions: Vec<Tx>) {
+        let tip = chain.tip().header();
+        let block = mine_transactions(tip.height() + 1, *tip.digest(), transactions);
+        chain.append(unverified(&block)).unwrap();
+    }
+
+    #[test]
+    fn state_diff() {
+        let alice = SecretAccount::create(&mut rand_core::OsRng {});
+        let create_tx = |content| Transaction::create(&alice, Timestamp::now(), content);
+
+        let first = create_tx(JellyfishTransactionContent::insert("v1"));
+        let id = TransactionIdentifier::new(0, *first.sign());
+        let genesis = unverified(&mine_transactions(0, Digest::ZERO, vec![first]));
+        let mut chain = Chain::new(
+            genesis
+                .verify_block(|_| true)
+                .unwrap()
+                .verify_transactions()
+                .unwrap(),
+        );
+
+        // height 1, 2, 3
+        append(
+            &mut chain,
+            vec![create_tx(JellyfishTransactionContent::insert("other"))],
+        );
+        append(
+            &mut chain,
+            vec![create_tx(JellyfishTransactionContent::modify(
+                "v2",
+                id.clone(),
+            ))],
+        );
+        append(
+            &mut chain,
+            vec![create_tx(JellyfishTransactionContent::remove(id.clone()))],
+        );
+
+        let diff = chain.state_diff(1, 2).unwrap();
+        assert!(diff.inserted.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].new_record.as_deref(), Some("v2"));
+
+        let diff = chain.state_diff(0, 3).unwrap();
+        assert_eq!(diff.inserted.len(), 1);
+        assert_eq!(diff.inserted[0].new_record.as_deref(), Some("other"));
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, id);
+        assert_eq!(diff.removed[0].old_record.as_deref(), Some("v1"));
+
+        assert!(chain.state_diff(2, 2).unwrap().is_empty());
+
+        assert_eq!(
+            chain.state_diff(3, 1),
+            Err(StateError::InvalidRange {
+                from_height: 3,
+                to_height: 1
+            })
+        );
+        assert!(chain.state_diff(1, 4).is_err());
+    }
+}
+
+#[cfg(test)]
+mod tests_state_root {
+    use super::tests_stab::*;
+    use super::*;
+    use crate::fixtures::{self, FixtureChain};
+    use crate::state::Outcome;
+    use crate::{Difficulty, SecretAccount, Timestamp, Transaction};
+
+    type Tx = Transaction<JellyfishTransactionContent, Verified>;
+
+    /// Create a block following the chain's tip, which commits to the given state root.
+    fn mine_with_state_root(
+        chain: &Chain<JellyfishTransactionContent>,
+        transactions: Vec<Tx>,
+        state_root: Digest,
+    ) -> Block<JellyfishTransactionContent, Yet, Yet> {
+        let tip = chain.tip().header();
+        let mut block = Block::create(
+            tip.height() + 1,
+            Timestamp::now(),
+            *tip.digest(),
+            Difficulty::new(1),
+            transactions,
+        )
+        .unwrap();
+        block.header_mut().modify_state_root(Some(state_root));
+        unverified(&proof_of_work(block))
+    }
+
+    fn genesis(account: &SecretAccount) -> (Chain<JellyfishTransactionContent>, LedgerState) {
+        let tx = fixtures::tx(account, JellyfishTransactionContent::insert("genesis"));
+        let block = unverified(&mine_transactions(0, Digest::ZERO, vec![tx]))
+            .verify_block(|_| true)
+            .unwrap()
+            .verify_transactions()
+            .unwrap();
+
+        let mut state = LedgerState::new();
+        state.apply_block(&block).unwrap();
+        (Chain::new(block), state)
+    }
+
+    #[test]
+    fn append_with_state() {
+        let fixture = FixtureChain::new(ChainParams::regtest())
+            .with_block(|b| b.insert("genesis"))
+            .with_block(|b| b.insert("hello"));
+        let (chain, state) = fixture.into_parts();
+
+        assert_eq!(chain.tip().header().state_root(), Some(&state.state_root()));
+        assert_eq!(state.records().count(), 2);
+
+        let receipt = chain.receipt(1, 0).unwrap();
+        assert_eq!(receipt.outcome, Outcome::Applied);
+        assert!(chain.receipt(1, 1).is_none());
+        // Genesis block was not applied by the chain.
+        assert!(chain.receipts(0).is_none());
+    }
+
+    #[test]
+    fn append_with_state_fail_wrong_root() {
+        let alice = fixtures::account(0);
+        let (mut chain, mut state) = genesis(&alice);
+        let root_before = state.state_root();
+
+        // The block lies that the record is not inserted.
+        let transactions = vec![fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert("hello"),
+        )];
+        let block = mine_with_state_root(&chain, transactions, root_before);
+
+        let result = chain.append_with_state(block, &mut state);
+
+        assert!(matches!(
+            result,
+            Err(BlockError::StateRoot { actual, .. }) if actual == root_before
+        ));
+        assert_eq!(chain.blocks().len(), 1);
+        assert_eq!(state.state_root(), root_before);
+    }
+
+    #[test]
+    fn prove_record() {
+        let fixture = FixtureChain::new(ChainParams::regtest())
+            .with_block(|b| b.insert("genesis"))
+            .with_block(|b| (0..5).fold(b, |b, i| b.insert(&i.to_string())));
+        let id = fixture.ids()[4].clone();
+        let (chain, state) = fixture.into_parts();
+
+        let proof = state.prove_record(&id).unwrap();
+        assert_eq!(proof.record(), "3");
+        assert!(proof.verify_header(chain.tip().header()));
+
+        // Proof does not verify against another state.
+        let genesis_header = chain.block(0).unwrap().header();
+        assert!(!proof.verify_header(genesis_header));
+        assert!(!proof.verify(&Digest::ZERO));
+
+        let unknown = TransactionIdentifier::new(2, proof.id().sign);
+        assert!(state.prove_record(&unknown).is_none());
+    }
+}
+
+#[cfg(test)]
+mod tests_state_at {
+    use super::tests_stab::*;
+    use super::*;
+    use crate::{SecretAccount, Timestamp, Transaction};
+
+    #[test]
+    fn state_at() {
+        let alice = SecretAccount::create(&mut rand_core::OsRng {});
+        let create_tx = |content| Transaction::create(&alice, Timestamp::now(), content);
+
+        let genesis = mine_transactions(
+            0,
+            Digest::ZERO,
+            vec![create_tx(JellyfishTransactionContent::insert("other"))],
+        );
+        let genesis = unverified(&genesis)
+            .verify_block(|_| true)
+            .unwrap()
+            .verify_transactions()
+            .unwrap();
+        let mut state = LedgerState::new();
+        state.apply_block(&genesis).unwrap();
+        let mut chain = Chain::new(genesis).with_snapshot_interval(2);
+
+        let insert = create_tx(JellyfishTransactionContent::insert("v1"));
+        let id = TransactionIdentifier::new(1, *insert.sign());
+        let contents = vec![
+            JellyfishTransactionContent::modify("v2", id.clone()),
+            JellyfishTransactionContent::insert("filler"),
+            JellyfishTransactionContent::modify("v3", id.clone()),
+            JellyfishTransactionContent::remove(id.clone()),
+        ];
+        // height 1 to 5
+        let transactions = std::iter::once(insert).chain(contents.into_iter().map(create_tx));
+        for tx in transactions {
+            let tip = chain.tip().header();
+            let block = mine_transactions(tip.height() + 1, *tip.digest(), vec![tx]);
+            chain
+                .append_with_state(unverified(&block), &mut state)
+                .unwrap();
+        }
+        assert_eq!(chain.snapshots.keys().copied().collect_vec(), vec![2, 4]);
+
+        let expected = [None, Some("v1"), Some("v2"), Some("v2"), Some("v3"), None];
+        for (height, expected) in expected.into_iter().enumerate() {
+            let height = height as u64;
+            assert_eq!(chain.record_at(&id, height).unwrap().as_deref(), expected);
+
+            let view = chain.state_at(height).unwrap();
+            assert_eq!(view.height(), height);
+            assert_eq!(view.state().record(&id), expected);
+
+            // Snapshot-assisted and full replay agree.
+            let full = chain.replay(height, false).unwrap();
+            assert_eq!(view.state().state_root(), full.state_root());
+            assert_eq!(view.state().snapshot(), full.snapshot());
+        }
+
+        assert_eq!(chain.record_at(&id, 6), Err(StateError::UnknownHeight(6)));
+        assert!(matches!(
+            chain.state_at(6),
+            Err(StateError::UnknownHeight(6))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests_resolve_target {
+    use super::tests_stab::*;
+    use super::*;
+    use crate::fixtures::FixtureChain;
+
+    /// Identifiers are `a`, `b`, modify of `a`, remove of `b`, `c` with short TTL and `d` in this order.
+    fn build() -> FixtureChain {
+        FixtureChain::new(ChainParams::regtest())
+            .with_block(|b| b.insert("a").insert("b"))
+            .with_block(|b| {
+                let a = b.tx(0);
+                b.modify(a, "a2")
+            })
+            .with_block(|b| {
+                let target = b.tx(1);
+                b.remove(target)
+                    .content(JellyfishTransactionContent::insert("c").with_ttl(1))
+            })
+            .with_block(|b| b.insert("d"))
+    }
+
+    #[test]
+    fn resolve_target() {
+        let fixture = build();
+        let chain = fixture.chain();
+        let ids = fixture.ids();
+
+        let resolved = chain.resolve_target(&ids[0]).unwrap();
+        assert_eq!(resolved.transaction.sign(), &ids[0].sign);
+        assert_eq!(resolved.transaction.content().record(), Some("a"));
+        assert_eq!(resolved.header, chain.block(0).unwrap().header());
+        assert_eq!(resolved.liveness, Liveness::Live);
+
+        let resolved = chain.resolve_target(&ids[5]).unwrap();
+        assert_eq!(resolved.header.height(), 3);
+        assert_eq!(resolved.liveness, Liveness::Live);
+
+        assert_eq!(
+            chain.resolve_target(&ids[4]).unwrap().liveness,
+            Liveness::Expired
+        );
+        // Modify is not a record, so its liveness is unknown.
+        assert_eq!(
+            chain.resolve_target(&ids[2]).unwrap().liveness,
+            Liveness::Unindexed
+        );
+    }
+
+    #[test]
+    fn resolve_target_fail() {
+        let fixture = build();
+        let chain = fixture.chain();
+        let ids = fixture.ids();
+
+        let beyond_tip = TransactionIdentifier::new(4, ids[0].sign);
+        assert_eq!(
+            chain.resolve_target(&beyond_tip).unwrap_err(),
+            TargetError::HeightOutOfRange { height: 4 }
+        );
+        let wrong_height = TransactionIdentifier::new(1, ids[0].sign);
+        assert_eq!(
+            chain.resolve_target(&wrong_height).unwrap_err(),
+            TargetError::SignNotInBlock { height: 1 }
+        );
+        assert_eq!(
+            chain.resolve_target(&ids[1]).unwrap_err(),
+            TargetError::AlreadyRemoved {
+                removed_at_height: 2
+            }
+        );
+    }
+
+    #[test]
+    fn append_fail_target() {
+        let (mut chain, mut state) = build().into_parts();
+        let fixture = build();
+        let params = chain.params().unwrap().clone();
+        let tip = chain.tip().header().clone();
+
+        let mut append = |content| {
+            let tx =
+                Transaction::create_for(&params, &fixture.accounts()[0], tip.timestamp(), content);
+            let block = mine_transactions_for(&params, 4, *tip.digest(), vec![tx]);
+            chain.append_with_state(unverified(&block), &mut state)
+        };
+
+        let removed = fixture.ids()[1].clone();
+        assert!(matches!(
+            append(JellyfishTransactionContent::modify("b2", removed)),
+            Err(BlockError::Target(TargetError::AlreadyRemoved {
+                removed_at_height: 2
+            }))
+        ));
+        let in_block = TransactionIdentifier::new(4, fixture.ids()[0].sign);
+        assert!(matches!(
+            append(JellyfishTransactionContent::modify("a3", in_block)),
+            Err(BlockError::Target(TargetError::SignNotInBlock {
+                height: 4
+            }))
+        ));
+        let target = fixture.ids()[0].clone();
+        assert!(append(JellyfishTransactionContent::modify("a3", target)).is_ok());
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::Header;
+use crate::ordering::canonical_sort_by_key;
+use crate::{Account, Digest, SecretAccount, Signature};
+
+/// Prepended to a checkpoint before signing, so that the signature is never valid for other kinds of message.
+const CHECKPOINT_DOMAIN: &[u8] = b"jellyfish-chain checkpoint";
+
+/// Trusted digests of blocks at specific heights.
+///
+/// A header at a checkpoint's height must have the checkpoint's digest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoints(BTreeMap<u64, Digest>);
+
+impl Checkpoints {
+    /// Returns empty checkpoints.
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Add a checkpoint. A previous checkpoint at the same height is replaced.
+    pub fn insert(&mut self, height: u64, digest: Digest) {
+        self.0.insert(height, digest);
+    }
+
+    /// Returns the digest at the given height.
+    pub fn get(&self, height: u64) -> Option<&Digest> {
+        self.0.get(&height)
+    }
+
+    /// Returns the checkpoint with the largest height.
+    pub fn latest(&self) -> Option<(u64, &Digest)> {
+        self.0
+            .iter()
+            .next_back()
+            .map(|(&height, digest)| (height, digest))
+    }
+
+    /// Build checkpoints from signed ones, each of which must be signed by at least `threshold` trusted accounts.
+    ///
+    /// Fails if any checkpoint lacks signatures, or two checkpoints have different digests at the same height.
+    pub fn from_signed(
+        checkpoints: Vec<SignedCheckpoint>,
+        trusted: &[Account],
+        threshold: usize,
+    ) -> Result<Self, CheckpointError> {
+        let mut ret = Self::new();
+        for checkpoint in checkpoints {
+            checkpoint.verify(trusted, threshold)?;
+
+            match ret.get(checkpoint.height) {
+                Some(digest) if digest != &checkpoint.digest => {
+                    return Err(CheckpointError::Conflict {
+                        height: checkpoint.height,
+                    });
+                }
+                _ => ret.insert(checkpoint.height, checkpoint.digest),
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns whether the header does not conflict with any checkpoint.
+    pub fn check(&self, header: &Header) -> bool {
+        match self.get(header.height()) {
+            Some(digest) => digest.ct_eq(header.digest()),
+            None => true,
+        }
+    }
+}
+
+impl FromIterator<(u64, Digest)> for Checkpoints {
+    fn from_iter<I: IntoIterator<Item = (u64, Digest)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Checkpoint signed by maintainer accounts.
+///
+/// This is published as chain metadata, so that new nodes can bootstrap without trusting a single party.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedCheckpoint {
+    height: u64,
+    #[serde(with = "hex")]
+    digest: Digest,
+    signatures: Vec<(Account, Signature)>,
+}
+
+impl SignedCheckpoint {
+    /// Returns a checkpoint signed by the given account.
+    pub fn sign(secret: &SecretAccount, height: u64, digest: Digest) -> Self {
+        let mut checkpoint = Self {
+            height,
+            digest,
+            signatures: vec![],
+        };
+        checkpoint.add_signature(secret);
+        checkpoint
+    }
+
+    /// Add the given account's signature to the checkpoint.
+    ///
+    /// Signatures are kept in the canonical order of their accounts,
+    /// so that the same signer set always results in the same checkpoint.
+    pub fn add_signature(&mut self, secret: &SecretAccount) {
+        let sign = secret.sign(&signing_bytes(self.height, &self.digest));
+        self.signatures.push((secret.to_public(), sign));
+        canonical_sort_by_key(&mut self.signatures, |(account, _)| account);
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    pub fn signatures(&self) -> &[(Account, Signature)] {
+        &self.signatures
+    }
+
+    /// Verify that at least `threshold` distinct accounts of `trusted` signed the checkpoint.
+    ///
+    /// Signatures by untrusted accounts, invalid signatures and repeated signers are not counted.
+    pub fn verify(&self, trusted: &[Account], threshold: usize) -> Result<(), CheckpointError> {
+        let msg = signing_bytes(self.height, &self.digest);
+
+        let mut signers: Vec<&Account> = vec![];
+        for (account, sign) in &self.signatures {
+            if trusted.contains(account)
+                && !signers.contains(&account)
+                && account.verify(&msg, sign).is_ok()
+            {
+                signers.push(account);
+            }
+        }
+
+        if signers.len() >= threshold {
+            Ok(())
+        } else {
+            Err(CheckpointError::Quorum {
+                height: self.height,
+                signers: signers.len(),
+                threshold,
+            })
+        }
+    }
+}
+
+fn signing_bytes(height: u64, digest: &Digest) -> Vec<u8> {
+    // Same byte order as block header.
+    let mut bytes = CHECKPOINT_DOMAIN.to_vec();
+    bytes.extend(height.to_be_bytes());
+    bytes.extend(digest.as_bytes());
+    bytes
+}
+
+/// An error occurred while verifying checkpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckpointError {
+    /// Checkpoint is signed by too few trusted accounts.
+    Quorum {
+        height: u64,
+        signers: usize,
+        threshold: usize,
+    },
+    /// Checkpoints have different digests at the same height.
+    Conflict { height: u64 },
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use CheckpointError::*;
+
+        match self {
+            Quorum {
+                height,
+                signers,
+                threshold,
+            } => write!(
+                f,
+                "Checkpoint at {} is signed by {} trusted accounts, but {} are required.",
+                height, signers, threshold
+            ),
+            Conflict { height } => write!(f, "Checkpoints conflict at {}.", height),
+        }
+    }
+}
+
+impl Error for CheckpointError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest() {
+        let checkpoints = [
+            (10, Digest::from_bytes([1; 32])),
+            (30, Digest::from_bytes([3; 32])),
+            (20, Digest::from_bytes([2; 32])),
+        ]
+        .into_iter()
+        .collect::<Checkpoints>();
+
+        assert_eq!(
+            checkpoints.latest(),
+            Some((30, &Digest::from_bytes([3; 32])))
+        );
+        assert_eq!(Checkpoints::new().latest(), None);
+    }
+
+    fn create_accounts(n: usize) -> Vec<SecretAccount> {
+        (0..n)
+            .map(|_| SecretAccount::create(&mut rand_core::OsRng {}))
+            .collect()
+    }
+
+    fn publics(secrets: &[SecretAccount]) -> Vec<Account> {
+        secrets.iter().map(SecretAccount::to_public).collect()
+    }
+
+    #[test]
+    fn verify_quorum() {
+        let maintainers = create_accounts(3);
+        let trusted = publics(&maintainers);
+
+        let mut checkpoint =
+            SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        checkpoint.add_signature(&maintainers[2]);
+
+        assert!(checkpoint.verify(&trusted, 2).is_ok());
+    }
+
+    #[test]
+    fn signer_order_independent() {
+        let maintainers = create_accounts(3);
+
+        let mut forward = SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        forward.add_signature(&maintainers[1]);
+        forward.add_signature(&maintainers[2]);
+        let mut backward =
+            SignedCheckpoint::sign(&maintainers[2], 100, Digest::from_bytes([1; 32]));
+        backward.add_signature(&maintainers[1]);
+        backward.add_signature(&maintainers[0]);
+
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn verify_fail_quorum() {
+        let maintainers = create_accounts(3);
+        let trusted = publics(&maintainers);
+
+        let checkpoint = SignedCheckpoint::sign(&maintainers[1], 100, Digest::from_bytes([1; 32]));
+
+        assert_eq!(
+            checkpoint.verify(&trusted, 2),
+            Err(CheckpointError::Quorum {
+                height: 100,
+                signers: 1,
+                threshold: 2
+            })
+        );
+    }
+
+    #[test]
+    fn verify_ignore_untrusted_signer() {
+        let maintainers = create_accounts(3);
+        let trusted = publics(&maintainers);
+        let outsider = create_accounts(1).remove(0);
+
+        let mut checkpoint =
+            SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        checkpoint.add_signature(&outsider);
+
+        assert!(matches!(
+            checkpoint.verify(&trusted, 2),
+            Err(CheckpointError::Quorum { signers: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn verify_fail_duplicate_signer() {
+        let maintainers = create_accounts(3);
+        let trusted = publics(&maintainers);
+
+        let mut checkpoint =
+            SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        checkpoint.add_signature(&maintainers[0]);
+        checkpoint.add_signature(&maintainers[0]);
+
+        assert!(matches!(
+            checkpoint.verify(&trusted, 2),
+            Err(CheckpointError::Quorum { signers: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn verify_fail_tampered_digest() {
+        let maintainers = create_accounts(3);
+        let trusted = publics(&maintainers);
+
+        let mut checkpoint =
+            SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        checkpoint.add_signature(&maintainers[1]);
+        checkpoint.digest = Digest::from_bytes([2; 32]);
+
+        assert!(checkpoint.verify(&trusted, 1).is_err());
+    }
+
+    #[test]
+    fn serialize_deserialize() {
+        let maintainers = create_accounts(2);
+        let mut checkpoint =
+            SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        checkpoint.add_signature(&maintainers[1]);
+
+        let ser = serde_json::to_string(&checkpoint).unwrap();
+        let de = serde_json::from_str::<SignedCheckpoint>(&ser).unwrap();
+
+        assert_eq!(checkpoint, de);
+        assert!(de.verify(&publics(&maintainers), 2).is_ok());
+    }
+
+    #[test]
+    fn serialize_digest_as_hex() {
+        let maintainers = create_accounts(1);
+        let checkpoint = SignedCheckpoint::sign(&maintainers[0], 100, Digest::from_bytes([1; 32]));
+        let hex = "01".repeat(32);
+
+        // Same as the digest was raw bytes with `#[serde(with = "hex")]`, in both of human-readable and binary formats.
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        assert!(json.contains(&format!(r#""digest":"{}""#, hex)));
+        let bin = bincode::serialize(&checkpoint).unwrap();
+        let mut expected = bincode::serialize(&100u64).unwrap();
+        expected.extend(bincode::serialize(&hex).unwrap());
+        assert!(bin.starts_with(&expected));
+        assert_eq!(
+            bincode::deserialize::<SignedCheckpoint>(&bin).unwrap(),
+            checkpoint
+        );
+    }
+
+    #[test]
+    fn from_signed() {
+        let maintainers = create_accounts(3);
+        let trusted = publics(&maintainers);
+        let signed = |height, digest| {
+            let mut checkpoint = SignedCheckpoint::sign(&maintainers[0], height, digest);
+            checkpoint.add_signature(&maintainers[1]);
+            checkpoint
+        };
+
+        let checkpoints = Checkpoints::from_signed(
+            vec![
+                signed(10, Digest::from_bytes([1; 32])),
+                signed(20, Digest::from_bytes([2; 32])),
+            ],
+            &trusted,
+            2,
+        )
+        .unwrap();
+        assert_eq!(checkpoints.get(10), Some(&Digest::from_bytes([1; 32])));
+        assert_eq!(
+            checkpoints.latest(),
+            Some((20, &Digest::from_bytes([2; 32])))
+        );
+
+        let result = Checkpoints::from_signed(
+            vec![
+                signed(10, Digest::from_bytes([1; 32])),
+                signed(10, Digest::from_bytes([2; 32])),
+            ],
+            &trusted,
+            2,
+        );
+        assert_eq!(result, Err(CheckpointError::Conflict { height: 10 }));
+
+        let unsigned = SignedCheckpoint::sign(&maintainers[0], 30, Digest::from_bytes([3; 32]));
+        let result = Checkpoints::from_signed(
+            vec![signed(10, Digest::from_bytes([1; 32])), unsigned],
+            &trusted,
+            2,
+        );
+        assert!(matches!(
+            result,
+            Err(CheckpointError::Quorum { height: 30, .. })
+        ));
+    }
+}
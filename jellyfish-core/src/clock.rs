@@ -0,0 +1,85 @@
+use std::fmt::Debug;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::atomic::{AtomicI64, Ordering};
+#[cfg(any(test, feature = "test-util"))]
+use std::time::Duration;
+
+use crate::Timestamp;
+
+/// Source of the current time.
+///
+/// Components reading wall-clock time take a clock instead of calling [`Timestamp::now()`],
+/// so that time-dependent behavior can be tested without sleeping.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// Clock which reads the system time by [`Timestamp::now()`].
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemClock;
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// Clock which moves only when it is told to.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    nanos: AtomicI64,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+    /// Returns a clock which stays at the given time.
+    pub fn new(timestamp: Timestamp) -> Self {
+        Self {
+            nanos: AtomicI64::new(timestamp.nanos()),
+        }
+    }
+
+    /// Move the clock forward by the duration.
+    pub fn advance(&self, duration: Duration) {
+        // fetch_update() never fails since the closure always returns Some.
+        let _ = self
+            .nanos
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                Some((Timestamp::from_nanos(current) + duration).nanos())
+            });
+    }
+
+    pub fn set(&self, timestamp: Timestamp) {
+        self.nanos.store(timestamp.nanos(), Ordering::SeqCst);
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock() {
+        let clock = ManualClock::new(Timestamp::from_nanos(100));
+        assert_eq!(clock.now(), Timestamp::from_nanos(100));
+
+        clock.advance(Duration::from_nanos(50));
+        assert_eq!(clock.now(), Timestamp::from_nanos(150));
+
+        clock.set(Timestamp::from_nanos(10));
+        assert_eq!(clock.now(), Timestamp::from_nanos(10));
+
+        clock.advance(Duration::MAX);
+        assert_eq!(clock.now(), Timestamp::from_nanos(i64::MAX));
+    }
+}
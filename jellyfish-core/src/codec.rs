@@ -0,0 +1,285 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Reads fields from untrusted bytes, checking every length before it is used.
+///
+/// Each variable-length field is checked against its own limit, and all fields together against the total budget.
+/// A length claimed by the input is rejected before anything is allocated for it,
+/// so a small malicious input never causes a large allocation.
+#[derive(Debug, Clone)]
+pub struct BoundedReader<'a> {
+    buf: &'a [u8],
+    position: usize,
+    /// Maximum number of bytes read in total.
+    budget: usize,
+}
+
+impl<'a> BoundedReader<'a> {
+    /// Create a reader which reads at most `budget` bytes from the buffer.
+    pub fn new(buf: &'a [u8], budget: usize) -> Self {
+        Self {
+            buf,
+            position: 0,
+            budget,
+        }
+    }
+
+    /// Returns number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns number of bytes which have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.position
+    }
+
+    /// Read `len` bytes, which must not exceed `limit`.
+    pub fn read_bytes(
+        &mut self,
+        field: &'static str,
+        len: usize,
+        limit: usize,
+    ) -> Result<&'a [u8], DecodeError> {
+        if len > limit {
+            return Err(DecodeError::LimitExceeded {
+                field,
+                limit,
+                requested: len,
+            });
+        }
+        let total = self.position.saturating_add(len);
+        if total > self.budget {
+            return Err(DecodeError::LimitExceeded {
+                field,
+                limit: self.budget,
+                requested: total,
+            });
+        }
+        if len > self.remaining() {
+            return Err(DecodeError::UnexpectedEnd {
+                field,
+                requested: len,
+                remaining: self.remaining(),
+            });
+        }
+
+        let bytes = &self.buf[self.position..total];
+        self.position = total;
+        Ok(bytes)
+    }
+
+    /// Read exactly `N` bytes, e.g., a fixed-length field or an integer in either endian.
+    pub fn read_array<const N: usize>(
+        &mut self,
+        field: &'static str,
+    ) -> Result<[u8; N], DecodeError> {
+        let bytes = self.read_bytes(field, N, N)?;
+        // The above read_bytes() returns exactly N bytes, so the below unwrap() always succeeds.
+        Ok(bytes.try_into().unwrap())
+    }
+
+    /// Returns `Err(DecodeError::Malformed)` if any byte remains after the field.
+    pub fn finish(&self, field: &'static str) -> Result<(), DecodeError> {
+        if self.remaining() == 0 {
+            Ok(())
+        } else {
+            Err(DecodeError::Malformed {
+                field,
+                reason: format!("{} bytes remain after the end.", self.remaining()),
+            })
+        }
+    }
+
+    /// Read big endian `u32`.
+    pub fn read_u32(&mut self, field: &'static str) -> Result<u32, DecodeError> {
+        let bytes = self.read_bytes(field, 4, 4)?;
+        // The above read_bytes() returns exactly 4 bytes, so the below unwrap() always succeeds.
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Read big endian `u64`.
+    pub fn read_u64(&mut self, field: &'static str) -> Result<u64, DecodeError> {
+        let bytes = self.read_bytes(field, 8, 8)?;
+        // The above read_bytes() returns exactly 8 bytes, so the below unwrap() always succeeds.
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Read number of elements as big endian `u32`, which must not exceed `limit`.
+    pub fn read_count(&mut self, field: &'static str, limit: usize) -> Result<usize, DecodeError> {
+        let count = self.read_u32(field)? as usize;
+        if count > limit {
+            Err(DecodeError::LimitExceeded {
+                field,
+                limit,
+                requested: count,
+            })
+        } else {
+            Ok(count)
+        }
+    }
+
+    /// Read bytes prefixed by their length as big endian `u32`, which must not exceed `limit`.
+    pub fn read_length_prefixed(
+        &mut self,
+        field: &'static str,
+        limit: usize,
+    ) -> Result<&'a [u8], DecodeError> {
+        let len = self.read_u32(field)? as usize;
+        self.read_bytes(field, len, limit)
+    }
+}
+
+/// Untrusted bytes cannot be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Length of the field, or total length, exceeds its limit.
+    LimitExceeded {
+        field: &'static str,
+        limit: usize,
+        requested: usize,
+    },
+    /// The field continues past the end of the input.
+    UnexpectedEnd {
+        field: &'static str,
+        requested: usize,
+        remaining: usize,
+    },
+    /// Content of the field is invalid.
+    Malformed { field: &'static str, reason: String },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use DecodeError::*;
+
+        match self {
+            LimitExceeded {
+                field,
+                limit,
+                requested,
+            } => write!(
+                f,
+                "'{}' requires {} bytes or elements, which exceeds limit {}.",
+                field, requested, limit
+            ),
+            UnexpectedEnd {
+                field,
+                requested,
+                remaining,
+            } => write!(
+                f,
+                "'{}' requires {} bytes, but only {} bytes remain.",
+                field, requested, remaining
+            ),
+            Malformed { field, reason } => write!(f, "'{}' is malformed: {}", field, reason),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let mut buf = vec![];
+        buf.extend(7u32.to_be_bytes());
+        buf.extend(2u64.to_be_bytes());
+        buf.extend(3u32.to_be_bytes());
+        buf.extend(b"abc");
+        let mut reader = BoundedReader::new(&buf, 1024);
+
+        assert_eq!(reader.read_count("count", 10), Ok(7));
+        assert_eq!(reader.read_u64("height"), Ok(2));
+        assert_eq!(reader.read_length_prefixed("record", 3), Ok(&b"abc"[..]));
+        assert_eq!(reader.position(), buf.len());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn count_exceeds_limit() {
+        // A count of u32::MAX elements
+        let buf = u32::MAX.to_be_bytes();
+        let mut reader = BoundedReader::new(&buf, 1024);
+
+        assert_eq!(
+            reader.read_count("transactions", 1000),
+            Err(DecodeError::LimitExceeded {
+                field: "transactions",
+                limit: 1000,
+                requested: u32::MAX as usize,
+            })
+        );
+    }
+
+    #[test]
+    fn read_array_and_finish() {
+        let buf = [1, 2, 3, 4, 5];
+        let mut reader = BoundedReader::new(&buf, 1024);
+
+        assert_eq!(reader.read_array::<4>("word"), Ok([1, 2, 3, 4]));
+        assert!(matches!(
+            reader.finish("word"),
+            Err(DecodeError::Malformed { field: "word", .. })
+        ));
+        assert!(matches!(
+            reader.read_array::<2>("word"),
+            Err(DecodeError::UnexpectedEnd { .. })
+        ));
+        assert_eq!(reader.read_array::<1>("tail"), Ok([5]));
+        assert_eq!(reader.finish("tail"), Ok(()));
+    }
+
+    #[test]
+    fn length_past_end() {
+        let mut buf = vec![];
+        buf.extend(100u32.to_be_bytes());
+        buf.extend(b"short");
+        let mut reader = BoundedReader::new(&buf, 1024);
+
+        assert_eq!(
+            reader.read_length_prefixed("record", 1000),
+            Err(DecodeError::UnexpectedEnd {
+                field: "record",
+                requested: 100,
+                remaining: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn field_exceeds_limit() {
+        let mut buf = vec![];
+        buf.extend(5u32.to_be_bytes());
+        buf.extend(b"hello");
+        let mut reader = BoundedReader::new(&buf, 1024);
+
+        assert!(matches!(
+            reader.read_length_prefixed("record", 4),
+            Err(DecodeError::LimitExceeded {
+                field: "record",
+                limit: 4,
+                requested: 5,
+            })
+        ));
+    }
+
+    #[test]
+    fn total_exceeds_budget() {
+        let buf = [0; 16];
+        let mut reader = BoundedReader::new(&buf, 10);
+
+        assert!(reader.read_u64("first").is_ok());
+        assert_eq!(
+            reader.read_u32("second"),
+            Err(DecodeError::LimitExceeded {
+                field: "second",
+                limit: 10,
+                requested: 12,
+            })
+        );
+    }
+}
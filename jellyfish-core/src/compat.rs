@@ -0,0 +1,439 @@
+//! Test vectors for other implementations of jellyfish-chain protocol.
+//!
+//! [`export_vectors()`] writes a JSON document of [`Vectors`], which is the same on every run.
+//! Another implementation reproduces the document, or checks its own one by [`check_vectors()`].
+//!
+//! Binary fields are lower-case hex strings, as well as in blocks and transactions.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{deserialize_optional_hex, serialize_optional_hex, Block, Header};
+use crate::chain::Chain;
+use crate::digest::Digest;
+use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+use crate::params::{ChainParams, NetworkId};
+use crate::transaction::build_signature_source;
+use crate::{
+    Account, ByteOrder, Difficulty, SecretAccount, Signature, Timestamp, Transaction, Verified, Yet,
+};
+
+/// Version of the schema of [`Vectors`].
+pub const VECTORS_VERSION: u32 = 1;
+
+/// Seed of accounts in exported vectors.
+pub const VECTORS_SEED: u64 = 0;
+
+/// Difficulties of headers in exported vectors.
+const HEADER_DIFFICULTIES: [u64; 4] = [0, 4, 8, 12];
+
+/// Timestamp of the first block in exported vectors, 2022-01-01T00:00:00Z.
+const GENESIS_SECS: u64 = 1_640_995_200;
+
+/// Document of test vectors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vectors {
+    /// Must be [`VECTORS_VERSION`].
+    pub version: u32,
+    /// Seed of `accounts`, which are derived by [`seeded_account()`].
+    pub seed: u64,
+    pub accounts: Vec<AccountVector>,
+    pub transactions: Vec<TransactionVector>,
+    pub headers: Vec<HeaderVector>,
+    /// Blocks from genesis in ascending order of height, which satisfy [`ChainParams::regtest()`].
+    pub chain: Vec<Block<JellyfishTransactionContent, Yet, Yet>>,
+}
+
+/// Account derived from the seed of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountVector {
+    pub index: u64,
+    #[serde(with = "hex")]
+    pub secret_key: [u8; 32],
+    pub public_key: Account,
+}
+
+/// Signed transaction and the bytes its signature covers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionVector {
+    /// Network which the transaction is signed for. `None` for the legacy format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_optional_hex")]
+    #[serde(deserialize_with = "deserialize_optional_hex")]
+    pub network: Option<NetworkId>,
+    pub transaction: Transaction<JellyfishTransactionContent, Yet>,
+    #[serde(with = "hex")]
+    pub signature_source: Vec<u8>,
+    pub signature: Signature,
+}
+
+/// Mined header with its byte order and digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderVector {
+    pub header: Header,
+    #[serde(with = "hex")]
+    pub byte_order: Vec<u8>,
+    #[serde(with = "hex")]
+    pub digest: Digest,
+}
+
+/// Returns the account at the index derived from the seed,
+/// whose secret key is SHA-256 of the seed and the index, both in 8-byte little endian.
+pub fn seeded_account(seed: u64, index: u64) -> SecretAccount {
+    let mut source = seed.to_le_bytes().to_vec();
+    source.extend(index.to_le_bytes());
+    SecretAccount::from_secret_key(Digest::create(&source).as_bytes())
+        .expect("Digest must be a valid secret key.")
+}
+
+/// Returns the vectors written by [`export_vectors()`].
+pub fn generate_vectors() -> Vectors {
+    let secrets = (0..3)
+        .map(|index| seeded_account(VECTORS_SEED, index))
+        .collect::<Vec<_>>();
+    let accounts = secrets
+        .iter()
+        .zip(0..)
+        .map(|(secret, index)| AccountVector {
+            index,
+            secret_key: *secret.expose_secret(),
+            public_key: secret.to_public(),
+        })
+        .collect();
+
+    Vectors {
+        version: VECTORS_VERSION,
+        seed: VECTORS_SEED,
+        accounts,
+        transactions: transaction_vectors(&secrets),
+        headers: header_vectors(&secrets[0]),
+        chain: mine_chain(&secrets),
+    }
+}
+
+/// Write [`generate_vectors()`] as a JSON document.
+pub fn export_vectors<W: Write>(w: W) -> io::Result<()> {
+    serde_json::to_writer_pretty(w, &generate_vectors())?;
+    Ok(())
+}
+
+/// Read a JSON document of [`Vectors`], then verify every entry by this implementation.
+///
+/// # Returns
+/// `Err(CompatError::Mismatch)` of the first entry which this implementation does not reproduce.
+pub fn check_vectors<R: Read>(r: R) -> Result<(), CompatError> {
+    let vectors: Vectors = serde_json::from_reader(r).map_err(CompatError::Json)?;
+    if vectors.version != VECTORS_VERSION {
+        return Err(CompatError::Version(vectors.version));
+    }
+
+    for (i, vector) in vectors.accounts.iter().enumerate() {
+        let secret = seeded_account(vectors.seed, vector.index);
+        if *secret.expose_secret() != vector.secret_key {
+            return Err(mismatch(format!("accounts[{}].secret_key", i)));
+        }
+        if secret.to_public() != vector.public_key {
+            return Err(mismatch(format!("accounts[{}].public_key", i)));
+        }
+    }
+
+    for (i, vector) in vectors.transactions.iter().enumerate() {
+        let tx = &vector.transaction;
+        let source = build_signature_source(
+            vector.network.as_ref(),
+            tx.account(),
+            tx.timestamp(),
+            tx.content(),
+        );
+        if source != vector.signature_source {
+            return Err(mismatch(format!("transactions[{}].signature_source", i)));
+        }
+        if !tx.sign().ct_eq(&vector.signature) {
+            return Err(mismatch(format!("transactions[{}].signature", i)));
+        }
+        if tx.clone().verify_in(vector.network.as_ref()).is_err() {
+            return Err(mismatch(format!("transactions[{}].transaction", i)));
+        }
+    }
+
+    for (i, vector) in vectors.headers.iter().enumerate() {
+        if vector.header.build_byte_order() != vector.byte_order {
+            return Err(mismatch(format!("headers[{}].byte_order", i)));
+        }
+        if Digest::create(&vector.byte_order) != vector.digest {
+            return Err(mismatch(format!("headers[{}].digest", i)));
+        }
+        if vector.header.verify_proof_of_work().is_err() {
+            return Err(mismatch(format!("headers[{}].header", i)));
+        }
+    }
+
+    check_chain(vectors.chain).map_err(|i| mismatch(format!("chain[{}]", i)))
+}
+
+/// Returns index of the first invalid block.
+fn check_chain(blocks: Vec<Block<JellyfishTransactionContent, Yet, Yet>>) -> Result<(), usize> {
+    let mut blocks = blocks.into_iter();
+    let genesis = blocks
+        .next()
+        .ok_or(0usize)?
+        .verify_block(|_| true)
+        .and_then(Block::verify_transactions)
+        .map_err(|_| 0usize)?;
+
+    let mut chain = Chain::new(genesis).with_params(ChainParams::regtest());
+    for (i, block) in blocks.enumerate() {
+        chain.append(block).map_err(|_| i + 1)?;
+    }
+    Ok(())
+}
+
+fn transaction_vectors(secrets: &[SecretAccount]) -> Vec<TransactionVector> {
+    let target = TransactionIdentifier::new(1, secrets[0].sign(b"jellyfish-chain vectors"));
+    let contents = vec![
+        JellyfishTransactionContent::insert(""),
+        JellyfishTransactionContent::insert("hello, jellyfish"),
+        JellyfishTransactionContent::insert("\u{1f41f}").with_ttl(60_000_000_000),
+        JellyfishTransactionContent::modify("modified", target.clone()),
+        JellyfishTransactionContent::remove(target.clone()),
+        JellyfishTransactionContent::append(" tail", target.clone()),
+        JellyfishTransactionContent::batch(vec![
+            JellyfishTransactionContent::insert("first"),
+            JellyfishTransactionContent::remove(target),
+        ])
+        .expect("Batch of vectors must be valid."),
+    ];
+    let networks = [
+        None,
+        ChainParams::regtest().network(),
+        ChainParams::mainnet().network(),
+    ];
+
+    let mut vectors = vec![];
+    for (i, content) in contents.into_iter().enumerate() {
+        for (j, network) in networks.iter().enumerate() {
+            let secret = &secrets[(i + j) % secrets.len()];
+            let timestamp = Timestamp::EPOCH + Duration::from_secs(GENESIS_SECS + i as u64);
+            let tx = Transaction::create_in(network.as_ref(), secret, timestamp, content.clone());
+            vectors.push(TransactionVector {
+                network: *network,
+                signature_source: build_signature_source(
+                    network.as_ref(),
+                    tx.account(),
+                    tx.timestamp(),
+                    tx.content(),
+                ),
+                signature: *tx.sign(),
+                transaction: unverified(&tx),
+            });
+        }
+    }
+    vectors
+}
+
+fn header_vectors(secret: &SecretAccount) -> Vec<HeaderVector> {
+    HEADER_DIFFICULTIES
+        .iter()
+        .zip(0..)
+        .map(|(&difficulty, height)| {
+            let timestamp = Timestamp::EPOCH + Duration::from_secs(GENESIS_SECS + height);
+            let content = JellyfishTransactionContent::insert(format!("difficulty {}", difficulty));
+            let tx = Transaction::create(secret, timestamp, content);
+            let block = Block::create(
+                height,
+                timestamp,
+                Digest::from_bytes([height as u8; 32]),
+                Difficulty::new(difficulty),
+                vec![tx],
+            )
+            .expect("Block of vectors must have a transaction.")
+            .mine();
+
+            let header = block.header().clone();
+            let byte_order = header.build_byte_order();
+            HeaderVector {
+                digest: *header.digest(),
+                header,
+                byte_order,
+            }
+        })
+        .collect()
+}
+
+fn mine_chain(secrets: &[SecretAccount]) -> Vec<Block<JellyfishTransactionContent, Yet, Yet>> {
+    let params = ChainParams::regtest();
+    let mut blocks = vec![];
+    let mut previous_digest = Digest::ZERO;
+
+    for height in 0..4u64 {
+        let timestamp = Timestamp::EPOCH
+            + Duration::from_secs(GENESIS_SECS)
+            + params.target_block_interval * height as u32;
+        let transactions = secrets
+            .iter()
+            .take(height as usize + 1)
+            .map(|secret| {
+                let content = JellyfishTransactionContent::insert(format!("block {}", height));
+                Transaction::create_for(&params, secret, timestamp, content)
+            })
+            .collect::<Vec<_>>();
+        let mut block = Block::create(
+            height,
+            timestamp,
+            previous_digest,
+            params.min_difficulty,
+            transactions,
+        )
+        .expect("Block of vectors must have a transaction.");
+        block.header_mut().modify_network(params.network());
+        block.header_mut().modify_hash_mode(params.hash_mode);
+        let block = block.mine();
+
+        previous_digest = *block.header().digest();
+        let ser = serde_json::to_vec(&block).expect("Block must be serializable.");
+        blocks.push(serde_json::from_slice(&ser).expect("Block must be deserializable."));
+    }
+    blocks
+}
+
+fn unverified(
+    tx: &Transaction<JellyfishTransactionContent, Verified>,
+) -> Transaction<JellyfishTransactionContent, Yet> {
+    Transaction::from_parts(
+        tx.account().clone(),
+        tx.timestamp(),
+        tx.content().clone(),
+        *tx.sign(),
+    )
+}
+
+fn mismatch(entry: String) -> CompatError {
+    CompatError::Mismatch { entry }
+}
+
+/// Test vectors are unreadable, or differ from this implementation.
+#[derive(Debug)]
+pub enum CompatError {
+    /// Document is not JSON of [`Vectors`].
+    Json(serde_json::Error),
+    /// Schema version is not [`VECTORS_VERSION`].
+    Version(u32),
+    /// Entry at the path, e.g., `transactions[3].signature_source`, is not reproduced by this implementation.
+    Mismatch { entry: String },
+}
+
+impl Display for CompatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatError::Json(e) => write!(f, "Malformed test vectors: {}", e),
+            CompatError::Version(version) => {
+                write!(f, "Unsupported test vector version {}.", version)
+            }
+            CompatError::Mismatch { entry } => write!(f, "Test vector {} mismatches.", entry),
+        }
+    }
+}
+
+impl Error for CompatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompatError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export() -> Vec<u8> {
+        let mut buf = vec![];
+        export_vectors(&mut buf).unwrap();
+        buf
+    }
+
+    /// Edit the exported document by the closure.
+    fn edit<F: FnOnce(&mut serde_json::Value)>(edit: F) -> Vec<u8> {
+        let mut json = serde_json::from_slice::<serde_json::Value>(&export()).unwrap();
+        edit(&mut json);
+        serde_json::to_vec(&json).unwrap()
+    }
+
+    fn mismatched_entry(document: &[u8]) -> String {
+        match check_vectors(document) {
+            Err(CompatError::Mismatch { entry }) => entry,
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn export_check() {
+        let document = export();
+        assert!(check_vectors(document.as_slice()).is_ok());
+
+        // Deterministic.
+        assert_eq!(document, export());
+
+        let vectors = generate_vectors();
+        assert_eq!(vectors.accounts.len(), 3);
+        assert_eq!(vectors.transactions.len(), 21);
+        assert_eq!(vectors.headers.len(), HEADER_DIFFICULTIES.len());
+        assert_eq!(vectors.chain.len(), 4);
+    }
+
+    #[test]
+    fn seeded_account_matches_fixture() {
+        use crate::fixtures::FixtureChain;
+
+        let fixture = FixtureChain::new(ChainParams::regtest()).with_accounts(2);
+        for (index, account) in fixture.accounts().iter().enumerate() {
+            assert_eq!(
+                seeded_account(VECTORS_SEED, index as u64).to_bytes(),
+                account.to_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn check_fail_mismatch() {
+        let document = edit(|json| json["accounts"][1]["index"] = 5.into());
+        assert_eq!(mismatched_entry(&document), "accounts[1].secret_key");
+
+        let document = edit(|json| {
+            json["transactions"][4]["signature_source"] = "00".into();
+        });
+        assert_eq!(
+            mismatched_entry(&document),
+            "transactions[4].signature_source"
+        );
+
+        // Altered byte order comes first.
+        let document = edit(|json| json["headers"][2]["header"]["nonce"] = 0.into());
+        assert_eq!(mismatched_entry(&document), "headers[2].byte_order");
+
+        let document = edit(|json| {
+            let header = &mut json["chain"][2]["header"];
+            header["previous_digest"] = hex::encode([0; 32]).into();
+        });
+        assert_eq!(mismatched_entry(&document), "chain[2]");
+    }
+
+    #[test]
+    fn check_fail_format() {
+        assert!(matches!(
+            check_vectors(&b"[]"[..]),
+            Err(CompatError::Json(_))
+        ));
+
+        let document = edit(|json| json["version"] = 2.into());
+        assert!(matches!(
+            check_vectors(document.as_slice()),
+            Err(CompatError::Version(2))
+        ));
+    }
+}
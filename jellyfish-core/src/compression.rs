@@ -0,0 +1,161 @@
+//! Compressed records, which keep large payloads small on chain.
+//!
+//! A compressed record is [`COMPRESSED_PREFIX`] followed by the standard base64 encoding of a zstd frame.
+//! Since the stored string itself is signed, verification never needs decompression.
+//! Records without the prefix are plain text, so existing records are read as they are.
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use crate::jellyfish_transaction::ContentError;
+
+/// Prefix of compressed records, whose digit is the version of the format.
+pub const COMPRESSED_PREFIX: &str = "zstd1:";
+
+/// Default limit of decompressed records in bytes, which rejects zip bombs.
+pub const MAX_DECOMPRESSED_RECORD_BYTES: usize = 1 << 20;
+
+/// Compress the record if it shrinks, otherwise, returns it as it is.
+///
+/// Plain text starting with [`COMPRESSED_PREFIX`] is compressed anyway, so that it is never mistaken for a compressed record.
+pub fn compress_record(record: &str) -> Cow<'_, str> {
+    let compressed = zstd::encode_all(record.as_bytes(), zstd::DEFAULT_COMPRESSION_LEVEL)
+        .expect("Compression of in-memory data must succeed.");
+    let compressed = format!("{}{}", COMPRESSED_PREFIX, base64::encode(compressed));
+
+    if compressed.len() < record.len() || record.starts_with(COMPRESSED_PREFIX) {
+        Cow::Owned(compressed)
+    } else {
+        Cow::Borrowed(record)
+    }
+}
+
+/// Returns the original text of the record, limiting its size to [`MAX_DECOMPRESSED_RECORD_BYTES`].
+pub fn decompress_record(record: &str) -> Result<Cow<'_, str>, ContentError> {
+    decompress_record_with_limit(record, MAX_DECOMPRESSED_RECORD_BYTES)
+}
+
+/// Returns the original text of the record, whose size must be at most `limit` bytes.
+///
+/// # Returns
+/// `Err(ContentError::DecompressedTooLarge)` if the record expands beyond the limit,
+/// and `Err(ContentError::Rule)` if the record has the prefix but is malformed.
+pub fn decompress_record_with_limit(
+    record: &str,
+    limit: usize,
+) -> Result<Cow<'_, str>, ContentError> {
+    let encoded = match record.strip_prefix(COMPRESSED_PREFIX) {
+        Some(encoded) => encoded,
+        None => return Ok(Cow::Borrowed(record)),
+    };
+    let compressed = base64::decode(encoded)
+        .map_err(|e| ContentError::new(format!("Malformed compressed record: {}", e)))?;
+
+    // Read one more byte than the limit to detect excess without expanding the whole record.
+    let decoder = zstd::stream::read::Decoder::new(compressed.as_slice())
+        .map_err(|e| ContentError::new(format!("Malformed compressed record: {}", e)))?;
+    let mut decompressed = vec![];
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| ContentError::new(format!("Malformed compressed record: {}", e)))?;
+    if decompressed.len() > limit {
+        return Err(ContentError::DecompressedTooLarge { limit });
+    }
+
+    String::from_utf8(decompressed)
+        .map(Cow::Owned)
+        .map_err(|_| ContentError::new("Compressed record is not UTF-8."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jellyfish_transaction::JellyfishTransactionContent;
+    use crate::ByteOrder;
+
+    fn large_json() -> String {
+        format!(r#"{{"data": "{}"}}"#, "0".repeat(4096))
+    }
+
+    #[test]
+    fn round_trip() {
+        let record = large_json();
+        let content = JellyfishTransactionContent::insert_compressed(&record);
+
+        let stored = content.record().unwrap();
+        assert!(stored.starts_with(COMPRESSED_PREFIX));
+        assert!(stored.len() < record.len());
+        assert_eq!(content.record_decompressed().unwrap().unwrap(), record);
+
+        // Signature covers the stored bytes, so it differs from the plain one.
+        assert_ne!(
+            content.build_byte_order(),
+            JellyfishTransactionContent::insert(record).build_byte_order()
+        );
+    }
+
+    #[test]
+    fn incompressible_fallback() {
+        let record = "a1b2c3d4";
+        let content = JellyfishTransactionContent::insert_compressed(record);
+
+        assert_eq!(content.record(), Some(record));
+        assert_eq!(content.record_decompressed().unwrap().unwrap(), record);
+    }
+
+    #[test]
+    fn prefixed_plain_text() {
+        let record = format!("{}a1b2", COMPRESSED_PREFIX);
+        let compressed = compress_record(&record);
+
+        assert_ne!(compressed, record);
+        assert_eq!(decompress_record(&compressed).unwrap(), record);
+    }
+
+    #[test]
+    fn reject_zip_bomb() {
+        let bomb = vec![0; MAX_DECOMPRESSED_RECORD_BYTES + 1];
+        let compressed =
+            zstd::encode_all(bomb.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL).unwrap();
+        let record = format!("{}{}", COMPRESSED_PREFIX, base64::encode(compressed));
+
+        assert_eq!(
+            decompress_record(&record),
+            Err(ContentError::DecompressedTooLarge {
+                limit: MAX_DECOMPRESSED_RECORD_BYTES
+            })
+        );
+        assert_eq!(
+            decompress_record_with_limit(&record, MAX_DECOMPRESSED_RECORD_BYTES + 1).unwrap(),
+            String::from_utf8(bomb).unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_malformed() {
+        let record = format!("{}not base64!", COMPRESSED_PREFIX);
+        assert!(matches!(
+            decompress_record(&record),
+            Err(ContentError::Rule(_))
+        ));
+
+        let record = format!("{}{}", COMPRESSED_PREFIX, base64::encode(b"not zstd"));
+        assert!(matches!(
+            decompress_record(&record),
+            Err(ContentError::Rule(_))
+        ));
+    }
+
+    #[test]
+    fn legacy_plain_record() {
+        let record = large_json();
+        let content = JellyfishTransactionContent::insert(record.as_str());
+
+        assert_eq!(content.record(), Some(record.as_str()));
+        assert!(matches!(
+            content.record_decompressed(),
+            Ok(Some(Cow::Borrowed(r))) if r == record
+        ));
+    }
+}
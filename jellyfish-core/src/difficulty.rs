@@ -1,31 +1,308 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-use crate::{byteorder::ByteOrder, Sha256Digest};
+use crate::block::Header;
+use crate::byteorder::ByteOrder;
+use crate::digest::{Digest, Hasher};
+
+/// The easiest difficulty, which every digest satisfies.
+pub const MIN_DIFFICULTY: Difficulty = Difficulty::new(0);
+
+/// The hardest difficulty, since a SHA256 digest has at most 256 leading zero bits.
+pub const MAX_DIFFICULTY: Difficulty = Difficulty(MAX_BITS);
 
-/// Difficulty to find a new block.
+/// Number of bits of a SHA256 digest, which bounds [`Difficulty`].
+const MAX_BITS: u64 = 256;
+
+/// Largest exponent of compact targets, with which the mantissa fills all 32 bytes.
+const MAX_COMPACT_EXPONENT: usize = 32;
+
+/// Kind of Proof-of-Work condition of headers, which is given by [`ChainParams::pow_mode`](crate::params::ChainParams::pow_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowMode {
+    /// Digests must have leading zero bits of [`Difficulty`], so that the work is doubled or halved by each step.
+    #[default]
+    ZeroBits,
+    /// Digests must not exceed [`Target`], which headers contain in compact form, so that the work changes smoothly.
+    Target,
+}
+
+/// Difficulty to find a new block, which is from [`MIN_DIFFICULTY`] to [`MAX_DIFFICULTY`].
+///
+/// Deserialization rejects values out of the range.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "u64")]
 pub struct Difficulty(u64);
 
 impl Difficulty {
+    /// # Panics
+    /// If the difficulty exceeds [`MAX_DIFFICULTY`]. Use [`Difficulty::try_new()`] for untrusted values.
     pub const fn new(difficulty: u64) -> Self {
-        Self(difficulty)
+        match Self::try_new(difficulty) {
+            Ok(difficulty) => difficulty,
+            Err(_) => panic!("Difficulty exceeds the maximum."),
+        }
     }
 
-    /// Returns more difficult condition by 1 step.
+    /// Returns the difficulty, or `Err(DifficultyError::OutOfRange)` if it exceeds [`MAX_DIFFICULTY`].
+    pub const fn try_new(difficulty: u64) -> Result<Self, DifficultyError> {
+        if difficulty > MAX_BITS {
+            Err(DifficultyError::OutOfRange(difficulty))
+        } else {
+            Ok(Self(difficulty))
+        }
+    }
+
+    /// Returns the number of leading zero bits which digests must have.
+    pub const fn bits(&self) -> u32 {
+        // Difficulty never exceeds 256, so the cast never truncates.
+        self.0 as u32
+    }
+
+    /// Same as [`Difficulty::bits()`] in `u64`.
+    pub const fn zero_bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns more difficult condition by 1 step, which saturates at [`MAX_DIFFICULTY`].
     pub fn raise(&self) -> Self {
-        Self(self.0 + 1)
+        self.checked_raise().unwrap_or(MAX_DIFFICULTY)
     }
 
-    /// Returns easer condition by 1 step, but the returned value never be negative.
+    /// Returns more difficult condition by 1 step, or `None` if the difficulty is already [`MAX_DIFFICULTY`].
+    pub fn checked_raise(&self) -> Option<Self> {
+        Self::try_new(self.0 + 1).ok()
+    }
+
+    /// Returns easier condition by 1 step, which saturates at [`MIN_DIFFICULTY`].
+    ///
+    /// Same as [`Difficulty::saturating_ease()`].
     pub fn ease(&self) -> Self {
-        let inner = self.0.checked_sub(1).unwrap_or(0);
-        Self(inner)
+        self.saturating_ease()
+    }
+
+    /// Returns easier condition by 1 step, or `None` if the difficulty is already [`MIN_DIFFICULTY`].
+    pub fn checked_ease(&self) -> Option<Self> {
+        self.0.checked_sub(1).map(Self)
+    }
+
+    /// Returns easier condition by 1 step, which saturates at [`MIN_DIFFICULTY`].
+    pub fn saturating_ease(&self) -> Self {
+        self.checked_ease().unwrap_or(MIN_DIFFICULTY)
+    }
+
+    /// Returns expected number of attempts to find a digest satisfying the difficulty, i.e., `2^bits`.
+    ///
+    /// Work of 128 bits or more saturates at `u128::MAX`, so such difficulties are not distinguished by their work.
+    pub fn work(&self) -> u128 {
+        1u128.checked_shl(self.bits()).unwrap_or(u128::MAX)
+    }
+
+    /// Returns difficulty of the block following `recent_headers`, which are in ascending order of height,
+    /// adjusted by at most 1 step from that of the latest header as [`Difficulty::adjust()`].
+    ///
+    /// The last `window` headers are compared with the intended interval, or all of them if fewer are given,
+    /// e.g., because the window spans the genesis block.
+    /// Time between them is zero if the latest header is not later than the oldest, since timestamps of blocks are not monotonic.
+    /// Without headers, this returns [`MIN_DIFFICULTY`], and with a single header, its difficulty.
+    ///
+    /// Chains with [`ChainParams`](crate::params::ChainParams) retarget only at boundaries by [`ChainParams::retarget()`](crate::params::ChainParams::retarget),
+    /// and reject blocks declaring other difficulties.
+    pub fn retarget(
+        recent_headers: &[Header],
+        target_block_interval: Duration,
+        window: usize,
+    ) -> Difficulty {
+        let start = recent_headers.len().saturating_sub(window.max(1));
+        let headers = &recent_headers[start..];
+        let (first, last) = match (headers.first(), headers.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return MIN_DIFFICULTY,
+        };
+
+        let intervals = u32::try_from(headers.len() - 1).unwrap_or(u32::MAX);
+        let expected = target_block_interval.saturating_mul(intervals);
+        let actual = last
+            .timestamp()
+            .duration_since(first.timestamp())
+            .unwrap_or_default();
+        last.difficulty().adjust(expected, actual, 1)
+    }
+
+    /// Returns the difficulty adjusted by comparing `actual` time to find blocks with `expected` one.
+    ///
+    /// Difficulty is raised by a step while blocks were found in half or less of the expected time, and eased likewise,
+    /// since each step doubles the expected work. The change is limited to `max_steps`,
+    /// and the result is within [`MIN_DIFFICULTY`] and [`MAX_DIFFICULTY`].
+    /// Zero `expected` time keeps the difficulty.
+    pub fn adjust(&self, expected: Duration, actual: Duration, max_steps: u64) -> Difficulty {
+        let mut difficulty = *self;
+        let mut expected = expected.as_nanos();
+        let mut actual = actual.as_nanos();
+
+        if expected > 0 {
+            for _ in 0..max_steps {
+                if actual.saturating_mul(2) <= expected {
+                    difficulty = difficulty.raise();
+                    actual = actual.saturating_mul(2);
+                } else if expected.saturating_mul(2) <= actual {
+                    difficulty = difficulty.ease();
+                    expected = expected.saturating_mul(2);
+                } else {
+                    break;
+                }
+            }
+        }
+        difficulty
     }
 
     /// Checks whether the given digest satisfies the difficulty.
-    pub fn verify_digest(&self, digest: &Sha256Digest) -> bool {
-        let count = count_first_0_bits(digest.as_ref());
-        count >= self.0
+    pub fn verify_digest(&self, digest: &Digest) -> bool {
+        digest.leading_zero_bits() >= self.bits()
+    }
+
+    /// Returns the largest target which digests satisfying the difficulty do not exceed, i.e., `2^(256 - bits) - 1`.
+    ///
+    /// A digest satisfies the difficulty if and only if it satisfies the target.
+    pub fn to_target(&self) -> Target {
+        let mut bytes = [0xff; 32];
+        let zero_bytes = self.0 as usize / 8;
+        bytes[..zero_bytes].fill(0);
+        if let Some(byte) = bytes.get_mut(zero_bytes) {
+            *byte = 0xff >> (self.0 % 8);
+        }
+        Target(bytes)
+    }
+}
+
+/// 256-bit threshold of Proof-of-Work in big endian, which digests must not exceed numerically.
+///
+/// Unlike [`Difficulty`], the expected work is inversely proportional to the target,
+/// so that it can be adjusted by any ratio. Headers contain the target in compact form given by [`Target::to_compact()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Target([u8; 32]);
+
+impl Target {
+    /// The easiest target, which every digest satisfies.
+    pub const MAX: Target = Target([0xff; 32]);
+
+    /// The hardest target, which only [`Digest::ZERO`] satisfies.
+    pub const ZERO: Target = Target([0; 32]);
+
+    pub const fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Checks whether the given digest does not exceed the target as a big-endian number.
+    pub fn verify_digest(&self, digest: &Digest) -> bool {
+        // Arrays are compared lexicographically, which is the numeric order of big-endian numbers.
+        digest.as_bytes() <= &self.0
+    }
+
+    /// Returns the hardest difficulty which every digest satisfying the target satisfies,
+    /// i.e., the number of leading zero bits of the target.
+    pub fn to_difficulty_bits(&self) -> Difficulty {
+        let bits = Digest::from_bytes(self.0).leading_zero_bits();
+        Difficulty(u64::from(bits))
+    }
+
+    /// Returns expected number of attempts to find a digest satisfying the target, i.e., `2^256 / (target + 1)`,
+    /// which is calculated from the 128 most significant bits of the target.
+    ///
+    /// Same as [`Difficulty::work()`] of the difficulty given by [`Target::to_difficulty_bits()`], if the target is given by [`Difficulty::to_target()`].
+    /// Targets below `2^128` saturate at `u128::MAX`.
+    pub fn work(&self) -> u128 {
+        let mut high = [0; 16];
+        high.copy_from_slice(&self.0[..16]);
+        let high = u128::from_be_bytes(high);
+        match high.checked_add(1) {
+            // The target is below 2^128, whose work is 2^128 or more.
+            _ if high == 0 => u128::MAX,
+            // floor(2^128 / (high + 1)), without overflow of 2^128.
+            Some(divisor) => !high / divisor + 1,
+            // The target is Target::MAX or truncated one.
+            None => 1,
+        }
+    }
+
+    /// Encodes the target into 32 bits as Bitcoin's `nBits`,
+    /// whose highest byte is the number of significant bytes and the rest is the 3 most significant of them.
+    ///
+    /// Lower bytes are truncated, so the decoded target may be less than the original one.
+    pub fn to_compact(&self) -> u32 {
+        let first = match self.0.iter().position(|&byte| byte != 0) {
+            Some(first) => first,
+            None => return 0,
+        };
+        let mut exponent = (self.0.len() - first) as u32;
+        let mut mantissa = (0..3).fold(0u32, |mantissa, i| {
+            let byte = self.0.get(first + i).copied().unwrap_or(0);
+            (mantissa << 8) | u32::from(byte)
+        });
+        // The highest bit of the mantissa is a sign, so the mantissa is shifted into the next byte.
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            exponent += 1;
+        }
+        (exponent << 24) | mantissa
+    }
+
+    /// Returns the target which [`Target::to_compact()`] of the target decodes to, i.e., the target in the precision of headers.
+    pub fn truncated(&self) -> Self {
+        Self::from_compact(self.to_compact()).expect("Compact form of a target must be valid.")
+    }
+
+    /// Decodes the target from [`Target::to_compact()`].
+    ///
+    /// # Returns
+    /// `Err(DifficultyError::NegativeCompact)` if the sign bit is set, or
+    /// `Err(DifficultyError::CompactOverflow)` if the target exceeds 256 bits.
+    pub fn from_compact(compact: u32) -> Result<Self, DifficultyError> {
+        let exponent = (compact >> 24) as usize;
+        let mantissa = compact & 0x007f_ffff;
+        if mantissa == 0 {
+            return Ok(Self::ZERO);
+        }
+        if compact & 0x0080_0000 != 0 {
+            return Err(DifficultyError::NegativeCompact(compact));
+        }
+
+        let mut bytes = [0; 32];
+        for (i, byte) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+            // Index of the byte in the target, which is negative if the byte is above 256 bits.
+            let index = i as isize + (MAX_COMPACT_EXPONENT as isize - exponent as isize);
+            match usize::try_from(index) {
+                Ok(index) if index < bytes.len() => bytes[index] = *byte,
+                // Bytes below the lowest one are truncated, as a right shift.
+                Ok(_) => {}
+                Err(_) if *byte == 0 => {}
+                Err(_) => return Err(DifficultyError::CompactOverflow(compact)),
+            }
+        }
+        Ok(Self(bytes))
+    }
+}
+
+/// Formats the target as lowercase hex of its 32 bytes, as [`Digest`].
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl TryFrom<u64> for Difficulty {
+    type Error = DifficultyError;
+
+    fn try_from(difficulty: u64) -> Result<Self, Self::Error> {
+        Self::try_new(difficulty)
     }
 }
 
@@ -33,51 +310,369 @@ impl ByteOrder for Difficulty {
     fn append_bytes(&self, buf: &mut Vec<u8>) {
         buf.extend(self.0.to_le_bytes());
     }
-}
 
-fn count_first_0_bits(bytes: &[u8]) -> u64 {
-    let mut count = 0;
+    fn append_to_hasher(&self, hasher: &mut Hasher) {
+        hasher.update(&self.0.to_le_bytes());
+    }
 
-    for &byte in bytes {
-        match count_first_0_bit(byte) {
-            8 => count += 8,
-            c => {
-                count += c;
-                break;
-            }
-        }
+    fn byte_len(&self) -> usize {
+        std::mem::size_of::<u64>()
     }
+}
 
-    count
+/// Difficulty is out of the valid range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifficultyError {
+    /// Number of zero bits exceeds [`MAX_DIFFICULTY`].
+    OutOfRange(u64),
+    /// Compact target has the sign bit.
+    NegativeCompact(u32),
+    /// Compact target exceeds 256 bits.
+    CompactOverflow(u32),
 }
 
-fn count_first_0_bit(x: u8) -> u64 {
-    let mut count = 0;
-    let mut flag = 1 << (8 - 1);
-    while flag > 0 {
-        if x & flag == 0 {
-            count += 1;
-            flag >>= 1;
-        } else {
-            break;
+impl Display for DifficultyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use DifficultyError::*;
+
+        match self {
+            OutOfRange(difficulty) => write!(
+                f,
+                "Difficulty {} exceeds the maximum {}.",
+                difficulty,
+                MAX_DIFFICULTY.zero_bits()
+            ),
+            NegativeCompact(compact) => write!(f, "Compact target {:#010x} is negative.", compact),
+            CompactOverflow(compact) => {
+                write!(f, "Compact target {:#010x} exceeds 256 bits.", compact)
+            }
         }
     }
-    count
 }
 
+impl Error for DifficultyError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chain::tests_stab::*;
+    use crate::{SecretAccount, Timestamp, Transaction};
+
+    #[test]
+    fn try_new() {
+        assert_eq!(Difficulty::try_new(0), Ok(MIN_DIFFICULTY));
+        assert_eq!(Difficulty::try_new(256), Ok(MAX_DIFFICULTY));
+        assert_eq!(
+            Difficulty::try_new(257),
+            Err(DifficultyError::OutOfRange(257))
+        );
+        assert_eq!(
+            Difficulty::try_new(u64::MAX),
+            Err(DifficultyError::OutOfRange(u64::MAX))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Difficulty exceeds the maximum.")]
+    fn new_out_of_range() {
+        Difficulty::new(257);
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(Difficulty(0).bits(), 0);
+        assert_eq!(Difficulty(42).bits(), 42);
+        assert_eq!(MAX_DIFFICULTY.bits(), 256);
+    }
 
     #[test]
     fn raise() {
         assert_eq!(Difficulty(101), Difficulty(100).raise());
+        assert_eq!(Difficulty(256), Difficulty(255).raise());
+        assert_eq!(MAX_DIFFICULTY, MAX_DIFFICULTY.raise());
+    }
+
+    #[test]
+    fn checked_raise() {
+        assert_eq!(Some(Difficulty(1)), MIN_DIFFICULTY.checked_raise());
+        assert_eq!(Some(MAX_DIFFICULTY), Difficulty(255).checked_raise());
+        assert_eq!(None, MAX_DIFFICULTY.checked_raise());
     }
 
     #[test]
     fn ease() {
         assert_eq!(Difficulty(99), Difficulty(100).ease());
-        assert_eq!(Difficulty(0), Difficulty(0).ease());
+        assert_eq!(Difficulty(0), Difficulty(1).ease());
+        assert_eq!(MIN_DIFFICULTY, MIN_DIFFICULTY.ease());
+        assert_eq!(Difficulty(255), MAX_DIFFICULTY.ease());
+    }
+
+    #[test]
+    fn checked_ease() {
+        assert_eq!(Some(Difficulty(99)), Difficulty(100).checked_ease());
+        assert_eq!(Some(MIN_DIFFICULTY), Difficulty(1).checked_ease());
+        assert_eq!(None, MIN_DIFFICULTY.checked_ease());
+    }
+
+    #[test]
+    fn saturating_ease() {
+        assert_eq!(Difficulty(99), Difficulty(100).saturating_ease());
+        assert_eq!(MIN_DIFFICULTY, MIN_DIFFICULTY.saturating_ease());
+    }
+
+    /// Headers of blocks found at the given seconds, whose difficulties are the given one.
+    fn headers(secs: &[u64], difficulty: Difficulty) -> Vec<Header> {
+        let account = SecretAccount::from_seed([0; 32]);
+        secs.iter()
+            .zip(0..)
+            .map(|(&secs, height)| {
+                let timestamp = Timestamp::EPOCH + Duration::from_secs(secs);
+                let transactions = vec![Transaction::create(&account, timestamp, Stab(height))];
+                Header::create(
+                    height,
+                    timestamp,
+                    Digest::ZERO,
+                    difficulty,
+                    &transactions,
+                    0,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adjust() {
+        let d = Difficulty(10);
+        let minute = Duration::from_secs(60);
+
+        assert_eq!(d.adjust(minute, minute, 4), d);
+        assert_eq!(d.adjust(minute, minute * 2, 4), Difficulty(9));
+        assert_eq!(d.adjust(minute * 2, minute, 4), Difficulty(11));
+        assert_eq!(d.adjust(minute * 8, minute, 4), Difficulty(13));
+        assert_eq!(d.adjust(minute * 8, minute, 1), Difficulty(11));
+        assert_eq!(d.adjust(minute, minute * 100, 4), Difficulty(6));
+        assert_eq!(d.adjust(minute, Duration::ZERO, 2), Difficulty(12));
+        assert_eq!(d.adjust(Duration::ZERO, Duration::ZERO, 4), d);
+
+        assert_eq!(
+            MAX_DIFFICULTY.adjust(minute, Duration::ZERO, 4),
+            MAX_DIFFICULTY
+        );
+        assert_eq!(
+            MIN_DIFFICULTY.adjust(minute, minute * 100, 4),
+            MIN_DIFFICULTY
+        );
+    }
+
+    #[test]
+    fn retarget() {
+        let interval = Duration::from_secs(60);
+        let d = Difficulty(10);
+
+        // On time.
+        let on_time = headers(&[0, 60, 120, 180], d);
+        assert_eq!(Difficulty::retarget(&on_time, interval, 4), d);
+
+        // Twice as fast, which raises by only 1 step even if much faster.
+        let fast = headers(&[0, 30, 60, 90], d);
+        assert_eq!(Difficulty::retarget(&fast, interval, 4), Difficulty(11));
+        let faster = headers(&[0, 1, 2, 3], d);
+        assert_eq!(Difficulty::retarget(&faster, interval, 4), Difficulty(11));
+
+        // Twice as slow.
+        let slow = headers(&[0, 120, 240, 360], d);
+        assert_eq!(Difficulty::retarget(&slow, interval, 4), Difficulty(9));
+
+        // Only the last headers of the window are compared.
+        let recovered = headers(&[0, 1000, 1060, 1120], d);
+        assert_eq!(Difficulty::retarget(&recovered, interval, 3), d);
+        assert_eq!(Difficulty::retarget(&recovered, interval, 4), Difficulty(9));
+    }
+
+    #[test]
+    fn retarget_short_history() {
+        let interval = Duration::from_secs(60);
+        let d = Difficulty(10);
+
+        assert_eq!(Difficulty::retarget(&[], interval, 4), MIN_DIFFICULTY);
+
+        // A single header, e.g., the genesis block, has no interval.
+        let genesis = headers(&[0], d);
+        assert_eq!(Difficulty::retarget(&genesis, interval, 4), d);
+        assert_eq!(Difficulty::retarget(&genesis, interval, 0), d);
+
+        // Window spanning the genesis block uses all of the headers.
+        let fast = headers(&[0, 30, 60], d);
+        assert_eq!(fast[0].height(), 0);
+        assert_eq!(Difficulty::retarget(&fast, interval, 100), Difficulty(11));
+    }
+
+    #[test]
+    fn retarget_non_monotonic() {
+        let interval = Duration::from_secs(60);
+        let d = Difficulty(10);
+
+        // The latest header is earlier than the oldest, which counts as zero time.
+        let backward = headers(&[1000, 1060, 1120, 900], d);
+        assert_eq!(Difficulty::retarget(&backward, interval, 4), Difficulty(11));
+
+        // Only the oldest and latest timestamps matter.
+        let shuffled = headers(&[0, 150, 30, 180], d);
+        assert_eq!(Difficulty::retarget(&shuffled, interval, 4), d);
+    }
+
+    #[test]
+    fn work() {
+        assert_eq!(MIN_DIFFICULTY.work(), 1);
+        assert_eq!(Difficulty(10).work(), 1024);
+        assert_eq!(Difficulty(127).work(), 1 << 127);
+        // Saturated.
+        assert_eq!(Difficulty(128).work(), u128::MAX);
+        assert_eq!(MAX_DIFFICULTY.work(), u128::MAX);
+    }
+
+    #[test]
+    fn target_work() {
+        for bits in 0..=256 {
+            let difficulty = Difficulty(bits);
+            assert_eq!(difficulty.to_target().work(), difficulty.work());
+        }
+        assert_eq!(Target::MAX.work(), 1);
+        assert_eq!(Target::ZERO.work(), u128::MAX);
+
+        // Truncation of the compact form lowers the target, so the work slightly increases.
+        assert_eq!(Difficulty(20).to_target().truncated().work(), (1 << 20) + 1);
+
+        // Work changes smoothly, unlike difficulties, e.g., 2^256 / (2^254 + 1) and 2^256 / (3 * 2^252 + 1).
+        assert_eq!(Target::from_compact(0x20400000).unwrap().work(), 3);
+        assert_eq!(Target::from_compact(0x20300000).unwrap().work(), 5);
+        assert_eq!(Target::from_compact(0x20600000).unwrap().work(), 2);
+    }
+
+    #[test]
+    fn to_target() {
+        assert_eq!(MIN_DIFFICULTY.to_target(), Target::MAX);
+        assert_eq!(MAX_DIFFICULTY.to_target(), Target::ZERO);
+
+        let target = Difficulty(9).to_target();
+        assert_eq!(&target.as_bytes()[..3], &[0x00, 0x7f, 0xff]);
+        assert!(target.as_bytes()[3..].iter().all(|&byte| byte == 0xff));
+
+        for bits in 0..=256 {
+            let difficulty = Difficulty(bits);
+            assert_eq!(difficulty.to_target().to_difficulty_bits(), difficulty);
+        }
+    }
+
+    #[test]
+    fn target_agrees_with_difficulty() {
+        let digests = [[0x00; 32], [0x01; 32], [0x0f; 32], [0x7f; 32], [0xff; 32]];
+        for bits in 0..=256 {
+            let difficulty = Difficulty(bits);
+            for digest in digests {
+                let digest = Digest::from_bytes(digest);
+                assert_eq!(
+                    difficulty.verify_digest(&digest),
+                    difficulty.to_target().verify_digest(&digest)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn target_verify_boundary() {
+        let target = Target::from_compact(0x1b0404cb).unwrap();
+
+        // Exactly at the target.
+        let at = Digest::from_bytes(*target.as_bytes());
+        assert!(target.verify_digest(&at));
+
+        // One above the target.
+        let mut above = *target.as_bytes();
+        let last = above.iter().rposition(|&byte| byte != 0xff).unwrap();
+        above[last] += 1;
+        above[last + 1..].fill(0);
+        assert!(!target.verify_digest(&Digest::from_bytes(above)));
+
+        // One below the target.
+        let mut below = *target.as_bytes();
+        let last = below.iter().rposition(|&byte| byte != 0).unwrap();
+        below[last] -= 1;
+        below[last + 1..].fill(0xff);
+        assert!(target.verify_digest(&Digest::from_bytes(below)));
+
+        assert!(Target::MAX.verify_digest(&Digest::from_bytes([0xff; 32])));
+        assert!(Target::ZERO.verify_digest(&Digest::ZERO));
+        assert!(!Target::ZERO.verify_digest(&Digest::from_bytes([0xff; 32])));
+    }
+
+    #[test]
+    fn compact_vectors() {
+        // Compact targets and their significant bytes from the lowest byte of the 4th most significant 8 bytes.
+        let vectors: [(u32, &[u8], usize); 6] = [
+            (0x1d00ffff, &[0xff, 0xff], 4),
+            (0x1b0404cb, &[0x04, 0x04, 0xcb], 5),
+            (0x01120000, &[0x12], 31),
+            (0x02123400, &[0x12, 0x34], 30),
+            (0x05009234, &[0x92, 0x34], 28),
+            (0x20123456, &[0x12, 0x34, 0x56], 0),
+        ];
+        for (compact, significant, offset) in vectors {
+            let mut bytes = [0; 32];
+            bytes[offset..offset + significant.len()].copy_from_slice(significant);
+
+            let target = Target::from_compact(compact).unwrap();
+            assert_eq!(target, Target::from_bytes(bytes));
+            assert_eq!(target.to_compact(), compact);
+        }
+    }
+
+    #[test]
+    fn compact_truncated() {
+        // Bytes below 256 bits are truncated.
+        assert_eq!(Target::from_compact(0x01003456).unwrap(), Target::ZERO);
+        assert_eq!(
+            Target::from_compact(0x01123456).unwrap().to_compact(),
+            0x01120000
+        );
+        assert_eq!(Target::from_compact(0).unwrap(), Target::ZERO);
+        assert_eq!(Target::ZERO.to_compact(), 0);
+        // Negative zero is zero.
+        assert_eq!(Target::from_compact(0x01800000).unwrap(), Target::ZERO);
+
+        // Bytes below the 3 most significant ones are lost.
+        let max = Target::MAX.truncated();
+        assert_eq!(Target::MAX.to_compact(), 0x2100ffff);
+        assert_eq!(&max.as_bytes()[..3], &[0xff, 0xff, 0x00]);
+        assert!(max < Target::MAX);
+    }
+
+    #[test]
+    fn compact_fail() {
+        assert_eq!(
+            Target::from_compact(0x04923456),
+            Err(DifficultyError::NegativeCompact(0x04923456))
+        );
+        assert_eq!(
+            Target::from_compact(0x21010000),
+            Err(DifficultyError::CompactOverflow(0x21010000))
+        );
+        assert_eq!(
+            Target::from_compact(0xff123456),
+            Err(DifficultyError::CompactOverflow(0xff123456))
+        );
+        assert!(Target::from_compact(0x2100ffff).is_ok());
+        assert!(Target::from_compact(0x22000001).is_ok());
+    }
+
+    #[test]
+    fn deserialize_out_of_range() {
+        assert_eq!(
+            serde_json::from_str::<Difficulty>("256").unwrap(),
+            MAX_DIFFICULTY
+        );
+        assert!(serde_json::from_str::<Difficulty>("257").is_err());
     }
 
     #[test]
@@ -96,6 +691,8 @@ mod tests {
             255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
         ];
 
+        let digest = Digest::from_bytes(digest);
+
         assert!(Difficulty(8).verify_digest(&digest));
         assert!(!Difficulty(9).verify_digest(&digest));
     }
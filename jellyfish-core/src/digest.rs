@@ -1,16 +1,419 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter, LowerHex, UpperHex};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sha2::{Digest as _, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::hex0x;
+use crate::{ByteOrder, Difficulty};
 
-/// SHA256 digest.
+/// Raw bytes of SHA256 digest.
+///
+/// APIs take and return [`Digest`]. This remains for merkle tree leaves and conversion from raw bytes.
 pub type Sha256Digest = [u8; 32];
 
+/// Compares digests in constant time.
+///
+/// `==` stops at the first differing byte, so its running time tells how many leading bytes match.
+/// Verification paths compare digests given by others with expected ones by this function,
+/// so that they do not leak the expected digest through timing.
+/// Other code, e.g., lookups and deduplication, may keep using `==`.
+pub fn ct_eq(a: &Sha256Digest, b: &Sha256Digest) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[deprecated(note = "Use Digest::create() instead.")]
 pub fn calculate_digest<T: AsRef<[u8]> + ?Sized>(msg: &T) -> Sha256Digest {
-    let mut hasher = Sha256::new();
-    hasher.update(msg);
-    hasher.finalize().into()
+    Digest::create(msg).to_bytes()
+}
+
+/// Length of [`Digest::short()`] in hex chars.
+pub const SHORT_HEX_LENGTH: usize = 8;
+
+/// Default number of hex chars which [`Abbrev`] shows before the ellipsis.
+pub const ABBREV_PREFIX_LENGTH: usize = 8;
+
+/// Default number of hex chars which [`Abbrev`] shows after the ellipsis.
+pub const ABBREV_SUFFIX_LENGTH: usize = 6;
+
+/// SHA256 digest, which is formatted as 64 lowercase hex chars.
+///
+/// Serialized form is the hex in human-readable formats such as JSON, and raw 32 bytes in binary formats such as bincode.
+///
+/// `==` is for general use, e.g., lookups and deduplication. Verification uses [`Digest::ct_eq()`] instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Digest(
+    #[serde(serialize_with = "serialize_digest")]
+    #[serde(deserialize_with = "deserialize_digest")]
+    Sha256Digest,
+);
+
+impl Digest {
+    /// All-zero digest, which genesis blocks have as their previous digest.
+    pub const ZERO: Self = Self([0; 32]);
+
+    /// Calculates SHA256 digest of the message.
+    pub fn create<T: AsRef<[u8]> + ?Sized>(msg: &T) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(msg);
+        Self(hasher.finalize().into())
+    }
+
+    /// Calculates SHA256 of SHA256 of the message, as Bitcoin-style tooling does.
+    pub fn create_double<T: AsRef<[u8]> + ?Sized>(msg: &T) -> Self {
+        Self::create(Self::create(msg).as_bytes())
+    }
+
+    /// Wraps raw bytes of a digest which was calculated elsewhere.
+    pub const fn from_bytes(bytes: Sha256Digest) -> Self {
+        Self(bytes)
+    }
+
+    /// Calculates digest of the byte order without building it into a buffer, same as digest of [`ByteOrder::build_byte_order()`].
+    pub fn of_byte_order<T: ByteOrder>(value: &T) -> Self {
+        let mut hasher = Hasher::new();
+        value.append_to_hasher(&mut hasher);
+        hasher.finalize()
+    }
+
+    /// Returns whether every byte is zero, as [`Digest::ZERO`].
+    pub fn is_zero(&self) -> bool {
+        self == &Self::ZERO
+    }
+
+    pub fn as_bytes(&self) -> &Sha256Digest {
+        &self.0
+    }
+
+    /// Compares digests in constant time, as [`ct_eq()`] does.
+    pub fn ct_eq(&self, other: &Digest) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+
+    /// Returns a copy of [`Digest::as_bytes()`].
+    pub fn to_bytes(&self) -> Sha256Digest {
+        self.0
+    }
+
+    /// Returns the number of leading zero bits, which Proof-of-Work requires at least [`Difficulty`] of.
+    pub fn leading_zero_bits(&self) -> u32 {
+        let mut count = 0;
+        for word in self.0.chunks_exact(8) {
+            let word = u64::from_be_bytes(word.try_into().unwrap());
+            count += word.leading_zeros();
+            if word != 0 {
+                break;
+            }
+        }
+        count
+    }
+
+    /// Returns bitwise XOR of the digests, which is the distance between them as a big-endian number.
+    pub fn xor(&self, other: &Digest) -> Digest {
+        let mut bytes = self.0;
+        for (byte, other) in bytes.iter_mut().zip(other.0) {
+            *byte ^= other;
+        }
+        Self(bytes)
+    }
+
+    /// Returns the position of the highest differing bit counted from the lowest bit starting at 1,
+    /// e.g., 0 for the same digests, 1 for digests differing only in the last bit, and 256 for ones differing in the first bit.
+    ///
+    /// This is the bucket index of Kademlia-like routing.
+    pub fn distance_bits(&self, other: &Digest) -> u32 {
+        256 - self.xor(other).leading_zero_bits()
+    }
+
+    /// Compares distances from the digest to `a` and to `b`, which sorts digests from the closest.
+    pub fn cmp_distance(&self, a: &Digest, b: &Digest) -> Ordering {
+        self.xor(a).cmp(&self.xor(b))
+    }
+
+    /// Returns whether the digest is strictly closer to `target` than `other` is.
+    pub fn closer_to(&self, target: &Digest, other: &Digest) -> bool {
+        target.cmp_distance(self, other) == Ordering::Less
+    }
+
+    /// Same as [`Difficulty::verify_digest()`].
+    pub fn meets_difficulty(&self, difficulty: Difficulty) -> bool {
+        difficulty.verify_digest(self)
+    }
+
+    /// Returns an adaptor which displays the first and last hex chars, e.g., `ba7816bf…0015ad`, for logs and UIs.
+    pub fn abbrev(&self) -> Abbrev<'_> {
+        Abbrev {
+            digest: self,
+            prefix: ABBREV_PREFIX_LENGTH,
+            suffix: ABBREV_SUFFIX_LENGTH,
+        }
+    }
+
+    /// Returns the first 8 hex chars for logs, which are NOT enough to identify the digest.
+    pub fn short(&self) -> String {
+        let mut hex = self.to_string();
+        hex.truncate(SHORT_HEX_LENGTH);
+        hex
+    }
+}
+
+/// Same as [`LowerHex`].
+impl Display for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        LowerHex::fmt(self, f)
+    }
+}
+
+impl LowerHex for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl UpperHex for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode_upper(self.0))
+    }
+}
+
+/// Parses lowercase or uppercase hex of 32 bytes, optionally with `0x` prefix, as deserialization does.
+impl FromStr for Digest {
+    type Err = ParseDigestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex0x::decode(s).map_err(ParseDigestError::Hex)?;
+        Self::try_from(bytes.as_slice())
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Digests can be looked up in maps and sets keyed by raw bytes, since they compare and hash as the bytes.
+impl Borrow<Sha256Digest> for Digest {
+    fn borrow(&self) -> &Sha256Digest {
+        &self.0
+    }
+}
+
+/// Same as [`Digest::from_bytes()`].
+impl From<Sha256Digest> for Digest {
+    fn from(bytes: Sha256Digest) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Digest> for Sha256Digest {
+    fn from(digest: Digest) -> Self {
+        digest.0
+    }
+}
+
+/// Decodes hex of 32 bytes without `0x` prefix, so that fields with `#[serde(with = "hex")]` can be digests.
+impl hex::FromHex for Digest {
+    type Error = hex::FromHexError;
+
+    fn from_hex<T: AsRef<[u8]>>(hex: T) -> Result<Self, Self::Error> {
+        Sha256Digest::from_hex(hex).map(Self)
+    }
+}
+
+/// Wraps bytes of exactly 32 bytes, e.g., read from a database.
+///
+/// # Returns
+/// `Err(ParseDigestError::Length)` if the bytes are shorter or longer.
+impl TryFrom<&[u8]> for Digest {
+    type Error = ParseDigestError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let digest = Sha256Digest::try_from(bytes).map_err(|_| ParseDigestError::Length {
+            expected: 32,
+            actual: bytes.len(),
+        })?;
+        Ok(Self(digest))
+    }
+}
+
+fn serialize_digest<S: Serializer>(bytes: &Sha256Digest, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        hex::serialize(bytes, serializer)
+    } else {
+        bytes.serialize(serializer)
+    }
+}
+
+fn deserialize_digest<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Sha256Digest, D::Error> {
+    if deserializer.is_human_readable() {
+        hex0x::deserialize(deserializer)
+    } else {
+        Sha256Digest::deserialize(deserializer)
+    }
+}
+
+/// How many times a message is hashed by SHA256, which is recorded in headers so that verification picks the same one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashMode {
+    /// SHA256 of the message, as [`Digest::create()`].
+    #[default]
+    Single,
+    /// SHA256 of SHA256 of the message, as [`Digest::create_double()`].
+    Double,
+}
+
+impl HashMode {
+    /// Returns the byte representing the mode in wire bytes of headers.
+    pub fn wire_byte(&self) -> u8 {
+        match self {
+            HashMode::Single => 0x00,
+            HashMode::Double => 0x01,
+        }
+    }
+
+    /// Calculates digest of the message in the mode.
+    pub fn digest<T: AsRef<[u8]> + ?Sized>(self, msg: &T) -> Digest {
+        self.finish(Digest::create(msg))
+    }
+
+    /// Returns digest in the mode from SHA256 of a message, e.g., given by [`Digest::of_byte_order()`] or [`Midstate`].
+    pub fn finish(self, single: Digest) -> Digest {
+        match self {
+            HashMode::Single => single,
+            HashMode::Double => Digest::create(single.as_bytes()),
+        }
+    }
+}
+
+/// Displays a digest as its first and last hex chars joined by `…`, which is given by [`Digest::abbrev()`].
+///
+/// The format is fixed so that log parsers can rely on it. Width and alignment of the formatter are applied.
+#[derive(Debug, Clone, Copy)]
+pub struct Abbrev<'a> {
+    digest: &'a Digest,
+    prefix: usize,
+    suffix: usize,
+}
+
+impl Abbrev<'_> {
+    /// Shows the given numbers of hex chars before and after the ellipsis.
+    /// The whole hex is shown if they cover it.
+    pub fn with_lengths(self, prefix: usize, suffix: usize) -> Self {
+        Self {
+            prefix,
+            suffix,
+            ..self
+        }
+    }
+}
+
+impl Display for Abbrev<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let hex = self.digest.to_string();
+        if self.prefix.saturating_add(self.suffix) >= hex.len() {
+            return f.pad(&hex);
+        }
+        let abbrev = format!(
+            "{}…{}",
+            &hex[..self.prefix],
+            &hex[hex.len() - self.suffix..]
+        );
+        f.pad(&abbrev)
+    }
+}
+
+/// Text is not a digest given by [`Digest`]'s [`Display`], or bytes are not 32 bytes.
+#[derive(Debug)]
+pub enum ParseDigestError {
+    Hex(hex::FromHexError),
+    Length { expected: usize, actual: usize },
+}
+
+impl Display for ParseDigestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDigestError::Hex(e) => write!(f, "Digest is not hex: {}", e),
+            ParseDigestError::Length { expected, actual } => write!(
+                f,
+                "Digest must be {} bytes, but {} bytes.",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for ParseDigestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseDigestError::Hex(e) => Some(e),
+            ParseDigestError::Length { .. } => None,
+        }
+    }
+}
+
+/// SHA256 which byte orders are written into directly by [`ByteOrder::append_to_hasher()`].
+#[derive(Clone, Default)]
+pub struct Hasher(Sha256);
+
+impl Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    pub fn finalize(self) -> Digest {
+        Digest(self.0.finalize().into())
+    }
+}
+
+impl Debug for Hasher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hasher").finish_non_exhaustive()
+    }
+}
+
+/// SHA256 state after absorbing a prefix.
+///
+/// Digests of messages sharing the prefix are calculated without absorbing the prefix again,
+/// e.g., headers differing only in nonce during Proof-of-Work process.
+#[derive(Clone)]
+pub struct Midstate(Sha256);
+
+impl Midstate {
+    pub fn from_prefix(prefix: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(prefix);
+        Self(hasher)
+    }
+
+    /// Returns digest of the prefix followed by `suffix`, which equals to [`Digest::create()`] of the concatenation.
+    pub fn finish_with(&self, suffix: &[u8]) -> Digest {
+        let mut hasher = self.0.clone();
+        hasher.update(suffix);
+        Digest(hasher.finalize().into())
+    }
+}
+
+impl Debug for Midstate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Midstate").finish_non_exhaustive()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use rand_core::RngCore;
     const DIGEST_SOURCE: &'static str = "abc";
     const DIGEST_BYTES: [u8; 32] = [
         186, 120, 22, 191, 143, 1, 207, 234, 65, 65, 64, 222, 93, 174, 34, 35, 176, 3, 97, 163,
@@ -18,8 +421,453 @@ mod tests {
     ];
 
     #[test]
+    fn create() {
+        let digest = super::Digest::create(DIGEST_SOURCE);
+        assert_eq!(digest.as_bytes(), &DIGEST_BYTES);
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn calculate_digest() {
         let digest = super::calculate_digest(DIGEST_SOURCE);
         assert_eq!(digest.as_ref(), DIGEST_BYTES);
     }
+
+    #[test]
+    fn midstate() {
+        let mut rng = rand_core::OsRng {};
+        let mut msg = [0; 200];
+        rng.fill_bytes(&mut msg);
+
+        // Every split, including ones at and around the block size of 64 bytes
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 127, 128, 129, 200] {
+            let msg = &msg[..len];
+            let expected = super::Digest::create(msg);
+            for split in 0..=len {
+                let midstate = super::Midstate::from_prefix(&msg[..split]);
+                assert_eq!(midstate.finish_with(&msg[split..]), expected);
+                // Midstate is reusable.
+                assert_eq!(midstate.finish_with(&msg[split..]), expected);
+            }
+        }
+
+        for _ in 0..1000 {
+            let len = (rng.next_u32() % 200) as usize;
+            let split = (rng.next_u32() as usize) % (len + 1);
+            let msg = &msg[..len];
+            let midstate = super::Midstate::from_prefix(&msg[..split]);
+            assert_eq!(
+                midstate.finish_with(&msg[split..]),
+                super::Digest::create(msg)
+            );
+        }
+    }
+
+    #[test]
+    fn ct_eq_agrees_with_eq() {
+        let mut rng = rand_core::OsRng {};
+        for _ in 0..1000 {
+            let mut a = [0; 32];
+            let mut b = [0; 32];
+            rng.fill_bytes(&mut a);
+            rng.fill_bytes(&mut b);
+            assert_eq!(super::ct_eq(&a, &b), a == b);
+            assert!(super::ct_eq(&a, &a));
+
+            // Differ only in a byte
+            let mut c = a;
+            c[(rng.next_u32() % 32) as usize] ^= 1 << (rng.next_u32() % 8);
+            assert_eq!(super::ct_eq(&a, &c), a == c);
+            assert!(!super::ct_eq(&a, &c));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_digest {
+    use rand_core::RngCore;
+
+    use super::*;
+
+    const DIGEST_HEX: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn display() {
+        let digest = Digest::create("abc");
+
+        assert_eq!(digest.to_string(), DIGEST_HEX);
+        assert_eq!(format!("{:x}", digest), DIGEST_HEX);
+        assert_eq!(format!("{:X}", digest), DIGEST_HEX.to_uppercase());
+        assert_eq!(digest.short(), "ba7816bf");
+        assert_eq!(hex::encode(digest.as_bytes()), DIGEST_HEX);
+    }
+
+    #[test]
+    fn abbrev() {
+        let digest = Digest::create("abc");
+
+        assert_eq!(digest.abbrev().to_string(), "ba7816bf…0015ad");
+        assert_eq!(digest.abbrev().with_lengths(4, 4).to_string(), "ba78…15ad");
+        assert_eq!(digest.abbrev().with_lengths(0, 2).to_string(), "…ad");
+        assert_eq!(digest.abbrev().with_lengths(2, 0).to_string(), "ba…");
+        // The whole hex if the lengths cover it.
+        assert_eq!(digest.abbrev().with_lengths(32, 32).to_string(), DIGEST_HEX);
+        assert_eq!(
+            digest.abbrev().with_lengths(usize::MAX, 1).to_string(),
+            DIGEST_HEX
+        );
+        assert_eq!(
+            digest.abbrev().with_lengths(32, 31).to_string(),
+            format!("{}…{}", &DIGEST_HEX[..32], &DIGEST_HEX[33..])
+        );
+
+        assert_eq!(format!("{:>17}|", digest.abbrev()), "  ba7816bf…0015ad|");
+        assert_eq!(format!("{:<16}|", digest.abbrev()), "ba7816bf…0015ad |");
+    }
+
+    #[test]
+    fn from_str() {
+        let digest = Digest::create("abc");
+
+        for hex in [
+            DIGEST_HEX.to_owned(),
+            DIGEST_HEX.to_uppercase(),
+            format!("0x{}", DIGEST_HEX),
+        ] {
+            assert_eq!(hex.parse::<Digest>().unwrap(), digest);
+        }
+    }
+
+    #[test]
+    fn from_str_fail() {
+        assert!(matches!(
+            "xyz".parse::<Digest>(),
+            Err(ParseDigestError::Hex(_))
+        ));
+        assert!(matches!(
+            DIGEST_HEX[2..].parse::<Digest>(),
+            Err(ParseDigestError::Length {
+                expected: 32,
+                actual: 31
+            })
+        ));
+        assert!(matches!(
+            format!("{}00", DIGEST_HEX).parse::<Digest>(),
+            Err(ParseDigestError::Length {
+                expected: 32,
+                actual: 33
+            })
+        ));
+    }
+
+    #[test]
+    fn create_double() {
+        let digest = Digest::create_double("abc");
+
+        assert_eq!(
+            digest.to_string(),
+            "4f8b42c22dd3729b519ba6f68d2da7cc5b2d606d05daed5ad5128cc03e6c6358"
+        );
+        assert_eq!(digest, Digest::create(Digest::create("abc").as_bytes()));
+        assert_eq!(HashMode::Double.digest("abc"), digest);
+        assert_eq!(HashMode::Single.digest("abc"), Digest::create("abc"));
+    }
+
+    /// Counts leading zero bits bit by bit, as Proof-of-Work originally did.
+    fn reference_leading_zero_bits(bytes: &[u8]) -> u32 {
+        let mut count = 0;
+        for &byte in bytes {
+            for i in (0..8).rev() {
+                if byte & (1 << i) != 0 {
+                    return count;
+                }
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn leading_zero_bits() {
+        assert_eq!(Digest::ZERO.leading_zero_bits(), 256);
+        assert_eq!(Digest::from_bytes([0xff; 32]).leading_zero_bits(), 0);
+
+        let mut bytes = [0xff; 32];
+        bytes[..8].fill(0);
+        bytes[8] = 0x01;
+        assert_eq!(Digest::from_bytes(bytes).leading_zero_bits(), 64 + 7);
+
+        let mut bytes = [0; 32];
+        bytes[31] = 0x80;
+        assert_eq!(Digest::from_bytes(bytes).leading_zero_bits(), 248);
+    }
+
+    #[test]
+    fn leading_zero_bits_agrees_with_reference() {
+        let mut rng = rand_core::OsRng {};
+        for _ in 0..10000 {
+            let mut bytes = [0; 32];
+            rng.fill_bytes(&mut bytes);
+            // Clear a random number of leading bits, so that every count is covered.
+            let zeros = (rng.next_u32() % 257) as usize;
+            for i in 0..zeros {
+                bytes[i / 8] &= !(0x80 >> (i % 8));
+            }
+
+            let digest = Digest::from_bytes(bytes);
+            let count = digest.leading_zero_bits();
+            assert_eq!(count, reference_leading_zero_bits(&bytes));
+            assert!(count as usize >= zeros);
+            assert!(digest.meets_difficulty(Difficulty::new(count as u64)));
+            if let Some(harder) = Difficulty::new(count as u64).checked_raise() {
+                assert!(!digest.meets_difficulty(harder));
+            }
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn verify_digest_agrees_with_reference(
+            mut bytes in proptest::prelude::any::<[u8; 32]>(),
+            zero_bytes in 0..=32usize,
+            bits in 0..=256u64,
+        ) {
+            // Random digests rarely begin with zeros, so clear some leading bytes to cover large counts.
+            bytes[..zero_bytes].fill(0);
+            let digest = Digest::from_bytes(bytes);
+            let reference = reference_leading_zero_bits(&bytes);
+
+            proptest::prop_assert_eq!(digest.leading_zero_bits(), reference);
+            proptest::prop_assert_eq!(
+                Difficulty::new(bits).verify_digest(&digest),
+                reference as u64 >= bits
+            );
+        }
+    }
+
+    #[test]
+    fn verify_digest_zero() {
+        assert_eq!(reference_leading_zero_bits(Digest::ZERO.as_bytes()), 256);
+        assert!(crate::difficulty::MAX_DIFFICULTY.verify_digest(&Digest::ZERO));
+        assert!(Difficulty::new(0).verify_digest(&Digest::from_bytes([0xff; 32])));
+        assert!(!Difficulty::new(1).verify_digest(&Digest::from_bytes([0xff; 32])));
+    }
+
+    #[test]
+    fn zero() {
+        assert!(Digest::ZERO.is_zero());
+        assert_eq!(Digest::ZERO, Digest::from_bytes([0; 32]));
+        assert!(!Digest::create("").is_zero());
+        assert!(
+            !Digest::from_bytes([[0; 31].as_slice(), &[1]].concat().try_into().unwrap()).is_zero()
+        );
+
+        let json = serde_json::to_string(&Digest::ZERO).unwrap();
+        assert_eq!(json, format!(r#""{}""#, "0".repeat(64)));
+        let zero = serde_json::from_str::<Digest>(&json).unwrap();
+        assert!(zero.is_zero());
+    }
+
+    /// Digest whose bits are all zero except the given one, which is counted from the first bit.
+    fn single_bit(bit: usize) -> Digest {
+        let mut bytes = [0; 32];
+        bytes[bit / 8] = 0x80 >> (bit % 8);
+        Digest::from_bytes(bytes)
+    }
+
+    #[test]
+    fn xor() {
+        let a = Digest::create("a");
+        let b = Digest::create("b");
+
+        assert!(a.xor(&a).is_zero());
+        assert_eq!(a.xor(&Digest::ZERO), a);
+        assert_eq!(a.xor(&b), b.xor(&a));
+        assert_eq!(a.xor(&b).xor(&b), a);
+    }
+
+    #[test]
+    fn distance_bits() {
+        let a = Digest::create("a");
+        assert_eq!(a.distance_bits(&a), 0);
+
+        for bit in 0..256 {
+            let expected = 256 - bit as u32;
+            assert_eq!(Digest::ZERO.distance_bits(&single_bit(bit)), expected);
+            assert_eq!(a.distance_bits(&a.xor(&single_bit(bit))), expected);
+        }
+        // Only the highest differing bit counts.
+        let mut bytes = [0; 32];
+        bytes[31] = 0xff;
+        assert_eq!(Digest::ZERO.distance_bits(&Digest::from_bytes(bytes)), 8);
+    }
+
+    #[test]
+    fn cmp_distance() {
+        let target = Digest::create("target");
+
+        assert_eq!(target.cmp_distance(&target, &target), Ordering::Equal);
+        for bit in 0..255 {
+            let near = target.xor(&single_bit(bit + 1));
+            let far = target.xor(&single_bit(bit));
+            assert_eq!(target.cmp_distance(&near, &far), Ordering::Less);
+            assert_eq!(target.cmp_distance(&far, &near), Ordering::Greater);
+            assert!(near.closer_to(&target, &far));
+            assert!(!far.closer_to(&target, &near));
+        }
+        // Differing only in the last bit is the closest except the target itself.
+        let last = target.xor(&single_bit(255));
+        assert!(target.closer_to(&target, &last));
+        assert!(!target.closer_to(&target, &target));
+
+        // Sorting by the distance agrees with the distance as a number.
+        let mut digests = (0..64u32)
+            .map(|i| Digest::create(&i.to_le_bytes()))
+            .collect::<Vec<_>>();
+        digests.sort_by(|a, b| target.cmp_distance(a, b));
+        for pair in digests.windows(2) {
+            assert!(target.xor(&pair[0]) < target.xor(&pair[1]));
+            assert!(target.distance_bits(&pair[0]) <= target.distance_bits(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn try_from_slice() {
+        let digest = Digest::create("abc");
+        let bytes = digest.to_bytes();
+
+        assert_eq!(Digest::try_from(&bytes[..]).unwrap(), digest);
+        assert!(matches!(
+            Digest::try_from(&bytes[1..]),
+            Err(ParseDigestError::Length {
+                expected: 32,
+                actual: 31
+            })
+        ));
+        assert!(matches!(
+            Digest::try_from(&[&bytes[..], &[0]].concat()[..]),
+            Err(ParseDigestError::Length {
+                expected: 32,
+                actual: 33
+            })
+        ));
+        assert!(matches!(
+            Digest::try_from(&[][..]),
+            Err(ParseDigestError::Length { actual: 0, .. })
+        ));
+
+        // Same as the hex forms.
+        let hex = hex::decode(DIGEST_HEX).unwrap();
+        assert_eq!(Digest::try_from(hex.as_slice()).unwrap(), digest);
+        assert_eq!(hex::encode(Sha256Digest::from(digest)), DIGEST_HEX);
+    }
+
+    #[test]
+    fn array_conversions() {
+        let digest = Digest::create("abc");
+        let bytes: Sha256Digest = digest.into();
+
+        assert_eq!(bytes, digest.to_bytes());
+        assert_eq!(Digest::from(bytes), digest);
+        assert_eq!(Digest::from(bytes), Digest::from_bytes(bytes));
+    }
+
+    #[test]
+    fn borrow_as_bytes() {
+        use std::collections::{BTreeSet, HashMap};
+
+        let digests = (0..16u32)
+            .map(|i| Digest::create(&i.to_le_bytes()))
+            .collect::<Vec<_>>();
+
+        let map = digests
+            .iter()
+            .enumerate()
+            .map(|(i, digest)| (*digest, i))
+            .collect::<HashMap<_, _>>();
+        let set = digests.iter().copied().collect::<BTreeSet<_>>();
+        for (i, digest) in digests.iter().enumerate() {
+            assert_eq!(map.get(digest.as_bytes()), Some(&i));
+            assert!(set.contains(digest.as_bytes()));
+        }
+        assert!(!map.contains_key(&[0; 32]));
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let digest = Digest::create("abc");
+
+        // Raw bytes without length, unlike hex.
+        let bin = bincode::serialize(&digest).unwrap();
+        assert_eq!(bin, digest.as_bytes());
+        assert_eq!(bincode::deserialize::<Digest>(&bin).unwrap(), digest);
+
+        for len in 0..bin.len() {
+            assert!(bincode::deserialize::<Digest>(&bin[..len]).is_err());
+        }
+        // Hex is not read by binary formats.
+        let hex = bincode::serialize(&digest.to_string()).unwrap();
+        assert_ne!(bincode::deserialize::<Digest>(&hex).ok(), Some(digest));
+    }
+
+    #[test]
+    fn display_agrees_with_serde() {
+        for i in 0..16u32 {
+            let digest = Digest::create(&i.to_le_bytes());
+            let text = digest.to_string();
+
+            let json = serde_json::to_string(&digest).unwrap();
+            assert_eq!(json, format!(r#""{}""#, text));
+            assert_eq!(serde_json::from_str::<Digest>(&json).unwrap(), digest);
+            assert_eq!(text.parse::<Digest>().unwrap(), digest);
+            // Same as the raw digest serialized by hex.
+            assert_eq!(text, hex::encode(digest.as_bytes()));
+        }
+    }
+
+    /// Asserts that the streaming digest equals to the digest of the built byte order.
+    fn assert_streaming_agrees<T: ByteOrder>(value: &T) {
+        let buffered = Digest::create(&value.build_byte_order());
+        assert_eq!(Digest::of_byte_order(value), buffered);
+    }
+
+    #[test]
+    fn of_byte_order() {
+        use crate::account::MultisigAccount;
+        use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+        use crate::{Difficulty, SecretAccount, Timestamp};
+
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let account = secret_account.to_public();
+        let sign = secret_account.sign("hello");
+
+        // Parts of signature sources of transactions.
+        assert_streaming_agrees(&account);
+        assert_streaming_agrees(&sign);
+        assert_streaming_agrees(&Timestamp::now());
+        assert_streaming_agrees(&Difficulty::new(12));
+
+        let id = TransactionIdentifier::new(3, sign);
+        assert_streaming_agrees(&id);
+        for content in [
+            JellyfishTransactionContent::insert("record"),
+            JellyfishTransactionContent::modify("record", id.clone()),
+            JellyfishTransactionContent::remove(id),
+        ] {
+            assert_streaming_agrees(&content);
+        }
+
+        let multisig = MultisigAccount::new(1, vec![account]).unwrap();
+        assert_streaming_agrees(&multisig);
+    }
+
+    #[test]
+    fn hasher_agrees_with_create() {
+        let mut hasher = Hasher::new();
+        hasher.update(b"a");
+        hasher.update(b"");
+        hasher.update(b"bc");
+
+        assert_eq!(hasher.finalize(), Digest::create("abc"));
+    }
 }
@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+use crate::digest::Digest;
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::{Account, ByteOrder};
+
+/// Number of bits in a filter.
+pub const FILTER_BITS: usize = 2048;
+
+/// Number of bit positions set per element.
+///
+/// With [`FILTER_BITS`] bits, false-positive rate is about 0.04% for 50 elements, and about 5% for 300 elements.
+pub const FILTER_HASHES: usize = 3;
+
+const FILTER_BYTES: usize = FILTER_BITS / 8;
+
+/// Bloom filter with fixed parameters.
+///
+/// Bit positions of an element are taken from SHA256 digest of the element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct BloomFilter(#[serde(with = "hex")] [u8; FILTER_BYTES]);
+
+impl BloomFilter {
+    /// Returns a filter which contains nothing.
+    pub fn new() -> Self {
+        Self([0; FILTER_BYTES])
+    }
+
+    pub fn insert(&mut self, element: &[u8]) {
+        for position in bit_positions(element) {
+            self.0[position / 8] |= 1 << (position % 8);
+        }
+    }
+
+    /// Returns `false` if the element has never been inserted.
+    /// `true` may be returned even if the element has never been inserted.
+    pub fn maybe_contains(&self, element: &[u8]) -> bool {
+        bit_positions(element).all(|position| self.0[position / 8] & (1 << (position % 8)) != 0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+fn bit_positions(element: &[u8]) -> impl Iterator<Item = usize> {
+    let digest = Digest::create(element);
+    (0..FILTER_HASHES).map(move |i| {
+        let word = u16::from_le_bytes([digest.as_bytes()[2 * i], digest.as_bytes()[2 * i + 1]]);
+        word as usize % FILTER_BITS
+    })
+}
+
+/// Bloom filter over accounts which offered transactions in a block.
+///
+/// This lets clients check whether a block may contain their transactions without downloading the block body.
+/// False-positive may occur, but false-negative never occurs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AccountFilter(BloomFilter);
+
+impl AccountFilter {
+    /// Returns a filter which contains no account.
+    pub fn new() -> Self {
+        Self(BloomFilter::new())
+    }
+
+    /// Build a filter which contains all the given accounts.
+    pub fn from_accounts<'a, I>(accounts: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Account>,
+    {
+        let mut filter = Self::new();
+        for account in accounts {
+            filter.insert(account);
+        }
+        filter
+    }
+
+    pub fn insert(&mut self, account: &Account) {
+        self.0.insert(&account.build_byte_order());
+    }
+
+    /// Returns `false` if the account is never contained.
+    /// `true` may be returned even if the account is not contained.
+    pub fn maybe_contains(&self, account: &Account) -> bool {
+        self.0.maybe_contains(&account.build_byte_order())
+    }
+
+    /// Returns digest of the filter, which can be used to commit the filter.
+    pub fn digest(&self) -> Digest {
+        Digest::create(self.0.as_bytes())
+    }
+}
+
+impl Default for AccountFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits a record into tokens, which are inserted into [`RecordFilter`].
+pub trait Tokenizer {
+    fn tokenize<'a>(&self, record: &'a str) -> Vec<&'a str>;
+}
+
+/// Splits a record at every non-alphanumeric character.
+///
+/// e.g., `"Invoice #42: paid."` is split into `"Invoice"`, `"42"` and `"paid"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StandardTokenizer;
+
+impl Tokenizer for StandardTokenizer {
+    fn tokenize<'a>(&self, record: &'a str) -> Vec<&'a str> {
+        record
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .collect()
+    }
+}
+
+/// Bloom filter over lowercase tokens of records in a block.
+///
+/// This lets clients find blocks which may mention watched keywords without downloading block bodies.
+/// False-positive is expected to occur.
+/// On the other hand, false-negative never occurs as long as a query is a token from [`StandardTokenizer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecordFilter(BloomFilter);
+
+impl RecordFilter {
+    /// Build a filter over records of the block.
+    /// Each token is lowercased before insertion.
+    pub fn build<VT, VB, Z>(
+        block: &Block<JellyfishTransactionContent, VT, VB>,
+        tokenizer: &Z,
+    ) -> Self
+    where
+        Z: Tokenizer + ?Sized,
+    {
+        let mut filter = BloomFilter::new();
+        let records = block
+            .transactions()
+            .iter()
+            .flat_map(|tx| tx.content().operations())
+            .filter_map(JellyfishTransactionContent::record);
+        for record in records {
+            for token in tokenizer.tokenize(record) {
+                filter.insert(token.to_lowercase().as_bytes());
+            }
+        }
+        Self(filter)
+    }
+
+    /// Returns `false` if no record in the block contains the token.
+    /// `true` may be returned even if no record contains the token.
+    ///
+    /// The token is compared case-insensitively.
+    pub fn maybe_matches(&self, token: &str) -> bool {
+        self.0.maybe_contains(token.to_lowercase().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests_account_filter {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn maybe_contains() {
+        let accounts = (0..50)
+            .map(|i| fixtures::account(i).to_public())
+            .collect::<Vec<_>>();
+
+        let filter = AccountFilter::from_accounts(&accounts);
+
+        // False-negative never occurs.
+        assert!(accounts
+            .iter()
+            .all(|account| filter.maybe_contains(account)));
+    }
+
+    #[test]
+    fn maybe_contains_empty() {
+        let filter = AccountFilter::new();
+        assert!(!filter.maybe_contains(&fixtures::account(0).to_public()));
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        let accounts = (0..50)
+            .map(|i| fixtures::account(i).to_public())
+            .collect::<Vec<_>>();
+        let filter = AccountFilter::from_accounts(&accounts);
+
+        // Accounts other than the inserted ones.
+        let trials = 10000;
+        let false_positives = (50..50 + trials)
+            .filter(|&i| filter.maybe_contains(&fixtures::account(i).to_public()))
+            .count();
+
+        // Expected rate is about 0.04%, so 0.5% gives plenty of margin.
+        assert!(false_positives < trials / 200, "{}", false_positives);
+    }
+
+    #[test]
+    fn serialize_deserialize() {
+        let filter = AccountFilter::from_accounts(&[
+            fixtures::account(0).to_public(),
+            fixtures::account(1).to_public(),
+        ]);
+
+        let ser = serde_json::to_string(&filter).unwrap();
+        let de = serde_json::from_str::<AccountFilter>(&ser).unwrap();
+
+        assert_eq!(filter, de);
+        // Serialized as a single hex string.
+        assert_eq!(ser.len(), FILTER_BYTES * 2 + 2);
+    }
+
+    #[test]
+    fn deserialize_fail_wrong_length() {
+        let json = format!(r#""{}""#, "00".repeat(FILTER_BYTES - 1));
+        let result = serde_json::from_str::<AccountFilter>(&json);
+
+        assert!(result.is_err());
+    }
+}
+
+#[cfg(test)]
+mod tests_tokenizer {
+    use super::*;
+
+    #[test]
+    fn tokenize() {
+        let tokens = StandardTokenizer.tokenize("Invoice #42: paid.");
+        assert_eq!(tokens, vec!["Invoice", "42", "paid"]);
+    }
+
+    #[test]
+    fn tokenize_punctuation_only() {
+        assert!(StandardTokenizer.tokenize("--- !? ...").is_empty());
+        assert!(StandardTokenizer.tokenize("").is_empty());
+    }
+
+    #[test]
+    fn tokenize_unicode() {
+        let tokens = StandardTokenizer.tokenize("Größe=ÄRGER,日本語 café");
+        assert_eq!(tokens, vec!["Größe", "ÄRGER", "日本語", "café"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_record_filter {
+    use super::*;
+    use crate::transaction::Transaction;
+    use crate::{Difficulty, SecretAccount, Timestamp, Verified, Yet};
+
+    fn create_block(records: &[&str]) -> Block<JellyfishTransactionContent, Verified, Yet> {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let timestamp = Timestamp::now();
+        let transactions = records
+            .iter()
+            .map(|&record| {
+                let content = JellyfishTransactionContent::insert(record);
+                Transaction::create(&account, timestamp, content)
+            })
+            .collect();
+
+        Block::create(1, timestamp, Digest::ZERO, Difficulty::new(1), transactions).unwrap()
+    }
+
+    #[test]
+    fn maybe_matches() {
+        let block = create_block(&["Invoice #42: paid.", "hello"]);
+        let filter = RecordFilter::build(&block, &StandardTokenizer);
+
+        assert!(filter.maybe_matches("invoice"));
+        assert!(filter.maybe_matches("INVOICE"));
+        assert!(filter.maybe_matches("42"));
+        assert!(filter.maybe_matches("hello"));
+    }
+
+    #[test]
+    fn maybe_matches_unrelated() {
+        // Records are fixed, so the result is deterministic.
+        let block = create_block(&["Payment for coffee", "Delivery scheduled on Monday"]);
+        let filter = RecordFilter::build(&block, &StandardTokenizer);
+
+        assert!(!filter.maybe_matches("invoice"));
+        assert!(!filter.maybe_matches("refund"));
+    }
+
+    #[test]
+    fn maybe_matches_case_folding() {
+        let block = create_block(&["ÄRGER über Größe"]);
+        let filter = RecordFilter::build(&block, &StandardTokenizer);
+
+        assert!(filter.maybe_matches("ärger"));
+        assert!(filter.maybe_matches("GRÖßE"));
+        assert!(filter.maybe_matches("ÜBER"));
+    }
+
+    #[test]
+    fn serialize_deserialize() {
+        let block = create_block(&["invoice"]);
+        let filter = RecordFilter::build(&block, &StandardTokenizer);
+
+        let ser = serde_json::to_string(&filter).unwrap();
+        let de = serde_json::from_str::<RecordFilter>(&ser).unwrap();
+
+        assert_eq!(filter, de);
+        assert!(de.maybe_matches("invoice"));
+    }
+}
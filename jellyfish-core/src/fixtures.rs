@@ -0,0 +1,370 @@
+//! Deterministic builders of chains for integration tests of downstream crates.
+//!
+//! Blocks are created, mined and verified by the same code paths as real nodes,
+//! i.e., [`Transaction::create_for()`], [`Block::mine()`] and [`Chain::append_with_state()`].
+//!
+//! # Stability
+//! Within a version of this crate, the same builder calls always produce byte-identical chains,
+//! since accounts are derived from the seed and timestamps are fixed.
+//! Across versions, the produced digests and signatures may change, e.g., when the header format changes,
+//! so tests should compare fixtures with each other rather than with pinned values.
+
+use std::time::Duration;
+
+use crate::block::Block;
+use crate::chain::Chain;
+use crate::compat::seeded_account;
+use crate::digest::Digest;
+use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+use crate::params::ChainParams;
+use crate::state::LedgerState;
+use crate::{SecretAccount, Timestamp, Transaction, Verified, Yet};
+
+/// Seed used by [`FixtureChain::new()`].
+pub const DEFAULT_SEED: u64 = 0;
+
+/// Timestamp of genesis blocks of fixtures. Following blocks are timestamped at intervals of [`ChainParams::target_block_interval`].
+pub const FIXTURE_GENESIS_SECS: u64 = 1_600_000_000;
+
+/// Builder of a chain, whose blocks are appended on each [`FixtureChain::with_block()`].
+///
+/// ```
+/// # use jellyfish_core::fixtures::FixtureChain;
+/// # use jellyfish_core::params::ChainParams;
+/// let fixture = FixtureChain::new(ChainParams::regtest())
+///     .with_accounts(2)
+///     .with_block(|b| b.insert("a").by(1).insert("b"))
+///     .with_block(|b| {
+///         let target = b.tx(0);
+///         b.modify(target, "c")
+///     });
+///
+/// assert_eq!(fixture.chain().tip().header().height(), 1);
+/// assert_eq!(fixture.state().record(&fixture.ids()[0]), Some("c"));
+/// ```
+#[derive(Debug)]
+pub struct FixtureChain {
+    params: ChainParams,
+    seed: u64,
+    accounts: Vec<SecretAccount>,
+    ids: Vec<TransactionIdentifier>,
+    chain: Option<Chain<JellyfishTransactionContent>>,
+    state: LedgerState,
+}
+
+impl FixtureChain {
+    /// Returns a builder without blocks, which has an account derived from [`DEFAULT_SEED`].
+    pub fn new(params: ChainParams) -> Self {
+        let state = LedgerState::new().with_max_record_len(params.max_record_bytes);
+        Self {
+            params,
+            seed: DEFAULT_SEED,
+            accounts: vec![],
+            ids: vec![],
+            chain: None,
+            state,
+        }
+        .with_accounts(1)
+    }
+
+    /// Derive accounts from the given seed instead of [`DEFAULT_SEED`].
+    ///
+    /// # Panics
+    /// If a block has been already added.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        assert!(self.chain.is_none(), "Seed must be set before blocks.");
+        self.seed = seed;
+        let count = self.accounts.len();
+        self.with_accounts(count)
+    }
+
+    /// Derive the given number of accounts from the seed, which sign transactions of following blocks.
+    pub fn with_accounts(mut self, count: usize) -> Self {
+        self.accounts = (0..count)
+            .map(|index| derive_account(self.seed, index))
+            .collect();
+        self
+    }
+
+    /// Append a block containing transactions given by `build`, whose state root is committed.
+    ///
+    /// The first block becomes the genesis block.
+    ///
+    /// # Panics
+    /// If the block is empty or invalid, e.g., it modifies a record of another account.
+    pub fn with_block<F>(mut self, build: F) -> Self
+    where
+        F: FnOnce(&mut FixtureBlock) -> &mut FixtureBlock,
+    {
+        let mut fixture_block = FixtureBlock {
+            ids: self.ids.clone(),
+            account_count: self.accounts.len(),
+            signer: 0,
+            contents: vec![],
+        };
+        build(&mut fixture_block);
+        let contents = fixture_block.contents;
+
+        let (height, previous_digest) = match &self.chain {
+            Some(chain) => {
+                let tip = chain.tip().header();
+                (tip.height() + 1, *tip.digest())
+            }
+            None => (0, Digest::ZERO),
+        };
+        let timestamp = Timestamp::EPOCH
+            + Duration::from_secs(FIXTURE_GENESIS_SECS)
+            + self.params.target_block_interval * height as u32;
+
+        let transactions = contents
+            .into_iter()
+            .map(|(signer, content)| {
+                let account = &self.accounts[signer];
+                Transaction::create_for(&self.params, account, timestamp, content)
+            })
+            .collect::<Vec<_>>();
+        let ids = transactions
+            .iter()
+            .map(|tx| TransactionIdentifier::new(height, *tx.sign()))
+            .collect::<Vec<_>>();
+
+        let block = self.mine(height, timestamp, previous_digest, transactions);
+        match self.chain.as_mut() {
+            Some(chain) => {
+                chain
+                    .append_with_state(block, &mut self.state)
+                    .unwrap_or_else(|e| {
+                        panic!("Invalid fixture block at height {}: {}", height, e)
+                    });
+            }
+            None => {
+                let genesis = block
+                    .verify_block(|_| true)
+                    .and_then(|block| block.verify_transactions_with_params(&self.params))
+                    .unwrap_or_else(|e| panic!("Invalid fixture genesis block: {}", e));
+                self.state
+                    .apply_block(&genesis)
+                    .unwrap_or_else(|e| panic!("Invalid fixture genesis block: {}", e));
+                self.chain = Some(Chain::new(genesis).with_params(self.params.clone()));
+            }
+        }
+        self.ids.extend(ids);
+        self
+    }
+
+    /// # Panics
+    /// If no block has been added.
+    pub fn chain(&self) -> &Chain<JellyfishTransactionContent> {
+        self.chain.as_ref().expect("Fixture has no block.")
+    }
+
+    /// Returns ledger state after applying all blocks.
+    pub fn state(&self) -> &LedgerState {
+        &self.state
+    }
+
+    pub fn accounts(&self) -> &[SecretAccount] {
+        &self.accounts
+    }
+
+    /// Returns identifiers of all transactions, in order of blocks and transactions in each block.
+    pub fn ids(&self) -> &[TransactionIdentifier] {
+        &self.ids
+    }
+
+    /// Returns the chain and the state.
+    ///
+    /// # Panics
+    /// If no block has been added.
+    pub fn into_parts(self) -> (Chain<JellyfishTransactionContent>, LedgerState) {
+        let chain = self.chain.expect("Fixture has no block.");
+        (chain, self.state)
+    }
+
+    /// Create a block committing to the post state, then mine it.
+    /// The block is converted into unverified one by serialization, as if it is received from a peer.
+    fn mine(
+        &self,
+        height: u64,
+        timestamp: Timestamp,
+        previous_digest: Digest,
+        transactions: Vec<Transaction<JellyfishTransactionContent, Verified>>,
+    ) -> Block<JellyfishTransactionContent, Yet, Yet> {
+        let mut block = Block::create(
+            height,
+            timestamp,
+            previous_digest,
+            self.params.min_difficulty,
+            transactions,
+        )
+        .expect("Fixture block must have at least one transaction.");
+
+        let mut next = self.state.clone();
+        next.apply_block(&block)
+            .unwrap_or_else(|e| panic!("Invalid fixture block at height {}: {}", height, e));
+        block
+            .header_mut()
+            .modify_state_root(Some(next.state_root()));
+        block.header_mut().modify_network(self.params.network());
+        block.header_mut().modify_hash_mode(self.params.hash_mode);
+        block
+            .header_mut()
+            .modify_target(self.params.initial_target());
+        let block = block.mine();
+
+        let ser = serde_json::to_vec(&block).expect("Block must be serializable.");
+        serde_json::from_slice(&ser).expect("Block must be deserializable.")
+    }
+}
+
+/// Transactions of a block added by [`FixtureChain::with_block()`].
+#[derive(Debug)]
+pub struct FixtureBlock {
+    ids: Vec<TransactionIdentifier>,
+    account_count: usize,
+    signer: usize,
+    contents: Vec<(usize, JellyfishTransactionContent)>,
+}
+
+impl FixtureBlock {
+    /// Following transactions are signed by the account at the index. The first account is used by default.
+    ///
+    /// # Panics
+    /// If the index is out of [`FixtureChain::accounts()`].
+    pub fn by(&mut self, account: usize) -> &mut Self {
+        assert!(account < self.account_count, "Unknown fixture account.");
+        self.signer = account;
+        self
+    }
+
+    pub fn insert(&mut self, record: &str) -> &mut Self {
+        self.content(JellyfishTransactionContent::insert(record))
+    }
+
+    pub fn modify(&mut self, target: TransactionIdentifier, record: &str) -> &mut Self {
+        self.content(JellyfishTransactionContent::modify(record, target))
+    }
+
+    pub fn remove(&mut self, target: TransactionIdentifier) -> &mut Self {
+        self.content(JellyfishTransactionContent::remove(target))
+    }
+
+    pub fn append(&mut self, target: TransactionIdentifier, fragment: &str) -> &mut Self {
+        self.content(JellyfishTransactionContent::append(fragment, target))
+    }
+
+    /// Add a transaction with arbitrary content.
+    pub fn content(&mut self, content: JellyfishTransactionContent) -> &mut Self {
+        self.contents.push((self.signer, content));
+        self
+    }
+
+    /// Returns identifier of the transaction at the index of [`FixtureChain::ids()`], which is in earlier blocks.
+    ///
+    /// # Panics
+    /// If the index is out of range.
+    pub fn tx(&self, index: usize) -> TransactionIdentifier {
+        self.ids[index].clone()
+    }
+}
+
+/// Returns the account at the index derived from [`DEFAULT_SEED`],
+/// which is the same as [`FixtureChain::accounts()`] of [`FixtureChain::new()`] at the index.
+///
+/// Distinct indices give distinct accounts, so tests needing several accounts use 0, 1, 2 and so on.
+pub fn account(index: usize) -> SecretAccount {
+    derive_account(DEFAULT_SEED, index)
+}
+
+/// Returns a transaction of the content in the legacy format, which is valid on any network.
+///
+/// Unlike blocks of [`FixtureChain`], the transaction is timestamped now,
+/// so that the same content by the same account still gives distinct transactions.
+pub fn tx(
+    account: &SecretAccount,
+    content: JellyfishTransactionContent,
+) -> Transaction<JellyfishTransactionContent, Verified> {
+    Transaction::create(account, Timestamp::now(), content)
+}
+
+/// Same as [`tx()`], but the transaction is valid only on the network of the parameters as [`Transaction::create_for()`].
+pub fn tx_for(
+    params: &ChainParams,
+    account: &SecretAccount,
+    content: JellyfishTransactionContent,
+) -> Transaction<JellyfishTransactionContent, Verified> {
+    Transaction::create_for(params, account, Timestamp::now(), content)
+}
+
+/// Secret key of an account is digest of the seed and the index, as well as accounts in test vectors.
+fn derive_account(seed: u64, index: usize) -> SecretAccount {
+    seeded_account(seed, index as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(seed: u64) -> FixtureChain {
+        FixtureChain::new(ChainParams::regtest())
+            .with_seed(seed)
+            .with_accounts(2)
+            .with_block(|b| b.insert("a").by(1).insert("b"))
+            .with_block(|b| {
+                let target = b.tx(0);
+                b.modify(target, "c")
+            })
+    }
+
+    #[test]
+    fn reproducible() {
+        let fixture = build(DEFAULT_SEED);
+        let other = build(DEFAULT_SEED);
+
+        assert_eq!(
+            serde_json::to_string(fixture.chain().blocks()).unwrap(),
+            serde_json::to_string(other.chain().blocks()).unwrap()
+        );
+        assert_eq!(fixture.ids(), other.ids());
+        assert_eq!(
+            fixture.accounts()[1].to_bytes(),
+            other.accounts()[1].to_bytes()
+        );
+
+        let another = build(1);
+        assert_ne!(
+            fixture.chain().tip().header().digest(),
+            another.chain().tip().header().digest()
+        );
+    }
+
+    #[test]
+    fn accounts_and_ids() {
+        let fixture = build(DEFAULT_SEED);
+        let genesis = fixture.chain().block(0).unwrap();
+
+        assert_eq!(fixture.ids().len(), 3);
+        assert_eq!(
+            genesis.transactions()[1].account(),
+            &fixture.accounts()[1].to_public()
+        );
+        assert_eq!(fixture.state().record(&fixture.ids()[0]), Some("c"));
+        assert_eq!(fixture.state().record(&fixture.ids()[1]), Some("b"));
+        assert_eq!(
+            fixture.chain().tip().header().state_root(),
+            Some(&fixture.state().state_root())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid fixture block at height 1")]
+    fn invalid_block() {
+        FixtureChain::new(ChainParams::regtest())
+            .with_accounts(2)
+            .with_block(|b| b.insert("a"))
+            .with_block(|b| {
+                let target = b.tx(0);
+                b.by(1).modify(target, "evil")
+            });
+    }
+}
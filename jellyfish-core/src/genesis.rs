@@ -0,0 +1,310 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Block, BlockError, Header};
+use crate::digest::Digest;
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::params::{ChainParams, ParamsError};
+use crate::{Account, Signature, Timestamp, Transaction, Verified, Yet};
+
+/// Genesis block of jellyfish protocol.
+pub type JellyfishGenesis = Block<JellyfishTransactionContent, Verified, Verified>;
+
+/// Everything required to reconstruct the genesis block of a network.
+///
+/// The genesis block consists of [`Insert`](crate::jellyfish_transaction::Method::Insert) transactions by the founder,
+/// all of which have the genesis timestamp and are signed for the network by [`Transaction::create_for()`].
+/// Since the founder's secret key must not be distributed, signatures and the nonce are stored in the configuration,
+/// and [`GenesisConfig::build()`] verifies them instead of signing and mining.
+/// Hence nodes loading the same configuration always produce byte-identical genesis blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    /// Consensus parameters of the network.
+    pub params: ChainParams,
+    /// Timestamp of the genesis block and its transactions.
+    pub timestamp: Timestamp,
+    /// Creator of the initial records.
+    pub founder: Account,
+    /// Initial records, in order of transactions in the genesis block.
+    pub records: Vec<GenesisRecord>,
+    /// Nonce which satisfies [`ChainParams::min_difficulty`].
+    pub nonce: u64,
+}
+
+/// An initial record and the founder's signature of its transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisRecord {
+    pub data: String,
+    pub sign: Signature,
+}
+
+impl GenesisConfig {
+    /// Parse a configuration from JSON.
+    pub fn from_json(json: &str) -> Result<Self, GenesisError> {
+        serde_json::from_str(json).map_err(GenesisError::Parse)
+    }
+
+    /// Reconstruct the genesis block, whose height is 0 and previous digest is all zero.
+    ///
+    /// # Returns
+    /// `Err(GenesisError::Nonce)` if the nonce does not satisfy the difficulty,
+    /// `Err(GenesisError::Block)` if a signature is invalid,
+    /// and `Err(GenesisError::Params)` if the block violates the parameters.
+    pub fn build(&self) -> Result<JellyfishGenesis, GenesisError> {
+        let mut block = Block::create(
+            0,
+            self.timestamp,
+            Digest::ZERO,
+            self.params.min_difficulty,
+            self.transactions(),
+        )
+        .map_err(|_| GenesisError::Empty)?;
+        block.header_mut().modify_network(self.params.network());
+        block.header_mut().modify_hash_mode(self.params.hash_mode);
+        block
+            .header_mut()
+            .modify_target(self.params.initial_target());
+        block.header_mut().modify_nonce(self.nonce);
+        let digest = *block.header().digest();
+
+        let block = block
+            .verify_block(|_| true)
+            .map_err(|e| match e {
+                BlockError::Difficulty => GenesisError::Nonce {
+                    nonce: self.nonce,
+                    digest,
+                },
+                e => GenesisError::Block(e),
+            })?
+            .verify_transactions_with_params(&self.params)
+            .map_err(GenesisError::Block)?;
+        self.params
+            .check_block(&block)
+            .map_err(GenesisError::Params)?;
+
+        Ok(block)
+    }
+
+    /// Returns the digest of the genesis block without verifying signatures or the nonce.
+    ///
+    /// This is cheap enough to identify a network, e.g., on handshakes.
+    /// Since the digest covers every field except the parameters,
+    /// the parameters should be compared separately if necessary.
+    pub fn digest(&self) -> Result<Digest, GenesisError> {
+        self.header().map(|header| *header.digest())
+    }
+
+    fn header(&self) -> Result<Header, GenesisError> {
+        let mut header = Header::create(
+            0,
+            self.timestamp,
+            Digest::ZERO,
+            self.params.min_difficulty,
+            &self.transactions(),
+            self.nonce,
+        )
+        .ok_or(GenesisError::Empty)?;
+        header.modify_network(self.params.network());
+        header.modify_hash_mode(self.params.hash_mode);
+        header.modify_target(self.params.initial_target());
+        Ok(header)
+    }
+
+    fn transactions(&self) -> Vec<Transaction<JellyfishTransactionContent, Yet>> {
+        self.records
+            .iter()
+            .map(|record| {
+                Transaction::from_parts(
+                    self.founder.clone(),
+                    self.timestamp,
+                    JellyfishTransactionContent::insert(record.data.clone()),
+                    record.sign,
+                )
+            })
+            .collect()
+    }
+}
+
+/// An error occurred during loading or building a genesis block.
+#[derive(Debug)]
+pub enum GenesisError {
+    /// Configuration is malformed.
+    Parse(serde_json::Error),
+    /// Configuration has no record.
+    Empty,
+    /// Genesis block's digest does not satisfy the minimum difficulty.
+    Nonce { nonce: u64, digest: Digest },
+    /// Genesis block is invalid, e.g., a signature is not by the founder.
+    Block(BlockError),
+    /// Genesis block violates the parameters.
+    Params(ParamsError),
+}
+
+impl Display for GenesisError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use GenesisError::*;
+
+        match self {
+            Parse(e) => write!(f, "Malformed genesis configuration: {}", e),
+            Empty => write!(f, "Genesis configuration has no record."),
+            Nonce { nonce, digest } => write!(
+                f,
+                "Genesis nonce {} does not satisfy the difficulty, resulting digest {}.",
+                nonce,
+                digest.abbrev()
+            ),
+            Block(e) => write!(f, "Invalid genesis block: {}", e),
+            Params(e) => write!(f, "Genesis block violates parameters: {}", e),
+        }
+    }
+}
+
+impl Error for GenesisError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use GenesisError::*;
+
+        match self {
+            Parse(e) => Some(e),
+            Block(e) => Some(e),
+            Params(e) => Some(e),
+            Empty | Nonce { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Difficulty, SecretAccount};
+
+    /// Fixture whose founder and signature are taken from the test vector of RFC 8032.
+    /// Since the signature is not of the genesis transaction, this pins only the digest.
+    const FIXTURE_JSON: &str = r#"{
+        "params": {
+            "network_id": "jellyfish-regtest",
+            "protocol_version": 1,
+            "min_difficulty": 1,
+            "max_difficulty": 256,
+            "target_block_interval": { "secs": 1, "nanos": 0 },
+            "retarget_window": 10,
+            "retarget_clamp": 4,
+            "max_block_transactions": 10000,
+            "max_block_bytes": 4194304,
+            "max_record_bytes": 65536,
+            "max_future_drift": { "secs": 7200, "nanos": 0 },
+            "allow_empty_blocks": false,
+            "max_reorg_depth": 10
+        },
+        "timestamp": 1600000000000000000,
+        "founder": { "name": "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a" },
+        "records": [
+            {
+                "data": "jellyfish",
+                "sign": "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+            }
+        ],
+        "nonce": 5
+    }"#;
+
+    const FIXTURE_DIGEST: &str = "5eb31caef9026920c8802376234e22dea35f649391e37b437300325abd96f9bb";
+
+    /// Sign the records, then find a nonce satisfying the difficulty.
+    fn create_config(founder: &SecretAccount, records: &[&str]) -> GenesisConfig {
+        let params = ChainParams::regtest();
+        let timestamp = Timestamp::now();
+        let records = records
+            .iter()
+            .map(|&data| {
+                let content = JellyfishTransactionContent::insert(data);
+                let tx = Transaction::create_for(&params, founder, timestamp, content);
+                GenesisRecord {
+                    data: data.to_owned(),
+                    sign: *tx.sign(),
+                }
+            })
+            .collect();
+        let mut config = GenesisConfig {
+            params,
+            timestamp,
+            founder: founder.to_public(),
+            records,
+            nonce: 0,
+        };
+        while !config
+            .params
+            .min_difficulty
+            .verify_digest(&config.digest().unwrap())
+        {
+            config.nonce += 1;
+        }
+        config
+    }
+
+    #[test]
+    fn fixture_digest() {
+        let config = GenesisConfig::from_json(FIXTURE_JSON).unwrap();
+        assert_eq!(config.params.min_difficulty, Difficulty::new(1));
+        assert_eq!(hex::encode(config.digest().unwrap()), FIXTURE_DIGEST);
+    }
+
+    #[test]
+    fn fixture_fail_nonce() {
+        let mut config = GenesisConfig::from_json(FIXTURE_JSON).unwrap();
+        config.nonce = 3;
+        match config.build() {
+            Err(GenesisError::Nonce { nonce: 3, digest }) => {
+                assert_eq!(digest, config.digest().unwrap())
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn build_deterministic() {
+        let founder = SecretAccount::create(&mut rand_core::OsRng {});
+        let json = serde_json::to_string(&create_config(&founder, &["hello", "world"])).unwrap();
+
+        let config = GenesisConfig::from_json(&json).unwrap();
+        let genesis = config.build().unwrap();
+        let other = GenesisConfig::from_json(&json).unwrap().build().unwrap();
+
+        assert_eq!(genesis.header().digest(), &config.digest().unwrap());
+        assert_eq!(genesis.transactions().len(), 2);
+        assert_eq!(
+            serde_json::to_vec(&genesis).unwrap(),
+            serde_json::to_vec(&other).unwrap()
+        );
+    }
+
+    #[test]
+    fn build_fail_sign() {
+        let founder = SecretAccount::create(&mut rand_core::OsRng {});
+        let mut config = create_config(&founder, &["hello"]);
+        config.records[0].data = "tampered".to_owned();
+        // Keep the nonce valid, so that only the signature is wrong.
+        while !config
+            .params
+            .min_difficulty
+            .verify_digest(&config.digest().unwrap())
+        {
+            config.nonce += 1;
+        }
+
+        assert!(matches!(
+            config.build(),
+            Err(GenesisError::Block(BlockError::Transaction(_)))
+        ));
+    }
+
+    #[test]
+    fn build_fail_empty() {
+        let founder = SecretAccount::create(&mut rand_core::OsRng {});
+        let mut config = create_config(&founder, &["hello"]);
+        config.records.clear();
+
+        assert!(matches!(config.build(), Err(GenesisError::Empty)));
+        assert!(matches!(config.digest(), Err(GenesisError::Empty)));
+    }
+}
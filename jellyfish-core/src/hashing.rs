@@ -0,0 +1,178 @@
+//! Hash algorithms of headers and merkle trees.
+//!
+//! [`Header`](crate::block::Header) and [`Block`](crate::block::Block) take the algorithm as a type parameter,
+//! which defaults to [`Sha256`], and [`Digest`] is the output of every algorithm.
+//! Ledger states are built by [`HashAlgorithm::Merkle`] of [`DefaultAlgorithm`].
+//!
+//! Byte orders of headers contain [`HashAlgorithm::ID`] after [`ALGORITHM_TAG`](crate::block::ALGORITHM_TAG),
+//! except for [`Sha256`] whose headers keep the original byte order,
+//! so that chains of different algorithms never validate each other.
+
+use std::fmt::Debug;
+
+use sha2::Digest as _;
+
+use crate::digest::{Digest, HashMode, Midstate, Sha256Digest};
+use crate::ByteOrder;
+
+/// Algorithm which the protocol uses for digests and merkle trees.
+pub type DefaultAlgorithm = Sha256;
+
+/// Merkle tree hasher of [`DefaultAlgorithm`].
+pub(crate) type DefaultMerkle = <DefaultAlgorithm as HashAlgorithm>::Merkle;
+
+/// Hash algorithm whose output is 32 bytes.
+///
+/// The supertraits let headers and blocks derive their traits for every algorithm.
+pub trait HashAlgorithm: Debug + Clone + Copy + PartialEq + Eq + Default {
+    /// Identifier of the algorithm, which is unique among algorithms.
+    const ID: u8;
+
+    /// Same algorithm as merkle tree hasher.
+    type Merkle: rs_merkle::Hasher<Hash = Sha256Digest>;
+
+    /// State after absorbing a prefix, which Proof-of-Work process clones for each nonce.
+    type Midstate: Clone;
+
+    fn hash(msg: &[u8]) -> Digest;
+
+    fn absorb_prefix(prefix: &[u8]) -> Self::Midstate;
+
+    /// Returns digest of the prefix of the midstate followed by `suffix`, which equals to [`HashAlgorithm::hash()`] of the concatenation.
+    fn finish_with(midstate: &Self::Midstate, suffix: &[u8]) -> Digest;
+
+    /// Returns digest of the byte order, same as [`HashAlgorithm::hash()`] of [`ByteOrder::build_byte_order()`].
+    fn hash_byte_order<T: ByteOrder>(value: &T) -> Digest {
+        Self::hash(&value.build_byte_order())
+    }
+
+    /// Returns digest in the mode from a digest of a message, as [`HashMode::finish()`] does for SHA256.
+    fn finish_mode(mode: HashMode, single: Digest) -> Digest {
+        match mode {
+            HashMode::Single => single,
+            HashMode::Double => Self::hash(single.as_bytes()),
+        }
+    }
+}
+
+/// SHA256, which the protocol uses for digests and merkle trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sha256;
+
+impl HashAlgorithm for Sha256 {
+    const ID: u8 = 0;
+
+    type Merkle = rs_merkle::algorithms::Sha256;
+
+    type Midstate = Midstate;
+
+    fn hash(msg: &[u8]) -> Digest {
+        Digest::from_bytes(sha2::Sha256::digest(msg).into())
+    }
+
+    fn absorb_prefix(prefix: &[u8]) -> Self::Midstate {
+        Midstate::from_prefix(prefix)
+    }
+
+    fn finish_with(midstate: &Self::Midstate, suffix: &[u8]) -> Digest {
+        midstate.finish_with(suffix)
+    }
+
+    /// Writes the byte order into the hasher directly, without building it into a buffer.
+    fn hash_byte_order<T: ByteOrder>(value: &T) -> Digest {
+        Digest::of_byte_order(value)
+    }
+}
+
+/// BLAKE3 with 32-byte output.
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Blake3;
+
+#[cfg(feature = "blake3")]
+impl HashAlgorithm for Blake3 {
+    const ID: u8 = 1;
+
+    type Merkle = Blake3Merkle;
+
+    type Midstate = blake3::Hasher;
+
+    fn hash(msg: &[u8]) -> Digest {
+        Digest::from_bytes(blake3::hash(msg).into())
+    }
+
+    fn absorb_prefix(prefix: &[u8]) -> Self::Midstate {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(prefix);
+        hasher
+    }
+
+    fn finish_with(midstate: &Self::Midstate, suffix: &[u8]) -> Digest {
+        let mut hasher = midstate.clone();
+        hasher.update(suffix);
+        Digest::from_bytes(hasher.finalize().into())
+    }
+}
+
+/// Merkle tree hasher of [`Blake3`].
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone)]
+pub struct Blake3Merkle;
+
+#[cfg(feature = "blake3")]
+impl rs_merkle::Hasher for Blake3Merkle {
+    type Hash = Sha256Digest;
+
+    fn hash(data: &[u8]) -> Self::Hash {
+        blake3::hash(data).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rs_merkle::Hasher as _;
+
+    use super::*;
+
+    #[test]
+    fn sha256_agrees_with_digest() {
+        for msg in [&b""[..], b"abc", &[0xab; 200]] {
+            assert_eq!(Sha256::hash(msg), Digest::create(msg));
+            assert_eq!(
+                <Sha256 as HashAlgorithm>::Merkle::hash(msg),
+                Digest::create(msg).to_bytes()
+            );
+            assert_eq!(
+                Sha256::finish_mode(HashMode::Double, Sha256::hash(msg)),
+                Digest::create_double(msg)
+            );
+        }
+    }
+
+    #[test]
+    fn finish_with_agrees_with_hash() {
+        fn check<H: HashAlgorithm>() {
+            let midstate = H::absorb_prefix(b"jelly");
+            assert_eq!(H::finish_with(&midstate, b"fish"), H::hash(b"jellyfish"));
+            assert_eq!(H::finish_with(&midstate, b""), H::hash(b"jelly"));
+        }
+
+        check::<Sha256>();
+        #[cfg(feature = "blake3")]
+        check::<Blake3>();
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_test_vector() {
+        // Official test vector of the empty input.
+        assert_eq!(
+            Blake3::hash(b"").to_string(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            <Blake3 as HashAlgorithm>::Merkle::hash(b"abc"),
+            Blake3::hash(b"abc").to_bytes()
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Hex with an optional `0x` prefix, which other tools often emit.
+//!
+//! Deserialization of the crate's hex, e.g., accounts, signatures and digests, accepts both `0x`-prefixed and plain hex
+//! by [`decode()`] and [`deserialize()`]. Serialization stays plain hex;
+//! [`serialize()`] is an opt-in for fields which need the prefixed form, e.g., `#[serde(serialize_with = "jellyfish_core::hex0x::serialize")]`.
+
+use hex::{FromHex, FromHexError};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Prefix of hex, which is stripped in either case, i.e., `0x` or `0X`.
+pub const PREFIX: &str = "0x";
+
+/// Returns the hex without `0x` or `0X` prefix, or as it is if it has no prefix.
+pub fn strip_prefix(hex: &str) -> &str {
+    hex.strip_prefix(PREFIX)
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex)
+}
+
+/// Decodes hex in lowercase or uppercase, with or without the prefix.
+pub fn decode(hex: &str) -> Result<Vec<u8>, FromHexError> {
+    hex::decode(strip_prefix(hex))
+}
+
+/// Decodes hex of exactly `N` bytes, with or without the prefix.
+pub fn decode_to_array<const N: usize>(hex: &str) -> Result<[u8; N], FromHexError> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(strip_prefix(hex), &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Encodes the data as lowercase hex with the prefix.
+pub fn encode<T: AsRef<[u8]>>(data: T) -> String {
+    format!("{}{}", PREFIX, hex::encode(data))
+}
+
+/// Serializes the data as lowercase hex with the prefix.
+pub fn serialize<S, T>(data: T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<[u8]>,
+{
+    serializer.serialize_str(&encode(data))
+}
+
+/// Same as `hex::deserialize`, but also accepts the prefix.
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromHex,
+    T::Error: std::fmt::Display,
+{
+    let hex = String::deserialize(deserializer)?;
+    T::from_hex(strip_prefix(&hex)).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn strip_prefix() {
+        assert_eq!(super::strip_prefix("0xabcd"), "abcd");
+        assert_eq!(super::strip_prefix("0XABCD"), "ABCD");
+        assert_eq!(super::strip_prefix("abcd"), "abcd");
+        // Only one prefix is stripped.
+        assert_eq!(super::strip_prefix("0x0xab"), "0xab");
+        assert_eq!(super::strip_prefix("x0ab"), "x0ab");
+    }
+
+    #[test]
+    fn decode() {
+        for hex in ["0xabCD", "0XABcd", "abcd", "ABCD"] {
+            assert_eq!(super::decode(hex).unwrap(), vec![0xab, 0xcd]);
+        }
+        assert!(super::decode("0x0xab").is_err());
+        assert!(super::decode("0xabc").is_err());
+    }
+
+    #[test]
+    fn decode_to_array() {
+        assert_eq!(super::decode_to_array::<2>("0xabcd").unwrap(), [0xab, 0xcd]);
+        // Length excludes the prefix.
+        assert!(super::decode_to_array::<3>("0xabcd").is_err());
+        assert!(super::decode_to_array::<1>("0xabcd").is_err());
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Prefixed(
+            #[serde(
+                serialize_with = "super::serialize",
+                deserialize_with = "super::deserialize"
+            )]
+            [u8; 2],
+        );
+
+        let json = serde_json::to_string(&Prefixed([0xab, 0xcd])).unwrap();
+        assert_eq!(json, r#""0xabcd""#);
+        assert_eq!(
+            serde_json::from_str::<Prefixed>(&json).unwrap(),
+            Prefixed([0xab, 0xcd])
+        );
+        assert_eq!(
+            serde_json::from_str::<Prefixed>(r#""ABCD""#).unwrap(),
+            Prefixed([0xab, 0xcd])
+        );
+    }
+}
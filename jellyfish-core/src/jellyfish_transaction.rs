@@ -1,34 +1,106 @@
 use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{byteorder::ByteOrder, Signature};
+use ed25519_dalek::Digest as PrehashDigest;
+
+use crate::account::Prehash;
+use crate::blob::{BlobError, RecordRef};
+use crate::codec::{BoundedReader, DecodeError};
+use crate::{byteorder::ByteOrder, Digest, Signature};
 
 /// Represents an operation of transaction.
+///
+/// Serialized as a lowercase string, e.g., `"insert"`.
+/// Capitalized strings, which were used by earlier versions, are also accepted on deserialization.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Method {
     /// Add new record.
+    #[serde(alias = "Insert")]
     Insert,
     /// Modify previously added record.
+    #[serde(alias = "Modify")]
     Modify,
     /// Remove previously added record.
+    #[serde(alias = "Remove")]
     Remove,
+    /// Add a fragment to the end of previously added record.
+    #[serde(alias = "Append")]
+    Append,
+    /// Apply multiple operations in order under a single signature.
+    #[serde(alias = "Batch")]
+    Batch,
 }
 
-impl ByteOrder for Method {
-    fn append_bytes(&self, buf: &mut Vec<u8>) {
-        let byte = match self {
+impl Method {
+    /// All methods in ascending order of their wire bytes.
+    pub const ALL: [Method; 5] = [
+        Method::Insert,
+        Method::Modify,
+        Method::Remove,
+        Method::Append,
+        Method::Batch,
+    ];
+
+    /// Returns the byte representing the method in byte order of transactions.
+    ///
+    /// Bytes are part of signed transactions, so they must never be changed nor reused.
+    pub fn wire_byte(&self) -> u8 {
+        match self {
             Method::Insert => 0x01,
             Method::Modify => 0x02,
             Method::Remove => 0x04,
-        };
-        buf.push(byte);
+            Method::Append => 0x08,
+            Method::Batch => 0x10,
+        }
+    }
+}
+
+impl TryFrom<u8> for Method {
+    type Error = MethodDecodeError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        Method::ALL
+            .into_iter()
+            .find(|method| method.wire_byte() == byte)
+            .ok_or(MethodDecodeError::Unknown(byte))
     }
 }
 
+impl ByteOrder for Method {
+    fn append_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(self.wire_byte());
+    }
+
+    fn byte_len(&self) -> usize {
+        1
+    }
+}
+
+/// Byte does not represent any [`Method`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodDecodeError {
+    Unknown(u8),
+}
+
+impl Display for MethodDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MethodDecodeError::Unknown(byte) => write!(f, "Unknown method byte 0x{:02x}.", byte),
+        }
+    }
+}
+
+impl Error for MethodDecodeError {}
+
 /// Specify a transaction in blocks.
 /// Used for `Modify` or `Remove` method.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Identifiers are ordered by height, then by sign.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct TransactionIdentifier {
     /// Which block contains the target transaction.
     pub height: u64,
@@ -51,20 +123,43 @@ impl ByteOrder for TransactionIdentifier {
         buf.extend(self.height.to_le_bytes());
         self.sign.append_bytes(buf);
     }
+
+    fn byte_len(&self) -> usize {
+        std::mem::size_of::<u64>() + self.sign.byte_len()
+    }
 }
 
+/// Maximum number of operations in a `Batch` transaction.
+pub const MAX_BATCH_OPERATIONS: usize = 64;
+
+/// Set in the method byte of contents with TTL, whose record is prefixed by its length in byte order.
+///
+/// Contents without TTL keep the original byte order, so that their signatures are unchanged.
+/// No [`Method::wire_byte()`] has this bit, so the two layouts never share byte order.
+pub const TTL_LAYOUT_FLAG: u8 = 0x80;
+
+/// Tag preceding the TTL in byte order of contents.
+pub const TTL_TAG: u8 = 0x01;
+
 /// Content of jellyfish-chain protocol's transaction.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 
 pub struct JellyfishTransactionContent {
     /// Operation.
     method: Method,
-    /// Record. Used on `Insert` or `Modify` method.
+    /// Record. Used on `Insert` or `Modify` method. Fragment of the record on `Append` method.
     #[serde(skip_serializing_if = "Option::is_none")]
     record: Option<String>,
-    /// Ientifer of operation's target transaction. Used on `Modify` or `Remove` method.
+    /// Ientifer of operation's target transaction. Used on `Modify`, `Remove` or `Append` method.
     #[serde(skip_serializing_if = "Option::is_none")]
     target: Option<TransactionIdentifier>,
+    /// Lifetime of the record in nanoseconds, counted from timestamp of the block containing the transaction.
+    /// Used on `Insert` or `Modify` method. `None` means the record never expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ttl_nanos: Option<i64>,
+    /// Nested operations in applied order. Used on `Batch` method.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    operations: Vec<JellyfishTransactionContent>,
 }
 
 impl JellyfishTransactionContent {
@@ -77,9 +172,34 @@ impl JellyfishTransactionContent {
             method: Method::Insert,
             record: Some(record.into().into_owned()),
             target: None,
+            ttl_nanos: None,
+            operations: vec![],
         }
     }
 
+    /// Create new transaction content with `Insert` method, whose record only the recipient can read.
+    ///
+    /// See [`sealed`](crate::sealed) for the format.
+    #[cfg(feature = "crypto-box")]
+    pub fn insert_sealed(recipient: &crate::Account, plaintext: &[u8]) -> Self {
+        Self::insert(crate::sealed::encrypt_record(recipient, plaintext))
+    }
+
+    /// Create new transaction content with `Insert` method, whose record is compressed if it shrinks.
+    ///
+    /// See [`compression`](crate::compression) for the format.
+    #[cfg(feature = "compression")]
+    pub fn insert_compressed(record: &str) -> Self {
+        Self::insert(crate::compression::compress_record(record))
+    }
+
+    /// Create new transaction content with `Insert` method, whose record refers to a blob stored off chain.
+    ///
+    /// See [`RecordRef`](crate::blob::RecordRef) for the format.
+    pub fn insert_ref(blob_digest: Digest, length: u64, uri_hint: Option<&str>) -> Self {
+        Self::insert(RecordRef::new(blob_digest, length, uri_hint).to_record())
+    }
+
     /// Create new transaction content with `Modify` method.
     pub fn modify<'a, T>(record: T, target: TransactionIdentifier) -> Self
     where
@@ -89,6 +209,8 @@ impl JellyfishTransactionContent {
             method: Method::Modify,
             record: Some(record.into().into_owned()),
             target: Some(target),
+            ttl_nanos: None,
+            operations: vec![],
         }
     }
 
@@ -98,6 +220,52 @@ impl JellyfishTransactionContent {
             method: Method::Remove,
             record: None,
             target: Some(target),
+            ttl_nanos: None,
+            operations: vec![],
+        }
+    }
+
+    /// Create new transaction content with `Append` method.
+    ///
+    /// The fragment is added to the end of the target record, instead of replacing the whole record.
+    pub fn append<'a, T>(fragment: T, target: TransactionIdentifier) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        Self {
+            method: Method::Append,
+            record: Some(fragment.into().into_owned()),
+            target: Some(target),
+            ttl_nanos: None,
+            operations: vec![],
+        }
+    }
+
+    /// Create new transaction content with `Batch` method, which applies the operations in order.
+    ///
+    /// A record inserted by the batch is identified by the batch transaction,
+    /// so a batch may contain at most one `Insert`.
+    ///
+    /// Fails if the operations are empty, exceed [`MAX_BATCH_OPERATIONS`], contain a batch, or contain multiple `Insert`.
+    pub fn batch(operations: Vec<JellyfishTransactionContent>) -> Result<Self, ContentError> {
+        let content = Self {
+            method: Method::Batch,
+            record: None,
+            target: None,
+            ttl_nanos: None,
+            operations,
+        };
+        content.check_batch(MAX_BATCH_OPERATIONS)?;
+        Ok(content)
+    }
+
+    /// Set lifetime of the record in nanoseconds.
+    ///
+    /// The record expires once timestamp of a later block exceeds timestamp of the block containing the transaction plus `ttl_nanos`.
+    pub fn with_ttl(self, ttl_nanos: i64) -> Self {
+        Self {
+            ttl_nanos: Some(ttl_nanos),
+            ..self
         }
     }
 
@@ -109,23 +277,292 @@ impl JellyfishTransactionContent {
         self.record.as_deref()
     }
 
+    /// Returns the original text of the record, which may be compressed by [`JellyfishTransactionContent::insert_compressed()`].
+    #[cfg(feature = "compression")]
+    pub fn record_decompressed(&self) -> Result<Option<Cow<'_, str>>, ContentError> {
+        self.record
+            .as_deref()
+            .map(crate::compression::decompress_record)
+            .transpose()
+    }
+
+    /// Parse the record as a reference to an off-chain blob.
+    ///
+    /// # Returns
+    /// `None` if the content has no record, or the record is not a reference.
+    pub fn record_ref(&self) -> Option<Result<RecordRef, BlobError>> {
+        self.record().and_then(RecordRef::parse)
+    }
+
     pub fn target_transaction(&self) -> Option<&TransactionIdentifier> {
         self.target.as_ref()
     }
+
+    pub fn ttl_nanos(&self) -> Option<i64> {
+        self.ttl_nanos
+    }
+
+    /// Returns the nested operations of `Batch`, or the content itself for other methods.
+    pub fn operations(&self) -> &[JellyfishTransactionContent] {
+        match self.method {
+            Method::Batch => &self.operations,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// Check that the `Batch` content has from one to `max_operations` operations,
+    /// none of which is a batch, and at most one of which is `Insert`.
+    pub(crate) fn check_batch(&self, max_operations: usize) -> Result<(), ContentError> {
+        let operations = &self.operations;
+        if operations.is_empty() {
+            return Err(ContentError::new("Batch has no operation."));
+        }
+        if operations.len() > max_operations {
+            return Err(ContentError::new(format!(
+                "Batch has {} operations, which exceeds limit {}.",
+                operations.len(),
+                max_operations
+            )));
+        }
+        if operations.iter().any(|op| op.method == Method::Batch) {
+            return Err(ContentError::new("Batch cannot be nested."));
+        }
+        let insert_count = operations
+            .iter()
+            .filter(|op| op.method == Method::Insert)
+            .count();
+        if insert_count > 1 {
+            return Err(ContentError::new("Batch has multiple Insert operations."));
+        }
+        Ok(())
+    }
 }
 
 impl ByteOrder for JellyfishTransactionContent {
     fn append_bytes(&self, buf: &mut Vec<u8>) {
-        self.method.append_bytes(buf);
-        if let Some(record) = self.record() {
-            buf.extend(record.as_bytes());
+        self.write_byte_order(&mut |bytes| buf.extend(bytes));
+    }
+
+    /// Records are written as they are, without building the byte order.
+    fn append_to_prehash(&self, prehash: &mut Prehash) {
+        self.write_byte_order(&mut |bytes| PrehashDigest::update(prehash, bytes));
+    }
+
+    fn byte_len(&self) -> usize {
+        let mut len = self.method.byte_len();
+        len += self.record().map_or(0, str::len);
+        len += self.target.as_ref().map_or(0, ByteOrder::byte_len);
+        if self.ttl_nanos.is_some() {
+            len += self.record().map_or(0, |_| 4);
+            len += 1 + std::mem::size_of::<i64>();
+        }
+        if self.method == Method::Batch {
+            len += 4;
+            len += self
+                .operations
+                .iter()
+                .map(|operation| 4 + operation.byte_len())
+                .sum::<usize>();
+        }
+        len
+    }
+}
+
+impl JellyfishTransactionContent {
+    /// Write the byte order by parts, so that the record is never copied.
+    fn write_byte_order(&self, write: &mut dyn FnMut(&[u8])) {
+        if self.ttl_nanos.is_none() {
+            write(&[self.method.wire_byte()]);
+            if let Some(record) = self.record() {
+                write(record.as_bytes());
+            }
+        } else {
+            // Record is prefixed by its length, so that it never absorbs bytes of the TTL.
+            write(&[self.method.wire_byte() | TTL_LAYOUT_FLAG]);
+            if let Some(record) = self.record() {
+                write(&(record.len() as u32).to_le_bytes());
+                write(record.as_bytes());
+            }
         }
         if let Some(target) = &self.target {
-            target.append_bytes(buf);
+            write(&target.build_byte_order());
+        }
+        if let Some(ttl_nanos) = self.ttl_nanos {
+            write(&[TTL_TAG]);
+            write(&ttl_nanos.to_le_bytes());
+        }
+        if self.method == Method::Batch {
+            // Each operation is prefixed by its length, so that boundaries of operations are signed too.
+            write(&(self.operations.len() as u32).to_le_bytes());
+            for operation in &self.operations {
+                write(&(operation.byte_len() as u32).to_le_bytes());
+                operation.write_byte_order(write);
+            }
         }
     }
+
+    /// Decode content from its byte order, which is the inverse of [`ByteOrder::build_byte_order()`].
+    ///
+    /// Records longer than `max_record_bytes` are rejected before they are read.
+    ///
+    /// # Returns
+    /// `Err(DecodeError::Malformed)` if the bytes are not byte order of any content, e.g., a batch is invalid.
+    pub fn from_byte_order(bytes: &[u8], max_record_bytes: usize) -> Result<Self, DecodeError> {
+        Self::decode(bytes, max_record_bytes, false)
+    }
+
+    /// Operations of a batch are decoded as `nested`, which must not be a batch,
+    /// so that a malicious input never recurses deeper than once.
+    fn decode(bytes: &[u8], max_record_bytes: usize, nested: bool) -> Result<Self, DecodeError> {
+        // The bytes are already in memory, so only each field is bounded, not the total.
+        let mut reader = BoundedReader::new(bytes, usize::MAX);
+
+        let [byte] = reader.read_array("method")?;
+        let has_ttl = byte & TTL_LAYOUT_FLAG != 0;
+        let method =
+            Method::try_from(byte & !TTL_LAYOUT_FLAG).map_err(|e| malformed("method", e))?;
+        if nested && method == Method::Batch {
+            return Err(malformed("method", "Batch cannot be nested."));
+        }
+        let has_record = matches!(method, Method::Insert | Method::Modify | Method::Append);
+        let target_len = match method {
+            Method::Modify | Method::Remove | Method::Append => TARGET_BYTES,
+            Method::Insert | Method::Batch => 0,
+        };
+
+        let record =
+            if !has_record {
+                None
+            } else if has_ttl {
+                let len = u32::from_le_bytes(reader.read_array("record")?) as usize;
+                Some(reader.read_bytes("record", len, max_record_bytes)?)
+            } else {
+                // Record without TTL continues up to the target.
+                let len = reader.remaining().checked_sub(target_len).ok_or(
+                    DecodeError::UnexpectedEnd {
+                        field: "target",
+                        requested: target_len,
+                        remaining: reader.remaining(),
+                    },
+                )?;
+                Some(reader.read_bytes("record", len, max_record_bytes)?)
+            };
+        let record = record
+            .map(|record| String::from_utf8(record.to_vec()))
+            .transpose()
+            .map_err(|e| malformed("record", e))?;
+
+        let target = if target_len == 0 {
+            None
+        } else {
+            let height = u64::from_le_bytes(reader.read_array("target")?);
+            let sign: [u8; 64] = reader.read_array("target")?;
+            let sign = Signature::from_bytes(&sign).map_err(|e| malformed("target", e))?;
+            Some(TransactionIdentifier::new(height, sign))
+        };
+
+        let ttl_nanos = if has_ttl {
+            let [tag] = reader.read_array("ttl")?;
+            if tag != TTL_TAG {
+                return Err(malformed("ttl", format!("Unknown tag 0x{:02x}.", tag)));
+            }
+            Some(i64::from_le_bytes(reader.read_array("ttl")?))
+        } else {
+            None
+        };
+
+        let mut operations = vec![];
+        if method == Method::Batch {
+            let count = u32::from_le_bytes(reader.read_array("operations")?) as usize;
+            if count > MAX_BATCH_OPERATIONS {
+                return Err(DecodeError::LimitExceeded {
+                    field: "operations",
+                    limit: MAX_BATCH_OPERATIONS,
+                    requested: count,
+                });
+            }
+            for _ in 0..count {
+                let len = u32::from_le_bytes(reader.read_array("operation")?) as usize;
+                let bytes = reader.read_bytes("operation", len, bytes.len())?;
+                operations.push(Self::decode(bytes, max_record_bytes, true)?);
+            }
+        }
+        reader.finish("content")?;
+
+        let content = Self {
+            method,
+            record,
+            target,
+            ttl_nanos,
+            operations,
+        };
+        if method == Method::Batch {
+            content
+                .check_batch(MAX_BATCH_OPERATIONS)
+                .map_err(|e| malformed("operations", e))?;
+        }
+        Ok(content)
+    }
 }
 
+/// Length of byte order of [`TransactionIdentifier`].
+const TARGET_BYTES: usize = std::mem::size_of::<u64>() + 64;
+
+fn malformed(field: &'static str, reason: impl Display) -> DecodeError {
+    DecodeError::Malformed {
+        field,
+        reason: reason.to_string(),
+    }
+}
+
+/// Rule which transaction contents must satisfy in addition to jellyfish-chain protocol.
+///
+/// e.g., [`AliasRule`](crate::alias::AliasRule) rejects malformed alias records.
+pub trait ContentRule: Debug + Send + Sync {
+    /// Returns `Err` if the content violates the rule.
+    fn check(&self, content: &JellyfishTransactionContent) -> Result<(), ContentError>;
+}
+
+/// Transaction content violates a [`ContentRule`], or its record cannot be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentError {
+    /// Content violates a rule for the reason.
+    Rule(String),
+    /// Compressed record expands beyond the limit.
+    DecompressedTooLarge { limit: usize },
+}
+
+impl ContentError {
+    pub fn new<'a, T>(reason: T) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        ContentError::Rule(reason.into().into_owned())
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            ContentError::Rule(reason) => reason,
+            ContentError::DecompressedTooLarge { .. } => "Decompressed record is too large.",
+        }
+    }
+}
+
+impl Display for ContentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::Rule(reason) => {
+                write!(f, "Transaction content violates a rule: {}", reason)
+            }
+            ContentError::DecompressedTooLarge { limit } => {
+                write!(f, "Decompressed record exceeds {} bytes.", limit)
+            }
+        }
+    }
+}
+
+impl Error for ContentError {}
+
 #[cfg(test)]
 mod tests_method {
     use super::*;
@@ -135,6 +572,96 @@ mod tests_method {
         assert_eq!(Method::Insert.build_byte_order(), &[0x01]);
         assert_eq!(Method::Modify.build_byte_order(), &[0x02]);
         assert_eq!(Method::Remove.build_byte_order(), &[0x04]);
+        assert_eq!(Method::Append.build_byte_order(), &[0x08]);
+        assert_eq!(Method::Batch.build_byte_order(), &[0x10]);
+    }
+
+    #[test]
+    fn all_exhaustive() {
+        // Fails to compile when a variant is added, so that `Method::ALL` is updated together.
+        for method in Method::ALL {
+            match method {
+                Method::Insert
+                | Method::Modify
+                | Method::Remove
+                | Method::Append
+                | Method::Batch => {}
+            }
+        }
+        let mut sorted = Method::ALL.to_vec();
+        sorted.dedup();
+        assert_eq!(sorted.len(), Method::ALL.len());
+    }
+
+    #[test]
+    fn wire_byte_round_trip() {
+        for method in Method::ALL {
+            assert_eq!(Method::try_from(method.wire_byte()), Ok(method));
+            assert_eq!(method.build_byte_order(), &[method.wire_byte()]);
+        }
+    }
+
+    #[test]
+    fn wire_byte_unique() {
+        let mut bytes = Method::ALL.map(|method| method.wire_byte()).to_vec();
+        assert!(bytes.windows(2).all(|w| w[0] < w[1]));
+        bytes.dedup();
+        assert_eq!(bytes.len(), Method::ALL.len());
+    }
+
+    #[test]
+    fn serialize_lowercase() {
+        let names = Method::ALL.map(|method| serde_json::to_string(&method).unwrap());
+        assert_eq!(
+            names,
+            [
+                r#""insert""#,
+                r#""modify""#,
+                r#""remove""#,
+                r#""append""#,
+                r#""batch""#
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_legacy() {
+        let legacy = [
+            r#""Insert""#,
+            r#""Modify""#,
+            r#""Remove""#,
+            r#""Append""#,
+            r#""Batch""#,
+        ];
+        for (json, method) in legacy.into_iter().zip(Method::ALL) {
+            assert_eq!(serde_json::from_str::<Method>(json).unwrap(), method);
+
+            let lowercase = json.to_lowercase();
+            assert_eq!(serde_json::from_str::<Method>(&lowercase).unwrap(), method);
+        }
+    }
+
+    #[test]
+    fn deserialize_unknown() {
+        let e = serde_json::from_str::<Method>(r#""upsert""#).unwrap_err();
+        assert!(e.to_string().contains("upsert"));
+
+        // Only the exact legacy form is accepted.
+        assert!(serde_json::from_str::<Method>(r#""INSERT""#).is_err());
+    }
+
+    #[test]
+    fn try_from_unknown() {
+        for byte in 0..=u8::MAX {
+            let expected = Method::ALL
+                .into_iter()
+                .any(|method| method.wire_byte() == byte);
+            assert_eq!(Method::try_from(byte).is_ok(), expected);
+        }
+        assert_eq!(
+            Method::try_from(0x03),
+            Err(MethodDecodeError::Unknown(0x03))
+        );
     }
 }
 
@@ -142,7 +669,7 @@ mod tests_method {
 mod tests_transaction_identifier {
     use super::*;
 
-    const SIGN_JSON: &'static str = "\"f980f643a1af9602564fb1da2fd296bc48e546d0958124c2a466756eb35bcf9e145a0b8eea383672d54ea9f10b67011cbb1df7896dd796de1ff326fbc39edd08\"";
+    pub(super) const SIGN_JSON: &'static str = "\"f980f643a1af9602564fb1da2fd296bc48e546d0958124c2a466756eb35bcf9e145a0b8eea383672d54ea9f10b67011cbb1df7896dd796de1ff326fbc39edd08\"";
 
     #[test]
     fn serialize() {
@@ -168,3 +695,272 @@ mod tests_transaction_identifier {
         assert_eq!(deserialized.sign, sign);
     }
 }
+
+#[cfg(test)]
+mod tests_content {
+    use super::*;
+    use crate::account::prehash;
+
+    #[test]
+    fn append_to_prehash() {
+        let sign = serde_json::from_str(tests_transaction_identifier::SIGN_JSON).unwrap();
+        let target = TransactionIdentifier::new(1, sign);
+        let contents = vec![
+            JellyfishTransactionContent::insert("hello"),
+            JellyfishTransactionContent::insert("session").with_ttl(1_000),
+            JellyfishTransactionContent::modify("world", target.clone()),
+            JellyfishTransactionContent::batch(vec![
+                JellyfishTransactionContent::insert("hello"),
+                JellyfishTransactionContent::remove(target).with_ttl(1_000),
+            ])
+            .unwrap(),
+        ];
+
+        for content in contents {
+            let mut streamed = Prehash::new();
+            content.append_to_prehash(&mut streamed);
+
+            let expected = prehash(&content.build_byte_order());
+            assert_eq!(streamed.finalize(), expected.finalize());
+        }
+    }
+
+    #[test]
+    fn serialize_deserialize_ttl() {
+        let content = JellyfishTransactionContent::insert("session").with_ttl(1_000);
+
+        let ser = serde_json::to_string(&content).unwrap();
+        let de = serde_json::from_str::<JellyfishTransactionContent>(&ser).unwrap();
+
+        assert_eq!(de, content);
+        assert_eq!(de.ttl_nanos(), Some(1_000));
+    }
+
+    #[test]
+    fn deserialize_without_ttl() {
+        let json = r#"{"method":"Insert","record":"hello"}"#;
+        let de = serde_json::from_str::<JellyfishTransactionContent>(json).unwrap();
+
+        assert_eq!(de, JellyfishTransactionContent::insert("hello"));
+        assert_eq!(de.ttl_nanos(), None);
+    }
+
+    #[test]
+    fn serialize_deserialize_append() {
+        let sign = serde_json::from_str(tests_transaction_identifier::SIGN_JSON).unwrap();
+        let content =
+            JellyfishTransactionContent::append("more", TransactionIdentifier::new(1, sign));
+
+        let ser = serde_json::to_string(&content).unwrap();
+        assert!(ser.starts_with(r#"{"method":"append","record":"more","target":"#));
+        let de = serde_json::from_str::<JellyfishTransactionContent>(&ser).unwrap();
+
+        assert_eq!(de, content);
+    }
+
+    #[test]
+    fn byte_order_ttl() {
+        let content = JellyfishTransactionContent::insert("a");
+        assert_eq!(content.build_byte_order(), &[0x01, b'a']);
+
+        // TTL is signed after its tag, and the record is prefixed by its length.
+        let content = content.with_ttl(2);
+        assert_eq!(
+            content.build_byte_order(),
+            &[0x81, 1, 0, 0, 0, b'a', TTL_TAG, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(content.build_byte_order().len(), content.byte_len());
+
+        // Record cannot forge or strip TTL.
+        let forged = JellyfishTransactionContent::insert("a\u{2}\0\0\0\0\0\0\0");
+        assert_ne!(forged.build_byte_order(), content.build_byte_order());
+        let forged = JellyfishTransactionContent::insert("\u{1}\0\0\0a\u{1}\u{2}\0\0\0\0\0\0\0");
+        assert_ne!(forged.build_byte_order(), content.build_byte_order());
+
+        let content = JellyfishTransactionContent::remove(target()).with_ttl(2);
+        assert_eq!(content.build_byte_order().len(), content.byte_len());
+    }
+
+    fn target() -> TransactionIdentifier {
+        let sign = serde_json::from_str(tests_transaction_identifier::SIGN_JSON).unwrap();
+        TransactionIdentifier::new(1, sign)
+    }
+
+    #[test]
+    fn from_byte_order() {
+        let contents = [
+            JellyfishTransactionContent::insert(""),
+            JellyfishTransactionContent::insert("record"),
+            JellyfishTransactionContent::insert("session").with_ttl(1_000),
+            JellyfishTransactionContent::modify("b", target()),
+            JellyfishTransactionContent::modify("b", target()).with_ttl(-1),
+            JellyfishTransactionContent::remove(target()),
+            JellyfishTransactionContent::remove(target()).with_ttl(2),
+            JellyfishTransactionContent::append("tail", target()),
+            JellyfishTransactionContent::batch(vec![
+                JellyfishTransactionContent::insert("a").with_ttl(5),
+                JellyfishTransactionContent::modify("b", target()),
+                JellyfishTransactionContent::remove(target()),
+            ])
+            .unwrap(),
+        ];
+
+        for content in contents {
+            let bytes = content.build_byte_order();
+            let de = JellyfishTransactionContent::from_byte_order(&bytes, 1024).unwrap();
+            assert_eq!(de, content);
+        }
+    }
+
+    #[test]
+    fn from_byte_order_fail() {
+        let decode = |bytes: &[u8]| JellyfishTransactionContent::from_byte_order(bytes, 8);
+
+        // Record length pointing past the end of the buffer
+        let mut bytes = vec![0x81];
+        bytes.extend(6u32.to_le_bytes());
+        bytes.extend(b"abc");
+        assert_eq!(
+            decode(&bytes),
+            Err(DecodeError::UnexpectedEnd {
+                field: "record",
+                requested: 6,
+                remaining: 3,
+            })
+        );
+
+        let bytes = JellyfishTransactionContent::insert("too long record").build_byte_order();
+        assert!(matches!(
+            decode(&bytes),
+            Err(DecodeError::LimitExceeded {
+                field: "record",
+                limit: 8,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            decode(&[0x03]),
+            Err(DecodeError::Malformed {
+                field: "method",
+                ..
+            })
+        ));
+        assert!(matches!(
+            decode(&[0x01, 0xff]),
+            Err(DecodeError::Malformed {
+                field: "record",
+                ..
+            })
+        ));
+        assert!(matches!(
+            decode(&JellyfishTransactionContent::remove(target()).build_byte_order()[..40]),
+            Err(DecodeError::UnexpectedEnd { .. })
+        ));
+
+        let mut bytes = JellyfishTransactionContent::insert("a")
+            .with_ttl(1)
+            .build_byte_order();
+        bytes.push(0);
+        assert!(matches!(
+            decode(&bytes),
+            Err(DecodeError::Malformed {
+                field: "content",
+                ..
+            })
+        ));
+
+        // Batch claiming u32::MAX operations
+        let mut bytes = vec![0x10];
+        bytes.extend(u32::MAX.to_le_bytes());
+        assert!(matches!(
+            decode(&bytes),
+            Err(DecodeError::LimitExceeded {
+                field: "operations",
+                ..
+            })
+        ));
+
+        // Batch inside a batch
+        let inner =
+            JellyfishTransactionContent::batch(vec![JellyfishTransactionContent::insert("a")])
+                .unwrap()
+                .build_byte_order();
+        let mut bytes = vec![0x10];
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend((inner.len() as u32).to_le_bytes());
+        bytes.extend(inner);
+        assert!(matches!(
+            decode(&bytes),
+            Err(DecodeError::Malformed {
+                field: "method",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn serialize_deserialize_batch() {
+        let content = JellyfishTransactionContent::batch(vec![
+            JellyfishTransactionContent::insert("a").with_ttl(5),
+            JellyfishTransactionContent::modify("b", target()),
+            JellyfishTransactionContent::remove(target()),
+        ])
+        .unwrap();
+
+        let ser = serde_json::to_string(&content).unwrap();
+        let de = serde_json::from_str::<JellyfishTransactionContent>(&ser).unwrap();
+
+        assert_eq!(de, content);
+        assert_eq!(de.operations().len(), 3);
+    }
+
+    #[test]
+    fn byte_order_batch() {
+        let content = JellyfishTransactionContent::batch(vec![
+            JellyfishTransactionContent::insert("a"),
+            JellyfishTransactionContent::remove(target()),
+        ])
+        .unwrap();
+
+        let mut expected = vec![0x10, 2, 0, 0, 0, 2, 0, 0, 0, 0x01, b'a'];
+        let remove = JellyfishTransactionContent::remove(target()).build_byte_order();
+        expected.extend((remove.len() as u32).to_le_bytes());
+        expected.extend(remove);
+        assert_eq!(content.build_byte_order(), expected);
+    }
+
+    #[test]
+    fn batch_fail() {
+        let insert = || JellyfishTransactionContent::insert("a");
+        let nested = JellyfishTransactionContent::batch(vec![insert()]).unwrap();
+
+        assert!(JellyfishTransactionContent::batch(vec![]).is_err());
+        assert!(JellyfishTransactionContent::batch(vec![nested]).is_err());
+        assert!(JellyfishTransactionContent::batch(vec![insert(), insert()]).is_err());
+
+        let removes = vec![JellyfishTransactionContent::remove(target()); MAX_BATCH_OPERATIONS + 1];
+        assert!(JellyfishTransactionContent::batch(removes.clone()).is_err());
+        assert!(JellyfishTransactionContent::batch(removes[1..].to_vec()).is_ok());
+    }
+
+    #[test]
+    fn byte_len() {
+        let contents = vec![
+            JellyfishTransactionContent::insert(""),
+            JellyfishTransactionContent::insert("record").with_ttl(1),
+            JellyfishTransactionContent::modify("\u{1f41f}", target()),
+            JellyfishTransactionContent::remove(target()),
+            JellyfishTransactionContent::append("tail", target()).with_ttl(-1),
+            JellyfishTransactionContent::batch(vec![
+                JellyfishTransactionContent::insert("a").with_ttl(1),
+                JellyfishTransactionContent::remove(target()),
+            ])
+            .unwrap(),
+        ];
+
+        for content in contents {
+            assert_eq!(content.byte_len(), content.build_byte_order().len());
+        }
+    }
+}
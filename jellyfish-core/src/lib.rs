@@ -1,21 +1,63 @@
 pub mod account;
+pub mod address;
+pub mod alias;
+#[cfg(any(test, feature = "arbitrary"))]
+pub mod arbitrary;
+pub mod blob;
 pub mod block;
+pub mod bundle;
+pub mod byteorder;
+pub mod chain;
+pub mod checkpoint;
+pub mod clock;
+pub mod codec;
+pub mod compat;
+#[cfg(feature = "compression")]
+pub mod compression;
 pub mod difficulty;
 pub mod digest;
+pub mod filter;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixtures;
+pub mod genesis;
+pub mod hashing;
+pub mod hex0x;
 pub mod jellyfish_transaction;
+pub mod light_client;
+pub mod mempool;
+pub mod ordering;
+pub mod orphan;
+pub mod params;
+pub mod prelude;
+pub mod protocol;
+#[cfg(any(test, feature = "test-util"))]
+pub mod regtest;
+pub mod schema;
+pub mod scheme;
+#[cfg(feature = "crypto-box")]
+pub mod sealed;
+pub mod sig_cache;
 pub mod signature;
+pub mod signer;
+pub mod state;
+pub mod status;
+pub mod subscription;
+pub mod sync;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tcp;
 pub mod timestamp;
 pub mod transaction;
+pub mod window;
 
-mod byteorder;
 mod verification;
 
 pub use account::{Account, SecretAccount};
+pub use byteorder::ByteOrder;
 pub use difficulty::Difficulty;
-pub use digest::Sha256Digest;
+pub use digest::{Digest, Sha256Digest};
 pub use signature::Signature;
 pub use timestamp::Timestamp;
 pub use transaction::Transaction;
 pub use verification::{Verified, Yet};
 
-use byteorder::{ByteOrder, ByteOrderBuilder};
+use byteorder::ByteOrderBuilder;
@@ -0,0 +1,630 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::block::{cumulative_work, BlockError, Header, MerkleProof};
+use crate::checkpoint::Checkpoints;
+use crate::params::ChainParams;
+use crate::transaction::{TransactionError, VerifiedTransaction};
+use crate::{ByteOrder, Difficulty, Transaction, Yet};
+
+/// Default depth below which a light client never switches to another fork.
+pub const DEFAULT_FINALITY_WINDOW: u64 = 100;
+
+/// Maximum number of headers buffered until they connect to the chain.
+pub const MAX_ORPHAN_HEADERS: usize = 64;
+
+/// Client which tracks only block headers.
+///
+/// Headers are verified by their Proof-of-Work, linkage and checkpoints,
+/// and by their difficulty if the client has parameters.
+/// Transactions are verified by merkle proofs against the tracked headers.
+#[derive(Debug, Clone)]
+pub struct LightClient {
+    /// Headers of the best known chain, in ascending order of height.
+    headers: Vec<Header>,
+    checkpoints: Checkpoints,
+    finality_window: u64,
+    params: Option<ChainParams>,
+    /// Verified headers which are not part of `headers`, in arrival order.
+    orphans: VecDeque<Header>,
+}
+
+impl LightClient {
+    /// Create a client which trusts the given genesis header and checkpoints.
+    pub fn new(genesis: Header, checkpoints: Checkpoints) -> Self {
+        Self {
+            headers: vec![genesis],
+            checkpoints,
+            finality_window: DEFAULT_FINALITY_WINDOW,
+            params: None,
+            orphans: VecDeque::new(),
+        }
+    }
+
+    /// Set consensus parameters, by which difficulty of headers and signatures of transactions are verified.
+    ///
+    /// Like [`Chain::expected_difficulty()`](crate::chain::Chain::expected_difficulty),
+    /// the retarget window is truncated at the trusted header given to [`LightClient::new()`].
+    pub fn with_params(self, params: ChainParams) -> Self {
+        Self {
+            params: Some(params),
+            ..self
+        }
+    }
+
+    pub fn params(&self) -> Option<&ChainParams> {
+        self.params.as_ref()
+    }
+
+    /// Set depth below which the client never switches to another fork.
+    pub fn with_finality_window(self, finality_window: u64) -> Self {
+        Self {
+            finality_window,
+            ..self
+        }
+    }
+
+    /// Returns the latest header of the best known chain.
+    pub fn tip(&self) -> &Header {
+        // A client has at least genesis header, so the below unwrap() always succeeds.
+        self.headers.last().unwrap()
+    }
+
+    /// Returns the header at the given height of the best known chain.
+    pub fn header(&self, height: u64) -> Option<&Header> {
+        let index = height.checked_sub(self.headers[0].height())?;
+        self.headers.get(usize::try_from(index).ok()?)
+    }
+
+    /// Returns number of buffered headers which are not part of the best known chain.
+    pub fn orphan_count(&self) -> usize {
+        self.orphans.len()
+    }
+
+    /// Verify the header, then add it to the client.
+    ///
+    /// A header whose previous header is unknown is buffered until it connects.
+    /// If more than [`MAX_ORPHAN_HEADERS`] headers are buffered, the oldest one is discarded.
+    ///
+    /// When buffered headers form a branch containing more work than the best known chain above its fork point,
+    /// the client switches to the branch.
+    /// Headers which fork the chain below the finality window are rejected,
+    /// and so are headers following the chain with a difficulty other than [`ChainParams::difficulty_after()`].
+    /// A buffered header with a wrong difficulty never connects.
+    pub fn accept_header(&mut self, header: Header) -> Result<(), LightClientError> {
+        header
+            .verify_proof_of_work()
+            .map_err(LightClientError::Header)?;
+
+        if !self.checkpoints.check(&header) {
+            return Err(LightClientError::Checkpoint {
+                height: header.height(),
+            });
+        }
+
+        let is_known = self
+            .header(header.height())
+            .into_iter()
+            .chain(&self.orphans)
+            .any(|known| known.digest() == header.digest());
+        if is_known {
+            return Ok(());
+        }
+
+        if !self.is_above_finality(header.height()) {
+            return Err(LightClientError::BelowFinality {
+                height: header.height(),
+            });
+        }
+
+        if let Some(expected) = self.wrong_difficulty(&header, &[]) {
+            return Err(LightClientError::WrongDifficulty {
+                height: header.height(),
+                expected,
+                declared: header.difficulty(),
+            });
+        }
+
+        if self.orphans.len() >= MAX_ORPHAN_HEADERS {
+            self.orphans.pop_front();
+        }
+        self.orphans.push_back(header);
+        self.connect_orphans();
+
+        Ok(())
+    }
+
+    /// Verify that the transaction is contained in the block at the given height, and verify its sign.
+    ///
+    /// The sign must be created for the network of the header,
+    /// under [`ChainParams::transaction_context()`] if the client has parameters of protocol version 2 or later.
+    pub fn verify_inclusion<T>(
+        &self,
+        height: u64,
+        proof: &MerkleProof,
+        tx: &Transaction<T, Yet>,
+    ) -> Result<VerifiedTransaction<T>, LightClientError>
+    where
+        T: ByteOrder + Clone,
+    {
+        let header = self
+            .header(height)
+            .ok_or(LightClientError::UnknownHeight(height))?;
+
+        if !proof.verify(header.merkle_root(), tx) {
+            return Err(LightClientError::Proof);
+        }
+
+        let context = self
+            .params
+            .as_ref()
+            .and_then(ChainParams::transaction_context);
+        tx.clone()
+            .verify_with(header.network(), context, None)
+            .map_err(LightClientError::Transaction)
+    }
+
+    /// Returns whether a header at the given height may replace the current one.
+    fn is_above_finality(&self, height: u64) -> bool {
+        height > self.headers[0].height()
+            && height.saturating_add(self.finality_window) > self.tip().height()
+    }
+
+    /// Returns the difficulty which the header must declare, if the header follows the best known chain or the branch and declares another one.
+    fn wrong_difficulty(&self, header: &Header, branch: &[Header]) -> Option<Difficulty> {
+        let params = self.params.as_ref()?;
+        let previous = self.parent(header, branch)?;
+        let expected = params.difficulty_after(previous, |h| self.parent(h, branch));
+        (header.difficulty() != expected).then_some(expected)
+    }
+
+    /// Returns the header which the given one follows, looking up the branch, then the best known chain.
+    fn parent<'a>(&'a self, header: &Header, branch: &'a [Header]) -> Option<&'a Header> {
+        let height = header.height().checked_sub(1)?;
+        branch
+            .iter()
+            .rev()
+            .find(|previous| header.follows(previous))
+            .or_else(|| {
+                self.header(height)
+                    .filter(|previous| header.follows(previous))
+            })
+    }
+
+    /// Switch to the branch formed by buffered headers containing the most work,
+    /// if it contains more work than the headers of the current chain which it replaces.
+    fn connect_orphans(&mut self) {
+        while let Some(branch) = self.heaviest_branch() {
+            let fork_height = branch[0].height();
+            let fork_index = (fork_height - self.headers[0].height()) as usize;
+            if cumulative_work(&branch) <= cumulative_work(&self.headers[fork_index..]) {
+                break;
+            }
+
+            self.orphans
+                .retain(|orphan| !branch.iter().any(|h| h.digest() == orphan.digest()));
+            self.headers.truncate(fork_index);
+            self.headers.extend(branch);
+        }
+
+        // Drop headers which can never be connected.
+        let first_height = self.headers[0].height();
+        let floor = self.tip().height().saturating_sub(self.finality_window);
+        self.orphans
+            .retain(|orphan| orphan.height() > first_height && orphan.height() > floor);
+    }
+
+    /// Returns the branch of buffered headers containing the most work, which forks from the best known chain.
+    fn heaviest_branch(&self) -> Option<Vec<Header>> {
+        self.orphans
+            .iter()
+            .filter(|orphan| self.is_above_finality(orphan.height()))
+            .filter(|orphan| self.parent(orphan, &[]).is_some())
+            .filter(|orphan| self.wrong_difficulty(orphan, &[]).is_none())
+            .map(|root| self.extend_branch(root))
+            .max_by_key(|branch| cumulative_work(branch))
+    }
+
+    /// Extend the branch by buffered headers, as long as they follow it with a right difficulty.
+    fn extend_branch(&self, root: &Header) -> Vec<Header> {
+        let mut branch = vec![root.clone()];
+        while let Some(next) = self.orphans.iter().find(|orphan| {
+            orphan.follows(&branch[branch.len() - 1])
+                && self.wrong_difficulty(orphan, &branch).is_none()
+        }) {
+            branch.push(next.clone());
+        }
+        branch
+    }
+}
+
+/// An error occurred in a light client.
+#[derive(Debug)]
+pub enum LightClientError {
+    /// Header's Proof-of-Work is invalid.
+    Header(BlockError),
+    /// Header conflicts with a checkpoint.
+    Checkpoint { height: u64 },
+    /// Header forks the chain below the finality window.
+    BelowFinality { height: u64 },
+    /// Header declares a difficulty other than the one demanded by the history it follows.
+    WrongDifficulty {
+        height: u64,
+        expected: Difficulty,
+        declared: Difficulty,
+    },
+    /// No header is tracked at the height.
+    UnknownHeight(u64),
+    /// Merkle proof does not match with header's merkle root.
+    Proof,
+    /// Transaction verification failed.
+    Transaction(TransactionError),
+}
+
+impl Display for LightClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use LightClientError::*;
+
+        match self {
+            Header(e) => write!(f, "Header verification failed: {}", e),
+            Checkpoint { height } => write!(f, "Header conflicts with checkpoint at {}.", height),
+            BelowFinality { height } => {
+                write!(f, "Header at {} forks below finality window.", height)
+            }
+            WrongDifficulty {
+                height,
+                expected,
+                declared,
+            } => write!(
+                f,
+                "Header at {} declares difficulty {:?}, but {:?} is expected.",
+                height, declared, expected
+            ),
+            UnknownHeight(height) => write!(f, "No header is tracked at {}.", height),
+            Proof => write!(f, "Merkle proof does not match with header."),
+            Transaction(e) => write!(f, "Transaction verification failed: {}", e),
+        }
+    }
+}
+
+impl Error for LightClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use LightClientError::*;
+
+        match self {
+            Header(e) => Some(e),
+            Transaction(e) => Some(e),
+            Checkpoint { .. }
+            | BelowFinality { .. }
+            | WrongDifficulty { .. }
+            | UnknownHeight(_)
+            | Proof => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::block::Block;
+    use crate::chain::tests_stab::*;
+    use crate::chain::Chain;
+    use crate::difficulty::MAX_DIFFICULTY;
+    use crate::digest::Digest;
+    use crate::fixtures::{self, FixtureChain};
+    use crate::{SecretAccount, Timestamp, Verified};
+
+    /// Mine a block containing 3 transactions.
+    fn mine3(previous: &Header, account: &SecretAccount) -> Block<Stab, Verified, Yet> {
+        let height = previous.height() + 1;
+        let transactions = (0..3)
+            .map(|i| Transaction::create(account, Timestamp::now(), Stab(height * 10 + i)))
+            .collect();
+        mine_transactions(height, *previous.digest(), transactions)
+    }
+
+    /// Build a chain whose tip height is `len`.
+    fn build_chain(len: u64) -> Chain<Stab> {
+        let account = fixtures::account(0);
+        let genesis = unverified(&mine(0, Digest::ZERO, &account));
+        let mut chain = Chain::new(
+            genesis
+                .verify_block(|_| true)
+                .unwrap()
+                .verify_transactions()
+                .unwrap(),
+        );
+
+        for _ in 0..len {
+            let block = mine3(chain.tip().header(), &account);
+            chain.append(unverified(&block)).unwrap();
+        }
+        chain
+    }
+
+    fn header(chain: &Chain<Stab>, height: u64) -> Header {
+        chain.block(height).unwrap().header().clone()
+    }
+
+    fn unverified_tx(tx: &Transaction<Stab, Verified>) -> Transaction<Stab, Yet> {
+        let ser = serde_json::to_string(tx).unwrap();
+        serde_json::from_str(&ser).unwrap()
+    }
+
+    #[test]
+    fn verify_inclusion() {
+        let chain = build_chain(5);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+
+        for height in 1..=5 {
+            client.accept_header(header(&chain, height)).unwrap();
+        }
+        assert_eq!(client.tip(), chain.tip().header());
+
+        for height in 1..=5 {
+            let block = chain.block(height).unwrap();
+            for (i, tx) in block.transactions().iter().enumerate() {
+                let proof = block.merkle_proof(i).unwrap();
+                let verified = client
+                    .verify_inclusion(height, &proof, &unverified_tx(tx))
+                    .unwrap();
+                assert_eq!(&verified, tx);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_inclusion_fail_forged_proof() {
+        let chain = build_chain(2);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+        client.accept_header(header(&chain, 1)).unwrap();
+        client.accept_header(header(&chain, 2)).unwrap();
+
+        let block = chain.block(2).unwrap();
+        let proof = block.merkle_proof(0).unwrap();
+        let tx = unverified_tx(&block.transactions()[1]);
+
+        let result = client.verify_inclusion(2, &proof, &tx);
+        assert!(matches!(result, Err(LightClientError::Proof)));
+
+        // Valid proof, but wrong height
+        let proof = block.merkle_proof(1).unwrap();
+        let result = client.verify_inclusion(1, &proof, &tx);
+        assert!(matches!(result, Err(LightClientError::Proof)));
+
+        let result = client.verify_inclusion(3, &proof, &tx);
+        assert!(matches!(result, Err(LightClientError::UnknownHeight(3))));
+    }
+
+    #[test]
+    fn accept_header_orphan() {
+        let chain = build_chain(5);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+
+        for height in [1, 2, 4, 5] {
+            client.accept_header(header(&chain, height)).unwrap();
+        }
+        assert_eq!(client.tip().height(), 2);
+        assert_eq!(client.orphan_count(), 2);
+
+        // Missing header arrives, then orphans connect.
+        client.accept_header(header(&chain, 3)).unwrap();
+        assert_eq!(client.tip(), chain.tip().header());
+        assert_eq!(client.orphan_count(), 0);
+    }
+
+    #[test]
+    fn accept_header_duplicate() {
+        let chain = build_chain(2);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+
+        client.accept_header(header(&chain, 1)).unwrap();
+        client.accept_header(header(&chain, 1)).unwrap();
+        client.accept_header(header(&chain, 0)).unwrap();
+
+        assert_eq!(client.tip().height(), 1);
+        assert_eq!(client.orphan_count(), 0);
+    }
+
+    #[test]
+    fn accept_header_switch_to_longer_fork() {
+        let chain = build_chain(5);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+        for height in 1..=5 {
+            client.accept_header(header(&chain, height)).unwrap();
+        }
+
+        // Build a fork from height 3
+        let account = fixtures::account(1);
+        let fork4 = mine3(&header(&chain, 3), &account).header().clone();
+        let fork5 = mine3(&fork4, &account).header().clone();
+        let fork6 = mine3(&fork5, &account).header().clone();
+
+        // The fork is not longer than the current chain yet.
+        client.accept_header(fork4.clone()).unwrap();
+        client.accept_header(fork5).unwrap();
+        assert_eq!(client.tip(), chain.tip().header());
+
+        client.accept_header(fork6.clone()).unwrap();
+        assert_eq!(client.tip(), &fork6);
+        assert_eq!(client.header(4), Some(&fork4));
+    }
+
+    #[test]
+    fn accept_header_switch_to_heavier_fork() {
+        let chain = build_chain(5);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+        for height in 1..=5 {
+            client.accept_header(header(&chain, height)).unwrap();
+        }
+
+        // A single header from height 3, which contains more work than the two headers it replaces.
+        let heavy = mine_at(&header(&chain, 3), Duration::ZERO, Difficulty::new(8));
+        assert!(heavy.work() > cumulative_work(&[header(&chain, 4), header(&chain, 5)]));
+
+        client.accept_header(heavy.clone()).unwrap();
+        assert_eq!(client.tip(), &heavy);
+        assert_eq!(client.header(5), None);
+
+        // The replaced headers are longer, but never win back.
+        client.accept_header(header(&chain, 4)).unwrap();
+        client.accept_header(header(&chain, 5)).unwrap();
+        assert_eq!(client.tip(), &heavy);
+    }
+
+    /// Retargets every 4 blocks aiming at a block per minute, by at most 2 steps.
+    fn retarget_params() -> ChainParams {
+        ChainParams {
+            min_difficulty: Difficulty::new(1),
+            max_difficulty: Difficulty::new(8),
+            target_block_interval: Duration::from_secs(60),
+            retarget_window: 4,
+            retarget_clamp: 2,
+            ..ChainParams::regtest()
+        }
+    }
+
+    /// Mine a header following `previous` by the given interval, declaring the difficulty.
+    fn mine_at(previous: &Header, interval: Duration, difficulty: Difficulty) -> Header {
+        let height = previous.height() + 1;
+        let timestamp = previous.timestamp() + interval;
+        let tx = Transaction::create(&fixtures::account(1), timestamp, Stab(height));
+        let block =
+            Block::create(height, timestamp, *previous.digest(), difficulty, vec![tx]).unwrap();
+        block.mine().header().clone()
+    }
+
+    #[test]
+    fn accept_header_retarget() {
+        let params = retarget_params();
+        let tx = Transaction::create(&fixtures::account(1), Timestamp::EPOCH, Stab(0));
+        let genesis = Block::create(
+            0,
+            Timestamp::EPOCH,
+            Digest::ZERO,
+            Difficulty::new(1),
+            vec![tx],
+        )
+        .unwrap()
+        .mine()
+        .header()
+        .clone();
+        let mut client = LightClient::new(genesis.clone(), Checkpoints::new()).with_params(params);
+
+        // Blocks were too fast, so difficulty is raised by the clamp at the boundary.
+        let fast = Duration::from_secs(1);
+        let mut headers = vec![genesis];
+        for _ in 0..3 {
+            let header = mine_at(&headers[headers.len() - 1], fast, Difficulty::new(1));
+            client.accept_header(header.clone()).unwrap();
+            headers.push(header);
+        }
+        let tip = headers[headers.len() - 1].clone();
+
+        let result = client.accept_header(mine_at(&tip, fast, Difficulty::new(1)));
+        assert!(matches!(
+            result,
+            Err(LightClientError::WrongDifficulty { height: 4, expected, declared })
+                if expected == Difficulty::new(3) && declared == Difficulty::new(1)
+        ));
+        let boundary = mine_at(&tip, fast, Difficulty::new(3));
+        client.accept_header(boundary.clone()).unwrap();
+        assert_eq!(client.tip(), &boundary);
+
+        // An orphan with a wrong difficulty never connects, even after its parent arrives.
+        let parent = mine_at(&boundary, fast, Difficulty::new(3));
+        let wrong = mine_at(&parent, fast, Difficulty::new(4));
+        client.accept_header(wrong).unwrap();
+        client.accept_header(parent.clone()).unwrap();
+        assert_eq!(client.tip(), &parent);
+        assert_eq!(client.orphan_count(), 1);
+    }
+
+    #[test]
+    fn verify_inclusion_with_params() {
+        let v2 = ChainParams {
+            protocol_version: 2,
+            ..ChainParams::regtest()
+        };
+        let fixture = FixtureChain::new(v2.clone())
+            .with_block(|b| b.insert("a"))
+            .with_block(|b| b.insert("b"));
+        let chain = fixture.chain();
+        let block = chain.block(1).unwrap();
+        let proof = block.merkle_proof(0).unwrap();
+        let tx = block.transactions()[0].clone().into_unverified();
+        let genesis = chain.block(0).unwrap().header().clone();
+
+        let mut client = LightClient::new(genesis.clone(), Checkpoints::new()).with_params(v2);
+        client.accept_header(block.header().clone()).unwrap();
+        let verified = client.verify_inclusion(1, &proof, &tx).unwrap();
+        assert_eq!(verified.content().record(), Some("b"));
+
+        // Signature under the transaction context is not valid in the legacy format.
+        let mut client = LightClient::new(genesis, Checkpoints::new());
+        client.accept_header(block.header().clone()).unwrap();
+        assert!(matches!(
+            client.verify_inclusion(1, &proof, &tx),
+            Err(LightClientError::Transaction(_))
+        ));
+    }
+
+    #[test]
+    fn accept_header_fail_below_finality() {
+        let chain = build_chain(5);
+        let mut client =
+            LightClient::new(header(&chain, 0), Checkpoints::new()).with_finality_window(2);
+        for height in 1..=5 {
+            client.accept_header(header(&chain, height)).unwrap();
+        }
+
+        let fork = mine3(&header(&chain, 2), &fixtures::account(1));
+        let result = client.accept_header(fork.header().clone());
+
+        assert!(matches!(
+            result,
+            Err(LightClientError::BelowFinality { height: 3 })
+        ));
+    }
+
+    #[test]
+    fn accept_header_fail_checkpoint() {
+        let chain = build_chain(2);
+        let checkpoints = [(2, Digest::ZERO)].into_iter().collect();
+        let mut client = LightClient::new(header(&chain, 0), checkpoints);
+
+        client.accept_header(header(&chain, 1)).unwrap();
+        let result = client.accept_header(header(&chain, 2));
+
+        assert!(matches!(
+            result,
+            Err(LightClientError::Checkpoint { height: 2 })
+        ));
+    }
+
+    #[test]
+    fn accept_header_fail_proof_of_work() {
+        let chain = build_chain(1);
+        let mut client = LightClient::new(header(&chain, 0), Checkpoints::new());
+
+        // Block without executing Proof-of-Work
+        let tip = chain.tip();
+        let block = Block::create(
+            2,
+            Timestamp::now(),
+            *tip.header().digest(),
+            MAX_DIFFICULTY,
+            tip.transactions().to_vec(),
+        )
+        .unwrap();
+        let result = client.accept_header(block.header().clone());
+
+        assert!(matches!(
+            result,
+            Err(LightClientError::Header(BlockError::Difficulty))
+        ));
+    }
+}
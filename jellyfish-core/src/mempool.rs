@@ -0,0 +1,378 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::block::Block;
+use crate::chain::{Chain, Liveness, TargetError};
+use crate::clock::Clock;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+use crate::clock::SystemClock;
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::params::{ChainParams, MAX_HEADER_BYTES};
+use crate::sig_cache::SigCache;
+use crate::state::RemovedTargets;
+use crate::transaction::TransactionError;
+use crate::{ByteOrder, Transaction, Verified, Yet};
+
+/// Verified transactions waiting to be included in a block, in arrival order.
+#[derive(Debug, Clone)]
+pub struct Mempool<T> {
+    transactions: Vec<Transaction<T, Verified>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<T> Mempool<T> {
+    /// Returns an empty pool, which reads the system time.
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Returns an empty pool, which reads the time from the given clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            transactions: vec![],
+            clock,
+        }
+    }
+
+    /// Add the transaction to the pool.
+    ///
+    /// # Returns
+    /// `false` if the same transaction is already in the pool.
+    pub fn insert(&mut self, tx: Transaction<T, Verified>) -> bool {
+        if self.contains(&tx) {
+            false
+        } else {
+            self.transactions.push(tx);
+            true
+        }
+    }
+
+    pub fn transactions(&self) -> &[Transaction<T, Verified>] {
+        &self.transactions
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Remove transactions which are included in the block.
+    pub fn remove_included<VT, VB>(&mut self, block: &Block<T, VT, VB>) {
+        self.transactions
+            .retain(|tx| block.transactions().iter().all(|b| b.sign() != tx.sign()));
+    }
+
+    /// Remove transactions whose timestamp is older than `max_age` from now.
+    pub fn remove_expired(&mut self, max_age: Duration) {
+        let now = self.clock.now();
+        self.transactions
+            .retain(|tx| tx.timestamp() + max_age >= now);
+    }
+
+    /// Signatures are compared by `==` rather than in constant time,
+    /// since all of them are public and the result does not decide validity.
+    fn contains(&self, tx: &Transaction<T, Verified>) -> bool {
+        self.transactions.iter().any(|t| t.sign() == tx.sign())
+    }
+}
+
+impl<T: ByteOrder> Mempool<T> {
+    /// Verify the transaction by [`Transaction::verify_with_params()`], then add it to the pool.
+    ///
+    /// # Returns
+    /// `Ok(false)` if the same transaction is already in the pool.
+    pub fn submit(
+        &mut self,
+        tx: Transaction<T, Yet>,
+        params: &ChainParams,
+    ) -> Result<bool, TransactionError> {
+        let tx = tx.verify_with_params(params)?;
+        Ok(self.insert(tx))
+    }
+
+    /// Same as [`Mempool::submit()`], but the verification is remembered by the cache,
+    /// so that the block including the transaction is verified by [`Block::verify_transactions_cached_with_params()`] without recalculation.
+    pub fn submit_cached(
+        &mut self,
+        tx: Transaction<T, Yet>,
+        params: &ChainParams,
+        cache: &SigCache,
+    ) -> Result<bool, TransactionError> {
+        let tx = tx.verify_cached_with_params(params, cache)?;
+        Ok(self.insert(tx))
+    }
+}
+
+impl Mempool<JellyfishTransactionContent> {
+    /// Add the transaction to the pool if all of its targets are resolved by [`Chain::resolve_target()`] and live.
+    ///
+    /// # Returns
+    /// `Ok(false)` if the same transaction is already in the pool.
+    pub fn admit(
+        &mut self,
+        tx: Transaction<JellyfishTransactionContent, Verified>,
+        chain: &Chain<JellyfishTransactionContent>,
+    ) -> Result<bool, TargetError> {
+        let targets = tx
+            .content()
+            .operations()
+            .iter()
+            .filter_map(JellyfishTransactionContent::target_transaction);
+        for target in targets {
+            if chain.resolve_target(target)?.liveness == Liveness::Expired {
+                return Err(TargetError::Expired);
+            }
+        }
+        Ok(self.insert(tx))
+    }
+
+    /// Returns at most `max_count` transactions to be included in a new block, in arrival order.
+    ///
+    /// Transactions targeting a record removed by an earlier selected transaction are skipped,
+    /// so that the block never fails with [`StateError::IntraBlockConflict`](crate::state::StateError::IntraBlockConflict).
+    /// Skipped transactions stay in the pool.
+    pub fn block_template(
+        &self,
+        max_count: usize,
+    ) -> Vec<Transaction<JellyfishTransactionContent, Verified>> {
+        self.select(max_count, usize::MAX)
+    }
+
+    /// Same as [`Mempool::block_template()`], but the template satisfies limits of the parameters,
+    /// that is, the number of transactions and the size of the block, reserving [`MAX_HEADER_BYTES`] for its header.
+    /// Transactions which would exceed the size limit are skipped.
+    pub fn block_template_with_params(
+        &self,
+        params: &ChainParams,
+    ) -> Vec<Transaction<JellyfishTransactionContent, Verified>> {
+        let max_bytes = params.max_block_bytes.saturating_sub(MAX_HEADER_BYTES);
+        self.select(params.max_block_transactions, max_bytes)
+    }
+
+    fn select(
+        &self,
+        max_count: usize,
+        max_bytes: usize,
+    ) -> Vec<Transaction<JellyfishTransactionContent, Verified>> {
+        let mut removed = RemovedTargets::default();
+        let mut template = vec![];
+        let mut bytes = 0usize;
+
+        for tx in &self.transactions {
+            if template.len() >= max_count {
+                break;
+            }
+            let tx_bytes = tx.encoded_size();
+            if bytes.saturating_add(tx_bytes) > max_bytes {
+                continue;
+            }
+            if removed
+                .check_and_record(template.len(), tx.content())
+                .is_ok()
+            {
+                bytes += tx_bytes;
+                template.push(tx.clone());
+            }
+        }
+        template
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+impl<T> Default for Mempool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::digest::Digest;
+    use crate::fixtures::{self, FixtureChain};
+    use crate::jellyfish_transaction::TransactionIdentifier;
+    use crate::{SecretAccount, Timestamp};
+
+    #[test]
+    fn admit() {
+        let fixture = FixtureChain::new(ChainParams::regtest())
+            .with_block(|b| b.insert("a").insert("b"))
+            .with_block(|b| {
+                let target = b.tx(1);
+                b.remove(target)
+                    .content(JellyfishTransactionContent::insert("c").with_ttl(1))
+            })
+            .with_block(|b| b.insert("d"));
+        let ids = fixture.ids();
+        let account = &fixture.accounts()[0];
+        let create_tx = |content| Transaction::create(account, Timestamp::now(), content);
+        let mut mempool = Mempool::new();
+
+        let modify = create_tx(JellyfishTransactionContent::modify("a2", ids[0].clone()));
+        assert_eq!(mempool.admit(modify.clone(), fixture.chain()), Ok(true));
+        assert_eq!(mempool.admit(modify, fixture.chain()), Ok(false));
+
+        let removed = create_tx(JellyfishTransactionContent::modify("b2", ids[1].clone()));
+        assert_eq!(
+            mempool.admit(removed, fixture.chain()),
+            Err(TargetError::AlreadyRemoved {
+                removed_at_height: 1
+            })
+        );
+        let expired = create_tx(JellyfishTransactionContent::remove(ids[3].clone()));
+        assert_eq!(
+            mempool.admit(expired, fixture.chain()),
+            Err(TargetError::Expired)
+        );
+        let unknown = create_tx(JellyfishTransactionContent::remove(
+            TransactionIdentifier::new(9, ids[0].sign),
+        ));
+        assert_eq!(
+            mempool.admit(unknown, fixture.chain()),
+            Err(TargetError::HeightOutOfRange { height: 9 })
+        );
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn submit() {
+        let v1 = ChainParams::mainnet();
+        let v2 = ChainParams {
+            protocol_version: 2,
+            ..v1.clone()
+        };
+        let account = fixtures::account(0);
+        let tx = Transaction::create_for(
+            &v2,
+            &account,
+            Timestamp::now(),
+            JellyfishTransactionContent::insert("hello"),
+        )
+        .into_unverified();
+        let mut mempool = Mempool::new();
+
+        assert!(mempool.submit(tx.clone(), &v1).is_err());
+        assert!(mempool.submit(tx.clone(), &v2).unwrap());
+        assert!(!mempool.submit(tx, &v2).unwrap());
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn submit_cached() {
+        let params = ChainParams {
+            protocol_version: 2,
+            ..ChainParams::regtest()
+        };
+        let account = fixtures::account(0);
+        let cache = SigCache::new();
+        let mut mempool = Mempool::new();
+        for record in ["a", "b"] {
+            let content = JellyfishTransactionContent::insert(record);
+            let tx = Transaction::create_for(&params, &account, Timestamp::now(), content);
+            assert!(mempool
+                .submit_cached(tx.into_unverified(), &params, &cache)
+                .unwrap());
+        }
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+
+        let mut block = Block::create(
+            1,
+            Timestamp::now(),
+            Digest::ZERO,
+            params.min_difficulty,
+            mempool.block_template_with_params(&params),
+        )
+        .unwrap();
+        block.header_mut().modify_network(params.network());
+        let block = block.into_unverified();
+
+        assert!(block
+            .verify_transactions_cached_with_params(&params, &cache)
+            .is_ok());
+        assert_eq!((cache.hits(), cache.misses()), (2, 2));
+    }
+
+    #[test]
+    fn insert_duplicate() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let tx = Transaction::create(
+            &account,
+            Timestamp::now(),
+            JellyfishTransactionContent::insert("hello"),
+        );
+        let mut mempool = Mempool::new();
+
+        assert!(mempool.insert(tx.clone()));
+        assert!(!mempool.insert(tx));
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn block_template_skip_conflict() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let create_tx = |content| Transaction::create(&account, Timestamp::now(), content);
+
+        let insert = create_tx(JellyfishTransactionContent::insert("hello"));
+        let target = TransactionIdentifier::new(1, *insert.sign());
+        let other = TransactionIdentifier::new(2, *insert.sign());
+
+        let mut mempool = Mempool::new();
+        mempool.insert(create_tx(JellyfishTransactionContent::modify(
+            "a",
+            target.clone(),
+        )));
+        mempool.insert(create_tx(JellyfishTransactionContent::remove(
+            target.clone(),
+        )));
+        // Conflicts with the above Remove
+        mempool.insert(create_tx(JellyfishTransactionContent::modify(
+            "b",
+            target.clone(),
+        )));
+        mempool.insert(create_tx(JellyfishTransactionContent::remove(target)));
+        mempool.insert(create_tx(JellyfishTransactionContent::modify("c", other)));
+
+        let template = mempool.block_template(10);
+        let signs = template.iter().map(|tx| *tx.sign()).collect::<Vec<_>>();
+        let expected = [0, 1, 4]
+            .iter()
+            .map(|&i| *mempool.transactions()[i].sign())
+            .collect::<Vec<_>>();
+        assert_eq!(signs, expected);
+
+        assert_eq!(mempool.block_template(2).len(), 2);
+        // Skipped transactions stay in the pool.
+        assert_eq!(mempool.len(), 5);
+    }
+
+    #[test]
+    fn remove_expired() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let create_tx = |nanos| {
+            let content = JellyfishTransactionContent::insert("hello");
+            Transaction::create(&account, Timestamp::from_nanos(nanos), content)
+        };
+        let clock = Arc::new(ManualClock::new(Timestamp::from_nanos(1_000)));
+        let mut mempool = Mempool::with_clock(clock.clone());
+        mempool.insert(create_tx(900));
+        mempool.insert(create_tx(1_000));
+
+        let max_age = Duration::from_nanos(100);
+        mempool.remove_expired(max_age);
+        assert_eq!(mempool.len(), 2);
+
+        clock.advance(Duration::from_nanos(1));
+        mempool.remove_expired(max_age);
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(mempool.transactions()[0].timestamp().nanos(), 1_000);
+
+        clock.set(Timestamp::from_nanos(2_000));
+        mempool.remove_expired(max_age);
+        assert!(mempool.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Canonical ordering shared by consensus rules.
+//!
+//! Whenever the protocol requires elements in a specific order, the order is the [`Ord`] of the elements,
+//! which is byte-lexicographic for [`Account`](crate::Account) and [`Signature`](crate::Signature).
+//! Sorting through the helpers below keeps every such rule on the same code path,
+//! so that all implementations agree on it.
+
+/// Sort elements in the canonical order.
+///
+/// The sort is stable, so equal elements keep their relative order.
+pub fn canonical_sort<T: Ord>(items: &mut [T]) {
+    items.sort();
+}
+
+/// Sort elements in the canonical order of their keys.
+///
+/// The sort is stable, so elements with equal keys keep their relative order.
+pub fn canonical_sort_by_key<T, K, F>(items: &mut [T], key: F)
+where
+    K: Ord + ?Sized,
+    F: Fn(&T) -> &K,
+{
+    items.sort_by(|a, b| key(a).cmp(key(b)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::fmt::Debug;
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use super::*;
+    use crate::jellyfish_transaction::TransactionIdentifier;
+    use crate::{Account, SecretAccount, Signature};
+
+    /// Asserts that the ordering is total and antisymmetric, and agrees with byte-lexicographic order.
+    fn assert_byte_lexicographic<T, F>(items: &[T], bytes: F)
+    where
+        T: Ord + Debug,
+        F: Fn(&T) -> Vec<u8>,
+    {
+        for a in items {
+            for b in items {
+                let ord = a.cmp(b);
+                assert_eq!(ord, bytes(a).cmp(&bytes(b)));
+                assert_eq!(b.cmp(a), ord.reverse());
+                assert_eq!(ord == Ordering::Equal, a == b);
+            }
+        }
+    }
+
+    /// Asserts that sorting commutes with serialization round trip.
+    fn assert_stable_across_round_trip<T>(items: &[T])
+    where
+        T: Ord + Clone + Debug + Serialize + DeserializeOwned,
+    {
+        let mut sorted = items.to_vec();
+        canonical_sort(&mut sorted);
+
+        let json = serde_json::to_string(items).unwrap();
+        let mut restored: Vec<T> = serde_json::from_str(&json).unwrap();
+        canonical_sort(&mut restored);
+
+        assert_eq!(sorted, restored);
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    fn accounts() -> Vec<Account> {
+        (0..30)
+            .map(|_| SecretAccount::create(&mut rand_core::OsRng {}).to_public())
+            .collect()
+    }
+
+    fn signs() -> Vec<Signature> {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        (0..30)
+            .map(|i: u32| account.sign(&i.to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn account_order() {
+        let accounts = accounts();
+        assert_byte_lexicographic(&accounts, |a| a.as_bytes().to_vec());
+        assert_stable_across_round_trip(&accounts);
+    }
+
+    #[test]
+    fn signature_order() {
+        let signs = signs();
+        assert_byte_lexicographic(&signs, |s| s.as_ref().to_vec());
+        assert_stable_across_round_trip(&signs);
+    }
+
+    #[test]
+    fn identifier_order() {
+        let ids = signs()
+            .into_iter()
+            .enumerate()
+            .map(|(i, sign)| TransactionIdentifier::new(i as u64 % 3, sign))
+            .collect::<Vec<_>>();
+        assert_byte_lexicographic(&ids, |id| {
+            let mut bytes = id.height.to_be_bytes().to_vec();
+            bytes.extend(id.sign.as_ref());
+            bytes
+        });
+        assert_stable_across_round_trip(&ids);
+    }
+
+    #[test]
+    fn sort_by_key_stable() {
+        let mut items = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+        canonical_sort_by_key(&mut items, |(key, _)| key);
+        assert_eq!(items, vec![(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]);
+    }
+}
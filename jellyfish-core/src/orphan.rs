@@ -0,0 +1,455 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::Clock;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+use crate::clock::SystemClock;
+use crate::jellyfish_transaction::{JellyfishTransactionContent, TransactionIdentifier};
+use crate::mempool::Mempool;
+use crate::state::{LedgerState, StateError};
+use crate::{Signature, Timestamp, Transaction, Verified};
+
+/// Default maximum number of transactions in an [`OrphanPool`].
+pub const DEFAULT_ORPHAN_CAPACITY: usize = 1000;
+
+type Tx = Transaction<JellyfishTransactionContent, Verified>;
+
+/// Transactions whose targets are known neither to the ledger state nor to the mempool, in arrival order.
+///
+/// A transaction may arrive before the transaction it targets, e.g., by races of gossip.
+/// Such a transaction waits here, and is moved to the mempool by [`OrphanPool::resolve()`]
+/// when all of its targets become known.
+/// When the pool is full, the oldest transaction is evicted.
+#[derive(Debug, Clone)]
+pub struct OrphanPool {
+    orphans: VecDeque<(Tx, Timestamp)>,
+    capacity: usize,
+    clock: Arc<dyn Clock>,
+}
+
+/// Where [`OrphanPool::admit()`] placed a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// All targets are known, so the transaction was validated and added to the mempool.
+    Mempool,
+    /// Some targets are unknown, so the transaction waits in the orphan pool.
+    Orphan,
+}
+
+impl OrphanPool {
+    /// Returns an empty pool, which reads the system time.
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Returns an empty pool, which reads the time from the given clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            orphans: VecDeque::new(),
+            capacity: DEFAULT_ORPHAN_CAPACITY,
+            clock,
+        }
+    }
+
+    /// Set the maximum number of transactions in the pool.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn transactions(&self) -> impl Iterator<Item = &Tx> {
+        self.orphans.iter().map(|(tx, _)| tx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.orphans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty()
+    }
+
+    /// Add the transaction to the mempool if its targets are resolvable, otherwise, to this pool.
+    ///
+    /// Since the new transaction may be a target of orphans, [`OrphanPool::resolve()`] is executed after adding it to the mempool.
+    pub fn admit(
+        &mut self,
+        tx: Tx,
+        state: &LedgerState,
+        mempool: &mut Mempool<JellyfishTransactionContent>,
+    ) -> Result<Admission, OrphanError> {
+        if is_resolvable(&tx, state, mempool) {
+            validate(&tx, state, mempool)?;
+            mempool.insert(tx);
+            self.resolve(state, mempool);
+            Ok(Admission::Mempool)
+        } else {
+            self.insert(tx)?;
+            Ok(Admission::Orphan)
+        }
+    }
+
+    /// Add the transaction to the pool, evicting the oldest one if the pool is full.
+    ///
+    /// # Returns
+    /// The evicted transaction if exists.
+    pub fn insert(&mut self, tx: Tx) -> Result<Option<Tx>, OrphanError> {
+        self.check_cycle(&tx)?;
+        if self
+            .orphans
+            .iter()
+            .any(|(orphan, _)| orphan.sign() == tx.sign())
+        {
+            return Ok(None);
+        }
+
+        let evicted = if self.capacity > 0 && self.orphans.len() >= self.capacity {
+            self.orphans.pop_front().map(|(tx, _)| tx)
+        } else {
+            None
+        };
+        if self.capacity > 0 {
+            let now = self.clock.now();
+            self.orphans.push_back((tx, now));
+            Ok(evicted)
+        } else {
+            Ok(Some(tx))
+        }
+    }
+
+    /// Move orphans whose targets have become resolvable into the mempool, after validating them against the state.
+    /// Orphans which turned out to be invalid are dropped.
+    ///
+    /// This should be executed when a block is appended to the chain or a transaction arrives.
+    /// Moved orphans may make other orphans resolvable, so this repeats until no orphan moves.
+    ///
+    /// # Returns
+    /// Number of transactions moved into the mempool.
+    pub fn resolve(
+        &mut self,
+        state: &LedgerState,
+        mempool: &mut Mempool<JellyfishTransactionContent>,
+    ) -> usize {
+        let mut promoted = 0;
+        loop {
+            let index = self
+                .orphans
+                .iter()
+                .position(|(tx, _)| is_resolvable(tx, state, mempool));
+            let (tx, _) = match index.and_then(|index| self.orphans.remove(index)) {
+                Some(orphan) => orphan,
+                None => return promoted,
+            };
+            if validate(&tx, state, mempool).is_ok() && mempool.insert(tx) {
+                promoted += 1;
+            }
+        }
+    }
+
+    /// Remove transactions which arrived earlier than `max_age` from now.
+    pub fn remove_expired(&mut self, max_age: Duration) {
+        let now = self.clock.now();
+        self.orphans
+            .retain(|(_, arrival)| *arrival + max_age >= now);
+    }
+
+    /// Reject the transaction if it targets itself, or a chain of orphans' targets leads back to it.
+    fn check_cycle(&self, tx: &Tx) -> Result<(), OrphanError> {
+        let mut pending = target_signs(tx).collect::<Vec<_>>();
+        if pending.contains(&tx.sign()) {
+            return Err(OrphanError::SelfReferential);
+        }
+
+        let mut visited = vec![];
+        while let Some(sign) = pending.pop() {
+            if sign == tx.sign() {
+                return Err(OrphanError::Cyclic);
+            }
+            if visited.contains(&sign) {
+                continue;
+            }
+            visited.push(sign);
+            if let Some((orphan, _)) = self.orphans.iter().find(|(o, _)| o.sign() == sign) {
+                pending.extend(target_signs(orphan));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+impl Default for OrphanPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn targets(tx: &Tx) -> impl Iterator<Item = &TransactionIdentifier> {
+    tx.content()
+        .operations()
+        .iter()
+        .filter_map(JellyfishTransactionContent::target_transaction)
+}
+
+fn target_signs(tx: &Tx) -> impl Iterator<Item = &Signature> {
+    targets(tx).map(|target| &target.sign)
+}
+
+/// Returns the mempool transaction which will create the target record.
+fn pending_target<'a>(
+    target: &TransactionIdentifier,
+    mempool: &'a Mempool<JellyfishTransactionContent>,
+) -> Option<&'a Tx> {
+    mempool
+        .transactions()
+        .iter()
+        .find(|tx| tx.sign() == &target.sign)
+}
+
+/// Whether every target is known to the state, or is created by a transaction in the mempool.
+fn is_resolvable(
+    tx: &Tx,
+    state: &LedgerState,
+    mempool: &Mempool<JellyfishTransactionContent>,
+) -> bool {
+    targets(tx).all(|target| {
+        !state.history(target).is_empty() || pending_target(target, mempool).is_some()
+    })
+}
+
+/// Validate a resolvable transaction.
+///
+/// Targets known to the state are checked by [`LedgerState::check_targets()`].
+/// Targets created by mempool transactions must be created by the same account,
+/// since the state cannot check them until they are included in a block.
+fn validate(
+    tx: &Tx,
+    state: &LedgerState,
+    mempool: &Mempool<JellyfishTransactionContent>,
+) -> Result<(), OrphanError> {
+    if targets(tx).all(|target| !state.history(target).is_empty()) {
+        return state.check_targets(tx).map_err(OrphanError::State);
+    }
+    for target in targets(tx) {
+        match pending_target(target, mempool) {
+            Some(parent) if state.history(target).is_empty() => {
+                if parent.account() != tx.account() {
+                    return Err(OrphanError::State(StateError::NotOwner));
+                }
+            }
+            _ => {
+                state
+                    .check_target_of(target, tx.account())
+                    .map_err(OrphanError::State)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An error occurred during admitting a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrphanError {
+    /// Transaction targets itself.
+    SelfReferential,
+    /// Targets of orphans form a cycle through the transaction.
+    Cyclic,
+    /// Transaction is invalid against the ledger state.
+    State(StateError),
+}
+
+impl Display for OrphanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use OrphanError::*;
+
+        match self {
+            SelfReferential => write!(f, "Transaction targets itself."),
+            Cyclic => write!(f, "Transaction targets form a cycle."),
+            State(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for OrphanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OrphanError::State(e) => Some(e),
+            OrphanError::SelfReferential | OrphanError::Cyclic => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::fixtures::{self, FixtureChain};
+    use crate::params::ChainParams;
+    use crate::SecretAccount;
+
+    /// Returns a transaction targeting a record which never exists.
+    fn create_orphan(account: &SecretAccount, i: u64) -> Tx {
+        let unknown = fixtures::tx(account, JellyfishTransactionContent::insert(i.to_string()));
+        let target = TransactionIdentifier::new(i, *unknown.sign());
+        fixtures::tx(account, JellyfishTransactionContent::remove(target))
+    }
+
+    fn genesis() -> FixtureChain {
+        FixtureChain::new(ChainParams::regtest()).with_block(|b| b.insert("genesis"))
+    }
+
+    #[test]
+    fn resolve_after_parent_block() {
+        // Fixtures are deterministic, so the identifier of the parent is known in advance.
+        let parent = genesis().with_block(|b| b.insert("hello"));
+        let target = parent.ids()[1].clone();
+        let fixture = genesis();
+        let modify = fixtures::tx(
+            &fixture.accounts()[0],
+            JellyfishTransactionContent::modify("world", target),
+        );
+
+        // Modify arrives before its target.
+        let mut mempool = Mempool::new();
+        let mut orphans = OrphanPool::new();
+        assert_eq!(
+            orphans.admit(modify.clone(), fixture.state(), &mut mempool),
+            Ok(Admission::Orphan)
+        );
+        assert!(mempool.is_empty());
+        assert_eq!(orphans.resolve(fixture.state(), &mut mempool), 0);
+
+        // The target lands in a block.
+        assert_eq!(orphans.resolve(parent.state(), &mut mempool), 1);
+        assert!(orphans.is_empty());
+        assert_eq!(mempool.transactions(), &[modify]);
+    }
+
+    #[test]
+    fn resolve_by_mempool_transaction() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let state = LedgerState::new();
+        let insert = fixtures::tx(&account, JellyfishTransactionContent::insert("hello"));
+        let target = TransactionIdentifier::new(1, *insert.sign());
+        let modify = fixtures::tx(
+            &account,
+            JellyfishTransactionContent::modify("world", target.clone()),
+        );
+
+        let mut mempool = Mempool::new();
+        let mut orphans = OrphanPool::new();
+        orphans.admit(modify, &state, &mut mempool).unwrap();
+        assert_eq!(orphans.len(), 1);
+
+        // Arrival of the target promotes the orphan.
+        assert_eq!(
+            orphans.admit(insert, &state, &mut mempool),
+            Ok(Admission::Mempool)
+        );
+        assert!(orphans.is_empty());
+        assert_eq!(mempool.len(), 2);
+
+        // Target in the mempool must be created by the same account.
+        let mallory = SecretAccount::create(&mut rand_core::OsRng {});
+        let remove = fixtures::tx(&mallory, JellyfishTransactionContent::remove(target));
+        assert_eq!(
+            orphans.admit(remove, &state, &mut mempool),
+            Err(OrphanError::State(StateError::NotOwner))
+        );
+        assert_eq!(mempool.len(), 2);
+    }
+
+    #[test]
+    fn resolve_drop_invalid() {
+        let parent = genesis().with_block(|b| b.insert("hello"));
+        let target = parent.ids()[1].clone();
+        let mallory = SecretAccount::create(&mut rand_core::OsRng {});
+        let modify = fixtures::tx(
+            &mallory,
+            JellyfishTransactionContent::modify("evil", target),
+        );
+
+        let mut mempool = Mempool::new();
+        let mut orphans = OrphanPool::new();
+        orphans.insert(modify).unwrap();
+
+        assert_eq!(orphans.resolve(parent.state(), &mut mempool), 0);
+        assert!(orphans.is_empty());
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let txs = (0..4)
+            .map(|i| create_orphan(&account, i))
+            .collect::<Vec<_>>();
+        let mut orphans = OrphanPool::new().with_capacity(3);
+
+        for tx in &txs[..3] {
+            assert_eq!(orphans.insert(tx.clone()), Ok(None));
+        }
+        // Duplicates neither occupy the pool nor evict others.
+        assert_eq!(orphans.insert(txs[1].clone()), Ok(None));
+        assert_eq!(orphans.insert(txs[3].clone()), Ok(Some(txs[0].clone())));
+        assert!(orphans.transactions().eq(&txs[1..]));
+    }
+
+    #[test]
+    fn remove_expired() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let clock = Arc::new(ManualClock::new(Timestamp::from_nanos(1_000)));
+        let mut orphans = OrphanPool::with_clock(clock.clone());
+        orphans.insert(create_orphan(&account, 1)).unwrap();
+
+        let max_age = Duration::from_nanos(100);
+        clock.advance(max_age);
+        orphans.remove_expired(max_age);
+        assert_eq!(orphans.len(), 1);
+
+        // The target never arrives.
+        clock.advance(Duration::from_nanos(1));
+        orphans.remove_expired(max_age);
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn reject_self_reference() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let sign = *create_orphan(&account, 0).sign();
+        let target = TransactionIdentifier::new(1, sign);
+        let tx =
+            fixtures::tx(&account, JellyfishTransactionContent::remove(target)).forge_sign(sign);
+
+        let mut orphans = OrphanPool::new();
+        assert_eq!(orphans.insert(tx), Err(OrphanError::SelfReferential));
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn reject_cycle() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let (a, b, c) = (
+            create_orphan(&account, 0),
+            create_orphan(&account, 1),
+            create_orphan(&account, 2),
+        );
+        let refer = |tx: &Tx, target: &Tx| {
+            let target = TransactionIdentifier::new(1, *target.sign());
+            fixtures::tx(&account, JellyfishTransactionContent::remove(target))
+                .forge_sign(*tx.sign())
+        };
+        // a -> b -> c -> a
+        let (a_to_b, b_to_c, c_to_a) = (refer(&a, &b), refer(&b, &c), refer(&c, &a));
+
+        let mut orphans = OrphanPool::new();
+        orphans.insert(a_to_b).unwrap();
+        orphans.insert(b_to_c).unwrap();
+        assert_eq!(orphans.insert(c_to_a), Err(OrphanError::Cyclic));
+        assert_eq!(orphans.len(), 2);
+    }
+}
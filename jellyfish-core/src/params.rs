@@ -0,0 +1,767 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Block, Header};
+use crate::difficulty::{PowMode, Target, MIN_DIFFICULTY};
+use crate::digest::{Digest, HashMode};
+use crate::state::DEFAULT_MAX_RECORD_LEN;
+use crate::transaction::TRANSACTION_CONTEXT;
+use crate::window::HeaderWindow;
+use crate::{ByteOrder, Difficulty, Timestamp, Transaction};
+
+/// Maximum size of a block header in bytes, which is measured by its byte order.
+///
+/// This is the byte order of a header which has every optional field after its tag,
+/// including the hash algorithm which headers of other algorithms than SHA256 have.
+pub const MAX_HEADER_BYTES: usize =
+    8 + 8 + 32 + 8 + 32 + 8 + (1 + 32) + (1 + 4) + (1 + 4) + (1 + 1);
+
+/// Prefix of [addresses](crate::address) of the mainnet accounts.
+pub const MAINNET_ADDRESS_PREFIX: u8 = 0x2b;
+
+/// Prefix of [addresses](crate::address) of the testnet accounts.
+pub const TESTNET_ADDRESS_PREFIX: u8 = 0x6f;
+
+/// Prefix of [addresses](crate::address) of the regtest accounts.
+pub const REGTEST_ADDRESS_PREFIX: u8 = 0x7a;
+
+/// Short identifier of a network, which is contained in signature sources and headers.
+/// This is given by [`ChainParams::network()`].
+pub type NetworkId = [u8; 4];
+
+/// Consensus parameters of a network.
+///
+/// Nodes of the same network must use the same parameters, otherwise they disagree on validity of blocks.
+/// Presets are given by [`ChainParams::mainnet()`], [`ChainParams::testnet()`] and [`ChainParams::regtest()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainParams {
+    /// Name of the network, which distinguishes networks sharing the same software.
+    pub network_id: String,
+    /// Version of transaction signature sources and headers.
+    ///
+    /// Since version 1, they contain [`ChainParams::network()`], so that transactions and blocks are never valid on other networks.
+    /// Since version 2, transaction signatures are also made under [`TRANSACTION_CONTEXT`] as given by [`ChainParams::transaction_context()`].
+    /// Version 0 is the legacy format, which parameters without this field are read as.
+    #[serde(default)]
+    pub protocol_version: u8,
+    /// Whether the network is for local testing, which is distinguished from real networks by [`ChainParams::network()`].
+    #[serde(default)]
+    pub regtest: bool,
+    /// Minimum difficulty of blocks.
+    pub min_difficulty: Difficulty,
+    /// Maximum difficulty of blocks.
+    pub max_difficulty: Difficulty,
+    /// Intended interval between blocks, which difficulty retargeting aims at.
+    pub target_block_interval: Duration,
+    /// Number of blocks between difficulty retargets.
+    pub retarget_window: u64,
+    /// Maximum steps of difficulty change by a single retarget.
+    pub retarget_clamp: u64,
+    /// Maximum number of transactions in a block.
+    pub max_block_transactions: usize,
+    /// Maximum size of a block in bytes, which is measured by [`block_bytes()`].
+    pub max_block_bytes: usize,
+    /// Maximum length of a record in bytes, e.g., for [`LedgerState::with_max_record_len()`](crate::state::LedgerState::with_max_record_len).
+    pub max_record_bytes: usize,
+    /// How far a block's timestamp may be ahead of the local time.
+    pub max_future_drift: Duration,
+    /// Whether blocks without transactions are valid.
+    ///
+    /// [`Block::create()`] and [`Block::verify_block()`] reject such blocks regardless of this,
+    /// so this must be `false` until the block format supports them.
+    pub allow_empty_blocks: bool,
+    /// Maximum number of blocks which a fork may replace, e.g., for [`LightClient::with_finality_window()`](crate::light_client::LightClient::with_finality_window).
+    pub max_reorg_depth: u64,
+    /// First byte of [addresses](crate::address) of accounts, which differs between networks.
+    ///
+    /// Parameters without this field are read as [`MAINNET_ADDRESS_PREFIX`].
+    #[serde(default = "default_address_prefix")]
+    pub address_prefix: u8,
+    /// How header digests are calculated, which every header of the network must record.
+    ///
+    /// Parameters without this field are read as [`HashMode::Single`].
+    #[serde(default)]
+    pub hash_mode: HashMode,
+    /// Whether headers give Proof-of-Work condition by their difficulty or by their target.
+    ///
+    /// On [`PowMode::Target`], targets of headers must be within those of the minimum and maximum difficulty.
+    /// Parameters without this field are read as [`PowMode::ZeroBits`].
+    #[serde(default)]
+    pub pow_mode: PowMode,
+}
+
+impl ChainParams {
+    /// Parameters of the public network.
+    pub fn mainnet() -> Self {
+        Self {
+            network_id: "jellyfish-mainnet".to_owned(),
+            protocol_version: 1,
+            regtest: false,
+            min_difficulty: Difficulty::new(16),
+            max_difficulty: Difficulty::new(256),
+            target_block_interval: Duration::from_secs(60),
+            retarget_window: 144,
+            retarget_clamp: 4,
+            max_block_transactions: 10_000,
+            max_block_bytes: 4 * 1024 * 1024,
+            max_record_bytes: DEFAULT_MAX_RECORD_LEN,
+            max_future_drift: Duration::from_secs(2 * 60 * 60),
+            allow_empty_blocks: false,
+            max_reorg_depth: 100,
+            address_prefix: MAINNET_ADDRESS_PREFIX,
+            hash_mode: HashMode::Single,
+            pow_mode: PowMode::ZeroBits,
+        }
+    }
+
+    /// Parameters of the public test network, which has lower difficulty than the mainnet.
+    pub fn testnet() -> Self {
+        Self {
+            network_id: "jellyfish-testnet".to_owned(),
+            min_difficulty: Difficulty::new(8),
+            address_prefix: TESTNET_ADDRESS_PREFIX,
+            ..Self::mainnet()
+        }
+    }
+
+    /// Parameters of a local network for testing, where blocks are mined instantly.
+    ///
+    /// Difficulty is pinned to [`MIN_DIFFICULTY`], so that [`Block::mine_regtest()`] accepts the first nonce.
+    pub fn regtest() -> Self {
+        Self {
+            network_id: "jellyfish-regtest".to_owned(),
+            regtest: true,
+            min_difficulty: MIN_DIFFICULTY,
+            max_difficulty: MIN_DIFFICULTY,
+            target_block_interval: Duration::from_secs(1),
+            retarget_window: 10,
+            retarget_clamp: 0,
+            max_reorg_depth: 10,
+            address_prefix: REGTEST_ADDRESS_PREFIX,
+            ..Self::mainnet()
+        }
+    }
+
+    /// Returns identifier of the network, which is the first 4 bytes of digest of [`ChainParams::network_id`].
+    /// The most significant bit is set if and only if [`ChainParams::regtest`] is `true`,
+    /// so that blocks of regtest networks are never accepted by real networks.
+    ///
+    /// # Returns
+    /// `None` if [`ChainParams::protocol_version`] is 0, that is, signatures and headers do not contain the identifier.
+    pub fn network(&self) -> Option<NetworkId> {
+        if self.protocol_version == 0 {
+            return None;
+        }
+        let digest = Digest::create(&self.network_id);
+        let mut network = NetworkId::default();
+        let len = network.len();
+        network.copy_from_slice(&digest.as_bytes()[..len]);
+        network[0] = (network[0] & 0x7f) | ((self.regtest as u8) << 7);
+        Some(network)
+    }
+
+    /// Returns the context which transaction signatures are made under by [`SecretAccount::sign_with_context()`](crate::SecretAccount::sign_with_context).
+    ///
+    /// # Returns
+    /// `None` if [`ChainParams::protocol_version`] is less than 2, so that existing chains still validate.
+    pub fn transaction_context(&self) -> Option<&'static [u8]> {
+        (self.protocol_version >= 2).then_some(TRANSACTION_CONTEXT)
+    }
+
+    /// Returns the target which headers of the first blocks have, i.e., that of the minimum difficulty.
+    ///
+    /// # Returns
+    /// `None` unless [`ChainParams::pow_mode`] is [`PowMode::Target`].
+    pub fn initial_target(&self) -> Option<Target> {
+        (self.pow_mode == PowMode::Target).then(|| self.min_difficulty.to_target())
+    }
+
+    /// Check the header's network, difficulty and timestamp, which must not be ahead of `now` by more than the drift.
+    pub fn check_header(&self, header: &Header, now: Timestamp) -> Result<(), ParamsError> {
+        self.check_network(header)?;
+        self.check_hash_mode(header)?;
+        self.check_difficulty(header)?;
+        self.check_target(header)?;
+
+        let limit = now + self.max_future_drift;
+        if header.timestamp() > limit {
+            return Err(ParamsError::FutureTimestamp {
+                timestamp: header.timestamp(),
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns difficulty of the block following the window, which is the last [`ChainParams::retarget_window`] headers before a retarget.
+    ///
+    /// Difficulty of the latest header is adjusted by [`Difficulty::adjust()`] by at most [`ChainParams::retarget_clamp`] steps,
+    /// comparing the span of the window with the intended time, and the result is within the minimum and maximum difficulty.
+    pub fn retarget(&self, window: &HeaderWindow<'_>) -> Difficulty {
+        let intervals = window.len() as u32 - 1;
+        let expected = self.target_block_interval.saturating_mul(intervals);
+        window
+            .last()
+            .difficulty()
+            .adjust(expected, window.span(), self.retarget_clamp)
+            .min(self.max_difficulty)
+            .max(self.min_difficulty)
+    }
+
+    /// Returns difficulty of the block following `previous`, whose ancestors are looked up by `parent`.
+    ///
+    /// Blocks below the first retarget boundary, i.e., [`ChainParams::retarget_window`], use the minimum difficulty,
+    /// and every boundary is retargeted by [`ChainParams::retarget()`] over the window ending with `previous`.
+    /// Other blocks use the same difficulty as `previous`.
+    /// The window is truncated where `parent` returns `None`, e.g., at the first known header.
+    pub fn difficulty_after<'a, F>(&self, previous: &'a Header, mut parent: F) -> Difficulty
+    where
+        F: FnMut(&'a Header) -> Option<&'a Header>,
+    {
+        let height = previous.height() + 1;
+        let window = self.retarget_window;
+        if window == 0 || height < window {
+            return self.min_difficulty;
+        }
+        if !height.is_multiple_of(window) {
+            return previous.difficulty();
+        }
+
+        let mut headers = std::iter::successors(Some(previous), |header| parent(header))
+            .take(window as usize)
+            .collect::<Vec<_>>();
+        headers.reverse();
+        self.retarget(&HeaderWindow::new(headers))
+    }
+
+    /// Check the block's network, difficulty, number of transactions and size.
+    ///
+    /// Timestamp is not checked since it depends on the local time. Use [`ChainParams::check_header()`] for that.
+    pub fn check_block<T, VT, VB>(&self, block: &Block<T, VT, VB>) -> Result<(), ParamsError>
+    where
+        T: ByteOrder,
+    {
+        self.check_network(block.header())?;
+        self.check_hash_mode(block.header())?;
+        self.check_difficulty(block.header())?;
+        self.check_target(block.header())?;
+
+        let count = block.transactions().len();
+        if count == 0 && !self.allow_empty_blocks {
+            return Err(ParamsError::Empty);
+        }
+        if count > self.max_block_transactions {
+            return Err(ParamsError::TooManyTransactions {
+                count,
+                limit: self.max_block_transactions,
+            });
+        }
+
+        let bytes = block_bytes(block);
+        if bytes > self.max_block_bytes {
+            return Err(ParamsError::TooLarge {
+                bytes,
+                limit: self.max_block_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_hash_mode(&self, header: &Header) -> Result<(), ParamsError> {
+        if header.hash_mode() != self.hash_mode {
+            return Err(ParamsError::HashMode {
+                expected: self.hash_mode,
+                actual: header.hash_mode(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_network(&self, header: &Header) -> Result<(), ParamsError> {
+        let expected = self.network();
+        let actual = header.network().copied();
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(ParamsError::Network { expected, actual })
+        }
+    }
+
+    /// Check that the header has a target if and only if the mode is [`PowMode::Target`],
+    /// and that the target is not easier than the minimum difficulty nor harder than the maximum difficulty.
+    ///
+    /// Targets of the difficulties are compared by [`Target::truncated()`], since headers contain targets in the compact form.
+    fn check_target(&self, header: &Header) -> Result<(), ParamsError> {
+        if header.pow_mode() != self.pow_mode {
+            return Err(ParamsError::PowMode {
+                expected: self.pow_mode,
+                actual: header.pow_mode(),
+            });
+        }
+        if let Some(target) = header.target() {
+            let easiest = self.min_difficulty.to_target().truncated();
+            let hardest = self.max_difficulty.to_target().truncated();
+            if target > easiest || target < hardest {
+                return Err(ParamsError::Target(target));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_difficulty(&self, header: &Header) -> Result<(), ParamsError> {
+        let difficulty = header.difficulty();
+        if difficulty < self.min_difficulty || difficulty > self.max_difficulty {
+            Err(ParamsError::Difficulty(difficulty))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns size of the block in bytes. Same as [`Block::encoded_size()`].
+pub fn block_bytes<T, VT, VB>(block: &Block<T, VT, VB>) -> usize
+where
+    T: ByteOrder,
+{
+    block.encoded_size()
+}
+
+/// Returns size of the transaction in bytes. Same as [`Transaction::encoded_size()`].
+pub fn transaction_bytes<T, VT>(transaction: &Transaction<T, VT>) -> usize
+where
+    T: ByteOrder,
+{
+    transaction.encoded_size()
+}
+
+/// Header or block violates [`ChainParams`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamsError {
+    /// Header is for another network or protocol version.
+    Network {
+        expected: Option<NetworkId>,
+        actual: Option<NetworkId>,
+    },
+    /// Header's digest is calculated in another hash mode.
+    HashMode {
+        expected: HashMode,
+        actual: HashMode,
+    },
+    /// Difficulty is out of the range.
+    Difficulty(Difficulty),
+    /// Header gives Proof-of-Work condition in another mode.
+    PowMode {
+        expected: PowMode,
+        actual: PowMode,
+    },
+    /// Target is out of the range.
+    Target(Target),
+    /// Timestamp is too far ahead of the local time.
+    FutureTimestamp {
+        timestamp: Timestamp,
+        limit: Timestamp,
+    },
+    /// Block has no transaction.
+    Empty,
+    TooManyTransactions {
+        count: usize,
+        limit: usize,
+    },
+    TooLarge {
+        bytes: usize,
+        limit: usize,
+    },
+}
+
+impl Display for ParamsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use ParamsError::*;
+
+        match self {
+            Network { expected, actual } => write!(
+                f,
+                "Header's network {} does not match with {}.",
+                format_network(actual),
+                format_network(expected)
+            ),
+            HashMode { expected, actual } => write!(
+                f,
+                "Header's hash mode {:?} does not match with {:?}.",
+                actual, expected
+            ),
+            Difficulty(difficulty) => write!(f, "Difficulty {:?} is out of range.", difficulty),
+            PowMode { expected, actual } => write!(
+                f,
+                "Header's Proof-of-Work mode {:?} does not match with {:?}.",
+                actual, expected
+            ),
+            Target(target) => write!(f, "Target {} is out of range.", target),
+            FutureTimestamp { timestamp, limit } => write!(
+                f,
+                "Timestamp {} is ahead of limit {}.",
+                timestamp.nanos(),
+                limit.nanos()
+            ),
+            Empty => write!(f, "No transaction in block."),
+            TooManyTransactions { count, limit } => write!(
+                f,
+                "Block has {} transactions, which exceeds limit {}.",
+                count, limit
+            ),
+            TooLarge { bytes, limit } => write!(
+                f,
+                "Block has {} bytes, which exceeds limit {}.",
+                bytes, limit
+            ),
+        }
+    }
+}
+
+impl Error for ParamsError {}
+
+fn default_address_prefix() -> u8 {
+    MAINNET_ADDRESS_PREFIX
+}
+
+fn format_network(network: &Option<NetworkId>) -> String {
+    match network {
+        Some(network) => hex::encode(network),
+        None => "none".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockError;
+    use crate::chain::tests_stab::*;
+    use crate::chain::{validate_pipeline_with_params, Chain};
+    use crate::fixtures;
+    use crate::jellyfish_transaction::JellyfishTransactionContent;
+    use crate::mempool::Mempool;
+    use crate::state::LedgerState;
+    use crate::{SecretAccount, Verified, Yet};
+
+    /// Regtest with tiny limits.
+    fn tiny_regtest() -> ChainParams {
+        ChainParams {
+            max_block_transactions: 2,
+            max_record_bytes: 16,
+            ..ChainParams::regtest()
+        }
+    }
+
+    /// Parameters accepting blocks in the legacy format, e.g., ones by [`mine()`].
+    fn legacy(params: ChainParams) -> ChainParams {
+        ChainParams {
+            protocol_version: 0,
+            ..params
+        }
+    }
+
+    fn mine_next<T>(
+        chain: &Chain<T>,
+        transactions: Vec<Transaction<T, Verified>>,
+    ) -> Block<T, Yet, Yet>
+    where
+        T: ByteOrder + Serialize + serde::de::DeserializeOwned,
+    {
+        let tip = chain.tip().header();
+        unverified(&mine_transactions_for(
+            chain.params().unwrap(),
+            tip.height() + 1,
+            *tip.digest(),
+            transactions,
+        ))
+    }
+
+    #[test]
+    fn serialize_deserialize() {
+        for params in [
+            ChainParams::mainnet(),
+            ChainParams::testnet(),
+            ChainParams::regtest(),
+        ] {
+            let ser = serde_json::to_string(&params).unwrap();
+            let de = serde_json::from_str::<ChainParams>(&ser).unwrap();
+            assert_eq!(de, params);
+        }
+    }
+
+    #[test]
+    fn regtest_mine_verify() {
+        let params = tiny_regtest();
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let genesis_tx = fixtures::tx_for(
+            &params,
+            &account,
+            JellyfishTransactionContent::insert("genesis"),
+        );
+        let genesis = unverified(&mine_transactions_for(
+            &params,
+            0,
+            Digest::ZERO,
+            vec![genesis_tx],
+        ))
+        .verify_block(|_| true)
+        .unwrap()
+        .verify_transactions()
+        .unwrap();
+        params.check_block(&genesis).unwrap();
+
+        let mut chain = Chain::new(genesis).with_params(params.clone());
+        let mut state = LedgerState::new().with_max_record_len(params.max_record_bytes);
+        state.apply_block(chain.tip()).unwrap();
+
+        let mut mempool = Mempool::new();
+        for record in ["a", "b", "c"] {
+            mempool.insert(fixtures::tx_for(
+                &params,
+                &account,
+                JellyfishTransactionContent::insert(record),
+            ));
+        }
+        let template = mempool.block_template_with_params(&params);
+        assert_eq!(template.len(), 2);
+
+        let block = mine_next(&chain, template);
+        params
+            .check_header(block.header(), Timestamp::now())
+            .unwrap();
+        chain.append_with_state(block, &mut state).unwrap();
+        mempool.remove_included(chain.tip());
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(state.records().count(), 3);
+
+        // Block exceeding the limit of transactions
+        let transactions = (0..3)
+            .map(|i| {
+                fixtures::tx_for(
+                    &params,
+                    &account,
+                    JellyfishTransactionContent::insert(i.to_string()),
+                )
+            })
+            .collect();
+        let block = mine_next(&chain, transactions);
+        assert!(matches!(
+            chain.append_with_state(block, &mut state),
+            Err(BlockError::Params(ParamsError::TooManyTransactions {
+                count: 3,
+                limit: 2
+            }))
+        ));
+        assert_eq!(chain.tip().header().height(), 1);
+    }
+
+    #[test]
+    fn check_block_fail_difficulty() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let block = mine(0, Digest::ZERO, &account);
+
+        assert!(legacy(ChainParams::regtest()).check_block(&block).is_ok());
+        assert_eq!(
+            legacy(ChainParams::mainnet()).check_block(&block),
+            Err(ParamsError::Difficulty(MIN_DIFFICULTY))
+        );
+    }
+
+    #[test]
+    fn check_block_fail_hash_mode() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let block = mine(0, Digest::ZERO, &account);
+        let single = legacy(ChainParams::regtest());
+        let double = ChainParams {
+            hash_mode: HashMode::Double,
+            ..single.clone()
+        };
+
+        assert!(single.check_block(&block).is_ok());
+        let expected = Err(ParamsError::HashMode {
+            expected: HashMode::Double,
+            actual: HashMode::Single,
+        });
+        assert_eq!(double.check_block(&block), expected);
+        assert_eq!(
+            double.check_header(block.header(), Timestamp::now()),
+            expected
+        );
+
+        let mut block = block.into_unverified();
+        block.header_mut().modify_hash_mode(HashMode::Double);
+        let block = block.mine().verify_block(|_| true).unwrap();
+        assert!(double.check_block(&block).is_ok());
+        assert!(matches!(
+            single.check_block(&block),
+            Err(ParamsError::HashMode { .. })
+        ));
+    }
+
+    #[test]
+    fn check_block_fail_target() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let block = mine(0, Digest::ZERO, &account);
+        let zero_bits = legacy(ChainParams::regtest());
+        let target = ChainParams {
+            pow_mode: PowMode::Target,
+            max_difficulty: Difficulty::new(8),
+            ..zero_bits.clone()
+        };
+        assert_eq!(target.initial_target(), Some(Target::MAX));
+        assert_eq!(zero_bits.initial_target(), None);
+
+        let expected = Err(ParamsError::PowMode {
+            expected: PowMode::Target,
+            actual: PowMode::ZeroBits,
+        });
+        assert_eq!(target.check_block(&block), expected);
+        assert_eq!(
+            target.check_header(block.header(), Timestamp::now()),
+            expected
+        );
+
+        let with_target = |t: Target| {
+            let mut block = block.clone().into_unverified();
+            block.header_mut().modify_target(Some(t));
+            block.mine().verify_block(|_| true).unwrap()
+        };
+
+        // The easiest target is accepted although its compact form is truncated.
+        let block = with_target(target.initial_target().unwrap());
+        assert!(target.check_block(&block).is_ok());
+        assert!(matches!(
+            zero_bits.check_block(&block),
+            Err(ParamsError::PowMode { .. })
+        ));
+
+        let hardest = Difficulty::new(8).to_target();
+        assert!(target.check_block(&with_target(hardest)).is_ok());
+
+        let too_hard = Difficulty::new(9).to_target().truncated();
+        assert_eq!(
+            target.check_block(&with_target(too_hard)),
+            Err(ParamsError::Target(too_hard))
+        );
+    }
+
+    #[test]
+    fn check_block_fail_too_large() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let block = mine(0, Digest::ZERO, &account);
+        let params = ChainParams {
+            max_block_bytes: block_bytes(&block) - 1,
+            ..legacy(ChainParams::regtest())
+        };
+
+        assert!(matches!(
+            params.check_block(&block),
+            Err(ParamsError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn check_header_fail_future() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let block = mine(0, Digest::ZERO, &account);
+        let header = block.header();
+        let params = legacy(ChainParams::regtest());
+
+        let now = header.timestamp() - params.max_future_drift;
+        assert!(params.check_header(header, now).is_ok());
+
+        let now = now - Duration::from_nanos(1);
+        assert!(matches!(
+            params.check_header(header, now),
+            Err(ParamsError::FutureTimestamp { .. })
+        ));
+    }
+
+    #[test]
+    fn pipeline_with_params() {
+        let (anchor, blocks) = build_chain(3, |_, block| block);
+
+        assert!(validate_pipeline_with_params(
+            blocks.clone(),
+            &anchor,
+            &legacy(ChainParams::regtest())
+        )
+        .is_ok());
+
+        let result =
+            validate_pipeline_with_params(blocks, &anchor, &legacy(ChainParams::mainnet()));
+        assert!(matches!(
+            result,
+            Err((0, BlockError::Params(ParamsError::Difficulty(_))))
+        ));
+    }
+
+    #[test]
+    fn network() {
+        assert_ne!(
+            ChainParams::mainnet().network(),
+            ChainParams::testnet().network()
+        );
+        assert_eq!(legacy(ChainParams::mainnet()).network(), None);
+
+        // Regtest flag is a part of the identifier.
+        let regtest = ChainParams::regtest().network().unwrap();
+        let not_regtest = ChainParams {
+            regtest: false,
+            ..ChainParams::regtest()
+        };
+        let not_regtest = not_regtest.network().unwrap();
+        assert_eq!(regtest[0] & 0x80, 0x80);
+        assert_eq!(not_regtest[0] & 0x80, 0);
+        assert_eq!(regtest[1..], not_regtest[1..]);
+
+        // Parameters written before protocol version was introduced
+        let mut value = serde_json::to_value(ChainParams::mainnet()).unwrap();
+        value.as_object_mut().unwrap().remove("protocol_version");
+        let params = serde_json::from_value::<ChainParams>(value).unwrap();
+        assert_eq!(params, legacy(ChainParams::mainnet()));
+    }
+
+    #[test]
+    fn check_block_fail_network() {
+        let testnet = ChainParams {
+            min_difficulty: MIN_DIFFICULTY,
+            ..ChainParams::testnet()
+        };
+        let mainnet = ChainParams {
+            min_difficulty: MIN_DIFFICULTY,
+            ..ChainParams::mainnet()
+        };
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let tx = Transaction::create_for(
+            &testnet,
+            &account,
+            Timestamp::now(),
+            JellyfishTransactionContent::insert("hello"),
+        );
+
+        let block = mine_transactions_for(&testnet, 0, Digest::ZERO, vec![tx.clone()]);
+        assert!(testnet.check_block(&block).is_ok());
+        assert_eq!(
+            mainnet.check_block(&block),
+            Err(ParamsError::Network {
+                expected: mainnet.network(),
+                actual: testnet.network()
+            })
+        );
+        assert!(matches!(
+            legacy(mainnet.clone()).check_block(&block),
+            Err(ParamsError::Network { expected: None, .. })
+        ));
+
+        // Replaying the testnet transaction in a mainnet block
+        let block = mine_transactions_for(&mainnet, 0, Digest::ZERO, vec![tx]);
+        assert!(mainnet.check_block(&block).is_ok());
+        assert!(matches!(
+            unverified(&block).verify_transactions(),
+            Err(BlockError::Transaction(_))
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+//! Types needed by most applications, which build, verify and apply blocks.
+//!
+//! ```
+//! use jellyfish_core::prelude::*;
+//! ```
+
+pub use crate::block::{Block, BlockError, Header};
+pub use crate::chain::{AppendOutcome, Chain};
+pub use crate::digest::Digest;
+pub use crate::jellyfish_transaction::{
+    JellyfishTransactionContent, Method, TransactionIdentifier,
+};
+pub use crate::params::ChainParams;
+pub use crate::state::LedgerState;
+pub use crate::{
+    Account, ByteOrder, Difficulty, SecretAccount, Sha256Digest, Signature, Timestamp, Transaction,
+    Verified, Yet,
+};
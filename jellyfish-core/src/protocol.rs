@@ -0,0 +1,202 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Block, Header};
+use crate::codec::{BoundedReader, DecodeError};
+use crate::filter::RecordFilter;
+use crate::Yet;
+
+/// Maximum length of a framed message's payload.
+pub const MAX_FRAME_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Message exchanged between nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Message<T> {
+    /// Request at most `max_count` consecutive headers beginning with the given height.
+    GetHeaders { from_height: u64, max_count: usize },
+    /// Response to [`Message::GetHeaders`], in ascending order of height.
+    /// Empty if the responder has no header at the height.
+    Headers(Vec<Header>),
+    /// Request blocks at the given heights.
+    GetBlocks { heights: Vec<u64> },
+    /// Response to [`Message::GetBlocks`], in the requested order.
+    Blocks(Vec<Block<T, Yet, Yet>>),
+    /// Request record filters of blocks at the given heights.
+    GetFilters { heights: Vec<u64> },
+    /// Response to [`Message::GetFilters`].
+    /// Each filter is paired with the height of its block.
+    /// Heights unknown to the responder are omitted.
+    Filters(Vec<(u64, RecordFilter)>),
+}
+
+impl<T: Serialize> Message<T> {
+    /// Encode the message into a frame, which is length of JSON payload as big endian `u32` followed by the payload.
+    ///
+    /// # Returns
+    /// `Err(FrameError::TooLong)` if the payload is longer than [`MAX_FRAME_LENGTH`], which receivers reject.
+    pub fn encode_framed(&self) -> Result<Vec<u8>, FrameError> {
+        let payload = serde_json::to_vec(self).map_err(FrameError::Json)?;
+        if payload.len() > MAX_FRAME_LENGTH {
+            return Err(FrameError::TooLong {
+                limit: MAX_FRAME_LENGTH,
+                requested: payload.len(),
+            });
+        }
+        let mut frame = Vec::with_capacity(4 + payload.len());
+        frame.extend((payload.len() as u32).to_be_bytes());
+        frame.extend(payload);
+        Ok(frame)
+    }
+}
+
+impl<T: DeserializeOwned> Message<T> {
+    /// Decode the frame at the beginning of the buffer.
+    ///
+    /// A length longer than [`MAX_FRAME_LENGTH`] is rejected before the payload arrives.
+    /// [`DecodeError::UnexpectedEnd`] means that the buffer does not contain the whole frame yet.
+    ///
+    /// # Returns
+    /// The message and number of bytes of the frame.
+    pub fn decode_framed(buf: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let mut reader = BoundedReader::new(buf, 4 + MAX_FRAME_LENGTH);
+        let payload = reader.read_length_prefixed("frame", MAX_FRAME_LENGTH)?;
+        let message = serde_json::from_slice(payload).map_err(|e| DecodeError::Malformed {
+            field: "frame",
+            reason: e.to_string(),
+        })?;
+        Ok((message, reader.position()))
+    }
+}
+
+/// Message cannot be encoded into a frame.
+#[derive(Debug)]
+pub enum FrameError {
+    /// Message cannot be serialized.
+    Json(serde_json::Error),
+    /// Payload is longer than the limit.
+    TooLong { limit: usize, requested: usize },
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Json(e) => write!(f, "Message cannot be serialized: {}", e),
+            FrameError::TooLong { limit, requested } => write!(
+                f,
+                "Payload has {} bytes, which exceeds limit {}.",
+                requested, limit
+            ),
+        }
+    }
+}
+
+impl Error for FrameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FrameError::Json(e) => Some(e),
+            FrameError::TooLong { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_get_filters() {
+        let message = Message::<()>::GetFilters {
+            heights: vec![1, 2],
+        };
+
+        let serialized = serde_json::to_string(&message).unwrap();
+
+        assert_eq!(serialized, r#"{"GetFilters":{"heights":[1,2]}}"#);
+    }
+
+    #[test]
+    fn deserialize_filters() {
+        let filter =
+            serde_json::from_str::<RecordFilter>(&format!(r#""{}""#, "00".repeat(256))).unwrap();
+        let message = Message::<()>::Filters(vec![(3, filter)]);
+
+        let ser = serde_json::to_string(&message).unwrap();
+        let de = serde_json::from_str::<Message<()>>(&ser).unwrap();
+
+        assert_eq!(message, de);
+    }
+
+    #[test]
+    fn encode_decode_framed() {
+        let message = Message::<()>::GetHeaders {
+            from_height: 3,
+            max_count: 10,
+        };
+        let mut frame = message.encode_framed().unwrap();
+        let len = frame.len();
+        // Following frame
+        frame.extend([0, 0]);
+
+        assert_eq!(Message::decode_framed(&frame), Ok((message, len)));
+    }
+
+    #[test]
+    fn encode_framed_fail_too_long() {
+        // Each height is 20 digits and a comma in JSON.
+        let heights = vec![u64::MAX; MAX_FRAME_LENGTH / 21 + 1];
+        let message = Message::<()>::GetBlocks { heights };
+
+        assert!(matches!(
+            message.encode_framed(),
+            Err(FrameError::TooLong {
+                limit: MAX_FRAME_LENGTH,
+                requested,
+            }) if requested > MAX_FRAME_LENGTH
+        ));
+    }
+
+    #[test]
+    fn decode_framed_fail_too_long() {
+        // A frame header claiming 4 GiB, without payload
+        let frame = u32::MAX.to_be_bytes();
+
+        assert_eq!(
+            Message::<()>::decode_framed(&frame),
+            Err(DecodeError::LimitExceeded {
+                field: "frame",
+                limit: MAX_FRAME_LENGTH,
+                requested: u32::MAX as usize,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_framed_fail_incomplete() {
+        let frame = Message::<()>::GetBlocks { heights: vec![1] }
+            .encode_framed()
+            .unwrap();
+
+        assert!(matches!(
+            Message::<()>::decode_framed(&frame[..frame.len() - 1]),
+            Err(DecodeError::UnexpectedEnd { .. })
+        ));
+        assert!(matches!(
+            Message::<()>::decode_framed(&frame[..2]),
+            Err(DecodeError::UnexpectedEnd { .. })
+        ));
+    }
+
+    #[test]
+    fn decode_framed_fail_malformed() {
+        let mut frame = 2u32.to_be_bytes().to_vec();
+        frame.extend(b"{]");
+
+        assert!(matches!(
+            Message::<()>::decode_framed(&frame),
+            Err(DecodeError::Malformed { .. })
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+//! Ready-made chains on [`ChainParams::regtest()`], which are built in milliseconds since Proof-of-Work process is skipped.
+
+use crate::block::Block;
+use crate::chain::Chain;
+use crate::digest::Digest;
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::params::ChainParams;
+use crate::signer::TransactionSigner;
+use crate::state::LedgerState;
+use crate::{SecretAccount, Timestamp, Transaction, Yet};
+
+/// Chain built by [`regtest_chain_with_blocks()`], with its ledger state and the account which created all transactions.
+pub struct RegtestChain {
+    pub chain: Chain<JellyfishTransactionContent>,
+    pub state: LedgerState,
+    pub account: SecretAccount,
+}
+
+/// Build a chain of `n` blocks on [`ChainParams::regtest()`].
+///
+/// The genesis block inserts `records`, and each following block inserts a record `"block {height}"`.
+///
+/// # Panics
+/// If `n` is 0 or `records` is empty, since a block needs at least one transaction.
+pub fn regtest_chain_with_blocks(n: usize, records: &[&str]) -> RegtestChain {
+    assert!(n > 0, "A chain has at least genesis block.");
+
+    let params = ChainParams::regtest();
+    let account = SecretAccount::create(&mut rand_core::OsRng {});
+    let mut state = LedgerState::new().with_max_record_len(params.max_record_bytes);
+
+    let genesis = create_block(&params, &account, 0, Digest::ZERO, records)
+        .verify_block(|_| true)
+        .and_then(Block::verify_transactions)
+        .expect("Regtest genesis block must be valid.");
+    state
+        .apply_block(&genesis)
+        .expect("Regtest genesis block must be applicable.");
+    let mut chain = Chain::new(genesis).with_params(params.clone());
+
+    for height in 1..n as u64 {
+        let record = format!("block {}", height);
+        let previous_digest = *chain.tip().header().digest();
+        let block = create_block(&params, &account, height, previous_digest, &[&record]);
+        chain
+            .append_with_state(block, &mut state)
+            .expect("Regtest block must be valid.");
+    }
+
+    RegtestChain {
+        chain,
+        state,
+        account,
+    }
+}
+
+fn create_block<S: TransactionSigner>(
+    params: &ChainParams,
+    signer: &S,
+    height: u64,
+    previous_digest: Digest,
+    records: &[&str],
+) -> Block<JellyfishTransactionContent, Yet, Yet> {
+    let timestamp = Timestamp::now();
+    let transactions = records
+        .iter()
+        .map(|&record| {
+            let content = JellyfishTransactionContent::insert(record);
+            Transaction::create_for_with_signer(params, signer, timestamp, content)
+                .expect("Regtest signer must not fail.")
+                .into_unverified()
+        })
+        .collect();
+
+    let mut block = Block::create(
+        height,
+        timestamp,
+        previous_digest,
+        params.min_difficulty,
+        transactions,
+    )
+    .expect("A block needs at least one transaction.");
+    block.header_mut().modify_network(params.network());
+    block.header_mut().modify_hash_mode(params.hash_mode);
+    block
+        .mine_regtest()
+        .expect("Regtest difficulty must be minimal.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regtest_chain_with_blocks() {
+        let RegtestChain { chain, state, .. } = super::regtest_chain_with_blocks(100, &["a", "b"]);
+
+        assert_eq!(chain.tip().header().height(), 99);
+        assert_eq!(chain.block(0).unwrap().transactions().len(), 2);
+        assert_eq!(state.records().count(), 101);
+        assert_eq!(
+            chain.tip().header().network().copied(),
+            ChainParams::regtest().network()
+        );
+    }
+
+    #[test]
+    fn mine_regtest_fail_real_network() {
+        let params = ChainParams::testnet();
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let tx = Transaction::create_for(
+            &params,
+            &account,
+            Timestamp::now(),
+            JellyfishTransactionContent::insert("a"),
+        );
+        let block = Block::create(
+            0,
+            Timestamp::now(),
+            Digest::ZERO,
+            params.min_difficulty,
+            vec![tx],
+        )
+        .unwrap();
+
+        assert!(block.mine_regtest().is_err());
+    }
+}
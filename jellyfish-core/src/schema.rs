@@ -0,0 +1,261 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
+
+use crate::alias::{AliasRule, ALIAS_PREFIX};
+use crate::blob::{RecordRefRule, REF_PREFIX};
+use crate::jellyfish_transaction::{ContentError, ContentRule, JellyfishTransactionContent};
+
+/// Validation of records of a schema, which is registered to [`RecordSchemaRegistry`] with the schema's prefix.
+pub trait RecordValidator: Debug + Send + Sync {
+    /// Returns `Err` if the record is invalid for the schema.
+    ///
+    /// The whole record including the prefix is given.
+    fn validate(&self, record: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// How [`RecordSchemaRegistry`] treats records which do not begin with any registered prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownPrefix {
+    /// Such records are accepted as plain records.
+    #[default]
+    Allow,
+    /// Every record must belong to a registered schema.
+    Deny,
+}
+
+/// Routes each record to the validator of the schema whose prefix the record begins with,
+/// so that applications can multiplex their record schemas onto one chain.
+///
+/// If prefixes overlap, e.g., `blob:` and `blob:v2:`, the longest matching prefix wins.
+/// The registry is a [`ContentRule`], which validates every record in a transaction content.
+#[derive(Debug, Clone, Default)]
+pub struct RecordSchemaRegistry {
+    validators: BTreeMap<String, Arc<dyn RecordValidator>>,
+    unknown: UnknownPrefix,
+}
+
+impl RecordSchemaRegistry {
+    /// Returns an empty registry, which allows records of unknown prefixes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a registry of alias records and references to off-chain blobs,
+    /// validated by [`AliasRule`] and [`RecordRefRule`] respectively.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        // Prefixes of the standard schemas are distinct, so the below registrations always succeed.
+        registry.register(ALIAS_PREFIX, AliasRule).unwrap();
+        registry.register(REF_PREFIX, RecordRefRule::new()).unwrap();
+        registry
+    }
+
+    /// Set how records which do not begin with any registered prefix are treated.
+    pub fn with_unknown_prefix(self, unknown: UnknownPrefix) -> Self {
+        Self { unknown, ..self }
+    }
+
+    /// Register the validator of records beginning with the prefix.
+    ///
+    /// # Returns
+    /// `Err(SchemaError::DuplicatePrefix)` if the prefix has already been registered.
+    pub fn register<'a, T, V>(&mut self, prefix: T, validator: V) -> Result<(), SchemaError>
+    where
+        T: Into<Cow<'a, str>>,
+        V: RecordValidator + 'static,
+    {
+        let prefix = prefix.into().into_owned();
+        if self.validators.contains_key(&prefix) {
+            return Err(SchemaError::DuplicatePrefix { prefix });
+        }
+        self.validators.insert(prefix, Arc::new(validator));
+        Ok(())
+    }
+
+    /// Returns registered prefixes in ascending order.
+    pub fn prefixes(&self) -> impl Iterator<Item = &str> {
+        self.validators.keys().map(String::as_str)
+    }
+
+    /// Validate the record by the schema of the longest prefix which the record begins with.
+    pub fn validate(&self, record: &str) -> Result<(), SchemaError> {
+        let schema = self
+            .validators
+            .iter()
+            .filter(|(prefix, _)| record.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len());
+
+        match (schema, self.unknown) {
+            (Some((prefix, validator)), _) => {
+                validator
+                    .validate(record)
+                    .map_err(|source| SchemaError::Invalid {
+                        prefix: prefix.clone(),
+                        source,
+                    })
+            }
+            (None, UnknownPrefix::Allow) => Ok(()),
+            (None, UnknownPrefix::Deny) => Err(SchemaError::UnknownPrefix),
+        }
+    }
+}
+
+impl ContentRule for RecordSchemaRegistry {
+    fn check(&self, content: &JellyfishTransactionContent) -> Result<(), ContentError> {
+        content
+            .operations()
+            .iter()
+            .filter_map(JellyfishTransactionContent::record)
+            .try_for_each(|record| self.validate(record))
+            .map_err(|e| ContentError::new(e.to_string()))
+    }
+}
+
+/// Record violates its schema, or a schema cannot be registered.
+#[derive(Debug)]
+pub enum SchemaError {
+    /// Record is rejected by the validator of the schema.
+    Invalid {
+        prefix: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// Record does not begin with any registered prefix, which is denied by [`UnknownPrefix::Deny`].
+    UnknownPrefix,
+    /// Another schema has already been registered with the prefix.
+    DuplicatePrefix { prefix: String },
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use SchemaError::*;
+
+        match self {
+            Invalid { prefix, source } => {
+                write!(f, "Record of schema '{}' is invalid: {}", prefix, source)
+            }
+            UnknownPrefix => write!(f, "Record does not belong to any registered schema."),
+            DuplicatePrefix { prefix } => {
+                write!(f, "Schema of prefix '{}' is already registered.", prefix)
+            }
+        }
+    }
+}
+
+impl Error for SchemaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SchemaError::Invalid { source, .. } => Some(source.as_ref()),
+            SchemaError::UnknownPrefix | SchemaError::DuplicatePrefix { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alias::AliasRecord;
+    use crate::blob::RecordRef;
+    use crate::jellyfish_transaction::TransactionIdentifier;
+    use crate::{SecretAccount, Signature};
+
+    /// Accepts records of the given length only.
+    #[derive(Debug)]
+    struct LengthValidator(usize);
+
+    impl RecordValidator for LengthValidator {
+        fn validate(&self, record: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if record.len() == self.0 {
+                Ok(())
+            } else {
+                Err(format!("Record must be {} bytes.", self.0).into())
+            }
+        }
+    }
+
+    #[test]
+    fn route_by_prefix() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {}).to_public();
+        let alias = AliasRecord::new("alice", account).unwrap().to_record();
+        let reference = RecordRef::for_blob(b"blob", None).to_record();
+
+        let mut registry = RecordSchemaRegistry::standard();
+        registry.register("len:", LengthValidator(6)).unwrap();
+        registry.register("len:long:", LengthValidator(12)).unwrap();
+
+        assert!(registry.validate(&alias).is_ok());
+        assert!(registry.validate(&reference).is_ok());
+        assert!(registry.validate("len:ab").is_ok());
+        assert!(registry.validate("len:long:abc").is_ok());
+        assert!(registry.validate("hello").is_ok());
+
+        assert!(matches!(
+            registry.validate("alias:Alice"),
+            Err(SchemaError::Invalid { prefix, .. }) if prefix == ALIAS_PREFIX
+        ));
+        assert!(matches!(
+            registry.validate("ref:0011:21"),
+            Err(SchemaError::Invalid { prefix, .. }) if prefix == REF_PREFIX
+        ));
+        assert!(matches!(
+            registry.validate("len:abc"),
+            Err(SchemaError::Invalid { prefix, .. }) if prefix == "len:"
+        ));
+        // The longest prefix wins.
+        assert!(matches!(
+            registry.validate("len:long:"),
+            Err(SchemaError::Invalid { prefix, .. }) if prefix == "len:long:"
+        ));
+    }
+
+    #[test]
+    fn deny_unknown_prefix() {
+        let registry = RecordSchemaRegistry::standard().with_unknown_prefix(UnknownPrefix::Deny);
+        let reference = RecordRef::for_blob(b"blob", None).to_record();
+
+        assert!(registry.validate(&reference).is_ok());
+        assert!(matches!(
+            registry.validate("hello"),
+            Err(SchemaError::UnknownPrefix)
+        ));
+        assert!(matches!(
+            registry.validate("blob:hello"),
+            Err(SchemaError::UnknownPrefix)
+        ));
+    }
+
+    #[test]
+    fn register_fail_duplicate_prefix() {
+        let mut registry = RecordSchemaRegistry::standard();
+
+        let result = registry.register(ALIAS_PREFIX, LengthValidator(6));
+
+        assert!(matches!(
+            result,
+            Err(SchemaError::DuplicatePrefix { prefix }) if prefix == ALIAS_PREFIX
+        ));
+        assert_eq!(
+            registry.prefixes().collect::<Vec<_>>(),
+            vec![ALIAS_PREFIX, REF_PREFIX]
+        );
+        // The original schema is kept.
+        assert!(registry.validate("alias:alice").is_err());
+    }
+
+    #[test]
+    fn check_every_operation() {
+        let registry = RecordSchemaRegistry::standard();
+        let valid = JellyfishTransactionContent::insert("hello");
+        let invalid = JellyfishTransactionContent::insert("ref:0011:21");
+
+        assert!(registry.check(&valid).is_ok());
+        assert!(registry.check(&invalid).is_err());
+
+        let target = TransactionIdentifier::new(0, Signature::from_bytes(&[0; 64]).unwrap());
+        let modify = JellyfishTransactionContent::modify("ref:0011:21", target);
+        let batch = JellyfishTransactionContent::batch(vec![valid, modify]).unwrap();
+        assert!(registry.check(&batch).is_err());
+    }
+}
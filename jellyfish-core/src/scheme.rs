@@ -0,0 +1,655 @@
+//! Abstraction of signature schemes of accounts and transactions.
+//!
+//! [`Ed25519`] is the default scheme, and [`Account`], [`SecretAccount`] and [`Signature`] are its keys and signatures.
+//! [`Transaction`](crate::Transaction) takes the scheme as a type parameter, which defaults to [`Ed25519`].
+//! With `secp256k1` feature, [`Secp256k1`] signs transactions by ECDSA over secp256k1.
+//!
+//! [`sign_message()`] and [`verify_message()`] put the scheme id at the beginning of the signed bytes,
+//! so that a signature of one scheme never validates as another one even if their keys are shared.
+//! Signed bytes of transactions contain the id in the same way, except for [`Ed25519`] whose transactions keep the original byte order.
+
+use std::error::Error;
+use std::fmt::Debug;
+#[cfg(feature = "secp256k1")]
+use std::fmt::{self, Display, Formatter};
+
+use rand_core::{CryptoRng, RngCore};
+use serde::de::DeserializeOwned;
+#[cfg(feature = "secp256k1")]
+use serde::de::{Error as _, SeqAccess, Visitor};
+#[cfg(feature = "secp256k1")]
+use serde::ser::SerializeTuple;
+use serde::Serialize;
+#[cfg(feature = "secp256k1")]
+use serde::{Deserialize, Deserializer, Serializer};
+#[cfg(feature = "secp256k1")]
+use zeroize::Zeroizing;
+
+use crate::account::AccountError;
+use crate::signature::SignatureError;
+use crate::{Account, ByteOrder, SecretAccount, Signature};
+
+/// Signature algorithm with its key and signature encodings.
+///
+/// The supertraits let transactions derive their traits for every scheme.
+pub trait SignatureScheme: Debug + Clone + Copy + PartialEq + Eq + Default {
+    /// Identifier of the scheme, which is unique among schemes.
+    const ID: u8;
+
+    /// Public key, whose byte order is a part of signed bytes of transactions.
+    type PublicKey: Debug + Clone + Eq + ByteOrder + Serialize + DeserializeOwned;
+    type SecretKey;
+    type Signature: Debug + Clone + Eq + ByteOrder + Serialize + DeserializeOwned;
+    /// Error of decoding public keys.
+    type KeyError: Error + Send + Sync + 'static;
+    /// Error of decoding or verifying signatures.
+    type SignatureError: Error + Send + Sync + 'static;
+
+    fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Self::SecretKey;
+
+    fn public_key(secret: &Self::SecretKey) -> Self::PublicKey;
+
+    /// Sign the message as it is. Use [`sign_message()`] to separate the message from other schemes.
+    fn sign(secret: &Self::SecretKey, msg: &[u8]) -> Self::Signature;
+
+    /// Verify the signature of the message as it is, which is created by [`SignatureScheme::sign()`].
+    fn verify(
+        public: &Self::PublicKey,
+        msg: &[u8],
+        sign: &Self::Signature,
+    ) -> Result<(), Self::SignatureError>;
+
+    fn public_key_to_bytes(public: &Self::PublicKey) -> Vec<u8>;
+
+    fn public_key_from_bytes(bytes: &[u8]) -> Result<Self::PublicKey, Self::KeyError>;
+
+    fn signature_to_bytes(sign: &Self::Signature) -> Vec<u8>;
+
+    fn signature_from_bytes(bytes: &[u8]) -> Result<Self::Signature, Self::SignatureError>;
+}
+
+/// ed25519, which the protocol uses for accounts and transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Ed25519;
+
+impl SignatureScheme for Ed25519 {
+    const ID: u8 = 0;
+
+    type PublicKey = Account;
+    type SecretKey = SecretAccount;
+    type Signature = Signature;
+    type KeyError = AccountError;
+    type SignatureError = SignatureError;
+
+    fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Self::SecretKey {
+        SecretAccount::create(rng)
+    }
+
+    fn public_key(secret: &Self::SecretKey) -> Self::PublicKey {
+        secret.to_public()
+    }
+
+    fn sign(secret: &Self::SecretKey, msg: &[u8]) -> Self::Signature {
+        secret.sign(msg)
+    }
+
+    fn verify(
+        public: &Self::PublicKey,
+        msg: &[u8],
+        sign: &Self::Signature,
+    ) -> Result<(), Self::SignatureError> {
+        public.verify(msg, sign)
+    }
+
+    fn public_key_to_bytes(public: &Self::PublicKey) -> Vec<u8> {
+        public.as_bytes().to_vec()
+    }
+
+    fn public_key_from_bytes(bytes: &[u8]) -> Result<Self::PublicKey, Self::KeyError> {
+        Account::from_bytes(bytes)
+    }
+
+    fn signature_to_bytes(sign: &Self::Signature) -> Vec<u8> {
+        sign.as_ref().to_vec()
+    }
+
+    fn signature_from_bytes(bytes: &[u8]) -> Result<Self::Signature, Self::SignatureError> {
+        Signature::from_bytes(bytes)
+    }
+}
+
+/// Length of [`Secp256k1Account`] in bytes, which is the compressed SEC1 encoding of the public key.
+#[cfg(feature = "secp256k1")]
+pub const SECP256K1_PUBLIC_KEY_LENGTH: usize = 33;
+
+/// Length of [`Secp256k1Signature`] in bytes, which is `r || s`.
+#[cfg(feature = "secp256k1")]
+pub const SECP256K1_SIGNATURE_LENGTH: usize = 64;
+
+/// ECDSA over secp256k1 with SHA256 and deterministic nonces of RFC 6979.
+#[cfg(feature = "secp256k1")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Secp256k1;
+
+#[cfg(feature = "secp256k1")]
+impl SignatureScheme for Secp256k1 {
+    const ID: u8 = 1;
+
+    type PublicKey = Secp256k1Account;
+    type SecretKey = Secp256k1SecretAccount;
+    type Signature = Secp256k1Signature;
+    type KeyError = Secp256k1Error;
+    type SignatureError = Secp256k1Error;
+
+    fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Self::SecretKey {
+        Secp256k1SecretAccount::create(rng)
+    }
+
+    fn public_key(secret: &Self::SecretKey) -> Self::PublicKey {
+        secret.to_public()
+    }
+
+    fn sign(secret: &Self::SecretKey, msg: &[u8]) -> Self::Signature {
+        secret.sign(msg)
+    }
+
+    fn verify(
+        public: &Self::PublicKey,
+        msg: &[u8],
+        sign: &Self::Signature,
+    ) -> Result<(), Self::SignatureError> {
+        public.verify(msg, sign)
+    }
+
+    fn public_key_to_bytes(public: &Self::PublicKey) -> Vec<u8> {
+        public.to_bytes().to_vec()
+    }
+
+    fn public_key_from_bytes(bytes: &[u8]) -> Result<Self::PublicKey, Self::KeyError> {
+        Secp256k1Account::from_bytes(bytes)
+    }
+
+    fn signature_to_bytes(sign: &Self::Signature) -> Vec<u8> {
+        sign.to_bytes().to_vec()
+    }
+
+    fn signature_from_bytes(bytes: &[u8]) -> Result<Self::Signature, Self::SignatureError> {
+        Secp256k1Signature::from_bytes(bytes)
+    }
+}
+
+/// Account of [`Secp256k1`].
+///
+/// This is hex of the compressed public key in human-readable formats, and raw 33 bytes in binary formats, as [`Account`] is.
+#[cfg(feature = "secp256k1")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1Account(k256::ecdsa::VerifyingKey);
+
+#[cfg(feature = "secp256k1")]
+impl Secp256k1Account {
+    /// Restores an account from the compressed or uncompressed SEC1 encoding of its public key.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Secp256k1Error> {
+        k256::ecdsa::VerifyingKey::from_sec1_bytes(bytes)
+            .map(Self)
+            .map_err(|_| Secp256k1Error::Key)
+    }
+
+    /// Returns the compressed SEC1 encoding of the public key, which is the byte order of the account.
+    pub fn to_bytes(&self) -> [u8; SECP256K1_PUBLIC_KEY_LENGTH] {
+        let point = self.0.to_encoded_point(true);
+        point
+            .as_bytes()
+            .try_into()
+            .expect("Compressed point is 33 bytes.")
+    }
+
+    /// Returns whether given message and sign was created by the account.
+    ///
+    /// Signatures whose `s` is high are rejected, so that a third party cannot create another valid signature from one.
+    pub fn verify<T>(&self, msg: &T, sign: &Secp256k1Signature) -> Result<(), Secp256k1Error>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        use k256::ecdsa::signature::Verifier;
+
+        if sign.0.normalize_s().is_some() {
+            return Err(Secp256k1Error::HighS);
+        }
+        self.0
+            .verify(msg.as_ref(), &sign.0)
+            .map_err(|_| Secp256k1Error::VerificationFailed)
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl Display for Secp256k1Account {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_bytes()))
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl ByteOrder for Secp256k1Account {
+    fn append_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.to_bytes());
+    }
+
+    fn byte_len(&self) -> usize {
+        SECP256K1_PUBLIC_KEY_LENGTH
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl Serialize for Secp256k1Account {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_fixed(&self.to_bytes(), serializer)
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl<'de> Deserialize<'de> for Secp256k1Account {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_fixed::<D, SECP256K1_PUBLIC_KEY_LENGTH>(deserializer)?;
+        Self::from_bytes(&bytes).map_err(D::Error::custom)
+    }
+}
+
+/// [`Secp256k1`] account with secret key.
+///
+/// DO NOT reveal its secret key.
+///
+/// The secret key is wiped from memory when the account is dropped,
+/// and `Debug` prints the public key only.
+#[cfg(feature = "secp256k1")]
+pub struct Secp256k1SecretAccount(k256::ecdsa::SigningKey);
+
+#[cfg(feature = "secp256k1")]
+impl Secp256k1SecretAccount {
+    /// Create an account ramdomly.
+    pub fn create<R>(rng: &mut R) -> Self
+    where
+        R: CryptoRng + RngCore,
+    {
+        let mut bytes = Zeroizing::new([0; 32]);
+        loop {
+            rng.fill_bytes(bytes.as_mut());
+            // Zero and bytes not less than the group order are not secret keys, which are drawn again.
+            if let Ok(key) = k256::ecdsa::SigningKey::from_slice(bytes.as_ref()) {
+                return Self(key);
+            }
+        }
+    }
+
+    pub fn to_public(&self) -> Secp256k1Account {
+        Secp256k1Account(*self.0.verifying_key())
+    }
+
+    /// Sign the message, whose `s` is always low.
+    pub fn sign<T>(&self, msg: &T) -> Secp256k1Signature
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        use k256::ecdsa::signature::Signer;
+
+        Secp256k1Signature(self.0.sign(msg.as_ref()))
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl Debug for Secp256k1SecretAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Secp256k1SecretAccount")
+            .field(&self.to_public())
+            .finish()
+    }
+}
+
+/// Signature of [`Secp256k1`].
+///
+/// This is hex in human-readable formats, and raw 64 bytes in binary formats, as [`Signature`] is.
+#[cfg(feature = "secp256k1")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1Signature(k256::ecdsa::Signature);
+
+#[cfg(feature = "secp256k1")]
+impl Secp256k1Signature {
+    /// Restores a signature from `r || s`.
+    ///
+    /// Signatures whose `s` is high are restored, but [`Secp256k1Account::verify()`] rejects them.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Secp256k1Error> {
+        k256::ecdsa::Signature::from_slice(bytes)
+            .map(Self)
+            .map_err(|_| Secp256k1Error::Signature)
+    }
+
+    /// Returns `r || s`, which is the byte order of the signature.
+    pub fn to_bytes(&self) -> [u8; SECP256K1_SIGNATURE_LENGTH] {
+        self.0.to_bytes().into()
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl Display for Secp256k1Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_bytes()))
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl ByteOrder for Secp256k1Signature {
+    fn append_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.to_bytes());
+    }
+
+    fn byte_len(&self) -> usize {
+        SECP256K1_SIGNATURE_LENGTH
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl Serialize for Secp256k1Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_fixed(&self.to_bytes(), serializer)
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl<'de> Deserialize<'de> for Secp256k1Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_fixed::<D, SECP256K1_SIGNATURE_LENGTH>(deserializer)?;
+        Self::from_bytes(&bytes).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Secp256k1Error {
+    /// Bytes are not a public key on the curve.
+    Key,
+    /// Bytes are not `r || s` of non-zero scalars.
+    Signature,
+    /// `s` of the signature is high, which is malleable.
+    HighS,
+    /// Signature does not match the account and message.
+    VerificationFailed,
+}
+
+#[cfg(feature = "secp256k1")]
+impl Display for Secp256k1Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use Secp256k1Error::*;
+
+        match self {
+            Key => write!(f, "Invalid secp256k1 public key."),
+            Signature => write!(f, "Invalid secp256k1 signature."),
+            HighS => write!(f, "secp256k1 signature has high s."),
+            VerificationFailed => write!(f, "secp256k1 signature verification failed."),
+        }
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl Error for Secp256k1Error {}
+
+/// Bytes are hex in human-readable formats, and a tuple without length in binary formats,
+/// since serde supports arrays of up to 32 elements.
+#[cfg(feature = "secp256k1")]
+fn serialize_fixed<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        hex::encode(bytes).serialize(serializer)
+    } else {
+        let mut tuple = serializer.serialize_tuple(bytes.len())?;
+        for byte in bytes {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+fn deserialize_fixed<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    if deserializer.is_human_readable() {
+        let hex = String::deserialize(deserializer)?;
+        let bytes = hex::decode(hex).map_err(D::Error::custom)?;
+        let len = bytes.len();
+        bytes
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &format!("{} bytes", N).as_str()))
+    } else {
+        deserializer.deserialize_tuple(N, FixedBytesVisitor::<N>)
+    }
+}
+
+/// Visits N bytes serialized by [`serialize_fixed()`] in binary formats.
+#[cfg(feature = "secp256k1")]
+struct FixedBytesVisitor<const N: usize>;
+
+#[cfg(feature = "secp256k1")]
+impl<'de, const N: usize> Visitor<'de> for FixedBytesVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", N)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Sign the message following the scheme id, which [`verify_message()`] verifies.
+pub fn sign_message<S: SignatureScheme>(secret: &S::SecretKey, msg: &[u8]) -> S::Signature {
+    S::sign(secret, &build_scheme_message::<S>(msg))
+}
+
+/// Verify the signature created by [`sign_message()`] of the same scheme.
+pub fn verify_message<S: SignatureScheme>(
+    public: &S::PublicKey,
+    msg: &[u8],
+    sign: &S::Signature,
+) -> Result<(), S::SignatureError> {
+    S::verify(public, &build_scheme_message::<S>(msg), sign)
+}
+
+/// Returns the id which signed bytes of transactions of the scheme contain, or `None` for [`Ed25519`].
+pub(crate) fn transaction_scheme_id<S: SignatureScheme>() -> Option<u8> {
+    (S::ID != Ed25519::ID).then_some(S::ID)
+}
+
+fn build_scheme_message<S: SignatureScheme>(msg: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + msg.len());
+    buf.push(S::ID);
+    buf.extend(msg);
+    buf
+}
+
+#[cfg(test)]
+pub(crate) mod tests_stab {
+    use super::*;
+
+    /// ed25519 under another id, which shares keys with [`Ed25519`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub(crate) struct OtherScheme;
+
+    impl SignatureScheme for OtherScheme {
+        const ID: u8 = 0xff;
+
+        type PublicKey = Account;
+        type SecretKey = SecretAccount;
+        type Signature = Signature;
+        type KeyError = AccountError;
+        type SignatureError = SignatureError;
+
+        fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Self::SecretKey {
+            Ed25519::generate(rng)
+        }
+
+        fn public_key(secret: &Self::SecretKey) -> Self::PublicKey {
+            Ed25519::public_key(secret)
+        }
+
+        fn sign(secret: &Self::SecretKey, msg: &[u8]) -> Self::Signature {
+            Ed25519::sign(secret, msg)
+        }
+
+        fn verify(
+            public: &Self::PublicKey,
+            msg: &[u8],
+            sign: &Self::Signature,
+        ) -> Result<(), Self::SignatureError> {
+            Ed25519::verify(public, msg, sign)
+        }
+
+        fn public_key_to_bytes(public: &Self::PublicKey) -> Vec<u8> {
+            Ed25519::public_key_to_bytes(public)
+        }
+
+        fn public_key_from_bytes(bytes: &[u8]) -> Result<Self::PublicKey, Self::KeyError> {
+            Ed25519::public_key_from_bytes(bytes)
+        }
+
+        fn signature_to_bytes(sign: &Self::Signature) -> Vec<u8> {
+            Ed25519::signature_to_bytes(sign)
+        }
+
+        fn signature_from_bytes(bytes: &[u8]) -> Result<Self::Signature, Self::SignatureError> {
+            Ed25519::signature_from_bytes(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tests_stab::OtherScheme;
+    use super::*;
+
+    #[test]
+    fn ed25519_agrees_with_account() {
+        let secret = Ed25519::generate(&mut rand_core::OsRng {});
+        let public = Ed25519::public_key(&secret);
+        let sign = Ed25519::sign(&secret, b"hello");
+
+        assert_eq!(sign, secret.sign(b"hello"));
+        assert!(public.verify(b"hello", &sign).is_ok());
+        assert!(Ed25519::verify(&public, b"hello", &sign).is_ok());
+        assert!(Ed25519::verify(&public, b"other", &sign).is_err());
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let secret = Ed25519::generate(&mut rand_core::OsRng {});
+        let public = Ed25519::public_key(&secret);
+        let sign = Ed25519::sign(&secret, b"hello");
+
+        let public_bytes = Ed25519::public_key_to_bytes(&public);
+        let sign_bytes = Ed25519::signature_to_bytes(&sign);
+
+        assert_eq!(
+            Ed25519::public_key_from_bytes(&public_bytes).unwrap(),
+            public
+        );
+        assert_eq!(Ed25519::signature_from_bytes(&sign_bytes).unwrap(), sign);
+        assert!(Ed25519::public_key_from_bytes(&public_bytes[1..]).is_err());
+        assert!(Ed25519::signature_from_bytes(&sign_bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn scheme_id_separates_signatures() {
+        let secret = Ed25519::generate(&mut rand_core::OsRng {});
+        let public = Ed25519::public_key(&secret);
+
+        let sign = sign_message::<Ed25519>(&secret, b"hello");
+
+        assert!(verify_message::<Ed25519>(&public, b"hello", &sign).is_ok());
+        assert!(verify_message::<OtherScheme>(&public, b"hello", &sign).is_err());
+        // Not valid as a plain signature of the message either.
+        assert!(Ed25519::verify(&public, b"hello", &sign).is_err());
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1_sign_and_verify() {
+        let secret = Secp256k1::generate(&mut rand_core::OsRng {});
+        let public = Secp256k1::public_key(&secret);
+        let sign = Secp256k1::sign(&secret, b"hello");
+
+        assert!(Secp256k1::verify(&public, b"hello", &sign).is_ok());
+        assert_eq!(
+            Secp256k1::verify(&public, b"other", &sign),
+            Err(Secp256k1Error::VerificationFailed)
+        );
+        let other = Secp256k1::public_key(&Secp256k1::generate(&mut rand_core::OsRng {}));
+        assert!(Secp256k1::verify(&other, b"hello", &sign).is_err());
+
+        // Same secret key signs deterministically.
+        assert_eq!(Secp256k1::sign(&secret, b"hello"), sign);
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1_encodings_round_trip() {
+        let secret = Secp256k1::generate(&mut rand_core::OsRng {});
+        let public = Secp256k1::public_key(&secret);
+        let sign = Secp256k1::sign(&secret, b"hello");
+
+        let public_bytes = Secp256k1::public_key_to_bytes(&public);
+        let sign_bytes = Secp256k1::signature_to_bytes(&sign);
+        assert_eq!(public_bytes.len(), SECP256K1_PUBLIC_KEY_LENGTH);
+        assert_eq!(sign_bytes.len(), SECP256K1_SIGNATURE_LENGTH);
+        assert_eq!(public.build_byte_order(), public_bytes);
+        assert_eq!(sign.build_byte_order(), sign_bytes);
+
+        assert_eq!(
+            Secp256k1::public_key_from_bytes(&public_bytes).unwrap(),
+            public
+        );
+        assert_eq!(Secp256k1::signature_from_bytes(&sign_bytes).unwrap(), sign);
+        assert_eq!(
+            Secp256k1::public_key_from_bytes(&public_bytes[1..]),
+            Err(Secp256k1Error::Key)
+        );
+        assert_eq!(
+            Secp256k1::signature_from_bytes(&sign_bytes[1..]),
+            Err(Secp256k1Error::Signature)
+        );
+
+        let json = serde_json::to_string(&(public, sign)).unwrap();
+        assert_eq!(json, format!("[\"{}\",\"{}\"]", public, sign));
+        assert_eq!(
+            serde_json::from_str::<(Secp256k1Account, Secp256k1Signature)>(&json).unwrap(),
+            (public, sign)
+        );
+        let binary = bincode::serialize(&(public, sign)).unwrap();
+        assert_eq!(binary, [public_bytes, sign_bytes].concat());
+        assert_eq!(
+            bincode::deserialize::<(Secp256k1Account, Secp256k1Signature)>(&binary).unwrap(),
+            (public, sign)
+        );
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1_verify_fail_high_s() {
+        let secret = Secp256k1::generate(&mut rand_core::OsRng {});
+        let public = Secp256k1::public_key(&secret);
+        let sign = Secp256k1::sign(&secret, b"hello");
+
+        // Negated s is also a valid ECDSA signature, which is rejected to prevent malleability.
+        let (r, s) = sign.0.split_scalars();
+        let high = k256::ecdsa::Signature::from_scalars(r, -*s).unwrap();
+        let high = Secp256k1Signature::from_bytes(&high.to_bytes()).unwrap();
+
+        assert_eq!(
+            Secp256k1::verify(&public, b"hello", &high),
+            Err(Secp256k1Error::HighS)
+        );
+    }
+}
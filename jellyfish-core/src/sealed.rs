@@ -0,0 +1,274 @@
+//! Records readable only by a designated account.
+//!
+//! Everything on chain is public, so a record for a single recipient is encrypted before insertion.
+//! The recipient's ed25519 key is converted to an x25519 key, and the record is encrypted by ChaCha20-Poly1305
+//! with a key agreed between an ephemeral sender key and the recipient's key.
+//! Hence the sender needs no key of its own, and only the recipient's secret key can decrypt the record.
+//!
+//! # Envelope
+//! A sealed record is the standard base64 encoding of the following bytes.
+//! | Bytes | Content |
+//! | --- | --- |
+//! | 1 | Version, which is [`SEALED_VERSION`] |
+//! | 32 | Recipient's x25519 public key |
+//! | 32 | Ephemeral x25519 public key |
+//! | rest | Ciphertext followed by 16-byte tag |
+//!
+//! The encryption key is SHA-256 of `"jellyfish-sealed-v1"`, the shared secret, the ephemeral key and the recipient's key in this order.
+//! The nonce is all zero, since each key is used only once.
+//! The first 65 bytes are authenticated as associated data.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest as _, Sha512};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::digest::Digest;
+use crate::{Account, SecretAccount};
+
+/// Version of the envelope produced by [`encrypt_record()`].
+pub const SEALED_VERSION: u8 = 1;
+
+const KEY_DOMAIN: &[u8] = b"jellyfish-sealed-v1";
+const HEADER_LEN: usize = 1 + 32 + 32;
+const TAG_LEN: usize = 16;
+
+/// Encrypt the plaintext so that only the recipient can decrypt it by [`decrypt_record()`].
+///
+/// # Returns
+/// A record string, which fits in [`JellyfishTransactionContent::insert()`](crate::jellyfish_transaction::JellyfishTransactionContent::insert).
+pub fn encrypt_record(recipient: &Account, plaintext: &[u8]) -> String {
+    encrypt_record_with(&mut rand_core::OsRng, recipient, plaintext)
+}
+
+/// Same as [`encrypt_record()`], but the ephemeral key is drawn from the given generator.
+pub fn encrypt_record_with<R>(rng: &mut R, recipient: &Account, plaintext: &[u8]) -> String
+where
+    R: RngCore + CryptoRng,
+{
+    let mut ephemeral = [0; 32];
+    rng.fill_bytes(&mut ephemeral);
+    seal(StaticSecret::from(ephemeral), recipient, plaintext)
+}
+
+/// Decrypt a record created by [`encrypt_record()`] for the account.
+pub fn decrypt_record(secret: &SecretAccount, record: &str) -> Result<Vec<u8>, SealedError> {
+    let envelope = base64::decode(record).map_err(|_| SealedError::Encoding)?;
+    if envelope.len() < HEADER_LEN + TAG_LEN {
+        return Err(SealedError::Length);
+    }
+    if envelope[0] != SEALED_VERSION {
+        return Err(SealedError::Version(envelope[0]));
+    }
+
+    let secret = to_x25519_secret(secret);
+    let recipient = PublicKey::from(&secret);
+    if &envelope[1..33] != recipient.as_bytes() {
+        return Err(SealedError::WrongRecipient);
+    }
+    let mut ephemeral = [0; 32];
+    ephemeral.copy_from_slice(&envelope[33..HEADER_LEN]);
+    let ephemeral = PublicKey::from(ephemeral);
+
+    let (header, ciphertext) = envelope.split_at(HEADER_LEN);
+    let shared = secret.diffie_hellman(&ephemeral);
+    let key = derive_key(shared.as_bytes(), &ephemeral, &recipient);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: header,
+    };
+    cipher(&key)
+        .decrypt(&Nonce::from([0; 12]), payload)
+        .map_err(|_| SealedError::Decryption)
+}
+
+fn seal(ephemeral: StaticSecret, recipient: &Account, plaintext: &[u8]) -> String {
+    let recipient = to_x25519_public(recipient);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+    let key = derive_key(shared.as_bytes(), &ephemeral_public, &recipient);
+
+    let mut envelope = vec![SEALED_VERSION];
+    envelope.extend(recipient.as_bytes());
+    envelope.extend(ephemeral_public.as_bytes());
+    let payload = Payload {
+        msg: plaintext,
+        aad: &envelope,
+    };
+    let ciphertext = cipher(&key)
+        .encrypt(&Nonce::from([0; 12]), payload)
+        .expect("Encryption of in-memory data must succeed.");
+    envelope.extend(ciphertext);
+
+    base64::encode(envelope)
+}
+
+fn derive_key(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> [u8; 32] {
+    let mut source = KEY_DOMAIN.to_vec();
+    source.extend(shared);
+    source.extend(ephemeral.as_bytes());
+    source.extend(recipient.as_bytes());
+    Digest::create(&source).to_bytes()
+}
+
+fn cipher(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&Key::from(*key))
+}
+
+/// Birationally map the ed25519 point to the Montgomery form.
+fn to_x25519_public(account: &Account) -> PublicKey {
+    let point = CompressedEdwardsY::from_slice(account.as_bytes())
+        .decompress()
+        .expect("Account must be a valid ed25519 point.");
+    PublicKey::from(point.to_montgomery().to_bytes())
+}
+
+/// The x25519 scalar is the one ed25519 derives from the secret key, i.e., the lower half of its SHA-512 digest.
+fn to_x25519_secret(secret: &SecretAccount) -> StaticSecret {
+    let digest = Sha512::digest(secret.expose_secret());
+    let mut scalar = [0; 32];
+    scalar.copy_from_slice(&digest[..32]);
+    StaticSecret::from(scalar)
+}
+
+/// An error occurred during decrypting a sealed record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealedError {
+    /// Record is not base64.
+    Encoding,
+    /// Envelope is too short to contain the header and the tag.
+    Length,
+    /// Envelope version is not supported.
+    Version(u8),
+    /// Record is sealed for another account.
+    WrongRecipient,
+    /// Ciphertext or header has been tampered with.
+    Decryption,
+}
+
+impl Display for SealedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use SealedError::*;
+
+        match self {
+            Encoding => write!(f, "Sealed record is not base64."),
+            Length => write!(f, "Sealed record is too short."),
+            Version(version) => write!(f, "Unsupported sealed record version {}.", version),
+            WrongRecipient => write!(f, "Record is sealed for another account."),
+            Decryption => write!(f, "Sealed record has been tampered with."),
+        }
+    }
+}
+
+impl Error for SealedError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, FixtureChain};
+    use crate::jellyfish_transaction::{JellyfishTransactionContent, Method};
+    use crate::params::ChainParams;
+
+    /// Sealed for the first account of the default fixture seed, with ephemeral secret key `[0x42; 32]`.
+    const FIXTURE_ENVELOPE: &str = "AY6tkUbOHN4ULokWpA2+o/nvMKoI8D4MpC8UQO9gRjMiEyxEK+AQ+9V+cmAzKKp25x/MwVA6riGTJ9FNnJmT9HKXP9QYWKVOQuYp/vcYsVevjXUBvEo+yaRlOqReiXH9jA==";
+    const FIXTURE_PLAINTEXT: &[u8] = b"hello, jellyfish";
+
+    fn fixture_account() -> SecretAccount {
+        let fixture = FixtureChain::new(ChainParams::regtest());
+        SecretAccount::from_bytes(&*fixture.accounts()[0].to_bytes()).unwrap()
+    }
+
+    /// Flip a bit of the decoded envelope at the index.
+    fn tamper(record: &str, index: usize) -> String {
+        let mut envelope = base64::decode(record).unwrap();
+        envelope[index] ^= 1;
+        base64::encode(envelope)
+    }
+
+    #[test]
+    fn round_trip() {
+        let alice = fixtures::account(0);
+        for plaintext in [&b""[..], b"secret", &[0xff; 1000]] {
+            let record = encrypt_record(&alice.to_public(), plaintext);
+            assert_eq!(decrypt_record(&alice, &record), Ok(plaintext.to_vec()));
+        }
+
+        // Each record has its own ephemeral key.
+        let record = encrypt_record(&alice.to_public(), b"secret");
+        assert_ne!(record, encrypt_record(&alice.to_public(), b"secret"));
+    }
+
+    #[test]
+    fn insert_sealed() {
+        let alice = fixtures::account(0);
+        let content = JellyfishTransactionContent::insert_sealed(&alice.to_public(), b"secret");
+
+        assert_eq!(content.method(), Method::Insert);
+        let record = content.record().unwrap();
+        assert_eq!(decrypt_record(&alice, record), Ok(b"secret".to_vec()));
+    }
+
+    #[test]
+    fn fixture_envelope() {
+        let account = fixture_account();
+        assert_eq!(
+            decrypt_record(&account, FIXTURE_ENVELOPE),
+            Ok(FIXTURE_PLAINTEXT.to_vec())
+        );
+
+        let record = seal(
+            StaticSecret::from([0x42; 32]),
+            &account.to_public(),
+            FIXTURE_PLAINTEXT,
+        );
+        assert_eq!(record, FIXTURE_ENVELOPE);
+    }
+
+    #[test]
+    fn decrypt_fail_wrong_recipient() {
+        let (alice, bob) = (fixtures::account(0), fixtures::account(1));
+        let record = encrypt_record(&alice.to_public(), b"secret");
+
+        assert_eq!(
+            decrypt_record(&bob, &record),
+            Err(SealedError::WrongRecipient)
+        );
+    }
+
+    #[test]
+    fn decrypt_fail_tampered() {
+        let account = fixture_account();
+        let len = base64::decode(FIXTURE_ENVELOPE).unwrap().len();
+
+        // Ephemeral key, ciphertext and tag.
+        for index in [HEADER_LEN - 1, HEADER_LEN, len - 1] {
+            assert_eq!(
+                decrypt_record(&account, &tamper(FIXTURE_ENVELOPE, index)),
+                Err(SealedError::Decryption)
+            );
+        }
+        assert_eq!(
+            decrypt_record(&account, &tamper(FIXTURE_ENVELOPE, 0)),
+            Err(SealedError::Version(0))
+        );
+    }
+
+    #[test]
+    fn decrypt_fail_malformed() {
+        let account = fixture_account();
+
+        assert_eq!(
+            decrypt_record(&account, "not base64!"),
+            Err(SealedError::Encoding)
+        );
+        assert_eq!(
+            decrypt_record(&account, &base64::encode([SEALED_VERSION; HEADER_LEN])),
+            Err(SealedError::Length)
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::digest::Digest;
+use crate::signature::SignatureError;
+use crate::{Account, Signature};
+
+/// Default number of verifications remembered by [`SigCache`].
+pub const DEFAULT_SIG_CACHE_CAPACITY: usize = 100_000;
+
+/// Remembers successful signature verifications, so that the same signature is verified only once,
+/// e.g., when a transaction arrives via mempool and later inside a block, or when blocks are re-applied by a reorg.
+///
+/// Entries are keyed by the digest of account, message and signature.
+/// Failures are never cached, so that a forged signature cannot occupy the cache or be accepted later.
+/// When the cache is full, the oldest entry is evicted.
+///
+/// The cache is `Sync`, so it can be shared across threads by wrapping it in [`Arc`](std::sync::Arc).
+#[derive(Debug)]
+pub struct SigCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    keys: HashSet<Digest>,
+    /// Keys in insertion order.
+    order: VecDeque<Digest>,
+}
+
+impl SigCache {
+    /// Returns an empty cache which remembers at most [`DEFAULT_SIG_CACHE_CAPACITY`] verifications.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_SIG_CACHE_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Verify the signature by the account, unless the same verification has succeeded before.
+    pub fn verify(
+        &self,
+        account: &Account,
+        msg: &[u8],
+        sign: &Signature,
+    ) -> Result<(), SignatureError> {
+        let key = cache_key(account, msg, sign);
+        if self.lock().keys.contains(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        account.verify(msg, sign)?;
+        self.insert(key);
+        Ok(())
+    }
+
+    /// Returns number of verifications answered by the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns number of verifications which were actually calculated.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns number of remembered verifications.
+    pub fn len(&self) -> usize {
+        self.lock().keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&self, key: Digest) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.lock();
+        if !entries.keys.insert(key) {
+            return;
+        }
+        entries.order.push_back(key);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.keys.remove(&oldest);
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for SigCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Account and signature have fixed lengths, so the concatenation is unambiguous.
+fn cache_key(account: &Account, msg: &[u8], sign: &Signature) -> Digest {
+    let mut source = account.as_bytes().to_vec();
+    source.extend(msg);
+    source.extend(sign.as_ref());
+    Digest::create(&source)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::block::Block;
+    use crate::chain::tests_stab::*;
+    use crate::fixtures;
+    use crate::params::ChainParams;
+    use crate::{ByteOrder, SecretAccount, Timestamp, Transaction, Yet};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct ContentStab(Vec<u8>);
+
+    impl ByteOrder for ContentStab {
+        fn append_bytes(&self, buf: &mut Vec<u8>) {
+            buf.extend(&self.0);
+        }
+    }
+
+    fn create_tx(account: &SecretAccount, content: Vec<u8>) -> Transaction<ContentStab, Yet> {
+        let tx = Transaction::create(account, Timestamp::now(), ContentStab(content));
+        let ser = serde_json::to_string(&tx).unwrap();
+        serde_json::from_str(&ser).unwrap()
+    }
+
+    #[test]
+    fn hit_second_verification() {
+        let cache = SigCache::new();
+        let tx = create_tx(&fixtures::account(0), vec![0, 1, 2]);
+
+        assert!(tx.clone().verify_cached(&cache).is_ok());
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        assert!(tx.verify_cached(&cache).is_ok());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn hit_second_verification_with_params() {
+        let cache = SigCache::new();
+        let v1 = ChainParams::mainnet();
+        let v2 = ChainParams {
+            protocol_version: 2,
+            ..v1.clone()
+        };
+        let tx = Transaction::create_for(
+            &v2,
+            &fixtures::account(1),
+            Timestamp::now(),
+            ContentStab(vec![0]),
+        )
+        .into_unverified();
+
+        assert!(tx.clone().verify_cached_with_params(&v2, &cache).is_ok());
+        assert!(tx.clone().verify_cached_with_params(&v2, &cache).is_ok());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // A cached signature is never accepted under another context.
+        assert!(tx.clone().verify_cached_with_params(&v1, &cache).is_err());
+        assert!(tx.verify_cached(&cache).is_err());
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+    }
+
+    #[test]
+    fn miss_modified_message() {
+        let cache = SigCache::new();
+        let account = fixtures::account(0);
+        let sign = account.sign(b"message");
+        let account = account.to_public();
+
+        assert!(cache.verify(&account, b"message", &sign).is_ok());
+        assert!(cache.verify(&account, b"massage", &sign).is_err());
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+
+        // Failure is not cached.
+        assert!(cache.verify(&account, b"massage", &sign).is_err());
+        assert_eq!((cache.hits(), cache.misses()), (0, 3));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let cache = SigCache::with_capacity(2);
+        let account = fixtures::account(0);
+        let signs = [b"a", b"b", b"c"].map(|msg| account.sign(msg));
+        let account = account.to_public();
+
+        for (msg, sign) in [b"a", b"b", b"c"].iter().zip(&signs) {
+            cache.verify(&account, *msg, sign).unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+
+        cache.verify(&account, b"c", &signs[2]).unwrap();
+        assert_eq!(cache.hits(), 1);
+        cache.verify(&account, b"a", &signs[0]).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 4));
+    }
+
+    #[test]
+    fn verify_block_transactions() {
+        let cache = SigCache::new();
+        let account = fixtures::account(0);
+        let block = mine(1, Digest::ZERO, &account);
+        let block: Block<Stab, Yet, Yet> = unverified(&block);
+
+        assert!(block.clone().verify_transactions_cached(&cache).is_ok());
+        assert!(block.verify_transactions_cached(&cache).is_ok());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    }
+}
@@ -1,12 +1,35 @@
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 use ed25519_dalek::ed25519::signature::Signature as _;
+use ed25519_dalek::SIGNATURE_LENGTH;
 use hex::ToHex;
-use serde::de::Error as _;
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use subtle::ConstantTimeEq;
 
-use crate::byteorder::ByteOrder;
+use crate::account::verify_batch;
+use crate::byteorder::{ByteOrder, ByteOrderBuilder};
+use crate::clock::Clock;
+use crate::digest::Digest;
+use crate::hex0x;
+use crate::{Account, SecretAccount, Timestamp};
+
+/// Order of the ed25519 base point, `2^252 + 27742317777372353535851937790883648493`, in little endian.
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// Prime of the ed25519 base field, `2^255 - 19`, in little endian.
+const FIELD_PRIME: [u8; 32] = [
+    0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+];
 
 /// Sign to a message by message's creator.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,6 +40,69 @@ pub struct Signature(
 );
 
 impl Signature {
+    /// Parse a signature from its 64 bytes.
+    ///
+    /// Non-canonical encodings are rejected by [`VerifyPolicy::default()`] as [`Signature::from_bytes_with_policy()`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignatureError> {
+        Self::from_bytes_with_policy(bytes, VerifyPolicy::default())
+    }
+
+    /// Same as [`Signature::from_bytes()`], but by the given policy.
+    ///
+    /// [`VerifyPolicy::Strict`] rejects a non-canonical encoding, whose `s` is not less than the group order
+    /// or whose `R` is not canonical. Otherwise, the same signature could be encoded into distinct bytes,
+    /// which would give distinct transaction identifiers and merkle leaves.
+    /// [`VerifyPolicy::Loose`] accepts them, which is only for reading historical data.
+    ///
+    /// # Returns
+    /// `Err(SignatureError::Malformed)` if the bytes are not a signature, or not canonical under the strict policy.
+    pub fn from_bytes_with_policy(
+        bytes: &[u8],
+        policy: VerifyPolicy,
+    ) -> Result<Self, SignatureError> {
+        let sign =
+            ed25519_dalek::Signature::from_bytes(bytes).map_err(SignatureError::Malformed)?;
+        if policy == VerifyPolicy::Strict && !is_canonical(bytes) {
+            return Err(SignatureError::Malformed(
+                ed25519_dalek::ed25519::Error::new(),
+            ));
+        }
+        Ok(Self(sign))
+    }
+
+    /// Returns a copy of the 64 bytes of the signature, which [`Signature::from_bytes()`] accepts.
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LENGTH] {
+        self.0.to_bytes()
+    }
+
+    /// Compares signatures in constant time.
+    ///
+    /// Use this instead of `==` where a signature given by others is compared with an expected one,
+    /// so that the comparison does not leak how many leading bytes match through timing.
+    pub fn ct_eq(&self, other: &Signature) -> bool {
+        self.as_ref().ct_eq(other.as_ref()).into()
+    }
+
+    /// Same as [`Account::verify()`] in the order of the signature first.
+    pub fn verified_by<T>(&self, account: &Account, msg: &T) -> Result<(), SignatureError>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        account.verify(msg, self)
+    }
+
+    /// Returns the first account which created the signature of the message, if any.
+    ///
+    /// Each account is verified one by one, so this costs a verification per candidate until it is found.
+    pub fn verify_any<'a, T>(&self, accounts: &'a [Account], msg: &T) -> Option<&'a Account>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        accounts
+            .iter()
+            .find(|account| self.verified_by(account, msg).is_ok())
+    }
+
     pub(crate) fn as_raw_sign(&self) -> &ed25519_dalek::Signature {
         &self.0
     }
@@ -26,53 +112,568 @@ impl Signature {
     }
 }
 
+/// Formats the signature as lowercase hex of its 64 bytes, which is the same as its serialized form.
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_ref().encode_hex::<String>())
+    }
+}
+
+/// Parses lowercase or uppercase hex given by [`Display`].
+impl FromStr for Signature {
+    type Err = ParseSignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex0x::decode(s).map_err(ParseSignatureError::Hex)?;
+        if bytes.len() != SIGNATURE_LENGTH {
+            return Err(ParseSignatureError::Length {
+                expected: SIGNATURE_LENGTH,
+                actual: bytes.len(),
+            });
+        }
+        Self::from_bytes(&bytes).map_err(ParseSignatureError::Signature)
+    }
+}
+
 impl AsRef<[u8]> for Signature {
     fn as_ref(&self) -> &[u8] {
         self.0.as_bytes()
     }
 }
 
+/// Hashes the 64 bytes, so that signatures equal by `==` hash equally.
+impl Hash for Signature {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
+/// Signatures are ordered lexicographically by their 64 bytes.
+/// The order is part of the API and never changes, so it may key persistent indexes.
+impl PartialOrd for Signature {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Signature {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_ref().cmp(other.as_ref())
+    }
+}
+
 impl ByteOrder for Signature {
     fn append_bytes(&self, buf: &mut Vec<u8>) {
         buf.extend(self.as_ref());
     }
+
+    fn append_to_hasher(&self, hasher: &mut crate::digest::Hasher) {
+        hasher.update(self.as_ref());
+    }
+
+    fn byte_len(&self) -> usize {
+        SIGNATURE_LENGTH
+    }
 }
 
+/// Signature is hex in human-readable formats such as JSON, and raw 64 bytes in binary formats such as bincode.
 fn serialize_signature<S: Serializer>(
     sign: &ed25519_dalek::Signature,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
-    let hex: String = sign.as_bytes().encode_hex();
-    hex.serialize(serializer)
+    if serializer.is_human_readable() {
+        Signature(*sign).to_string().serialize(serializer)
+    } else {
+        // serde supports arrays of up to 32 elements, so 64 bytes are serialized as a tuple without length.
+        let mut tuple = serializer.serialize_tuple(SIGNATURE_LENGTH)?;
+        for byte in sign.as_bytes() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
 }
 
 fn deserialize_signature<'de, D: Deserializer<'de>>(
     deserializer: D,
 ) -> Result<ed25519_dalek::Signature, D::Error> {
-    let hex = String::deserialize(deserializer)?;
-    let bytes = hex::decode(&hex).map_err(D::Error::custom)?;
-    let sign = ed25519_dalek::Signature::from_bytes(&bytes).map_err(D::Error::custom)?;
-    Ok(sign)
+    if deserializer.is_human_readable() {
+        let hex = String::deserialize(deserializer)?;
+        let sign = hex.parse::<Signature>().map_err(D::Error::custom)?;
+        Ok(sign.0)
+    } else {
+        let bytes = deserializer.deserialize_tuple(SIGNATURE_LENGTH, BytesVisitor)?;
+        let sign = Signature::from_bytes(&bytes).map_err(D::Error::custom)?;
+        Ok(sign.0)
+    }
 }
 
-#[derive(Debug)]
-pub struct SignatureError(ed25519_dalek::ed25519::Error);
+/// Visits 64 bytes serialized by [`serialize_signature()`] in binary formats.
+struct BytesVisitor;
 
-impl From<ed25519_dalek::ed25519::Error> for SignatureError {
-    fn from(e: ed25519_dalek::ed25519::Error) -> Self {
-        Self(e)
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = [u8; SIGNATURE_LENGTH];
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes of a signature", SIGNATURE_LENGTH)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0; SIGNATURE_LENGTH];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// How strictly [`Account::verify_with_policy()`](crate::Account::verify_with_policy) checks signatures.
+///
+/// Nodes which disagree on the policy may disagree on validity of transactions, so every node of a network must use the same one.
+/// The default is [`VerifyPolicy::Strict`] with `strict-verification` feature, which is enabled by default,
+/// and [`VerifyPolicy::Loose`] otherwise. [`Account::verify()`](crate::Account::verify),
+/// and hence transaction and block verification, follow the default.
+/// So do [`Account::from_bytes()`](crate::Account::from_bytes) and deserialization of accounts, which reject weak keys under the strict policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyPolicy {
+    /// Cofactorless verification of ed25519, which accepts some signatures by weak keys of small order.
+    Loose,
+    /// Also rejects weak keys and signatures whose `R` has small order, as `verify_strict` of ed25519-dalek does.
+    Strict,
+}
+
+impl Default for VerifyPolicy {
+    fn default() -> Self {
+        if cfg!(feature = "strict-verification") {
+            VerifyPolicy::Strict
+        } else {
+            VerifyPolicy::Loose
+        }
     }
 }
 
+/// Returns whether the signature is in the canonical encoding,
+/// that is, its `s` is less than the group order, and its `R` encodes a field element less than the prime
+/// without the sign bit set for `x = 0`.
+fn is_canonical(bytes: &[u8]) -> bool {
+    let (r, s) = bytes.split_at(32);
+
+    let mut y = [0; 32];
+    y.copy_from_slice(r);
+    y[31] &= 0x7f;
+    let is_negative = r[31] & 0x80 != 0;
+    // x = 0 if and only if y = 1 or y = p - 1.
+    let mut one = [0; 32];
+    one[0] = 1;
+    let mut minus_one = FIELD_PRIME;
+    minus_one[0] -= 1;
+    let is_x_zero = y == one || y == minus_one;
+
+    less_than(s, &GROUP_ORDER) && less_than(&y, &FIELD_PRIME) && !(is_x_zero && is_negative)
+}
+
+/// Returns whether the point encoding is one of the 8 points of small order, which [`VerifyPolicy::Strict`] rejects.
+///
+/// Small-order points are the identity, `y = -1`, `y = 0`, and the order-8 points of the two `y` below,
+/// with either sign of `x`. Non-canonical encodings `y + p` of `y = 0` and `y = 1` are also covered.
+pub(crate) fn is_small_order(bytes: &[u8]) -> bool {
+    const ORDER_8_Y: [[u8; 32]; 2] = [
+        [
+            0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef,
+            0x98, 0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39, 0xb1, 0x38, 0x02, 0x88,
+            0x6d, 0x53, 0xfc, 0x05,
+        ],
+        [
+            0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f, 0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10,
+            0x67, 0x0f, 0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6, 0x4e, 0xc7, 0xfd, 0x77,
+            0x92, 0xac, 0x03, 0x7a,
+        ],
+    ];
+
+    let mut y = [0; 32];
+    y.copy_from_slice(bytes);
+    y[31] &= 0x7f;
+
+    let mut one = [0; 32];
+    one[0] = 1;
+    let mut minus_one = FIELD_PRIME;
+    minus_one[0] -= 1;
+    let mut prime_plus_one = FIELD_PRIME;
+    prime_plus_one[0] += 1;
+
+    [[0; 32], one, minus_one, FIELD_PRIME, prime_plus_one]
+        .iter()
+        .chain(&ORDER_8_Y)
+        .any(|small| *small == y)
+}
+
+/// Compares little endian integers of the same length.
+fn less_than(a: &[u8], b: &[u8]) -> bool {
+    a.iter().rev().lt(b.iter().rev())
+}
+
+/// Signature is invalid.
+///
+/// [`SignatureError::Malformed`] is found when the bytes are parsed, e.g., at deserialization,
+/// and the others only at verification.
+#[derive(Debug)]
+pub enum SignatureError {
+    /// Bytes are not an ed25519 signature, or not in the canonical encoding with `strict-verification` feature.
+    Malformed(ed25519_dalek::ed25519::Error),
+    /// Signature was not created for the message by the account.
+    VerificationFailed(ed25519_dalek::ed25519::Error),
+    /// Public key or `R` of the signature has small order, which [`VerifyPolicy::Strict`] rejects.
+    WeakKey,
+}
+
 impl Display for SignatureError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+        match self {
+            SignatureError::Malformed(e) => write!(f, "Signature is malformed: {}", e),
+            SignatureError::VerificationFailed(e) => {
+                write!(f, "Signature does not match the message: {}", e)
+            }
+            SignatureError::WeakKey => write!(f, "Key or signature has small order."),
+        }
     }
 }
 
 impl Error for SignatureError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.0)
+        match self {
+            SignatureError::Malformed(e) | SignatureError::VerificationFailed(e) => Some(e),
+            SignatureError::WeakKey => None,
+        }
+    }
+}
+
+/// Text is not a signature given by [`Signature`]'s [`Display`].
+#[derive(Debug)]
+pub enum ParseSignatureError {
+    Hex(hex::FromHexError),
+    Length {
+        expected: usize,
+        actual: usize,
+    },
+    /// Bytes are not an ed25519 signature, or not canonical with `strict-verification` feature.
+    Signature(SignatureError),
+}
+
+impl Display for ParseSignatureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseSignatureError::Hex(e) => write!(f, "Signature is not hex: {}", e),
+            ParseSignatureError::Length { expected, actual } => write!(
+                f,
+                "Signature must be {} bytes, but {} bytes.",
+                expected, actual
+            ),
+            ParseSignatureError::Signature(e) => write!(f, "Invalid signature: {}", e),
+        }
+    }
+}
+
+impl Error for ParseSignatureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseSignatureError::Hex(e) => Some(e),
+            ParseSignatureError::Length { .. } => None,
+            ParseSignatureError::Signature(e) => Some(e),
+        }
+    }
+}
+
+/// [`MultiSig::verify_all()`] verifies entries by batch verification if they are more than this.
+pub const MULTISIG_BATCH_THRESHOLD: usize = 4;
+
+/// Signatures of distinct accounts over the same message.
+///
+/// Unlike [`MultisigAccount`](crate::account::MultisigAccount), there is no threshold, and every entry must be valid.
+/// Each account appears at most once; a repeated account is ignored, keeping its first signature,
+/// and so is on deserialization.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<(Account, Signature)>", into = "Vec<(Account, Signature)>")]
+pub struct MultiSig {
+    entries: Vec<(Account, Signature)>,
+}
+
+impl MultiSig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the signature of the account.
+    ///
+    /// # Returns
+    /// `false` if the account has been added, in which case the signature is ignored.
+    pub fn push(&mut self, account: Account, sign: Signature) -> bool {
+        if self.entries.iter().any(|(a, _)| *a == account) {
+            return false;
+        }
+        self.entries.push((account, sign));
+        true
+    }
+
+    pub fn entries(&self) -> &[(Account, Signature)] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Verify that every entry signed the message.
+    ///
+    /// Entries are verified by [`verify_batch()`] if they are more than [`MULTISIG_BATCH_THRESHOLD`],
+    /// and one by one otherwise. No entries is vacuously valid.
+    ///
+    /// # Returns
+    /// `Err` reporting all accounts whose signatures are invalid, in the order of the entries.
+    pub fn verify_all<T>(&self, msg: &T) -> Result<(), MultiSigError>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        let msg = msg.as_ref();
+        let failed = if self.entries.len() > MULTISIG_BATCH_THRESHOLD {
+            let items = self
+                .entries
+                .iter()
+                .map(|(account, sign)| (account, msg, sign))
+                .collect::<Vec<_>>();
+            match verify_batch(&items) {
+                Ok(()) => vec![],
+                Err(e) => e
+                    .indices()
+                    .iter()
+                    .map(|&i| self.entries[i].0.clone())
+                    .collect(),
+            }
+        } else {
+            self.entries
+                .iter()
+                .filter(|(account, sign)| account.verify(msg, sign).is_err())
+                .map(|(account, _)| account.clone())
+                .collect()
+        };
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(MultiSigError { failed })
+        }
+    }
+}
+
+impl From<Vec<(Account, Signature)>> for MultiSig {
+    fn from(entries: Vec<(Account, Signature)>) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl From<MultiSig> for Vec<(Account, Signature)> {
+    fn from(multisig: MultiSig) -> Self {
+        multisig.entries
+    }
+}
+
+impl FromIterator<(Account, Signature)> for MultiSig {
+    fn from_iter<I: IntoIterator<Item = (Account, Signature)>>(iter: I) -> Self {
+        let mut multisig = Self::new();
+        for (account, sign) in iter {
+            multisig.push(account, sign);
+        }
+        multisig
+    }
+}
+
+/// Number of entries in 4 bytes little endian, followed by each account and its signature.
+impl ByteOrder for MultiSig {
+    fn append_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend((self.entries.len() as u32).to_le_bytes());
+        for (account, sign) in self.entries.iter() {
+            account.append_bytes(buf);
+            sign.append_bytes(buf);
+        }
+    }
+
+    fn byte_len(&self) -> usize {
+        4 + self.entries.len() * (ed25519_dalek::PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH)
+    }
+}
+
+/// Some entries of [`MultiSig`] are invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiSigError {
+    failed: Vec<Account>,
+}
+
+impl MultiSigError {
+    /// Returns accounts whose signatures are invalid.
+    pub fn failed(&self) -> &[Account] {
+        &self.failed
+    }
+}
+
+impl Display for MultiSigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} signatures are invalid.", self.failed.len())
+    }
+}
+
+impl Error for MultiSigError {}
+
+/// Context of signatures of [`SignedEnvelope`], which separates them from signatures of transactions.
+pub const ENVELOPE_CONTEXT: &[u8] = b"jellyfish-envelope-v1";
+
+/// Detached signature of an arbitrary payload, e.g., a file or a configuration blob.
+///
+/// The signed bytes are the byte order of the account, the timestamp and the digest of the payload,
+/// signed under [`ENVELOPE_CONTEXT`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    account: Account,
+    timestamp: Timestamp,
+    #[serde(
+        serialize_with = "hex::serialize",
+        deserialize_with = "crate::hex0x::deserialize"
+    )]
+    payload_digest: Digest,
+    sign: Signature,
+}
+
+impl SignedEnvelope {
+    /// Sign the payload by the account at the current time of the clock.
+    pub fn create(secret_account: &SecretAccount, clock: &dyn Clock, payload: &[u8]) -> Self {
+        Self::create_at(secret_account, clock.now(), payload)
+    }
+
+    /// Sign the payload by the account at the given time.
+    pub fn create_at(secret_account: &SecretAccount, timestamp: Timestamp, payload: &[u8]) -> Self {
+        let account = secret_account.to_public();
+        let payload_digest = Digest::create(payload);
+        let bytes = build_envelope_bytes(&account, timestamp, &payload_digest);
+        let sign = secret_account.sign_with_context(ENVELOPE_CONTEXT, &bytes);
+        Self {
+            account,
+            timestamp,
+            payload_digest,
+            sign,
+        }
+    }
+
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    pub fn payload_digest(&self) -> &Digest {
+        &self.payload_digest
+    }
+
+    pub fn sign(&self) -> &Signature {
+        &self.sign
+    }
+
+    /// Verify that the envelope is signed by its account, and the payload is the signed one.
+    ///
+    /// # Returns
+    /// `Err(EnvelopeError::Signature)` if any field of the envelope is modified, e.g., the digest,
+    /// or `Err(EnvelopeError::PayloadMismatch)` if the envelope is intact but the payload is another one.
+    pub fn verify(&self, payload: &[u8]) -> Result<(), EnvelopeError> {
+        let bytes = build_envelope_bytes(&self.account, self.timestamp, &self.payload_digest);
+        self.account
+            .verify_with_context(ENVELOPE_CONTEXT, &bytes, &self.sign)
+            .map_err(EnvelopeError::Signature)?;
+
+        let actual = Digest::create(payload);
+        if !actual.ct_eq(&self.payload_digest) {
+            return Err(EnvelopeError::PayloadMismatch {
+                expected: self.payload_digest,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Same as [`SignedEnvelope::verify()`], but also requires the envelope to be signed by the account.
+    ///
+    /// # Returns
+    /// `Err(EnvelopeError::WrongAccount)` if another account signed the envelope, even if it is valid.
+    pub fn verify_by(&self, account: &Account, payload: &[u8]) -> Result<(), EnvelopeError> {
+        if &self.account != account {
+            return Err(EnvelopeError::WrongAccount {
+                expected: Box::new(account.clone()),
+                actual: Box::new(self.account.clone()),
+            });
+        }
+        self.verify(payload)
+    }
+}
+
+fn build_envelope_bytes(
+    account: &Account,
+    timestamp: Timestamp,
+    payload_digest: &Digest,
+) -> Vec<u8> {
+    struct DigestBytes<'a>(&'a Digest);
+
+    impl ByteOrder for DigestBytes<'_> {
+        fn append_bytes(&self, buf: &mut Vec<u8>) {
+            buf.extend(self.0.as_bytes());
+        }
+    }
+
+    ByteOrderBuilder::new()
+        .append(account)
+        .append(&timestamp)
+        .append(&DigestBytes(payload_digest))
+        .finalize()
+}
+
+/// [`SignedEnvelope`] is invalid for the payload.
+#[derive(Debug)]
+pub enum EnvelopeError {
+    /// Envelope is not signed by its account, or modified after signed.
+    Signature(SignatureError),
+    /// Envelope is intact, but the payload is not the signed one.
+    PayloadMismatch { expected: Digest, actual: Digest },
+    /// Envelope is signed by another account than the expected one.
+    WrongAccount {
+        expected: Box<Account>,
+        actual: Box<Account>,
+    },
+}
+
+impl Display for EnvelopeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::Signature(e) => write!(f, "Envelope signature is invalid: {}", e),
+            EnvelopeError::PayloadMismatch { expected, actual } => {
+                write!(f, "Payload digest must be {}, but {}.", expected, actual)
+            }
+            EnvelopeError::WrongAccount { expected, actual } => write!(
+                f,
+                "Envelope must be signed by {}, but by {}.",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for EnvelopeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EnvelopeError::Signature(e) => Some(e),
+            EnvelopeError::PayloadMismatch { .. } | EnvelopeError::WrongAccount { .. } => None,
+        }
     }
 }
 
@@ -133,4 +734,560 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn verified_by() {
+        let account = crate::SecretAccount::create(&mut rand_core::OsRng {});
+        let sign = account.sign("hello");
+
+        assert!(sign.verified_by(&account.to_public(), "hello").is_ok());
+        assert!(matches!(
+            sign.verified_by(&account.to_public(), "other"),
+            Err(SignatureError::VerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn verify_any() {
+        let secrets = (0..4)
+            .map(|_| crate::SecretAccount::create(&mut rand_core::OsRng {}))
+            .collect::<Vec<_>>();
+        let accounts = secrets.iter().map(|s| s.to_public()).collect::<Vec<_>>();
+        let sign = secrets[2].sign("hello");
+
+        assert_eq!(sign.verify_any(&accounts, "hello"), Some(&accounts[2]));
+        assert_eq!(sign.verify_any(&accounts, "other"), None);
+        assert_eq!(sign.verify_any(&accounts[..2], "hello"), None);
+        assert_eq!(sign.verify_any(&[], "hello"), None);
+
+        // The first of duplicated candidates.
+        let candidates = [
+            accounts[0].clone(),
+            accounts[2].clone(),
+            accounts[2].clone(),
+        ];
+        let found = sign.verify_any(&candidates, "hello").unwrap();
+        assert!(std::ptr::eq(found, &candidates[1]));
+    }
+
+    #[test]
+    fn ct_eq_agrees_with_eq() {
+        let account = crate::SecretAccount::create(&mut rand_core::OsRng {});
+        let signs = (0..100)
+            .map(|i: u32| account.sign(&i.to_le_bytes()))
+            .collect::<Vec<_>>();
+
+        for a in &signs {
+            for b in &signs {
+                assert_eq!(a.ct_eq(b), a == b);
+            }
+        }
+    }
+
+    /// `SIGN_BYTES` with `s + L`, where `L` is the group order.
+    fn sign_bytes_s_plus_order() -> [u8; 64] {
+        let mut bytes = SIGN_BYTES;
+        let mut carry = 0;
+        for i in 0..32 {
+            let sum = bytes[32 + i] as u16 + GROUP_ORDER[i] as u16 + carry;
+            bytes[32 + i] = sum as u8;
+            carry = sum >> 8;
+        }
+        bytes
+    }
+
+    /// `SIGN_BYTES` whose `R` is `y = 1` with the sign bit set, i.e., negative zero `x`.
+    fn sign_bytes_tweaked_r() -> [u8; 64] {
+        let mut bytes = SIGN_BYTES;
+        bytes[..32].fill(0);
+        bytes[0] = 1;
+        bytes[31] = 0x80;
+        bytes
+    }
+
+    #[test]
+    fn is_canonical() {
+        assert!(super::is_canonical(&SIGN_BYTES));
+        assert!(!super::is_canonical(&sign_bytes_s_plus_order()));
+        assert!(!super::is_canonical(&sign_bytes_tweaked_r()));
+
+        // y = p, which should be encoded as 0
+        let mut bytes = SIGN_BYTES;
+        bytes[..32].copy_from_slice(&FIELD_PRIME);
+        assert!(!super::is_canonical(&bytes));
+    }
+
+    #[test]
+    fn is_small_order() {
+        let account = crate::SecretAccount::create(&mut rand_core::OsRng {});
+        assert!(!super::is_small_order(account.public_key()));
+        assert!(!super::is_small_order(&SIGN_BYTES[..32]));
+
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        assert!(super::is_small_order(&identity));
+        // y = -1, and y = 0 with the sign bit.
+        let mut minus_one = FIELD_PRIME;
+        minus_one[0] -= 1;
+        assert!(super::is_small_order(&minus_one));
+        let mut zero = [0; 32];
+        zero[31] = 0x80;
+        assert!(super::is_small_order(&zero));
+    }
+
+    #[test]
+    fn from_bytes_with_policy() {
+        let non_canonicals = {
+            // y = p, which should be encoded as 0
+            let mut r_plus_prime = SIGN_BYTES;
+            r_plus_prime[..32].copy_from_slice(&FIELD_PRIME);
+            [
+                sign_bytes_s_plus_order(),
+                sign_bytes_tweaked_r(),
+                r_plus_prime,
+            ]
+        };
+
+        for bytes in non_canonicals {
+            assert!(matches!(
+                Signature::from_bytes_with_policy(&bytes, VerifyPolicy::Strict),
+                Err(SignatureError::Malformed(_))
+            ));
+            let loose = Signature::from_bytes_with_policy(&bytes, VerifyPolicy::Loose).unwrap();
+            assert_eq!(loose.as_ref(), bytes);
+        }
+        assert!(Signature::from_bytes_with_policy(&SIGN_BYTES, VerifyPolicy::Strict).is_ok());
+    }
+
+    #[test]
+    fn from_bytes() {
+        assert!(Signature::from_bytes(&SIGN_BYTES).is_ok());
+
+        let is_strict = cfg!(feature = "strict-verification");
+        for bytes in [sign_bytes_s_plus_order(), sign_bytes_tweaked_r()] {
+            assert_eq!(Signature::from_bytes(&bytes).is_ok(), !is_strict);
+
+            let json = format!(r#""{}""#, hex::encode(bytes));
+            let result = serde_json::from_str::<Signature>(&json);
+            assert_eq!(result.is_ok(), !is_strict);
+            let result = bincode::deserialize::<Signature>(&bytes);
+            assert_eq!(result.is_ok(), !is_strict);
+        }
+    }
+
+    #[test]
+    fn to_bytes() {
+        let sign = Signature::from_bytes(&SIGN_BYTES).unwrap();
+
+        assert_eq!(sign.to_bytes(), SIGN_BYTES);
+        assert_eq!(Signature::from_bytes(&sign.to_bytes()).unwrap(), sign);
+    }
+
+    #[test]
+    fn display_agrees_with_serde() {
+        let account = crate::SecretAccount::create(&mut rand_core::OsRng {});
+
+        for i in 0..16u32 {
+            let sign = account.sign(&i.to_le_bytes());
+            let text = sign.to_string();
+
+            assert_eq!(
+                serde_json::to_string(&sign).unwrap(),
+                format!(r#""{}""#, text)
+            );
+            assert_eq!(text.parse::<Signature>().unwrap(), sign);
+            assert_eq!(
+                serde_json::from_str::<Signature>(&format!(r#""{}""#, text)).unwrap(),
+                sign
+            );
+        }
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(SIGN_HEX.parse::<Signature>().unwrap().as_ref(), SIGN_BYTES);
+        assert_eq!(
+            SIGN_HEX
+                .to_uppercase()
+                .parse::<Signature>()
+                .unwrap()
+                .as_ref(),
+            SIGN_BYTES
+        );
+    }
+
+    #[test]
+    fn from_str_prefixed() {
+        let mixed_case = SIGN_HEX.replacen("f", "F", 4);
+        for hex in [
+            format!("0x{}", SIGN_HEX),
+            format!("0X{}", SIGN_HEX.to_uppercase()),
+            format!("0x{}", mixed_case),
+            mixed_case,
+        ] {
+            assert_eq!(hex.parse::<Signature>().unwrap().as_ref(), SIGN_BYTES);
+            let json = format!(r#""{}""#, hex);
+            assert_eq!(
+                serde_json::from_str::<Signature>(&json).unwrap().as_ref(),
+                SIGN_BYTES
+            );
+        }
+
+        // Serialization stays unprefixed.
+        let sign = Signature::from_bytes(&SIGN_BYTES).unwrap();
+        assert_eq!(sign.to_string(), SIGN_HEX);
+        assert!(matches!(
+            format!("0x{}", &SIGN_HEX[2..]).parse::<Signature>(),
+            Err(ParseSignatureError::Length {
+                expected: 64,
+                actual: 63
+            })
+        ));
+    }
+
+    #[test]
+    fn from_str_fail() {
+        assert!(matches!(
+            "xyz".parse::<Signature>(),
+            Err(ParseSignatureError::Hex(_))
+        ));
+        assert!(matches!(
+            SIGN_HEX[..126].parse::<Signature>(),
+            Err(ParseSignatureError::Length {
+                expected: 64,
+                actual: 63
+            })
+        ));
+        assert!(matches!(
+            format!("{}00", SIGN_HEX).parse::<Signature>(),
+            Err(ParseSignatureError::Length {
+                expected: 64,
+                actual: 65
+            })
+        ));
+
+        // s whose highest bits are set is never a valid scalar.
+        let mut bytes = SIGN_BYTES;
+        bytes[63] = 0xff;
+        assert!(matches!(
+            hex::encode(bytes).parse::<Signature>(),
+            Err(ParseSignatureError::Signature(SignatureError::Malformed(_)))
+        ));
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let sign = Signature::from_bytes(&SIGN_BYTES).unwrap();
+
+        let ser = bincode::serialize(&sign).unwrap();
+        assert_eq!(ser, SIGN_BYTES);
+        assert_eq!(bincode::deserialize::<Signature>(&ser).unwrap(), sign);
+    }
+
+    #[test]
+    fn bincode_fail_short() {
+        assert!(bincode::deserialize::<Signature>(&SIGN_BYTES[..63]).is_err());
+        assert!(bincode::deserialize::<Signature>(&[]).is_err());
+    }
+
+    #[test]
+    fn bincode_fail_invalid_sign() {
+        let mut bytes = SIGN_BYTES;
+        bytes[63] = 0xff;
+
+        assert!(bincode::deserialize::<Signature>(&bytes).is_err());
+    }
+
+    fn signs() -> Vec<Signature> {
+        let account = crate::SecretAccount::create(&mut rand_core::OsRng {});
+        (0..20u32).map(|i| account.sign(&i.to_le_bytes())).collect()
+    }
+
+    #[test]
+    fn hash_set_dedup() {
+        let signs = signs();
+        let set = signs
+            .iter()
+            .chain(&signs)
+            .copied()
+            .collect::<std::collections::HashSet<_>>();
+
+        assert_eq!(set.len(), signs.len());
+        assert!(signs.iter().all(|sign| set.contains(sign)));
+    }
+
+    #[test]
+    fn btree_map_order() {
+        let signs = signs();
+        let map = signs
+            .iter()
+            .enumerate()
+            .map(|(i, sign)| (*sign, i))
+            .collect::<std::collections::BTreeMap<_, _>>();
+
+        let mut expected = signs.iter().map(|sign| sign.to_bytes()).collect::<Vec<_>>();
+        expected.sort();
+        let keys = map.keys().map(|sign| sign.to_bytes()).collect::<Vec<_>>();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn hash_after_round_trip() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash(sign: &Signature) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            sign.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        for sign in signs() {
+            let json = serde_json::from_str::<Signature>(&serde_json::to_string(&sign).unwrap());
+            let binary = bincode::deserialize::<Signature>(&bincode::serialize(&sign).unwrap());
+
+            for restored in [json.unwrap(), binary.unwrap()] {
+                assert_eq!(restored, sign);
+                assert_eq!(hash(&restored), hash(&sign));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_multi_sig {
+    use super::*;
+    use crate::SecretAccount;
+
+    const MSG: &[u8] = b"jellyfish";
+
+    fn create_multisig(len: usize) -> MultiSig {
+        (0..len)
+            .map(|_| {
+                let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+                (secret_account.to_public(), secret_account.sign(MSG))
+            })
+            .collect()
+    }
+
+    /// Replace signatures at the indexes by signatures of another message.
+    fn corrupt(multisig: &MultiSig, indexes: &[usize]) -> MultiSig {
+        let other = SecretAccount::create(&mut rand_core::OsRng {}).sign(b"other");
+        multisig
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(i, (account, sign))| {
+                let sign = if indexes.contains(&i) { other } else { *sign };
+                (account.clone(), sign)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify_all() {
+        for len in [
+            0,
+            1,
+            MULTISIG_BATCH_THRESHOLD,
+            MULTISIG_BATCH_THRESHOLD + 1,
+            20,
+        ] {
+            let multisig = create_multisig(len);
+
+            assert_eq!(multisig.len(), len);
+            assert_eq!(multisig.verify_all(MSG), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verify_all_fail_serial() {
+        let multisig = create_multisig(MULTISIG_BATCH_THRESHOLD);
+        let corrupted = corrupt(&multisig, &[0, 2]);
+
+        let error = corrupted.verify_all(MSG).unwrap_err();
+
+        let expected = [&multisig.entries()[0].0, &multisig.entries()[2].0];
+        assert_eq!(error.failed().iter().collect::<Vec<_>>(), expected);
+        assert!(multisig.verify_all(b"other").is_err());
+    }
+
+    #[test]
+    fn verify_all_fail_batch() {
+        let multisig = create_multisig(10);
+        let corrupted = corrupt(&multisig, &[3, 9]);
+
+        let error = corrupted.verify_all(MSG).unwrap_err();
+
+        let expected = [&multisig.entries()[3].0, &multisig.entries()[9].0];
+        assert_eq!(error.failed().iter().collect::<Vec<_>>(), expected);
+        assert_eq!(
+            multisig.verify_all(b"other").unwrap_err().failed().len(),
+            10
+        );
+    }
+
+    #[test]
+    fn push_dedup() {
+        let mut multisig = create_multisig(2);
+        let (account, _) = multisig.entries()[0].clone();
+        let other = SecretAccount::create(&mut rand_core::OsRng {}).sign(MSG);
+
+        assert!(!multisig.push(account, other));
+        assert_eq!(multisig.len(), 2);
+        assert_eq!(multisig.verify_all(MSG), Ok(()));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let multisig = create_multisig(3);
+
+        let json = serde_json::to_string(&multisig).unwrap();
+        assert!(json.starts_with(r#"[[{"name":""#));
+        assert!(json.contains(&multisig.entries()[0].1.to_string()));
+        assert_eq!(serde_json::from_str::<MultiSig>(&json).unwrap(), multisig);
+
+        let binary = bincode::serialize(&multisig).unwrap();
+        assert_eq!(bincode::deserialize::<MultiSig>(&binary).unwrap(), multisig);
+    }
+
+    #[test]
+    fn deserialize_dedup() {
+        let multisig = create_multisig(2);
+        let mut entries = multisig.entries().to_vec();
+        entries.push(entries[0].clone());
+
+        let json = serde_json::to_string(&entries).unwrap();
+        let de = serde_json::from_str::<MultiSig>(&json).unwrap();
+
+        assert_eq!(de, multisig);
+    }
+
+    #[test]
+    fn byte_order() {
+        let multisig = create_multisig(3);
+
+        let bytes = multisig.build_byte_order();
+
+        assert_eq!(bytes.len(), multisig.byte_len());
+        assert_eq!(&bytes[..4], 3u32.to_le_bytes());
+        assert_eq!(&bytes[4..36], multisig.entries()[0].0.as_bytes());
+        assert_eq!(&bytes[36..100], multisig.entries()[0].1.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests_envelope {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const PAYLOAD: &[u8] = b"jellyfish";
+
+    /// Secret key of RFC 8032 7.1 TEST 1.
+    const SECRET_HEX: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+    /// Envelope of `PAYLOAD` by `SECRET_HEX` at `TIMESTAMP_NANOS`, which must never change.
+    ///
+    /// Captured from `serde_json::to_string(&golden_envelope())`.
+    /// Ed25519 signing is deterministic, so the signature is reproducible.
+    const GOLDEN_JSON: &str = r#"{"account":{"name":"d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"},"timestamp":1600000000000000000,"payload_digest":"a0ea5328f032f1557fbc5d6516c59cc85e7c0fa270c43085f9c994ef2915449b","sign":"7f11810ac21b01ce9c3712fa39774d5dc629e71334bb1ff1dbfc2df47a3db463d9e2b18f3abbe749a57862d790dd0b200d8c00fd16957415ce306234bd266c02"}"#;
+
+    const TIMESTAMP_NANOS: i64 = 1_600_000_000_000_000_000;
+
+    fn golden_envelope() -> SignedEnvelope {
+        let secret = hex::decode(SECRET_HEX).unwrap();
+        let secret_account = SecretAccount::from_secret_bytes(&secret).unwrap();
+        SignedEnvelope::create_at(
+            &secret_account,
+            Timestamp::from_nanos(TIMESTAMP_NANOS),
+            PAYLOAD,
+        )
+    }
+
+    #[test]
+    fn golden_json() {
+        let envelope = golden_envelope();
+
+        let json = serde_json::to_string(&envelope).unwrap();
+
+        assert_eq!(json, GOLDEN_JSON);
+        let de = serde_json::from_str::<SignedEnvelope>(GOLDEN_JSON).unwrap();
+        assert_eq!(de, envelope);
+        assert!(de.verify(PAYLOAD).is_ok());
+    }
+
+    #[test]
+    fn create_and_verify() {
+        let secret_account = SecretAccount::create(&mut rand_core::OsRng {});
+        let clock = ManualClock::new(Timestamp::from_nanos(TIMESTAMP_NANOS));
+        let envelope = SignedEnvelope::create(&secret_account, &clock, PAYLOAD);
+
+        assert_eq!(envelope.account(), &secret_account.to_public());
+        assert_eq!(envelope.timestamp(), Timestamp::from_nanos(TIMESTAMP_NANOS));
+        assert_eq!(envelope.payload_digest(), &Digest::create(PAYLOAD));
+        assert!(envelope.verify(PAYLOAD).is_ok());
+        assert!(envelope
+            .verify_by(&secret_account.to_public(), PAYLOAD)
+            .is_ok());
+    }
+
+    #[test]
+    fn verify_fail_payload_substitution() {
+        let envelope = golden_envelope();
+
+        let result = envelope.verify(b"jellyfisH");
+
+        match result {
+            Err(EnvelopeError::PayloadMismatch { expected, actual }) => {
+                assert_eq!(expected, Digest::create(PAYLOAD));
+                assert_eq!(actual, Digest::create(b"jellyfisH"));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn verify_fail_digest_modified() {
+        let mut envelope = golden_envelope();
+        // Digest of the substituted payload, so that the payload alone looks consistent.
+        envelope.payload_digest = Digest::create(b"other");
+
+        assert!(matches!(
+            envelope.verify(b"other"),
+            Err(EnvelopeError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn verify_fail_wrong_account() {
+        let envelope = golden_envelope();
+        let other = SecretAccount::create(&mut rand_core::OsRng {});
+
+        // Envelope re-signed by another account is valid, but not by the expected one.
+        let forged = SignedEnvelope::create_at(&other, envelope.timestamp(), PAYLOAD);
+        assert!(forged.verify(PAYLOAD).is_ok());
+        assert!(matches!(
+            forged.verify_by(envelope.account(), PAYLOAD),
+            Err(EnvelopeError::WrongAccount { .. })
+        ));
+
+        // Account replaced without re-signing.
+        let mut replaced = envelope.clone();
+        replaced.account = other.to_public();
+        assert!(matches!(
+            replaced.verify(PAYLOAD),
+            Err(EnvelopeError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn not_valid_as_plain_signature() {
+        let envelope = golden_envelope();
+        let bytes = build_envelope_bytes(
+            envelope.account(),
+            envelope.timestamp(),
+            envelope.payload_digest(),
+        );
+
+        assert!(envelope.account().verify(&bytes, envelope.sign()).is_err());
+    }
 }
@@ -0,0 +1,229 @@
+//! Abstraction of signers, so that transactions can be created by keys out of the process, e.g., in an HSM or a remote signing service.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::account::Prehash;
+use crate::{Account, SecretAccount, Signature};
+
+/// Creates signatures of an account, which [`Transaction::create_with_signer()`](crate::Transaction::create_with_signer) uses.
+pub trait TransactionSigner {
+    /// Returns the account whose signatures the signer creates.
+    fn public(&self) -> Account;
+
+    /// Sign the message by ed25519.
+    fn sign(&self, msg: &[u8]) -> Result<Signature, SignError>;
+
+    /// Sign the prehash by ed25519ph under the context, which large contents are signed by.
+    ///
+    /// Signers supporting only ed25519 keep the default, which returns [`SignError::Unsupported`],
+    /// and then large contents are signed by [`TransactionSigner::sign()`] instead.
+    fn sign_prehashed(
+        &self,
+        prehash: &Prehash,
+        context: Option<&[u8]>,
+    ) -> Result<Signature, SignError> {
+        let _ = (prehash, context);
+        Err(SignError::Unsupported)
+    }
+}
+
+/// Signer of a secret key in memory, which never fails.
+impl TransactionSigner for SecretAccount {
+    fn public(&self) -> Account {
+        self.to_public()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Signature, SignError> {
+        Ok(SecretAccount::sign(self, msg))
+    }
+
+    fn sign_prehashed(
+        &self,
+        prehash: &Prehash,
+        context: Option<&[u8]>,
+    ) -> Result<Signature, SignError> {
+        Ok(SecretAccount::sign_prehashed(self, prehash, context))
+    }
+}
+
+/// Signer cannot create the signature.
+#[derive(Debug)]
+pub enum SignError {
+    /// Signer does not support the algorithm, e.g., ed25519ph.
+    Unsupported,
+    /// Signer failed, e.g., a device is disconnected or a remote service refused.
+    Signer(Box<dyn Error + Send + Sync>),
+}
+
+impl Display for SignError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SignError::Unsupported => write!(f, "Signer does not support the algorithm."),
+            SignError::Signer(e) => write!(f, "Signer failed: {}", e),
+        }
+    }
+}
+
+impl Error for SignError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SignError::Unsupported => None,
+            SignError::Signer(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::params::ChainParams;
+    use crate::transaction::{SignMode, PREHASH_THRESHOLD};
+    use crate::{ByteOrder, Timestamp, Transaction, Yet};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct ContentStab(Vec<u8>);
+
+    impl ByteOrder for ContentStab {
+        fn append_bytes(&self, buf: &mut Vec<u8>) {
+            buf.extend(&self.0);
+        }
+    }
+
+    /// Supports only ed25519, and counts signatures.
+    struct CountingSigner {
+        secret: SecretAccount,
+        count: Cell<usize>,
+    }
+
+    impl CountingSigner {
+        fn new() -> Self {
+            Self {
+                secret: SecretAccount::create(&mut rand_core::OsRng {}),
+                count: Cell::new(0),
+            }
+        }
+    }
+
+    impl TransactionSigner for CountingSigner {
+        fn public(&self) -> Account {
+            self.secret.to_public()
+        }
+
+        fn sign(&self, msg: &[u8]) -> Result<Signature, SignError> {
+            self.count.set(self.count.get() + 1);
+            Ok(self.secret.sign(msg))
+        }
+    }
+
+    /// Device which is disconnected.
+    struct FailingSigner(Account);
+
+    impl TransactionSigner for FailingSigner {
+        fn public(&self) -> Account {
+            self.0.clone()
+        }
+
+        fn sign(&self, _msg: &[u8]) -> Result<Signature, SignError> {
+            Err(SignError::Signer("device is disconnected".into()))
+        }
+    }
+
+    fn verify(tx: Transaction<ContentStab, crate::Verified>, params: &ChainParams) -> bool {
+        tx.into_unverified().verify_with_params(params).is_ok()
+    }
+
+    #[test]
+    fn create_with_signer() {
+        let signer = CountingSigner::new();
+        let params = ChainParams::mainnet();
+
+        let tx = Transaction::create_for_with_signer(
+            &params,
+            &signer,
+            Timestamp::now(),
+            ContentStab(vec![0]),
+        )
+        .unwrap();
+
+        assert_eq!(signer.count.get(), 1);
+        assert_eq!(tx.account(), &signer.public());
+        assert!(verify(tx, &params));
+    }
+
+    #[test]
+    fn create_with_signer_same_as_secret_account() {
+        let secret = SecretAccount::create(&mut rand_core::OsRng {});
+        let timestamp = Timestamp::now();
+
+        for len in [1, PREHASH_THRESHOLD + 1] {
+            let content = ContentStab(vec![0; len]);
+            let by_signer =
+                Transaction::create_with_signer(&secret, timestamp, content.clone()).unwrap();
+            let by_secret = Transaction::create(&secret, timestamp, content);
+
+            assert_eq!(by_signer, by_secret);
+        }
+    }
+
+    #[test]
+    fn create_large_content_without_prehash_support() {
+        let signer = CountingSigner::new();
+        let params = ChainParams {
+            protocol_version: 2,
+            ..ChainParams::mainnet()
+        };
+        let content = ContentStab(vec![0; PREHASH_THRESHOLD + 1]);
+
+        let tx = Transaction::create_for_with_signer(&params, &signer, Timestamp::now(), content)
+            .unwrap();
+
+        assert_eq!(tx.sign_mode(), SignMode::Plain);
+        assert_eq!(signer.count.get(), 1);
+        assert!(verify(tx, &params));
+    }
+
+    #[test]
+    fn create_fail_signer() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {}).to_public();
+        let signer = FailingSigner(account);
+
+        let result = Transaction::<_, crate::Verified>::create_with_signer(
+            &signer,
+            Timestamp::now(),
+            ContentStab(vec![0]),
+        );
+
+        match result {
+            Err(SignError::Signer(e)) => assert_eq!(e.to_string(), "device is disconnected"),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn forged_public_not_verified() {
+        // Signer which reports another account than its key.
+        struct LyingSigner(CountingSigner, Account);
+
+        impl TransactionSigner for LyingSigner {
+            fn public(&self) -> Account {
+                self.1.clone()
+            }
+
+            fn sign(&self, msg: &[u8]) -> Result<Signature, SignError> {
+                self.0.sign(msg)
+            }
+        }
+
+        let other = SecretAccount::create(&mut rand_core::OsRng {}).to_public();
+        let signer = LyingSigner(CountingSigner::new(), other);
+
+        let tx = Transaction::create_with_signer(&signer, Timestamp::now(), ContentStab(vec![0]))
+            .unwrap()
+            .into_unverified();
+
+        assert!(Transaction::<_, Yet>::verify(tx).is_err());
+    }
+}
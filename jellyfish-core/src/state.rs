@@ -0,0 +1,2356 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use itertools::Itertools;
+use rs_merkle::MerkleTree;
+use serde::{Deserialize, Serialize};
+
+use crate::alias::AliasRecord;
+use crate::block::{Block, Header};
+use crate::digest::Digest;
+use crate::hashing::DefaultMerkle;
+use crate::jellyfish_transaction::{
+    ContentError, ContentRule, JellyfishTransactionContent, Method, TransactionIdentifier,
+    MAX_BATCH_OPERATIONS,
+};
+use crate::ordering::{canonical_sort, canonical_sort_by_key};
+use crate::schema::RecordSchemaRegistry;
+use crate::{Account, ByteOrder, Sha256Digest, Timestamp, Transaction, Verified};
+
+/// Separator placed between a record and a fragment appended to it, unless configured otherwise.
+pub const DEFAULT_APPEND_SEPARATOR: &str = "\n";
+
+/// Maximum length in bytes of a record grown by `Append`, unless configured otherwise.
+pub const DEFAULT_MAX_RECORD_LEN: usize = 64 * 1024;
+
+/// A change applied to a record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordEvent {
+    method: Method,
+    /// Offerer of the transaction.
+    account: Account,
+    /// Record after the change. `None` if the record was removed.
+    record: Option<String>,
+    /// Lifetime of the record after the change in nanoseconds.
+    ttl_nanos: Option<i64>,
+    /// Height of the block containing the transaction.
+    height: u64,
+    /// Timestamp of the block containing the transaction.
+    timestamp: Timestamp,
+}
+
+impl RecordEvent {
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    pub fn record(&self) -> Option<&str> {
+        self.record.as_deref()
+    }
+
+    pub fn ttl_nanos(&self) -> Option<i64> {
+        self.ttl_nanos
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Returns whether TTL of the record after the change has elapsed at the given time.
+    pub(crate) fn is_expired_at(&self, now: Timestamp) -> bool {
+        self.ttl_nanos
+            .is_some_and(|ttl| self.timestamp.nanos().saturating_add(ttl) < now.nanos())
+    }
+}
+
+/// Who removed a record and when.
+///
+/// A tombstone is kept after `Remove`, so that a removed record can be distinguished from an unknown one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tombstone {
+    /// Offerer of `Remove` transaction.
+    pub account: Account,
+    /// Height of the block containing `Remove` transaction.
+    pub height: u64,
+    /// Timestamp of the block containing `Remove` transaction.
+    pub timestamp: Timestamp,
+}
+
+/// A record in the ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RecordEntry {
+    /// Offerer of `Insert` transaction.
+    owner: Account,
+    /// All changes in applied order. The first one is always `Insert`.
+    history: Vec<RecordEvent>,
+}
+
+impl RecordEntry {
+    /// Returns the current record, or `None` if the record has been removed.
+    fn record(&self) -> Option<&str> {
+        self.latest().record()
+    }
+
+    fn latest(&self) -> &RecordEvent {
+        // An entry has at least `Insert` event, so the below unwrap() always succeeds.
+        self.history.last().unwrap()
+    }
+
+    /// Returns the tombstone if the record has been removed.
+    fn tombstone(&self) -> Option<Tombstone> {
+        let latest = self.latest();
+        (latest.method == Method::Remove).then(|| Tombstone {
+            account: latest.account.clone(),
+            height: latest.height,
+            timestamp: latest.timestamp,
+        })
+    }
+
+    /// Returns whether the record exists at the given time, that is, it is neither removed nor expired.
+    fn is_live(&self, now: Option<Timestamp>) -> bool {
+        self.record().is_some() && !self.is_expired(now)
+    }
+
+    fn is_expired(&self, now: Option<Timestamp>) -> bool {
+        now.is_some_and(|now| self.latest().is_expired_at(now))
+    }
+
+    fn alias(&self) -> Option<AliasRecord> {
+        self.record()
+            .and_then(AliasRecord::parse)
+            .and_then(Result::ok)
+    }
+
+    /// Returns the account which may modify or remove the record.
+    ///
+    /// An alias record belongs to the aliased account, so that the alias can be transferred.
+    /// Other records belong to the offerer of `Insert` transaction.
+    fn authority(&self) -> Account {
+        match self.alias() {
+            Some(alias) => alias.account().clone(),
+            None => self.owner.clone(),
+        }
+    }
+}
+
+/// Records built by applying jellyfish transactions in blocks.
+///
+/// A record is identified by its `Insert` transaction.
+/// `Modify`, `Remove` and `Append` transactions target the `Insert` transaction of the record,
+/// and only the record's owner may offer them.
+///
+/// `Append` adds a fragment to the end of the record following a separator,
+/// as long as the resulting record does not exceed the maximum length.
+///
+/// A removed record is kept as a tombstone until it is purged by [`LedgerState::purge_tombstones_older_than()`].
+///
+/// A record with TTL expires once timestamp of a later block exceeds its block timestamp plus TTL.
+/// Expired records are excluded from queries, but their history is kept.
+///
+/// Records beginning with [`ALIAS_PREFIX`](crate::alias::ALIAS_PREFIX) are also maintained as a unique name-to-account map.
+#[derive(Debug, Clone)]
+pub struct LedgerState {
+    records: HashMap<TransactionIdentifier, RecordEntry>,
+    /// Record of each alias name, and the account which the name is resolved to.
+    aliases: HashMap<String, (TransactionIdentifier, Account)>,
+    rules: Vec<Arc<dyn ContentRule>>,
+    observers: Vec<Arc<dyn StateObserver>>,
+    /// Maximum number of operations in a `Batch` transaction.
+    max_batch_operations: usize,
+    /// Placed between a record and a fragment appended to it.
+    append_separator: String,
+    /// Maximum length in bytes of a record grown by `Append`.
+    max_record_len: usize,
+    /// Timestamp of the latest applied block.
+    timestamp: Option<Timestamp>,
+}
+
+impl LedgerState {
+    /// Returns a state without records, which enforces [`RecordSchemaRegistry::standard()`].
+    pub fn new() -> Self {
+        Self {
+            records: HashMap::new(),
+            aliases: HashMap::new(),
+            rules: vec![Arc::new(RecordSchemaRegistry::standard())],
+            observers: vec![],
+            max_batch_operations: MAX_BATCH_OPERATIONS,
+            append_separator: DEFAULT_APPEND_SEPARATOR.to_owned(),
+            max_record_len: DEFAULT_MAX_RECORD_LEN,
+            timestamp: None,
+        }
+    }
+
+    /// Add a rule which every transaction content must satisfy.
+    pub fn with_rule<R>(mut self, rule: R) -> Self
+    where
+        R: ContentRule + 'static,
+    {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Add an observer which is notified of every [`StateEvent`] once the state has been changed.
+    pub fn with_observer(mut self, observer: Arc<dyn StateObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Set the maximum number of operations in a `Batch` transaction.
+    pub fn with_max_batch_operations(mut self, max_batch_operations: usize) -> Self {
+        self.max_batch_operations = max_batch_operations;
+        self
+    }
+
+    /// Set the separator placed between a record and a fragment appended to it.
+    pub fn with_append_separator<'a, T>(mut self, separator: T) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        self.append_separator = separator.into().into_owned();
+        self
+    }
+
+    /// Set the maximum length in bytes of a record grown by `Append`.
+    pub fn with_max_record_len(mut self, max_record_len: usize) -> Self {
+        self.max_record_len = max_record_len;
+        self
+    }
+
+    /// Returns the current record identified by the `Insert` transaction.
+    /// `None` if the record does not exist, has been removed or has expired.
+    pub fn record(&self, id: &TransactionIdentifier) -> Option<&str> {
+        self.live_entry(id).and_then(RecordEntry::record)
+    }
+
+    /// Returns all current records, except for removed or expired ones.
+    pub fn records(&self) -> impl Iterator<Item = (&TransactionIdentifier, &str)> {
+        self.records
+            .iter()
+            .filter(|(_, entry)| entry.is_live(self.timestamp))
+            .filter_map(|(id, entry)| entry.record().map(|record| (id, record)))
+    }
+
+    /// Returns the tombstone of the record if it has been removed and not purged yet.
+    pub fn tombstone(&self, id: &TransactionIdentifier) -> Option<Tombstone> {
+        self.records.get(id).and_then(RecordEntry::tombstone)
+    }
+
+    /// Forget records removed before the given height, including their history.
+    ///
+    /// This bounds memory used by tombstones.
+    /// After purge, changing a purged record fails with [`StateError::TargetUnknown`].
+    pub fn purge_tombstones_older_than(&mut self, height: u64) {
+        self.records.retain(|_, entry| match entry.tombstone() {
+            Some(tombstone) => tombstone.height >= height,
+            None => true,
+        });
+    }
+
+    /// Returns a snapshot of all records in ascending order of identifier, including tombstones which have not been purged.
+    pub fn snapshot(&self) -> StateSnapshot {
+        let mut records = self
+            .records
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.clone()))
+            .collect_vec();
+        canonical_sort_by_key(&mut records, |(id, _)| id);
+
+        StateSnapshot {
+            records,
+            timestamp: self.timestamp,
+        }
+    }
+
+    /// Restore a state from the snapshot. The restored state enforces [`RecordSchemaRegistry::standard()`].
+    pub fn from_snapshot(snapshot: StateSnapshot) -> Self {
+        let mut state = Self::new();
+        state.timestamp = snapshot.timestamp;
+        state.records = snapshot.records.into_iter().collect();
+        state.rebuild_aliases();
+        state
+    }
+
+    fn rebuild_aliases(&mut self) {
+        self.aliases.clear();
+        for (id, entry) in &self.records {
+            if let Some(alias) = entry.alias().filter(|_| entry.is_live(self.timestamp)) {
+                let value = (id.clone(), alias.account().clone());
+                self.aliases.insert(alias.name().to_owned(), value);
+            }
+        }
+    }
+
+    /// Returns all changes applied to the record, including its removal or expiry.
+    /// Empty if the record never existed.
+    pub fn history(&self, id: &TransactionIdentifier) -> &[RecordEvent] {
+        self.records
+            .get(id)
+            .map_or(&[], |entry| entry.history.as_slice())
+    }
+
+    /// Returns all records and their history, including removed or expired ones.
+    pub(crate) fn histories(
+        &self,
+    ) -> impl Iterator<Item = (&TransactionIdentifier, &[RecordEvent])> {
+        self.records
+            .iter()
+            .map(|(id, entry)| (id, entry.history.as_slice()))
+    }
+
+    /// Returns the account which the alias name is resolved to.
+    pub fn resolve_alias(&self, name: &str) -> Option<&Account> {
+        let (id, account) = self.aliases.get(name)?;
+        self.live_entry(id).map(|_| account)
+    }
+
+    /// Returns alias names resolved to the account, in ascending order.
+    pub fn aliases_of(&self, account: &Account) -> Vec<&str> {
+        let mut names = self
+            .aliases
+            .keys()
+            .filter(|name| self.resolve_alias(name) == Some(account))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns merkle root over all current records, which is committed by block headers.
+    ///
+    /// Leaves are digests of identifier and content of each record, in ascending order of identifier.
+    /// Root of a state without records is all zero.
+    pub fn state_root(&self) -> Digest {
+        Digest::from_bytes(self.state_tree().root().unwrap_or_default())
+    }
+
+    /// Build a proof that the record currently has its content.
+    ///
+    /// # Returns
+    /// `None` if the record does not exist, has been removed or has expired.
+    pub fn prove_record(&self, id: &TransactionIdentifier) -> Option<StateProof> {
+        let records = self.sorted_records();
+        let index = records.iter().position(|(record_id, _)| *record_id == id)?;
+        let proof = self.state_tree().proof(&[index]);
+
+        Some(StateProof {
+            id: id.clone(),
+            record: records[index].1.to_owned(),
+            leaf_index: index,
+            leaf_count: records.len(),
+            hashes: proof.to_bytes(),
+        })
+    }
+
+    fn state_tree(&self) -> MerkleTree<DefaultMerkle> {
+        let leaves = self
+            .sorted_records()
+            .into_iter()
+            .map(|(id, record)| state_leaf(id, record))
+            .collect_vec();
+        MerkleTree::from_leaves(&leaves)
+    }
+
+    /// Returns all current records in ascending order of identifier.
+    fn sorted_records(&self) -> Vec<(&TransactionIdentifier, &str)> {
+        let mut records = self.records().collect_vec();
+        canonical_sort_by_key(&mut records, |(id, _)| id);
+        records
+    }
+
+    /// Returns changes of records from `self` to `other`.
+    ///
+    /// Records are compared by their current content, so an expired record is reported as removed.
+    pub fn diff(&self, other: &LedgerState) -> StateDiff {
+        self.diff_records(other, self.records.keys().chain(other.records.keys()))
+    }
+
+    /// Same as [`LedgerState::diff()`], but only the given records are compared.
+    pub(crate) fn diff_records<'a, I>(&self, other: &LedgerState, ids: I) -> StateDiff
+    where
+        I: IntoIterator<Item = &'a TransactionIdentifier>,
+    {
+        let mut ids = ids.into_iter().collect::<Vec<_>>();
+        canonical_sort(&mut ids);
+        ids.dedup();
+
+        let mut diff = StateDiff::default();
+        for id in ids {
+            let latest = other.history(id).last().or_else(|| self.history(id).last());
+            let account = match latest {
+                Some(event) => event.account().clone(),
+                None => continue,
+            };
+            let change = |old_record: Option<&str>, new_record: Option<&str>| RecordChange {
+                id: id.clone(),
+                account: account.clone(),
+                old_record: old_record.map(str::to_owned),
+                new_record: new_record.map(str::to_owned),
+            };
+
+            match (self.record(id), other.record(id)) {
+                (None, Some(new)) => diff.inserted.push(change(None, Some(new))),
+                (Some(old), Some(new)) if old != new => {
+                    diff.modified.push(change(Some(old), Some(new)))
+                }
+                (Some(old), None) => diff.removed.push(change(Some(old), None)),
+                _ => {}
+            }
+        }
+        diff
+    }
+
+    /// Returns identifiers of records which may expire without any transaction.
+    pub(crate) fn expirable_records(&self) -> impl Iterator<Item = &TransactionIdentifier> {
+        self.records
+            .iter()
+            .filter(|(_, entry)| entry.latest().ttl_nanos.is_some())
+            .map(|(id, _)| id)
+    }
+
+    /// Check targets of the transaction against the current state without applying it,
+    /// e.g., before accepting the transaction into a mempool.
+    ///
+    /// Applying the transaction may still fail for other reasons, e.g., a conflicting alias.
+    pub fn check_targets(
+        &self,
+        tx: &Transaction<JellyfishTransactionContent, Verified>,
+    ) -> Result<(), StateError> {
+        let content = tx.content();
+        if content.method() == Method::Batch {
+            content
+                .check_batch(self.max_batch_operations)
+                .map_err(StateError::Content)?;
+        }
+        for target in content
+            .operations()
+            .iter()
+            .filter_map(JellyfishTransactionContent::target_transaction)
+        {
+            self.check_target(target, tx.account())?;
+        }
+        Ok(())
+    }
+
+    /// Check a single target of a transaction by the account, as [`LedgerState::check_targets()`] does.
+    pub(crate) fn check_target_of(
+        &self,
+        target: &TransactionIdentifier,
+        account: &Account,
+    ) -> Result<(), StateError> {
+        self.check_target(target, account).map(|_| ())
+    }
+
+    /// Apply all transactions in the block strictly in block order.
+    ///
+    /// When transactions in the block target the same record, a later one sees the result of an earlier one.
+    /// So among `Modify` transactions, the last one wins, and `Remove` after `Modify` leaves the record removed.
+    /// However, any transaction targeting a record removed earlier in the same block makes the whole block invalid
+    /// with [`StateError::IntraBlockConflict`].
+    ///
+    /// If any transaction is rejected, the state is left unchanged.
+    ///
+    /// # Returns
+    /// Receipt of each transaction in block order.
+    /// Applying the same block to the same state always yields identical receipts.
+    pub fn apply_block<VB>(
+        &mut self,
+        block: &Block<JellyfishTransactionContent, Verified, VB>,
+    ) -> Result<Vec<Receipt>, StateError> {
+        self.apply_block_with_events(block)
+            .map(|(receipts, _)| receipts)
+    }
+
+    /// Same as [`LedgerState::apply_block()`], but also returns events of the changes in applied order.
+    ///
+    /// Records expired by timestamp of the block come first, in ascending order of identifier,
+    /// followed by changes of transactions in block order.
+    /// Transactions with [`Outcome::NoOp`] emit no event.
+    pub fn apply_block_with_events<VB>(
+        &mut self,
+        block: &Block<JellyfishTransactionContent, Verified, VB>,
+    ) -> Result<(Vec<Receipt>, Vec<StateEvent>), StateError> {
+        let result = self.apply_block_unnotified(block)?;
+        self.notify_observers(&result.1);
+        Ok(result)
+    }
+
+    /// Same as [`LedgerState::apply_block_with_events()`], but observers are not notified.
+    pub(crate) fn apply_block_unnotified<VB>(
+        &mut self,
+        block: &Block<JellyfishTransactionContent, Verified, VB>,
+    ) -> Result<(Vec<Receipt>, Vec<StateEvent>), StateError> {
+        let mut removed = RemovedTargets::default();
+        for (index, tx) in block.transactions().iter().enumerate() {
+            removed
+                .check_and_record(index, tx.content())
+                .map_err(|first_index| StateError::IntraBlockConflict {
+                    first_index,
+                    second_index: index,
+                })?;
+        }
+
+        let header = block.header();
+        let mut next = self.clone();
+        next.timestamp = Some(header.timestamp());
+        let mut events = self.expire_records(header.height(), header.timestamp());
+        let mut receipts = vec![];
+        for (tx_index, tx) in block.transactions().iter().enumerate() {
+            let (outcome, id) =
+                next.apply_transaction(header.height(), header.timestamp(), tx, &mut events)?;
+            receipts.push(Receipt {
+                tx_index,
+                sign_digest: Digest::create(tx.sign()),
+                outcome,
+                resulting_record_id: id,
+            });
+        }
+        mark_superseded(&mut receipts, block.transactions());
+
+        *self = next;
+        Ok((receipts, events))
+    }
+
+    /// Undo the block, which must be the latest block applied to the state.
+    /// `parent` is the header of the block preceding it.
+    ///
+    /// If the block cannot be undone, the state is left unchanged.
+    ///
+    /// # Returns
+    /// Inverse of events emitted by applying the block, in reverse order.
+    pub fn disconnect_block<VB>(
+        &mut self,
+        block: &Block<JellyfishTransactionContent, Verified, VB>,
+        parent: &Header,
+    ) -> Result<Vec<StateEvent>, StateError> {
+        let height = block.header().height();
+        let error = StateError::Disconnect { height };
+        if self.timestamp != Some(block.header().timestamp()) {
+            return Err(error);
+        }
+
+        let mut previous = self.clone();
+        previous.timestamp = Some(parent.timestamp());
+        for id in touched_records(block) {
+            if let Some(entry) = previous.records.get_mut(&id) {
+                entry.history.retain(|event| event.height != height);
+                if entry.history.is_empty() {
+                    previous.records.remove(&id);
+                }
+            }
+        }
+        previous.rebuild_aliases();
+
+        // Applying the block again must reproduce the current state, otherwise the block was not the latest one
+        // or its records have been purged.
+        let mut replayed = previous.clone();
+        let (_, events) = replayed
+            .apply_block_unnotified(block)
+            .map_err(|_| error.clone())?;
+        if replayed.records != self.records {
+            return Err(error);
+        }
+
+        let events = events
+            .iter()
+            .rev()
+            .map(|event| event.inverse(height))
+            .collect_vec();
+        *self = previous;
+        self.notify_observers(&events);
+        Ok(events)
+    }
+
+    pub(crate) fn notify_observers(&self, events: &[StateEvent]) {
+        for observer in &self.observers {
+            for event in events {
+                observer.on_event(event);
+            }
+        }
+    }
+
+    /// Returns events of records which are live now but expire at the given timestamp.
+    fn expire_records(&self, height: u64, timestamp: Timestamp) -> Vec<StateEvent> {
+        let mut expired = self
+            .records
+            .iter()
+            .filter(|(_, entry)| entry.is_live(self.timestamp))
+            .filter(|(_, entry)| entry.is_expired(Some(timestamp)))
+            .collect_vec();
+        canonical_sort_by_key(&mut expired, |(id, _)| id);
+
+        expired
+            .into_iter()
+            .map(|(id, entry)| StateEvent::RecordExpired {
+                id: id.clone(),
+                account: entry.latest().account.clone(),
+                text: entry.record().unwrap_or_default().to_owned(),
+                height,
+            })
+            .collect()
+    }
+
+    /// Apply the transaction, or each operation of a `Batch` transaction in order.
+    ///
+    /// # Returns
+    /// Identifier of the changed record, or `None` for `Batch`.
+    /// `Batch` is `NoOp` only if all of its operations are.
+    fn apply_transaction(
+        &mut self,
+        height: u64,
+        timestamp: Timestamp,
+        tx: &Transaction<JellyfishTransactionContent, Verified>,
+        events: &mut Vec<StateEvent>,
+    ) -> Result<(Outcome, Option<TransactionIdentifier>), StateError> {
+        let content = tx.content();
+        if content.method() != Method::Batch {
+            let (outcome, id) = self.apply_operation(height, timestamp, tx, content, events)?;
+            return Ok((outcome, Some(id)));
+        }
+
+        content
+            .check_batch(self.max_batch_operations)
+            .map_err(StateError::Content)?;
+        let mut outcome = Outcome::NoOp;
+        for operation in content.operations() {
+            let (op_outcome, _) = self.apply_operation(height, timestamp, tx, operation, events)?;
+            if op_outcome == Outcome::Applied {
+                outcome = Outcome::Applied;
+            }
+        }
+        Ok((outcome, None))
+    }
+
+    /// Apply a single operation, which is not `Batch`, offered by the transaction.
+    fn apply_operation(
+        &mut self,
+        height: u64,
+        timestamp: Timestamp,
+        tx: &Transaction<JellyfishTransactionContent, Verified>,
+        content: &JellyfishTransactionContent,
+        events: &mut Vec<StateEvent>,
+    ) -> Result<(Outcome, TransactionIdentifier), StateError> {
+        for rule in &self.rules {
+            rule.check(content).map_err(StateError::Content)?;
+        }
+        if content.ttl_nanos().is_some_and(|ttl| ttl < 0) {
+            return Err(StateError::Malformed);
+        }
+
+        let event = RecordEvent {
+            method: content.method(),
+            account: tx.account().clone(),
+            record: content.record().map(str::to_owned),
+            ttl_nanos: None,
+            height,
+            timestamp,
+        };
+
+        match content.method() {
+            Method::Insert => {
+                let record = content.record().ok_or(StateError::Malformed)?;
+                let id = TransactionIdentifier::new(height, *tx.sign());
+                if self.records.contains_key(&id) {
+                    return Err(StateError::Duplicate);
+                }
+
+                if let Some(Ok(alias)) = AliasRecord::parse(record) {
+                    self.claim_alias(alias, &id, tx.account(), None)?;
+                }
+
+                let entry = RecordEntry {
+                    owner: tx.account().clone(),
+                    history: vec![RecordEvent {
+                        ttl_nanos: content.ttl_nanos(),
+                        ..event
+                    }],
+                };
+                self.records.insert(id.clone(), entry);
+                events.push(StateEvent::RecordInserted {
+                    id: id.clone(),
+                    account: tx.account().clone(),
+                    text: record.to_owned(),
+                    height,
+                });
+                Ok((Outcome::Applied, id))
+            }
+            Method::Modify => {
+                let record = content.record().ok_or(StateError::Malformed)?;
+                let target = content.target_transaction().ok_or(StateError::Malformed)?;
+                let entry = self.check_target(target, tx.account())?;
+                let is_unchanged = entry.record() == Some(record)
+                    && content.ttl_nanos().is_none()
+                    && entry.latest().ttl_nanos.is_none();
+                if is_unchanged {
+                    return Ok((Outcome::NoOp, target.clone()));
+                }
+                let old_text = entry.record().unwrap_or_default().to_owned();
+                let old_alias = entry.alias();
+                // Modify refreshes TTL, keeping the previous one unless a new one is given.
+                let ttl_nanos = content.ttl_nanos().or(entry.latest().ttl_nanos);
+
+                if let Some(old_alias) = &old_alias {
+                    self.aliases.remove(old_alias.name());
+                }
+                if let Some(Ok(alias)) = AliasRecord::parse(record) {
+                    let released = old_alias.as_ref().map(AliasRecord::name);
+                    self.claim_alias(alias, target, tx.account(), released)?;
+                }
+
+                self.push_event(target, RecordEvent { ttl_nanos, ..event });
+                events.push(StateEvent::RecordModified {
+                    id: target.clone(),
+                    account: tx.account().clone(),
+                    old_text,
+                    new_text: record.to_owned(),
+                    height,
+                });
+                Ok((Outcome::Applied, target.clone()))
+            }
+            Method::Remove => {
+                let target = content.target_transaction().ok_or(StateError::Malformed)?;
+                let entry = self.check_target(target, tx.account())?;
+                let text = entry.record().unwrap_or_default().to_owned();
+                if let Some(alias) = entry.alias() {
+                    self.aliases.remove(alias.name());
+                }
+
+                self.push_event(target, event);
+                events.push(StateEvent::RecordRemoved {
+                    id: target.clone(),
+                    account: tx.account().clone(),
+                    text,
+                    height,
+                });
+                Ok((Outcome::Applied, target.clone()))
+            }
+            Method::Append => {
+                let fragment = content.record().ok_or(StateError::Malformed)?;
+                let target = content.target_transaction().ok_or(StateError::Malformed)?;
+                let entry = self.check_target(target, tx.account())?;
+                // Appended alias record would be malformed.
+                if entry.alias().is_some() {
+                    return Err(StateError::Content(ContentError::new(
+                        "Alias record cannot be appended.",
+                    )));
+                }
+                let old_text = entry.record().unwrap_or_default();
+                let len = old_text.len() + self.append_separator.len() + fragment.len();
+                if len > self.max_record_len {
+                    return Err(StateError::RecordTooLarge {
+                        len,
+                        limit: self.max_record_len,
+                    });
+                }
+                let new_text = [old_text, fragment].join(&self.append_separator);
+                let old_text = old_text.to_owned();
+                // Append refreshes TTL in the same way as Modify.
+                let ttl_nanos = content.ttl_nanos().or(entry.latest().ttl_nanos);
+
+                let event = RecordEvent {
+                    record: Some(new_text.clone()),
+                    ttl_nanos,
+                    ..event
+                };
+                self.push_event(target, event);
+                events.push(StateEvent::RecordModified {
+                    id: target.clone(),
+                    account: tx.account().clone(),
+                    old_text,
+                    new_text,
+                    height,
+                });
+                Ok((Outcome::Applied, target.clone()))
+            }
+            Method::Batch => Err(StateError::Malformed),
+        }
+    }
+
+    fn live_entry(&self, id: &TransactionIdentifier) -> Option<&RecordEntry> {
+        self.records
+            .get(id)
+            .filter(|entry| entry.is_live(self.timestamp))
+    }
+
+    /// Check that the target record exists and the account may change it.
+    fn check_target(
+        &self,
+        target: &TransactionIdentifier,
+        account: &Account,
+    ) -> Result<&RecordEntry, StateError> {
+        let entry = self.records.get(target).ok_or(StateError::TargetUnknown)?;
+
+        if let Some(tombstone) = entry.tombstone() {
+            return Err(StateError::TargetRemoved {
+                removed_at_height: tombstone.height,
+            });
+        }
+        if entry.is_expired(self.timestamp) {
+            return Err(StateError::TargetExpired);
+        }
+        if &entry.authority() != account {
+            return Err(StateError::NotOwner);
+        }
+        Ok(entry)
+    }
+
+    /// Register the alias offered by the account.
+    ///
+    /// A new name must be claimed by the aliased account itself,
+    /// while a name released by the same transaction may be transferred to another account.
+    ///
+    /// A name whose record has expired may be claimed again.
+    fn claim_alias(
+        &mut self,
+        alias: AliasRecord,
+        id: &TransactionIdentifier,
+        offerer: &Account,
+        released: Option<&str>,
+    ) -> Result<(), StateError> {
+        let is_transfer = released == Some(alias.name());
+        if !is_transfer && alias.account() != offerer {
+            return Err(StateError::NotOwner);
+        }
+        if self.resolve_alias(alias.name()).is_some() {
+            return Err(StateError::AliasTaken {
+                name: alias.name().to_owned(),
+            });
+        }
+
+        self.aliases.insert(
+            alias.name().to_owned(),
+            (id.clone(), alias.account().clone()),
+        );
+        Ok(())
+    }
+
+    fn push_event(&mut self, target: &TransactionIdentifier, event: RecordEvent) {
+        if let Some(entry) = self.records.get_mut(target) {
+            entry.history.push(event);
+        }
+    }
+}
+
+impl Default for LedgerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outcome of a transaction applied to ledger state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The transaction changed the state.
+    Applied,
+    /// The transaction was applied, but a later transaction in the same block overwrote its result.
+    Superseded { by_index: usize },
+    /// The transaction changed nothing, e.g., `Modify` with the same record.
+    NoOp,
+}
+
+/// Result of applying a transaction in a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// Index of the transaction in the block.
+    pub tx_index: usize,
+    /// Digest of the transaction's sign, which is also the merkle leaf of the transaction.
+    #[serde(with = "hex")]
+    pub sign_digest: Digest,
+    pub outcome: Outcome,
+    /// Identifier of the record which the transaction created or changed.
+    pub resulting_record_id: Option<TransactionIdentifier>,
+}
+
+/// Mark `Modify` transactions overwritten by a later `Modify` or `Remove` on the same record as superseded.
+///
+/// `Append` builds on the result of an earlier `Modify`, so it does not supersede the `Modify`.
+fn mark_superseded(
+    receipts: &mut [Receipt],
+    transactions: &[Transaction<JellyfishTransactionContent, Verified>],
+) {
+    for i in 0..receipts.len() {
+        if receipts[i].outcome != Outcome::Applied
+            || transactions[i].content().method() != Method::Modify
+        {
+            continue;
+        }
+        let later = (i + 1..receipts.len()).find(|&j| {
+            receipts[j].outcome == Outcome::Applied
+                && matches!(
+                    transactions[j].content().method(),
+                    Method::Modify | Method::Remove
+                )
+                && receipts[j].resulting_record_id == receipts[i].resulting_record_id
+        });
+        if let Some(by_index) = later {
+            receipts[i].outcome = Outcome::Superseded { by_index };
+        }
+    }
+}
+
+/// A change of a record, emitted when a block is applied to or disconnected from [`LedgerState`].
+///
+/// `height` is the height of the block being applied or disconnected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StateEvent {
+    RecordInserted {
+        id: TransactionIdentifier,
+        account: Account,
+        text: String,
+        height: u64,
+    },
+    RecordModified {
+        id: TransactionIdentifier,
+        account: Account,
+        old_text: String,
+        new_text: String,
+        height: u64,
+    },
+    RecordRemoved {
+        id: TransactionIdentifier,
+        account: Account,
+        text: String,
+        height: u64,
+    },
+    /// TTL of the record elapsed. `account` is the offerer of the latest change of the record.
+    RecordExpired {
+        id: TransactionIdentifier,
+        account: Account,
+        text: String,
+        height: u64,
+    },
+}
+
+impl StateEvent {
+    /// Returns identifier of the changed record.
+    pub fn id(&self) -> &TransactionIdentifier {
+        use StateEvent::*;
+
+        match self {
+            RecordInserted { id, .. }
+            | RecordModified { id, .. }
+            | RecordRemoved { id, .. }
+            | RecordExpired { id, .. } => id,
+        }
+    }
+
+    /// Returns the event which reverts this event.
+    /// Expiry is reverted by insertion, since the record comes back.
+    fn inverse(&self, height: u64) -> StateEvent {
+        use StateEvent::*;
+
+        match self.clone() {
+            RecordInserted {
+                id, account, text, ..
+            } => RecordRemoved {
+                id,
+                account,
+                text,
+                height,
+            },
+            RecordModified {
+                id,
+                account,
+                old_text,
+                new_text,
+                ..
+            } => RecordModified {
+                id,
+                account,
+                old_text: new_text,
+                new_text: old_text,
+                height,
+            },
+            RecordRemoved {
+                id, account, text, ..
+            }
+            | RecordExpired {
+                id, account, text, ..
+            } => RecordInserted {
+                id,
+                account,
+                text,
+                height,
+            },
+        }
+    }
+}
+
+/// Receives [`StateEvent`]s of a [`LedgerState`], e.g., to mirror records into another database.
+pub trait StateObserver: fmt::Debug + Send + Sync {
+    /// Called for each event in order, after the state has been changed.
+    fn on_event(&self, event: &StateEvent);
+}
+
+/// Leaf of state merkle tree, which is digest of the record's identifier and content.
+fn state_leaf(id: &TransactionIdentifier, record: &str) -> Sha256Digest {
+    let mut bytes = id.build_byte_order();
+    bytes.extend(record.as_bytes());
+    Digest::create(&bytes).to_bytes()
+}
+
+/// Proof that a record has the content in a ledger state, without revealing other records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProof {
+    id: TransactionIdentifier,
+    record: String,
+    /// Index of the record in the state merkle tree.
+    leaf_index: usize,
+    /// Number of records in the state.
+    leaf_count: usize,
+    /// Sibling hashes required to calculate state root.
+    #[serde(with = "hex")]
+    hashes: Vec<u8>,
+}
+
+impl StateProof {
+    pub fn id(&self) -> &TransactionIdentifier {
+        &self.id
+    }
+
+    pub fn record(&self) -> &str {
+        &self.record
+    }
+
+    /// Returns whether the record has the content in a state which has the given root.
+    pub fn verify(&self, state_root: &Digest) -> bool {
+        let proof = match rs_merkle::MerkleProof::<DefaultMerkle>::from_bytes(&self.hashes) {
+            Ok(proof) => proof,
+            Err(_) => return false,
+        };
+        let leaf = state_leaf(&self.id, &self.record);
+
+        proof.verify(
+            state_root.to_bytes(),
+            &[self.leaf_index],
+            &[leaf],
+            self.leaf_count,
+        )
+    }
+
+    /// Returns whether the record has the content after applying the block of the header.
+    /// `false` if the header does not commit to state.
+    pub fn verify_header(&self, header: &Header) -> bool {
+        header
+            .state_root()
+            .is_some_and(|state_root| self.verify(state_root))
+    }
+}
+
+/// Returns identifiers of records which transactions in the block insert or target.
+pub(crate) fn touched_records<VT, VB>(
+    block: &Block<JellyfishTransactionContent, VT, VB>,
+) -> impl Iterator<Item = TransactionIdentifier> + '_ {
+    let height = block.header().height();
+    block.transactions().iter().flat_map(move |tx| {
+        tx.content()
+            .operations()
+            .iter()
+            .map(move |op| match op.target_transaction() {
+                Some(target) if op.method() != Method::Insert => target.clone(),
+                _ => TransactionIdentifier::new(height, *tx.sign()),
+            })
+    })
+}
+
+/// Targets removed so far in a block, which is used to find intra-block conflicts.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RemovedTargets(HashMap<TransactionIdentifier, usize>);
+
+impl RemovedTargets {
+    /// Check that no operation of the content at the index targets a removed record,
+    /// then record targets of its `Remove` operations.
+    ///
+    /// # Returns
+    /// `Err(index)` of the earlier `Remove` transaction on conflict. The content is not recorded in that case.
+    pub fn check_and_record(
+        &mut self,
+        index: usize,
+        content: &JellyfishTransactionContent,
+    ) -> Result<(), usize> {
+        let operations = content.operations();
+        let conflict = operations
+            .iter()
+            .filter_map(JellyfishTransactionContent::target_transaction)
+            .find_map(|target| self.0.get(target));
+        if let Some(&first_index) = conflict {
+            return Err(first_index);
+        }
+
+        for operation in operations {
+            if let (Method::Remove, Some(target)) =
+                (operation.method(), operation.target_transaction())
+            {
+                self.0.insert(target.clone(), index);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Changes of records between two ledger states, in ascending order of record identifier.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub inserted: Vec<RecordChange>,
+    pub modified: Vec<RecordChange>,
+    pub removed: Vec<RecordChange>,
+}
+
+impl StateDiff {
+    /// Returns whether no record changed.
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// A change of a record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordChange {
+    /// Identifier of the record, that is, of its `Insert` transaction.
+    pub id: TransactionIdentifier,
+    /// Offerer of the latest transaction applied to the record.
+    pub account: Account,
+    /// Record before the change. `None` on insertion.
+    pub old_record: Option<String>,
+    /// Record after the change. `None` on removal.
+    pub new_record: Option<String>,
+}
+
+/// Serializable records of [`LedgerState`].
+///
+/// Content rules are not included, so they must be given again after restoring.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    records: Vec<(TransactionIdentifier, RecordEntry)>,
+    timestamp: Option<Timestamp>,
+}
+
+/// Ledger state as of a height of the chain, which is only for reading.
+#[derive(Debug, Clone)]
+pub struct LedgerStateView {
+    height: u64,
+    state: LedgerState,
+}
+
+impl LedgerStateView {
+    pub(crate) fn new(height: u64, state: LedgerState) -> Self {
+        Self { height, state }
+    }
+
+    /// Returns height of the latest block applied to the state.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn state(&self) -> &LedgerState {
+        &self.state
+    }
+}
+
+/// A transaction cannot be applied to the ledger state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// Transaction content violates a [`ContentRule`].
+    Content(ContentError),
+    /// Record or target transaction is missing for the method, or TTL is negative.
+    Malformed,
+    /// The same `Insert` transaction has already been applied.
+    Duplicate,
+    /// Target record has never existed, or its tombstone has been purged.
+    TargetUnknown,
+    /// Target record has been removed.
+    TargetRemoved { removed_at_height: u64 },
+    /// Target record has expired.
+    TargetExpired,
+    /// Transaction's offerer is not allowed to change the record.
+    NotOwner,
+    /// Alias name has already been claimed.
+    AliasTaken { name: String },
+    /// Record grown by `Append` exceeds the maximum length in bytes.
+    RecordTooLarge { len: usize, limit: usize },
+    /// A transaction targets a record removed by an earlier transaction in the same block.
+    /// Indices are positions of the two transactions in the block.
+    IntraBlockConflict {
+        first_index: usize,
+        second_index: usize,
+    },
+    /// Height range is reversed or out of the chain.
+    InvalidRange { from_height: u64, to_height: u64 },
+    /// The chain does not have a block at the height.
+    UnknownHeight(u64),
+    /// The block is not the latest block applied to the state, or its records have been purged.
+    Disconnect { height: u64 },
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use StateError::*;
+
+        match self {
+            Content(e) => e.fmt(f),
+            Malformed => write!(
+                f,
+                "Transaction lacks record or target, or has negative TTL."
+            ),
+            Duplicate => write!(f, "Transaction has already been applied."),
+            TargetUnknown => write!(f, "Target record does not exist."),
+            TargetRemoved { removed_at_height } => {
+                write!(f, "Target record was removed at {}.", removed_at_height)
+            }
+            TargetExpired => write!(f, "Target record has expired."),
+            NotOwner => write!(f, "Transaction offerer is not the owner of the record."),
+            AliasTaken { name } => write!(f, "Alias '{}' has already been claimed.", name),
+            RecordTooLarge { len, limit } => write!(
+                f,
+                "Appended record has {} bytes, which exceeds limit {}.",
+                len, limit
+            ),
+            IntraBlockConflict {
+                first_index,
+                second_index,
+            } => write!(
+                f,
+                "Transaction {} targets a record removed by transaction {} in the same block.",
+                second_index, first_index
+            ),
+            InvalidRange {
+                from_height,
+                to_height,
+            } => write!(
+                f,
+                "Height range {}..={} is invalid.",
+                from_height, to_height
+            ),
+            UnknownHeight(height) => write!(f, "Block at height {} does not exist.", height),
+            Disconnect { height } => write!(
+                f,
+                "Block at height {} cannot be disconnected from the state.",
+                height
+            ),
+        }
+    }
+}
+
+impl Error for StateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StateError::Content(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::fixtures::{self, FixtureChain};
+    use crate::params::ChainParams;
+    use crate::{Difficulty, SecretAccount, Yet};
+
+    type Tx = Transaction<JellyfishTransactionContent, Verified>;
+
+    fn create_block(
+        height: u64,
+        transactions: Vec<Tx>,
+    ) -> Block<JellyfishTransactionContent, Verified, Yet> {
+        Block::create(
+            height,
+            Timestamp::now(),
+            Digest::ZERO,
+            Difficulty::new(1),
+            transactions,
+        )
+        .unwrap()
+    }
+
+    fn alias_record(name: &str, account: &SecretAccount) -> String {
+        AliasRecord::new(name, account.to_public())
+            .unwrap()
+            .to_record()
+    }
+
+    /// Apply a block containing the single transaction, and returns identifier of the transaction.
+    fn apply(
+        state: &mut LedgerState,
+        height: u64,
+        tx: Tx,
+    ) -> Result<TransactionIdentifier, StateError> {
+        let id = TransactionIdentifier::new(height, *tx.sign());
+        state.apply_block(&create_block(height, vec![tx]))?;
+        Ok(id)
+    }
+
+    #[test]
+    fn insert_modify_remove() {
+        let fixture = FixtureChain::new(ChainParams::regtest())
+            .with_block(|b| b.insert("hello"))
+            .with_block(|b| {
+                let target = b.tx(0);
+                b.modify(target, "world")
+            });
+        let id = fixture.ids()[0].clone();
+        assert_eq!(fixture.state().record(&id), Some("world"));
+        assert_eq!(fixture.state().records().count(), 1);
+
+        let fixture = fixture.with_block(|b| b.remove(b.tx(0)));
+        let state = fixture.state();
+        assert_eq!(state.record(&id), None);
+        assert_eq!(state.records().count(), 0);
+
+        let history = state.history(&id);
+        let methods = history.iter().map(RecordEvent::method).collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            vec![Method::Insert, Method::Modify, Method::Remove]
+        );
+        assert_eq!(history[1].record(), Some("world"));
+        assert_eq!(history[2].height(), 2);
+
+        let alice = &fixture.accounts()[0];
+        let modify = fixtures::tx(alice, JellyfishTransactionContent::modify("again", id));
+        let result = apply(&mut state.clone(), 3, modify);
+        assert_eq!(
+            result,
+            Err(StateError::TargetRemoved {
+                removed_at_height: 2
+            })
+        );
+    }
+
+    #[test]
+    fn modify_fail_not_owner() {
+        let alice = fixtures::account(0);
+        let mallory = fixtures::account(1);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        let modify = fixtures::tx(
+            &mallory,
+            JellyfishTransactionContent::modify("evil", id.clone()),
+        );
+        assert_eq!(apply(&mut state, 2, modify), Err(StateError::NotOwner));
+
+        let remove = fixtures::tx(&mallory, JellyfishTransactionContent::remove(id.clone()));
+        assert_eq!(apply(&mut state, 2, remove), Err(StateError::NotOwner));
+
+        assert_eq!(state.record(&id), Some("hello"));
+    }
+
+    #[test]
+    fn apply_block_atomic() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let valid = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let unknown = TransactionIdentifier::new(0, *valid.sign());
+        let invalid = fixtures::tx(&alice, JellyfishTransactionContent::remove(unknown));
+        let result = state.apply_block(&create_block(1, vec![valid, invalid]));
+
+        assert_eq!(result, Err(StateError::TargetUnknown));
+        assert_eq!(state.records().count(), 0);
+    }
+
+    #[test]
+    fn claim_alias() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let claim = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert(alias_record("alice", &alice)),
+        );
+        apply(&mut state, 1, claim).unwrap();
+
+        assert_eq!(state.resolve_alias("alice"), Some(&alice.to_public()));
+        assert_eq!(state.aliases_of(&alice.to_public()), vec!["alice"]);
+        assert_eq!(state.resolve_alias("bob"), None);
+    }
+
+    #[test]
+    fn claim_alias_fail_taken() {
+        let alice = fixtures::account(0);
+        let bob = fixtures::account(1);
+        let mut state = LedgerState::new();
+
+        let claim = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert(alias_record("alice", &alice)),
+        );
+        apply(&mut state, 1, claim).unwrap();
+
+        let claim = fixtures::tx(
+            &bob,
+            JellyfishTransactionContent::insert(alias_record("alice", &bob)),
+        );
+        let result = apply(&mut state, 2, claim);
+
+        assert_eq!(
+            result,
+            Err(StateError::AliasTaken {
+                name: "alice".to_owned()
+            })
+        );
+        assert_eq!(state.resolve_alias("alice"), Some(&alice.to_public()));
+        assert!(state.aliases_of(&bob.to_public()).is_empty());
+    }
+
+    #[test]
+    fn claim_alias_fail_for_other_account() {
+        let alice = fixtures::account(0);
+        let mallory = fixtures::account(1);
+        let mut state = LedgerState::new();
+
+        let claim = fixtures::tx(
+            &mallory,
+            JellyfishTransactionContent::insert(alias_record("alice", &alice)),
+        );
+
+        assert_eq!(apply(&mut state, 1, claim), Err(StateError::NotOwner));
+        assert_eq!(state.resolve_alias("alice"), None);
+    }
+
+    #[test]
+    fn claim_alias_fail_invalid_name() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let record = alias_record("alice", &alice).replace("alias:alice", "alias:Al ice");
+        let claim = fixtures::tx(&alice, JellyfishTransactionContent::insert(record));
+
+        assert!(matches!(
+            apply(&mut state, 1, claim),
+            Err(StateError::Content(_))
+        ));
+    }
+
+    #[test]
+    fn transfer_alias() {
+        let alice = fixtures::account(0);
+        let bob = fixtures::account(1);
+        let mut state = LedgerState::new();
+
+        let claim = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert(alias_record("alice", &alice)),
+        );
+        let id = apply(&mut state, 1, claim).unwrap();
+
+        let transfer = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify(alias_record("alice", &bob), id.clone()),
+        );
+        apply(&mut state, 2, transfer).unwrap();
+
+        assert_eq!(state.resolve_alias("alice"), Some(&bob.to_public()));
+        assert!(state.aliases_of(&alice.to_public()).is_empty());
+        assert_eq!(state.aliases_of(&bob.to_public()), vec!["alice"]);
+
+        // The former owner no longer controls the alias.
+        let take_back = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify(alias_record("alice", &alice), id.clone()),
+        );
+        assert_eq!(apply(&mut state, 3, take_back), Err(StateError::NotOwner));
+
+        // The new owner does.
+        let remove = fixtures::tx(&bob, JellyfishTransactionContent::remove(id));
+        apply(&mut state, 3, remove).unwrap();
+        assert_eq!(state.resolve_alias("alice"), None);
+    }
+
+    #[test]
+    fn hijack_alias() {
+        let alice = fixtures::account(0);
+        let mallory = fixtures::account(1);
+        let mut state = LedgerState::new();
+
+        let claim = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert(alias_record("alice", &alice)),
+        );
+        let id = apply(&mut state, 1, claim).unwrap();
+
+        let hijack = fixtures::tx(
+            &mallory,
+            JellyfishTransactionContent::modify(alias_record("alice", &mallory), id.clone()),
+        );
+        assert_eq!(apply(&mut state, 2, hijack), Err(StateError::NotOwner));
+
+        let remove = fixtures::tx(&mallory, JellyfishTransactionContent::remove(id));
+        assert_eq!(apply(&mut state, 2, remove), Err(StateError::NotOwner));
+
+        assert_eq!(state.resolve_alias("alice"), Some(&alice.to_public()));
+    }
+
+    /// Apply a block with the given timestamp.
+    /// If no transaction is given, an unrelated record is inserted since a block cannot be empty.
+    fn apply_at(
+        state: &mut LedgerState,
+        height: u64,
+        nanos: i64,
+        mut transactions: Vec<Tx>,
+    ) -> Result<Vec<Receipt>, StateError> {
+        if transactions.is_empty() {
+            let filler = JellyfishTransactionContent::insert("filler");
+            transactions.push(fixtures::tx(&fixtures::account(0), filler));
+        }
+        let block = Block::create(
+            height,
+            Timestamp::from_nanos(nanos),
+            Digest::ZERO,
+            Difficulty::new(1),
+            transactions,
+        )
+        .unwrap();
+        state.apply_block(&block)
+    }
+
+    #[test]
+    fn ttl_expire() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert("session").with_ttl(100),
+        );
+        let id = TransactionIdentifier::new(1, *insert.sign());
+        apply_at(&mut state, 1, 1_000, vec![insert]).unwrap();
+        assert_eq!(state.record(&id), Some("session"));
+
+        // Exactly at the expiry, the record is still visible.
+        apply_at(&mut state, 2, 1_100, vec![]).unwrap();
+        assert_eq!(state.record(&id), Some("session"));
+
+        apply_at(&mut state, 3, 1_101, vec![]).unwrap();
+        assert_eq!(state.record(&id), None);
+        assert!(state.records().all(|(_, record)| record == "filler"));
+        // History is kept.
+        assert_eq!(state.history(&id).len(), 1);
+    }
+
+    #[test]
+    fn ttl_zero() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert("flash").with_ttl(0),
+        );
+        let id = TransactionIdentifier::new(1, *insert.sign());
+        apply_at(&mut state, 1, 1_000, vec![insert]).unwrap();
+        assert_eq!(state.record(&id), Some("flash"));
+
+        apply_at(&mut state, 2, 1_001, vec![]).unwrap();
+        assert_eq!(state.record(&id), None);
+    }
+
+    #[test]
+    fn ttl_refresh_by_modify() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert("v1").with_ttl(100),
+        );
+        let id = TransactionIdentifier::new(1, *insert.sign());
+        apply_at(&mut state, 1, 1_000, vec![insert]).unwrap();
+
+        // Refresh without changing TTL.
+        let modify = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify("v2", id.clone()),
+        );
+        apply_at(&mut state, 2, 1_050, vec![modify]).unwrap();
+
+        apply_at(&mut state, 3, 1_150, vec![]).unwrap();
+        assert_eq!(state.record(&id), Some("v2"));
+
+        apply_at(&mut state, 4, 1_151, vec![]).unwrap();
+        assert_eq!(state.record(&id), None);
+    }
+
+    #[test]
+    fn modify_fail_expired() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert("v1").with_ttl(100),
+        );
+        let id = TransactionIdentifier::new(1, *insert.sign());
+        apply_at(&mut state, 1, 1_000, vec![insert]).unwrap();
+
+        // The record expires at the modifying block itself.
+        let modify = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify("v2", id.clone()),
+        );
+        let result = apply_at(&mut state, 2, 1_101, vec![modify]);
+
+        assert_eq!(result, Err(StateError::TargetExpired));
+        assert_eq!(state.history(&id).len(), 1);
+    }
+
+    #[test]
+    fn insert_fail_negative_ttl() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert("v1").with_ttl(-1),
+        );
+
+        assert_eq!(
+            apply_at(&mut state, 1, 1_000, vec![insert]),
+            Err(StateError::Malformed)
+        );
+    }
+
+    #[test]
+    fn alias_expire() {
+        let alice = fixtures::account(0);
+        let bob = fixtures::account(1);
+        let mut state = LedgerState::new();
+
+        let claim = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert(alias_record("shared", &alice)).with_ttl(100),
+        );
+        apply_at(&mut state, 1, 1_000, vec![claim]).unwrap();
+        assert_eq!(state.resolve_alias("shared"), Some(&alice.to_public()));
+
+        apply_at(&mut state, 2, 2_000, vec![]).unwrap();
+        assert_eq!(state.resolve_alias("shared"), None);
+        assert!(state.aliases_of(&alice.to_public()).is_empty());
+
+        // An expired name can be claimed again.
+        let claim = fixtures::tx(
+            &bob,
+            JellyfishTransactionContent::insert(alias_record("shared", &bob)),
+        );
+        apply_at(&mut state, 3, 3_000, vec![claim]).unwrap();
+        assert_eq!(state.resolve_alias("shared"), Some(&bob.to_public()));
+    }
+
+    /// Insert records at height 1, then remove each of them at height 2, 3, ...
+    /// Returns identifiers of removed records.
+    fn insert_and_remove(
+        state: &mut LedgerState,
+        account: &SecretAccount,
+        count: u64,
+    ) -> Vec<TransactionIdentifier> {
+        let inserts = (0..count)
+            .map(|i| fixtures::tx(account, JellyfishTransactionContent::insert(i.to_string())))
+            .collect::<Vec<_>>();
+        let ids = inserts
+            .iter()
+            .map(|tx| TransactionIdentifier::new(1, *tx.sign()))
+            .collect::<Vec<_>>();
+        state.apply_block(&create_block(1, inserts)).unwrap();
+
+        for (height, id) in (2..).zip(&ids) {
+            let remove = fixtures::tx(account, JellyfishTransactionContent::remove(id.clone()));
+            apply(state, height, remove).unwrap();
+        }
+        ids
+    }
+
+    #[test]
+    fn tombstone() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let ids = insert_and_remove(&mut state, &alice, 1);
+
+        let tombstone = state.tombstone(&ids[0]).unwrap();
+        assert_eq!(tombstone.account, alice.to_public());
+        assert_eq!(tombstone.height, 2);
+        assert_eq!(tombstone.timestamp, state.history(&ids[0])[1].timestamp());
+    }
+
+    #[test]
+    fn append() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new().with_append_separator(", ");
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("first"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        for (height, fragment) in [(2, "second"), (3, "third")] {
+            let content = JellyfishTransactionContent::append(fragment, id.clone());
+            apply(&mut state, height, fixtures::tx(&alice, content)).unwrap();
+        }
+
+        assert_eq!(state.record(&id), Some("first, second, third"));
+        let methods = state
+            .history(&id)
+            .iter()
+            .map(RecordEvent::method)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            vec![Method::Insert, Method::Append, Method::Append]
+        );
+        assert_eq!(state.history(&id)[1].record(), Some("first, second"));
+    }
+
+    #[test]
+    fn append_fail_removed() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let ids = insert_and_remove(&mut state, &alice, 1);
+
+        let append = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::append("x", ids[0].clone()),
+        );
+        let result = apply(&mut state, 3, append);
+
+        assert_eq!(
+            result,
+            Err(StateError::TargetRemoved {
+                removed_at_height: 2
+            })
+        );
+    }
+
+    #[test]
+    fn append_fail_too_large() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new().with_max_record_len(10);
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        // "hello" + "\n" + "abcd" is exactly 10 bytes.
+        let append = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::append("abcd", id.clone()),
+        );
+        apply(&mut state, 2, append).unwrap();
+
+        let append = fixtures::tx(&alice, JellyfishTransactionContent::append("", id.clone()));
+        let result = apply(&mut state, 3, append);
+
+        assert_eq!(
+            result,
+            Err(StateError::RecordTooLarge { len: 11, limit: 10 })
+        );
+        assert_eq!(state.record(&id), Some("hello\nabcd"));
+    }
+
+    #[test]
+    fn append_fail_not_owner() {
+        let alice = fixtures::account(0);
+        let bob = fixtures::account(1);
+        let mut state = LedgerState::new();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        let append = fixtures::tx(&bob, JellyfishTransactionContent::append("x", id));
+        let result = apply(&mut state, 2, append);
+
+        assert_eq!(result, Err(StateError::NotOwner));
+    }
+
+    #[test]
+    fn batch() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        let batch = JellyfishTransactionContent::batch(vec![
+            JellyfishTransactionContent::insert("new"),
+            JellyfishTransactionContent::modify("world", id.clone()),
+            JellyfishTransactionContent::append("!", id.clone()),
+        ])
+        .unwrap();
+        let batch = fixtures::tx(&alice, batch);
+        let new_id = TransactionIdentifier::new(2, *batch.sign());
+        let receipts = state.apply_block(&create_block(2, vec![batch])).unwrap();
+
+        assert_eq!(state.record(&id), Some("world\n!"));
+        assert_eq!(state.record(&new_id), Some("new"));
+        assert_eq!(state.history(&id).len(), 3);
+        assert_eq!(receipts[0].outcome, Outcome::Applied);
+        assert_eq!(receipts[0].resulting_record_id, None);
+    }
+
+    #[test]
+    fn batch_fail_atomic() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let id = apply(&mut state, 1, insert).unwrap();
+        let unknown = TransactionIdentifier::new(5, id.sign);
+
+        let batch = JellyfishTransactionContent::batch(vec![
+            JellyfishTransactionContent::insert("new"),
+            JellyfishTransactionContent::modify("world", id.clone()),
+            JellyfishTransactionContent::modify("x", unknown),
+        ])
+        .unwrap();
+        let result = apply(&mut state, 2, fixtures::tx(&alice, batch));
+
+        assert_eq!(result, Err(StateError::TargetUnknown));
+        assert_eq!(state.record(&id), Some("hello"));
+        assert_eq!(state.records().count(), 1);
+    }
+
+    #[test]
+    fn batch_fail_limit() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new().with_max_batch_operations(2);
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        let modifies = ["a", "b", "c"]
+            .iter()
+            .map(|&record| JellyfishTransactionContent::modify(record, id.clone()))
+            .collect::<Vec<_>>();
+        let batch = JellyfishTransactionContent::batch(modifies).unwrap();
+        let result = apply(&mut state, 2, fixtures::tx(&alice, batch));
+
+        assert!(matches!(result, Err(StateError::Content(_))));
+        assert_eq!(state.record(&id), Some("hello"));
+    }
+
+    #[test]
+    fn batch_fail_nested() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let inner =
+            JellyfishTransactionContent::batch(vec![JellyfishTransactionContent::insert("a")])
+                .unwrap();
+        // Bypass the check of batch() by deserialization.
+        let json = format!(
+            r#"{{"method":"Batch","operations":[{}]}}"#,
+            serde_json::to_string(&inner).unwrap()
+        );
+        let nested = serde_json::from_str::<JellyfishTransactionContent>(&json).unwrap();
+
+        let result = apply(&mut state, 1, fixtures::tx(&alice, nested));
+
+        assert!(matches!(result, Err(StateError::Content(_))));
+    }
+
+    #[test]
+    fn modify_fail_unknown() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        let unknown = TransactionIdentifier::new(5, *insert.sign());
+
+        let modify = fixtures::tx(&alice, JellyfishTransactionContent::modify("x", unknown));
+        let result = apply(&mut state, 1, modify);
+
+        assert_eq!(result, Err(StateError::TargetUnknown));
+    }
+
+    #[test]
+    fn purge_tombstones_older_than() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        // Removed at height 2, 3, 4
+        let ids = insert_and_remove(&mut state, &alice, 3);
+
+        state.purge_tombstones_older_than(3);
+
+        assert!(state.tombstone(&ids[0]).is_none());
+        assert!(state.history(&ids[0]).is_empty());
+        assert!(state.tombstone(&ids[1]).is_some());
+        assert_eq!(state.history(&ids[1]).len(), 2);
+        assert!(state.tombstone(&ids[2]).is_some());
+
+        let modify = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify("x", ids[0].clone()),
+        );
+        assert_eq!(apply(&mut state, 5, modify), Err(StateError::TargetUnknown));
+        let modify = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify("x", ids[1].clone()),
+        );
+        assert_eq!(
+            apply(&mut state, 5, modify),
+            Err(StateError::TargetRemoved {
+                removed_at_height: 3
+            })
+        );
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let removed = insert_and_remove(&mut state, &alice, 3);
+
+        let claim = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::insert(alias_record("alice", &alice)),
+        );
+        let live = apply(&mut state, 5, claim).unwrap();
+
+        state.purge_tombstones_older_than(3);
+        let ser = serde_json::to_string(&state.snapshot()).unwrap();
+        let de = serde_json::from_str::<StateSnapshot>(&ser).unwrap();
+        let restored = LedgerState::from_snapshot(de);
+
+        assert_eq!(restored.record(&live), state.record(&live));
+        assert_eq!(restored.resolve_alias("alice"), Some(&alice.to_public()));
+        for id in &removed {
+            assert_eq!(restored.tombstone(id), state.tombstone(id));
+            assert_eq!(restored.history(id), state.history(id));
+        }
+        assert!(restored.tombstone(&removed[0]).is_none());
+        assert!(restored.tombstone(&removed[1]).is_some());
+    }
+
+    fn outcomes(receipts: Vec<Receipt>) -> Vec<Outcome> {
+        receipts
+            .into_iter()
+            .map(|receipt| receipt.outcome)
+            .collect()
+    }
+
+    /// Insert a record at height 1, then apply a block at height 2 containing the given contents.
+    fn apply_same_block<F>(
+        contents: F,
+    ) -> (
+        LedgerState,
+        TransactionIdentifier,
+        Result<Vec<Receipt>, StateError>,
+    )
+    where
+        F: Fn(&TransactionIdentifier) -> Vec<JellyfishTransactionContent>,
+    {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("v1"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        let transactions = contents(&id)
+            .into_iter()
+            .map(|content| fixtures::tx(&alice, content))
+            .collect();
+        let result = state.apply_block(&create_block(2, transactions));
+        (state, id, result)
+    }
+
+    #[test]
+    fn same_block_modify_modify() {
+        let (state, id, result) = apply_same_block(|id| {
+            vec![
+                JellyfishTransactionContent::modify("v2", id.clone()),
+                JellyfishTransactionContent::modify("v3", id.clone()),
+            ]
+        });
+
+        // The later one wins.
+        let outcomes = outcomes(result.unwrap());
+        assert_eq!(
+            outcomes,
+            vec![Outcome::Superseded { by_index: 1 }, Outcome::Applied]
+        );
+        assert_eq!(state.record(&id), Some("v3"));
+        assert_eq!(state.history(&id).len(), 3);
+    }
+
+    #[test]
+    fn same_block_modify_remove() {
+        let (state, id, result) = apply_same_block(|id| {
+            vec![
+                JellyfishTransactionContent::modify("v2", id.clone()),
+                JellyfishTransactionContent::remove(id.clone()),
+            ]
+        });
+
+        let outcomes = outcomes(result.unwrap());
+        assert_eq!(
+            outcomes,
+            vec![Outcome::Superseded { by_index: 1 }, Outcome::Applied]
+        );
+        assert_eq!(state.record(&id), None);
+        assert_eq!(state.tombstone(&id).unwrap().height, 2);
+    }
+
+    #[test]
+    fn same_block_remove_modify() {
+        let (state, id, result) = apply_same_block(|id| {
+            vec![
+                JellyfishTransactionContent::insert("unrelated"),
+                JellyfishTransactionContent::remove(id.clone()),
+                JellyfishTransactionContent::modify("v2", id.clone()),
+            ]
+        });
+
+        assert_eq!(
+            result,
+            Err(StateError::IntraBlockConflict {
+                first_index: 1,
+                second_index: 2
+            })
+        );
+        // The whole block is rejected.
+        assert_eq!(state.record(&id), Some("v1"));
+        assert_eq!(state.records().count(), 1);
+    }
+
+    #[test]
+    fn same_block_remove_remove() {
+        let (state, id, result) = apply_same_block(|id| {
+            vec![
+                JellyfishTransactionContent::remove(id.clone()),
+                JellyfishTransactionContent::remove(id.clone()),
+            ]
+        });
+
+        assert_eq!(
+            result,
+            Err(StateError::IntraBlockConflict {
+                first_index: 0,
+                second_index: 1
+            })
+        );
+        assert_eq!(state.record(&id), Some("v1"));
+    }
+
+    #[test]
+    fn same_block_insert_modify() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("v1"));
+        let id = TransactionIdentifier::new(1, *insert.sign());
+        let modify = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify("v2", id.clone()),
+        );
+        state
+            .apply_block(&create_block(1, vec![insert, modify]))
+            .unwrap();
+
+        assert_eq!(state.record(&id), Some("v2"));
+    }
+
+    #[test]
+    fn diff() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = |record| fixtures::tx(&alice, JellyfishTransactionContent::insert(record));
+        let kept = apply(&mut state, 1, insert("kept")).unwrap();
+        let modified = apply(&mut state, 2, insert("v1")).unwrap();
+        let removed = apply(&mut state, 3, insert("bye")).unwrap();
+        let old = state.clone();
+
+        let modify = fixtures::tx(
+            &alice,
+            JellyfishTransactionContent::modify("v2", modified.clone()),
+        );
+        let remove = fixtures::tx(&alice, JellyfishTransactionContent::remove(removed.clone()));
+        let new = insert("new");
+        let inserted = TransactionIdentifier::new(4, *new.sign());
+        state
+            .apply_block(&create_block(4, vec![modify, remove, new]))
+            .unwrap();
+
+        let diff = old.diff(&state);
+
+        assert_eq!(diff.inserted.len(), 1);
+        assert_eq!(diff.inserted[0].id, inserted);
+        assert_eq!(diff.inserted[0].new_record.as_deref(), Some("new"));
+        assert_eq!(diff.inserted[0].account, alice.to_public());
+
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].id, modified);
+        assert_eq!(diff.modified[0].old_record.as_deref(), Some("v1"));
+        assert_eq!(diff.modified[0].new_record.as_deref(), Some("v2"));
+
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, removed);
+        assert_eq!(diff.removed[0].old_record.as_deref(), Some("bye"));
+        assert_eq!(diff.removed[0].new_record, None);
+
+        assert!(old.record(&kept).is_some());
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn diff_serialize() {
+        let alice = fixtures::account(0);
+        let old = LedgerState::new();
+        let mut state = old.clone();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("hello"));
+        apply(&mut state, 1, insert).unwrap();
+
+        let diff = old.diff(&state);
+        let ser = serde_json::to_string(&diff).unwrap();
+        let de = serde_json::from_str::<StateDiff>(&ser).unwrap();
+
+        assert_eq!(de, diff);
+        assert!(ser.contains(r#""new_record":"hello""#));
+    }
+
+    #[test]
+    fn receipts() {
+        let (_, id, result) = apply_same_block(|id| {
+            vec![
+                JellyfishTransactionContent::insert("new"),
+                JellyfishTransactionContent::modify("v1", id.clone()),
+                JellyfishTransactionContent::modify("v2", id.clone()),
+                JellyfishTransactionContent::modify("v3", id.clone()),
+            ]
+        });
+        let receipts = result.unwrap();
+
+        assert_eq!(
+            outcomes(receipts.clone()),
+            vec![
+                Outcome::Applied,
+                // Same record as the current one
+                Outcome::NoOp,
+                Outcome::Superseded { by_index: 3 },
+                Outcome::Applied,
+            ]
+        );
+        assert_eq!(
+            receipts.iter().map(|r| r.tx_index).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert_ne!(receipts[0].resulting_record_id, Some(id.clone()));
+        assert!(receipts[1..]
+            .iter()
+            .all(|r| r.resulting_record_id == Some(id.clone())));
+    }
+
+    #[test]
+    fn receipts_reproducible() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+        let insert = fixtures::tx(&alice, JellyfishTransactionContent::insert("v1"));
+        let id = apply(&mut state, 1, insert).unwrap();
+
+        let transactions = vec![
+            fixtures::tx(
+                &alice,
+                JellyfishTransactionContent::modify("v2", id.clone()),
+            ),
+            fixtures::tx(&alice, JellyfishTransactionContent::remove(id)),
+        ];
+        let block = create_block(2, transactions);
+
+        let receipts1 = state.clone().apply_block(&block).unwrap();
+        let receipts2 = state.clone().apply_block(&block).unwrap();
+        assert_eq!(receipts1, receipts2);
+        assert_eq!(
+            receipts1[1].sign_digest,
+            Digest::create(block.transactions()[1].sign())
+        );
+
+        let ser = serde_json::to_string(&receipts1).unwrap();
+        let de = serde_json::from_str::<Vec<Receipt>>(&ser).unwrap();
+        assert_eq!(de, receipts1);
+    }
+
+    #[derive(Debug, Default)]
+    struct Collector(Mutex<Vec<StateEvent>>);
+
+    impl StateObserver for Collector {
+        fn on_event(&self, event: &StateEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn events() {
+        let alice = fixtures::account(0);
+        let collector = Arc::new(Collector::default());
+        let mut state = LedgerState::new().with_observer(collector.clone());
+
+        let parent = create_block(
+            1,
+            vec![
+                fixtures::tx(&alice, JellyfishTransactionContent::insert("a")),
+                fixtures::tx(&alice, JellyfishTransactionContent::insert("b")),
+            ],
+        );
+        let a = TransactionIdentifier::new(1, *parent.transactions()[0].sign());
+        let b = TransactionIdentifier::new(1, *parent.transactions()[1].sign());
+        state.apply_block(&parent).unwrap();
+        let before = state.snapshot();
+        collector.0.lock().unwrap().clear();
+
+        let block = create_block(
+            2,
+            vec![
+                fixtures::tx(&alice, JellyfishTransactionContent::insert("c")),
+                fixtures::tx(&alice, JellyfishTransactionContent::modify("a2", a.clone())),
+                fixtures::tx(&alice, JellyfishTransactionContent::modify("a2", a.clone())),
+                fixtures::tx(&alice, JellyfishTransactionContent::remove(b.clone())),
+            ],
+        );
+        let c = TransactionIdentifier::new(2, *block.transactions()[0].sign());
+        let account = alice.to_public();
+
+        let (_, events) = state.apply_block_with_events(&block).unwrap();
+        let expected = vec![
+            StateEvent::RecordInserted {
+                id: c.clone(),
+                account: account.clone(),
+                text: "c".to_owned(),
+                height: 2,
+            },
+            // The second Modify is NoOp, which emits no event.
+            StateEvent::RecordModified {
+                id: a.clone(),
+                account: account.clone(),
+                old_text: "a".to_owned(),
+                new_text: "a2".to_owned(),
+                height: 2,
+            },
+            StateEvent::RecordRemoved {
+                id: b.clone(),
+                account: account.clone(),
+                text: "b".to_owned(),
+                height: 2,
+            },
+        ];
+        assert_eq!(events, expected);
+        assert_eq!(*collector.0.lock().unwrap(), expected);
+
+        let ser = serde_json::to_string(&events).unwrap();
+        let de = serde_json::from_str::<Vec<StateEvent>>(&ser).unwrap();
+        assert_eq!(de, events);
+
+        // Reorg
+        collector.0.lock().unwrap().clear();
+        let events = state.disconnect_block(&block, parent.header()).unwrap();
+        let expected = vec![
+            StateEvent::RecordInserted {
+                id: b,
+                account: account.clone(),
+                text: "b".to_owned(),
+                height: 2,
+            },
+            StateEvent::RecordModified {
+                id: a,
+                account: account.clone(),
+                old_text: "a2".to_owned(),
+                new_text: "a".to_owned(),
+                height: 2,
+            },
+            StateEvent::RecordRemoved {
+                id: c,
+                account,
+                text: "c".to_owned(),
+                height: 2,
+            },
+        ];
+        assert_eq!(events, expected);
+        assert_eq!(*collector.0.lock().unwrap(), expected);
+        assert_eq!(state.snapshot(), before);
+    }
+
+    #[test]
+    fn events_expire() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let insert = JellyfishTransactionContent::insert("hello").with_ttl(10);
+        let tx = fixtures::tx(&alice, insert);
+        let id = TransactionIdentifier::new(1, *tx.sign());
+        apply_at(&mut state, 1, 100, vec![tx]).unwrap();
+
+        let block = Block::create(
+            2,
+            Timestamp::from_nanos(200),
+            Digest::ZERO,
+            Difficulty::new(1),
+            vec![fixtures::tx(
+                &alice,
+                JellyfishTransactionContent::insert("x"),
+            )],
+        )
+        .unwrap();
+        let (_, events) = state.apply_block_with_events(&block).unwrap();
+        let expired = StateEvent::RecordExpired {
+            id: id.clone(),
+            account: alice.to_public(),
+            text: "hello".to_owned(),
+            height: 2,
+        };
+        assert_eq!(events[0], expired);
+        assert_eq!(events.len(), 2);
+
+        let parent = Block::create(
+            1,
+            Timestamp::from_nanos(100),
+            Digest::ZERO,
+            Difficulty::new(1),
+            vec![fixtures::tx(
+                &alice,
+                JellyfishTransactionContent::insert("y"),
+            )],
+        )
+        .unwrap();
+        let events = state.disconnect_block(&block, parent.header()).unwrap();
+        assert_eq!(
+            events[1],
+            StateEvent::RecordInserted {
+                id: id.clone(),
+                account: alice.to_public(),
+                text: "hello".to_owned(),
+                height: 2,
+            }
+        );
+        assert_eq!(state.record(&id), Some("hello"));
+    }
+
+    #[test]
+    fn disconnect_fail_not_latest() {
+        let alice = fixtures::account(0);
+        let mut state = LedgerState::new();
+
+        let block1 = create_block(
+            1,
+            vec![fixtures::tx(
+                &alice,
+                JellyfishTransactionContent::insert("a"),
+            )],
+        );
+        let block2 = create_block(
+            2,
+            vec![fixtures::tx(
+                &alice,
+                JellyfishTransactionContent::insert("b"),
+            )],
+        );
+        state.apply_block(&block1).unwrap();
+        state.apply_block(&block2).unwrap();
+        let before = state.snapshot();
+
+        assert_eq!(
+            state.disconnect_block(&block1, block1.header()),
+            Err(StateError::Disconnect { height: 1 })
+        );
+        assert_eq!(state.snapshot(), before);
+    }
+}
@@ -0,0 +1,283 @@
+//! Health of a node, which operators see to decide whether the node is synced and whether the chain is stalled.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::chain::Chain;
+use crate::digest::Digest;
+use crate::mempool::Mempool;
+use crate::orphan::OrphanPool;
+use crate::params::ChainParams;
+use crate::sync::{PeerId, SyncMachine, SyncState};
+use crate::Timestamp;
+
+/// Default number of target block intervals without a new block, after which the chain is regarded as stale.
+pub const DEFAULT_STALE_INTERVALS: u32 = 10;
+
+/// Marker of [`ChainHealth`] which has not received any block.
+const NEVER: i64 = i64::MIN;
+
+/// Remembers when the latest block was connected.
+///
+/// Recording is a single atomic store, so the block verification path can record without locking,
+/// while status readers on other threads share the same instance.
+#[derive(Debug)]
+pub struct ChainHealth {
+    last_block_nanos: AtomicI64,
+    stale_after: Duration,
+}
+
+impl ChainHealth {
+    /// The chain is regarded as stale when no block has been connected for longer than `stale_after`.
+    pub fn new(stale_after: Duration) -> Self {
+        Self {
+            last_block_nanos: AtomicI64::new(NEVER),
+            stale_after,
+        }
+    }
+
+    /// The chain is regarded as stale after [`DEFAULT_STALE_INTERVALS`] target block intervals of the parameters.
+    pub fn for_params(params: &ChainParams) -> Self {
+        Self::new(params.target_block_interval * DEFAULT_STALE_INTERVALS)
+    }
+
+    /// Record that a block was connected at the given time.
+    pub fn record_block(&self, received_at: Timestamp) {
+        self.last_block_nanos
+            .fetch_max(received_at.nanos(), Ordering::Relaxed);
+    }
+
+    /// Returns when the latest block was connected. `None` if no block has been recorded.
+    pub fn last_block_received(&self) -> Option<Timestamp> {
+        match self.last_block_nanos.load(Ordering::Relaxed) {
+            NEVER => None,
+            nanos => Some(Timestamp::from_nanos(nanos)),
+        }
+    }
+
+    pub fn stale_after(&self) -> Duration {
+        self.stale_after
+    }
+}
+
+/// Best-known tip of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PeerStatus {
+    pub peer: PeerId,
+    /// Height of the latest header which the peer has shown.
+    pub best_height: u64,
+    /// Number of blocks which the local chain is behind the peer. Zero if the local chain is not behind.
+    pub blocks_behind: u64,
+}
+
+/// Snapshot of a node's sync progress and chain health, e.g., for a status endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeStatus {
+    pub tip_height: u64,
+    pub tip_digest: Digest,
+    /// Time since the tip's timestamp. Zero if the tip is timestamped in the future.
+    pub tip_age: Duration,
+    pub peers: Vec<PeerStatus>,
+    pub sync: SyncState,
+    pub mempool_len: usize,
+    pub orphan_len: usize,
+    pub last_block_received: Option<Timestamp>,
+    /// Whether no block has been connected for longer than [`ChainHealth::stale_after()`].
+    /// Until a block is recorded, this is measured from the tip's timestamp.
+    pub stale: bool,
+}
+
+impl NodeStatus {
+    /// Assemble the status from live components at the given time.
+    pub fn collect<T>(
+        chain: &Chain<T>,
+        sync: &SyncMachine<T>,
+        mempool: &Mempool<T>,
+        orphans: &OrphanPool,
+        health: &ChainHealth,
+        now: Timestamp,
+    ) -> Self {
+        let tip = chain.tip().header();
+        let tip_height = tip.height();
+        let peers = sync
+            .peer_tips()
+            .map(|(peer, best_height)| PeerStatus {
+                peer,
+                best_height,
+                blocks_behind: best_height.saturating_sub(tip_height),
+            })
+            .collect();
+        let last_block_received = health.last_block_received();
+        let since = last_block_received.unwrap_or(tip.timestamp());
+        let stale = now
+            .duration_since(since)
+            .is_some_and(|elapsed| elapsed > health.stale_after());
+
+        Self {
+            tip_height,
+            tip_digest: *tip.digest(),
+            tip_age: now.duration_since(tip.timestamp()).unwrap_or_default(),
+            peers,
+            sync: sync.state(),
+            mempool_len: mempool.len(),
+            orphan_len: orphans.len(),
+            last_block_received,
+            stale,
+        }
+    }
+
+    /// Returns whether the node is idle and no peer has shown a block beyond the local tip.
+    pub fn is_synced(&self) -> bool {
+        self.sync == SyncState::Idle && self.peers.iter().all(|peer| peer.blocks_behind == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::block::Block;
+    use crate::chain::tests_stab::*;
+    use crate::clock::ManualClock;
+    use crate::protocol::Message;
+    use crate::sync::Action;
+    use crate::{SecretAccount, Verified, Yet};
+
+    const PEER: PeerId = PeerId(1);
+
+    fn verified(block: &Block<Stab, Verified, Yet>) -> Block<Stab, Verified, Verified> {
+        unverified(block)
+            .verify_block(|_| true)
+            .unwrap()
+            .verify_transactions()
+            .unwrap()
+    }
+
+    /// Respond to the request from the chain of the peer.
+    fn respond(chain: &Chain<Stab>, request: Message<Stab>) -> Message<Stab> {
+        match request {
+            Message::GetHeaders { from_height, .. } => Message::Headers(
+                (from_height..)
+                    .map_while(|height| chain.block(height))
+                    .map(|block| block.header().clone())
+                    .collect(),
+            ),
+            Message::GetBlocks { heights } => Message::Blocks(
+                heights
+                    .iter()
+                    .filter_map(|&height| chain.block(height))
+                    .map(|block| block.clone().into_unverified())
+                    .collect(),
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the only message sent by the actions.
+    fn sent(actions: &[Action<Stab>]) -> Message<Stab> {
+        match actions {
+            [Action::Send(PEER, message)] => message.clone(),
+            _ => panic!("Unexpected actions: {:?}", actions),
+        }
+    }
+
+    #[test]
+    fn sync_status_transitions() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let genesis = mine(0, Digest::ZERO, &account);
+        let mut remote = Chain::new(verified(&genesis));
+        for height in 1..=3 {
+            let block = mine(height, *remote.tip().header().digest(), &account);
+            remote.append(unverified(&block)).unwrap();
+        }
+
+        let now = Timestamp::now();
+        let clock = Arc::new(ManualClock::new(now));
+        let mut chain = Chain::new(verified(&genesis));
+        let mut sync = SyncMachine::new(chain.tip().header().clone(), now);
+        let mempool = Mempool::with_clock(clock.clone());
+        let orphans = OrphanPool::with_clock(clock);
+        let health = ChainHealth::new(Duration::from_secs(60));
+        let status = |chain: &Chain<Stab>, sync: &SyncMachine<Stab>, now| {
+            NodeStatus::collect(chain, sync, &mempool, &orphans, &health, now)
+        };
+
+        let status_before = status(&chain, &sync, now);
+        assert_eq!(status_before.sync, SyncState::Idle);
+        assert!(status_before.peers.is_empty());
+        assert_eq!(status_before.last_block_received, None);
+
+        let request = sent(&sync.add_peer(PEER));
+        assert_eq!(
+            status(&chain, &sync, now).sync,
+            SyncState::SyncingHeaders { from_height: 1 }
+        );
+
+        let request = sent(&sync.on_message(PEER, respond(&remote, request)));
+        let syncing = status(&chain, &sync, now);
+        assert_eq!(
+            syncing.sync,
+            SyncState::SyncingBodies {
+                from_height: 1,
+                to_height: 3
+            }
+        );
+        assert_eq!(
+            syncing.peers,
+            vec![PeerStatus {
+                peer: PEER,
+                best_height: 3,
+                blocks_behind: 3
+            }]
+        );
+        assert!(!syncing.is_synced());
+
+        for action in sync.on_message(PEER, respond(&remote, request)) {
+            match action {
+                Action::AppendBlock(block) => {
+                    chain.append(block).unwrap();
+                    health.record_block(now);
+                }
+                action => panic!("Unexpected action: {:?}", action),
+            }
+        }
+
+        let synced = status(&chain, &sync, now);
+        assert_eq!(synced.sync, SyncState::Idle);
+        assert_eq!(synced.tip_height, 3);
+        assert_eq!(&synced.tip_digest, remote.tip().header().digest());
+        assert_eq!(synced.peers[0].blocks_behind, 0);
+        assert_eq!(synced.last_block_received, Some(now));
+        assert!(synced.is_synced());
+        assert!(!synced.stale);
+
+        // No block for a while.
+        let later = status(&chain, &sync, now + Duration::from_secs(61));
+        assert!(later.stale);
+        assert!(later.is_synced());
+    }
+
+    #[test]
+    fn stale_from_tip_until_block_recorded() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let genesis = mine(0, Digest::ZERO, &account);
+        let chain = Chain::new(verified(&genesis));
+        let tip_timestamp = chain.tip().header().timestamp();
+        let sync = SyncMachine::new(chain.tip().header().clone(), tip_timestamp);
+        let mempool = Mempool::new();
+        let orphans = OrphanPool::new();
+        let health = ChainHealth::new(Duration::from_secs(60));
+
+        let now = tip_timestamp + Duration::from_secs(120);
+        let status = NodeStatus::collect(&chain, &sync, &mempool, &orphans, &health, now);
+        assert!(status.stale);
+        assert_eq!(status.tip_age, Duration::from_secs(120));
+
+        health.record_block(tip_timestamp + Duration::from_secs(100));
+        let status = NodeStatus::collect(&chain, &sync, &mempool, &orphans, &health, now);
+        assert!(!status.stale);
+    }
+}
@@ -0,0 +1,433 @@
+//! Pull-based streams of blocks connected to and disconnected from a [`Chain`](crate::chain::Chain).
+//!
+//! Each [`BlockReceiver`] has its own bounded buffer.
+//! When the buffer is full, the oldest event is discarded and the receiver is told how many events it missed,
+//! so that a slow consumer never makes the chain buffer without bound.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+#[cfg(feature = "tokio")]
+use std::task::Waker;
+
+use crate::block::{Block, Header};
+use crate::Verified;
+
+/// Default number of events buffered for each receiver.
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 64;
+
+/// Change of the chain delivered to subscribers, in the order the chain changed.
+#[derive(Debug)]
+pub enum ChainEvent<T> {
+    /// Block has been appended to the tip.
+    Connected(Arc<Block<T, Verified, Verified>>),
+    /// Block with the header has been removed from the tip.
+    Disconnected(Header),
+    /// Receiver did not keep up, so the given number of events before the next one were discarded.
+    Lagged { missed: u64 },
+}
+
+impl<T> Clone for ChainEvent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            ChainEvent::Connected(block) => ChainEvent::Connected(block.clone()),
+            ChainEvent::Disconnected(header) => ChainEvent::Disconnected(header.clone()),
+            ChainEvent::Lagged { missed } => ChainEvent::Lagged { missed: *missed },
+        }
+    }
+}
+
+/// Receiving side of [`Chain::subscribe_blocks()`](crate::chain::Chain::subscribe_blocks).
+///
+/// Dropping the receiver unsubscribes it.
+pub struct BlockReceiver<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> BlockReceiver<T> {
+    /// Wait for the next event.
+    ///
+    /// # Returns
+    /// `None` if the chain has been dropped and all buffered events have been received.
+    pub fn recv(&self) -> Option<ChainEvent<T>> {
+        let mut queue = self.channel.lock();
+        loop {
+            if let Some(event) = queue.pop() {
+                return Some(event);
+            }
+            if queue.closed {
+                return None;
+            }
+            queue = self
+                .channel
+                .ready
+                .wait(queue)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Returns the next event without waiting.
+    pub fn try_recv(&self) -> Result<ChainEvent<T>, TryRecvError> {
+        let mut queue = self.channel.lock();
+        match queue.pop() {
+            Some(event) => Ok(event),
+            None if queue.closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns number of buffered events.
+    pub fn len(&self) -> usize {
+        self.channel.lock().events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Debug for BlockReceiver<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockReceiver")
+            .field("capacity", &self.channel.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T> futures_core::Stream for BlockReceiver<T> {
+    type Item = ChainEvent<T>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::task::Poll;
+
+        let mut queue = self.channel.lock();
+        match queue.pop() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// [`BlockReceiver::try_recv()`] returned no event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No event is buffered now.
+    Empty,
+    /// No event is buffered, and the chain has been dropped.
+    Closed,
+}
+
+impl Display for TryRecvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "No chain event is buffered."),
+            TryRecvError::Closed => write!(f, "Chain has been dropped."),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+/// Sending side held by a chain, which delivers each event to all live receivers.
+///
+/// A clone of a chain does not share its subscribers, so a clone starts without subscribers.
+pub(crate) struct BlockBroadcast<T> {
+    channels: Mutex<Vec<Weak<Channel<T>>>>,
+}
+
+impl<T> BlockBroadcast<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            channels: Mutex::new(vec![]),
+        }
+    }
+
+    /// At least one event is buffered even if `capacity` is zero.
+    pub(crate) fn subscribe(&self, capacity: usize) -> BlockReceiver<T> {
+        let capacity = capacity.max(1);
+        let channel = Arc::new(Channel {
+            queue: Mutex::new(Queue {
+                events: VecDeque::with_capacity(capacity),
+                missed: 0,
+                closed: false,
+                #[cfg(feature = "tokio")]
+                waker: None,
+            }),
+            ready: Condvar::new(),
+            capacity,
+        });
+        self.lock().push(Arc::downgrade(&channel));
+        BlockReceiver { channel }
+    }
+
+    /// Returns whether any receiver is alive, so that callers can skip building events nobody receives.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        let mut channels = self.lock();
+        channels.retain(|channel| channel.strong_count() > 0);
+        !channels.is_empty()
+    }
+
+    /// Deliver the event to live receivers, and forget dropped ones.
+    pub(crate) fn send(&self, event: ChainEvent<T>) {
+        self.lock().retain(|channel| match channel.upgrade() {
+            Some(channel) => {
+                channel.push(event.clone());
+                true
+            }
+            None => false,
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Weak<Channel<T>>>> {
+        self.channels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Clone for BlockBroadcast<T> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for BlockBroadcast<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockBroadcast")
+            .field("subscribers", &self.lock().len())
+            .finish()
+    }
+}
+
+impl<T> Drop for BlockBroadcast<T> {
+    fn drop(&mut self) {
+        for channel in self.lock().iter().filter_map(Weak::upgrade) {
+            let mut queue = channel.lock();
+            queue.closed = true;
+            queue.wake();
+            channel.ready.notify_all();
+        }
+    }
+}
+
+/// Buffer shared by a receiver and the chain.
+struct Channel<T> {
+    queue: Mutex<Queue<T>>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+impl<T> Channel<T> {
+    fn push(&self, event: ChainEvent<T>) {
+        let mut queue = self.lock();
+        if queue.events.len() >= self.capacity {
+            queue.events.pop_front();
+            queue.missed += 1;
+        }
+        queue.events.push_back(event);
+        queue.wake();
+        self.ready.notify_all();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queue<T>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct Queue<T> {
+    events: VecDeque<ChainEvent<T>>,
+    /// Number of events discarded since the last received one.
+    missed: u64,
+    closed: bool,
+    #[cfg(feature = "tokio")]
+    waker: Option<Waker>,
+}
+
+impl<T> Queue<T> {
+    /// Returns [`ChainEvent::Lagged`] before the retained events, if any event has been discarded.
+    fn pop(&mut self) -> Option<ChainEvent<T>> {
+        if self.missed > 0 {
+            let missed = std::mem::take(&mut self.missed);
+            return Some(ChainEvent::Lagged { missed });
+        }
+        self.events.pop_front()
+    }
+
+    fn wake(&mut self) {
+        #[cfg(feature = "tokio")]
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::tests_stab::*;
+    use crate::chain::Chain;
+    use crate::digest::Digest;
+    use crate::fixtures;
+    use crate::SecretAccount;
+
+    fn genesis(account: &SecretAccount) -> Chain<Stab> {
+        let block = unverified(&mine(0, Digest::ZERO, account));
+        Chain::new(
+            block
+                .verify_block(|_| true)
+                .unwrap()
+                .verify_transactions()
+                .unwrap(),
+        )
+    }
+
+    /// Mine a block on the tip, and returns its digest.
+    fn append(chain: &mut Chain<Stab>, account: &SecretAccount) -> Digest {
+        let tip = chain.tip().header();
+        let block = mine(tip.height() + 1, *tip.digest(), account);
+        chain.append(unverified(&block)).unwrap();
+        *chain.tip().header().digest()
+    }
+
+    fn connected(event: ChainEvent<Stab>) -> Digest {
+        match event {
+            ChainEvent::Connected(block) => *block.header().digest(),
+            e => panic!("Unexpected event {:?}", e),
+        }
+    }
+
+    fn disconnected(event: ChainEvent<Stab>) -> Digest {
+        match event {
+            ChainEvent::Disconnected(header) => *header.digest(),
+            e => panic!("Unexpected event {:?}", e),
+        }
+    }
+
+    #[test]
+    fn ordered_across_reorg() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+        let receiver = chain.subscribe_blocks();
+
+        let old = [append(&mut chain, &account), append(&mut chain, &account)];
+        assert!(chain.disconnect_tip().is_some());
+        assert!(chain.disconnect_tip().is_some());
+        assert!(chain.disconnect_tip().is_none());
+        let new = [
+            append(&mut chain, &account),
+            append(&mut chain, &account),
+            append(&mut chain, &account),
+        ];
+        assert_eq!(chain.tip().header().height(), 3);
+
+        assert_eq!(receiver.len(), 7);
+        assert_eq!(connected(receiver.recv().unwrap()), old[0]);
+        assert_eq!(connected(receiver.recv().unwrap()), old[1]);
+        assert_eq!(disconnected(receiver.recv().unwrap()), old[1]);
+        assert_eq!(disconnected(receiver.recv().unwrap()), old[0]);
+        for digest in new {
+            assert_eq!(connected(receiver.try_recv().unwrap()), digest);
+        }
+        assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Empty);
+
+        drop(chain);
+        assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Closed);
+        assert!(receiver.recv().is_none());
+    }
+
+    #[test]
+    fn lagged() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+        let receiver = chain.subscribe_blocks_with_capacity(2);
+
+        let digests = (0..5)
+            .map(|_| append(&mut chain, &account))
+            .collect::<Vec<_>>();
+
+        // The oldest events are discarded.
+        assert_eq!(receiver.len(), 2);
+        assert!(matches!(
+            receiver.recv(),
+            Some(ChainEvent::Lagged { missed: 3 })
+        ));
+        assert_eq!(connected(receiver.recv().unwrap()), digests[3]);
+        assert_eq!(connected(receiver.recv().unwrap()), digests[4]);
+
+        // Lag is reported once.
+        let digest = append(&mut chain, &account);
+        assert_eq!(connected(receiver.recv().unwrap()), digest);
+    }
+
+    #[test]
+    fn independent_subscribers() {
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+        let first = chain.subscribe_blocks();
+        let digest = append(&mut chain, &account);
+
+        let second = chain.subscribe_blocks();
+        let dropped = chain.subscribe_blocks();
+        drop(dropped);
+        let next = append(&mut chain, &account);
+
+        assert_eq!(connected(first.recv().unwrap()), digest);
+        assert_eq!(connected(first.recv().unwrap()), next);
+        assert_eq!(connected(second.recv().unwrap()), next);
+        assert!(second.is_empty());
+
+        // Receivers of the original chain are not notified by its clone.
+        let mut clone = chain.clone();
+        append(&mut clone, &account);
+        assert!(first.is_empty());
+        assert!(clone.subscribe_blocks().is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn stream() {
+        use std::pin::Pin;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::task::{Context, Poll, Wake};
+
+        use futures_core::Stream;
+
+        #[derive(Default)]
+        struct CountWaker(AtomicUsize);
+
+        impl Wake for CountWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let account = fixtures::account(0);
+        let mut chain = genesis(&account);
+        let mut receiver = chain.subscribe_blocks();
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut receiver).poll_next(&mut cx).is_pending());
+        let digest = append(&mut chain, &account);
+        assert_eq!(count.0.load(Ordering::SeqCst), 1);
+        match Pin::new(&mut receiver).poll_next(&mut cx) {
+            Poll::Ready(Some(event)) => assert_eq!(connected(event), digest),
+            _ => panic!("Event must be ready."),
+        }
+
+        drop(chain);
+        assert!(matches!(
+            Pin::new(&mut receiver).poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+    }
+}
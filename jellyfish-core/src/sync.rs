@@ -0,0 +1,992 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{cumulative_work, Block, Header};
+use crate::chain::{check_linkage, Chain, DEFAULT_FORK_DEPTH};
+use crate::params::ChainParams;
+use crate::protocol::Message;
+use crate::window::HeaderWindow;
+use crate::{Timestamp, Yet};
+
+/// Maximum number of headers requested at once.
+pub const MAX_HEADERS_PER_REQUEST: usize = 500;
+
+/// Default time a peer may take to respond to a request.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default interval between requests asking peers for new headers.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default interval between ticks requested by [`SyncMachine`].
+pub const DEFAULT_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Identifier of a peer, assigned by the driver of [`SyncMachine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PeerId(pub u64);
+
+/// What [`SyncMachine`] is waiting for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncState {
+    /// No request is in flight.
+    Idle,
+    /// Headers beginning with the height are requested.
+    SyncingHeaders { from_height: u64 },
+    /// Blocks of verified headers from `from_height` to `to_height` inclusive are requested.
+    SyncingBodies { from_height: u64, to_height: u64 },
+}
+
+/// What the driver of [`SyncMachine`] should do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action<T> {
+    /// Send the message to the peer.
+    Send(PeerId, Message<T>),
+    /// Verify the block and append it to the chain.
+    AppendBlock(Block<T, Yet, Yet>),
+    /// Close the connection to the peer.
+    Disconnect(PeerId, Reason),
+    /// Call [`SyncMachine::on_tick()`] after the duration.
+    ScheduleTick(Duration),
+}
+
+/// Why a peer is disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The peer did not respond to a request in time.
+    Stalled,
+    /// Headers have invalid Proof-of-Work or a wrong difficulty, violate chain parameters, or more headers than requested were given.
+    InvalidHeaders,
+    /// Headers do not link to each other, or do not fork from recent headers of the local chain, e.g., they belong to another chain.
+    UnlinkedHeaders,
+    /// Blocks do not match the requested headers.
+    UnexpectedBlocks,
+}
+
+/// Request waiting for a response.
+#[derive(Debug, Clone)]
+struct Request {
+    peer: PeerId,
+    kind: RequestKind,
+    deadline: Timestamp,
+}
+
+#[derive(Debug, Clone)]
+enum RequestKind {
+    Headers {
+        from_height: u64,
+        max_count: usize,
+    },
+    /// Blocks of the given headers, which have been verified.
+    Blocks {
+        headers: Vec<Header>,
+        more: bool,
+    },
+}
+
+/// Downloads blocks following the local chain's tip from peers.
+///
+/// This is a pure state machine without threads, sockets or clocks.
+/// The driver feeds received messages and the current time, then performs returned [`Action`]s.
+///
+/// Headers are downloaded first and verified by Proof-of-Work and linkage,
+/// then blocks of the headers are downloaded from the same peer.
+/// At most one request is in flight at once.
+///
+/// If headers of a peer do not follow the local tip, the machine requests headers from lower heights,
+/// doubling the distance from the tip, until they fork from a recent header of the local chain.
+/// Blocks of the fork are downloaded only if it contains more work than the local headers it replaces,
+/// and the driver switches its chain to the fork, e.g., by [`Chain::disconnect_tip()`] before appending them.
+///
+/// Requests from peers, e.g., [`Message::GetHeaders`], are not handled by the machine,
+/// so the driver responds to them from its chain.
+#[derive(Debug, Clone)]
+pub struct SyncMachine<T> {
+    /// Recent headers of the local chain in ascending order of height, including blocks handed to the driver by [`Action::AppendBlock`].
+    /// The last one is the tip. Headers deeper than both the reorganization depth and the retarget window are dropped.
+    history: Vec<Header>,
+    /// Latest time given by the driver.
+    now: Timestamp,
+    peers: BTreeSet<PeerId>,
+    /// Height of the latest header which each peer has shown.
+    peer_tips: BTreeMap<PeerId, u64>,
+    request: Option<Request>,
+    /// Peer polled most recently, used to poll peers in turn.
+    last_polled: Option<PeerId>,
+    next_poll: Timestamp,
+    request_timeout: Duration,
+    poll_interval: Duration,
+    tick_interval: Duration,
+    /// Parameters which received headers must satisfy.
+    params: Option<ChainParams>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> SyncMachine<T> {
+    /// Create a machine which syncs the chain ending with the given tip.
+    ///
+    /// The machine knows no header below the tip, so forks are never followed.
+    /// Use [`SyncMachine::from_window()`] to follow forks and check retargeted difficulty.
+    pub fn new(tip: Header, now: Timestamp) -> Self {
+        Self {
+            history: vec![tip],
+            now,
+            peers: BTreeSet::new(),
+            peer_tips: BTreeMap::new(),
+            request: None,
+            last_polled: None,
+            next_poll: now,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            tick_interval: DEFAULT_TICK_INTERVAL,
+            params: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a machine which syncs the chain ending with the latest header of the window,
+    /// e.g., [`Chain::header_window()`](crate::chain::Chain::header_window) at the tip.
+    ///
+    /// The window should be at least as long as [`ChainParams::max_reorg_depth`] and [`ChainParams::retarget_window`],
+    /// so that every fork which the chain accepts is followed and difficulty of every header is checked.
+    pub fn from_window(window: &HeaderWindow<'_>, now: Timestamp) -> Self {
+        let mut machine = Self::new(window.last().clone(), now);
+        machine.history = window.headers().iter().map(|&h| h.clone()).collect();
+        machine
+    }
+
+    /// Create a machine which syncs the chain, knowing as many recent headers as it needs to follow forks and check difficulty.
+    /// Headers are checked by the parameters of the chain, if any.
+    pub fn from_chain(chain: &Chain<T>, now: Timestamp) -> Self {
+        let tip_height = chain.tip().header().height();
+        // The tip is in the chain, so the below unwrap() always succeeds.
+        let window = chain
+            .header_window(tip_height, history_len(chain.params()))
+            .unwrap();
+        let machine = Self::from_window(&window, now);
+        match chain.params() {
+            Some(params) => machine.with_params(params.clone()),
+            None => machine,
+        }
+    }
+
+    /// Set time a peer may take to respond to a request.
+    pub fn with_request_timeout(self, request_timeout: Duration) -> Self {
+        Self {
+            request_timeout,
+            ..self
+        }
+    }
+
+    /// Set interval between requests asking peers for new headers.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Check received headers by [`ChainParams::check_header()`] at the latest time given by the driver,
+    /// and their difficulty by [`ChainParams::difficulty_after()`] over the known headers.
+    pub fn with_params(self, params: ChainParams) -> Self {
+        Self {
+            params: Some(params),
+            ..self
+        }
+    }
+
+    /// Returns the latest header of the local chain known to the machine.
+    pub fn tip(&self) -> &Header {
+        // History has at least the tip, so the below unwrap() always succeeds.
+        self.history.last().unwrap()
+    }
+
+    /// Replace the local chain's tip, e.g., when the driver failed to append a block.
+    ///
+    /// Known headers below the tip are kept if the tip follows them.
+    pub fn set_tip(&mut self, tip: Header) {
+        let len = self
+            .history
+            .iter()
+            .take_while(|h| h.height() < tip.height())
+            .count();
+        self.history.truncate(len);
+        if self
+            .history
+            .last()
+            .is_some_and(|previous| check_linkage(previous, &tip).is_err())
+        {
+            self.history.clear();
+        }
+        self.history.push(tip);
+    }
+
+    /// Returns what the machine is waiting for.
+    pub fn state(&self) -> SyncState {
+        match self.request.as_ref().map(|request| &request.kind) {
+            None => SyncState::Idle,
+            Some(RequestKind::Headers { from_height, .. }) => SyncState::SyncingHeaders {
+                from_height: *from_height,
+            },
+            Some(RequestKind::Blocks { headers, .. }) => SyncState::SyncingBodies {
+                // Requested headers are never empty.
+                from_height: headers[0].height(),
+                to_height: headers[headers.len() - 1].height(),
+            },
+        }
+    }
+
+    /// Returns height of the latest header which each connected peer has shown, in ascending order of peers.
+    ///
+    /// A peer responding no new header is regarded as being at the local tip at that time.
+    /// Peers which have not responded yet are omitted.
+    pub fn peer_tips(&self) -> impl Iterator<Item = (PeerId, u64)> + '_ {
+        self.peer_tips.iter().map(|(&peer, &height)| (peer, height))
+    }
+
+    /// Register a connected peer. If no request is in flight, headers are requested from the peer.
+    pub fn add_peer(&mut self, peer: PeerId) -> Vec<Action<T>> {
+        self.peers.insert(peer);
+        if self.request.is_none() {
+            vec![self.request_headers(peer)]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Forget a disconnected peer.
+    /// Its request in flight, if any, is abandoned, and another peer is polled on the next tick.
+    pub fn remove_peer(&mut self, peer: PeerId) {
+        self.peers.remove(&peer);
+        self.peer_tips.remove(&peer);
+        if self.request.as_ref().is_some_and(|r| r.peer == peer) {
+            self.request = None;
+            self.next_poll = self.now;
+        }
+    }
+
+    /// Handle a message received from the peer.
+    ///
+    /// Responses which were not requested from the peer, e.g., duplicate ones, are ignored.
+    pub fn on_message(&mut self, peer: PeerId, message: Message<T>) -> Vec<Action<T>> {
+        let request = match &self.request {
+            Some(request) if request.peer == peer => request.kind.clone(),
+            _ => return vec![],
+        };
+
+        match (request, message) {
+            (
+                RequestKind::Headers {
+                    from_height,
+                    max_count,
+                },
+                Message::Headers(headers),
+            ) => self.on_headers(peer, from_height, max_count, headers),
+            (RequestKind::Blocks { headers, more }, Message::Blocks(blocks)) => {
+                self.on_blocks(peer, headers, more, blocks)
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Handle passage of time.
+    ///
+    /// A peer which has not responded in time is disconnected.
+    /// While no request is in flight, peers are asked for new headers in turn every poll interval.
+    pub fn on_tick(&mut self, now: Timestamp) -> Vec<Action<T>> {
+        self.now = now;
+        let mut actions = vec![];
+
+        if let Some(request) = self.request.as_ref().filter(|r| r.deadline < now) {
+            let peer = request.peer;
+            self.remove_peer(peer);
+            actions.push(Action::Disconnect(peer, Reason::Stalled));
+        }
+
+        if self.request.is_none() && self.next_poll <= now {
+            if let Some(peer) = self.next_peer() {
+                actions.push(self.request_headers(peer));
+            }
+        }
+
+        actions.push(Action::ScheduleTick(self.tick_interval));
+        actions
+    }
+
+    fn on_headers(
+        &mut self,
+        peer: PeerId,
+        from_height: u64,
+        max_count: usize,
+        headers: Vec<Header>,
+    ) -> Vec<Action<T>> {
+        if headers.is_empty() {
+            return self.on_nothing_new(peer);
+        }
+        let is_valid = |h: &Header| {
+            h.verify_proof_of_work().is_ok()
+                && self
+                    .params
+                    .as_ref()
+                    .is_none_or(|params| params.check_header(h, self.now).is_ok())
+        };
+        if headers.len() > max_count || !headers.iter().all(is_valid) {
+            return self.disconnect(peer, Reason::InvalidHeaders);
+        }
+
+        let is_linked = headers[0].height() == from_height
+            && headers
+                .windows(2)
+                .all(|pair| check_linkage(&pair[0], &pair[1]).is_ok());
+        if !is_linked {
+            return self.disconnect(peer, Reason::UnlinkedHeaders);
+        }
+
+        let parent = from_height
+            .checked_sub(1)
+            .and_then(|height| self.known_header(height));
+        if !parent.is_some_and(|parent| headers[0].follows(parent)) {
+            // The peer's chain forks below the requested height, or is another chain.
+            return match self.lower_from_height(from_height) {
+                Some(lower) => vec![self.request_headers_from(peer, lower)],
+                None => self.disconnect(peer, Reason::UnlinkedHeaders),
+            };
+        }
+
+        // Headers which the local chain already has are skipped.
+        let known = headers
+            .iter()
+            .take_while(|h| self.known_header(h.height()) == Some(*h))
+            .count();
+        let more = headers.len() == max_count;
+        let last_height = headers[headers.len() - 1].height();
+        let headers = headers[known..].to_vec();
+        if headers.is_empty() {
+            return self.on_nothing_new(peer);
+        }
+
+        if !headers
+            .iter()
+            .enumerate()
+            .all(|(i, header)| self.has_valid_difficulty(header, &headers[..i]))
+        {
+            return self.disconnect(peer, Reason::InvalidHeaders);
+        }
+
+        self.observe_peer_tip(peer, last_height);
+        let replaced = &self.history[self.history_index(headers[0].height())..];
+        if !more && cumulative_work(&headers) <= cumulative_work(replaced) {
+            // The peer is on a fork containing less work.
+            self.request = None;
+            return vec![];
+        }
+
+        let heights = headers.iter().map(Header::height).collect();
+        self.request = Some(Request {
+            peer,
+            kind: RequestKind::Blocks { headers, more },
+            deadline: self.deadline(),
+        });
+        vec![Action::Send(peer, Message::GetBlocks { heights })]
+    }
+
+    fn on_blocks(
+        &mut self,
+        peer: PeerId,
+        headers: Vec<Header>,
+        more: bool,
+        blocks: Vec<Block<T, Yet, Yet>>,
+    ) -> Vec<Action<T>> {
+        let is_expected = blocks.len() == headers.len()
+            && blocks.iter().zip(&headers).all(|(b, h)| b.header() == h);
+        if !is_expected {
+            return self.disconnect(peer, Reason::UnexpectedBlocks);
+        }
+
+        // The above check ensures that headers are not empty, and they fork from a known header.
+        let index = self.history_index(headers[0].height());
+        self.history.truncate(index);
+        self.history.extend(headers);
+        let excess = self.history.len().saturating_sub(self.history_len());
+        self.history.drain(..excess);
+        self.request = None;
+
+        let mut actions = blocks
+            .into_iter()
+            .map(Action::AppendBlock)
+            .collect::<Vec<_>>();
+        if more {
+            // The peer may have more blocks.
+            actions.push(self.request_headers(peer));
+        }
+        actions
+    }
+
+    /// The peer has no header following the local chain.
+    fn on_nothing_new(&mut self, peer: PeerId) -> Vec<Action<T>> {
+        self.request = None;
+        self.observe_peer_tip(peer, self.tip().height());
+        vec![]
+    }
+
+    fn request_headers(&mut self, peer: PeerId) -> Action<T> {
+        let from_height = self.tip().height() + 1;
+        self.request_headers_from(peer, from_height)
+    }
+
+    fn request_headers_from(&mut self, peer: PeerId, from_height: u64) -> Action<T> {
+        let max_count = MAX_HEADERS_PER_REQUEST;
+        self.request = Some(Request {
+            peer,
+            kind: RequestKind::Headers {
+                from_height,
+                max_count,
+            },
+            deadline: self.deadline(),
+        });
+        self.last_polled = Some(peer);
+        self.next_poll = self.now + self.poll_interval;
+
+        Action::Send(
+            peer,
+            Message::GetHeaders {
+                from_height,
+                max_count,
+            },
+        )
+    }
+
+    /// Returns the height to request headers from, which is twice as far from the tip as `from_height`.
+    ///
+    /// # Returns
+    /// `None` if headers at `from_height` already follow the oldest known header.
+    fn lower_from_height(&self, from_height: u64) -> Option<u64> {
+        let floor = self.history[0].height() + 1;
+        if from_height <= floor {
+            return None;
+        }
+        let next = self.tip().height() + 1;
+        let distance = (next - from_height).max(1).saturating_mul(2);
+        Some(next.saturating_sub(distance).max(floor))
+    }
+
+    /// Returns the known header of the local chain at the height.
+    fn known_header(&self, height: u64) -> Option<&Header> {
+        let index = height.checked_sub(self.history[0].height())?;
+        self.history.get(usize::try_from(index).ok()?)
+    }
+
+    /// Returns index of the height in the history, where a header at the height replaces the known one.
+    /// The height must be above the oldest known header and at most one above the tip.
+    fn history_index(&self, height: u64) -> usize {
+        (height - self.history[0].height()) as usize
+    }
+
+    fn history_len(&self) -> usize {
+        history_len(self.params.as_ref())
+    }
+
+    /// Returns whether the header declares the difficulty demanded by the known headers and `branch` it follows.
+    /// Difficulty is not checked without parameters.
+    fn has_valid_difficulty(&self, header: &Header, branch: &[Header]) -> bool {
+        let params = match &self.params {
+            Some(params) => params,
+            None => return true,
+        };
+        let parent = |h: &Header| {
+            let height = h.height().checked_sub(1)?;
+            branch
+                .iter()
+                .rev()
+                .find(|previous| h.follows(previous))
+                .or_else(|| {
+                    self.known_header(height)
+                        .filter(|previous| h.follows(previous))
+                })
+        };
+        // Headers are linked, so a parent is always found.
+        parent(header).is_some_and(|previous| {
+            params.difficulty_after(previous, parent) == header.difficulty()
+        })
+    }
+
+    fn observe_peer_tip(&mut self, peer: PeerId, height: u64) {
+        let tip = self.peer_tips.entry(peer).or_insert(height);
+        *tip = (*tip).max(height);
+    }
+
+    fn disconnect(&mut self, peer: PeerId, reason: Reason) -> Vec<Action<T>> {
+        self.remove_peer(peer);
+        vec![Action::Disconnect(peer, reason)]
+    }
+
+    /// Returns the peer following the most recently polled one.
+    fn next_peer(&self) -> Option<PeerId> {
+        let after = self
+            .last_polled
+            .and_then(|last| self.peers.range(last..).find(|&&p| p != last));
+        after.or_else(|| self.peers.iter().next()).copied()
+    }
+
+    fn deadline(&self) -> Timestamp {
+        self.now + self.request_timeout
+    }
+}
+
+/// Returns number of headers which a machine keeps, enough to follow forks and check difficulty under the parameters.
+fn history_len(params: Option<&ChainParams>) -> usize {
+    let (depth, window) = params.map_or((DEFAULT_FORK_DEPTH, 0), |params| {
+        (params.max_reorg_depth, params.retarget_window)
+    });
+    usize::try_from(depth.max(window).saturating_add(1)).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::tests_stab::*;
+    use crate::difficulty::MIN_DIFFICULTY;
+    use crate::digest::Digest;
+    use crate::{Difficulty, SecretAccount, Transaction};
+
+    const ALICE: PeerId = PeerId(1);
+    const BOB: PeerId = PeerId(2);
+
+    fn headers(blocks: &[Block<Stab, Yet, Yet>]) -> Vec<Header> {
+        blocks.iter().map(|b| b.header().clone()).collect()
+    }
+
+    fn send_headers(actions: &[Action<Stab>]) -> Option<(PeerId, u64)> {
+        match actions {
+            [Action::Send(
+                peer,
+                Message::GetHeaders {
+                    from_height,
+                    max_count: MAX_HEADERS_PER_REQUEST,
+                },
+            )] => Some((*peer, *from_height)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn sync() {
+        let (genesis, blocks) = build_chain(3, |_, block| block);
+        let mut machine = SyncMachine::new(genesis, Timestamp::from_nanos(0));
+
+        let actions = machine.add_peer(ALICE);
+        assert_eq!(send_headers(&actions), Some((ALICE, 1)));
+
+        let actions = machine.on_message(ALICE, Message::Headers(headers(&blocks)));
+        assert_eq!(
+            actions,
+            vec![Action::Send(
+                ALICE,
+                Message::GetBlocks {
+                    heights: vec![1, 2, 3]
+                }
+            )]
+        );
+
+        let actions = machine.on_message(ALICE, Message::Blocks(blocks.clone()));
+        let expected = blocks
+            .iter()
+            .cloned()
+            .map(Action::AppendBlock)
+            .collect::<Vec<_>>();
+        assert_eq!(actions, expected);
+        assert_eq!(machine.tip(), blocks[2].header());
+
+        // Duplicate responses are ignored.
+        assert!(machine
+            .on_message(ALICE, Message::Blocks(blocks.clone()))
+            .is_empty());
+        assert!(machine
+            .on_message(ALICE, Message::Headers(headers(&blocks)))
+            .is_empty());
+    }
+
+    #[test]
+    fn ignore_unsolicited() {
+        let (genesis, blocks) = build_chain(1, |_, block| block);
+        let mut machine = SyncMachine::new(genesis, Timestamp::from_nanos(0));
+        machine.add_peer(ALICE);
+        machine.add_peer(BOB);
+
+        // Headers are requested only from Alice.
+        assert!(machine
+            .on_message(BOB, Message::Headers(headers(&blocks)))
+            .is_empty());
+        assert!(machine
+            .on_message(ALICE, Message::Blocks(blocks))
+            .is_empty());
+    }
+
+    #[test]
+    fn reject_future_headers() {
+        let (genesis, blocks) = build_chain(1, |_, block| block);
+        // Blocks are timestamped now, which is far ahead of the machine's time.
+        let mut machine =
+            SyncMachine::<Stab>::new(genesis, Timestamp::from_nanos(0)).with_params(ChainParams {
+                protocol_version: 0,
+                ..ChainParams::regtest()
+            });
+        machine.add_peer(ALICE);
+
+        let actions = machine.on_message(ALICE, Message::Headers(headers(&blocks)));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::InvalidHeaders)]
+        );
+    }
+
+    #[test]
+    fn stalled_peer() {
+        let (genesis, _) = build_chain(0, |_, block| block);
+        let mut machine = SyncMachine::<Stab>::new(genesis, Timestamp::from_nanos(0))
+            .with_request_timeout(Duration::from_nanos(100))
+            .with_poll_interval(Duration::from_nanos(10));
+        machine.add_peer(ALICE);
+        machine.add_peer(BOB);
+
+        let actions = machine.on_tick(Timestamp::from_nanos(100));
+        assert_eq!(actions, vec![Action::ScheduleTick(DEFAULT_TICK_INTERVAL)]);
+
+        let actions = machine.on_tick(Timestamp::from_nanos(101));
+        assert_eq!(actions[0], Action::Disconnect(ALICE, Reason::Stalled));
+        assert_eq!(send_headers(&actions[1..2]), Some((BOB, 1)));
+        assert_eq!(actions[2], Action::ScheduleTick(DEFAULT_TICK_INTERVAL));
+    }
+
+    #[test]
+    fn poll_peers_in_turn() {
+        let (genesis, _) = build_chain(0, |_, block| block);
+        let mut machine = SyncMachine::<Stab>::new(genesis, Timestamp::from_nanos(0))
+            .with_poll_interval(Duration::from_nanos(10));
+        machine.add_peer(ALICE);
+        machine.add_peer(BOB);
+        // Alice has nothing new.
+        assert!(machine
+            .on_message(ALICE, Message::Headers(vec![]))
+            .is_empty());
+
+        // Too early to poll.
+        let actions = machine.on_tick(Timestamp::from_nanos(9));
+        assert_eq!(actions.len(), 1);
+
+        let actions = machine.on_tick(Timestamp::from_nanos(10));
+        assert_eq!(send_headers(&actions[..1]), Some((BOB, 1)));
+        machine.on_message(BOB, Message::Headers(vec![]));
+
+        let actions = machine.on_tick(Timestamp::from_nanos(20));
+        assert_eq!(send_headers(&actions[..1]), Some((ALICE, 1)));
+    }
+
+    #[test]
+    fn headers_of_another_chain() {
+        let (genesis, _) = build_chain(0, |_, block| block);
+        let (_, other) = build_chain(2, |_, block| block);
+        let mut machine = SyncMachine::new(genesis, Timestamp::from_nanos(0));
+        machine.add_peer(ALICE);
+        machine.add_peer(BOB);
+
+        let actions = machine.on_message(ALICE, Message::Headers(headers(&other)));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::UnlinkedHeaders)]
+        );
+
+        // Bob takes over.
+        let actions = machine.on_tick(Timestamp::from_nanos(0));
+        assert_eq!(send_headers(&actions[..1]), Some((BOB, 1)));
+    }
+
+    /// Mine a block following `previous` by the given interval, declaring the difficulty.
+    fn mine_at(
+        previous: &Header,
+        interval: Duration,
+        difficulty: Difficulty,
+        account: &SecretAccount,
+    ) -> Block<Stab, Yet, Yet> {
+        let height = previous.height() + 1;
+        let timestamp = previous.timestamp() + interval;
+        let tx = Transaction::create(account, timestamp, Stab(height));
+        let block =
+            Block::create(height, timestamp, *previous.digest(), difficulty, vec![tx]).unwrap();
+        unverified(&block.mine())
+    }
+
+    /// Mine blocks following `previous` at minimum difficulty.
+    fn extend(
+        previous: &Header,
+        len: usize,
+        account: &SecretAccount,
+    ) -> Vec<Block<Stab, Yet, Yet>> {
+        let mut blocks: Vec<Block<Stab, Yet, Yet>> = vec![];
+        for _ in 0..len {
+            let previous = blocks.last().map_or(previous, Block::header);
+            let block = mine_at(previous, Duration::from_secs(1), MIN_DIFFICULTY, account);
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    /// Response of a peer whose chain consists of the headers.
+    fn respond(chain: &[Header], from_height: u64) -> Message<Stab> {
+        let headers = chain
+            .iter()
+            .filter(|header| header.height() >= from_height)
+            .take(MAX_HEADERS_PER_REQUEST)
+            .cloned()
+            .collect();
+        Message::Headers(headers)
+    }
+
+    #[test]
+    fn follow_fork() {
+        let (genesis, blocks) = build_chain(3, |_, block| block);
+        let local = std::iter::once(genesis)
+            .chain(headers(&blocks))
+            .collect::<Vec<_>>();
+        let window = HeaderWindow::new(local.iter().collect());
+        let mut machine = SyncMachine::<Stab>::from_window(&window, Timestamp::from_nanos(0));
+
+        // The peer's chain forks from height 1, and is longer than the local chain.
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let fork = extend(&local[1], 4, &account);
+        let remote = local[..2]
+            .iter()
+            .cloned()
+            .chain(headers(&fork))
+            .collect::<Vec<_>>();
+
+        let actions = machine.add_peer(ALICE);
+        assert_eq!(send_headers(&actions), Some((ALICE, 4)));
+
+        // Headers do not follow the tip, so ones from lower heights are requested.
+        let actions = machine.on_message(ALICE, respond(&remote, 4));
+        assert_eq!(send_headers(&actions), Some((ALICE, 2)));
+        assert_eq!(
+            machine.state(),
+            SyncState::SyncingHeaders { from_height: 2 }
+        );
+
+        let actions = machine.on_message(ALICE, respond(&remote, 2));
+        assert_eq!(
+            actions,
+            vec![Action::Send(
+                ALICE,
+                Message::GetBlocks {
+                    heights: vec![2, 3, 4, 5]
+                }
+            )]
+        );
+        let actions = machine.on_message(ALICE, Message::Blocks(fork.clone()));
+        assert_eq!(
+            actions,
+            fork.iter()
+                .cloned()
+                .map(Action::AppendBlock)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(machine.tip(), fork[3].header());
+        assert_eq!(machine.known_header(1), Some(&local[1]));
+        assert_eq!(machine.known_header(2), Some(fork[0].header()));
+    }
+
+    #[test]
+    fn ignore_lighter_fork() {
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let (genesis, blocks) = build_chain(1, |_, block| block);
+        let mut local = std::iter::once(genesis)
+            .chain(headers(&blocks))
+            .collect::<Vec<_>>();
+        for _ in 0..2 {
+            let previous = &local[local.len() - 1];
+            let block = mine_at(
+                previous,
+                Duration::from_secs(1),
+                Difficulty::new(4),
+                &account,
+            );
+            local.push(block.header().clone());
+        }
+        let window = HeaderWindow::new(local.iter().collect());
+        let mut machine = SyncMachine::<Stab>::from_window(&window, Timestamp::from_nanos(0));
+
+        // The fork is longer, but contains less work than the two headers it would replace.
+        let fork = extend(&local[1], 3, &account);
+        let remote = local[..2]
+            .iter()
+            .cloned()
+            .chain(headers(&fork))
+            .collect::<Vec<_>>();
+
+        machine.add_peer(ALICE);
+        let actions = machine.on_message(ALICE, respond(&remote, 4));
+        assert_eq!(send_headers(&actions), Some((ALICE, 2)));
+        assert!(machine.on_message(ALICE, respond(&remote, 2)).is_empty());
+
+        assert_eq!(machine.state(), SyncState::Idle);
+        assert_eq!(machine.tip(), &local[3]);
+        assert_eq!(machine.peer_tips().collect::<Vec<_>>(), vec![(ALICE, 4)]);
+    }
+
+    #[test]
+    fn headers_of_another_chain_below_tip() {
+        let (genesis, blocks) = build_chain(3, |_, block| block);
+        let local = std::iter::once(genesis)
+            .chain(headers(&blocks))
+            .collect::<Vec<_>>();
+        let window = HeaderWindow::new(local.iter().collect());
+        let mut machine = SyncMachine::<Stab>::from_window(&window, Timestamp::from_nanos(0));
+        let (other_genesis, other) = build_chain(5, |_, block| block);
+        let remote = std::iter::once(other_genesis)
+            .chain(headers(&other))
+            .collect::<Vec<_>>();
+
+        machine.add_peer(ALICE);
+        let actions = machine.on_message(ALICE, respond(&remote, 4));
+        assert_eq!(send_headers(&actions), Some((ALICE, 2)));
+        let actions = machine.on_message(ALICE, respond(&remote, 2));
+        assert_eq!(send_headers(&actions), Some((ALICE, 1)));
+
+        // Even the header at height 1 does not follow the local genesis.
+        let actions = machine.on_message(ALICE, respond(&remote, 1));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::UnlinkedHeaders)]
+        );
+        assert_eq!(machine.tip(), &local[3]);
+    }
+
+    #[test]
+    fn wrong_difficulty() {
+        // Retargets every 4 blocks aiming at a block per minute, by at most 2 steps.
+        let params = ChainParams {
+            protocol_version: 0,
+            min_difficulty: Difficulty::new(1),
+            max_difficulty: Difficulty::new(8),
+            target_block_interval: Duration::from_secs(60),
+            retarget_window: 4,
+            retarget_clamp: 2,
+            ..ChainParams::regtest()
+        };
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let tx = Transaction::create(&account, Timestamp::EPOCH, Stab(0));
+        let genesis = Block::create(
+            0,
+            Timestamp::EPOCH,
+            Digest::ZERO,
+            Difficulty::new(1),
+            vec![tx],
+        )
+        .unwrap()
+        .mine()
+        .header()
+        .clone();
+        let now = Timestamp::EPOCH + Duration::from_secs(60);
+
+        // Blocks are too fast, so the block at height 4 must raise difficulty by the clamp.
+        let fast = Duration::from_secs(1);
+        let mut remote = vec![genesis.clone()];
+        for _ in 0..3 {
+            let block = mine_at(
+                &remote[remote.len() - 1],
+                fast,
+                Difficulty::new(1),
+                &account,
+            );
+            remote.push(block.header().clone());
+        }
+        let tip = remote[remote.len() - 1].clone();
+        let wrong = mine_at(&tip, fast, Difficulty::new(1), &account);
+        let right = mine_at(&tip, fast, Difficulty::new(3), &account);
+
+        let mut machine =
+            SyncMachine::<Stab>::new(genesis.clone(), now).with_params(params.clone());
+        machine.add_peer(ALICE);
+        let mut headers = remote[1..].to_vec();
+        headers.push(wrong.header().clone());
+        let actions = machine.on_message(ALICE, Message::Headers(headers));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::InvalidHeaders)]
+        );
+
+        let mut machine = SyncMachine::<Stab>::new(genesis, now).with_params(params);
+        machine.add_peer(ALICE);
+        let mut headers = remote[1..].to_vec();
+        headers.push(right.header().clone());
+        let actions = machine.on_message(ALICE, Message::Headers(headers));
+        assert_eq!(
+            actions,
+            vec![Action::Send(
+                ALICE,
+                Message::GetBlocks {
+                    heights: vec![1, 2, 3, 4]
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn unlinked_headers() {
+        let (genesis, blocks) = build_chain(3, |_, block| block);
+        let mut machine = SyncMachine::<Stab>::new(genesis, Timestamp::from_nanos(0));
+        machine.add_peer(ALICE);
+
+        let mut headers = headers(&blocks);
+        headers.swap(1, 2);
+        let actions = machine.on_message(ALICE, Message::Headers(headers));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::UnlinkedHeaders)]
+        );
+    }
+
+    #[test]
+    fn invalid_proof_of_work() {
+        let (genesis, _) = build_chain(0, |_, block| block);
+        let mut machine = SyncMachine::new(genesis.clone(), Timestamp::from_nanos(0));
+        machine.add_peer(ALICE);
+
+        // Not mined
+        let account = SecretAccount::create(&mut rand_core::OsRng {});
+        let block = Block::create(
+            1,
+            Timestamp::from_nanos(0),
+            *genesis.digest(),
+            Difficulty::new(64),
+            vec![Transaction::create(
+                &account,
+                Timestamp::from_nanos(0),
+                Stab(1),
+            )],
+        )
+        .unwrap();
+
+        let actions = machine.on_message(ALICE, Message::Headers(vec![block.header().clone()]));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::InvalidHeaders)]
+        );
+        assert_eq!(send_headers(&machine.add_peer(BOB)), Some((BOB, 1)));
+    }
+
+    #[test]
+    fn unexpected_blocks() {
+        let (genesis, blocks) = build_chain(2, |_, block| block);
+        let mut machine = SyncMachine::new(genesis, Timestamp::from_nanos(0));
+        machine.add_peer(ALICE);
+        machine.on_message(ALICE, Message::Headers(headers(&blocks)));
+
+        let actions = machine.on_message(ALICE, Message::Blocks(blocks[..1].to_vec()));
+        assert_eq!(
+            actions,
+            vec![Action::Disconnect(ALICE, Reason::UnexpectedBlocks)]
+        );
+        assert_eq!(machine.tip().height(), 0);
+    }
+}
@@ -0,0 +1,362 @@
+//! Thin driver of [`SyncMachine`] over TCP, which exchanges framed [`Message`]s with peers.
+//!
+//! All decisions about syncing are made by the machine.
+//! The node only reads frames from sockets, responds to requests from its chain, and performs returned [`Action`]s.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::block::Block;
+use crate::chain::{AppendOutcome, Chain};
+use crate::clock::{Clock, SystemClock};
+use crate::codec::DecodeError;
+use crate::protocol::Message;
+use crate::sync::{Action, PeerId, SyncMachine};
+use crate::{ByteOrder, Timestamp, Yet};
+
+/// Size of a chunk read from a socket at once.
+const READ_CHUNK_BYTES: usize = 4096;
+
+/// What a reader thread of a peer tells the node.
+enum Event<T> {
+    Message(PeerId, Message<T>),
+    /// The connection was closed, or the peer sent a malformed frame.
+    Closed(PeerId),
+}
+
+/// Node which syncs its chain from peers connected over TCP, and serves its chain to them.
+///
+/// Each connection has a thread which decodes received frames, and the node handles them one by one in [`TcpNode::poll()`].
+/// Ticks of the machine follow the clock of the node.
+/// Fork blocks received from the machine replace the blocks of the chain above their fork point.
+pub struct TcpNode<T> {
+    chain: Chain<T>,
+    machine: SyncMachine<T>,
+    /// Write side of the connection of each peer.
+    peers: BTreeMap<PeerId, TcpStream>,
+    next_peer: u64,
+    /// When [`SyncMachine::on_tick()`] should be called next.
+    next_tick: Timestamp,
+    clock: Arc<dyn Clock>,
+    sender: Sender<Event<T>>,
+    receiver: Receiver<Event<T>>,
+}
+
+impl<T> TcpNode<T>
+where
+    T: ByteOrder + Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// Create a node syncing the chain by [`SyncMachine::from_chain()`], which reads the time from the system clock.
+    pub fn new(chain: Chain<T>) -> Self {
+        Self::with_clock(chain, Arc::new(SystemClock))
+    }
+
+    /// Same as [`TcpNode::new()`], but the node reads the time from the given clock.
+    pub fn with_clock(chain: Chain<T>, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+        let machine = SyncMachine::from_chain(&chain, now);
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            chain,
+            machine,
+            peers: BTreeMap::new(),
+            next_peer: 0,
+            next_tick: now,
+            clock,
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn chain(&self) -> &Chain<T> {
+        &self.chain
+    }
+
+    pub fn machine(&self) -> &SyncMachine<T> {
+        &self.machine
+    }
+
+    /// Returns number of connected peers.
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Connect to a peer, then start syncing from it if no request is in flight.
+    pub fn connect<A: ToSocketAddrs>(&mut self, addr: A) -> io::Result<PeerId> {
+        let stream = TcpStream::connect(addr)?;
+        self.add_stream(stream)
+    }
+
+    /// Wait for a peer to connect to the listener, then start syncing from it if no request is in flight.
+    pub fn accept(&mut self, listener: &TcpListener) -> io::Result<PeerId> {
+        let (stream, _) = listener.accept()?;
+        self.add_stream(stream)
+    }
+
+    /// Handle a message or a closed connection if one arrives within the timeout, and a tick if it is due.
+    ///
+    /// # Returns
+    /// `false` if nothing arrived within the timeout.
+    pub fn poll(&mut self, timeout: Duration) -> bool {
+        let until_tick = self
+            .next_tick
+            .duration_since(self.clock.now())
+            .unwrap_or_default();
+        let arrived = match self.receiver.recv_timeout(timeout.min(until_tick)) {
+            Ok(Event::Message(peer, message)) => {
+                self.on_message(peer, message);
+                true
+            }
+            Ok(Event::Closed(peer)) => {
+                self.remove_peer(peer);
+                true
+            }
+            // The node has a sender itself, so the channel is never disconnected.
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => false,
+        };
+
+        let now = self.clock.now();
+        if self.next_tick <= now {
+            let actions = self.machine.on_tick(now);
+            self.perform(actions);
+        }
+        arrived
+    }
+
+    fn add_stream(&mut self, stream: TcpStream) -> io::Result<PeerId> {
+        let peer = PeerId(self.next_peer);
+        self.next_peer += 1;
+
+        let reader = stream.try_clone()?;
+        let sender = self.sender.clone();
+        thread::spawn(move || read_frames(peer, reader, sender));
+        self.peers.insert(peer, stream);
+
+        let actions = self.machine.add_peer(peer);
+        self.perform(actions);
+        Ok(peer)
+    }
+
+    fn remove_peer(&mut self, peer: PeerId) {
+        if let Some(stream) = self.peers.remove(&peer) {
+            // The reader thread ends as the connection is closed.
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.machine.remove_peer(peer);
+    }
+
+    fn on_message(&mut self, peer: PeerId, message: Message<T>) {
+        match respond(&self.chain, &message) {
+            Some(response) => self.send(peer, &response),
+            None => {
+                let actions = self.machine.on_message(peer, message);
+                self.perform(actions);
+            }
+        }
+    }
+
+    fn perform(&mut self, actions: Vec<Action<T>>) {
+        for action in actions {
+            match action {
+                Action::Send(peer, message) => self.send(peer, &message),
+                Action::AppendBlock(block) => self.append(block),
+                Action::Disconnect(peer, _) => self.remove_peer(peer),
+                Action::ScheduleTick(after) => self.next_tick = self.clock.now() + after,
+            }
+        }
+    }
+
+    /// A peer which cannot be written to is disconnected.
+    fn send(&mut self, peer: PeerId, message: &Message<T>) {
+        let stream = match self.peers.get_mut(&peer) {
+            Some(stream) => stream,
+            None => return,
+        };
+        let written = message
+            .encode_framed()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .and_then(|frame| stream.write_all(&frame));
+        if written.is_err() {
+            self.remove_peer(peer);
+        }
+    }
+
+    /// Append the block, disconnecting blocks of the chain at and above its height first, since the machine requests such a block only for a fork containing more work.
+    /// If the block is invalid, the disconnected blocks are restored.
+    fn append(&mut self, block: Block<T, Yet, Yet>) {
+        let height = block.header().height();
+        let mut disconnected = vec![];
+        while self.chain.tip().header().height() >= height {
+            match self.chain.disconnect_tip() {
+                Some(tip) => disconnected.push(tip),
+                None => break,
+            }
+        }
+
+        if !matches!(self.chain.append(block), Ok(AppendOutcome::Appended)) {
+            for tip in disconnected.into_iter().rev() {
+                // Restored blocks have been verified and linked before, so they are always appended.
+                let _ = self.chain.append(tip.into_unverified());
+            }
+            self.machine.set_tip(self.chain.tip().header().clone());
+        }
+    }
+}
+
+/// Respond to a request from a peer by the chain.
+///
+/// # Returns
+/// `None` if the message is not a request handled here, e.g., a response to the machine's request.
+fn respond<T: Clone>(chain: &Chain<T>, message: &Message<T>) -> Option<Message<T>> {
+    match message {
+        Message::GetHeaders {
+            from_height,
+            max_count,
+        } => {
+            let headers = (*from_height..)
+                .map_while(|height| chain.block(height))
+                .take(*max_count)
+                .map(|block| block.header().clone())
+                .collect();
+            Some(Message::Headers(headers))
+        }
+        Message::GetBlocks { heights } => {
+            let blocks = heights
+                .iter()
+                .filter_map(|&height| chain.block(height))
+                .map(|block| block.clone().into_unverified())
+                .collect();
+            Some(Message::Blocks(blocks))
+        }
+        _ => None,
+    }
+}
+
+/// Decode frames from the connection until it is closed or a frame is malformed.
+fn read_frames<T: DeserializeOwned>(peer: PeerId, mut stream: TcpStream, sender: Sender<Event<T>>) {
+    let mut buf = vec![];
+    let mut chunk = [0; READ_CHUNK_BYTES];
+    loop {
+        match Message::decode_framed(&buf) {
+            Ok((message, len)) => {
+                buf.drain(..len);
+                if sender.send(Event::Message(peer, message)).is_err() {
+                    return;
+                }
+            }
+            Err(DecodeError::UnexpectedEnd { .. }) => match stream.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => buf.extend(&chunk[..len]),
+            },
+            Err(_) => break,
+        }
+    }
+    let _ = sender.send(Event::Closed(peer));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::chain::tests_stab::*;
+    use crate::clock::ManualClock;
+    use crate::fixtures;
+    use crate::sync::DEFAULT_REQUEST_TIMEOUT;
+
+    /// Poll both nodes until the condition holds, or panic after a few seconds.
+    fn poll_until<F>(a: &mut TcpNode<Stab>, b: &mut TcpNode<Stab>, condition: F)
+    where
+        F: Fn(&TcpNode<Stab>, &TcpNode<Stab>) -> bool,
+    {
+        let started = Instant::now();
+        while !condition(a, b) {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "Nodes did not converge."
+            );
+            a.poll(Duration::from_millis(10));
+            b.poll(Duration::from_millis(10));
+        }
+    }
+
+    fn genesis_chain() -> Chain<Stab> {
+        let genesis = mine(0, crate::Digest::ZERO, &fixtures::account(0));
+        Chain::new(
+            unverified(&genesis)
+                .verify_block(|_| true)
+                .unwrap()
+                .verify_transactions()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn sync_over_tcp() {
+        let account = fixtures::account(1);
+        let genesis = genesis_chain();
+        let mut source = genesis.clone();
+        for height in 1..=3 {
+            let block = mine(height, *source.tip().header().digest(), &account);
+            source.append(unverified(&block)).unwrap();
+        }
+        let clock = Arc::new(ManualClock::new(Timestamp::now()));
+        let mut source = TcpNode::with_clock(source, clock.clone());
+        let mut fresh = TcpNode::with_clock(genesis, clock);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        fresh.connect(listener.local_addr().unwrap()).unwrap();
+        source.accept(&listener).unwrap();
+
+        poll_until(&mut fresh, &mut source, |fresh, source| {
+            fresh.chain().tip().header() == source.chain().tip().header()
+        });
+        assert_eq!(fresh.machine().tip(), source.chain().tip().header());
+    }
+
+    #[test]
+    fn disconnect_malformed_frame() {
+        let clock = Arc::new(ManualClock::new(Timestamp::now()));
+        let mut node = TcpNode::with_clock(genesis_chain(), clock);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        node.accept(&listener).unwrap();
+        assert_eq!(node.peer_count(), 1);
+
+        peer.write_all(&[0, 0, 0, 2, b'{', b'}']).unwrap();
+        let started = Instant::now();
+        while node.peer_count() > 0 {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "Peer was not disconnected."
+            );
+            node.poll(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn disconnect_stalled_peer_by_clock() {
+        let clock = Arc::new(ManualClock::new(Timestamp::now()));
+        let mut node = TcpNode::with_clock(genesis_chain(), clock.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        // Peer which never responds to the request of headers.
+        let _peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        node.accept(&listener).unwrap();
+
+        node.poll(Duration::from_millis(10));
+        assert_eq!(node.peer_count(), 1);
+
+        // Deadline passes only by the clock, however long the node waits.
+        clock.advance(DEFAULT_REQUEST_TIMEOUT + Duration::from_secs(1));
+        node.poll(Duration::from_millis(10));
+        assert_eq!(node.peer_count(), 0);
+    }
+}
@@ -1,38 +1,266 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Sub};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::clock::Clock;
+use crate::digest::Hasher;
 use crate::ByteOrder;
 
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
 /// Duration from Unix Epoch (1970-01-01 00:00:00 UTC) in nanoseconds.
+///
+/// Valid timestamps are from [`Timestamp::EPOCH`] to [`Timestamp::MAX`], which is in April 2262,
+/// since byte order of a timestamp is fixed to 8 bytes.
+/// Timestamps from untrusted sources, i.e., deserialization, [`Timestamp::try_from_nanos()`] and [`Timestamp::from_system_time()`],
+/// are rejected outside the range.
+/// Arithmetic of timestamps may give a negative one, which is only for local computation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "i64")]
 pub struct Timestamp(i64);
 
 impl Timestamp {
+    /// Unix Epoch, the earliest valid timestamp.
+    pub const EPOCH: Timestamp = Timestamp(0);
+
+    /// The latest valid timestamp, 2262-04-11 23:47:16.854775807 UTC.
+    pub const MAX: Timestamp = Timestamp(i64::MAX);
+
     /// Returns current timestamp.
+    ///
+    /// On `wasm32` targets, this is available only with `wasm` feature.
+    /// Otherwise, give a timestamp by [`Timestamp::from_nanos()`] instead.
+    ///
+    /// # Panics
+    /// Panics if the system time is before Unix Epoch or after [`Timestamp::MAX`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now()).expect("System time is out of range.")
+    }
+
+    /// Returns current timestamp by `Date.now()` of JavaScript, which has millisecond precision.
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
     pub fn now() -> Self {
-        // SystemTime::now() is never smaller than UNIX_EPOCH.
-        // So unwrapping duration always succeeds.
-        let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let nanos = duration.as_nanos() as i64;
+        let millis = js_sys::Date::now();
+        Self((millis * 1_000_000.0) as i64)
+    }
+
+    /// Returns timestamp of the given unix timestamp in nanoseconds.
+    ///
+    /// The value is not validated, so this is only for trusted values such as constants and local clocks.
+    /// Untrusted values must go through [`Timestamp::try_from_nanos()`],
+    /// as deserialization and [`Block::from_wire_bytes()`](crate::block::Block::from_wire_bytes) do.
+    pub fn from_nanos(nanos: i64) -> Self {
         Self(nanos)
     }
 
+    /// Returns timestamp of the given unix timestamp in nanoseconds, which must not be negative.
+    pub fn try_from_nanos(nanos: i64) -> Result<Self, TimestampError> {
+        if nanos < 0 {
+            Err(TimestampError::Negative(nanos))
+        } else {
+            Ok(Self(nanos))
+        }
+    }
+
+    /// Returns timestamp of the system time, which must be from Unix Epoch to [`Timestamp::MAX`].
+    pub fn from_system_time(time: SystemTime) -> Result<Self, TimestampError> {
+        let duration = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TimestampError::BeforeEpoch)?;
+        let nanos = i64::try_from(duration.as_nanos()).map_err(|_| TimestampError::Overflow)?;
+        Ok(Self(nanos))
+    }
+
     /// Returns unix timestamp in nanoseconds.
     pub fn nanos(&self) -> i64 {
         self.0
     }
+
+    /// Returns unix timestamp in whole seconds, rounded toward negative infinity.
+    pub fn secs(&self) -> i64 {
+        self.0.div_euclid(NANOS_PER_SEC)
+    }
+
+    /// Returns unix timestamp in whole milliseconds, rounded toward negative infinity.
+    pub fn millis(&self) -> i64 {
+        self.0.div_euclid(NANOS_PER_MILLI)
+    }
+
+    /// Returns the fractional part of [`Timestamp::secs()`] in nanoseconds, which is always non-negative.
+    pub fn subsec_nanos(&self) -> u32 {
+        // The remainder is in 0..NANOS_PER_SEC, so the cast never truncates.
+        self.0.rem_euclid(NANOS_PER_SEC) as u32
+    }
+
+    /// Returns the timestamp after the duration, or `None` on overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        i64::try_from(self.0 as i128 + duration.as_nanos() as i128)
+            .ok()
+            .map(Self)
+    }
+
+    /// Returns the timestamp before the duration, or `None` on overflow.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        i64::try_from(self.0 as i128 - duration.as_nanos() as i128)
+            .ok()
+            .map(Self)
+    }
+
+    /// Returns the duration from `earlier` to `self`, or `None` if `earlier` is actually later than `self`.
+    pub fn duration_since(&self, earlier: Timestamp) -> Option<Duration> {
+        (earlier <= *self).then(|| self.abs_diff(earlier))
+    }
+
+    /// Returns the duration between the two timestamps, regardless of their order.
+    pub fn abs_diff(&self, other: Timestamp) -> Duration {
+        Duration::from_nanos(self.0.abs_diff(other.0))
+    }
+
+    /// Returns the duration from `self` to now of the clock, or zero if `self` is in the future.
+    pub fn saturating_elapsed(&self, clock: &dyn Clock) -> Duration {
+        clock.now().duration_since(*self).unwrap_or_default()
+    }
+}
+
+/// Saturates at the maximum or minimum timestamp instead of overflow.
+/// Use [`Timestamp::checked_add()`] to detect overflow.
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Self::Output {
+        self.checked_add(duration).unwrap_or(Self(i64::MAX))
+    }
+}
+
+/// Saturates at the maximum or minimum timestamp instead of overflow.
+/// Use [`Timestamp::checked_sub()`] to detect overflow.
+impl Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: Duration) -> Self::Output {
+        self.checked_sub(duration).unwrap_or(Self(i64::MIN))
+    }
+}
+
+impl TryFrom<i64> for Timestamp {
+    type Error = TimestampError;
+
+    fn try_from(nanos: i64) -> Result<Self, Self::Error> {
+        Self::try_from_nanos(nanos)
+    }
 }
 
 impl ByteOrder for Timestamp {
     fn append_bytes(&self, buf: &mut Vec<u8>) {
         buf.extend(self.0.to_le_bytes())
     }
+
+    fn append_to_hasher(&self, hasher: &mut Hasher) {
+        hasher.update(&self.0.to_le_bytes());
+    }
+
+    fn byte_len(&self) -> usize {
+        std::mem::size_of::<i64>()
+    }
+}
+
+/// Timestamp is out of the valid range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampError {
+    /// Nanoseconds are negative.
+    Negative(i64),
+    /// Time is before Unix Epoch.
+    BeforeEpoch,
+    /// Time is after [`Timestamp::MAX`].
+    Overflow,
+}
+
+impl Display for TimestampError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use TimestampError::*;
+
+        match self {
+            Negative(nanos) => write!(f, "Timestamp {} is negative.", nanos),
+            BeforeEpoch => write!(f, "Time is before Unix Epoch."),
+            Overflow => write!(f, "Time exceeds the maximum timestamp."),
+        }
+    }
+}
+
+impl Error for TimestampError {}
+
+/// Serializes a [`Timestamp`] as a decimal string in human-readable formats such as JSON,
+/// and as an integer in binary formats.
+///
+/// Nanoseconds of current time exceed `2^53`, which JavaScript numbers cannot represent exactly.
+/// So `JSON.parse()` silently rounds integer timestamps, and signatures over them no longer verify.
+/// Use this module by `#[serde(with = "jellyfish_core::timestamp::serde_string")]` on fields read by JavaScript.
+///
+/// # Migration
+/// Deserialization accepts both a string and an integer,
+/// so data stored in the integer form can still be read after a field switched to this module.
+/// Switching back requires that all stored data has been rewritten in the integer form.
+pub mod serde_string {
+    use std::fmt::{self, Formatter};
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    use super::{Timestamp, TimestampError};
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &Timestamp,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&timestamp.0)
+        } else {
+            serializer.serialize_i64(timestamp.0)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(NanosVisitor)
+        } else {
+            deserializer.deserialize_i64(NanosVisitor)
+        }
+    }
+
+    struct NanosVisitor;
+
+    impl<'de> Visitor<'de> for NanosVisitor {
+        type Value = Timestamp;
+
+        fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "nanoseconds as a decimal string or an integer")
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Timestamp::try_from_nanos(v).map_err(E::custom)
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            let v = i64::try_from(v).map_err(|_| E::custom(TimestampError::Overflow))?;
+            self.visit_i64(v)
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            let v = v.parse().map_err(E::custom)?;
+            self.visit_i64(v)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::ManualClock;
 
     #[test]
     fn byte_order() {
@@ -42,4 +270,202 @@ mod tests {
         // Byte order must be alligned with little endian
         assert_eq!(byte_order, &[3, 2, 1, 0, 0, 0, 0, 0]);
     }
+
+    #[test]
+    fn add_sub() {
+        let timestamp = Timestamp(100);
+
+        assert_eq!(timestamp + Duration::from_nanos(5), Timestamp(105));
+        assert_eq!(timestamp - Duration::from_nanos(105), Timestamp(-5));
+        assert_eq!(
+            timestamp.checked_add(Duration::from_nanos(5)),
+            Some(Timestamp(105))
+        );
+        assert_eq!(
+            timestamp.checked_sub(Duration::from_nanos(105)),
+            Some(Timestamp(-5))
+        );
+    }
+
+    #[test]
+    fn add_sub_saturate() {
+        let max = Timestamp(i64::MAX);
+        let min = Timestamp(i64::MIN);
+
+        assert_eq!(max + Duration::from_nanos(1), max);
+        assert_eq!(Timestamp(0) + Duration::MAX, max);
+        assert_eq!(min - Duration::from_nanos(1), min);
+        assert_eq!(Timestamp(0) - Duration::MAX, min);
+
+        assert_eq!(max.checked_add(Duration::from_nanos(1)), None);
+        assert_eq!(Timestamp(0).checked_add(Duration::MAX), None);
+        assert_eq!(min.checked_sub(Duration::from_nanos(1)), None);
+        assert_eq!(
+            Timestamp(i64::MAX - 1).checked_add(Duration::from_nanos(1)),
+            Some(max)
+        );
+        // The duration exceeds i64::MAX nanoseconds, but the result does not.
+        assert_eq!(
+            max.checked_sub(Duration::from_nanos(i64::MAX as u64 + 1)),
+            Some(Timestamp(-1))
+        );
+    }
+
+    #[test]
+    fn duration_since() {
+        let earlier = Timestamp(-10);
+        let later = Timestamp(30);
+
+        assert_eq!(
+            later.duration_since(earlier),
+            Some(Duration::from_nanos(40))
+        );
+        assert_eq!(earlier.duration_since(later), None);
+        assert_eq!(later.duration_since(later), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn abs_diff() {
+        let earlier = Timestamp(-10);
+        let later = Timestamp(30);
+
+        assert_eq!(later.abs_diff(earlier), Duration::from_nanos(40));
+        assert_eq!(earlier.abs_diff(later), Duration::from_nanos(40));
+        assert_eq!(
+            Timestamp(i64::MIN).abs_diff(Timestamp(i64::MAX)),
+            Duration::from_nanos(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn saturating_elapsed() {
+        let clock = ManualClock::new(Timestamp(100));
+
+        assert_eq!(
+            Timestamp(40).saturating_elapsed(&clock),
+            Duration::from_nanos(60)
+        );
+        assert_eq!(Timestamp(200).saturating_elapsed(&clock), Duration::ZERO);
+    }
+
+    #[test]
+    fn secs_millis() {
+        let timestamp = Timestamp(1_234_567_890_123);
+        assert_eq!(timestamp.secs(), 1_234);
+        assert_eq!(timestamp.millis(), 1_234_567);
+        assert_eq!(timestamp.subsec_nanos(), 567_890_123);
+
+        // Half a second before the epoch.
+        let timestamp = Timestamp(-500_000_000);
+        assert_eq!(timestamp.secs(), -1);
+        assert_eq!(timestamp.millis(), -500);
+        assert_eq!(timestamp.subsec_nanos(), 500_000_000);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct StringMode {
+        #[serde(with = "serde_string")]
+        timestamp: Timestamp,
+    }
+
+    /// Above 2^53, which is not exactly representable by `f64`.
+    const LARGE: i64 = (1 << 53) + 1;
+
+    /// Reads JSON as JavaScript does, where every number is `f64`.
+    fn parse_as_javascript(json: &str) -> serde_json::Value {
+        let value = serde_json::from_str::<serde_json::Value>(json).unwrap();
+        match value {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .map(|(key, value)| match value.as_f64() {
+                    Some(f) if value.is_number() => (key, serde_json::json!(f)),
+                    _ => (key, value),
+                })
+                .collect(),
+            value => value,
+        }
+    }
+
+    #[test]
+    fn serde_string_round_trip() {
+        let value = StringMode {
+            timestamp: Timestamp(LARGE),
+        };
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, format!(r#"{{"timestamp":"{}"}}"#, LARGE));
+
+        let reparsed = parse_as_javascript(&json).to_string();
+        let de = serde_json::from_str::<StringMode>(&reparsed).unwrap();
+        assert_eq!(de, value);
+    }
+
+    #[test]
+    fn integer_mode_loses_precision() {
+        let json = serde_json::to_string(&Timestamp(LARGE)).unwrap();
+        assert_eq!(json, LARGE.to_string());
+
+        let reparsed = parse_as_javascript(&format!(r#"{{"timestamp":{}}}"#, json));
+        let nanos = reparsed["timestamp"].as_f64().unwrap() as i64;
+        assert_ne!(nanos, LARGE);
+    }
+
+    #[test]
+    fn serde_string_accept_integer() {
+        let json = format!(r#"{{"timestamp":{}}}"#, LARGE);
+        let de = serde_json::from_str::<StringMode>(&json).unwrap();
+        assert_eq!(de.timestamp, Timestamp(LARGE));
+
+        assert!(serde_json::from_str::<StringMode>(r#"{"timestamp":"1.5"}"#).is_err());
+    }
+
+    #[test]
+    fn deserialize_range() {
+        let max = serde_json::from_str::<Timestamp>(&i64::MAX.to_string()).unwrap();
+        assert_eq!(max, Timestamp::MAX);
+        assert_eq!(
+            serde_json::from_str::<Timestamp>("0").unwrap(),
+            Timestamp::EPOCH
+        );
+
+        // One nanosecond past the maximum
+        let past_max = (i64::MAX as u64 + 1).to_string();
+        assert!(serde_json::from_str::<Timestamp>(&past_max).is_err());
+        assert!(serde_json::from_str::<Timestamp>("-1").is_err());
+    }
+
+    #[test]
+    fn serde_string_range() {
+        let json = format!(r#"{{"timestamp":"{}"}}"#, i64::MAX as u64 + 1);
+        assert!(serde_json::from_str::<StringMode>(&json).is_err());
+        assert!(serde_json::from_str::<StringMode>(r#"{"timestamp":"-1"}"#).is_err());
+        assert!(serde_json::from_str::<StringMode>(r#"{"timestamp":-1}"#).is_err());
+    }
+
+    #[test]
+    fn try_from_nanos() {
+        assert_eq!(Timestamp::try_from_nanos(i64::MAX), Ok(Timestamp::MAX));
+        assert_eq!(
+            Timestamp::try_from_nanos(-1),
+            Err(TimestampError::Negative(-1))
+        );
+    }
+
+    #[test]
+    fn from_system_time() {
+        let max = UNIX_EPOCH + Duration::from_nanos(i64::MAX as u64);
+        assert_eq!(Timestamp::from_system_time(max), Ok(Timestamp::MAX));
+        assert_eq!(
+            Timestamp::from_system_time(max + Duration::from_nanos(1)),
+            Err(TimestampError::Overflow)
+        );
+        assert_eq!(
+            Timestamp::from_system_time(UNIX_EPOCH - Duration::from_nanos(1)),
+            Err(TimestampError::BeforeEpoch)
+        );
+    }
+
+    #[test]
+    fn byte_order_max() {
+        assert_eq!(Timestamp::MAX.build_byte_order().len(), 8);
+    }
 }
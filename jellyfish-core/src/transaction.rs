@@ -2,33 +2,88 @@ use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::marker::PhantomData;
 
-use serde::{Deserialize, Serialize};
+use ed25519_dalek::Digest;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 
+use crate::account::{build_context_message, Prehash};
+use crate::codec::{BoundedReader, DecodeError};
+use crate::jellyfish_transaction::JellyfishTransactionContent;
+use crate::params::{ChainParams, NetworkId};
+use crate::scheme::{self, Ed25519, SignatureScheme};
+use crate::sig_cache::SigCache;
 use crate::signature::SignatureError;
+use crate::signer::{SignError, TransactionSigner};
 use crate::{Account, SecretAccount, Signature, Timestamp, Verified, Yet};
 use crate::{ByteOrder, ByteOrderBuilder};
 
+/// Context of transaction signatures since protocol version 2, which is given by [`ChainParams::transaction_context()`].
+pub const TRANSACTION_CONTEXT: &[u8] = b"jellyfish-tx-v1";
+
+/// Transactions whose content is larger than this in bytes are signed by [`SignMode::Prehashed`].
+pub const PREHASH_THRESHOLD: usize = 16 * 1024;
+
+/// Algorithm of a transaction signature, which is recorded in the transaction so that verification picks the same one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignMode {
+    /// ed25519 over the signature source.
+    #[default]
+    Plain,
+    /// ed25519ph over SHA-512 of the signature source, which is used for contents larger than [`PREHASH_THRESHOLD`].
+    Prehashed,
+}
+
+impl SignMode {
+    /// Returns the byte representing the mode in wire bytes of transactions.
+    pub fn wire_byte(&self) -> u8 {
+        match self {
+            SignMode::Plain => 0x00,
+            SignMode::Prehashed => 0x01,
+        }
+    }
+
+    /// Returns the mode for the content of the size, which [`Transaction::create()`] uses.
+    pub fn for_content_len(len: usize) -> Self {
+        if len > PREHASH_THRESHOLD {
+            SignMode::Prehashed
+        } else {
+            SignMode::Plain
+        }
+    }
+}
+
 /// The smallest unit of contract.
 /// ### Generic type parameter
 /// - `T` transaction content.
 /// - `V` verification process marker.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-pub struct Transaction<T, V> {
+/// - `S` signature scheme of the account and sign.
+///
+/// Methods for [`Ed25519`] sign and verify the transaction as the protocol does, e.g., by [`SignMode::Prehashed`] and [`SigCache`].
+/// Transactions of other schemes are created and verified by [`Transaction::create_with_scheme()`] and [`Transaction::verify_with_scheme()`].
+///
+/// `mode` is omitted from human-readable formats if it is [`SignMode::Plain`], so that such transactions keep the format before the mode was introduced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction<T, V, S: SignatureScheme = Ed25519> {
     /// Creator of the transaction
-    account: Account,
+    account: S::PublicKey,
     /// When this transaction offered
     timestamp: Timestamp,
     /// Transaction content
     content: T,
     /// Sign by offerer
-    sign: Signature,
-    /// Marker of verification process
-    #[serde(skip_serializing)]
-    _phantom: PhantomData<fn() -> V>,
+    sign: S::Signature,
+    /// Algorithm of the sign
+    mode: SignMode,
+    /// Marker of verification process and signature scheme
+    _phantom: PhantomData<fn() -> (V, S)>,
 }
 
-impl<T, V> Transaction<T, V> {
-    pub fn account(&self) -> &Account {
+/// Transaction whose sign has been verified.
+pub type VerifiedTransaction<T> = Transaction<T, Verified>;
+
+impl<T, V, S: SignatureScheme> Transaction<T, V, S> {
+    pub fn account(&self) -> &S::PublicKey {
         &self.account
     }
 
@@ -40,31 +95,143 @@ impl<T, V> Transaction<T, V> {
         &self.content
     }
 
-    pub fn sign(&self) -> &Signature {
+    pub fn sign(&self) -> &S::Signature {
         &self.sign
     }
+
+    pub fn sign_mode(&self) -> SignMode {
+        self.mode
+    }
+
+    /// Drop the verification marker, e.g., to include the transaction in a block given to [`Chain::append()`](crate::chain::Chain::append),
+    /// which verifies transactions by itself.
+    pub fn into_unverified(self) -> Transaction<T, Yet, S> {
+        Transaction {
+            account: self.account,
+            timestamp: self.timestamp,
+            content: self.content,
+            sign: self.sign,
+            mode: self.mode,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ByteOrder, V, S: SignatureScheme> Transaction<T, V, S> {
+    /// Returns size of the transaction in bytes, which is the sum of byte order of its account, timestamp, content and sign.
+    ///
+    /// This is calculated without building the byte order.
+    pub fn encoded_size(&self) -> usize {
+        self.account.byte_len()
+            + self.timestamp.byte_len()
+            + self.content.byte_len()
+            + self.sign.byte_len()
+    }
+}
+
+impl<T: ByteOrder, V, S: SignatureScheme> Transaction<T, V, S> {
+    /// Append wire bytes of the transaction, which are
+    /// `account || timestamp || sign mode || length of content as big endian u32 || content || sign`.
+    pub(crate) fn append_wire_bytes(&self, buf: &mut Vec<u8>) {
+        self.account.append_bytes(buf);
+        self.timestamp.append_bytes(buf);
+        buf.push(self.mode.wire_byte());
+        buf.extend((self.content.byte_len() as u32).to_be_bytes());
+        self.content.append_bytes(buf);
+        self.sign.append_bytes(buf);
+    }
+}
+
+impl Transaction<JellyfishTransactionContent, Yet> {
+    /// Read a transaction written by [`Transaction::append_wire_bytes()`],
+    /// whose content and record are at most `max_content_bytes` and `max_record_bytes`.
+    pub(crate) fn read_wire(
+        reader: &mut BoundedReader<'_>,
+        max_content_bytes: usize,
+        max_record_bytes: usize,
+    ) -> Result<Self, DecodeError> {
+        let account: [u8; 32] = reader.read_array("account")?;
+        let account = Account::from_bytes(&account).map_err(|e| DecodeError::Malformed {
+            field: "account",
+            reason: e.to_string(),
+        })?;
+        let timestamp = i64::from_le_bytes(reader.read_array("timestamp")?);
+        let timestamp =
+            Timestamp::try_from_nanos(timestamp).map_err(|e| DecodeError::Malformed {
+                field: "timestamp",
+                reason: e.to_string(),
+            })?;
+        let [byte] = reader.read_array("sign mode")?;
+        let mode = [SignMode::Plain, SignMode::Prehashed]
+            .into_iter()
+            .find(|mode| mode.wire_byte() == byte)
+            .ok_or_else(|| DecodeError::Malformed {
+                field: "sign mode",
+                reason: format!("Unknown sign mode byte 0x{:02x}.", byte),
+            })?;
+        let content = reader.read_length_prefixed("content", max_content_bytes)?;
+        let content = JellyfishTransactionContent::from_byte_order(content, max_record_bytes)?;
+        let sign: [u8; 64] = reader.read_array("sign")?;
+        let sign = Signature::from_bytes(&sign).map_err(|e| DecodeError::Malformed {
+            field: "sign",
+            reason: e.to_string(),
+        })?;
+
+        Ok(Transaction {
+            account,
+            timestamp,
+            content,
+            sign,
+            mode,
+            _phantom: PhantomData,
+        })
+    }
 }
 
 /// Module-inner struct, which has save field with Transaction, except verification marker field.
 /// This is used to deserialize data into unverified transaction.
 #[derive(Deserialize)]
-struct TransactionWithoutMarker<T> {
-    account: Account,
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct TransactionWithoutMarker<T, S: SignatureScheme> {
+    account: S::PublicKey,
     timestamp: Timestamp,
     content: T,
-    sign: Signature,
+    sign: S::Signature,
+    #[serde(default)]
+    mode: SignMode,
+}
+
+/// Binary formats such as bincode cannot omit fields of a struct, so they always contain the mode.
+impl<T: Serialize, V, S: SignatureScheme> Serialize for Transaction<T, V, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let omits_mode = serializer.is_human_readable() && self.mode == SignMode::Plain;
+        let len = if omits_mode { 4 } else { 5 };
+
+        let mut state = serializer.serialize_struct("Transaction", len)?;
+        state.serialize_field("account", &self.account)?;
+        state.serialize_field("timestamp", &self.timestamp)?;
+        state.serialize_field("content", &self.content)?;
+        state.serialize_field("sign", &self.sign)?;
+        if omits_mode {
+            state.skip_field("mode")?;
+        } else {
+            state.serialize_field("mode", &self.mode)?;
+        }
+        state.end()
+    }
 }
 
-impl<'de, T> Deserialize<'de> for Transaction<T, Yet>
+impl<'de, T, S> Deserialize<'de> for Transaction<T, Yet, S>
 where
     T: Deserialize<'de>,
+    S: SignatureScheme,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         // Deserialize into transaction without marker
-        let inner = TransactionWithoutMarker::deserialize(deserializer)?;
+        let inner = TransactionWithoutMarker::<T, S>::deserialize(deserializer)?;
 
         // Append 'unverified' marker to deserialized transaction.
         Ok(Transaction {
@@ -72,22 +239,122 @@ where
             timestamp: inner.timestamp,
             content: inner.content,
             sign: inner.sign,
+            mode: inner.mode,
             _phantom: PhantomData,
         })
     }
 }
 
 impl<T: ByteOrder> Transaction<T, Yet> {
-    /// Verify transaction signature.
+    /// Assemble an unverified transaction from its fields, e.g., ones stored in a genesis configuration.
+    pub(crate) fn from_parts(
+        account: Account,
+        timestamp: Timestamp,
+        content: T,
+        sign: Signature,
+    ) -> Self {
+        Transaction {
+            account,
+            timestamp,
+            content,
+            sign,
+            mode: SignMode::Plain,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Verify transaction signature in the legacy format, which does not contain a network.
+    ///
+    /// This is available for existing data. Use [`Transaction::verify_for()`] for protocol version 1 or later.
     pub fn verify(self) -> Result<Transaction<T, Verified>, TransactionError> {
-        let signature_source = build_signature_source(&self.account, self.timestamp, &self.content);
+        self.verify_in(None)
+    }
 
-        match self.account.verify(&signature_source, &self.sign) {
+    /// Verify transaction signature, which must be created for the given network by [`Transaction::create_for()`].
+    pub fn verify_for(
+        self,
+        network: &NetworkId,
+    ) -> Result<Transaction<T, Verified>, TransactionError> {
+        self.verify_in(Some(network))
+    }
+
+    /// Verify transaction signature, which must be created for the parameters by [`Transaction::create_for()`].
+    ///
+    /// Unlike [`Transaction::verify_for()`], this follows [`ChainParams::transaction_context()`] of protocol version 2 or later.
+    pub fn verify_with_params(
+        self,
+        params: &ChainParams,
+    ) -> Result<Transaction<T, Verified>, TransactionError> {
+        self.verify_with(
+            params.network().as_ref(),
+            params.transaction_context(),
+            None,
+        )
+    }
+
+    /// Same as [`Transaction::verify()`], but a signature verified before is accepted by the cache.
+    pub fn verify_cached(
+        self,
+        cache: &SigCache,
+    ) -> Result<Transaction<T, Verified>, TransactionError> {
+        self.verify_with(None, None, Some(cache))
+    }
+
+    /// Same as [`Transaction::verify_with_params()`], but a signature verified before is accepted by the cache.
+    pub fn verify_cached_with_params(
+        self,
+        params: &ChainParams,
+        cache: &SigCache,
+    ) -> Result<Transaction<T, Verified>, TransactionError> {
+        self.verify_with(
+            params.network().as_ref(),
+            params.transaction_context(),
+            Some(cache),
+        )
+    }
+
+    pub(crate) fn verify_in(
+        self,
+        network: Option<&NetworkId>,
+    ) -> Result<Transaction<T, Verified>, TransactionError> {
+        self.verify_with(network, None, None)
+    }
+
+    pub(crate) fn verify_with(
+        self,
+        network: Option<&NetworkId>,
+        context: Option<&[u8]>,
+        cache: Option<&SigCache>,
+    ) -> Result<Transaction<T, Verified>, TransactionError> {
+        let result = match self.mode {
+            SignMode::Plain => {
+                let signature_source = build_signed_bytes::<Ed25519, _>(
+                    network,
+                    context,
+                    &self.account,
+                    self.timestamp,
+                    &self.content,
+                );
+                match cache {
+                    Some(cache) => cache.verify(&self.account, &signature_source, &self.sign),
+                    None => self.account.verify(&signature_source, &self.sign),
+                }
+            }
+            // Cache is keyed by messages of plain signatures, so prehashed ones are always verified.
+            SignMode::Prehashed => {
+                let prehash =
+                    build_signature_prehash(network, &self.account, self.timestamp, &self.content);
+                self.account.verify_prehashed(&prehash, context, &self.sign)
+            }
+        };
+
+        match result {
             Ok(()) => Ok(Transaction {
                 account: self.account,
                 timestamp: self.timestamp,
                 content: self.content,
                 sign: self.sign,
+                mode: self.mode,
                 _phantom: PhantomData,
             }),
             Err(e) => Err(TransactionError::Signature(e)),
@@ -95,38 +362,269 @@ impl<T: ByteOrder> Transaction<T, Yet> {
     }
 }
 
+impl<T: ByteOrder, S: SignatureScheme> Transaction<T, Yet, S> {
+    /// Verify transaction signature of the scheme, which must be created for the parameters by [`Transaction::create_with_scheme()`].
+    ///
+    /// # Returns
+    /// `Err(TransactionError::UnsupportedMode)` if the sign mode is not [`SignMode::Plain`], since ed25519ph is specific to ed25519.
+    pub fn verify_with_scheme(
+        self,
+        params: &ChainParams,
+    ) -> Result<Transaction<T, Verified, S>, TransactionError> {
+        if self.mode != SignMode::Plain {
+            return Err(TransactionError::UnsupportedMode(self.mode));
+        }
+        let signed_bytes = build_signed_bytes::<S, _>(
+            params.network().as_ref(),
+            params.transaction_context(),
+            &self.account,
+            self.timestamp,
+            &self.content,
+        );
+        S::verify(&self.account, &signed_bytes, &self.sign)
+            .map_err(|e| TransactionError::Scheme(Box::new(e)))?;
+
+        Ok(Transaction {
+            account: self.account,
+            timestamp: self.timestamp,
+            content: self.content,
+            sign: self.sign,
+            mode: self.mode,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T> Transaction<T, Verified> {
+    /// Replace the signature without invalidating the verification marker,
+    /// so that tests can build transactions which honest signers never produce, e.g., ones referring to each other.
+    #[cfg(test)]
+    pub(crate) fn forge_sign(mut self, sign: Signature) -> Self {
+        self.sign = sign;
+        self
+    }
+}
+
 impl<T: ByteOrder> Transaction<T, Verified> {
+    /// Create a transaction in the legacy format, whose signature is valid on any network.
+    ///
+    /// Use [`Transaction::create_for()`] for protocol version 1 or later.
     pub fn create(secret_account: &SecretAccount, timestamp: Timestamp, content: T) -> Self {
-        let account = secret_account.to_public();
-        let signature_source = build_signature_source(&account, timestamp, &content);
-        let sign = secret_account.sign(&signature_source);
+        Self::create_in(None, secret_account, timestamp, content)
+    }
+
+    /// Create a transaction whose signature is valid only on the network of the parameters.
+    ///
+    /// This is the same as [`Transaction::create()`] if the protocol version of the parameters is 0.
+    /// Since protocol version 2, the signature is made under [`ChainParams::transaction_context()`],
+    /// and must be verified by [`Transaction::verify_with_params()`].
+    pub fn create_for(
+        params: &ChainParams,
+        secret_account: &SecretAccount,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Self {
+        Self::create_with(
+            params.network().as_ref(),
+            params.transaction_context(),
+            secret_account,
+            timestamp,
+            content,
+        )
+    }
+
+    /// Same as [`Transaction::create()`], but signed by the signer, e.g., a key in an HSM.
+    ///
+    /// If the signer does not support ed25519ph, large contents are signed by [`SignMode::Plain`].
+    pub fn create_with_signer<S: TransactionSigner>(
+        signer: &S,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Result<Self, SignError> {
+        Self::try_create_with(None, None, signer, timestamp, content)
+    }
+
+    /// Same as [`Transaction::create_for()`], but signed by the signer as [`Transaction::create_with_signer()`].
+    pub fn create_for_with_signer<S: TransactionSigner>(
+        params: &ChainParams,
+        signer: &S,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Result<Self, SignError> {
+        Self::try_create_with(
+            params.network().as_ref(),
+            params.transaction_context(),
+            signer,
+            timestamp,
+            content,
+        )
+    }
+
+    pub(crate) fn create_in(
+        network: Option<&NetworkId>,
+        secret_account: &SecretAccount,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Self {
+        Self::create_with(network, None, secret_account, timestamp, content)
+    }
+
+    fn create_with(
+        network: Option<&NetworkId>,
+        context: Option<&'static [u8]>,
+        secret_account: &SecretAccount,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Self {
+        Self::try_create_with(network, context, secret_account, timestamp, content)
+            .expect("Secret account never fails to sign.")
+    }
+
+    fn try_create_with<S: TransactionSigner>(
+        network: Option<&NetworkId>,
+        context: Option<&'static [u8]>,
+        signer: &S,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Result<Self, SignError> {
+        let account = signer.public();
+        let prehashed = match SignMode::for_content_len(content.byte_len()) {
+            SignMode::Plain => None,
+            SignMode::Prehashed => {
+                let prehash = build_signature_prehash(network, &account, timestamp, &content);
+                match signer.sign_prehashed(&prehash, context) {
+                    Err(SignError::Unsupported) => None,
+                    result => Some(result?),
+                }
+            }
+        };
+        let (sign, mode) = match prehashed {
+            Some(sign) => (sign, SignMode::Prehashed),
+            None => {
+                let signed_bytes = build_signed_bytes::<Ed25519, _>(
+                    network, context, &account, timestamp, &content,
+                );
+                (signer.sign(&signed_bytes)?, SignMode::Plain)
+            }
+        };
+
+        Ok(Transaction {
+            account,
+            timestamp,
+            content,
+            sign,
+            mode,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: ByteOrder, S: SignatureScheme> Transaction<T, Verified, S> {
+    /// Create a transaction of the scheme whose signature is valid only under the parameters,
+    /// which is verified by [`Transaction::verify_with_scheme()`].
+    ///
+    /// The sign mode is always [`SignMode::Plain`].
+    /// For [`Ed25519`], this is the same as [`Transaction::create_for()`] if the content is not larger than [`PREHASH_THRESHOLD`].
+    pub fn create_with_scheme(
+        params: &ChainParams,
+        secret: &S::SecretKey,
+        timestamp: Timestamp,
+        content: T,
+    ) -> Self {
+        let account = S::public_key(secret);
+        let signed_bytes = build_signed_bytes::<S, _>(
+            params.network().as_ref(),
+            params.transaction_context(),
+            &account,
+            timestamp,
+            &content,
+        );
+        let sign = S::sign(secret, &signed_bytes);
 
         Transaction {
             account,
             timestamp,
             content,
             sign,
+            mode: SignMode::Plain,
             _phantom: PhantomData,
         }
     }
 }
 
-fn build_signature_source<T: ByteOrder>(
+/// Network is prepended to the source since protocol version 1,
+/// so that a transaction signed for a network is never valid on other networks.
+pub(crate) fn build_signature_source<T: ByteOrder>(
+    network: Option<&NetworkId>,
+    account: &Account,
+    timestamp: Timestamp,
+    content: &T,
+) -> Vec<u8> {
+    build_scheme_signature_source::<Ed25519, _>(network, account, timestamp, content)
+}
+
+/// Same as [`build_signature_source()`], but the scheme id follows the network for schemes other than [`Ed25519`],
+/// so that a signature of one scheme is never valid as one of another scheme.
+fn build_scheme_signature_source<S: SignatureScheme, T: ByteOrder>(
+    network: Option<&NetworkId>,
+    account: &S::PublicKey,
+    timestamp: Timestamp,
+    content: &T,
+) -> Vec<u8> {
+    let mut source = network.map(|network| network.to_vec()).unwrap_or_default();
+    source.extend(scheme::transaction_scheme_id::<S>());
+    source.extend(
+        ByteOrderBuilder::new()
+            .append(account)
+            .append(&timestamp)
+            .append(content)
+            .finalize(),
+    );
+    source
+}
+
+/// Same as SHA-512 of [`build_signature_source()`], but the source is hashed by parts without being concatenated.
+///
+/// The content is written by [`ByteOrder::append_to_prehash()`], so large records are never copied.
+fn build_signature_prehash<T: ByteOrder>(
+    network: Option<&NetworkId>,
     account: &Account,
     timestamp: Timestamp,
     content: &T,
+) -> Prehash {
+    let mut prehash = Prehash::new();
+    if let Some(network) = network {
+        prehash.update(network);
+    }
+    prehash.update(account.as_bytes());
+    prehash.update(timestamp.build_byte_order());
+    content.append_to_prehash(&mut prehash);
+    prehash
+}
+
+/// Bytes which the signature is made over, i.e., the signature source under the context if any.
+fn build_signed_bytes<S: SignatureScheme, T: ByteOrder>(
+    network: Option<&NetworkId>,
+    context: Option<&[u8]>,
+    account: &S::PublicKey,
+    timestamp: Timestamp,
+    content: &T,
 ) -> Vec<u8> {
-    ByteOrderBuilder::new()
-        .append(account)
-        .append(&timestamp)
-        .append(content)
-        .finalize()
+    let source = build_scheme_signature_source::<S, _>(network, account, timestamp, content);
+    match context {
+        Some(context) => build_context_message(context, &source),
+        None => source,
+    }
 }
 
 #[derive(Debug)]
 pub enum TransactionError {
     /// Invalid sign in transaction.
     Signature(SignatureError),
+    /// Invalid sign in transaction of another scheme than ed25519, or one verified by [`Transaction::verify_with_scheme()`].
+    Scheme(Box<dyn Error + Send + Sync>),
+    /// Sign mode which the scheme does not support.
+    UnsupportedMode(SignMode),
 }
 
 impl Display for TransactionError {
@@ -135,6 +633,12 @@ impl Display for TransactionError {
             TransactionError::Signature(e) => {
                 write!(f, "Transaction signature verification failed: {}", e)
             }
+            TransactionError::Scheme(e) => {
+                write!(f, "Transaction signature verification failed: {}", e)
+            }
+            TransactionError::UnsupportedMode(mode) => {
+                write!(f, "Sign mode {:?} is not supported by the scheme.", mode)
+            }
         }
     }
 }
@@ -143,6 +647,8 @@ impl Error for TransactionError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             TransactionError::Signature(e) => Some(e),
+            TransactionError::Scheme(e) => Some(e.as_ref()),
+            TransactionError::UnsupportedMode(_) => None,
         }
     }
 }
@@ -150,7 +656,7 @@ impl Error for TransactionError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SecretAccount;
+    use crate::fixtures;
 
     #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     struct ContentStab(Vec<u8>);
@@ -161,14 +667,10 @@ mod tests {
         }
     }
 
-    fn create_account() -> SecretAccount {
-        SecretAccount::create(&mut rand_core::OsRng {})
-    }
-
     #[test]
     fn verify() {
         // Create transaction
-        let account = create_account();
+        let account = fixtures::account(0);
         let timestamp = Timestamp::now();
         let content = ContentStab(vec![0, 1, 2]);
 
@@ -184,17 +686,46 @@ mod tests {
         assert_eq!(tx, verified);
     }
 
+    #[test]
+    fn bincode_round_trip() {
+        let account = fixtures::account(0);
+        let tx = Transaction::create(&account, Timestamp::now(), ContentStab(vec![0, 1, 2]));
+
+        let ser = bincode::serialize(&tx).unwrap();
+        // Account is raw 32 bytes at the beginning, rather than length-prefixed hex.
+        assert_eq!(&ser[..32], account.public_key());
+        let de = bincode::deserialize::<Transaction<ContentStab, Yet>>(&ser).unwrap();
+
+        assert_eq!(de.verify().unwrap(), tx);
+        assert_eq!(bincode::serialize(&tx).unwrap(), ser);
+    }
+
+    #[test]
+    fn bincode_raw_sign() {
+        let account = fixtures::account(0);
+        let tx = Transaction::create(&account, Timestamp::now(), ContentStab(vec![0, 1, 2]));
+
+        let ser = bincode::serialize(&tx).unwrap();
+        // Signature is raw 64 bytes followed by the mode, rather than length-prefixed hex.
+        let sign_end = ser.len() - 4;
+        assert_eq!(&ser[sign_end - 64..sign_end], tx.sign().as_ref());
+
+        // Truncated in the middle of the signature.
+        let truncated = &ser[..sign_end - 32];
+        assert!(bincode::deserialize::<Transaction<ContentStab, Yet>>(truncated).is_err());
+    }
+
     #[test]
     fn verify_corrupt_account() {
         // Create transaction
-        let account = create_account();
+        let account = fixtures::account(0);
         let timestamp = Timestamp::now();
         let content = ContentStab(vec![0, 1, 2]);
 
         let mut tx = Transaction::create(&account, timestamp, content);
 
         // Cheat account
-        tx.account = create_account().to_public();
+        tx.account = fixtures::account(1).to_public();
 
         // Convert transaction to unverified one, but same content.
         let ser = serde_json::to_string(&tx).unwrap();
@@ -203,13 +734,18 @@ mod tests {
         // Try verification
         let res = de.verify();
 
-        assert!(matches!(res, Err(TransactionError::Signature(_))));
+        assert!(matches!(
+            res,
+            Err(TransactionError::Signature(
+                SignatureError::VerificationFailed(_)
+            ))
+        ));
     }
 
     #[test]
     fn verify_corrupt_timestamp() {
         // Create transaction
-        let account = create_account();
+        let account = fixtures::account(0);
         let timestamp = Timestamp::now();
         let content = ContentStab(vec![0, 1, 2]);
 
@@ -225,13 +761,18 @@ mod tests {
         // Try verification
         let res = de.verify();
 
-        assert!(matches!(res, Err(TransactionError::Signature(_))));
+        assert!(matches!(
+            res,
+            Err(TransactionError::Signature(
+                SignatureError::VerificationFailed(_)
+            ))
+        ));
     }
 
     #[test]
     fn verify_corrupt_content() {
         // Create transaction
-        let account = create_account();
+        let account = fixtures::account(0);
         let timestamp = Timestamp::now();
         let content = ContentStab(vec![0, 1, 2]);
 
@@ -247,13 +788,18 @@ mod tests {
         // Try verification
         let res = de.verify();
 
-        assert!(matches!(res, Err(TransactionError::Signature(_))));
+        assert!(matches!(
+            res,
+            Err(TransactionError::Signature(
+                SignatureError::VerificationFailed(_)
+            ))
+        ));
     }
 
     #[test]
     fn verify_corrupt_sign() {
         // Create transaction
-        let account = create_account();
+        let account = fixtures::account(0);
         let timestamp = Timestamp::now();
         let content = ContentStab(vec![0, 1, 2]);
 
@@ -269,6 +815,280 @@ mod tests {
         // Try verification
         let res = de.verify();
 
-        assert!(matches!(res, Err(TransactionError::Signature(_))));
+        assert!(matches!(
+            res,
+            Err(TransactionError::Signature(
+                SignatureError::VerificationFailed(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn verify_for_network() {
+        let account = fixtures::account(0);
+        let mainnet = ChainParams::mainnet();
+        let testnet = ChainParams::testnet();
+        let (mainnet_id, testnet_id) = (mainnet.network().unwrap(), testnet.network().unwrap());
+
+        let create_for = |params| {
+            let tx =
+                Transaction::create_for(params, &account, Timestamp::now(), ContentStab(vec![0]));
+            let ser = serde_json::to_string(&tx).unwrap();
+            serde_json::from_str::<Transaction<ContentStab, Yet>>(&ser).unwrap()
+        };
+
+        let tx = create_for(&testnet);
+        assert!(tx.clone().verify_for(&testnet_id).is_ok());
+        assert!(tx.clone().verify_for(&mainnet_id).is_err());
+        assert!(tx.verify().is_err());
+
+        let tx = create_for(&mainnet);
+        assert!(tx.clone().verify_for(&mainnet_id).is_ok());
+        assert!(tx.clone().verify_for(&testnet_id).is_err());
+        assert!(tx.verify().is_err());
+
+        // Legacy transactions are valid only in the legacy format.
+        let legacy = ChainParams {
+            protocol_version: 0,
+            ..mainnet.clone()
+        };
+        let tx = create_for(&legacy);
+        assert!(tx.clone().verify().is_ok());
+        assert!(tx.verify_for(&mainnet_id).is_err());
+    }
+
+    #[test]
+    fn create_large_content_prehashed() {
+        let account = fixtures::account(0);
+        let small = ContentStab(vec![0; PREHASH_THRESHOLD]);
+        let large = ContentStab(vec![0; PREHASH_THRESHOLD + 1]);
+
+        let tx = Transaction::create(&account, Timestamp::now(), small);
+        assert_eq!(tx.sign_mode(), SignMode::Plain);
+
+        let tx = Transaction::create(&account, Timestamp::now(), large);
+        assert_eq!(tx.sign_mode(), SignMode::Prehashed);
+        assert!(tx.into_unverified().verify().is_ok());
+    }
+
+    #[test]
+    fn build_signature_prehash() {
+        let account = fixtures::account(0).to_public();
+        let network = ChainParams::mainnet().network();
+        let timestamp = Timestamp::now();
+        let content = ContentStab(vec![1, 2, 3]);
+
+        let source = build_signature_source(network.as_ref(), &account, timestamp, &content);
+        let prehash =
+            super::build_signature_prehash(network.as_ref(), &account, timestamp, &content);
+
+        assert_eq!(
+            prehash.finalize(),
+            crate::account::prehash(&source).finalize()
+        );
+    }
+
+    #[test]
+    fn verify_fail_swapped_mode() {
+        let account = fixtures::account(0);
+        let content = ContentStab(vec![0; PREHASH_THRESHOLD + 1]);
+        let tx = Transaction::create(&account, Timestamp::now(), content);
+
+        // Mode recorded as plain for the prehashed signature.
+        let mut json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["mode"], "prehashed");
+        json.as_object_mut().unwrap().remove("mode");
+        let tx = serde_json::from_value::<Transaction<ContentStab, Yet>>(json).unwrap();
+        assert_eq!(tx.sign_mode(), SignMode::Plain);
+        assert!(tx.verify().is_err());
+
+        // Mode recorded as prehashed for a plain signature.
+        let tx = Transaction::create(&account, Timestamp::now(), ContentStab(vec![0]));
+        let mut json = serde_json::to_value(&tx).unwrap();
+        assert!(json.get("mode").is_none());
+        json["mode"] = serde_json::json!("prehashed");
+        let tx = serde_json::from_value::<Transaction<ContentStab, Yet>>(json).unwrap();
+        assert!(tx.verify().is_err());
+    }
+
+    #[test]
+    fn prehashed_serde_round_trip() {
+        let account = fixtures::account(0);
+        let content = ContentStab(vec![0; PREHASH_THRESHOLD + 1]);
+        let tx = Transaction::create(&account, Timestamp::now(), content);
+
+        let json = serde_json::to_string(&tx).unwrap();
+        let de = serde_json::from_str::<Transaction<ContentStab, Yet>>(&json).unwrap();
+        assert_eq!(de.sign_mode(), SignMode::Prehashed);
+        assert!(de.verify().is_ok());
+
+        let binary = bincode::serialize(&tx).unwrap();
+        let de = bincode::deserialize::<Transaction<ContentStab, Yet>>(&binary).unwrap();
+        assert_eq!(de.sign_mode(), SignMode::Prehashed);
+        assert!(de.verify().is_ok());
+    }
+
+    #[test]
+    fn prehashed_verify_with_params_context() {
+        let account = fixtures::account(0);
+        let v1 = ChainParams::mainnet();
+        let v2 = ChainParams {
+            protocol_version: 2,
+            ..v1.clone()
+        };
+        let content = ContentStab(vec![0; PREHASH_THRESHOLD + 1]);
+
+        let tx =
+            Transaction::create_for(&v2, &account, Timestamp::now(), content).into_unverified();
+
+        assert_eq!(tx.sign_mode(), SignMode::Prehashed);
+        assert!(tx.clone().verify_with_params(&v2).is_ok());
+        assert!(tx.verify_with_params(&v1).is_err());
+    }
+
+    #[test]
+    fn verify_with_params_context() {
+        let account = fixtures::account(0);
+        let v1 = ChainParams::mainnet();
+        let v2 = ChainParams {
+            protocol_version: 2,
+            ..v1.clone()
+        };
+        assert_eq!(v1.transaction_context(), None);
+        assert_eq!(v2.transaction_context(), Some(TRANSACTION_CONTEXT));
+
+        let create_for = |params| {
+            let tx =
+                Transaction::create_for(params, &account, Timestamp::now(), ContentStab(vec![0]));
+            let ser = serde_json::to_string(&tx).unwrap();
+            serde_json::from_str::<Transaction<ContentStab, Yet>>(&ser).unwrap()
+        };
+
+        // Both versions share the network, so only the context tells them apart.
+        let tx = create_for(&v2);
+        assert!(tx.clone().verify_with_params(&v2).is_ok());
+        assert!(tx.clone().verify_with_params(&v1).is_err());
+        assert!(tx.verify_for(&v1.network().unwrap()).is_err());
+
+        // Existing transactions still validate under the parameters of their version.
+        let tx = create_for(&v1);
+        assert!(tx.clone().verify_with_params(&v1).is_ok());
+        assert!(tx.clone().verify_for(&v1.network().unwrap()).is_ok());
+        assert!(tx.verify_with_params(&v2).is_err());
+    }
+
+    #[test]
+    fn create_with_scheme_agrees_with_create_for() {
+        let account = fixtures::account(0);
+        let params = ChainParams {
+            protocol_version: 2,
+            ..ChainParams::mainnet()
+        };
+        let timestamp = Timestamp::now();
+
+        let tx = Transaction::<_, _, Ed25519>::create_with_scheme(
+            &params,
+            &account,
+            timestamp,
+            ContentStab(vec![0]),
+        );
+
+        assert_eq!(
+            tx,
+            Transaction::create_for(&params, &account, timestamp, ContentStab(vec![0]))
+        );
+        assert!(tx
+            .clone()
+            .into_unverified()
+            .verify_with_params(&params)
+            .is_ok());
+        assert!(tx.into_unverified().verify_with_scheme(&params).is_ok());
+    }
+
+    #[test]
+    fn scheme_id_separates_transactions() {
+        use crate::scheme::tests_stab::OtherScheme;
+
+        let account = fixtures::account(0);
+        let params = ChainParams::mainnet();
+        let timestamp = Timestamp::now();
+
+        let other = Transaction::<_, _, OtherScheme>::create_with_scheme(
+            &params,
+            &account,
+            timestamp,
+            ContentStab(vec![0]),
+        )
+        .into_unverified();
+        let ed25519 = Transaction::create_for(&params, &account, timestamp, ContentStab(vec![0]));
+
+        // Keys are shared, but signed bytes differ by the scheme id.
+        assert_eq!(other.account(), ed25519.account());
+        assert_ne!(other.sign(), ed25519.sign());
+        assert!(other.clone().verify_with_scheme(&params).is_ok());
+
+        let json = serde_json::to_string(&other).unwrap();
+        let as_ed25519 = serde_json::from_str::<Transaction<ContentStab, Yet>>(&json).unwrap();
+        assert!(as_ed25519.clone().verify_with_params(&params).is_err());
+        assert!(matches!(
+            as_ed25519.verify_with_scheme(&params),
+            Err(TransactionError::Scheme(_))
+        ));
+    }
+
+    #[test]
+    fn verify_with_scheme_fail_prehashed() {
+        let account = fixtures::account(0);
+        let params = ChainParams::mainnet();
+        let content = ContentStab(vec![0; PREHASH_THRESHOLD + 1]);
+
+        let tx =
+            Transaction::create_for(&params, &account, Timestamp::now(), content).into_unverified();
+
+        assert!(tx.clone().verify_with_params(&params).is_ok());
+        assert!(matches!(
+            tx.verify_with_scheme(&params),
+            Err(TransactionError::UnsupportedMode(SignMode::Prehashed))
+        ));
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1_round_trip() {
+        use crate::scheme::{Secp256k1, SECP256K1_PUBLIC_KEY_LENGTH};
+
+        let secret = Secp256k1::generate(&mut rand_core::OsRng {});
+        let mainnet = ChainParams::mainnet();
+        let testnet = ChainParams::testnet();
+
+        let tx = Transaction::<_, _, Secp256k1>::create_with_scheme(
+            &mainnet,
+            &secret,
+            Timestamp::now(),
+            ContentStab(vec![0, 1, 2]),
+        );
+        assert_eq!(tx.account(), &secret.to_public());
+        assert_eq!(tx.encoded_size(), SECP256K1_PUBLIC_KEY_LENGTH + 8 + 3 + 64);
+
+        let json = serde_json::to_string(&tx).unwrap();
+        let de = serde_json::from_str::<Transaction<ContentStab, Yet, Secp256k1>>(&json).unwrap();
+        assert_eq!(de.clone().verify_with_scheme(&mainnet).unwrap(), tx);
+        assert!(de.verify_with_scheme(&testnet).is_err());
+
+        let binary = bincode::serialize(&tx).unwrap();
+        assert_eq!(
+            &binary[..SECP256K1_PUBLIC_KEY_LENGTH],
+            tx.account().to_bytes()
+        );
+        let mut de =
+            bincode::deserialize::<Transaction<ContentStab, Yet, Secp256k1>>(&binary).unwrap();
+        assert_eq!(de.clone().verify_with_scheme(&mainnet).unwrap(), tx);
+
+        // Cheat content
+        de.content = ContentStab(vec![42]);
+        assert!(matches!(
+            de.verify_with_scheme(&mainnet),
+            Err(TransactionError::Scheme(_))
+        ));
     }
 }
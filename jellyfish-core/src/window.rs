@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use crate::block::Header;
+use crate::difficulty::Difficulty;
+use crate::Timestamp;
+
+/// Contiguous headers of a chain in ascending order of height, which is obtained by [`Chain::header_window()`](crate::chain::Chain::header_window).
+///
+/// This is the common input of computations over recent blocks, e.g., difficulty retargeting and median-time-past.
+/// A window contains at least one header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderWindow<'a> {
+    headers: Vec<&'a Header>,
+}
+
+impl<'a> HeaderWindow<'a> {
+    /// The caller guarantees that headers are non-empty, contiguous and in ascending order.
+    pub(crate) fn new(headers: Vec<&'a Header>) -> Self {
+        debug_assert!(!headers.is_empty());
+        debug_assert!(headers
+            .windows(2)
+            .all(|pair| pair[1].height() == pair[0].height() + 1));
+        Self { headers }
+    }
+
+    pub fn headers(&self) -> &[&'a Header] {
+        &self.headers
+    }
+
+    /// Returns number of headers, which may be less than requested if the window was truncated at the first block.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Always returns `false`, since a window contains at least one header.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// Returns the oldest header.
+    pub fn first(&self) -> &'a Header {
+        self.headers[0]
+    }
+
+    /// Returns the latest header, i.e., the one at the end height.
+    pub fn last(&self) -> &'a Header {
+        self.headers[self.headers.len() - 1]
+    }
+
+    pub fn timestamps(&self) -> impl Iterator<Item = Timestamp> + '_ {
+        self.headers.iter().map(|header| header.timestamp())
+    }
+
+    pub fn difficulties(&self) -> impl Iterator<Item = Difficulty> + '_ {
+        self.headers.iter().map(|header| header.difficulty())
+    }
+
+    /// Returns time from the oldest header to the latest one.
+    ///
+    /// Zero if the latest header is not later than the oldest, since timestamps of blocks are not monotonic.
+    pub fn span(&self) -> Duration {
+        self.last()
+            .timestamp()
+            .duration_since(self.first().timestamp())
+            .unwrap_or_default()
+    }
+
+    /// Returns the median of timestamps in the window.
+    ///
+    /// For an even number of headers, the later of the two middle timestamps is returned.
+    pub fn median_time_past(&self) -> Timestamp {
+        let mut timestamps = self.timestamps().collect::<Vec<_>>();
+        timestamps.sort_unstable();
+        timestamps[timestamps.len() / 2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chain::ChainError;
+    use crate::fixtures::FixtureChain;
+    use crate::params::ChainParams;
+    use crate::Timestamp;
+
+    fn build(len: usize) -> FixtureChain {
+        (0..len).fold(FixtureChain::new(ChainParams::regtest()), |fixture, i| {
+            fixture.with_block(|b| b.insert(&i.to_string()))
+        })
+    }
+
+    #[test]
+    fn full_window() {
+        let fixture = build(10);
+        let chain = fixture.chain();
+        let window = chain.header_window(8, 5).unwrap();
+
+        assert_eq!(window.len(), 5);
+        let heights = window
+            .headers()
+            .iter()
+            .map(|h| h.height())
+            .collect::<Vec<_>>();
+        assert_eq!(heights, vec![4, 5, 6, 7, 8]);
+        assert_eq!(window.last(), chain.block(8).unwrap().header());
+
+        let timestamps = window.timestamps().collect::<Vec<_>>();
+        assert_eq!(timestamps[0], chain.block(4).unwrap().header().timestamp());
+        assert!(timestamps.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(window
+            .difficulties()
+            .all(|difficulty| difficulty == ChainParams::regtest().min_difficulty));
+
+        // Fixture blocks are 1 second apart.
+        assert_eq!(window.span().as_secs(), 4);
+        assert_eq!(window.median_time_past(), timestamps[2]);
+    }
+
+    #[test]
+    fn truncate_at_genesis() {
+        let fixture = build(4);
+        let chain = fixture.chain();
+
+        let window = chain.header_window(2, 10).unwrap();
+        assert_eq!(window.len(), 3);
+        assert_eq!(window.first(), chain.block(0).unwrap().header());
+        assert_eq!(window.last(), chain.block(2).unwrap().header());
+
+        // Empty window is extended to the end height.
+        let window = chain.header_window(0, 0).unwrap();
+        assert_eq!(window.len(), 1);
+        assert_eq!(window.span().as_secs(), 0);
+
+        assert_eq!(
+            chain.header_window(4, 1),
+            Err(ChainError::HeightOutOfRange { height: 4 })
+        );
+    }
+
+    #[test]
+    fn median_time_past() {
+        let fixture = build(6);
+        let window = fixture.chain().header_window(5, 6).unwrap();
+        let timestamps = window.timestamps().collect::<Vec<Timestamp>>();
+
+        // Later of the middle two.
+        assert_eq!(window.median_time_past(), timestamps[3]);
+    }
+}
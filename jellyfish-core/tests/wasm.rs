@@ -0,0 +1,59 @@
+//! Run in Node by `wasm-pack test --node -- --no-default-features --features wasm`.
+#![cfg(target_arch = "wasm32")]
+
+use jellyfish_core::jellyfish_transaction::JellyfishTransactionContent;
+use jellyfish_core::{SecretAccount, Timestamp, Transaction, Yet};
+use rand_core::{CryptoRng, Error, RngCore};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// Always yields the same bytes, so that the fixture account is reproducible.
+struct FixedRng;
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        0x2a2a_2a2a
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        0x2a2a_2a2a_2a2a_2a2a
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.fill(0x2a);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+fn fixture() -> String {
+    let account = SecretAccount::create(&mut FixedRng);
+    let content = JellyfishTransactionContent::insert("hello");
+    let tx = Transaction::create(&account, Timestamp::from_nanos(1_000_000_000), content);
+    serde_json::to_string(&tx).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn verify_fixture_transaction() {
+    let json = fixture();
+    let tx = serde_json::from_str::<Transaction<JellyfishTransactionContent, Yet>>(&json).unwrap();
+
+    assert!(tx.verify().is_ok());
+}
+
+#[wasm_bindgen_test]
+fn verify_tampered_fixture_transaction() {
+    let json = fixture().replace("hello", "hellO");
+    let tx = serde_json::from_str::<Transaction<JellyfishTransactionContent, Yet>>(&json).unwrap();
+
+    assert!(tx.verify().is_err());
+}
+
+#[wasm_bindgen_test]
+fn now() {
+    assert!(Timestamp::now() > Timestamp::from_nanos(0));
+}